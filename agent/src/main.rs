@@ -3,16 +3,28 @@
 //! This agent runs inside the container and communicates with the host control server via TCP.
 
 use clap::{Parser, Subcommand};
-use devcon_proto::{AgentMessage, OpenUrl, StartPortForward, StopPortForward, agent_message};
+use devcon_proto::auto_forward::{AutoForward, AutoForwardPolicy, PortActions, parse_port_ranges};
+use devcon_proto::bandwidth::{BandwidthLimits, RateLimitedReader};
+use devcon_proto::flow_control::SendWindow;
+use devcon_proto::hosts::{self, HOST_NAME, RUNTIME_HOST_NAMES};
+use devcon_proto::noise::{self, TunnelKey};
+use devcon_proto::proxy::proxy;
+use devcon_proto::tunnel_writer::TunnelWriter;
+use devcon_proto::{
+    AgentMessage, AutoForwardAction, Capability, ConnectHostPort, Hello, MIN_PROTOCOL_VERSION,
+    Notify, OpenFile, OpenUrl, PROTOCOL_VERSION, Ping, Pong, StartPortForward, StopPortForward,
+    TUNNEL_CHUNK_SIZE, TunnelClose, TunnelData, TunnelWindowUpdate, agent_message,
+};
 use prost::Message;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read, Write};
-use std::net::TcpStream;
-use std::sync::Arc;
+use std::net::{IpAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::RangeInclusive;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, TryRecvError};
-use std::time::Duration;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "devcon-agent")]
@@ -36,10 +48,31 @@ struct Cli {
     )]
     control_port: u16,
 
+    /// Transport for the control connection
+    #[arg(
+        long,
+        env = "DEVCON_CONTROL_SCHEME",
+        default_value = "tcp",
+        value_parser = ["tcp", "grpc"]
+    )]
+    control_scheme: String,
+
     /// Comma-separated list of ports to exclude from auto-forwarding
     #[arg(long, value_delimiter = ',')]
     exclude_ports: Option<Vec<u16>>,
 
+    /// Bandwidth limits for forwarded ports, e.g. "3000=1M,8000-8010=512K,*=10M"
+    #[arg(long, env = "DEVCON_MAX_BANDWIDTH")]
+    max_bandwidth: Option<BandwidthLimits>,
+
+    /// Comma-separated list of ports whose tunnels are never compressed
+    #[arg(long, env = "DEVCON_UNCOMPRESSED_PORTS", value_delimiter = ',')]
+    uncompressed_ports: Option<Vec<u16>>,
+
+    /// Hex key encrypting tunnel connections, disables multiplexing
+    #[arg(long, env = "DEVCON_TUNNEL_KEY", value_parser = noise::parse_key, hide_env_values = true)]
+    tunnel_key: Option<TunnelKey>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -64,14 +97,124 @@ enum Commands {
         #[arg(value_name = "URL")]
         url: String,
     },
+    /// Request the host to open a workspace file
+    Open {
+        /// File or directory inside the workspace
+        #[arg(value_name = "PATH")]
+        path: std::path::PathBuf,
+
+        /// Open in the host's configured editor instead of the default application
+        #[arg(long, short)]
+        editor: bool,
+    },
+    /// Request the host to show a desktop notification
+    Notify {
+        /// Notification text
+        #[arg(value_name = "MESSAGE")]
+        message: String,
+
+        /// Notification title, defaults to the workspace name
+        #[arg(long, short)]
+        title: Option<String>,
+    },
+    /// Make a port of the host reachable on localhost inside the container
+    ForwardHostPort {
+        /// Port on the host to connect to
+        #[arg(value_name = "HOST_PORT")]
+        host_port: u16,
+
+        /// Port to listen on inside the container, defaults to the host port
+        #[arg(long = "as", value_name = "CONTAINER_PORT")]
+        container_port: Option<u16>,
+    },
     /// Run as a daemon, maintaining connection to control server
     Daemon {
         /// Port scan interval in seconds
         #[arg(long, default_value = "1")]
         scan_interval: u64,
+
+        /// Seconds between heartbeat pings to the control server (0 disables heartbeats)
+        #[arg(long, env = "DEVCON_HEARTBEAT_INTERVAL", default_value = "10")]
+        heartbeat_interval: u64,
+
+        /// Seconds without messages from the control server before reconnecting
+        #[arg(long, env = "DEVCON_HEARTBEAT_TIMEOUT", default_value = "30")]
+        heartbeat_timeout: u64,
+
+        /// Which detected ports are forwarded automatically (all, none or allowlist)
+        #[arg(long, env = "DEVCON_AUTO_FORWARD", default_value = "all")]
+        auto_forward: AutoForward,
+
+        /// Ports forwarded automatically in allowlist mode, e.g. "3000-3999,8080"
+        #[arg(long, env = "DEVCON_AUTO_FORWARD_PORTS", value_parser = parse_port_ranges)]
+        auto_forward_ports: Option<::std::vec::Vec<RangeInclusive<u16>>>,
+
+        /// Ports never forwarded automatically, e.g. "9229,9090"
+        #[arg(long, env = "DEVCON_AUTO_FORWARD_IGNORE_PORTS", value_parser = parse_port_ranges)]
+        auto_forward_ignore_ports: Option<::std::vec::Vec<RangeInclusive<u16>>>,
+
+        /// Actions of the host for detected ports, e.g. "3000=openBrowser,*=notify"
+        #[arg(long, env = "DEVCON_ON_AUTO_FORWARD")]
+        on_auto_forward: Option<PortActions>,
+
+        /// Port of the host's registry cache, served on the same port of localhost
+        #[arg(long, env = "DEVCON_REGISTRY_CACHE_PORT")]
+        registry_cache_port: Option<u16>,
+
+        /// Additional names of the host added to /etc/hosts, e.g. "db.internal,llm.internal"
+        #[arg(long, env = "DEVCON_HOST_ALIASES", value_parser = hosts::parse_host_names)]
+        host_aliases: Option<::std::vec::Vec<String>>,
     },
 }
 
+/// Settings requested from the control server for forwarded ports
+#[derive(Clone, Default)]
+struct PortSettings {
+    /// Bandwidth limits for tunnels
+    bandwidth: BandwidthLimits,
+    /// Ports whose tunnels are never compressed
+    uncompressed: HashSet<u16>,
+    /// Key encrypting dedicated tunnel connections
+    tunnel_key: Option<TunnelKey>,
+    /// Which detected ports are forwarded automatically
+    auto_forward: AutoForwardPolicy,
+    /// Actions requested of the host when a detected port was forwarded
+    actions: PortActions,
+    /// Ports whose action was requested already, kept across reconnects so
+    /// every action is taken once per container
+    announced: Arc<Mutex<HashSet<u16>>>,
+}
+
+impl PortSettings {
+    /// Build the request to forward a port with its settings and the process listening on it
+    fn start_request(&self, port: u16, process: Option<&ListeningProcess>) -> StartPortForward {
+        StartPortForward {
+            port: port as u32,
+            max_bandwidth: self.bandwidth.for_port(port).unwrap_or(0),
+            disable_compression: self.uncompressed.contains(&port),
+            auto: false,
+            action: AutoForwardAction::None as i32,
+            process: process.map(|p| p.command.clone()).unwrap_or_default(),
+            pid: process.map(|p| p.pid).unwrap_or_default(),
+        }
+    }
+
+    /// Action for a detected port, requested only the first time it is forwarded
+    fn announce(&self, port: u16) -> AutoForwardAction {
+        let action = self.actions.for_port(port);
+        if action != AutoForwardAction::None && self.announced.lock().unwrap().insert(port) {
+            action
+        } else {
+            AutoForwardAction::None
+        }
+    }
+
+    /// Whether tunnels of a port may be compressed
+    fn compress(&self, port: u16) -> bool {
+        !self.uncompressed.contains(&port)
+    }
+}
+
 /// Send a protobuf message over a TCP stream with length prefix
 fn send_message(stream: &mut TcpStream, msg: &AgentMessage) -> io::Result<()> {
     let mut buf = Vec::new();
@@ -104,12 +247,50 @@ fn connect_to_control_server(host: &str, port: u16) -> io::Result<TcpStream> {
     TcpStream::connect(addr)
 }
 
+/// Resolve the address control connections are made to
+///
+/// With the gRPC scheme, a local bridge forwards every connection as a gRPC
+/// session to the control server.
+fn control_address(host: &str, port: u16, scheme: &str) -> io::Result<(String, u16)> {
+    match scheme {
+        #[cfg(feature = "grpc")]
+        "grpc" => {
+            let bridge = devcon_proto::grpc::spawn_client(
+                &format!("http://{}:{}", host, port),
+                Duration::from_secs(10),
+            )?;
+            eprintln!("Using gRPC control plane at {}:{}", host, port);
+            Ok(("127.0.0.1".to_string(), bridge.port()))
+        }
+        #[cfg(not(feature = "grpc"))]
+        "grpc" => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "gRPC control scheme requested, but the agent was built without the 'grpc' feature",
+        )),
+        _ => Ok((host.to_string(), port)),
+    }
+}
+
+/// Close the sending half and wait for the control server to close the connection
+///
+/// This makes sure a bridged connection delivered the request before the agent exits.
+fn close_connection(stream: &mut TcpStream) -> io::Result<()> {
+    stream.shutdown(std::net::Shutdown::Write)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let _ = io::copy(stream, &mut io::sink());
+    Ok(())
+}
+
 /// Handle tunnel request - open NEW connection to data port and proxy data
+///
+/// With a `tunnel_key`, the data connection is encrypted after sending the tunnel id.
 fn handle_tunnel_request(
     host: &str,
     data_port: u16,
     service_port: u16,
     tunnel_id: u32,
+    max_bandwidth: Option<u64>,
+    tunnel_key: Option<TunnelKey>,
 ) -> io::Result<()> {
     eprintln!(
         "Tunnel request received: tunnel_id={}, service_port={}, connecting to {}:{}",
@@ -142,24 +323,18 @@ fn handle_tunnel_request(
     };
 
     // Proxy data bidirectionally
-    let mut tunnel_read = tunnel_stream.try_clone()?;
-    let mut tunnel_write = tunnel_stream;
-    let mut local_read = local_stream.try_clone()?;
-    let mut local_write = local_stream;
-
-    // Spawn thread to copy from tunnel to local service
-    let handle = std::thread::spawn(move || {
-        let result = std::io::copy(&mut tunnel_read, &mut local_write);
-        let _ = local_write.shutdown(std::net::Shutdown::Write);
-        result
-    });
-
-    // Copy from local service to tunnel in this thread
-    let result = std::io::copy(&mut local_read, &mut tunnel_write);
-    let _ = tunnel_write.shutdown(std::net::Shutdown::Write);
-
-    // Wait for the other direction to complete
-    let _ = handle.join();
+    let local_read = RateLimitedReader::new(local_stream.try_clone()?, max_bandwidth);
+    let result = match tunnel_key {
+        Some(key) => {
+            let (reader, writer) = noise::handshake(tunnel_stream, &key, true)?;
+            let tunnel_read = RateLimitedReader::new(reader, max_bandwidth);
+            proxy(tunnel_read, writer, local_read, local_stream)
+        }
+        None => {
+            let tunnel_read = RateLimitedReader::new(tunnel_stream.try_clone()?, max_bandwidth);
+            proxy(tunnel_read, tunnel_stream, local_read, local_stream)
+        }
+    };
 
     eprintln!(
         "Tunnel closed: tunnel_id={}, service_port={}",
@@ -168,63 +343,446 @@ fn handle_tunnel_request(
     result.map(|_| ())
 }
 
+/// Hosts file of the container
+const HOSTS_FILE: &str = "/etc/hosts";
+
+/// Resolve a host name to its IPv4 address
+fn resolve(name: &str) -> Option<IpAddr> {
+    (name, 0)
+        .to_socket_addrs()
+        .ok()?
+        .map(|addr| addr.ip())
+        .find(IpAddr::is_ipv4)
+}
+
+/// Find the address of the host: the control host, the names runtimes give
+/// the host, or the default gateway on runtimes without such a name
+fn host_address(control_host: &str) -> Option<IpAddr> {
+    std::iter::once(control_host)
+        .chain(RUNTIME_HOST_NAMES.iter().copied())
+        .find_map(resolve)
+        .or_else(|| {
+            let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+            hosts::default_gateway(&routes).map(IpAddr::V4)
+        })
+}
+
+/// Host the control server is reached at, the host address if the
+/// configured name does not resolve in this container
+fn control_host(configured: &str) -> String {
+    if resolve(configured).is_some() {
+        return configured.to_string();
+    }
+    match host_address(configured) {
+        Some(address) => {
+            eprintln!(
+                "Control host {} not found, using the host address {}",
+                configured, address
+            );
+            address.to_string()
+        }
+        None => configured.to_string(),
+    }
+}
+
+/// Add the host names to /etc/hosts, replacing the entry of an earlier start
+fn configure_hosts(control_host: &str, aliases: &[String]) -> io::Result<()> {
+    let address = host_address(control_host);
+    let mut names = vec![HOST_NAME.to_string()];
+    names.extend(aliases.iter().cloned());
+    match address {
+        Some(address) => eprintln!("Host {} is reachable as {}", address, names.join(", ")),
+        None => eprintln!("Host address not found, {} is not available", HOST_NAME),
+    }
+
+    // Runtimes mount the hosts file, so it is rewritten in place
+    let contents = std::fs::read_to_string(HOSTS_FILE)?;
+    let updated = hosts::update_hosts(&contents, address, &names);
+    if updated != contents {
+        std::fs::write(HOSTS_FILE, updated)?;
+    }
+    Ok(())
+}
+
+/// Cargo configuration of the container, found by cargo from every directory
+const CARGO_CONFIG: &str = "/.cargo/config.toml";
+
+/// First line of the cargo configuration written by the agent
+const CARGO_CONFIG_MARKER: &str = "# Written by devcon-agent for the devcon registry cache";
+
+/// Point cargo to the registry cache on `port`, or remove the configuration
+/// of an earlier start if the cache is disabled
+///
+/// Configurations not written by the agent are left alone.
+fn configure_cargo(port: Option<u16>) -> io::Result<()> {
+    let path = std::path::Path::new(CARGO_CONFIG);
+    let ours = match std::fs::read_to_string(path) {
+        Ok(contents) => contents.starts_with(CARGO_CONFIG_MARKER),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => true,
+        Err(e) => return Err(e),
+    };
+    if !ours {
+        if port.is_some() {
+            eprintln!(
+                "Keeping {}, cargo does not use the registry cache",
+                CARGO_CONFIG
+            );
+        }
+        return Ok(());
+    }
+
+    match port {
+        Some(port) => {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(
+                path,
+                format!(
+                    "{}\n[source.crates-io]\nreplace-with = \"devcon-cache\"\n\n[source.devcon-cache]\nregistry = \"sparse+http://localhost:{}/cargo/index/\"\n",
+                    CARGO_CONFIG_MARKER, port
+                ),
+            )
+        }
+        None if path.exists() => std::fs::remove_file(path),
+        None => Ok(()),
+    }
+}
+
+/// Listen on a container port and connect every accepted client to a port of the host
+///
+/// Each client gets its own connection to the control server, which dials the
+/// host port and then carries its raw stream.
+fn run_host_port_forward(
+    host: &str,
+    port: u16,
+    host_port: u16,
+    container_port: u16,
+    tunnel_key: Option<TunnelKey>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", container_port))?;
+    eprintln!(
+        "Forwarding 127.0.0.1:{} to host port {}",
+        container_port, host_port
+    );
+
+    for client in listener.incoming() {
+        let client = match client {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let host = host.to_string();
+        std::thread::spawn(move || {
+            if let Err(e) = connect_host_port(&host, port, host_port, client, tunnel_key) {
+                eprintln!("Connection to host port {} failed: {}", host_port, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Ask the control server to connect to a host port and proxy a client to it
+fn connect_host_port(
+    host: &str,
+    port: u16,
+    host_port: u16,
+    client: TcpStream,
+    tunnel_key: Option<TunnelKey>,
+) -> io::Result<()> {
+    let mut stream = connect_to_control_server(host, port)?;
+    send_message(
+        &mut stream,
+        &AgentMessage {
+            message: Some(agent_message::Message::ConnectHostPort(ConnectHostPort {
+                port: host_port as u32,
+                workspace: std::env::var("DEVCON_WORKSPACE_NAME").unwrap_or_default(),
+                encrypted: tunnel_key.is_some(),
+            })),
+        },
+    )?;
+
+    // Hosts which don't know the request never answer
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let reply = read_message(&mut stream);
+    stream.set_read_timeout(None)?;
+    match reply {
+        Ok(AgentMessage {
+            message: Some(agent_message::Message::HostPortConnected(reply)),
+        }) if reply.connected => {}
+        Ok(AgentMessage {
+            message: Some(agent_message::Message::HostPortConnected(reply)),
+        }) => {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                reply.reason,
+            ));
+        }
+        Ok(message) => {
+            return Err(io::Error::other(format!(
+                "Unexpected reply from control server: {:?}",
+                message
+            )));
+        }
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Control server does not support forwarding host ports",
+            ));
+        }
+        Err(e) => return Err(e),
+    }
+
+    let client_read = client.try_clone()?;
+    let result = match tunnel_key {
+        Some(key) => {
+            let (reader, writer) = noise::handshake(stream, &key, true)?;
+            proxy(client_read, writer, reader, client)
+        }
+        None => proxy(client_read, stream.try_clone()?, stream, client),
+    };
+    result.map(|_| ())
+}
+
+/// Multiplexed tunnels of a control connection
+#[derive(Clone, Default)]
+struct MuxTunnels {
+    /// Map of tunnel_id -> writer of the local service stream
+    writers: Arc<Mutex<HashMap<u32, TunnelWriter>>>,
+    /// Map of tunnel_id -> window of the data sent to the host
+    windows: Arc<Mutex<HashMap<u32, Arc<SendWindow>>>>,
+    /// Whether the tunnels are flow controlled, see [`devcon_proto::flow_control`]
+    flow_control: bool,
+}
+
+impl MuxTunnels {
+    /// Close all tunnels, used when the control connection goes away
+    fn close_all(&self) {
+        for (_, local) in self.writers.lock().unwrap().drain() {
+            local.abort();
+        }
+        for (_, window) in self.windows.lock().unwrap().drain() {
+            window.close();
+        }
+    }
+}
+
+/// Handle a multiplexed tunnel request - connect to the local service and relay its
+/// output back over the control connection as TunnelData messages
+fn open_multiplexed_tunnel(
+    service_port: u16,
+    tunnel_id: u32,
+    tunnels: &MuxTunnels,
+    tx: &SyncSender<AgentMessage>,
+    max_bandwidth: Option<u64>,
+    compress: bool,
+) -> io::Result<()> {
+    let local_stream = TcpStream::connect(format!("127.0.0.1:{}", service_port))?;
+    eprintln!(
+        "Opened multiplexed tunnel: tunnel_id={}, service_port={}",
+        tunnel_id, service_port
+    );
+    let (writer, window) = if tunnels.flow_control {
+        let tx = tx.clone();
+        let writer = TunnelWriter::with_credit(local_stream.try_clone()?, move |credit| {
+            let _ = tx.send(window_update_message(tunnel_id, credit));
+        })?;
+        (writer, SendWindow::new())
+    } else {
+        let writer = TunnelWriter::spawn(local_stream.try_clone()?)?;
+        (writer, SendWindow::unlimited())
+    };
+    let window = Arc::new(window);
+    tunnels.writers.lock().unwrap().insert(tunnel_id, writer);
+    tunnels
+        .windows
+        .lock()
+        .unwrap()
+        .insert(tunnel_id, window.clone());
+
+    // Copy from local service to the control connection, pausing while the
+    // host has no room for more data of the tunnel
+    let tx = tx.clone();
+    let windows = tunnels.windows.clone();
+    std::thread::spawn(move || {
+        let mut local_read = RateLimitedReader::new(local_stream, max_bandwidth);
+        let mut buf = vec![0u8; TUNNEL_CHUNK_SIZE];
+        while let Some(max) = window.wait(buf.len()) {
+            let n = match local_read.read(&mut buf[..max]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            window.consume(n);
+            let msg = AgentMessage {
+                message: Some(agent_message::Message::TunnelData(TunnelData::new(
+                    tunnel_id,
+                    &buf[..n],
+                    compress,
+                ))),
+            };
+            if tx.send(msg).is_err() {
+                break;
+            }
+        }
+        windows.lock().unwrap().remove(&tunnel_id);
+        let _ = tx.send(close_tunnel_message(tunnel_id));
+        eprintln!("Local side of tunnel {} closed", tunnel_id);
+    });
+
+    Ok(())
+}
+
+/// Build a TunnelWindowUpdate message granting the host credit for a tunnel
+fn window_update_message(tunnel_id: u32, credit: u32) -> AgentMessage {
+    AgentMessage {
+        message: Some(agent_message::Message::TunnelWindowUpdate(
+            TunnelWindowUpdate { tunnel_id, credit },
+        )),
+    }
+}
+
+/// Build a TunnelClose message for the given tunnel
+fn close_tunnel_message(tunnel_id: u32) -> AgentMessage {
+    AgentMessage {
+        message: Some(agent_message::Message::TunnelClose(TunnelClose {
+            tunnel_id,
+        })),
+    }
+}
+
+/// Interpreters whose script name identifies a process better than the interpreter itself
+const INTERPRETERS: &[&str] = &[
+    "node", "python", "python3", "ruby", "bun", "deno", "php", "perl", "java",
+];
+
+/// Process listening on a forwarded port
+#[derive(Debug, Clone)]
+struct ListeningProcess {
+    pid: u32,
+    command: String,
+}
+
 /// Scan for listening ports on the container
 /// Reads /proc/net/tcp and /proc/net/tcp6 to find ports in LISTEN state (0A)
-/// Returns only ports > 1024 (non-privileged ports)
-fn scan_listening_ports() -> io::Result<Vec<u16>> {
-    let mut ports = HashSet::new();
-
-    // Read IPv4 listening ports from /proc/net/tcp
-    if let Ok(file) = File::open("/proc/net/tcp") {
-        let reader = BufReader::new(file);
-        for line in reader.lines().skip(1).flatten() {
-            // Skip header line
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let state = parts[3];
+/// Returns only ports > 1024 (non-privileged ports) with the inode of their socket
+fn scan_listening_ports() -> io::Result<HashMap<u16, u64>> {
+    let mut ports = HashMap::new();
+
+    // Read IPv4 and IPv6 listening ports
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(file) = File::open(path) {
+            let reader = BufReader::new(file);
+            for line in reader.lines().skip(1).flatten() {
+                // Skip header line
+                let parts: Vec<&str> = line.split_whitespace().collect();
                 // 0A = LISTEN state in hex
-                if state == "0A" {
+                if parts.len() >= 10 && parts[3] == "0A" {
                     // Local address is in format "ADDR:PORT" in hex
-                    if let Some(local_addr) = parts.get(1)
-                        && let Some(port_hex) = local_addr.split(':').nth(1)
+                    if let Some(port_hex) = parts[1].split(':').nth(1)
                         && let Ok(port) = u16::from_str_radix(port_hex, 16)
                         && port > 1024
                     {
-                        ports.insert(port);
+                        let inode = parts[9].parse().unwrap_or(0);
+                        ports.insert(port, inode);
                     }
                 }
             }
         }
     }
 
-    // Read IPv6 listening ports from /proc/net/tcp6
-    if let Ok(file) = File::open("/proc/net/tcp6") {
-        let reader = BufReader::new(file);
-        for line in reader.lines().skip(1).flatten() {
-            // Skip header line
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let state = parts[3];
-                // 0A = LISTEN state in hex
-                if state == "0A" {
-                    // Local address is in format "ADDR:PORT" in hex
-                    if let Some(local_addr) = parts.get(1)
-                        && let Some(port_hex) = local_addr.split(':').nth(1)
-                        && let Ok(port) = u16::from_str_radix(port_hex, 16)
-                        && port > 1024
-                    {
-                        ports.insert(port);
-                    }
-                }
+    Ok(ports)
+}
+
+/// Find the processes owning the given socket inodes
+///
+/// Walks the file descriptors in /proc/*/fd; processes of other users are
+/// skipped, as their descriptors can't be read.
+fn socket_processes(inodes: &HashSet<u64>) -> HashMap<u64, ListeningProcess> {
+    let mut processes = HashMap::new();
+    if inodes.is_empty() {
+        return processes;
+    }
+
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return processes;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            // Socket descriptors link to "socket:[<inode>]"
+            if let Some(inode) = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u64>().ok())
+                && inodes.contains(&inode)
+                && let Some(command) = process_command(pid)
+            {
+                processes.insert(inode, ListeningProcess { pid, command });
             }
         }
+        if processes.len() == inodes.len() {
+            break;
+        }
+    }
+    processes
+}
+
+/// Short command name of a process, e.g. "vite" for `node node_modules/.bin/vite`
+///
+/// Falls back to /proc/<pid>/comm if the command line can't be read.
+fn process_command(pid: u32) -> Option<String> {
+    let basename = |arg: &str| arg.rsplit('/').next().unwrap_or(arg).to_string();
+
+    if let Ok(cmdline) = std::fs::read(format!("/proc/{}/cmdline", pid)) {
+        let args: Vec<String> = cmdline
+            .split(|b| *b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .collect();
+        if let Some(program) = args.first().map(|arg| basename(arg)) {
+            let script = args.iter().skip(1).find(|arg| !arg.starts_with('-'));
+            return Some(match script {
+                Some(script) if INTERPRETERS.contains(&program.as_str()) => basename(script),
+                _ => program,
+            });
+        }
     }
 
-    Ok(ports.into_iter().collect())
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|comm| comm.trim().to_string())
+        .filter(|comm| !comm.is_empty())
+}
+
+/// Find the process listening on a port, if it can be determined
+fn port_process(port: u16) -> Option<ListeningProcess> {
+    let inode = *scan_listening_ports().ok()?.get(&port)?;
+    socket_processes(&HashSet::from([inode])).remove(&inode)
 }
 
 /// Run port forward daemon for a specific port
-fn run_port_forward_daemon(stream: &mut TcpStream, port: u16, host: &str) -> io::Result<()> {
+///
+/// With a `tunnel_key`, the data connections of the tunnels are encrypted.
+fn run_port_forward_daemon(
+    stream: &mut TcpStream,
+    port: u16,
+    host: &str,
+    max_bandwidth: Option<u64>,
+    tunnel_key: Option<TunnelKey>,
+) -> io::Result<()> {
     eprintln!("Port forward daemon running for port {}", port);
 
     // Keep the connection alive and handle tunnel requests
@@ -244,9 +802,14 @@ fn run_port_forward_daemon(stream: &mut TcpStream, port: u16, host: &str) -> io:
                         // Spawn new thread to handle this tunnel
                         let host = host.to_string();
                         std::thread::spawn(move || {
-                            if let Err(e) =
-                                handle_tunnel_request(&host, data_port, service_port, tunnel_id)
-                            {
+                            if let Err(e) = handle_tunnel_request(
+                                &host,
+                                data_port,
+                                service_port,
+                                tunnel_id,
+                                max_bandwidth,
+                                tunnel_key,
+                            ) {
                                 eprintln!("Error handling tunnel: {}", e);
                             }
                         });
@@ -270,37 +833,199 @@ fn run_port_forward_daemon(stream: &mut TcpStream, port: u16, host: &str) -> io:
     Ok(())
 }
 
+/// Perform the Hello/HelloAck handshake with the control server
+///
+/// Hosts which predate the handshake never answer; in that case the agent
+/// continues with protocol version 1 and no capabilities. Returns the enabled
+/// capabilities, or an `Unsupported` error if the host rejects the agent.
+///
+/// When `offered` includes encrypted tunnels, the agent refuses hosts which
+/// don't enable them, rather than sending plain data.
+fn negotiate_protocol(
+    stream: &mut TcpStream,
+    offered: &[Capability],
+) -> io::Result<Vec<Capability>> {
+    let encrypt = offered.contains(&Capability::EncryptedTunnels);
+    let capabilities = offered.iter().map(|c| *c as i32).collect();
+    send_message(
+        stream,
+        &AgentMessage {
+            message: Some(agent_message::Message::Hello(Hello {
+                protocol_version: PROTOCOL_VERSION,
+                min_protocol_version: MIN_PROTOCOL_VERSION,
+                capabilities,
+                workspace: std::env::var("DEVCON_WORKSPACE_NAME").unwrap_or_default(),
+            })),
+        },
+    )?;
+
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let reply = read_message(stream);
+    stream.set_read_timeout(None)?;
+
+    let capabilities = match reply {
+        Ok(AgentMessage {
+            message: Some(agent_message::Message::HelloAck(ack)),
+        }) => {
+            if !ack.accepted {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Control server rejected agent: {}", ack.reason),
+                ));
+            }
+            let capabilities: Vec<Capability> = ack.capabilities().collect();
+            eprintln!(
+                "Negotiated protocol version {} with capabilities {:?}",
+                ack.protocol_version, capabilities
+            );
+            capabilities
+        }
+        Ok(message) => {
+            eprintln!("Unexpected reply to Hello: {:?}", message);
+            Vec::new()
+        }
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            eprintln!("Control server did not answer Hello, using protocol version 1");
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+
+    if encrypt && !capabilities.contains(&Capability::EncryptedTunnels) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Control server does not support encrypted tunnels",
+        ));
+    }
+    Ok(capabilities)
+}
+
+/// Spawn a thread sending pings to the control server and closing the
+/// connection once no message was received within the heartbeat timeout
+fn spawn_heartbeat(
+    stream: TcpStream,
+    tx: SyncSender<AgentMessage>,
+    last_seen: Arc<Mutex<Instant>>,
+    running: Arc<AtomicBool>,
+    interval: Duration,
+    timeout: Duration,
+) {
+    std::thread::spawn(move || {
+        let mut nonce = 0u64;
+        while running.load(Ordering::SeqCst) {
+            let slept = Instant::now();
+            std::thread::sleep(interval);
+
+            // Waking up far too late means the container was paused, the
+            // control server expects the silence of a paused agent
+            if slept.elapsed() > interval * 2 {
+                *last_seen.lock().unwrap() = Instant::now();
+                continue;
+            }
+
+            let silence = last_seen.lock().unwrap().elapsed();
+            if silence > timeout {
+                eprintln!(
+                    "No message from control server for {:?}, dropping connection",
+                    silence
+                );
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+                break;
+            }
+
+            nonce += 1;
+            let ping = AgentMessage {
+                message: Some(agent_message::Message::Ping(Ping { nonce })),
+            };
+            if tx.send(ping).is_err() {
+                break;
+            }
+        }
+    });
+}
+
 /// Run the agent as a daemon, maintaining connection to control server
+///
+/// Returns once the connection to the control server is lost.
 fn run_daemon(
     host: &str,
     port: u16,
     scan_interval_secs: u64,
     excluded_ports: HashSet<u16>,
+    heartbeat_interval: Duration,
+    heartbeat_timeout: Duration,
+    settings: &PortSettings,
 ) -> io::Result<()> {
     let mut stream = connect_to_control_server(host, port)?;
     eprintln!("Connected to control server");
 
-    // Set read timeout to allow checking channel messages periodically
-    stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+    // Encrypted tunnels dial dedicated data connections instead of multiplexing
+    let offered: &[Capability] = if settings.tunnel_key.is_some() {
+        &[Capability::EncryptedTunnels, Capability::Heartbeat]
+    } else {
+        &[
+            Capability::Multiplex,
+            Capability::Heartbeat,
+            Capability::Compression,
+            Capability::FlowControl,
+        ]
+    };
+    let capabilities = negotiate_protocol(&mut stream, offered)?;
+    let compression = capabilities.contains(&Capability::Compression);
+    let running = Arc::new(AtomicBool::new(true));
+    let last_seen = Arc::new(Mutex::new(Instant::now()));
 
     let scan_failed_warning_shown = Arc::new(AtomicBool::new(false));
 
-    // Create channel for port scanner to send messages to main thread
-    let (tx, rx) = mpsc::channel::<AgentMessage>();
+    // Create bounded channel for all outgoing messages (port scanner and tunnels).
+    // The bound applies backpressure to tunnels when the control connection is slow.
+    let (tx, rx) = mpsc::sync_channel::<AgentMessage>(64);
+
+    // Spawn writer thread which owns the sending half of the control connection
+    {
+        let mut writer = stream.try_clone()?;
+        std::thread::spawn(move || {
+            for msg in rx {
+                if let Err(e) = send_message(&mut writer, &msg) {
+                    eprintln!("Failed to send message to control server: {}", e);
+                    break;
+                }
+            }
+        });
+    }
+
+    let tunnels = MuxTunnels {
+        flow_control: capabilities.contains(&Capability::FlowControl),
+        ..Default::default()
+    };
+
+    if capabilities.contains(&Capability::Heartbeat) && !heartbeat_interval.is_zero() {
+        spawn_heartbeat(
+            stream.try_clone()?,
+            tx.clone(),
+            last_seen.clone(),
+            running.clone(),
+            heartbeat_interval,
+            heartbeat_timeout,
+        );
+    }
 
     // Spawn port scanner thread
     {
         let scan_failed_warning = Arc::clone(&scan_failed_warning_shown);
+        let tx = tx.clone();
+        let running = running.clone();
+        let settings = settings.clone();
         std::thread::spawn(move || {
             let mut forwarded_ports: HashSet<u16> = HashSet::new();
             let mut candidate_new_ports: HashSet<u16> = HashSet::new();
             let mut candidate_removed_ports: HashSet<u16> = HashSet::new();
 
-            loop {
+            while running.load(Ordering::SeqCst) {
                 // Scan for listening ports
                 match scan_listening_ports() {
                     Ok(current_ports) => {
-                        let current_set: HashSet<u16> = current_ports.into_iter().collect();
+                        let current_set: HashSet<u16> = current_ports.keys().copied().collect();
 
                         // Find ports that are listening but not yet forwarded
                         let new_ports: HashSet<u16> =
@@ -310,18 +1035,42 @@ fn run_daemon(
                         let removed_ports: HashSet<u16> =
                             forwarded_ports.difference(&current_set).copied().collect();
 
-                        // Filter out excluded ports (already forwarded by Docker)
-                        let new_ports: HashSet<u16> =
-                            new_ports.difference(&excluded_ports).copied().collect();
+                        // Filter out excluded ports (already forwarded by Docker) and
+                        // ports which the auto-forward policy excludes
+                        let new_ports: HashSet<u16> = new_ports
+                            .difference(&excluded_ports)
+                            .copied()
+                            .filter(|port| settings.auto_forward.permits(*port))
+                            .collect();
+
+                        // Look up the processes of ports about to be forwarded
+                        let inodes: HashSet<u64> = new_ports
+                            .intersection(&candidate_new_ports)
+                            .filter_map(|port| current_ports.get(port).copied())
+                            .collect();
+                        let processes = socket_processes(&inodes);
 
                         // Process new ports with debouncing (2 consecutive scans)
                         for port in &new_ports {
                             if candidate_new_ports.contains(port) {
                                 // Port seen in 2 consecutive scans, start forwarding
-                                eprintln!("Auto-forwarding port {} (detected)", port);
+                                let process = current_ports
+                                    .get(port)
+                                    .and_then(|inode| processes.get(inode));
+                                match process {
+                                    Some(process) => eprintln!(
+                                        "Auto-forwarding port {} (detected, {} pid {})",
+                                        port, process.command, process.pid
+                                    ),
+                                    None => eprintln!("Auto-forwarding port {} (detected)", port),
+                                }
                                 let msg = AgentMessage {
                                     message: Some(agent_message::Message::StartPortForward(
-                                        StartPortForward { port: *port as u32 },
+                                        StartPortForward {
+                                            auto: true,
+                                            action: settings.announce(*port) as i32,
+                                            ..settings.start_request(*port, process)
+                                        },
                                     )),
                                 };
                                 if tx.send(msg).is_ok() {
@@ -390,58 +1139,103 @@ fn run_daemon(
 
     // Keep the connection alive and handle any incoming messages
     loop {
-        // Check for port forward requests from scanner thread
-        match rx.try_recv() {
-            Ok(msg) => {
-                eprintln!("Sending port forward request from scanner");
-                if let Err(e) = send_message(&mut stream, &msg) {
-                    eprintln!("Failed to send message to control server: {}", e);
-                }
-            }
-            Err(TryRecvError::Empty) => {
-                // No messages from scanner, continue
-            }
-            Err(TryRecvError::Disconnected) => {
-                eprintln!("Scanner thread disconnected");
-                break;
-            }
+        let message = read_message(&mut stream);
+        if message.is_ok() {
+            *last_seen.lock().unwrap() = Instant::now();
         }
 
-        // Read incoming messages from control server
-        match read_message(&mut stream) {
-            Ok(message) => {
-                eprintln!("Received message from host: {:?}", message);
-                // Handle incoming messages from host
-                match message.message {
-                    Some(agent_message::Message::TunnelRequest(req)) => {
-                        let service_port = req.port as u16;
-                        let tunnel_id = req.tunnel_id;
-                        let data_port = req.data_port as u16;
+        match message {
+            Ok(message) => match message.message {
+                Some(agent_message::Message::Ping(ping)) => {
+                    let _ = tx.send(AgentMessage {
+                        message: Some(agent_message::Message::Pong(Pong { nonce: ping.nonce })),
+                    });
+                }
+                Some(agent_message::Message::Pong(_)) => {}
+                Some(agent_message::Message::TunnelRequest(req)) if req.data_port == 0 => {
+                    let service_port = req.port as u16;
+                    let tunnel_id = req.tunnel_id;
+                    if let Err(e) = open_multiplexed_tunnel(
+                        service_port,
+                        tunnel_id,
+                        &tunnels,
+                        &tx,
+                        settings.bandwidth.for_port(service_port),
+                        compression && settings.compress(service_port),
+                    ) {
                         eprintln!(
-                            "Received tunnel request: tunnel_id={}, service_port={}, data_port={}",
-                            tunnel_id, service_port, data_port
+                            "Failed to connect to local service on port {}: {}",
+                            service_port, e
                         );
+                        let _ = tx.send(close_tunnel_message(tunnel_id));
+                    }
+                }
+                Some(agent_message::Message::TunnelRequest(req)) => {
+                    let service_port = req.port as u16;
+                    let tunnel_id = req.tunnel_id;
+                    let data_port = req.data_port as u16;
+                    eprintln!(
+                        "Received tunnel request: tunnel_id={}, service_port={}, data_port={}",
+                        tunnel_id, service_port, data_port
+                    );
 
-                        // Spawn new thread to handle this tunnel
-                        let host = host.to_string();
-                        std::thread::spawn(move || {
-                            if let Err(e) =
-                                handle_tunnel_request(&host, data_port, service_port, tunnel_id)
-                            {
-                                eprintln!("Error handling tunnel: {}", e);
+                    // Spawn new thread to handle this tunnel
+                    let host = host.to_string();
+                    let max_bandwidth = settings.bandwidth.for_port(service_port);
+                    let tunnel_key = settings.tunnel_key;
+                    std::thread::spawn(move || {
+                        if let Err(e) = handle_tunnel_request(
+                            &host,
+                            data_port,
+                            service_port,
+                            tunnel_id,
+                            max_bandwidth,
+                            tunnel_key,
+                        ) {
+                            eprintln!("Error handling tunnel: {}", e);
+                        }
+                    });
+                }
+                Some(agent_message::Message::TunnelData(data)) => {
+                    // Only queue the data, a slow local service must not stall the connection
+                    let mut writers = tunnels.writers.lock().unwrap();
+                    match writers.get(&data.tunnel_id) {
+                        Some(local) => {
+                            let queued = data.payload().and_then(|payload| local.send(payload));
+                            if let Err(e) = queued {
+                                eprintln!("Closing tunnel {}: {}", data.tunnel_id, e);
+                                if let Some(local) = writers.remove(&data.tunnel_id) {
+                                    local.abort();
+                                }
+                                let _ = tx.send(close_tunnel_message(data.tunnel_id));
                             }
-                        });
+                        }
+                        // Data of a closed tunnel is dropped, but still credited so
+                        // the host doesn't pause the tunnel forever
+                        None if tunnels.flow_control => {
+                            if let Ok(payload) = data.payload() {
+                                let credit = payload.len() as u32;
+                                let _ = tx.send(window_update_message(data.tunnel_id, credit));
+                            }
+                        }
+                        None => {}
                     }
-                    _ => {
-                        eprintln!("Received message: {:?}", message);
+                }
+                Some(agent_message::Message::TunnelWindowUpdate(update)) => {
+                    if let Some(window) = tunnels.windows.lock().unwrap().get(&update.tunnel_id) {
+                        window.grant(update.credit);
                     }
                 }
-            }
-            Err(e) => {
-                // Ignore timeout errors (expected due to read timeout)
-                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut {
-                    continue;
+                Some(agent_message::Message::TunnelClose(close)) => {
+                    if let Some(local) = tunnels.writers.lock().unwrap().remove(&close.tunnel_id) {
+                        local.close();
+                    }
                 }
+                _ => {
+                    eprintln!("Received message: {:?}", message);
+                }
+            },
+            Err(e) => {
                 if e.kind() == io::ErrorKind::UnexpectedEof {
                     eprintln!("Control server connection closed");
                     break;
@@ -452,27 +1246,74 @@ fn run_daemon(
         }
     }
 
+    // Stop background threads and close tunnels of this connection
+    running.store(false, Ordering::SeqCst);
+    tunnels.close_all();
+
     Ok(())
 }
 
 fn main() {
     let cli = Cli::parse();
+    let settings = PortSettings {
+        bandwidth: cli.max_bandwidth.unwrap_or_default(),
+        uncompressed: cli
+            .uncompressed_ports
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+        tunnel_key: cli.tunnel_key,
+        ..Default::default()
+    };
+    // The gRPC bridge only carries control connections, the dedicated data
+    // connections of encrypted tunnels can't reach the host through it
+    if cli.control_scheme == "grpc" && settings.tunnel_key.is_some() {
+        eprintln!("Error: Encrypted tunnels require the tcp control scheme");
+        std::process::exit(1);
+    }
+    let (control_host, control_port) = match control_address(
+        &control_host(&cli.control_host),
+        cli.control_port,
+        &cli.control_scheme,
+    ) {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let result = match cli.command {
         Commands::StartPortForward { port } => {
-            match connect_to_control_server(&cli.control_host, cli.control_port) {
+            match connect_to_control_server(&control_host, control_port) {
                 Ok(mut stream) => {
+                    // Only dedicated tunnels are served here, the host must not
+                    // multiplex them or expect answers to pings
+                    let offered: &[Capability] = if settings.tunnel_key.is_some() {
+                        &[Capability::EncryptedTunnels]
+                    } else {
+                        &[]
+                    };
                     eprintln!("Requesting port forward for port {}", port);
+                    let max_bandwidth = settings.bandwidth.for_port(port);
                     let msg = AgentMessage {
-                        message: Some(agent_message::Message::StartPortForward(StartPortForward {
-                            port: port as u32,
-                        })),
+                        message: Some(agent_message::Message::StartPortForward(
+                            settings.start_request(port, port_process(port).as_ref()),
+                        )),
                     };
-                    match send_message(&mut stream, &msg) {
+                    match negotiate_protocol(&mut stream, offered)
+                        .and_then(|_| send_message(&mut stream, &msg))
+                    {
                         Ok(_) => {
                             eprintln!("Port forward request sent, keeping connection alive...");
                             // Keep connection alive and handle any reverse tunnel requests
-                            run_port_forward_daemon(&mut stream, port, &cli.control_host)
+                            run_port_forward_daemon(
+                                &mut stream,
+                                port,
+                                &control_host,
+                                max_bandwidth,
+                                settings.tunnel_key,
+                            )
                         }
                         Err(e) => Err(e),
                     }
@@ -481,30 +1322,93 @@ fn main() {
             }
         }
         Commands::StopPortForward { port } => {
-            match connect_to_control_server(&cli.control_host, cli.control_port) {
+            match connect_to_control_server(&control_host, control_port) {
                 Ok(mut stream) => {
                     let msg = AgentMessage {
                         message: Some(agent_message::Message::StopPortForward(StopPortForward {
                             port: port as u32,
                         })),
                     };
-                    send_message(&mut stream, &msg)
+                    send_message(&mut stream, &msg).and_then(|_| close_connection(&mut stream))
                 }
                 Err(e) => Err(e),
             }
         }
-        Commands::OpenUrl { url } => {
-            match connect_to_control_server(&cli.control_host, cli.control_port) {
+        Commands::OpenUrl { url } => match connect_to_control_server(&control_host, control_port) {
+            Ok(mut stream) => {
+                let msg = AgentMessage {
+                    message: Some(agent_message::Message::OpenUrl(OpenUrl { url })),
+                };
+                send_message(&mut stream, &msg).and_then(|_| close_connection(&mut stream))
+            }
+            Err(e) => Err(e),
+        },
+        Commands::Open { path, editor } => std::fs::canonicalize(&path).and_then(|path| {
+            let mut stream = connect_to_control_server(&control_host, control_port)?;
+            let msg = AgentMessage {
+                message: Some(agent_message::Message::OpenFile(OpenFile {
+                    path: path.to_string_lossy().to_string(),
+                    workspace: std::env::var("DEVCON_WORKSPACE_NAME").unwrap_or_default(),
+                    editor,
+                })),
+            };
+            send_message(&mut stream, &msg).and_then(|_| close_connection(&mut stream))
+        }),
+        Commands::Notify { message, title } => {
+            match connect_to_control_server(&control_host, control_port) {
                 Ok(mut stream) => {
                     let msg = AgentMessage {
-                        message: Some(agent_message::Message::OpenUrl(OpenUrl { url })),
+                        message: Some(agent_message::Message::Notify(Notify {
+                            title: title.unwrap_or_default(),
+                            body: message,
+                            workspace: std::env::var("DEVCON_WORKSPACE_NAME").unwrap_or_default(),
+                        })),
                     };
-                    send_message(&mut stream, &msg)
+                    send_message(&mut stream, &msg).and_then(|_| close_connection(&mut stream))
                 }
                 Err(e) => Err(e),
             }
         }
-        Commands::Daemon { scan_interval } => {
+        Commands::ForwardHostPort {
+            host_port,
+            container_port,
+        } => {
+            if cli.control_scheme == "grpc" {
+                Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Forwarding host ports requires the tcp control scheme",
+                ))
+            } else {
+                run_host_port_forward(
+                    &control_host,
+                    control_port,
+                    host_port,
+                    container_port.unwrap_or(host_port),
+                    settings.tunnel_key,
+                )
+            }
+        }
+        Commands::Daemon {
+            scan_interval,
+            heartbeat_interval,
+            heartbeat_timeout,
+            auto_forward,
+            auto_forward_ports,
+            auto_forward_ignore_ports,
+            on_auto_forward,
+            registry_cache_port,
+            host_aliases,
+        } => {
+            let settings = PortSettings {
+                auto_forward: AutoForwardPolicy {
+                    mode: auto_forward,
+                    allowed: auto_forward_ports.unwrap_or_default(),
+                    denied: auto_forward_ignore_ports.unwrap_or_default(),
+                },
+                actions: on_auto_forward.unwrap_or_default(),
+                ..settings
+            };
+
             // Parse excluded ports from CLI arg or environment variable
             let mut excluded_ports = HashSet::new();
 
@@ -518,16 +1422,66 @@ fn main() {
                 }
             }
 
+            if let Err(e) = configure_hosts(&cli.control_host, &host_aliases.unwrap_or_default()) {
+                eprintln!("Failed to add the host names to {}: {}", HOSTS_FILE, e);
+            }
+
+            // Serve the registry cache of the host in the background
+            if let Err(e) = configure_cargo(registry_cache_port) {
+                eprintln!("Failed to configure cargo for the registry cache: {}", e);
+            }
+            if let Some(port) = registry_cache_port {
+                // The listener is the host's port, it must not be forwarded back
+                excluded_ports.insert(port);
+                if cli.control_scheme == "grpc" {
+                    eprintln!("The registry cache requires the tcp control scheme");
+                } else {
+                    let host = control_host.clone();
+                    let tunnel_key = settings.tunnel_key;
+                    std::thread::spawn(move || {
+                        if let Err(e) =
+                            run_host_port_forward(&host, control_port, port, port, tunnel_key)
+                        {
+                            eprintln!("Failed to serve the registry cache: {}", e);
+                        }
+                    });
+                }
+            }
+
             if !excluded_ports.is_empty() {
                 eprintln!("Excluding ports from auto-forwarding: {:?}", excluded_ports);
             }
+            if !settings.bandwidth.is_empty() {
+                eprintln!("Limiting forwarded port bandwidth: {}", settings.bandwidth);
+            }
+            if !settings.uncompressed.is_empty() {
+                eprintln!("Not compressing ports: {:?}", settings.uncompressed);
+            }
+            if settings.auto_forward != AutoForwardPolicy::default() {
+                eprintln!("Auto-forward policy: {:?}", settings.auto_forward);
+            }
 
-            run_daemon(
-                &cli.control_host,
-                cli.control_port,
-                scan_interval,
-                excluded_ports,
-            )
+            // Reconnect with exponential backoff whenever the connection is lost
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                match run_daemon(
+                    &control_host,
+                    control_port,
+                    scan_interval,
+                    excluded_ports.clone(),
+                    Duration::from_secs(heartbeat_interval),
+                    Duration::from_secs(heartbeat_timeout),
+                    &settings,
+                ) {
+                    Ok(()) => backoff = Duration::from_secs(1),
+                    Err(e) if e.kind() == io::ErrorKind::Unsupported => break Err(e),
+                    Err(e) => eprintln!("Connection to control server failed: {}", e),
+                }
+
+                eprintln!("Reconnecting to control server in {:?}", backoff);
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(Duration::from_secs(30));
+            }
         }
     };
 
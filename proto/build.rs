@@ -1,3 +1,10 @@
 fn main() {
+    // The gRPC control plane needs the service stubs, the raw TCP protocol only the messages
+    #[cfg(feature = "grpc")]
+    tonic_prost_build::configure()
+        .compile_protos(&["agent.proto"], &["."])
+        .unwrap();
+
+    #[cfg(not(feature = "grpc"))]
     prost_build::compile_protos(&["agent.proto"], &["."]).unwrap();
 }
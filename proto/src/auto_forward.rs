@@ -0,0 +1,310 @@
+//! Auto-forward policy for ports detected by the agent, shared by the agent and the host.
+//!
+//! The agent skips detected ports which the policy of its workspace excludes,
+//! and the host refuses automatic forwards which its configured policy excludes.
+//! Forwarded ports can request an action of the host, like opening the browser.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::AutoForwardAction;
+
+/// Parses a comma-separated list of ports and port ranges like `3000-3999,8080`.
+pub fn parse_port_ranges(value: &str) -> Result<Vec<RangeInclusive<u16>>, String> {
+    let parse_port = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port > 0)
+            .ok_or_else(|| format!("Invalid port '{}'", port.trim()))
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (start, end) = match entry.split_once('-') {
+                Some((start, end)) => (parse_port(start)?, parse_port(end)?),
+                None => {
+                    let port = parse_port(entry)?;
+                    (port, port)
+                }
+            };
+            if start > end {
+                return Err(format!(
+                    "Invalid port range '{}', start is after end",
+                    entry
+                ));
+            }
+            Ok(start..=end)
+        })
+        .collect()
+}
+
+/// Formats port ranges in the form read by [`parse_port_ranges`].
+pub fn format_port_ranges(ranges: &[RangeInclusive<u16>]) -> String {
+    ranges
+        .iter()
+        .map(|range| {
+            if range.start() == range.end() {
+                range.start().to_string()
+            } else {
+                format!("{}-{}", range.start(), range.end())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the ports contained in both lists of ranges.
+pub fn intersect_port_ranges(
+    a: &[RangeInclusive<u16>],
+    b: &[RangeInclusive<u16>],
+) -> Vec<RangeInclusive<u16>> {
+    a.iter()
+        .flat_map(|a| {
+            b.iter().filter_map(move |b| {
+                let start = *a.start().max(b.start());
+                let end = *a.end().min(b.end());
+                (start <= end).then_some(start..=end)
+            })
+        })
+        .collect()
+}
+
+/// Which detected ports are forwarded automatically.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AutoForward {
+    /// Every detected port
+    #[default]
+    All,
+    /// No detected port, ports can still be forwarded explicitly
+    None,
+    /// Only detected ports of the allow list
+    Allowlist,
+}
+
+impl FromStr for AutoForward {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "all" => Ok(AutoForward::All),
+            "none" => Ok(AutoForward::None),
+            "allowlist" => Ok(AutoForward::Allowlist),
+            other => Err(format!(
+                "Invalid auto-forward mode '{}', expected all, none or allowlist",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AutoForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoForward::All => write!(f, "all"),
+            AutoForward::None => write!(f, "none"),
+            AutoForward::Allowlist => write!(f, "allowlist"),
+        }
+    }
+}
+
+/// Decides which detected ports are forwarded automatically.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoForwardPolicy {
+    /// Which detected ports are forwarded
+    pub mode: AutoForward,
+    /// Ports forwarded in [`AutoForward::Allowlist`] mode
+    pub allowed: Vec<RangeInclusive<u16>>,
+    /// Ports never forwarded automatically, takes precedence over `allowed`
+    pub denied: Vec<RangeInclusive<u16>>,
+}
+
+impl AutoForwardPolicy {
+    /// Whether a detected `port` is forwarded automatically.
+    pub fn permits(&self, port: u16) -> bool {
+        let allowed = match self.mode {
+            AutoForward::All => true,
+            AutoForward::None => false,
+            AutoForward::Allowlist => self.allowed.iter().any(|r| r.contains(&port)),
+        };
+        allowed && !self.denied.iter().any(|r| r.contains(&port))
+    }
+}
+
+/// Parses an action name as used in `onAutoForward`.
+fn parse_action(value: &str) -> Result<AutoForwardAction, String> {
+    match value.trim() {
+        "silent" => Ok(AutoForwardAction::None),
+        "notify" => Ok(AutoForwardAction::Notify),
+        "openBrowser" => Ok(AutoForwardAction::OpenBrowser),
+        other => Err(format!(
+            "Invalid auto-forward action '{}', expected silent, notify or openBrowser",
+            other
+        )),
+    }
+}
+
+/// Name of an action in the form read by [`parse_action`].
+fn action_name(action: AutoForwardAction) -> &'static str {
+    match action {
+        AutoForwardAction::None => "silent",
+        AutoForwardAction::Notify => "notify",
+        AutoForwardAction::OpenBrowser => "openBrowser",
+    }
+}
+
+/// Per-port actions taken when a detected port was forwarded, with an
+/// optional default for all other ports.
+///
+/// The textual form is a comma-separated list of `PORT=ACTION` or
+/// `START-END=ACTION` rules, with `*=ACTION` for the default, e.g.
+/// `3000=openBrowser,8000-8010=notify,*=silent`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortActions {
+    /// Port ranges and their action, first match wins
+    pub rules: Vec<(RangeInclusive<u16>, AutoForwardAction)>,
+    /// Action for ports not matched by any rule
+    pub default: Option<AutoForwardAction>,
+}
+
+impl PortActions {
+    /// Returns the action for a forwarded port.
+    pub fn for_port(&self, port: u16) -> AutoForwardAction {
+        self.rules
+            .iter()
+            .find(|(range, _)| range.contains(&port))
+            .map(|(_, action)| *action)
+            .or(self.default)
+            .unwrap_or(AutoForwardAction::None)
+    }
+
+    /// Returns true if no actions are configured.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.default.is_none()
+    }
+}
+
+impl FromStr for PortActions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut actions = PortActions::default();
+
+        for rule in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (ports, action) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid action rule '{}', expected PORT=ACTION", rule))?;
+            let action = parse_action(action)?;
+
+            if ports.trim() == "*" {
+                actions.default = Some(action);
+                continue;
+            }
+            for range in parse_port_ranges(ports)? {
+                actions.rules.push((range, action));
+            }
+        }
+
+        Ok(actions)
+    }
+}
+
+impl fmt::Display for PortActions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|(range, action)| {
+                format!(
+                    "{}={}",
+                    format_port_ranges(std::slice::from_ref(range)),
+                    action_name(*action)
+                )
+            })
+            .collect();
+        if let Some(action) = self.default {
+            rules.push(format!("*={}", action_name(action)));
+        }
+        write!(f, "{}", rules.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_port_ranges() {
+        assert_eq!(
+            parse_port_ranges(" 3000-3999, 8080 ,").unwrap(),
+            vec![3000..=3999, 8080..=8080]
+        );
+        assert!(parse_port_ranges("").unwrap().is_empty());
+        for invalid in ["0", "70000", "4000-3000", "http"] {
+            assert!(parse_port_ranges(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            format_port_ranges(&[3000..=3999, 8080..=8080]),
+            "3000-3999,8080"
+        );
+    }
+
+    #[test]
+    fn test_intersect_port_ranges() {
+        assert_eq!(
+            intersect_port_ranges(&[3000..=3999, 8080..=8080], &[3500..=9000]),
+            vec![3500..=3999, 8080..=8080]
+        );
+        assert!(intersect_port_ranges(&[3000..=3999], &[4000..=4000]).is_empty());
+    }
+
+    #[test]
+    fn test_auto_forward_policy() {
+        assert!(AutoForwardPolicy::default().permits(3000));
+
+        let policy = AutoForwardPolicy {
+            mode: AutoForward::Allowlist,
+            allowed: vec![3000..=3999],
+            denied: vec![3306..=3306],
+        };
+        assert!(policy.permits(3000));
+        assert!(!policy.permits(3306));
+        assert!(!policy.permits(9229));
+
+        let policy = AutoForwardPolicy {
+            mode: AutoForward::None,
+            allowed: vec![3000..=3999],
+            ..Default::default()
+        };
+        assert!(!policy.permits(3000));
+
+        assert_eq!("allowlist".parse(), Ok(AutoForward::Allowlist));
+        assert!("some".parse::<AutoForward>().is_err());
+    }
+
+    #[test]
+    fn test_port_actions() {
+        let actions: PortActions = "3000=openBrowser, 8000-8010=notify, *=silent"
+            .parse()
+            .unwrap();
+        assert_eq!(actions.for_port(3000), AutoForwardAction::OpenBrowser);
+        assert_eq!(actions.for_port(8005), AutoForwardAction::Notify);
+        assert_eq!(actions.for_port(9000), AutoForwardAction::None);
+        assert_eq!(
+            actions.to_string(),
+            "3000=openBrowser,8000-8010=notify,*=silent"
+        );
+        assert_eq!(actions.to_string().parse::<PortActions>(), Ok(actions));
+
+        assert_eq!(
+            PortActions::default().for_port(3000),
+            AutoForwardAction::None
+        );
+        assert!("3000=preview".parse::<PortActions>().is_err());
+        assert!("3000".parse::<PortActions>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Bandwidth limiting for forwarded ports, shared by the agent and the host.
+//!
+//! Limits are expressed in bytes per second and enforced with a token bucket
+//! around the tunnel copy loops.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Parses a bandwidth value such as `1048576`, `512K`, `10MB/s` or `1G`.
+///
+/// Suffixes are binary multiples (1K = 1024 bytes). Returns bytes per second.
+pub fn parse_bandwidth(value: &str) -> Result<u64, String> {
+    let normalized = value.trim().to_ascii_uppercase();
+    let normalized = normalized.strip_suffix("/S").unwrap_or(&normalized);
+    let normalized = normalized.strip_suffix('B').unwrap_or(normalized);
+
+    let (number, multiplier) = match normalized.chars().last() {
+        Some('K') => (&normalized[..normalized.len() - 1], 1024),
+        Some('M') => (&normalized[..normalized.len() - 1], 1024 * 1024),
+        Some('G') => (&normalized[..normalized.len() - 1], 1024 * 1024 * 1024),
+        _ => (normalized, 1),
+    };
+
+    let number: u64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid bandwidth '{}' (e.g. '512K', '10M')", value))?;
+    if number == 0 {
+        return Err(format!("Bandwidth '{}' must be greater than zero", value));
+    }
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Bandwidth '{}' is too large", value))
+}
+
+/// Per-port bandwidth limits with an optional default for all other ports.
+///
+/// The textual form is a comma-separated list of `PORT=RATE` or
+/// `START-END=RATE` rules, with `*=RATE` for the default, e.g.
+/// `3000=1048576,8000-8010=512K,*=10M`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BandwidthLimits {
+    /// Inclusive port ranges and their limit in bytes per second, first match wins
+    pub rules: Vec<(u16, u16, u64)>,
+    /// Limit for ports not matched by any rule
+    pub default: Option<u64>,
+}
+
+impl BandwidthLimits {
+    /// Returns the limit in bytes per second for a port, if any.
+    pub fn for_port(&self, port: u16) -> Option<u64> {
+        self.rules
+            .iter()
+            .find(|(start, end, _)| (*start..=*end).contains(&port))
+            .map(|(_, _, rate)| *rate)
+            .or(self.default)
+    }
+
+    /// Returns true if no limits are configured.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.default.is_none()
+    }
+}
+
+impl FromStr for BandwidthLimits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut limits = BandwidthLimits::default();
+
+        for rule in s.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (ports, rate) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid bandwidth rule '{}', expected PORT=RATE", rule))?;
+            let rate = parse_bandwidth(rate)?;
+
+            let ports = ports.trim();
+            if ports == "*" {
+                limits.default = Some(rate);
+                continue;
+            }
+
+            let (start, end) = ports.split_once('-').unwrap_or((ports, ports));
+            let parse_port = |p: &str| {
+                p.trim()
+                    .parse::<u16>()
+                    .map_err(|_| format!("Invalid port '{}' in bandwidth rule '{}'", p, rule))
+            };
+            let (start, end) = (parse_port(start)?, parse_port(end)?);
+            if start > end {
+                return Err(format!("Invalid port range in bandwidth rule '{}'", rule));
+            }
+            limits.rules.push((start, end, rate));
+        }
+
+        Ok(limits)
+    }
+}
+
+impl fmt::Display for BandwidthLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|(start, end, rate)| {
+                if start == end {
+                    format!("{}={}", start, rate)
+                } else {
+                    format!("{}-{}={}", start, end, rate)
+                }
+            })
+            .collect();
+        if let Some(rate) = self.default {
+            rules.push(format!("*={}", rate));
+        }
+        write!(f, "{}", rules.join(","))
+    }
+}
+
+/// Token bucket allowing bursts of up to one second worth of traffic.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full bucket refilling at `rate` bytes per second.
+    pub fn new(rate: u64) -> Self {
+        let rate = rate.max(1);
+        Self {
+            rate,
+            tokens: rate as f64,
+            last: Instant::now(),
+        }
+    }
+
+    /// Largest amount of bytes which can be taken at once.
+    pub fn capacity(&self) -> u64 {
+        self.rate
+    }
+
+    /// Takes `bytes` from the bucket and returns how long the caller has to
+    /// wait before the bytes are covered by the rate.
+    pub fn take(&mut self, bytes: u64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate as f64)
+        }
+    }
+}
+
+/// Reader throttling the wrapped reader to a bandwidth limit.
+///
+/// Without a limit, reads are passed through unchanged.
+pub struct RateLimitedReader<R> {
+    inner: R,
+    bucket: Option<TokenBucket>,
+}
+
+impl<R> RateLimitedReader<R> {
+    /// Wraps `inner`, limiting it to `limit` bytes per second if set.
+    pub fn new(inner: R, limit: Option<u64>) -> Self {
+        Self {
+            inner,
+            bucket: limit.map(TokenBucket::new),
+        }
+    }
+}
+
+impl<R: Read> Read for RateLimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(bucket) = &mut self.bucket else {
+            return self.inner.read(buf);
+        };
+
+        let len = buf.len().min(bucket.capacity() as usize);
+        let n = self.inner.read(&mut buf[..len])?;
+        let wait = bucket.take(n as u64);
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bandwidth() {
+        assert_eq!(parse_bandwidth("1000"), Ok(1000));
+        assert_eq!(parse_bandwidth("512K"), Ok(512 * 1024));
+        assert_eq!(parse_bandwidth("10MB/s"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_bandwidth("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_bandwidth("0").is_err());
+        assert!(parse_bandwidth("fast").is_err());
+    }
+
+    #[test]
+    fn test_bandwidth_limits_roundtrip() {
+        let limits: BandwidthLimits = "3000=1K, 8000-8010=2048, *=10M".parse().unwrap();
+
+        assert_eq!(limits.for_port(3000), Some(1024));
+        assert_eq!(limits.for_port(8005), Some(2048));
+        assert_eq!(limits.for_port(9000), Some(10 * 1024 * 1024));
+        assert_eq!(
+            limits.to_string().parse::<BandwidthLimits>().unwrap(),
+            limits
+        );
+
+        assert!("".parse::<BandwidthLimits>().unwrap().is_empty());
+        assert!("3000".parse::<BandwidthLimits>().is_err());
+        assert!("9000-8000=1K".parse::<BandwidthLimits>().is_err());
+    }
+
+    #[test]
+    fn test_token_bucket_delays_after_burst() {
+        let mut bucket = TokenBucket::new(1000);
+
+        assert_eq!(bucket.take(1000), Duration::ZERO);
+        let wait = bucket.take(500);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_rate_limited_reader_caps_reads() {
+        let data = [7u8; 64];
+        let mut reader = RateLimitedReader::new(&data[..], Some(16));
+        let mut buf = [0u8; 64];
+
+        assert_eq!(reader.read(&mut buf).unwrap(), 16);
+
+        let mut unlimited = RateLimitedReader::new(&data[..], None);
+        assert_eq!(unlimited.read(&mut buf).unwrap(), 64);
+    }
+}
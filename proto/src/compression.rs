@@ -0,0 +1,117 @@
+//! Compression of multiplexed tunnel payloads.
+//!
+//! Once `CAPABILITY_COMPRESSION` is negotiated, `TunnelData` payloads may be
+//! LZ4 compressed. Each message carries its own `Compression` marker, so chunks
+//! which do not shrink (e.g. already compressed protocols) are sent as is.
+
+use std::io;
+
+use crate::{Compression, TUNNEL_CHUNK_SIZE, TunnelData};
+
+/// Payloads smaller than this are never compressed.
+const MIN_COMPRESS_SIZE: usize = 64;
+
+impl TunnelData {
+    /// Creates a `TunnelData` message, compressing the payload if `compress`
+    /// is set and doing so makes it smaller.
+    pub fn new(tunnel_id: u32, data: &[u8], compress: bool) -> Self {
+        if compress && data.len() >= MIN_COMPRESS_SIZE {
+            let compressed = lz4_flex::compress_prepend_size(data);
+            if compressed.len() < data.len() {
+                return TunnelData {
+                    tunnel_id,
+                    data: compressed,
+                    compression: Compression::Lz4 as i32,
+                };
+            }
+        }
+
+        TunnelData {
+            tunnel_id,
+            data: data.to_vec(),
+            compression: Compression::None as i32,
+        }
+    }
+
+    /// Returns the decompressed payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidData` error if the payload is corrupt, uses an unknown
+    /// compression, or would decompress to more than `TUNNEL_CHUNK_SIZE` bytes.
+    pub fn payload(&self) -> io::Result<Vec<u8>> {
+        match Compression::try_from(self.compression) {
+            Ok(Compression::None) => Ok(self.data.clone()),
+            Ok(Compression::Lz4) => {
+                let size = self
+                    .data
+                    .get(..4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                    .ok_or_else(|| invalid_data("Truncated compressed tunnel data"))?;
+                if size > TUNNEL_CHUNK_SIZE {
+                    return Err(invalid_data("Compressed tunnel data exceeds chunk size"));
+                }
+                lz4_flex::decompress(&self.data[4..], size).map_err(invalid_data)
+            }
+            Err(_) => Err(invalid_data(format!(
+                "Unknown tunnel data compression {}",
+                self.compression
+            ))),
+        }
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressible_data_roundtrip() {
+        let data = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".repeat(32);
+        let message = TunnelData::new(7, &data, true);
+
+        assert_eq!(message.compression(), Compression::Lz4);
+        assert!(message.data.len() < data.len());
+        assert_eq!(message.payload().unwrap(), data);
+    }
+
+    #[test]
+    fn test_incompressible_data_is_sent_raw() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let data: Vec<u8> = (0..1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        let message = TunnelData::new(7, &data, true);
+
+        assert_eq!(message.compression(), Compression::None);
+        assert_eq!(message.payload().unwrap(), data);
+
+        let message = TunnelData::new(7, &[b'a'; 1024], false);
+        assert_eq!(message.compression(), Compression::None);
+    }
+
+    #[test]
+    fn test_oversized_payload_is_rejected() {
+        let mut data = ((TUNNEL_CHUNK_SIZE + 1) as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(&[0u8; 16]);
+        let message = TunnelData {
+            tunnel_id: 7,
+            data,
+            compression: Compression::Lz4 as i32,
+        };
+
+        assert_eq!(
+            message.payload().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
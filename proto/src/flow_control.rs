@@ -0,0 +1,134 @@
+//! Flow control of multiplexed tunnels.
+//!
+//! Once `CAPABILITY_FLOW_CONTROL` is negotiated, each direction of a
+//! multiplexed tunnel starts with a window of [`TUNNEL_WINDOW`] bytes. The
+//! sender consumes the window with every `TunnelData` message and pauses the
+//! tunnel once it is exhausted. The receiver grants the window back with
+//! `TunnelWindowUpdate` messages as its writer drains the data to the local
+//! stream, so it never queues more than one window per tunnel however slow
+//! the local side is. As in yamux, updates are batched until half a window
+//! was written.
+
+use std::sync::{Condvar, Mutex};
+
+/// Initial send window of each direction of a tunnel in bytes.
+pub const TUNNEL_WINDOW: u32 = 256 * 1024;
+
+/// Written bytes after which the receiver grants them back to the sender.
+pub const WINDOW_UPDATE_THRESHOLD: u32 = TUNNEL_WINDOW / 2;
+
+/// Send window of one direction of a tunnel.
+pub struct SendWindow {
+    /// Bytes which may be sent and whether the window was closed
+    state: Mutex<(u32, bool)>,
+    opened: Condvar,
+    /// Whether the peer grants credit, without flow control the window is unlimited
+    limited: bool,
+}
+
+impl SendWindow {
+    /// Creates a window of [`TUNNEL_WINDOW`] bytes.
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new((TUNNEL_WINDOW, false)),
+            opened: Condvar::new(),
+            limited: true,
+        }
+    }
+
+    /// Creates a window for peers which don't grant credit.
+    pub fn unlimited() -> Self {
+        Self {
+            limited: false,
+            ..Self::new()
+        }
+    }
+
+    /// Waits until data may be sent, returning how many bytes up to `max`.
+    ///
+    /// Returns `None` once the window was closed.
+    pub fn wait(&self, max: usize) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let (available, closed) = *state;
+            if closed {
+                return None;
+            }
+            if !self.limited {
+                return Some(max);
+            }
+            if available > 0 {
+                return Some(max.min(available as usize));
+            }
+            state = self.opened.wait(state).unwrap();
+        }
+    }
+
+    /// Consumes the window for `bytes` sent.
+    pub fn consume(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = state.0.saturating_sub(bytes as u32);
+    }
+
+    /// Adds credit granted by the receiver, waking a paused sender.
+    pub fn grant(&self, credit: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = state.0.saturating_add(credit);
+        self.opened.notify_all();
+    }
+
+    /// Closes the window, e.g. when the connection is lost, so a paused
+    /// sender gives up.
+    pub fn close(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.opened.notify_all();
+    }
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sender_pauses_until_credit_is_granted() {
+        let window = Arc::new(SendWindow::new());
+        assert_eq!(window.wait(16), Some(16));
+        window.consume(TUNNEL_WINDOW as usize);
+
+        let (tx, rx) = mpsc::channel();
+        let sender = window.clone();
+        std::thread::spawn(move || tx.send(sender.wait(16 * 1024)).unwrap());
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        window.grant(100);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), Some(100));
+    }
+
+    #[test]
+    fn test_closed_window_releases_sender() {
+        let window = Arc::new(SendWindow::new());
+        window.consume(TUNNEL_WINDOW as usize);
+
+        let (tx, rx) = mpsc::channel();
+        let sender = window.clone();
+        std::thread::spawn(move || tx.send(sender.wait(1)).unwrap());
+        window.close();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unlimited_window() {
+        let window = SendWindow::unlimited();
+        window.consume(TUNNEL_WINDOW as usize * 4);
+        assert_eq!(window.wait(1024), Some(1024));
+    }
+}
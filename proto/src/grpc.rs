@@ -0,0 +1,266 @@
+//! gRPC transport for the control connection.
+//!
+//! The `ControlPlane` service carries the same `AgentMessage`s as the
+//! length-prefixed TCP protocol, one bidirectional `Session` stream per agent
+//! connection. Instead of reimplementing the protocol on top of async code, both
+//! sides bridge the gRPC stream to a local length-prefixed TCP connection:
+//!
+//! - the host runs [`spawn_server`] in front of its raw control listener
+//! - the agent runs [`spawn_client`] and points its daemon at the returned address
+
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use prost::Message;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::transport::{Endpoint, Server};
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::AgentMessage;
+use crate::control_plane_client::ControlPlaneClient;
+use crate::control_plane_server::{ControlPlane, ControlPlaneServer};
+
+/// Maximum size of a single length-prefixed message, matching the TCP protocol.
+const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Number of messages buffered per direction before back-pressure applies.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Starts a gRPC server on `listen` which forwards every `Session` to a new
+/// TCP connection to the raw control server at `backend`.
+///
+/// The server runs on a background thread. Returns the address it listens on.
+///
+/// # Errors
+///
+/// Returns an error if the runtime cannot be created or `listen` cannot be bound.
+pub fn spawn_server(listen: SocketAddr, backend: SocketAddr) -> io::Result<SocketAddr> {
+    let runtime = runtime()?;
+    let listener = runtime.block_on(TcpListener::bind(listen))?;
+    let addr = listener.local_addr()?;
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            // Failures of single sessions only end that session, an error here
+            // means the listener itself is gone.
+            let _ = Server::builder()
+                .add_service(ControlPlaneServer::new(SessionBridge { backend }))
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await;
+        })
+    });
+
+    Ok(addr)
+}
+
+/// Starts a local TCP listener which forwards every accepted connection as a
+/// new `Session` to the gRPC server at `endpoint` (e.g. `http://host:15000`).
+///
+/// If the server cannot be reached within `connect_timeout`, the local
+/// connection is closed, which the agent treats like any other disconnect.
+/// Returns the local address to connect to.
+///
+/// # Errors
+///
+/// Returns an error if the runtime cannot be created, the local listener
+/// cannot be bound, or `endpoint` is not a valid URI.
+pub fn spawn_client(endpoint: &str, connect_timeout: Duration) -> io::Result<SocketAddr> {
+    let endpoint = Endpoint::from_shared(endpoint.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .connect_timeout(connect_timeout);
+
+    let runtime = runtime()?;
+    let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0"))?;
+    let addr = listener.local_addr()?;
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let endpoint = endpoint.clone();
+                tokio::spawn(async move {
+                    let Ok(channel) = endpoint.connect().await else {
+                        return;
+                    };
+                    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+                    let Ok(response) = ControlPlaneClient::new(channel)
+                        .session(ReceiverStream::new(rx))
+                        .await
+                    else {
+                        return;
+                    };
+
+                    let (reader, writer) = stream.into_split();
+                    tokio::spawn(tcp_to_grpc(reader, tx, |message| message));
+                    grpc_to_tcp(response.into_inner(), writer).await;
+                });
+            }
+        })
+    });
+
+    Ok(addr)
+}
+
+/// `ControlPlane` implementation forwarding sessions to the raw control server.
+struct SessionBridge {
+    backend: SocketAddr,
+}
+
+#[tonic::async_trait]
+impl ControlPlane for SessionBridge {
+    type SessionStream = ReceiverStream<Result<AgentMessage, Status>>;
+
+    async fn session(
+        &self,
+        request: Request<Streaming<AgentMessage>>,
+    ) -> Result<Response<Self::SessionStream>, Status> {
+        let stream = TcpStream::connect(self.backend)
+            .await
+            .map_err(|e| Status::unavailable(format!("Control server unavailable: {}", e)))?;
+        let (reader, writer) = stream.into_split();
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+
+        tokio::spawn(grpc_to_tcp(request.into_inner(), writer));
+        tokio::spawn(tcp_to_grpc(reader, tx, Ok));
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn runtime() -> io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()
+}
+
+/// Writes messages received on a gRPC stream as length-prefixed frames until
+/// either side closes.
+async fn grpc_to_tcp(mut inbound: Streaming<AgentMessage>, mut writer: impl AsyncWrite + Unpin) {
+    while let Ok(Some(message)) = inbound.message().await {
+        if write_frame(&mut writer, &message).await.is_err() {
+            break;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+/// Reads length-prefixed frames and sends them to a gRPC stream until either
+/// side closes.
+async fn tcp_to_grpc<T>(
+    mut reader: impl AsyncRead + Unpin,
+    tx: mpsc::Sender<T>,
+    wrap: fn(AgentMessage) -> T,
+) {
+    while let Ok(message) = read_frame(&mut reader).await {
+        if tx.send(wrap(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<AgentMessage> {
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message too large: {} bytes", len),
+        ));
+    }
+
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    AgentMessage::decode(&buf[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+async fn write_frame(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &AgentMessage,
+) -> io::Result<()> {
+    let buf = message.encode_to_vec();
+    writer.write_u32(buf.len() as u32).await?;
+    writer.write_all(&buf).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+    use crate::{Hello, HelloAck, agent_message};
+
+    fn send(stream: &mut std::net::TcpStream, message: &AgentMessage) {
+        let buf = message.encode_to_vec();
+        stream.write_all(&(buf.len() as u32).to_be_bytes()).unwrap();
+        stream.write_all(&buf).unwrap();
+    }
+
+    fn receive(stream: &mut std::net::TcpStream) -> AgentMessage {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut buf = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut buf).unwrap();
+        AgentMessage::decode(&buf[..]).unwrap()
+    }
+
+    #[test]
+    fn test_session_roundtrip() {
+        // Raw control server answering a Hello with a HelloAck
+        let backend = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = backend.accept().unwrap();
+            let message = receive(&mut stream);
+            let Some(agent_message::Message::Hello(hello)) = message.message else {
+                panic!("Expected Hello, got {:?}", message);
+            };
+            let ack = HelloAck {
+                accepted: true,
+                protocol_version: hello.protocol_version,
+                capabilities: hello.capabilities,
+                reason: String::new(),
+            };
+            send(
+                &mut stream,
+                &AgentMessage {
+                    message: Some(agent_message::Message::HelloAck(ack)),
+                },
+            );
+        });
+
+        let server = spawn_server("127.0.0.1:0".parse().unwrap(), backend_addr).unwrap();
+        let client = spawn_client(&format!("http://{}", server), Duration::from_secs(5)).unwrap();
+
+        let mut stream = std::net::TcpStream::connect(client).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        send(
+            &mut stream,
+            &AgentMessage {
+                message: Some(agent_message::Message::Hello(Hello {
+                    protocol_version: 3,
+                    min_protocol_version: 1,
+                    capabilities: vec![1, 2],
+                    workspace: "test".to_string(),
+                })),
+            },
+        );
+
+        let message = receive(&mut stream);
+        let Some(agent_message::Message::HelloAck(ack)) = message.message else {
+            panic!("Expected HelloAck, got {:?}", message);
+        };
+        assert!(ack.accepted);
+        assert_eq!(ack.capabilities, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_invalid_endpoint_is_rejected() {
+        assert!(spawn_client("not a uri", Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Host names of the host inside containers, shared by the agent and the host.
+//!
+//! Runtimes name the host differently, `host.docker.internal` on Docker
+//! Desktop, `host.containers.internal` on Podman and `host.container.internal`
+//! on Apple's container runtime, while Linux Docker has no name at all. The
+//! agent resolves the host address once and adds [`HOST_NAME`] and the
+//! configured aliases to `/etc/hosts`, in a block it replaces on every start.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Name of the host in every container.
+pub const HOST_NAME: &str = "host.devcon.internal";
+
+/// Names runtimes give the host, tried in order when resolving its address.
+pub const RUNTIME_HOST_NAMES: &[&str] = &[
+    "host.docker.internal",
+    "host.containers.internal",
+    "host.container.internal",
+];
+
+const BEGIN_MARKER: &str = "# BEGIN devcon host names";
+const END_MARKER: &str = "# END devcon host names";
+
+/// Parses a comma-separated list of host names like `db.internal,llm.internal`.
+pub fn parse_host_names(value: &str) -> Result<Vec<String>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let valid = name.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+            if valid {
+                Ok(name.to_ascii_lowercase())
+            } else {
+                Err(format!("Invalid host name '{}'", name))
+            }
+        })
+        .collect()
+}
+
+/// Returns the default gateway of a `/proc/net/route` table, which is the
+/// host for containers on a bridge network.
+pub fn default_gateway(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+        // Addresses are hex in host byte order, little endian on all Linux targets
+        let gateway = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        (gateway != 0).then(|| Ipv4Addr::from(gateway.swap_bytes()))
+    })
+}
+
+/// Replaces the devcon block of a hosts file with an entry mapping `names`
+/// to `address`, or removes it if `address` is `None`. Other entries are
+/// kept as they are.
+pub fn update_hosts(contents: &str, address: Option<IpAddr>, names: &[String]) -> String {
+    let mut updated = String::new();
+    let mut in_block = false;
+    for line in contents.lines() {
+        match line.trim() {
+            BEGIN_MARKER => in_block = true,
+            END_MARKER => in_block = false,
+            _ if in_block => {}
+            _ => {
+                updated.push_str(line);
+                updated.push('\n');
+            }
+        }
+    }
+
+    if let Some(address) = address
+        && !names.is_empty()
+    {
+        updated.push_str(&format!(
+            "{}\n{}\t{}\n{}\n",
+            BEGIN_MARKER,
+            address,
+            names.join(" "),
+            END_MARKER
+        ));
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_names() {
+        assert_eq!(
+            parse_host_names(" db.internal, LLM ,").unwrap(),
+            vec!["db.internal", "llm"]
+        );
+        assert!(parse_host_names("").unwrap().is_empty());
+        for invalid in ["-db", "db..internal", "db_internal", "db internal"] {
+            assert!(parse_host_names(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_default_gateway() {
+        let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                     eth0\t0011A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                     eth0\t00000000\t0111A8C0\t0003\t0\t0\t0\t00000000\n";
+        assert_eq!(default_gateway(table), Some(Ipv4Addr::new(192, 168, 17, 1)));
+        assert_eq!(default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+
+    #[test]
+    fn test_update_hosts() {
+        let names = vec![HOST_NAME.to_string(), "db.internal".to_string()];
+        let original = "127.0.0.1\tlocalhost\n172.17.0.2\tabc123\n";
+
+        let updated = update_hosts(original, Some("172.17.0.1".parse().unwrap()), &names);
+        assert!(updated.starts_with(original));
+        assert!(updated.contains("172.17.0.1\thost.devcon.internal db.internal\n"));
+
+        // A new address replaces the block
+        let replaced = update_hosts(&updated, Some("10.0.0.1".parse().unwrap()), &names);
+        assert!(!replaced.contains("172.17.0.1"));
+        assert_eq!(replaced.matches(BEGIN_MARKER).count(), 1);
+
+        assert_eq!(update_hosts(&replaced, None, &names), original);
+    }
+}
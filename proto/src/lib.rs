@@ -4,3 +4,111 @@ pub mod agent {
 }
 
 pub use agent::*;
+
+pub mod auto_forward;
+pub mod bandwidth;
+pub mod compression;
+pub mod flow_control;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hosts;
+pub mod noise;
+pub mod proxy;
+pub mod tunnel_writer;
+
+/// Protocol version spoken by this build of the agent and host.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Oldest protocol version this build can still talk to.
+///
+/// Version 1 agents predate the handshake and never send a `Hello`.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Maximum payload size of a single `TunnelData` message.
+pub const TUNNEL_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Negotiates the protocol for an agent `Hello` against the locally supported capabilities.
+///
+/// The lower of both protocol versions is used, and the agent is rejected if
+/// that version is below the minimum either side supports. The enabled
+/// capabilities are those supported by both sides.
+pub fn negotiate(hello: &Hello, supported: &[Capability]) -> HelloAck {
+    let version = hello.protocol_version.min(PROTOCOL_VERSION);
+    let minimum = hello.min_protocol_version.max(MIN_PROTOCOL_VERSION);
+
+    if version < minimum {
+        return HelloAck {
+            accepted: false,
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Vec::new(),
+            reason: format!(
+                "Unsupported protocol version {} (minimum {}), host supports {} to {}",
+                hello.protocol_version,
+                hello.min_protocol_version,
+                MIN_PROTOCOL_VERSION,
+                PROTOCOL_VERSION
+            ),
+        };
+    }
+
+    HelloAck {
+        accepted: true,
+        protocol_version: version,
+        capabilities: hello
+            .capabilities()
+            .filter(|c| supported.contains(c))
+            .map(|c| c as i32)
+            .collect(),
+        reason: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hello(version: u32, min: u32, capabilities: &[Capability]) -> Hello {
+        Hello {
+            protocol_version: version,
+            min_protocol_version: min,
+            capabilities: capabilities.iter().map(|c| *c as i32).collect(),
+            workspace: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_negotiate_same_version() {
+        let ack = negotiate(
+            &hello(
+                PROTOCOL_VERSION,
+                1,
+                &[Capability::Multiplex, Capability::Udp],
+            ),
+            &[Capability::Multiplex],
+        );
+        assert!(ack.accepted);
+        assert_eq!(ack.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(
+            ack.capabilities().collect::<Vec<_>>(),
+            vec![Capability::Multiplex]
+        );
+    }
+
+    #[test]
+    fn test_negotiate_downgrades_newer_agent() {
+        let ack = negotiate(&hello(PROTOCOL_VERSION + 5, 1, &[]), &[]);
+        assert!(ack.accepted);
+        assert_eq!(ack.protocol_version, PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_negotiate_rejects_incompatible_agent() {
+        let ack = negotiate(
+            &hello(PROTOCOL_VERSION + 2, PROTOCOL_VERSION + 1, &[]),
+            &[Capability::Multiplex],
+        );
+        assert!(!ack.accepted);
+        assert!(ack.capabilities.is_empty());
+        assert!(!ack.reason.is_empty());
+    }
+}
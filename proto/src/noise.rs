@@ -0,0 +1,317 @@
+//! Encryption of dedicated tunnel connections.
+//!
+//! Agents of projects with encrypted tunnels run a Noise `NNpsk0` handshake on
+//! every data connection after sending the tunnel id, using a 32 byte key
+//! shared with the host out of band. Afterwards all tunnel data is sent as
+//! length-prefixed ChaCha20-Poly1305 frames, so it can neither be read nor
+//! modified on the network between container and host.
+//!
+//! Both directions keep their own implicit nonce counter, which allows the
+//! reading and writing halves to be used from different threads.
+
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+
+use snow::{Builder, StatelessTransportState};
+
+use crate::proxy::CloseWrite;
+
+/// Noise protocol used for tunnel encryption.
+const NOISE_PARAMS: &str = "Noise_NNpsk0_25519_ChaChaPoly_BLAKE2s";
+
+/// Length of a tunnel key in bytes.
+pub const TUNNEL_KEY_LEN: usize = 32;
+
+/// Maximum length of a Noise message.
+const MAX_FRAME_LEN: usize = 65535;
+
+/// Length of the authentication tag of every frame.
+const TAG_LEN: usize = 16;
+
+/// Maximum payload carried by a single frame.
+const MAX_PAYLOAD_LEN: usize = MAX_FRAME_LEN - TAG_LEN;
+
+/// Key shared by the host and the agent of one workspace.
+pub type TunnelKey = [u8; TUNNEL_KEY_LEN];
+
+/// Generates a new random tunnel key.
+///
+/// # Errors
+///
+/// Returns an error if the system random number generator fails.
+pub fn generate_key() -> io::Result<TunnelKey> {
+    let keypair = Builder::new(params())
+        .generate_keypair()
+        .map_err(noise_error)?;
+    keypair
+        .private
+        .try_into()
+        .map_err(|_| io::Error::other("Unexpected key length"))
+}
+
+/// Formats a tunnel key as lowercase hex, e.g. for the agent environment.
+pub fn format_key(key: &TunnelKey) -> String {
+    key.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Parses a tunnel key from its hex representation.
+pub fn parse_key(value: &str) -> Result<TunnelKey, String> {
+    let value = value.trim();
+    if value.len() != TUNNEL_KEY_LEN * 2 || !value.is_ascii() {
+        return Err(format!(
+            "Tunnel key must be {} hex characters",
+            TUNNEL_KEY_LEN * 2
+        ));
+    }
+
+    let mut key = [0u8; TUNNEL_KEY_LEN];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("Invalid tunnel key: '{}' is not hex", value))?;
+    }
+    Ok(key)
+}
+
+/// Runs the handshake on `stream` and splits it into encrypted halves.
+///
+/// The agent is the `initiator`, the host the responder. Both sides must use
+/// the same `key`, otherwise the handshake fails.
+///
+/// # Errors
+///
+/// Returns an error if the connection fails or the peer uses a different key.
+pub fn handshake(
+    mut stream: TcpStream,
+    key: &TunnelKey,
+    initiator: bool,
+) -> io::Result<(EncryptedReader<TcpStream>, EncryptedWriter)> {
+    let builder = Builder::new(params()).psk(0, key).map_err(noise_error)?;
+    let mut state = if initiator {
+        builder.build_initiator()
+    } else {
+        builder.build_responder()
+    }
+    .map_err(noise_error)?;
+
+    let mut buf = vec![0u8; MAX_FRAME_LEN];
+    while !state.is_handshake_finished() {
+        if state.is_my_turn() {
+            let len = state.write_message(&[], &mut buf).map_err(noise_error)?;
+            write_frame(&mut stream, &buf[..len])?;
+        } else {
+            let frame = read_frame(&mut stream)?
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            state
+                .read_message(&frame, &mut buf)
+                .map_err(|_| invalid_data("Tunnel handshake failed, keys do not match"))?;
+        }
+    }
+
+    let state = Arc::new(state.into_stateless_transport_mode().map_err(noise_error)?);
+    let reader = EncryptedReader {
+        inner: stream.try_clone()?,
+        state: state.clone(),
+        nonce: 0,
+        plaintext: Vec::new(),
+        pos: 0,
+    };
+    let writer = EncryptedWriter {
+        stream,
+        state,
+        nonce: 0,
+        buf: Vec::new(),
+    };
+    Ok((reader, writer))
+}
+
+/// Reading half of an encrypted tunnel connection.
+pub struct EncryptedReader<R> {
+    inner: R,
+    state: Arc<StatelessTransportState>,
+    nonce: u64,
+    plaintext: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Read for EncryptedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.plaintext.len() {
+            let Some(frame) = read_frame(&mut self.inner)? else {
+                return Ok(0);
+            };
+            self.plaintext.resize(frame.len(), 0);
+            let len = self
+                .state
+                .read_message(self.nonce, &frame, &mut self.plaintext)
+                .map_err(|_| invalid_data("Failed to decrypt tunnel data"))?;
+            self.nonce += 1;
+            self.plaintext.truncate(len);
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.plaintext.len() - self.pos);
+        buf[..len].copy_from_slice(&self.plaintext[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Writing half of an encrypted tunnel connection.
+pub struct EncryptedWriter {
+    stream: TcpStream,
+    state: Arc<StatelessTransportState>,
+    nonce: u64,
+    buf: Vec<u8>,
+}
+
+impl Write for EncryptedWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // An empty frame would read as EOF on the other side
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        let payload = &data[..data.len().min(MAX_PAYLOAD_LEN)];
+        self.buf.resize(payload.len() + TAG_LEN, 0);
+        let len = self
+            .state
+            .write_message(self.nonce, payload, &mut self.buf)
+            .map_err(noise_error)?;
+        self.nonce += 1;
+        write_frame(&mut self.stream, &self.buf[..len])?;
+        Ok(payload.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl CloseWrite for EncryptedWriter {
+    fn close_write(&mut self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Write)
+    }
+}
+
+fn params() -> snow::params::NoiseParams {
+    NOISE_PARAMS.parse().expect("valid noise parameters")
+}
+
+/// Reads a frame, returning `None` on a clean EOF before the length prefix.
+fn read_frame(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 2];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut frame = vec![0u8; u16::from_be_bytes(len) as usize];
+    reader.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
+
+fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(frame.len() + 2);
+    buf.extend_from_slice(&(frame.len() as u16).to_be_bytes());
+    buf.extend_from_slice(frame);
+    writer.write_all(&buf)
+}
+
+fn noise_error(error: snow::Error) -> io::Error {
+    io::Error::other(format!("Tunnel encryption error: {}", error))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_key_roundtrip() {
+        let key = generate_key().unwrap();
+        assert_ne!(key, generate_key().unwrap());
+        assert_eq!(parse_key(&format_key(&key)).unwrap(), key);
+        assert!(parse_key("abc").is_err());
+        assert!(parse_key(&"zz".repeat(TUNNEL_KEY_LEN)).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let key = generate_key().unwrap();
+        let (client, server) = connected_pair();
+
+        let responder = thread::spawn(move || {
+            let (mut reader, mut writer) = handshake(server, &key, false).unwrap();
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).unwrap();
+            writer.write_all(&received).unwrap();
+            writer.close_write().unwrap();
+            received
+        });
+
+        let (mut reader, mut writer) = handshake(client, &key, true).unwrap();
+        // Larger than a single frame
+        let data: Vec<u8> = (0..200_001u32).map(|i| (i % 251) as u8).collect();
+        writer.write_all(&data).unwrap();
+        writer.close_write().unwrap();
+
+        let mut echoed = Vec::new();
+        reader.read_to_end(&mut echoed).unwrap();
+
+        assert_eq!(responder.join().unwrap(), data);
+        assert_eq!(echoed, data);
+    }
+
+    #[test]
+    fn test_wrong_key_fails_handshake() {
+        let (client, server) = connected_pair();
+
+        let responder = thread::spawn(move || handshake(server, &[1u8; 32], false).is_err());
+
+        assert!(handshake(client, &[2u8; 32], true).is_err());
+        assert!(responder.join().unwrap());
+    }
+
+    #[test]
+    fn test_ciphertext_does_not_leak_plaintext() {
+        let key = generate_key().unwrap();
+        let (client, server) = connected_pair();
+        let mut raw = server.try_clone().unwrap();
+
+        let responder = thread::spawn(move || handshake(server, &key, false).unwrap());
+        let (_, mut writer) = handshake(client, &key, true).unwrap();
+        // Keep the responder halves alive, the handshake frames are consumed
+        let _responder = responder.join().unwrap();
+
+        writer.write_all(b"very secret payload").unwrap();
+        writer.close_write().unwrap();
+
+        let mut captured = Vec::new();
+        raw.read_to_end(&mut captured).unwrap();
+        assert!(captured.len() > b"very secret payload".len());
+        assert!(
+            !captured
+                .windows(b"secret".len())
+                .any(|window| window == b"secret")
+        );
+    }
+}
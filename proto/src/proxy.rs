@@ -0,0 +1,130 @@
+//! Bidirectional TCP proxying for dedicated tunnel connections.
+//!
+//! Both the agent and the host relay a tunnel connection to its peer with the
+//! same loop: copy each direction with `std::io::copy` and half-close the
+//! destination once its source reaches EOF, so neither side sees more, fewer,
+//! or padded bytes.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+
+/// Writer whose sending side can be closed while reading continues.
+pub trait CloseWrite: Write {
+    /// Signals EOF to the peer.
+    fn close_write(&mut self) -> io::Result<()>;
+}
+
+impl CloseWrite for TcpStream {
+    fn close_write(&mut self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+/// Relays data between two connections until both directions are closed.
+///
+/// `a_read` is copied to `b_write` on a separate thread while `b_read` is
+/// copied to `a_write` on the current thread. The readers are usually clones
+/// of the writers, optionally wrapped for rate limiting, accounting or
+/// encryption.
+///
+/// Returns the number of bytes copied from `a` to `b` and from `b` to `a`.
+///
+/// # Errors
+///
+/// Returns the first I/O error of either direction.
+pub fn proxy(
+    mut a_read: impl Read + Send + 'static,
+    mut a_write: impl CloseWrite,
+    mut b_read: impl Read,
+    mut b_write: impl CloseWrite + Send + 'static,
+) -> io::Result<(u64, u64)> {
+    let handle = thread::spawn(move || {
+        let result = io::copy(&mut a_read, &mut b_write);
+        let _ = b_write.close_write();
+        result
+    });
+
+    let result = io::copy(&mut b_read, &mut a_write);
+    let _ = a_write.close_write();
+
+    let forwarded = handle
+        .join()
+        .map_err(|_| io::Error::other("Proxy thread panicked"))?;
+    Ok((forwarded?, result?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{SocketAddr, TcpListener};
+
+    use super::*;
+
+    /// Starts a server echoing every byte of a single connection.
+    fn echo_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = stream.try_clone().unwrap();
+            io::copy(&mut reader, &mut stream).unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+        });
+        addr
+    }
+
+    /// Starts a proxy in front of `backend` serving a single connection.
+    fn proxy_server(backend: SocketAddr) -> (SocketAddr, thread::JoinHandle<(u64, u64)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (client, _) = listener.accept().unwrap();
+            let backend = TcpStream::connect(backend).unwrap();
+            proxy(
+                client.try_clone().unwrap(),
+                client,
+                backend.try_clone().unwrap(),
+                backend,
+            )
+            .unwrap()
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_proxy_is_byte_accurate() {
+        let (addr, handle) = proxy_server(echo_server());
+
+        // Deliberately not a multiple of any buffer size
+        let data: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let writer_data = data.clone();
+        let mut writer = stream.try_clone().unwrap();
+        let writer = thread::spawn(move || {
+            writer.write_all(&writer_data).unwrap();
+            writer.shutdown(Shutdown::Write).unwrap();
+        });
+
+        let mut echoed = Vec::new();
+        stream.read_to_end(&mut echoed).unwrap();
+        writer.join().unwrap();
+
+        assert_eq!(echoed, data);
+        assert_eq!(handle.join().unwrap(), (100_003, 100_003));
+    }
+
+    #[test]
+    fn test_proxy_short_message() {
+        let (addr, handle) = proxy_server(echo_server());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"ping").unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+
+        let mut echoed = Vec::new();
+        stream.read_to_end(&mut echoed).unwrap();
+
+        assert_eq!(echoed, b"ping");
+        assert_eq!(handle.join().unwrap(), (4, 4));
+    }
+}
@@ -0,0 +1,173 @@
+//! Writers of the local side of multiplexed tunnels.
+//!
+//! The agent and the host read every `TunnelData` message of a control
+//! connection on a single thread. Writing to a tunnel's stream there would let
+//! one slow consumer stall all other tunnels and the heartbeat. Instead, each
+//! tunnel has its own writer thread, fed through a queue.
+//!
+//! With [flow control](crate::flow_control), the writer grants the sender
+//! credit as it drains the queue, so a slow consumer pauses its tunnel and the
+//! queue never holds more than a window. The queue is still bounded, a tunnel
+//! whose peer ignores its window is closed rather than blocking the reader.
+
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+
+use crate::flow_control::WINDOW_UPDATE_THRESHOLD;
+
+/// Bytes queued per tunnel before it is considered stalled.
+pub const TUNNEL_QUEUE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Writes data to the local stream of a tunnel on a dedicated thread.
+pub struct TunnelWriter {
+    tx: Sender<Vec<u8>>,
+    queued: Arc<AtomicUsize>,
+    capacity: usize,
+    stream: TcpStream,
+}
+
+impl TunnelWriter {
+    /// Spawns the writer thread of `stream` with the default queue size.
+    pub fn spawn(stream: TcpStream) -> io::Result<Self> {
+        Self::with_capacity(stream, TUNNEL_QUEUE_BYTES, |_| {})
+    }
+
+    /// Spawns the writer thread of `stream`, calling `credit` with the number
+    /// of bytes written whenever half a window was written.
+    pub fn with_credit(
+        stream: TcpStream,
+        credit: impl FnMut(u32) + Send + 'static,
+    ) -> io::Result<Self> {
+        Self::with_capacity(stream, TUNNEL_QUEUE_BYTES, credit)
+    }
+
+    /// Spawns the writer thread of `stream`, queueing up to `capacity` bytes.
+    ///
+    /// The thread shuts down the write half of the stream once the writer is
+    /// closed and the queue is drained, or both halves if a write fails.
+    pub fn with_capacity(
+        stream: TcpStream,
+        capacity: usize,
+        mut credit: impl FnMut(u32) + Send + 'static,
+    ) -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let mut writer = stream.try_clone()?;
+        let written = queued.clone();
+        std::thread::spawn(move || {
+            let mut uncredited = 0;
+            for data in rx {
+                if writer.write_all(&data).is_err() {
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+                written.fetch_sub(data.len(), Ordering::SeqCst);
+                uncredited += data.len() as u32;
+                if uncredited >= WINDOW_UPDATE_THRESHOLD {
+                    credit(uncredited);
+                    uncredited = 0;
+                }
+            }
+            let _ = writer.shutdown(Shutdown::Write);
+        });
+        Ok(Self {
+            tx,
+            queued,
+            capacity,
+            stream,
+        })
+    }
+
+    /// Queues data for the stream without blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns `WouldBlock` if the queue is full and `BrokenPipe` if a write
+    /// to the stream failed. The tunnel should be aborted in both cases.
+    pub fn send(&self, data: Vec<u8>) -> io::Result<()> {
+        let len = data.len();
+        let queued = self.queued.load(Ordering::SeqCst);
+        if queued > 0 && queued + len > self.capacity {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "tunnel consumer is too slow",
+            ));
+        }
+        self.queued.fetch_add(len, Ordering::SeqCst);
+        self.tx
+            .send(data)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "tunnel stream is closed"))
+    }
+
+    /// Closes the write half of the stream once all queued data is written.
+    pub fn close(self) {}
+
+    /// Closes the stream immediately, dropping any queued data.
+    pub fn abort(self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    fn connected() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_writes_queued_data_before_closing() {
+        let (client, mut server) = connected();
+        let writer = TunnelWriter::spawn(client).unwrap();
+        writer.send(b"hello ".to_vec()).unwrap();
+        writer.send(b"world".to_vec()).unwrap();
+        writer.close();
+
+        let mut received = String::new();
+        server.read_to_string(&mut received).unwrap();
+        assert_eq!(received, "hello world");
+    }
+
+    #[test]
+    fn test_full_queue_does_not_block() {
+        let (client, _server) = connected();
+        let writer = TunnelWriter::with_capacity(client, 1, |_| {}).unwrap();
+
+        // The peer never reads, so the writer thread blocks on the first chunk
+        let chunk = vec![0u8; 16 * 1024 * 1024];
+        let error = (0..4)
+            .find_map(|_| writer.send(chunk.clone()).err())
+            .expect("queue never filled up");
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        writer.abort();
+    }
+
+    #[test]
+    fn test_written_data_is_credited() {
+        let (client, mut server) = connected();
+        let (tx, rx) = mpsc::channel();
+        let writer =
+            TunnelWriter::with_credit(client, move |credit| tx.send(credit).unwrap()).unwrap();
+
+        let chunk = vec![0u8; WINDOW_UPDATE_THRESHOLD as usize / 2];
+        writer.send(chunk.clone()).unwrap();
+        writer.send(chunk.clone()).unwrap();
+        writer.send(chunk).unwrap();
+        writer.close();
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).unwrap();
+        assert_eq!(received.len(), WINDOW_UPDATE_THRESHOLD as usize * 3 / 2);
+        // Credit is only granted in batches of half a window
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![WINDOW_UPDATE_THRESHOLD]);
+    }
+}
@@ -0,0 +1,370 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Programmatic API
+//!
+//! High level entry point for editor plugins and other tools embedding
+//! devcon. A [`Devcon`] is opened for one project with [`DevconBuilder`] and
+//! builds, starts or opens a shell in its development container, the same
+//! way the `devcon` commands do.
+//!
+//! ```no_run
+//! use devcon::api::Devcon;
+//!
+//! # fn example() -> Result<(), devcon::api::Error> {
+//! let devcon = Devcon::builder()
+//!     .workspace("/path/to/project")
+//!     .runtime("docker")
+//!     .open()?;
+//! devcon.up()?;
+//! devcon.shell()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Failures are reported as [`Error`], whose variant tells which step
+//! failed. The underlying cause is available through
+//! [`std::error::Error::source`].
+
+use std::path::PathBuf;
+
+pub use crate::config::ConfigError;
+pub use crate::driver::control_server::TunnelError;
+pub use crate::driver::feature_process::FeatureError;
+pub use crate::driver::runtime::RuntimeError;
+
+use crate::config::Config;
+use crate::driver::cancel::CancelToken;
+use crate::driver::container::ContainerDriver;
+use crate::driver::runtime::create_runtime;
+use crate::exit_code;
+use crate::workspace::Workspace;
+
+/// Errors of the programmatic API.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// No project directory was given to the builder.
+    #[error("No workspace given, set one with DevconBuilder::workspace")]
+    MissingWorkspace,
+
+    /// The devcon configuration cannot be loaded.
+    #[error("Failed to load the devcon configuration")]
+    Config(#[source] anyhow::Error),
+
+    /// The project has no valid devcontainer definition.
+    #[error("Failed to open the workspace {}", path.display())]
+    Workspace {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// No usable container runtime was found or the name is unknown.
+    #[error("Failed to select the container runtime")]
+    Runtime(#[source] anyhow::Error),
+
+    /// Preparing features or building the image failed.
+    #[error("Failed to build the development container")]
+    Build(#[source] anyhow::Error),
+
+    /// The container could not be started.
+    #[error("Failed to start the development container")]
+    Start(#[source] anyhow::Error),
+
+    /// No shell could be opened in the container.
+    #[error("Failed to open a shell in the development container")]
+    Shell(#[source] anyhow::Error),
+
+    /// The operation was cancelled through [`Devcon::cancel_token`].
+    #[error("The operation was cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// Returns the exit code the devcon command line uses for this error.
+    ///
+    /// The code is taken from the typed error ([`ConfigError`],
+    /// [`FeatureError`], [`RuntimeError`] or [`TunnelError`]) the failure
+    /// was caused by, see [`crate::exit_code`].
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::MissingWorkspace => exit_code::FAILURE,
+            Self::Cancelled => exit_code::INTERRUPTED,
+            Self::Config(source) | Self::Workspace { source, .. } => {
+                match exit_code::exit_code(source) {
+                    exit_code::FAILURE => exit_code::CONFIG,
+                    code => code,
+                }
+            }
+            Self::Runtime(source)
+            | Self::Build(source)
+            | Self::Start(source)
+            | Self::Shell(source) => exit_code::exit_code(source),
+        }
+    }
+}
+
+/// Result of the programmatic API.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Builder of a [`Devcon`] instance.
+#[derive(Debug, Default)]
+pub struct DevconBuilder {
+    workspace: Option<PathBuf>,
+    devcontainer_config: Option<String>,
+    runtime: Option<String>,
+    config: Option<Config>,
+    offline: bool,
+    build_path: Option<PathBuf>,
+}
+
+impl DevconBuilder {
+    /// Sets the project directory containing the devcontainer definition.
+    pub fn workspace(mut self, path: impl Into<PathBuf>) -> Self {
+        self.workspace = Some(path.into());
+        self
+    }
+
+    /// Selects a named configuration in `.devcontainer/<name>/`.
+    pub fn devcontainer_config(mut self, name: impl Into<String>) -> Self {
+        self.devcontainer_config = Some(name.into());
+        self
+    }
+
+    /// Sets the container runtime: "docker", "podman", "apple" or "auto".
+    ///
+    /// Defaults to the runtime of the configuration.
+    pub fn runtime(mut self, name: impl Into<String>) -> Self {
+        self.runtime = Some(name.into());
+        self
+    }
+
+    /// Uses this configuration instead of loading the user's config file.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Uses only cached features and local base images.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Sets the directory the temporary build context is created in.
+    pub fn build_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.build_path = Some(path.into());
+        self
+    }
+
+    /// Opens the workspace and selects the container runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no workspace is set, the configuration or the
+    /// devcontainer definition cannot be loaded, or no runtime is usable.
+    pub fn open(self) -> Result<Devcon> {
+        let path = self.workspace.ok_or(Error::MissingWorkspace)?;
+        let mut config = match self.config {
+            Some(config) => config,
+            None => Config::load().map_err(Error::Config)?,
+        };
+        if self.offline {
+            config.offline = Some(true);
+        }
+        if let Some(runtime) = self.runtime {
+            config.runtime = runtime;
+        }
+
+        let workspace = Workspace::open(path.clone(), self.devcontainer_config.as_deref())
+            .map_err(|source| Error::Workspace { path, source })?;
+
+        let runtime_name = config.resolve_runtime().map_err(Error::Runtime)?;
+        let runtime = create_runtime(&config, &runtime_name).map_err(Error::Runtime)?;
+        let build_path = self
+            .build_path
+            .or_else(|| config.build_path.as_ref().map(PathBuf::from));
+
+        Ok(Devcon {
+            driver: ContainerDriver::new(config, runtime),
+            workspace,
+            build_path,
+        })
+    }
+}
+
+/// Development container of one project.
+pub struct Devcon {
+    driver: ContainerDriver,
+    workspace: Workspace,
+    build_path: Option<PathBuf>,
+}
+
+impl Devcon {
+    /// Returns a builder to open a project.
+    pub fn builder() -> DevconBuilder {
+        DevconBuilder::default()
+    }
+
+    /// Returns the opened workspace.
+    pub fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    /// Returns the token cancelling builds, e.g. from another thread.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.driver.cancel_token()
+    }
+
+    /// Builds the image of the development container.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Build`] if features or the image build fail, or
+    /// [`Error::Cancelled`] if the build was cancelled.
+    pub fn build(&self) -> Result<()> {
+        self.driver
+            .build(self.workspace.clone(), &[], self.build_path.clone())
+            .map_err(|e| self.error(Error::Build, e))
+    }
+
+    /// Starts the development container from its built image.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Start`] if the container cannot be started.
+    pub fn start(&self) -> Result<()> {
+        self.driver
+            .start(self.workspace.clone(), &[])
+            .map_err(|e| self.error(Error::Start, e))
+    }
+
+    /// Builds and starts the development container, resolving features once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Build`] or [`Error::Start`] for the failing step, or
+    /// [`Error::Cancelled`] if the build was cancelled.
+    pub fn up(&self) -> Result<()> {
+        let features = self
+            .driver
+            .check_offline_artifacts(&self.workspace, true)
+            .and_then(|_| self.driver.prepare_features(&self.workspace))
+            .map(|(features, _)| features)
+            .map_err(|e| self.error(Error::Build, e))?;
+
+        self.driver
+            .build_with_features(
+                self.workspace.clone(),
+                &[],
+                Some(features.clone()),
+                self.build_path.clone(),
+            )
+            .map_err(|e| self.error(Error::Build, e))?;
+        self.driver
+            .start_with_features(self.workspace.clone(), &[], Some(features))
+            .map_err(|e| self.error(Error::Start, e))
+    }
+
+    /// Opens an interactive shell in the running development container.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Shell`] if no container is running or the shell fails.
+    pub fn shell(&self) -> Result<()> {
+        self.driver
+            .shell(self.workspace.clone())
+            .map_err(|e| self.error(Error::Shell, e))
+    }
+
+    fn error(&self, kind: fn(anyhow::Error) -> Error, error: anyhow::Error) -> Error {
+        if self.driver.cancel_token().is_cancelled() {
+            Error::Cancelled
+        } else {
+            kind(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+    use std::fs;
+
+    use super::*;
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            dir.path().join(".devcontainer").join("devcontainer.json"),
+            r#"{ "image": "ubuntu" }"#,
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = project();
+        let devcon = Devcon::builder()
+            .workspace(dir.path())
+            .runtime("docker")
+            .config(Config::default())
+            .open()
+            .unwrap();
+        assert_eq!(
+            devcon.workspace().path,
+            fs::canonicalize(dir.path()).unwrap()
+        );
+        assert!(!devcon.cancel_token().is_cancelled());
+    }
+
+    #[test]
+    fn test_open_errors() {
+        assert!(matches!(
+            Devcon::builder().config(Config::default()).open(),
+            Err(Error::MissingWorkspace)
+        ));
+
+        let empty = tempfile::tempdir().unwrap();
+        let error = Devcon::builder()
+            .workspace(empty.path())
+            .config(Config::default())
+            .open()
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::Workspace { .. }));
+        assert!(error.source().is_some());
+
+        let dir = project();
+        assert!(matches!(
+            Devcon::builder()
+                .workspace(dir.path())
+                .runtime("lxc")
+                .config(Config::default())
+                .open(),
+            Err(Error::Runtime(_))
+        ));
+    }
+}
@@ -33,41 +33,196 @@
 //! - Executing the requested operation
 //! - Handling errors and returning results
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::{Duration, Instant};
 
 use crate::{
-    config::Config,
+    config::{Config, DotfilesRepository, RecentPath, probe_runtimes},
+    config_diff, config_migration,
+    credentials::{self, Credential},
+    devcontainer::{find_devcontainer_file, parse_feature},
+    discovery::{discover_projects, is_project, search_projects},
+    download::DownloadPolicy,
     driver::{
-        container::ContainerDriver,
+        audit,
+        build_stage::BuildStages,
+        build_timing::BuildTimings,
+        container::{self, ContainerDriver, INSTANCE_LABEL},
         control_server,
-        runtime::{apple::AppleRuntime, docker::DockerRuntime},
+        dashboard::{self, AgentInfo, ContainerUsage, Event, EventKind, WorkspaceStatus},
+        effective::Sourced,
+        feature_process,
+        hooks::{self, Hook, HookContext},
+        metrics::{ForwardStats, format_bytes},
+        oci::HttpOciClient,
+        open::{OpenPolicy, WORKSPACE_PATH_LABEL, WorkspaceMount},
+        registry_cache::RegistryCache,
+        runtime::{
+            ContainerAction, ContainerHandle, ContainerRuntime, RuntimeEvent, create_runtime,
+        },
+        sbom::{self, SbomFormat},
+        service::{ServiceConfig, ServiceManager},
+        snapshot, workspace_lock,
+        workspace_state::WorkspaceState,
     },
+    import::{self, VscodeImport},
+    project::ProjectConfig,
+    secrets,
+    validate::{self, Diagnostic, Severity},
     workspace::Workspace,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use comfy_table::{Cell, Color, ContentArrangement, Table, presets::UTF8_FULL};
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
-/// Helper function to get runtime-specific config
-fn get_runtime_specific_config(
-    config: &Config,
-    runtime_name: &str,
-) -> Result<Box<dyn crate::driver::runtime::ContainerRuntime>> {
-    let runtime_config = config.get_runtime_config();
+/// Loads the configuration, with offline mode forced on by `--offline`.
+fn load_config(offline: bool) -> Result<Config> {
+    let mut config = Config::load()?;
+    if offline {
+        config.offline = Some(true);
+    }
+    Ok(config)
+}
 
-    let runtime: Box<dyn crate::driver::runtime::ContainerRuntime> = match runtime_name {
-        "docker" => {
-            let docker_config = runtime_config.docker.unwrap_or_default();
-            Box::new(DockerRuntime::new(docker_config))
-        }
-        "apple" => {
-            let apple_config = runtime_config.apple.unwrap_or_default();
-            Box::new(AppleRuntime::new(apple_config))
+/// Opens the workspace of a project, with the named configuration if given.
+///
+/// If the project has several named configurations, no default one and none
+/// is given, the user is asked to pick one on an interactive terminal.
+fn open_workspace(
+    path: PathBuf,
+    config: Option<&str>,
+    instance: Option<&str>,
+) -> Result<Workspace> {
+    let selected = match config {
+        Some(config) => Some(config.to_string()),
+        None => prompt_config(&path)?,
+    };
+    let workspace = Workspace::open(path, selected.as_deref())?.with_instance(instance)?;
+    record_recent(&workspace);
+    Ok(workspace)
+}
+
+/// Moves the workspace to the front of the recent projects.
+///
+/// Failing to update the config doesn't fail the command.
+fn record_recent(devcontainer_workspace: &Workspace) {
+    let result = Config::load().and_then(|mut config| {
+        config.record_recent(
+            &devcontainer_workspace.path.to_string_lossy(),
+            chrono::Utc::now().timestamp(),
+        );
+        config.save()
+    });
+    if let Err(e) = result {
+        warn!("Failed to record the recent project: {:#}", e);
+    }
+}
+
+/// Asks which named configuration to use if the choice is ambiguous and a
+/// terminal is attached. Returns `None` if there is nothing to choose.
+fn prompt_config(path: &std::path::Path) -> Result<Option<String>> {
+    let configs = Workspace::configs(path);
+    let term = console::Term::stderr();
+    if configs.len() < 2 || find_devcontainer_file(path, None).is_ok() || !term.is_term() {
+        return Ok(None);
+    }
+
+    term.write_line("Multiple devcontainer configurations found:")?;
+    for (index, config) in configs.iter().enumerate() {
+        term.write_line(&format!("  {}) {}", index + 1, config))?;
+    }
+    loop {
+        term.write_str(&format!("Select a configuration [1-{}]: ", configs.len()))?;
+        let answer = term.read_line()?;
+        let answer = answer.trim();
+        let selected = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| configs.get(index.wrapping_sub(1)))
+            .or_else(|| configs.iter().find(|config| *config == answer));
+        if let Some(config) = selected {
+            return Ok(Some(config.clone()));
         }
-        _ => anyhow::bail!("Unknown runtime: {}", runtime_name),
+    }
+}
+
+/// Handles the validate command.
+///
+/// Checks the devcontainer.json of the project against the devcontainer
+/// schema and, if that succeeds, resolves every feature to verify it exists.
+/// Problems are printed as `file:line:column: severity: message`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `config` - Named configuration to validate
+///
+/// # Errors
+///
+/// Returns an error if no devcontainer.json is found or it has errors.
+/// Warnings, e.g. unknown properties, do not fail the validation.
+pub fn handle_validate_command(path: PathBuf, config: Option<&str>) -> Result<()> {
+    let file = find_devcontainer_file(&path, config)?;
+    let content = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+
+    let mut diagnostics = validate::validate(&content);
+    if !diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        diagnostics.extend(resolve_features(&content, file.parent().unwrap_or(&path))?);
+    }
+
+    for diagnostic in &diagnostics {
+        println!("{}:{}", file.display(), diagnostic);
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!("{} is invalid, found {} error(s)", file.display(), errors);
+    }
+
+    println!("{} is valid", file.display());
+    Ok(())
+}
+
+/// Resolves the features of a devcontainer.json in `dir`, reporting the ones
+/// that cannot be found at their key.
+fn resolve_features(content: &str, dir: &Path) -> Result<Vec<Diagnostic>> {
+    let mut stripped = content.to_string();
+    json_strip_comments::strip(&mut stripped)?;
+    let value: serde_json::Value = serde_json::from_str(&stripped)?;
+    let Some(features) = value.get("features").and_then(|f| f.as_object()) else {
+        return Ok(vec![]);
     };
 
-    Ok(runtime)
+    let config = Config::load()?;
+    let policy = DownloadPolicy::from_config(&config)?;
+    let client = HttpOciClient::new(&policy)?;
+    let mut diagnostics = vec![];
+    for (url, options) in features {
+        let result = parse_feature::<serde::de::value::Error>(url, options.clone())
+            .map_err(anyhow::Error::from)
+            .and_then(|feature| {
+                feature_process::process_feature(&feature.relative_to(dir), &client, &policy)
+            });
+        if let Err(e) = result {
+            let (line, column) =
+                validate::property_position(content, &["features", url]).unwrap_or((1, 1));
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                line,
+                column,
+                message: format!("Feature '{}' cannot be resolved: {:#}", url, e),
+            });
+        }
+    }
+    Ok(diagnostics)
 }
 
 /// Handles the config show command to display current configuration.
@@ -94,14 +249,65 @@ pub fn handle_config_show() -> Result<()> {
 # General Settings:
 #   dotfilesRepository: URL to dotfiles repository
 #   dotfilesInstallCommand: Custom install command for dotfiles
+#   dotfilesBranch: Branch of the dotfiles repository (default branch if unset)
+#   dotfilesDepth: Shallow clone depth of the dotfiles (full history if unset)
+#   dotfilesTargetDir: Directory the dotfiles are cloned into - default: ~/.dotfiles
+#   dotfilesRerun: Install dotfiles once or always (once, always) - default: once
+#   dotfiles: Further dotfiles repositories, a list of {{repo, installCommand, targetDir, branch}}
+#             managed with 'devcon config dotfiles add|list|remove'
 #   defaultShell: Default shell for shell command (e.g., /bin/zsh)
+#   fallbackShells: Shells to try if the default shell is missing (default: zsh, bash, sh)
 #   buildPath: Default build path for container builds
-#   runtime: Container runtime (auto, docker, apple) - default: auto
+#   dockerfileTemplate: Path of a custom Dockerfile template (see `devcon config dockerfile-template`)
+#   buildProgress: How build output is shown (auto, plain, tty, quiet) - default: auto
+#   maxBandwidth: Bandwidth limit per forwarded tunnel (e.g., 512K, 10M bytes/s)
+#   tunnelCompression: Compress multiplexed tunnel traffic (true/false) - default: true
+#   controlScheme: Agent control connection scheme (tcp, grpc) - default: tcp
+#   downloadBandwidth: Bandwidth limit for feature downloads (e.g., 1M bytes/s)
+#   downloadWindows: Local time windows for large downloads (e.g., 22:00-06:00)
+#   downloadRetries: Retries of a failing feature download - default: 5
+#   downloadSizeLimit: Largest accepted download, e.g. a feature layer (e.g., 512M)
+#   openEditor: Editor command for files opened from containers (e.g., code)
+#   openAllowlist: Host directories besides the workspace where files may be opened
+#   projectRoots: Directories scanned for projects by devcon projects (e.g., ~/src)
+#   recentLimit: Number of unpinned recent projects kept - default: 10
+#   containerCpus: Maximum CPUs of a running container (e.g., 2, 0.5)
+#   containerMemory: Maximum memory of a running container (e.g., 8g, 512m)
+#   forwardDockerSocket: Share the host's Docker socket with containers (true/false) - default: false
+#   propagateProxy: Pass the host's HTTP proxy settings to builds and containers (true/false) - default: true
+#   offline: Use only cached features and local base images (true/false) - default: false
+#   selinuxRelabel: Relabel bind mounts for SELinux with :Z (true/false) - default: if SELinux is enforcing
+#   featureCache: Share feature layers across projects (true/false) - default: true
+#   buildkit: Use BuildKit cache mounts and inline build cache (true/false) - default: true
+#   bakeVscodeCustomizations: Bake VS Code extensions and settings into images (true/false) - default: true
+#   buildCacheFrom: Images to import the build cache from, comma-separated
+#   buildSecretRegistries: Registries whose credentials are passed to feature installs (e.g., ghcr.io)
+#   caCertificates: PEM files with additional CA certificates, comma-separated
+#   verifySignatures: Require cosign signatures of features and base images (true/false) - default: false
+#   trustedKeys: PEM files with trusted cosign public keys, comma-separated
+#   registryMirrors: Registry mirrors for features and base images (e.g., ghcr.io=mirror.corp/ghcr)
+#   forwardAllowPorts: Ports agents may forward (e.g., 3000-3999,8080) - default: all
+#   forwardDenyPorts: Ports agents may never forward (e.g., 22,5432)
+#   forwardHostPorts: Host ports containers may reach with forward-host-port (e.g., 5432,11434) - default: none
+#   registryCache: Proxy cargo, npm and pip registries through a host cache (true/false) - default: false
+#   registryCachePort: Port of the registry caching proxy - default: 15080
+#   hostAliases: Additional names of the host in containers (e.g., db.internal,llm.internal)
+#   autoForward: Which detected ports are forwarded automatically (all/none/allowlist) - default: all
+#   autoForwardPorts: Ports forwarded automatically in allowlist mode (e.g., 3000-3999,8080)
+#   notifications: Show desktop notifications requested by agents (true/false) - default: true
+#   propagateTimezone: Set the host's timezone as TZ in containers (true/false) - default: false
+#   propagateLocale: Set the host's LANG and LC_* in containers (true/false) - default: false
+#   propagateLocaltime: Mount the host's /etc/localtime into containers (true/false) - default: false
+#   gitConfigAllowlist: Host git config keys copied into containers (e.g., user.name,user.email)
+#   backgroundLifecycle: Run postCreateCommand and postStartCommand in the background (true/false) - default: false
+#   runtime: Container runtime (auto, docker, podman, apple) - default: auto
 #
 # Agent Settings (under 'agents'):
 #   binaryUrl: URL to precompiled agent binary
 #   gitRepository: Git repository URL for building agent from source
 #   gitBranch: Git branch for agent source (default: main)
+#   releaseUrl: Base URL of prebuilt agent binaries (default: GitHub release)
+#   prebuilt: Use prebuilt agent binaries (true/false) - default: true
 #   disable: Disable agent installation (true/false)
 #
 # Runtime Settings (under 'runtimeConfig'):
@@ -110,6 +316,12 @@ pub fn handle_config_show() -> Result<()> {
 #   apple.buildMemory: Memory limit for Apple builds (default: 4g)
 #   apple.buildCpu: CPU limit for Apple builds (e.g., 2, 0.5)
 #
+# Host Hooks (under 'hooks', shell commands run on the host):
+#   preUp: Run before devcon up, a failure aborts it
+#   postUp: Run after devcon up started the container
+#   preShell: Run before devcon shell, a failure aborts it
+#   portForwarded: Run by devcon serve when a port is forwarded
+#
 # Current Configuration:
 
 {}
@@ -143,14 +355,45 @@ pub fn handle_config_get(property: &str) -> Result<()> {
 
 /// Handles the config set command to set a property value.
 ///
+/// The change is shown as a diff of the config file and the resulting config
+/// is validated before it is saved.
+///
+/// # Arguments
+///
+/// * `property` - Property path in camelCase dot-notation
+/// * `value` - Value to set
+/// * `dry_run` - Only show the change without saving it
+///
 /// # Errors
 ///
 /// Returns an error if the config cannot be loaded, the property is invalid,
-/// or the value fails validation.
-pub fn handle_config_set(property: &str, value: &str) -> Result<()> {
+/// or the value or resulting config fails validation.
+pub fn handle_config_set(property: &str, value: &str, dry_run: bool) -> Result<()> {
+    let config_path = Config::get_config_path()?;
+    let before = std::fs::read_to_string(&config_path).unwrap_or_default();
     let mut config = Config::load()?;
 
     config.set_value(property, value.to_string())?;
+    config
+        .validate()
+        .context("The resulting configuration is invalid")?;
+
+    let after = yaml_serde::to_string(&config)?;
+    let file_name = config_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let diff = config_diff::unified_diff(&before, &after, &file_name, 3);
+    config_diff::print_diff(&diff);
+
+    if dry_run {
+        println!("Dry run, {} was not changed", config_path.display());
+        return Ok(());
+    }
+    if diff.is_empty() {
+        println!("{} is already {}", property, value);
+        return Ok(());
+    }
     config.save()?;
 
     println!("Set {} = {}", property, value);
@@ -172,6 +415,133 @@ pub fn handle_config_unset(property: &str) -> Result<()> {
     Ok(())
 }
 
+/// Handles the config migrate command, which migrates the config file to the
+/// current version and prints the transformations.
+///
+/// Loading the config migrates it as well, this command shows what changes
+/// with `dry_run` before the file is touched.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, parsed or written.
+pub fn handle_config_migrate(dry_run: bool) -> Result<()> {
+    let config_path = Config::get_config_path()?;
+    if !config_path.exists() {
+        println!("No config file at {}", config_path.display());
+        return Ok(());
+    }
+
+    let outcome = Config::migrate_file(&config_path, dry_run)?;
+    if outcome.changes.is_empty() {
+        println!(
+            "Config file is up to date (version {})",
+            config_migration::CONFIG_VERSION
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} from version {} to {}:",
+        if dry_run { "Would migrate" } else { "Migrated" },
+        config_path.display(),
+        outcome.from,
+        config_migration::CONFIG_VERSION
+    );
+    for change in &outcome.changes {
+        println!("  {}", change);
+    }
+    if let Some(backup) = outcome.backup {
+        println!("The original is saved as {}", backup.display());
+    }
+    Ok(())
+}
+
+/// Handles the config edit command, which opens the config file in the
+/// user's editor and only accepts the result if it parses and validates.
+///
+/// The file is edited as a copy. On errors the diagnostics are printed and
+/// the editor is opened again on request, otherwise the config stays as it
+/// was. The editor is taken from `$VISUAL` or `$EDITOR` and may contain
+/// arguments, e.g. `code --wait`.
+///
+/// # Errors
+///
+/// Returns an error if the editor cannot be started or the edited config is
+/// invalid and not fixed.
+pub fn handle_config_edit() -> Result<()> {
+    let config_path = Config::get_config_path()?;
+    let original = if config_path.exists() {
+        std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read {}", config_path.display()))?
+    } else {
+        yaml_serde::to_string(&Config::default())?
+    };
+
+    let edit_path = config_path.with_extension("edit.yaml");
+    if let Some(parent) = edit_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&edit_path, &original)
+        .with_context(|| format!("Failed to write {}", edit_path.display()))?;
+
+    let term = console::Term::stderr();
+    let result = loop {
+        run_editor(&edit_path)?;
+        let edited = std::fs::read_to_string(&edit_path)
+            .with_context(|| format!("Failed to read {}", edit_path.display()))?;
+        let error = match Config::from_yaml(&edited) {
+            Ok(config) => match config.validate() {
+                Ok(()) => break Ok(edited),
+                Err(e) => e,
+            },
+            Err(e) => e,
+        };
+
+        eprintln!("✗ Configuration is invalid:");
+        eprintln!("  {:#}", error);
+        if !term.is_term() {
+            break Err(error);
+        }
+        term.write_str("Edit again? [Y/n] ")?;
+        let answer = term.read_line()?;
+        if answer.trim().eq_ignore_ascii_case("n") {
+            break Err(error);
+        }
+    };
+    let _ = std::fs::remove_file(&edit_path);
+
+    let edited = result.context("Configuration not changed")?;
+    if edited == original {
+        println!("Configuration not changed");
+        return Ok(());
+    }
+    std::fs::write(&config_path, edited)
+        .with_context(|| format!("Failed to write {}", config_path.display()))?;
+    println!("✓ Configuration saved to {}", config_path.display());
+    Ok(())
+}
+
+/// Opens `path` in the editor of `$VISUAL` or `$EDITOR` and waits for it.
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor '{}' exited with {}", editor, status);
+    }
+    Ok(())
+}
+
 /// Handles the config validate command to check all configuration values.
 ///
 /// # Errors
@@ -193,6 +563,93 @@ pub fn handle_config_validate() -> Result<()> {
     }
 }
 
+/// Handles the config effective command, which shows the merged
+/// configuration of a workspace with the source of each value.
+///
+/// # Errors
+///
+/// Returns an error if the workspace or a configuration cannot be read.
+pub fn handle_config_effective(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    offline: bool,
+    format: &str,
+) -> Result<()> {
+    let config = load_config(offline)?;
+    let devcontainer_workspace = Workspace::open(path, devcontainer_config)?;
+    // The runtime only adds the metadata of local images, so it may be unreachable
+    let runtime_name = config
+        .resolve_runtime()
+        .unwrap_or_else(|_| config.planned_runtime());
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let effective =
+        ContainerDriver::new(config, runtime).effective_config(&devcontainer_workspace)?;
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&effective)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Setting").fg(Color::Green),
+            Cell::new("Value").fg(Color::Green),
+            Cell::new("Source").fg(Color::Green),
+        ]);
+    let mut rows: Vec<(String, &Sourced)> = Vec::new();
+    for (name, value) in &effective.container_env {
+        rows.push((format!("containerEnv.{}", name), value));
+    }
+    for (name, value) in &effective.remote_env {
+        rows.push((format!("remoteEnv.{}", name), value));
+    }
+    rows.extend(effective.mounts.iter().map(|m| ("mounts".to_string(), m)));
+    rows.extend(
+        effective
+            .forward_ports
+            .iter()
+            .map(|p| ("forwardPorts".to_string(), p)),
+    );
+    rows.extend(
+        effective
+            .container_user
+            .iter()
+            .map(|u| ("containerUser".to_string(), u)),
+    );
+    rows.extend(
+        effective
+            .remote_user
+            .iter()
+            .map(|u| ("remoteUser".to_string(), u)),
+    );
+    for (setting, value) in rows {
+        table.add_row(vec![setting, value.value.clone(), value.source.clone()]);
+    }
+    for step in &effective.lifecycle {
+        table.add_row(vec![&step.stage, &step.command, &step.source]);
+    }
+    for (name, command) in &effective.aliases {
+        table.add_row(vec![
+            format!("aliases.{}", name),
+            command.value.clone(),
+            command.source.clone(),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Handles the config dockerfile-template command, which prints the built-in
+/// template to start a custom `dockerfileTemplate` from.
+pub fn handle_config_dockerfile_template() {
+    print!("{}", container::DOCKERFILE_TEMPLATE);
+}
+
 /// Handles the config path command to show the configuration file location.
 ///
 /// # Errors
@@ -251,260 +708,2663 @@ pub fn handle_config_list(filter: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-/// Handles the build command for creating a development container.
-///
-/// This function:
-/// 1. Loads the user configuration from XDG directories
-/// 2. Loads the devcontainer configuration from the specified path
-/// 3. Merges additional features from user config
-/// 4. Creates a `ContainerDriver` instance
-/// 5. Builds the container image with all configured features
-///
-/// # Arguments
+/// Handles the config features add command.
 ///
-/// * `path` - The path to the project directory containing `.devcontainer/devcontainer.json`
-/// * `build_path` - Optional path to the build directory
+/// Options are given as `KEY=VALUE`; the values `true` and `false` are stored as booleans.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The devcontainer configuration cannot be found or parsed
-/// - Additional features cannot be merged
-/// - The container build process fails
-/// - Required dependencies are missing
-///
-/// # Examples
+/// Returns an error if an option is malformed, the feature reference is invalid,
+/// or the config cannot be saved.
+pub fn handle_config_features_add(feature: &str, options: &[String]) -> Result<()> {
+    let mut option_map = serde_json::Map::new();
+    for option in options {
+        let (key, value) = option
+            .split_once('=')
+            .with_context(|| format!("Invalid option '{}', expected KEY=VALUE", option))?;
+        let value = match value {
+            "true" => serde_json::Value::Bool(true),
+            "false" => serde_json::Value::Bool(false),
+            other => serde_json::Value::String(other.to_string()),
+        };
+        option_map.insert(key.to_string(), value);
+    }
+
+    let mut config = Config::load()?;
+    config.add_feature(feature, serde_json::Value::Object(option_map))?;
+    config.save()?;
+
+    println!("Added feature {}", feature);
+    Ok(())
+}
+
+/// Handles the config features list command.
 ///
-/// ```no_run
-/// use std::path::PathBuf;
-/// # use devcon::command::handle_build_command;
+/// # Errors
 ///
-/// let project_path = PathBuf::from("/path/to/project");
-/// handle_build_command(project_path)?;
-/// # Ok::<(), anyhow::Error>(())
-/// ```
-pub fn handle_build_command(path: PathBuf, build_path: Option<PathBuf>) -> anyhow::Result<()> {
+/// Returns an error if the config cannot be loaded.
+pub fn handle_config_features_list() -> Result<()> {
     let config = Config::load()?;
 
-    trace!("Config loaded {:?}", config);
-    let devcontainer_workspace = Workspace::try_from(path)?;
-
-    // Resolve build_path: CLI argument takes precedence over config
-    let effective_build_path = build_path.or_else(|| config.build_path.as_ref().map(PathBuf::from));
-
-    // Create runtime based on config
-    let runtime_name = config.resolve_runtime()?;
-    debug!("Using runtime {:?}", runtime_name);
-    let runtime = get_runtime_specific_config(&config, &runtime_name)?;
+    if config.additional_features.is_empty() {
+        println!("No features configured");
+        return Ok(());
+    }
 
-    let driver = ContainerDriver::new(config, runtime);
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
 
-    let result = driver.build(devcontainer_workspace, &[], effective_build_path);
+    table.set_header(vec![
+        Cell::new("Feature").fg(Color::Green),
+        Cell::new("Options").fg(Color::Green),
+    ]);
 
-    if result.is_err() {
-        anyhow::bail!(
-            "Failed to build the development container. Error: {:?}",
-            result.err()
-        );
+    let mut features: Vec<_> = config.additional_features.iter().collect();
+    features.sort_by_key(|(reference, _)| reference.as_str());
+    for (reference, options) in features {
+        table.add_row(vec![Cell::new(reference), Cell::new(options.to_string())]);
     }
 
+    println!("{}", table);
     Ok(())
 }
 
-/// Handles the start command for launching a development container.
-///
-/// This function:
-/// 1. Loads the user configuration from XDG directories
-/// 2. Loads the devcontainer configuration from the specified path
-/// 3. Resolves the canonical path to the project directory
-/// 4. Creates a `ContainerDriver` instance
-/// 5. Starts the container with the project mounted as a volume and env variables
+/// Handles the config features remove command.
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `path` - The path to the project directory containing `.devcontainer/devcontainer.json`
+/// Returns an error if the feature is not configured or the config cannot be saved.
+pub fn handle_config_features_remove(feature: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    config.remove_feature(feature)?;
+    config.save()?;
+
+    println!("Removed feature {}", feature);
+    Ok(())
+}
+
+/// Handles the config dotfiles add command.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The devcontainer configuration cannot be found or parsed
-/// - The path cannot be canonicalized
-/// - The container image doesn't exist (must be built first)
-/// - The container fails to start
+/// Returns an error if the URL is invalid, the repository is already
+/// configured or the config cannot be saved.
+pub fn handle_config_dotfiles_add(dotfiles: DotfilesRepository) -> Result<()> {
+    let repo = dotfiles.repo.clone();
+    let mut config = Config::load()?;
+    config.add_dotfiles(dotfiles)?;
+    config.save()?;
+
+    println!("Added dotfiles repository {}", repo);
+    Ok(())
+}
+
+/// Handles the config dotfiles list command.
 ///
-/// # Examples
+/// `dotfilesRepository` is listed first, with the defaults of unset values.
 ///
-/// ```no_run
-/// use std::path::PathBuf;
-/// # use devcon::command::handle_start_command;
+/// # Errors
 ///
-/// let project_path = PathBuf::from("/path/to/project");
-/// handle_start_command(project_path)?;
-/// # Ok::<(), anyhow::Error>(())
-/// ```
-pub fn handle_start_command(path: PathBuf) -> anyhow::Result<()> {
+/// Returns an error if the config cannot be loaded.
+pub fn handle_config_dotfiles_list() -> Result<()> {
     let config = Config::load()?;
-    trace!("Config loaded {:?}", config);
-    let devcontainer_workspace = Workspace::try_from(path.clone())?;
+    let repositories = config.dotfiles_repositories();
 
-    // Create runtime based on config
-    let runtime_name = config.resolve_runtime()?;
-    debug!("Using runtime {:?}", runtime_name);
-    let runtime = get_runtime_specific_config(&config, &runtime_name)?;
+    if repositories.is_empty() {
+        println!("No dotfiles repositories configured");
+        return Ok(());
+    }
 
-    let driver = ContainerDriver::new(config, runtime);
-    driver.start(devcontainer_workspace, &[])?;
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
 
-    println!("Container started. Agent listener running. Press Ctrl+C to stop.");
+    table.set_header(vec![
+        Cell::new("Repository").fg(Color::Green),
+        Cell::new("Branch").fg(Color::Green),
+        Cell::new("Target directory").fg(Color::Green),
+        Cell::new("Install command").fg(Color::Green),
+    ]);
+    for dotfiles in repositories {
+        table.add_row(vec![
+            Cell::new(&dotfiles.repo),
+            Cell::new(dotfiles.branch.as_deref().unwrap_or("default")),
+            Cell::new(dotfiles.target_dir.as_deref().unwrap_or_default()),
+            Cell::new(
+                dotfiles
+                    .install_command
+                    .as_deref()
+                    .unwrap_or("auto-detected"),
+            ),
+        ]);
+    }
 
+    println!("{}", table);
     Ok(())
 }
 
-/// Handles the shell command for opening a shell in a running container.
+/// Handles the config dotfiles remove command.
 ///
-/// # Arguments
+/// # Errors
 ///
-/// * `path` - Path to the project directory
-/// * `_env` - Environment variables to pass to the shell (currently unused)
+/// Returns an error if the repository is not configured or the config cannot be saved.
+pub fn handle_config_dotfiles_remove(repo: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    config.remove_dotfiles(repo)?;
+    config.save()?;
+
+    println!("Removed dotfiles repository {}", repo);
+    Ok(())
+}
+
+/// Handles the list command to display running devcon containers.
+///
+/// By default only containers started by the current host user (and legacy
+/// containers without an owner label) are shown. With `all_users`, containers
+/// of other users are listed as well but marked as view-only. With `stats`,
+/// the CPU, memory and network usage of every container is sampled.
 ///
 /// # Errors
 ///
-/// Currently always returns `Ok(())` as it's not implemented.
-pub fn handle_shell_command(path: PathBuf, _env: &[String]) -> anyhow::Result<()> {
+/// Returns an error if the config cannot be loaded or the runtime fails to list containers.
+pub fn handle_list_command(all_users: bool, stats: bool) -> Result<()> {
     let config = Config::load()?;
-    trace!("Config loaded {:?}", config);
-    let devcontainer_workspace = Workspace::try_from(path.clone())?;
-
-    // Create runtime based on config
     let runtime_name = config.resolve_runtime()?;
     debug!("Using runtime {:?}", runtime_name);
-    let runtime = get_runtime_specific_config(&config, &runtime_name)?;
+    let runtime = create_runtime(&config, &runtime_name)?;
 
-    let driver = ContainerDriver::new(config, runtime);
-    driver.shell(devcontainer_workspace)?;
-    Ok(())
-}
+    let containers: Vec<_> = runtime
+        .list()?
+        .into_iter()
+        .filter(|(_, handle)| all_users || audit::is_owned_by_current_user(handle.as_ref()))
+        .collect();
 
-/// Handles the up command for building and starting a development container.
+    if containers.is_empty() {
+        println!("No running containers");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let mut header = vec![
+        Cell::new("Name").fg(Color::Green),
+        Cell::new("Instance").fg(Color::Green),
+        Cell::new("Container ID").fg(Color::Green),
+        Cell::new("Owner").fg(Color::Green),
+        Cell::new("Access").fg(Color::Green),
+    ];
+    if stats {
+        header.extend([
+            Cell::new("CPU").fg(Color::Green),
+            Cell::new("Memory").fg(Color::Green),
+            Cell::new("Net I/O (in / out)").fg(Color::Green),
+        ]);
+    }
+    table.set_header(header);
+
+    for (name, handle) in containers {
+        let access = if audit::is_owned_by_current_user(handle.as_ref()) {
+            "manage"
+        } else {
+            "view"
+        };
+        let mut row = vec![
+            Cell::new(&name),
+            Cell::new(handle.label(INSTANCE_LABEL).unwrap_or("-")),
+            Cell::new(handle.id()),
+            Cell::new(audit::owner_of(handle.as_ref()).unwrap_or("-")),
+            Cell::new(access),
+        ];
+        if stats {
+            match runtime.stats(handle.as_ref()) {
+                Ok(usage) => row.extend([
+                    Cell::new(usage.cpu_label()),
+                    Cell::new(usage.memory_label()),
+                    Cell::new(usage.network_label()),
+                ]),
+                Err(e) => {
+                    warn!("Failed to sample usage of {}: {:#}", name, e);
+                    row.extend([Cell::new("-"), Cell::new("-"), Cell::new("-")]);
+                }
+            }
+        }
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// Handles the status command to inspect a workspace.
+///
+/// Shows whether the image is built and up to date with the configuration,
+/// whether the container is running with its CPU and memory limits, the
+/// lifecycle hooks which ran, its mounts, and the agent connection and port
+/// forwards as seen by the control server. For a stopped workspace the limits
+/// of the next start are shown.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `format` - Output format, `table` or `json`
+/// * `metrics_port` - Metrics port of the control server to query
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the config or workspace cannot be loaded or the runtime
+/// fails to list containers. An unreachable control server is reported as
+/// unknown agent status.
+pub fn handle_status_command(
+    path: PathBuf,
+    format: &str,
+    metrics_port: u16,
+    instance: Option<&str>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace = Workspace::try_from(path)?.with_instance(instance)?;
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let name = devcontainer_workspace.get_sanitized_name();
+    let workspace_name = devcontainer_workspace.agent_name();
+    let status = ContainerDriver::new(config, runtime).status(&devcontainer_workspace)?;
+    let agent = match query_control_server::<Vec<AgentInfo>>(metrics_port, "/api/agents") {
+        Ok(agents) => Some(WorkspaceStatus::of(&agents, &workspace_name)),
+        Err(e) => {
+            debug!("Agent status unknown: {:#}", e);
+            None
+        }
+    };
+
+    if format == "json" {
+        let output = serde_json::json!({
+            "workspace": name,
+            "image": {
+                "built": status.image_built,
+                "fresh": status.image_fresh,
+            },
+            "container": status.container_id,
+            "limits": {
+                "cpus": status.limits.cpus,
+                "memory": status.limits.memory,
+            },
+            "lifecycleStage": status.lifecycle_stage,
+            "lifecycleHooks": status.lifecycle_hooks,
+            "mounts": status.mounts,
+            "agent": agent,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let running = status.container_id.is_some();
+    let limit_suffix = if running { "" } else { " (on start)" };
+    let limit = |value: Option<String>| {
+        format!(
+            "{}{}",
+            value.as_deref().unwrap_or("unlimited"),
+            limit_suffix
+        )
+    };
+    let list = |values: &[String]| {
+        if values.is_empty() {
+            "-".to_string()
+        } else {
+            values.join("\n")
+        }
+    };
+
+    let image = match (status.image_built, status.image_fresh) {
+        (false, _) => "not built",
+        (true, Some(true)) => "built, up to date",
+        (true, Some(false)) => "built, configuration changed since (run 'devcon build')",
+        (true, None) => "built",
+    };
+    let ports = agent.as_ref().map(|agent| {
+        let ports: Vec<String> = agent.ports.iter().map(|p| p.to_string()).collect();
+        list(&ports)
+    });
+    let agent = match &agent {
+        Some(agent) if agent.running => "connected".to_string(),
+        Some(_) => "not connected".to_string(),
+        None => format!(
+            "unknown (no control server on metrics port {})",
+            metrics_port
+        ),
+    };
+
+    table.add_row(vec![
+        Cell::new("Workspace").fg(Color::Green),
+        Cell::new(name),
+    ]);
+    table.add_row(vec![Cell::new("Image").fg(Color::Green), Cell::new(image)]);
+    table.add_row(vec![
+        Cell::new("Container").fg(Color::Green),
+        Cell::new(status.container_id.as_deref().unwrap_or("not running")),
+    ]);
+    table.add_row(vec![
+        Cell::new("CPU limit").fg(Color::Green),
+        Cell::new(limit(status.limits.cpus)),
+    ]);
+    table.add_row(vec![
+        Cell::new("Memory limit").fg(Color::Green),
+        Cell::new(limit(status.limits.memory)),
+    ]);
+    if running {
+        table.add_row(vec![
+            Cell::new("Lifecycle stage").fg(Color::Green),
+            Cell::new(status.lifecycle_stage.as_deref().unwrap_or("unknown")),
+        ]);
+        table.add_row(vec![
+            Cell::new("Lifecycle hooks").fg(Color::Green),
+            Cell::new(list(&status.lifecycle_hooks)),
+        ]);
+        table.add_row(vec![
+            Cell::new("Mounts").fg(Color::Green),
+            Cell::new(list(&status.mounts)),
+        ]);
+    }
+    table.add_row(vec![Cell::new("Agent").fg(Color::Green), Cell::new(agent)]);
+    if let Some(ports) = ports {
+        table.add_row(vec![
+            Cell::new("Forwarded ports").fg(Color::Green),
+            Cell::new(ports),
+        ]);
+    }
+
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// Handles the build command for creating a development container.
 ///
 /// This function:
 /// 1. Loads the user configuration from XDG directories
 /// 2. Loads the devcontainer configuration from the specified path
-/// 3. Processes features once (avoiding redundant processing)
-/// 4. Builds the container image with all configured features
-/// 5. Starts the container with the project mounted as a volume
-///
-/// This is more efficient than running build then start separately, as it
-/// processes features only once.
+/// 3. Merges additional features from user config
+/// 4. Creates a `ContainerDriver` instance
+/// 5. Builds the container image with all configured features
 ///
 /// # Arguments
 ///
 /// * `path` - The path to the project directory containing `.devcontainer/devcontainer.json`
 /// * `build_path` - Optional path to the build directory
+/// * `offline` - Use only cached features and local base images
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+/// * `progress` - Build output mode overriding `buildProgress`
+/// * `dry_run` - Only print the generated Dockerfiles
+/// * `wait` - Wait for a concurrent operation on the workspace instead of failing
+/// * `stages` - The build stages to run, a partial image is tagged `:partial`
+/// * `no_interaction` - Fail on missing required feature options instead of asking
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The devcontainer configuration cannot be found or parsed
-/// - Feature processing fails
+/// - Another operation on the workspace is in progress and `wait` is not set
+/// - Additional features cannot be merged
 /// - The container build process fails
-/// - The container fails to start
+/// - Required dependencies are missing
 ///
 /// # Examples
 ///
 /// ```no_run
 /// use std::path::PathBuf;
-/// # use devcon::command::handle_up_command;
+/// # use devcon::command::handle_build_command;
+/// # use devcon::driver::build_stage::BuildStages;
 ///
 /// let project_path = PathBuf::from("/path/to/project");
-/// handle_up_command(project_path)?;
+/// handle_build_command(
+///     project_path,
+///     None,
+///     false,
+///     None,
+///     None,
+///     None,
+///     false,
+///     false,
+///     BuildStages::default(),
+///     false,
+/// )?;
 /// # Ok::<(), anyhow::Error>(())
 /// ```
-pub fn handle_up_command(path: PathBuf, build_path: Option<PathBuf>) -> anyhow::Result<()> {
-    let config = Config::load()?;
+#[allow(clippy::too_many_arguments)]
+pub fn handle_build_command(
+    path: PathBuf,
+    build_path: Option<PathBuf>,
+    offline: bool,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+    progress: Option<&str>,
+    dry_run: bool,
+    wait: bool,
+    stages: BuildStages,
+    no_interaction: bool,
+) -> anyhow::Result<()> {
+    let mut config = load_config(offline)?;
+    if let Some(progress) = progress {
+        config.build_progress = Some(progress.to_string());
+    }
+
     trace!("Config loaded {:?}", config);
-    let devcontainer_workspace = Workspace::try_from(path)?;
+    let devcontainer_workspace = open_workspace(path, devcontainer_config, instance)?;
+    let _lock = if dry_run {
+        None
+    } else {
+        Some(workspace_lock::lock(&devcontainer_workspace, wait)?)
+    };
 
     // Resolve build_path: CLI argument takes precedence over config
     let effective_build_path = build_path.or_else(|| config.build_path.as_ref().map(PathBuf::from));
 
-    // Create runtime based on config
-    let runtime_name = config.resolve_runtime()?;
+    // Create runtime based on config, a dry run doesn't contact it
+    let runtime_name = if dry_run {
+        config.planned_runtime()
+    } else {
+        config.resolve_runtime()?
+    };
     debug!("Using runtime {:?}", runtime_name);
-    let runtime = get_runtime_specific_config(&config, &runtime_name)?;
-
-    let driver = ContainerDriver::new(config, runtime);
-
-    // Process features once
-    let (processed_features, _) = driver.prepare_features(&devcontainer_workspace)?;
-
-    // Build with pre-processed features
-    driver.build_with_features(
-        devcontainer_workspace.clone(),
-        &[],
-        Some(processed_features.clone()),
-        effective_build_path,
-    )?;
+    let runtime = create_runtime(&config, &runtime_name)?;
 
-    // Start the container with pre-processed features
-    driver.start_with_features(devcontainer_workspace, &[], Some(processed_features))?;
+    let driver = ContainerDriver::new(config, runtime)
+        .with_dry_run(dry_run)
+        .with_stages(stages)
+        .with_interaction(!no_interaction);
 
-    println!("Container built and started. Agent listener running. Press Ctrl+C to stop.");
+    driver
+        .build(devcontainer_workspace, &[], effective_build_path)
+        .context("Failed to build the development container")?;
 
     Ok(())
 }
 
-/// Handles the serve command to start the control server.
+/// Handles the start command for launching a development container.
 ///
-/// This function starts a TCP server that listens for connections from
-/// container agents and manages port forwarding requests.
+/// This function:
+/// 1. Loads the user configuration from XDG directories
+/// 2. Loads the devcontainer configuration from the specified path
+/// 3. Resolves the canonical path to the project directory
+/// 4. Creates a `ContainerDriver` instance
+/// 5. Starts the container with the project mounted as a volume and env variables
 ///
 /// # Arguments
 ///
-/// * `port` - The port number to listen on for agent connections
+/// * `path` - The path to the project directory containing `.devcontainer/devcontainer.json`
+/// * `offline` - Use only cached features
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+/// * `wait` - Wait for a concurrent operation on the workspace instead of failing
 ///
 /// # Errors
 ///
-/// Returns an error if the server fails to start or bind to the port.
+/// Returns an error if:
+/// - The devcontainer configuration cannot be found or parsed
+/// - Another operation on the workspace is in progress and `wait` is not set
+/// - The path cannot be canonicalized
+/// - The container image doesn't exist (must be built first)
+/// - The container fails to start
 ///
 /// # Examples
 ///
 /// ```no_run
-/// # use devcon::command::handle_serve_command;
-/// handle_serve_command(15000)?;
+/// use std::path::PathBuf;
+/// # use devcon::command::handle_start_command;
+///
+/// let project_path = PathBuf::from("/path/to/project");
+/// handle_start_command(project_path, false, None, None, false)?;
 /// # Ok::<(), anyhow::Error>(())
 /// ```
-pub fn handle_serve_command(port: u16) -> Result<()> {
+pub fn handle_start_command(
+    path: PathBuf,
+    offline: bool,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+    wait: bool,
+) -> anyhow::Result<()> {
+    let config = load_config(offline)?;
+    trace!("Config loaded {:?}", config);
+    let devcontainer_workspace = open_workspace(path, devcontainer_config, instance)?;
+    let _lock = workspace_lock::lock(&devcontainer_workspace, wait)?;
+
+    // Create runtime based on config
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let driver = ContainerDriver::new(config, runtime);
+    driver.start(devcontainer_workspace, &[])?;
+
+    println!("Container started. Agent listener running. Press Ctrl+C to stop.");
+
+    Ok(())
+}
+
+/// Handles the shell command for opening a shell in a running container.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `_env` - Environment variables to pass to the shell (currently unused)
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Currently always returns `Ok(())` as it's not implemented.
+pub fn handle_shell_command(
+    path: PathBuf,
+    _env: &[String],
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
     let config = Config::load()?;
     trace!("Config loaded {:?}", config);
+    let devcontainer_workspace = open_workspace(path, devcontainer_config, instance)?;
 
     // Create runtime based on config
     let runtime_name = config.resolve_runtime()?;
     debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
 
-    if runtime_name == "apple" {
-        println!(
-            "⚠️  Warning: For the connection to work, you have to register the dns entry 'host.container.internal' for localhost."
-        );
-        println!("   You can do this by invoking following command with sudo: ");
-        println!(
-            "     sudo container system dns create --localhost 192.168.2.10 host.container.internal"
-        );
-        println!(
-            "   More info: https://github.com/apple/container/blob/main/docs/how-to.md#access-a-host-service-from-a-container"
-        );
+    hooks::run(
+        &config,
+        Hook::PreShell,
+        &hook_context(&devcontainer_workspace),
+    )?;
+
+    let driver = ContainerDriver::new(config, runtime);
+    driver.shell(devcontainer_workspace)?;
+    Ok(())
+}
+
+/// Handles the inspect command, which prints the details of a workspace as
+/// JSON: the effective configuration, the resolved features, the labels of
+/// the image, the runtime's inspect data of the container and the agent
+/// status reported by the control server.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `metrics_port` - Metrics port of the running control server
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the workspace or configuration cannot be read or the
+/// runtime fails to inspect the workspace.
+pub fn handle_inspect_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    metrics_port: u16,
+    instance: Option<&str>,
+) -> Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let workspace_name = devcontainer_workspace.agent_name();
+    let inspection = ContainerDriver::new(config, runtime).inspect(&devcontainer_workspace)?;
+    let agent = match query_control_server::<Vec<AgentInfo>>(metrics_port, "/api/agents") {
+        Ok(agents) => Some(WorkspaceStatus::of(&agents, &workspace_name)),
+        Err(e) => {
+            debug!("Agent status unknown: {:#}", e);
+            None
+        }
+    };
+
+    let output = serde_json::json!({
+        "workspace": devcontainer_workspace.get_sanitized_name(),
+        "path": devcontainer_workspace.path,
+        "runtime": runtime_name,
+        "config": inspection.config,
+        "features": inspection.features,
+        "image": inspection.image,
+        "container": inspection.container,
+        "agent": agent,
+    });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+/// Handles the stop command for stopping the container of a workspace.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the container of the workspace isn't running or the
+/// runtime fails to stop it.
+pub fn handle_stop_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+    let name = devcontainer_workspace.agent_name();
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    ContainerDriver::new(config, runtime).stop(&devcontainer_workspace)?;
+    println!("Stopped container of {}", name);
+    Ok(())
+}
+
+/// Handles the pause command, which suspends the container of a workspace.
+///
+/// The control server is told first to keep the forwards of the workspace
+/// as suspended, so the silent agent isn't dropped for missing heartbeats.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `metrics_port` - Metrics port of the running control server
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the container of the workspace isn't running or the
+/// runtime fails to pause it. An unreachable control server only warns.
+pub fn handle_pause_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    metrics_port: u16,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+    let name = devcontainer_workspace.agent_name();
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    suspend_forwards(metrics_port, &name, true);
+    if let Err(e) = ContainerDriver::new(config, runtime).pause(&devcontainer_workspace) {
+        suspend_forwards(metrics_port, &name, false);
+        return Err(e);
     }
-    control_server::start_control_server(port)
+    println!("Paused container of {}", name);
+    Ok(())
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+/// Handles the resume command, which resumes the container of a workspace
+/// paused with `devcon pause`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `metrics_port` - Metrics port of the running control server
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the container of the workspace isn't running or the
+/// runtime fails to resume it. An unreachable control server only warns.
+pub fn handle_resume_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    metrics_port: u16,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+    let name = devcontainer_workspace.agent_name();
 
-    #[test]
-    fn test_handle_config_command() {
-        let result = handle_config_path();
-        assert!(result.is_ok());
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    ContainerDriver::new(config, runtime).resume(&devcontainer_workspace)?;
+    suspend_forwards(metrics_port, &name, false);
+    println!("Resumed container of {}", name);
+    Ok(())
+}
+
+/// Handles the snapshot create command, which commits the running container
+/// of a workspace and its volumes to a snapshot.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the container of the workspace isn't running or the
+/// runtime fails to take the snapshot.
+pub fn handle_snapshot_create_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let name = ContainerDriver::new(config, runtime).create_snapshot(&devcontainer_workspace)?;
+    println!(
+        "Created snapshot {} of {}",
+        name,
+        devcontainer_workspace.agent_name()
+    );
+    Ok(())
+}
+
+/// Handles the snapshot list command, which prints the snapshots of a
+/// workspace, newest first.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the runtime fails to list images.
+pub fn handle_snapshot_list_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let snapshots = ContainerDriver::new(config, runtime).snapshots(&devcontainer_workspace)?;
+    if snapshots.is_empty() {
+        println!("No snapshots of {}", devcontainer_workspace.agent_name());
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Snapshot").fg(Color::Green),
+            Cell::new("Taken").fg(Color::Green),
+        ]);
+    for name in &snapshots {
+        let taken = snapshot::taken_at(name)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        table.add_row(vec![name.clone(), taken]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Handles the snapshot restore command, which restarts the container of a
+/// workspace from a snapshot.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+/// * `name` - Snapshot to restore, the newest if `None`
+///
+/// # Errors
+///
+/// Returns an error if the workspace has no such snapshot or the runtime
+/// fails to restore it.
+pub fn handle_snapshot_restore_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+    name: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+    let driver = ContainerDriver::new(config, runtime);
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => driver
+            .snapshots(&devcontainer_workspace)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                anyhow::anyhow!("No snapshots of {}", devcontainer_workspace.agent_name())
+            })?,
+    };
+    let workspace_name = devcontainer_workspace.agent_name();
+    driver.restore_snapshot(devcontainer_workspace, &name)?;
+    println!("Restored snapshot {} of {}", name, workspace_name);
+    Ok(())
+}
+
+/// Handles the network link command, which connects the running containers
+/// of two workspaces to a shared network so they reach each other by name.
+///
+/// # Arguments
+///
+/// * `first` - Path to the project directory of the first workspace
+/// * `second` - Path to the project directory of the second workspace
+/// * `devcontainer_config` - Named configuration of multi-config projects
+///
+/// # Errors
+///
+/// Returns an error if a workspace cannot be read, a container isn't running
+/// or the runtime fails to connect them.
+pub fn handle_network_link_command(
+    first: PathBuf,
+    second: PathBuf,
+    devcontainer_config: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let first = Workspace::open(first, devcontainer_config)?;
+    let second = Workspace::open(second, devcontainer_config)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let network = ContainerDriver::new(config, runtime).link(&first, &second)?;
+    println!("Linked on network {}:", network);
+    for workspace in [&first, &second] {
+        println!(
+            "  {} is reachable as {}",
+            workspace.agent_name(),
+            container::network_aliases(workspace).join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Handles the gc command, which removes all but the newest snapshots of
+/// every workspace.
+///
+/// # Arguments
+///
+/// * `keep` - Number of snapshots kept per workspace
+///
+/// # Errors
+///
+/// Returns an error if the runtime fails to list or remove images.
+pub fn handle_gc_command(keep: usize) -> anyhow::Result<()> {
+    let config = Config::load()?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let pruned = ContainerDriver::new(config, runtime).prune_snapshots(keep)?;
+    for image in &pruned {
+        println!("Removed snapshot {}", image);
+    }
+    println!("Removed {} snapshot(s)", pruned.len());
+    Ok(())
+}
+
+/// Tells the control server to mark the forwards of a workspace as
+/// suspended, or as running again. Failures only warn, the container can be
+/// paused without a control server.
+fn suspend_forwards(metrics_port: u16, workspace: &str, suspended: bool) {
+    let action = if suspended { "suspend" } else { "resume" };
+    let url = format!(
+        "http://127.0.0.1:{}/api/workspaces/{}/{}",
+        metrics_port, workspace, action
+    );
+    debug!("Notifying control server at {}", url);
+
+    if let Err(e) = reqwest::blocking::Client::new()
+        .post(&url)
+        .send()
+        .and_then(|response| response.error_for_status())
+    {
+        debug!("Failed to notify control server: {:#}", e);
+        if suspended {
+            warn!(
+                "Control server not reachable on metrics port {}, forwards of {} may be dropped while paused",
+                metrics_port, workspace
+            );
+        }
+    }
+}
+
+/// Handles the hidden run-lifecycle command, which runs the lifecycle hooks
+/// `devcon start` left to the background with `backgroundLifecycle`.
+///
+/// # Errors
+///
+/// Returns an error if the container isn't running or a hook fails.
+pub fn handle_run_lifecycle_command(
+    path: PathBuf,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let driver = ContainerDriver::new(config, runtime);
+    driver.run_background_lifecycle(devcontainer_workspace)
+}
+
+/// Handles the up command for building and starting a development container.
+///
+/// This function:
+/// 1. Loads the user configuration from XDG directories
+/// 2. Loads the devcontainer configuration from the specified path
+/// 3. Processes features once (avoiding redundant processing)
+/// 4. Builds the container image with all configured features
+/// 5. Starts the container with the project mounted as a volume
+///
+/// This is more efficient than running build then start separately, as it
+/// processes features only once.
+///
+/// # Arguments
+///
+/// * `path` - The path to the project directory containing `.devcontainer/devcontainer.json`
+/// * `build_path` - Optional path to the build directory
+/// * `offline` - Use only cached features and local base images
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+/// * `progress` - Build output mode overriding `buildProgress`
+/// * `plan` - Only print the Dockerfiles and the planned container run
+/// * `wait` - Wait for a concurrent operation on the workspace instead of failing
+/// * `format` - Format of the timing report, `text` for a table or `json`
+/// * `no_interaction` - Fail on missing required feature options instead of asking
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The devcontainer configuration cannot be found or parsed
+/// - Another operation on the workspace is in progress and `wait` is not set
+/// - Feature processing fails
+/// - The container build process fails
+/// - The container fails to start
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::path::PathBuf;
+/// # use devcon::command::handle_up_command;
+///
+/// let project_path = PathBuf::from("/path/to/project");
+/// handle_up_command(
+///     project_path,
+///     None,
+///     false,
+///     None,
+///     None,
+///     None,
+///     false,
+///     false,
+///     "text",
+///     false,
+/// )?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[allow(clippy::too_many_arguments)]
+pub fn handle_up_command(
+    path: PathBuf,
+    build_path: Option<PathBuf>,
+    offline: bool,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+    progress: Option<&str>,
+    plan: bool,
+    wait: bool,
+    format: &str,
+    no_interaction: bool,
+) -> anyhow::Result<()> {
+    let mut config = load_config(offline)?;
+    if let Some(progress) = progress {
+        config.build_progress = Some(progress.to_string());
+    }
+    trace!("Config loaded {:?}", config);
+    let devcontainer_workspace = open_workspace(path, devcontainer_config, instance)?;
+
+    // Resolve build_path: CLI argument takes precedence over config
+    let effective_build_path = build_path.or_else(|| config.build_path.as_ref().map(PathBuf::from));
+
+    // Create runtime based on config, a plan doesn't contact it
+    let runtime_name = if plan {
+        config.planned_runtime()
+    } else {
+        config.resolve_runtime()?
+    };
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    if plan {
+        let driver = ContainerDriver::new(config, runtime)
+            .with_dry_run(true)
+            .with_interaction(!no_interaction);
+        let (processed_features, _) = driver.prepare_features(&devcontainer_workspace)?;
+        driver.build_with_features(
+            devcontainer_workspace.clone(),
+            &[],
+            Some(processed_features.clone()),
+            effective_build_path,
+        )?;
+        driver.start_with_features(devcontainer_workspace, &[], Some(processed_features))?;
+        return Ok(());
+    }
+
+    // Held until the container is up, so concurrent runs build and start one after another
+    let _lock = workspace_lock::lock(&devcontainer_workspace, wait)?;
+    hooks::run(&config, Hook::PreUp, &hook_context(&devcontainer_workspace))?;
+    let hooks_config = config.clone();
+
+    let driver = ContainerDriver::new(config, runtime).with_interaction(!no_interaction);
+
+    // Process features once
+    driver.check_offline_artifacts(&devcontainer_workspace, true)?;
+    let (processed_features, _) = driver.prepare_features(&devcontainer_workspace)?;
+
+    // Build with pre-processed features
+    driver.build_with_features(
+        devcontainer_workspace.clone(),
+        &[],
+        Some(processed_features.clone()),
+        effective_build_path,
+    )?;
+
+    // Start the container with pre-processed features
+    let context = hook_context(&devcontainer_workspace);
+    driver.start_with_features(devcontainer_workspace, &[], Some(processed_features))?;
+    hooks::run_logged(&hooks_config, Hook::PostUp, &context);
+
+    println!("Container built and started. Agent listener running. Press Ctrl+C to stop.");
+    print_timings(driver.timings(), format);
+
+    Ok(())
+}
+
+/// Prints the timings of a build as a table, slowest first, or as JSON.
+fn print_timings(timings: &BuildTimings, format: &str) {
+    let mut entries = timings.timings();
+    if format == "json" {
+        println!(
+            "{}",
+            serde_json::json!({
+                "timings": entries,
+                "totalSeconds": timings.total_seconds(),
+            })
+        );
+        return;
+    }
+    if entries.is_empty() {
+        return;
+    }
+
+    entries.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("Step").fg(Color::Green),
+            Cell::new("Name").fg(Color::Green),
+            Cell::new("Time").fg(Color::Green),
+        ]);
+    for entry in &entries {
+        table.add_row(vec![
+            Cell::new(entry.kind),
+            Cell::new(&entry.name),
+            Cell::new(format!("{:.1}s", entry.seconds)),
+        ]);
+    }
+    table.add_row(vec![
+        Cell::new("total"),
+        Cell::new(""),
+        Cell::new(format!("{:.1}s", timings.total_seconds())),
+    ]);
+    println!("{table}");
+}
+
+/// Handles the serve command to start the control server.
+///
+/// This function starts a TCP server that listens for connections from
+/// container agents and manages port forwarding requests.
+///
+/// # Arguments
+///
+/// * `port` - The port number to listen on for agent connections
+/// * `heartbeat_interval` - Seconds between pings to agents (0 disables heartbeats)
+/// * `heartbeat_timeout` - Seconds without agent messages before a connection is dropped
+/// * `metrics_port` - Optional localhost port serving the dashboard and forward metrics over HTTP
+///
+/// # Errors
+///
+/// Returns an error if the server fails to start or bind to the port.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use devcon::command::handle_serve_command;
+/// handle_serve_command(15000, 10, 30, Some(15001))?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn handle_serve_command(
+    port: u16,
+    heartbeat_interval: u64,
+    heartbeat_timeout: u64,
+    metrics_port: Option<u16>,
+) -> Result<()> {
+    let config = Config::load()?;
+    trace!("Config loaded {:?}", config);
+
+    // Create runtime based on config
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+
+    if runtime_name == "apple" {
+        println!(
+            "⚠️  Warning: For the connection to work, you have to register the dns entry 'host.container.internal' for localhost."
+        );
+        println!("   You can do this by invoking following command with sudo: ");
+        println!(
+            "     sudo container system dns create --localhost 192.168.2.10 host.container.internal"
+        );
+        println!(
+            "   More info: https://github.com/apple/container/blob/main/docs/how-to.md#access-a-host-service-from-a-container"
+        );
+    }
+
+    // The pid file is only written once the port is ours
+    let listener =
+        control_server::bind_control_server(port, config.get_control_scheme() == "grpc")?;
+    let _pid_file = PidFile::create(serve_pid_file(port)?)?;
+
+    if config.registry_cache_enabled() {
+        RegistryCache::from_config(&config)?.start(config.get_registry_cache_port())?;
+    }
+
+    let policy = server_policy(&config, &runtime_name)?;
+    control_server::start_control_server(
+        listener,
+        control_server::HeartbeatConfig {
+            interval: Duration::from_secs(heartbeat_interval),
+            timeout: Duration::from_secs(heartbeat_timeout),
+        },
+        metrics_port,
+        policy,
+        move || {
+            let config = Config::load()?;
+            config.validate()?;
+            server_policy(&config, &runtime_name)
+        },
+    )
+}
+
+/// Time a detached control server must keep running to count as started.
+const SERVE_STARTUP_GRACE: Duration = Duration::from_secs(1);
+
+/// Handles `devcon serve --detach`, which starts the control server in the
+/// background.
+///
+/// The server runs as `devcon serve` in its own session, so it outlives the
+/// terminal. Its output is appended to `serve-<port>.log` in the state
+/// directory and it holds its pid file itself. Returns once the server has
+/// survived its startup, e.g. binding the port.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot be spawned or exits during startup.
+pub fn handle_serve_detach_command(
+    port: u16,
+    heartbeat_interval: u64,
+    heartbeat_timeout: u64,
+    metrics_port: Option<u16>,
+) -> Result<()> {
+    let log_path = Config::get_state_dir()?.join(format!("serve-{}.log", port));
+    if let Some(parent) = log_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Failed to open {}", log_path.display()))?;
+
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    cmd.arg("serve")
+        .arg("--port")
+        .arg(port.to_string())
+        .arg("--heartbeat-interval")
+        .arg(heartbeat_interval.to_string())
+        .arg("--heartbeat-timeout")
+        .arg(heartbeat_timeout.to_string());
+    if let Some(metrics_port) = metrics_port {
+        cmd.arg("--metrics-port").arg(metrics_port.to_string());
+    }
+    cmd.stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setsid is async-signal-safe and touches no memory
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+    }
+    #[cfg(windows)]
+    std::os::windows::process::CommandExt::creation_flags(&mut cmd, 0x0000_0008 | 0x0000_0200);
+
+    let mut child = cmd.spawn().context("Failed to start the control server")?;
+    let started = Instant::now();
+    while started.elapsed() < SERVE_STARTUP_GRACE {
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!(
+                "Control server exited during startup ({}), see {}",
+                status,
+                log_path.display()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    println!(
+        "Control server running in the background on port {} (pid {}), log in {}",
+        port,
+        child.id(),
+        log_path.display()
+    );
+    Ok(())
+}
+
+/// Handles the serve stop command to stop a control server running in the
+/// background.
+///
+/// # Errors
+///
+/// Returns an error if no control server is running on `port`.
+pub fn handle_serve_stop_command(port: u16) -> Result<()> {
+    let pid_file = serve_pid_file(port)?;
+    let pid = serve_pid(&pid_file, port)?;
+
+    #[cfg(unix)]
+    // SAFETY: kill has no memory safety requirements
+    let stopped = unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } == 0;
+    #[cfg(not(unix))]
+    let stopped = std::process::Command::new("taskkill")
+        .arg("/PID")
+        .arg(pid.to_string())
+        .arg("/F")
+        .status()
+        .is_ok_and(|status| status.success());
+
+    // A server ended by a signal can't remove its pid file
+    let _ = std::fs::remove_file(&pid_file);
+    if !stopped {
+        anyhow::bail!("No control server running on port {}", port);
+    }
+
+    println!("Stopped the control server on port {}", port);
+    Ok(())
+}
+
+/// Handles the serve install-service command, which starts the control server
+/// at login as a systemd user unit or launchd agent.
+///
+/// # Errors
+///
+/// Returns an error if the platform has no supported service manager, the
+/// service file cannot be written or the service fails to start.
+pub fn handle_serve_install_service_command(port: u16, metrics_port: Option<u16>) -> Result<()> {
+    let manager = ServiceManager::detect()?;
+    let config = ServiceConfig {
+        binary: std::env::current_exe()?,
+        port,
+        metrics_port,
+        log_path: Config::get_state_dir()?.join(format!("serve-{}.log", port)),
+    };
+    let path = manager.install(&config)?;
+    println!(
+        "Installed the control server service on port {} in {}",
+        port,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Handles the serve uninstall-service command, which stops the control
+/// server service and removes it.
+///
+/// # Errors
+///
+/// Returns an error if the service isn't installed or cannot be removed.
+pub fn handle_serve_uninstall_service_command() -> Result<()> {
+    let path = ServiceManager::detect()?.uninstall()?;
+    println!("Removed the control server service {}", path.display());
+    Ok(())
+}
+
+/// Handles the serve status command, showing whether the control server
+/// service is installed and running and whether a server answers on `port`.
+///
+/// # Errors
+///
+/// Returns an error if the service file location cannot be determined.
+pub fn handle_serve_status_command(port: u16) -> Result<()> {
+    match ServiceManager::detect() {
+        Ok(manager) => {
+            let (installed, running) = manager.status()?;
+            println!(
+                "Service:   {} ({})",
+                if installed {
+                    "installed"
+                } else {
+                    "not installed"
+                },
+                manager.service_path()?.display()
+            );
+            if installed {
+                println!("State:     {}", if running { "running" } else { "stopped" });
+            }
+        }
+        Err(e) => println!("Service:   {}", e),
+    }
+
+    let listening = std::net::TcpStream::connect_timeout(
+        &std::net::SocketAddr::from(([127, 0, 0, 1], port)),
+        Duration::from_secs(1),
+    )
+    .is_ok();
+    println!(
+        "Port {}: {}",
+        port,
+        if listening {
+            "control server listening"
+        } else {
+            "nothing listening"
+        }
+    );
+    Ok(())
+}
+
+/// Handles the serve reload command to reload the config of a running control server.
+///
+/// Sends `SIGHUP` to the control server listening on `port`, which re-reads
+/// the forwarding policy, port lists and notification settings without
+/// dropping agent connections.
+///
+/// # Errors
+///
+/// Returns an error if no control server is running on `port` or the
+/// platform does not support signals.
+#[cfg(unix)]
+pub fn handle_serve_reload_command(port: u16) -> Result<()> {
+    let pid_file = serve_pid_file(port)?;
+    let pid = serve_pid(&pid_file, port)?;
+
+    // SAFETY: kill has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGHUP) } != 0 {
+        let _ = std::fs::remove_file(&pid_file);
+        anyhow::bail!(
+            "No control server running on port {}: {}",
+            port,
+            std::io::Error::last_os_error()
+        );
+    }
+
+    println!(
+        "Requested configuration reload of the control server on port {}",
+        port
+    );
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn handle_serve_reload_command(_port: u16) -> Result<()> {
+    anyhow::bail!(
+        "Reloading the control server is not supported on this platform, restart 'devcon serve' instead"
+    )
+}
+
+/// Path of the file holding the process id of the control server on `port`.
+fn serve_pid_file(port: u16) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?.join(format!("serve-{}.pid", port)))
+}
+
+/// Reads the process id of the control server on `port` from its pid file.
+///
+/// A pid file left behind by a crashed server is removed, as its process id
+/// may have been reused by an unrelated process.
+fn serve_pid(pid_file: &Path, port: u16) -> Result<u32> {
+    let pid = read_pid(pid_file)
+        .with_context(|| format!("No control server running on port {}", port))?;
+    if !is_devcon_server(pid) {
+        let _ = std::fs::remove_file(pid_file);
+        anyhow::bail!("No control server running on port {}", port);
+    }
+    Ok(pid)
+}
+
+/// Reads the process id of a pid file.
+fn read_pid(pid_file: &Path) -> Option<u32> {
+    std::fs::read_to_string(pid_file)
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+}
+
+/// Checks whether `pid` is a running `devcon serve` process.
+fn is_devcon_server(pid: u32) -> bool {
+    #[cfg(unix)]
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "command="])
+        .output();
+    #[cfg(not(unix))]
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output();
+
+    let Ok(output) = output else {
+        return false;
+    };
+    let command = String::from_utf8_lossy(&output.stdout).to_lowercase();
+    // tasklist only shows the image name, ps the full command line
+    output.status.success()
+        && command.contains("devcon")
+        && (cfg!(not(unix)) || command.contains("serve"))
+}
+
+/// Pid file of the running process, removed again when dropped.
+struct PidFile(PathBuf);
+
+impl PidFile {
+    /// Writes the id of the current process to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `path` names another running control server or
+    /// cannot be written.
+    fn create(path: PathBuf) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if let Some(pid) = read_pid(&path)
+            && pid != std::process::id()
+            && is_devcon_server(pid)
+        {
+            anyhow::bail!(
+                "A control server is already running with pid {} ({})",
+                pid,
+                path.display()
+            );
+        }
+        std::fs::write(&path, std::process::id().to_string())
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Another server may have taken over the port in the meantime
+        let ours = std::fs::read_to_string(&self.0)
+            .is_ok_and(|pid| pid.trim() == std::process::id().to_string());
+        if ours {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
+
+/// Builds the reloadable control server settings from the config. Agents may
+/// connect to the registry cache if it is enabled.
+fn server_policy(config: &Config, runtime_name: &str) -> Result<control_server::ServerPolicy> {
+    let mut host_ports = config.get_forward_host_ports()?;
+    if config.registry_cache_enabled() {
+        let port = config.get_registry_cache_port();
+        host_ports.push(port..=port);
+    }
+    Ok(control_server::ServerPolicy {
+        max_bandwidth: config.get_max_bandwidth()?,
+        compression: config.tunnel_compression_enabled(),
+        allowed_ports: config.get_forward_allow_ports()?,
+        denied_ports: config.get_forward_deny_ports()?,
+        host_ports,
+        auto_forward: config.get_auto_forward_policy()?,
+        notifications: config.notifications_enabled(),
+        open: open_policy(config, runtime_name)?,
+        port_forwarded_hook: Hook::PortForwarded.command(config),
+        container_usage: container_usage(config, runtime_name)?,
+        peer_workspace: peer_workspace(config, runtime_name)?,
+    })
+}
+
+/// Builds the lookup of the resource usage shown on the dashboard, sampling
+/// the current user's running containers.
+fn container_usage(
+    config: &Config,
+    runtime_name: &str,
+) -> Result<Arc<control_server::UsageLookup>> {
+    let runtime = Mutex::new(create_runtime(config, runtime_name)?);
+    Ok(Arc::new(move || {
+        let runtime = runtime.lock().unwrap();
+        let containers = match runtime.list() {
+            Ok(containers) => containers,
+            Err(e) => {
+                debug!("Failed to list containers: {:#}", e);
+                return Vec::new();
+            }
+        };
+        containers
+            .into_iter()
+            .filter(|(_, handle)| audit::is_owned_by_current_user(handle.as_ref()))
+            .filter_map(|(name, handle)| match runtime.stats(handle.as_ref()) {
+                Ok(stats) => Some(ContainerUsage {
+                    name,
+                    container: handle.id().to_string(),
+                    stats,
+                }),
+                Err(e) => {
+                    debug!("Failed to sample usage of {}: {:#}", name, e);
+                    None
+                }
+            })
+            .collect()
+    }))
+}
+
+/// Builds the context of a host hook run for a workspace. The container ID is
+/// the one of the last start recorded in the workspace state.
+fn hook_context(devcontainer_workspace: &Workspace) -> HookContext {
+    let container_id = WorkspaceState::load(&devcontainer_workspace.get_sanitized_name())
+        .ok()
+        .and_then(|state| state.container_id);
+    HookContext {
+        workspace: devcontainer_workspace.agent_name(),
+        workspace_path: Some(devcontainer_workspace.path.clone()),
+        container_id,
+        port: None,
+    }
+}
+
+/// Time after which the addresses of the containers are looked up again.
+const PEER_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Name of the workspace the agent of a container reports, read from its
+/// labels: the directory name, followed by `@instance` for an instance.
+fn container_workspace(handle: &dyn ContainerHandle) -> Option<String> {
+    let dir = Path::new(handle.label(WORKSPACE_PATH_LABEL)?).file_name()?;
+    let dir = dir.to_string_lossy();
+    Some(match handle.label(INSTANCE_LABEL) {
+        Some(instance) => format!("{}@{}", dir, instance),
+        None => dir.to_string(),
+    })
+}
+
+/// Builds the lookup of the workspace an agent connects from, matching its
+/// address against the addresses of the current user's running containers.
+/// The addresses are cached for [`PEER_REFRESH_INTERVAL`], so agents can't
+/// make the server inspect all containers for every request.
+fn peer_workspace(config: &Config, runtime_name: &str) -> Result<Arc<control_server::PeerLookup>> {
+    let runtime = create_runtime(config, runtime_name)?;
+    let cache = Mutex::new((runtime, None::<Instant>, HashMap::new()));
+    Ok(Arc::new(move |peer: IpAddr| {
+        let mut cache = cache.lock().unwrap();
+        let (runtime, refreshed, workspaces) = &mut *cache;
+        if refreshed.is_none_or(|at| at.elapsed() >= PEER_REFRESH_INTERVAL) {
+            *workspaces = container_addresses(runtime.as_ref());
+            *refreshed = Some(Instant::now());
+        }
+        workspaces.get(&peer).cloned()
+    }))
+}
+
+/// Maps the addresses of the current user's running containers to their
+/// workspace names.
+fn container_addresses(runtime: &dyn ContainerRuntime) -> HashMap<IpAddr, String> {
+    let containers = match runtime.list() {
+        Ok(containers) => containers,
+        Err(e) => {
+            debug!("Failed to list containers: {:#}", e);
+            return HashMap::new();
+        }
+    };
+    containers
+        .into_iter()
+        .filter(|(_, handle)| audit::is_owned_by_current_user(handle.as_ref()))
+        .filter_map(|(name, handle)| {
+            let workspace = container_workspace(handle.as_ref())?;
+            match runtime.inspect(handle.as_ref()) {
+                Ok(details) => Some((details.addresses, workspace)),
+                Err(e) => {
+                    debug!("Failed to inspect {}: {:#}", name, e);
+                    None
+                }
+            }
+        })
+        .flat_map(|(addresses, workspace)| {
+            addresses
+                .into_iter()
+                .map(move |address| (address, workspace.clone()))
+        })
+        .collect()
+}
+
+/// Builds the policy for opening workspace files, looking up the host path of
+/// a workspace in the labels of the current user's running containers.
+fn open_policy(config: &Config, runtime_name: &str) -> Result<OpenPolicy> {
+    let runtime = Mutex::new(create_runtime(config, runtime_name)?);
+    let workspaces = move |workspace: &str| {
+        let containers = runtime.lock().unwrap().list().ok()?;
+        containers
+            .into_iter()
+            .filter(|(_, handle)| audit::is_owned_by_current_user(handle.as_ref()))
+            .find(|(_, handle)| {
+                container_workspace(handle.as_ref()).is_some_and(|name| name == workspace)
+            })
+            .and_then(|(_, handle)| {
+                WorkspaceMount::from_labels(|name| handle.label(name).map(str::to_string))
+            })
+    };
+
+    Ok(OpenPolicy::new(
+        workspaces,
+        config.get_open_allowlist(),
+        config.open_editor.clone(),
+    ))
+}
+
+/// Handles the ports command to display forwarded ports of the control server.
+///
+/// Queries the metrics endpoint of a running `devcon serve --metrics-port`.
+///
+/// # Arguments
+///
+/// * `stats` - Whether to include transfer statistics
+/// * `metrics_port` - The port of the control server's metrics endpoint
+///
+/// # Errors
+///
+/// Returns an error if the metrics endpoint cannot be reached or returns invalid data.
+pub fn handle_ports_command(stats: bool, metrics_port: u16) -> Result<()> {
+    let forwards: Vec<ForwardStats> = query_control_server(metrics_port, "/api/ports")?;
+
+    if forwards.is_empty() {
+        println!("No ports forwarded");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let mut header = vec![
+        Cell::new("Port").fg(Color::Green),
+        Cell::new("Container Port").fg(Color::Green),
+        Cell::new("Active").fg(Color::Green),
+    ];
+    if stats {
+        header.extend([
+            Cell::new("Connections").fg(Color::Green),
+            Cell::new("To Container").fg(Color::Green),
+            Cell::new("From Container").fg(Color::Green),
+            Cell::new("Avg. First Byte").fg(Color::Green),
+        ]);
+    }
+    table.set_header(header);
+
+    for forward in forwards {
+        let mut row = vec![
+            Cell::new(forward.port_label()),
+            Cell::new(forward.container_port),
+            if forward.suspended {
+                Cell::new("suspended")
+            } else {
+                Cell::new(forward.active_connections)
+            },
+        ];
+        if stats {
+            row.extend([
+                Cell::new(forward.total_connections),
+                Cell::new(format_bytes(forward.bytes_to_container)),
+                Cell::new(format_bytes(forward.bytes_from_container)),
+                Cell::new(
+                    forward
+                        .average_latency()
+                        .map(|latency| format!("{:.1} ms", latency.as_secs_f64() * 1000.0))
+                        .unwrap_or_else(|| "-".to_string()),
+                ),
+            ]);
+        }
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// Handles the events command to print control server events of a workspace.
+///
+/// Polls the JSON API of a running `devcon serve --metrics-port` and prints
+/// every event of the workspace together with the resulting workspace status,
+/// either as text or as one JSON object per line for editor integrations.
+///
+/// While following, container start, stop, die and OOM events of the
+/// runtime and completed lifecycle hooks are printed as well. Runtimes which
+/// don't report events only get a warning.
+///
+/// # Arguments
+///
+/// * `path` - The path to the project directory
+/// * `follow` - Whether to keep printing new events until interrupted
+/// * `format` - Output format, "text" or "json"
+/// * `metrics_port` - The port of the control server's metrics endpoint
+///
+/// # Errors
+///
+/// Returns an error if the path is invalid, or if the metrics endpoint cannot
+/// be reached without `follow`. While following, the control server may come
+/// and go.
+pub fn handle_events_command(
+    path: PathBuf,
+    follow: bool,
+    format: &str,
+    metrics_port: u16,
+) -> Result<()> {
+    let path = std::fs::canonicalize(&path)
+        .with_context(|| format!("Invalid workspace path {}", path.display()))?;
+    let workspace = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .context("Workspace path has no directory name")?;
+    debug!("Printing events of workspace {}", workspace);
+    let json = format == "json";
+
+    let runtime_events = if follow {
+        subscribe_runtime_events(&path)
+    } else {
+        None
+    };
+    // Only hooks completing from now on are reported
+    let state_name = Workspace::try_from(path.clone())
+        .ok()
+        .map(|w| w.get_sanitized_name());
+    let mut completed_hooks = state_name
+        .as_deref()
+        .and_then(|name| WorkspaceState::load(name).ok())
+        .map(|state| state.lifecycle_hooks)
+        .unwrap_or_default();
+
+    let mut last_id = 0;
+    loop {
+        match workspace_events(metrics_port, &workspace, last_id) {
+            Ok((events, latest_id)) => {
+                last_id = latest_id;
+                for (event, status) in events {
+                    print_event(&event, &status, "devcon", json)?;
+                }
+            }
+            Err(e) if follow => debug!("Failed to fetch events: {}", e),
+            Err(e) => return Err(e),
+        }
+
+        if !follow {
+            return Ok(());
+        }
+
+        if let Some(name) = &state_name
+            && let Ok(state) = WorkspaceState::load(name)
+        {
+            for hook in &state.lifecycle_hooks {
+                if !completed_hooks.contains(hook) {
+                    let event = local_event(
+                        EventKind::LifecycleStageCompleted,
+                        dashboard::now(),
+                        &workspace,
+                        format!("Lifecycle hook {} completed", hook),
+                    );
+                    print_event(&event, &status_of(metrics_port, &workspace), "devcon", json)?;
+                }
+            }
+            completed_hooks = state.lifecycle_hooks;
+        }
+
+        let Some(receiver) = &runtime_events else {
+            std::thread::sleep(Duration::from_secs(1));
+            continue;
+        };
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now())
+            && let Ok(runtime_event) = receiver.recv_timeout(timeout)
+        {
+            let event = container_event(&runtime_event, &workspace);
+            print_event(
+                &event,
+                &status_of(metrics_port, &workspace),
+                "runtime",
+                json,
+            )?;
+        }
+    }
+}
+
+/// Prints an event of `devcon events`, `source` tells where it came from.
+fn print_event(event: &Event, status: &WorkspaceStatus, source: &str, json: bool) -> Result<()> {
+    if json {
+        let mut line = serde_json::to_value(event)?;
+        line["source"] = serde_json::to_value(source)?;
+        line["status"] = serde_json::to_value(status)?;
+        println!("{}", line);
+    } else {
+        println!("{} (devcon: {})", event.message, status);
+    }
+    Ok(())
+}
+
+/// Creates an event which is not recorded by the control server.
+fn local_event(kind: EventKind, timestamp: u64, workspace: &str, message: String) -> Event {
+    Event {
+        id: 0,
+        timestamp,
+        kind,
+        workspace: workspace.to_string(),
+        message,
+    }
+}
+
+/// Describes a container event of the runtime.
+fn container_event(event: &RuntimeEvent, workspace: &str) -> Event {
+    let id: String = event.container_id.chars().take(12).collect();
+    let (kind, message) = match event.action {
+        ContainerAction::Start => (
+            EventKind::ContainerStarted,
+            format!("Container {} started", id),
+        ),
+        ContainerAction::Stop => (
+            EventKind::ContainerStopped,
+            format!("Container {} stopped", id),
+        ),
+        ContainerAction::Die(Some(code)) => (
+            EventKind::ContainerDied,
+            format!("Container {} exited with code {}", id, code),
+        ),
+        ContainerAction::Die(None) => {
+            (EventKind::ContainerDied, format!("Container {} exited", id))
+        }
+        ContainerAction::Oom => (
+            EventKind::ContainerOom,
+            format!("Container {} ran out of memory", id),
+        ),
+    };
+    local_event(kind, event.timestamp, workspace, message)
+}
+
+/// Returns the status of `workspace`, stopped if the control server is not
+/// running.
+fn status_of(metrics_port: u16, workspace: &str) -> WorkspaceStatus {
+    query_control_server::<Vec<AgentInfo>>(metrics_port, "/api/agents")
+        .map(|agents| WorkspaceStatus::of(&agents, workspace))
+        .unwrap_or_default()
+}
+
+/// Subscribes to the runtime events of the containers of the workspace at
+/// `path`, `None` if the runtime does not report events.
+fn subscribe_runtime_events(path: &Path) -> Option<mpsc::Receiver<RuntimeEvent>> {
+    let subscribe = || -> Result<_> {
+        let config = Config::load()?;
+        let runtime = create_runtime(&config, &config.resolve_runtime()?)?;
+        runtime.events(&format!("{}={}", WORKSPACE_PATH_LABEL, path.display()))
+    };
+    let events = match subscribe() {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Container events are not available: {:#}", e);
+            return None;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for event in events {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Some(receiver)
+}
+
+/// Fetches the events of `workspace` newer than `last_id`, oldest first, each
+/// with the current workspace status. Also returns the latest event id.
+///
+/// If the control server was restarted, its ids start over and all of its
+/// events are returned.
+fn workspace_events(
+    metrics_port: u16,
+    workspace: &str,
+    last_id: u64,
+) -> Result<(Vec<(Event, WorkspaceStatus)>, u64)> {
+    let events: Vec<Event> = query_control_server(metrics_port, "/api/events")?;
+    let latest_id = events.iter().map(|e| e.id).max().unwrap_or_default();
+    let since = if latest_id < last_id { 0 } else { last_id };
+
+    let events: Vec<Event> = events
+        .into_iter()
+        .rev()
+        .filter(|e| e.id > since && e.workspace == workspace)
+        .collect();
+    if events.is_empty() {
+        return Ok((Vec::new(), latest_id));
+    }
+
+    let agents: Vec<AgentInfo> = query_control_server(metrics_port, "/api/agents")?;
+    let status = WorkspaceStatus::of(&agents, workspace);
+    Ok((
+        events.into_iter().map(|e| (e, status.clone())).collect(),
+        latest_id,
+    ))
+}
+
+/// Handles the run-alias command to run a project alias in the container.
+///
+/// The alias is looked up in the project's `.devcon.yaml` and run in the
+/// container's workspace folder with `args` appended.
+///
+/// # Errors
+///
+/// Returns an error if the alias is not defined, the container is not running
+/// or the command fails.
+pub fn handle_run_alias_command(path: PathBuf, alias: &str, args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    trace!("Config loaded {:?}", config);
+    let devcontainer_workspace = Workspace::try_from(path)?;
+    let project_config = ProjectConfig::load(&devcontainer_workspace.path)?;
+    let command = project_config.alias(alias)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    ContainerDriver::new(config, runtime).run_alias(&devcontainer_workspace, command, args)
+}
+
+/// Handles the secret set command to store a secret in the keychain.
+///
+/// The value is read from stdin with `stdin`, otherwise it is prompted for
+/// without echo.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid, the value is empty, or the
+/// system keychain is unavailable.
+pub fn handle_secret_set_command(name: &str, stdin: bool) -> Result<()> {
+    secrets::validate_name(name)?;
+
+    let value = if stdin {
+        let mut value = String::new();
+        std::io::stdin()
+            .read_to_string(&mut value)
+            .context("Failed to read secret from stdin")?;
+        value.trim_end_matches(['\r', '\n']).to_string()
+    } else {
+        let term = console::Term::stderr();
+        term.write_str(&format!("Value for {}: ", name))?;
+        term.read_secure_line()?
+    };
+    if value.is_empty() {
+        anyhow::bail!("Secret value must not be empty");
+    }
+
+    secrets::set(name, &value)?;
+    println!("Stored secret {} in the system keychain", name);
+
+    Ok(())
+}
+
+/// Handles the projects command to list the projects below the project roots.
+///
+/// Every project is printed on its own line, filtered and ordered by the
+/// fuzzy `query`, so the output can be used directly as a path argument.
+///
+/// # Errors
+///
+/// Returns an error if the config cannot be loaded or no project roots are
+/// configured.
+pub fn handle_projects_command(query: &str, depth: usize) -> Result<()> {
+    let config = Config::load()?;
+    let roots = config.get_project_roots();
+    if roots.is_empty() && config.recent_paths.is_empty() {
+        anyhow::bail!(
+            "No project roots configured. Set them with: devcon config set projectRoots ~/src"
+        );
+    }
+
+    let mut projects = discover_projects(&roots, depth);
+    for path in config.recent_paths.iter().map(|e| PathBuf::from(&e.path)) {
+        if is_project(&path) && !projects.contains(&path) {
+            projects.push(path);
+        }
+    }
+    for project in search_projects(&projects, query) {
+        println!("{}", project.display());
+    }
+
+    Ok(())
+}
+
+/// Handles the ci command, which runs a command in a throwaway container.
+///
+/// The container is a separate instance of the workspace, so a running
+/// development container of the project is left alone. The agent and
+/// background lifecycle hooks are disabled and the build output is plain,
+/// unless `progress` is given.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `image` - Prebuilt image to pull instead of building
+/// * `offline` - Use only cached features and local base images
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `progress` - How the build output is shown
+/// * `command` - Command run in the workspace folder with its arguments
+///
+/// # Errors
+///
+/// Returns an error with the exit code of the command if it fails, or if the
+/// image cannot be built or the container cannot be started.
+pub fn handle_ci_command(
+    path: PathBuf,
+    image: Option<&str>,
+    offline: bool,
+    devcontainer_config: Option<&str>,
+    progress: Option<&str>,
+    command: &[String],
+) -> anyhow::Result<()> {
+    let mut config = load_config(offline)?;
+    config.build_progress = Some(progress.unwrap_or("plain").to_string());
+    config.agents.get_or_insert_with(Default::default).disable = Some(true);
+    config.background_lifecycle = Some(false);
+
+    let instance = format!("ci-{}", std::process::id());
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(Some(&instance))?;
+    let build_path = config.build_path.as_ref().map(PathBuf::from);
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    // Pipelines cannot answer questions
+    let driver = ContainerDriver::new(config, runtime).with_interaction(false);
+    driver.check_offline_artifacts(&devcontainer_workspace, image.is_none())?;
+    driver.run_ephemeral(devcontainer_workspace, image, build_path, command)
+}
+
+/// Handles the export command, which bundles the built image of a workspace
+/// with its feature lockfile, configuration and cached features into an
+/// archive for `devcon import`.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `output` - Path of the archive
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the image wasn't built or the archive cannot be
+/// written.
+pub fn handle_export_command(
+    path: PathBuf,
+    output: &Path,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    ContainerDriver::new(config, runtime).export_environment(&devcontainer_workspace, output)?;
+    println!(
+        "Exported {} to {}",
+        devcontainer_workspace.agent_name(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Handles the sbom command, which prints or writes a software bill of
+/// materials of the devcontainer image of a workspace.
+///
+/// # Arguments
+///
+/// * `path` - Path to the project directory
+/// * `format` - Document format, `cyclonedx` or `spdx`
+/// * `output` - File to write the document to, stdout by default
+/// * `devcontainer_config` - Named configuration of a multi-config project
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the format is unknown, the features cannot be
+/// resolved or the document cannot be written.
+pub fn handle_sbom_command(
+    path: PathBuf,
+    format: &str,
+    output: Option<&Path>,
+    devcontainer_config: Option<&str>,
+    instance: Option<&str>,
+) -> Result<()> {
+    let format: SbomFormat = format.parse()?;
+    let config = Config::load()?;
+    let devcontainer_workspace =
+        Workspace::open(path, devcontainer_config)?.with_instance(instance)?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let inventory =
+        ContainerDriver::new(config, runtime).sbom_inventory(&devcontainer_workspace)?;
+    let document = sbom::render(&inventory, format)?;
+    match output {
+        Some(output) => {
+            std::fs::write(output, document)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            eprintln!(
+                "Wrote the SBOM of {} to {}",
+                inventory.workspace,
+                output.display()
+            );
+        }
+        None => println!("{}", document),
+    }
+    Ok(())
+}
+
+/// Handles the import command for an environment archive written by
+/// `devcon export`, which loads its image and restores the configuration
+/// and cached features, so the workspace starts without network access.
+///
+/// # Arguments
+///
+/// * `archive` - Path of the environment archive
+/// * `path` - The project directory to import into
+/// * `instance` - Named container instance of the workspace
+///
+/// # Errors
+///
+/// Returns an error if the archive is invalid or the runtime fails to load
+/// the image.
+pub fn handle_import_environment_command(
+    archive: &Path,
+    path: PathBuf,
+    instance: Option<&str>,
+) -> Result<()> {
+    let config = Config::load()?;
+
+    let runtime_name = config.resolve_runtime()?;
+    debug!("Using runtime {:?}", runtime_name);
+    let runtime = create_runtime(&config, &runtime_name)?;
+
+    let devcontainer_workspace =
+        ContainerDriver::new(config, runtime).import_environment(archive, path, instance)?;
+    println!(
+        "Imported {}, start it with 'devcon start {}'",
+        devcontainer_workspace.agent_name(),
+        devcontainer_workspace.path.display()
+    );
+    Ok(())
+}
+
+/// Handles the import vscode command, which takes over the recent
+/// devcontainer projects and settings of the VS Code Dev Containers extension.
+///
+/// # Arguments
+///
+/// * `user_dir` - VS Code user directory, by default every one found
+/// * `dry_run` - Only print the changes without saving them
+///
+/// # Errors
+///
+/// Returns an error if no VS Code user directory is found, its files cannot
+/// be parsed or the config cannot be saved.
+pub fn handle_import_vscode_command(user_dir: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    let user_dirs = match user_dir {
+        Some(dir) => vec![dir],
+        None => import::vscode_user_dirs(),
+    };
+    if user_dirs.is_empty() {
+        anyhow::bail!("No VS Code user directory found, pass it with --user-dir");
+    }
+
+    let mut config = Config::load()?;
+    let mut changes = Vec::new();
+    for dir in &user_dirs {
+        debug!("Importing VS Code settings from {}", dir.display());
+        changes.extend(VscodeImport::read(dir)?.apply(&mut config));
+    }
+
+    if changes.is_empty() {
+        println!("Nothing to import");
+        return Ok(());
+    }
+    for change in &changes {
+        println!(
+            "{} {}",
+            if dry_run { "Would import" } else { "Imported" },
+            change
+        );
+    }
+    if !dry_run {
+        config.save()?;
+    }
+    Ok(())
+}
+
+/// Handles the recent list command, showing the recent projects with their
+/// number, pin, label and when they were last opened.
+///
+/// # Errors
+///
+/// Returns an error if the config cannot be loaded.
+pub fn handle_recent_list_command() -> Result<()> {
+    let config = Config::load()?;
+    if config.recent_paths.is_empty() {
+        println!("No recent projects");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("#").fg(Color::Green),
+        Cell::new("Pinned").fg(Color::Green),
+        Cell::new("Label").fg(Color::Green),
+        Cell::new("Path").fg(Color::Green),
+        Cell::new("Last Opened").fg(Color::Green),
+    ]);
+    for (index, entry) in config.recent_paths.iter().enumerate() {
+        let last_opened = entry
+            .last_opened
+            .and_then(|time| chrono::DateTime::from_timestamp(time, 0))
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            Cell::new(index + 1),
+            Cell::new(if entry.pinned { "yes" } else { "" }),
+            Cell::new(entry.label.as_deref().unwrap_or("")),
+            Cell::new(&entry.path),
+            Cell::new(last_opened),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+/// Handles the recent pin and unpin commands.
+///
+/// A pinned project is kept regardless of `recentLimit`. Pinning a project
+/// path which isn't listed yet adds it.
+///
+/// # Arguments
+///
+/// * `entry` - Number, label or path of the recent project
+/// * `pinned` - Whether to pin or unpin it
+/// * `label` - Label to set, kept if `None`
+///
+/// # Errors
+///
+/// Returns an error if the entry is not found or the config cannot be saved.
+pub fn handle_recent_pin_command(entry: &str, pinned: bool, label: Option<&str>) -> Result<()> {
+    let mut config = Config::load()?;
+    let index = match config.find_recent(entry) {
+        Some(index) => index,
+        None if pinned && is_project(Path::new(entry)) => {
+            let path = std::fs::canonicalize(entry)?;
+            config
+                .recent_paths
+                .push(RecentPath::new(path.to_string_lossy(), None));
+            config.recent_paths.len() - 1
+        }
+        None => anyhow::bail!("No recent project '{}'", entry),
+    };
+
+    let recent = &mut config.recent_paths[index];
+    recent.pinned = pinned;
+    if let Some(label) = label {
+        recent.label = (!label.is_empty()).then(|| label.to_string());
+    }
+    let path = recent.path.clone();
+    config.truncate_recent();
+    config.save()?;
+
+    println!("{} {}", if pinned { "Pinned" } else { "Unpinned" }, path);
+    Ok(())
+}
+
+/// Handles the recent rm command, removing a project from the recent list.
+///
+/// # Errors
+///
+/// Returns an error if the entry is not found or the config cannot be saved.
+pub fn handle_recent_rm_command(entry: &str) -> Result<()> {
+    let mut config = Config::load()?;
+    let index = config
+        .find_recent(entry)
+        .with_context(|| format!("No recent project '{}'", entry))?;
+    let removed = config.recent_paths.remove(index);
+    config.save()?;
+
+    println!("Removed {}", removed.path);
+    Ok(())
+}
+
+/// Handles the recent clear command, removing the unpinned recent projects
+/// or all of them with `all`.
+///
+/// # Errors
+///
+/// Returns an error if the config cannot be saved.
+pub fn handle_recent_clear_command(all: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    let before = config.recent_paths.len();
+    config.recent_paths.retain(|entry| entry.pinned && !all);
+    let removed = before - config.recent_paths.len();
+    config.save()?;
+
+    println!("Removed {} recent project(s)", removed);
+    Ok(())
+}
+
+/// Handles the runtime list command to show the container runtimes of this host.
+///
+/// Every runtime is printed with its version and why it is usable or not. The
+/// runtime that `auto` selects is marked with `*`.
+///
+/// # Errors
+///
+/// Returns an error if the config cannot be loaded.
+pub fn handle_runtime_list_command() -> Result<()> {
+    let config = Config::load()?;
+    let probes = probe_runtimes();
+    let selected = Config::select_runtime(&probes).map(|probe| probe.name);
+
+    for probe in &probes {
+        let marker = if Some(probe.name) == selected {
+            "*"
+        } else {
+            " "
+        };
+        println!(
+            "{} {:<8} {:<10} {}",
+            marker,
+            probe.name,
+            probe.version.as_deref().unwrap_or("-"),
+            probe.reason
+        );
+    }
+
+    println!();
+    match selected {
+        Some(name) => println!(
+            "auto selects {}, the first usable runtime of docker, podman and apple",
+            name
+        ),
+        None => println!("auto finds no usable runtime"),
+    }
+    if config.runtime != "auto" {
+        println!("Configured runtime: {}", config.runtime);
+    }
+
+    Ok(())
+}
+
+/// Handles the secret list command to print the names of stored secrets.
+///
+/// # Errors
+///
+/// Returns an error if the system keychain is unavailable.
+pub fn handle_secret_list_command() -> Result<()> {
+    let names = secrets::list()?;
+    if names.is_empty() {
+        println!("No secrets stored");
+        return Ok(());
+    }
+
+    for name in names {
+        println!("{}", name);
+    }
+
+    Ok(())
+}
+
+/// Handles the secret rm command to remove a secret from the keychain.
+///
+/// # Errors
+///
+/// Returns an error if the system keychain is unavailable.
+pub fn handle_secret_rm_command(name: &str) -> Result<()> {
+    if secrets::remove(name)? {
+        println!("Removed secret {}", name);
+    } else {
+        println!("No secret named {}", name);
+    }
+
+    Ok(())
+}
+
+/// Handles the login command to store registry credentials in the keychain.
+///
+/// Prompts for the username if none is given. The secret is read from stdin
+/// with `password_stdin`, otherwise it is prompted for without echo.
+///
+/// # Errors
+///
+/// Returns an error if reading the input fails, the username or secret is
+/// empty, or the system keychain is unavailable.
+pub fn handle_login_command(
+    registry: &str,
+    username: Option<&str>,
+    password_stdin: bool,
+) -> Result<()> {
+    let registry = credentials::normalize_registry(registry)?;
+    let term = console::Term::stderr();
+
+    let username = match username {
+        Some(username) => username.to_string(),
+        None => {
+            term.write_str("Username: ")?;
+            term.read_line()?
+        }
+    };
+
+    let secret = if password_stdin {
+        let mut secret = String::new();
+        std::io::stdin()
+            .read_line(&mut secret)
+            .context("Failed to read secret from stdin")?;
+        secret
+    } else {
+        term.write_str("Password or token: ")?;
+        term.read_secure_line()?
+    };
+
+    let credential = Credential {
+        username: username.trim().to_string(),
+        secret: secret.trim_end_matches(['\r', '\n']).to_string(),
+    };
+    if credential.username.is_empty() {
+        anyhow::bail!("Username must not be empty");
+    }
+    if credential.secret.is_empty() {
+        anyhow::bail!("Password or token must not be empty");
+    }
+
+    credentials::store(&registry, &credential)?;
+    println!("Stored credentials for {} in the system keychain", registry);
+
+    Ok(())
+}
+
+/// Handles the logout command to remove registry credentials from the keychain.
+///
+/// # Errors
+///
+/// Returns an error if the system keychain is unavailable.
+pub fn handle_logout_command(registry: &str) -> Result<()> {
+    let registry = credentials::normalize_registry(registry)?;
+    if credentials::delete(&registry)? {
+        println!("Removed credentials for {}", registry);
+    } else {
+        println!("No credentials stored for {}", registry);
+    }
+
+    Ok(())
+}
+
+/// Queries a JSON endpoint of the control server's metrics port.
+fn query_control_server<T: serde::de::DeserializeOwned>(
+    metrics_port: u16,
+    path: &str,
+) -> Result<T> {
+    let url = format!("http://127.0.0.1:{}{}", metrics_port, path);
+    debug!("Querying control server at {}", url);
+
+    reqwest::blocking::get(&url)
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .with_context(|| {
+            format!(
+                "Failed to query control server at {}. Is 'devcon serve --metrics-port {}' running?",
+                url, metrics_port
+            )
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_handle_config_command() {
+        let result = handle_config_path();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/serve.pid");
+
+        let pid_file = PidFile::create(path.clone()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // The pid file of another server stays
+        let pid_file = PidFile::create(path.clone()).unwrap();
+        std::fs::write(&path, "1").unwrap();
+        drop(pid_file);
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_pid_of_reused_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("serve-15000.pid");
+        let mut other = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        std::fs::write(&path, other.id().to_string()).unwrap();
+
+        // The pid now belongs to an unrelated process, it must not be signalled
+        assert!(serve_pid(&path, 15000).is_err());
+        assert!(!path.exists());
+
+        std::fs::write(&path, other.id().to_string()).unwrap();
+        let pid_file = PidFile::create(path.clone()).unwrap();
+        drop(pid_file);
+        other.kill().unwrap();
+        other.wait().unwrap();
+    }
+
+    #[test]
+    fn test_container_event() {
+        let event = container_event(
+            &RuntimeEvent {
+                container_id: "0123456789abcdef".to_string(),
+                action: ContainerAction::Die(Some(137)),
+                timestamp: 42,
+                labels: Default::default(),
+            },
+            "demo",
+        );
+        assert_eq!(event.kind, EventKind::ContainerDied);
+        assert_eq!(event.message, "Container 0123456789ab exited with code 137");
+        assert_eq!(event.timestamp, 42);
+        assert_eq!(event.workspace, "demo");
     }
 
     #[test]
@@ -0,0 +1,132 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Shell Completion
+//!
+//! Dynamic completion candidates for CLI arguments. Completion is enabled by
+//! sourcing the script emitted by `COMPLETE=<shell> devcon`, e.g.
+//! `source <(COMPLETE=bash devcon)`.
+//!
+//! Feature references are completed from the local feature cache and the
+//! features published in the official devcontainers index.
+
+use std::ffi::OsStr;
+
+use clap_complete::engine::CompletionCandidate;
+
+use crate::config::Config;
+use crate::driver::feature_process::cached_feature_references;
+
+/// Features published in the official devcontainers index (ghcr.io/devcontainers/features).
+const KNOWN_FEATURES: &[&str] = &[
+    "anaconda",
+    "aws-cli",
+    "azure-cli",
+    "common-utils",
+    "conda",
+    "desktop-lite",
+    "docker-in-docker",
+    "docker-outside-of-docker",
+    "dotnet",
+    "git",
+    "git-lfs",
+    "github-cli",
+    "go",
+    "hugo",
+    "java",
+    "kubectl-helm-minikube",
+    "node",
+    "nvidia-cuda",
+    "oryx",
+    "php",
+    "powershell",
+    "python",
+    "ruby",
+    "rust",
+    "sshd",
+    "terraform",
+];
+
+/// Completes feature references from the feature cache and the devcontainers index.
+pub fn complete_feature_reference(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let cached = cached_feature_references().unwrap_or_default();
+
+    let mut candidates: Vec<CompletionCandidate> = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+
+    let known = KNOWN_FEATURES
+        .iter()
+        .map(|name| (format!("ghcr.io/devcontainers/features/{}", name), "index"));
+    let all = cached
+        .into_iter()
+        .map(|reference| (reference, "cached"))
+        .chain(known);
+
+    for (reference, source) in all {
+        if reference.starts_with(current.as_ref()) && !seen.contains(&reference) {
+            candidates.push(CompletionCandidate::new(&reference).help(Some(source.into())));
+            seen.push(reference);
+        }
+    }
+
+    candidates
+}
+
+/// Completes feature references which are configured as additional features.
+pub fn complete_configured_feature(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let Ok(config) = Config::load() else {
+        return Vec::new();
+    };
+
+    let mut features: Vec<&String> = config
+        .additional_features
+        .keys()
+        .filter(|reference| reference.starts_with(current.as_ref()))
+        .collect();
+    features.sort();
+
+    features.into_iter().map(CompletionCandidate::new).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_feature_reference_from_index() {
+        let candidates =
+            complete_feature_reference(OsStr::new("ghcr.io/devcontainers/features/no"));
+        let values: Vec<String> = candidates
+            .iter()
+            .map(|c| c.get_value().to_string_lossy().to_string())
+            .collect();
+
+        assert!(values.contains(&"ghcr.io/devcontainers/features/node".to_string()));
+        assert!(
+            values
+                .iter()
+                .all(|v| v.starts_with("ghcr.io/devcontainers/features/no"))
+        );
+    }
+}
@@ -49,12 +49,60 @@
 
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use anyhow::{Context, Result};
+use devcon_proto::auto_forward::{self, AutoForward, AutoForwardPolicy};
+use devcon_proto::hosts;
 use serde::{Deserialize, Serialize};
 
+use crate::config_migration::{self, CONFIG_VERSION};
+use crate::driver::oci::mirror::RegistryMirrors;
+use crate::driver::runtime::RuntimeError;
+use crate::driver::signature;
+use crate::exit_code;
+
+/// Errors of loading and changing the configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The config file exists but cannot be read.
+    #[error("Failed to read config file: {}", path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// The config file is not valid YAML or has values of the wrong type.
+    #[error("Failed to parse config file: {}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: yaml_serde::Error,
+    },
+
+    /// The config file was written by a newer release of devcon.
+    #[error(
+        "Config file {} has version {version}, this devcon supports up to version {}. Please update devcon.",
+        path.display(),
+        CONFIG_VERSION
+    )]
+    UnsupportedVersion { path: PathBuf, version: u64 },
+
+    /// The property does not exist.
+    #[error("Unknown config property: {0}")]
+    UnknownProperty(String),
+}
+
+impl ConfigError {
+    /// Returns the process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        exit_code::CONFIG
+    }
+}
+
 /// Property metadata for configuration fields.
 #[derive(Debug, Clone, Copy)]
 pub struct PropertyMetadata {
@@ -84,6 +132,10 @@ pub enum PropertyValidator {
     Memory,
     Cpu,
     NonEmpty,
+    Bandwidth,
+    TimeWindows,
+    Ports,
+    Count,
 }
 
 /// Trait for types that can provide property metadata and get/set operations.
@@ -382,9 +434,41 @@ fn validate_property_value(validator: &PropertyValidator, value: &str) -> Result
             }
             Ok(value.to_string())
         }
+
+        PropertyValidator::Bandwidth => {
+            devcon_proto::bandwidth::parse_bandwidth(value).map_err(|e| anyhow::anyhow!(e))?;
+            Ok(value.trim().to_string())
+        }
+
+        PropertyValidator::TimeWindows => {
+            let windows = crate::download::parse_windows(value)?;
+            Ok(windows
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .join(","))
+        }
+
+        PropertyValidator::Ports => {
+            parse_port_ranges(value)?;
+            Ok(value.trim().to_string())
+        }
+
+        PropertyValidator::Count => {
+            let count = value
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("Value must be a whole number (e.g., '5')"))?;
+            Ok(count.to_string())
+        }
     }
 }
 
+/// Parses a comma-separated list of ports and port ranges like "3000-3999,8080".
+pub fn parse_port_ranges(value: &str) -> Result<Vec<RangeInclusive<u16>>> {
+    auto_forward::parse_port_ranges(value).map_err(anyhow::Error::msg)
+}
+
 /// Normalizes memory values to Docker format.
 fn normalize_memory_value(value: &str) -> Result<String> {
     let value_lower = value.to_lowercase();
@@ -407,6 +491,68 @@ fn normalize_memory_value(value: &str) -> Result<String> {
     }
 }
 
+/// Validates a comma-separated list of git config keys like `user.name`.
+fn validate_git_config_keys(value: &str) -> Result<()> {
+    for key in value
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+    {
+        let valid_chars = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        if !valid_chars || !key.contains('.') || key.starts_with('.') || key.ends_with('.') {
+            anyhow::bail!(
+                "Invalid git config key '{}', expected section.name (e.g., user.email)",
+                key
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Validates a comma-separated list of registry hosts like `ghcr.io`.
+fn validate_registries(value: &str) -> Result<()> {
+    for registry in split_list(Some(value)) {
+        crate::credentials::normalize_registry(&registry)?;
+    }
+    Ok(())
+}
+
+/// Splits a comma-separated setting, ignoring empty entries.
+/// Checks that every CA certificate file exists and contains a PEM certificate.
+fn validate_ca_certificates(paths: &[String]) -> Result<()> {
+    for path in paths {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("CA certificate '{}' cannot be read", path))?;
+        if !content.contains("-----BEGIN CERTIFICATE-----") {
+            anyhow::bail!("CA certificate '{}' is not a PEM certificate", path);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every trusted key file contains a supported public key.
+fn validate_trusted_keys(paths: &[String]) -> Result<()> {
+    for path in paths {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Trusted key '{}' cannot be read", path))?;
+        signature::parse_public_key(&content)
+            .with_context(|| format!("Trusted key '{}' is invalid", path))?;
+    }
+    Ok(())
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 /// Agent configuration settings.
 ///
 /// This structure holds all agent-related configuration options.
@@ -434,6 +580,20 @@ pub struct AgentConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub git_branch: Option<String>,
 
+    /// Base URL of the prebuilt agent binaries and their `checksums.txt`.
+    ///
+    /// Defaults to the GitHub release of the running devcon version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_url: Option<String>,
+
+    /// Use prebuilt agent binaries.
+    ///
+    /// If not set to false (and neither binary_url nor a git repository or
+    /// branch is set), the prebuilt binaries are downloaded on the host,
+    /// verified and copied into the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prebuilt: Option<bool>,
+
     /// Disable the agent installation.
     ///
     /// If set to true, the agent will not be installed in the container.
@@ -460,6 +620,12 @@ impl_property_registry! {
             property_type: PropertyType::String,
             description: "Git branch for agent source (default: main)",
             validator: PropertyValidator::None,
+        },
+        release_url: Option<String> => {
+            path: "releaseUrl",
+            property_type: PropertyType::String,
+            description: "Base URL of prebuilt agent binaries (default: GitHub release)",
+            validator: PropertyValidator::Url,
         }
         ---
         disable: Option<bool> => {
@@ -467,10 +633,73 @@ impl_property_registry! {
             property_type: PropertyType::Boolean,
             description: "Disable agent installation in containers",
             validator: PropertyValidator::None,
+        },
+        prebuilt: Option<bool> => {
+            path: "prebuilt",
+            property_type: PropertyType::Boolean,
+            description: "Use prebuilt agent binaries (default: true)",
+            validator: PropertyValidator::None,
         }
     }
 }
 
+/// Host hooks, user scripts run on devcon events.
+///
+/// Every hook is a shell command, see [`crate::driver::hooks`] for the
+/// environment passed to it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HooksConfig {
+    /// Run before `devcon up` builds and starts the container.
+    ///
+    /// A failing hook aborts `devcon up`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_up: Option<String>,
+
+    /// Run after `devcon up` started the container.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_up: Option<String>,
+
+    /// Run before `devcon shell` opens a shell.
+    ///
+    /// A failing hook aborts `devcon shell`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_shell: Option<String>,
+
+    /// Run by `devcon serve` after it started forwarding a port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_forwarded: Option<String>,
+}
+
+impl_property_registry! {
+    HooksConfig {
+        pre_up: Option<String> => {
+            path: "preUp",
+            property_type: PropertyType::String,
+            description: "Host command run before devcon up (aborts on failure)",
+            validator: PropertyValidator::NonEmpty,
+        },
+        post_up: Option<String> => {
+            path: "postUp",
+            property_type: PropertyType::String,
+            description: "Host command run after devcon up started the container",
+            validator: PropertyValidator::NonEmpty,
+        },
+        pre_shell: Option<String> => {
+            path: "preShell",
+            property_type: PropertyType::String,
+            description: "Host command run before devcon shell (aborts on failure)",
+            validator: PropertyValidator::NonEmpty,
+        },
+        port_forwarded: Option<String> => {
+            path: "portForwarded",
+            property_type: PropertyType::String,
+            description: "Host command run by devcon serve when a port is forwarded",
+            validator: PropertyValidator::NonEmpty,
+        },
+    }
+}
+
 /// Docker runtime-specific configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
@@ -539,6 +768,10 @@ pub struct RuntimeConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
+    /// Version of the config layout, see [`crate::config_migration`].
+    #[serde(default)]
+    pub version: u64,
+
     /// URL to a dotfiles repository.
     ///
     /// If set, this repository will be cloned into the container
@@ -553,12 +786,41 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dotfiles_install_command: Option<String>,
 
+    /// Branch of the dotfiles repository, the default branch if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotfiles_branch: Option<String>,
+
+    /// Depth of a shallow dotfiles clone, the full history if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotfiles_depth: Option<u32>,
+
+    /// Directory the dotfiles are cloned into (default: ~/.dotfiles).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotfiles_target_dir: Option<String>,
+
+    /// When the dotfiles are installed: "once" (default) skips an existing
+    /// clone, e.g. in a persisted home, "always" updates it and reruns the
+    /// install command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dotfiles_rerun: Option<String>,
+
+    /// Dotfiles repositories applied in order after `dotfiles_repository`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dotfiles: Vec<DotfilesRepository>,
+
     /// Default shell
     ///
     /// If set, the shell command will use this shell to exec into the container
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_shell: Option<String>,
 
+    /// Fallback shells
+    ///
+    /// Shells tried in order when the default shell is not available in the container.
+    /// If empty, defaults to zsh, bash and sh.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_shells: Vec<String>,
+
     /// Additional devcontainer features to include in all containers.
     ///
     /// These features are merged with features defined in devcontainer.json.
@@ -576,7 +838,7 @@ pub struct Config {
 
     /// Container runtime to use.
     ///
-    /// Valid values: "auto", "docker", "apple"
+    /// Valid values: "auto", "docker", "podman", "apple"
     /// If set to "auto" (default), the runtime will be auto-detected.
     #[serde(
         default = "default_runtime",
@@ -590,530 +852,3432 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub build_path: Option<String>,
 
-    /// Agent configuration settings.
+    /// Path of a minijinja template replacing the built-in Dockerfile template.
     ///
-    /// Contains all agent-related options like binary URL, git repository, etc.
+    /// Files next to the template can be copied with `COPY --from=template`.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub agents: Option<AgentConfig>,
+    pub dockerfile_template: Option<String>,
 
-    /// Runtime-specific configuration settings.
+    /// How the runtime build output is shown: "auto", "plain", "tty" or "quiet".
     ///
-    /// Contains runtime-specific options for Docker and Apple container runtimes.
+    /// The complete output is saved to the build log of the workspace in
+    /// every mode. Can be overridden with `--progress`.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub runtime_config: Option<RuntimeConfig>,
-}
+    pub build_progress: Option<String>,
 
-fn default_runtime() -> String {
-    "auto".to_string()
-}
+    /// Bandwidth limit for forwarded ports.
+    ///
+    /// If set, every tunnel of the control server is limited to this rate
+    /// (e.g., "512K" or "10M" bytes per second).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bandwidth: Option<String>,
 
-fn is_default_runtime(runtime: &str) -> bool {
-    runtime == "auto"
-}
+    /// Compression of multiplexed tunnel traffic.
+    ///
+    /// If unset or true, agents may compress tunnel payloads. Set to false for
+    /// fast local setups where compression only costs CPU time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tunnel_compression: Option<bool>,
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            dotfiles_repository: None,
-            dotfiles_install_command: None,
-            default_shell: None,
-            additional_features: HashMap::new(),
-            env_variables: Vec::new(),
-            runtime: default_runtime(),
-            build_path: None,
-            agents: None,
-            runtime_config: None,
-        }
-    }
-}
+    /// Transport used for agent control connections.
+    ///
+    /// Either "tcp" (default), the length-prefixed protocol, or "grpc", which
+    /// requires devcon and the agent to be built with the `grpc` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_scheme: Option<String>,
 
-impl Config {
-    /// Loads the configuration from the XDG config directory.
+    /// Bandwidth limit for large downloads such as feature layers.
     ///
-    /// This method looks for the config file at:
-    /// - `$XDG_CONFIG_HOME/devcon/config.yaml` (if XDG_CONFIG_HOME is set)
-    /// - `~/.config/devcon/config.yaml` (default on Linux/macOS)
-    /// - `%APPDATA%/devcon/config.yaml` (on Windows)
+    /// If set, downloads are throttled to this rate (e.g., "1M" bytes per second).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_bandwidth: Option<String>,
+
+    /// Daily local time windows in which large downloads are allowed.
     ///
-    /// If no config file exists, returns a default empty configuration.
+    /// Comma-separated list like "22:00-06:00,12:00-13:00". Downloads outside of
+    /// the windows are paused, and builds wait for the next window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_windows: Option<String>,
+
+    /// Retries of a failing feature download (default: 5).
     ///
-    /// # Errors
+    /// Transient failures such as timeouts or server errors are retried with
+    /// exponential backoff; interrupted transfers resume where they stopped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_retries: Option<u32>,
+
+    /// Largest accepted download, such as a feature layer (e.g., "512M").
     ///
-    /// Returns an error if:
-    /// - The config file exists but cannot be read
-    /// - The config file contains invalid YAML
+    /// Downloads are streamed to disk, larger ones are aborted. Unlimited if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_size_limit: Option<String>,
+
+    /// Editor command for files opened from containers (e.g., "code").
     ///
-    /// # Examples
+    /// Used by `devcon-agent open --editor`; files are opened with the default
+    /// application otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_editor: Option<String>,
+
+    /// Host directories besides the workspace in which files may be opened.
     ///
-    /// ```no_run
-    /// # use devcon::config::Config;
-    /// let config = Config::load()?;
-    /// if let Some(dotfiles) = &config.dotfiles_repository {
-    ///     println!("Dotfiles repo: {}", dotfiles);
-    /// }
-    /// # Ok::<(), anyhow::Error>(())
-    /// ```
-    pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
+    /// Comma-separated list of absolute paths, only relevant for symlinks
+    /// pointing out of the workspace. By default only the workspace is allowed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_allowlist: Option<String>,
 
-        if !config_path.exists() {
-            return Ok(Self::default());
-        }
+    /// Directories scanned for projects by `devcon projects` (e.g., "~/src").
+    ///
+    /// Comma-separated list of absolute paths, `~` is the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_roots: Option<String>,
 
-        let content = fs::read_to_string(&config_path)
-            .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    /// Recently opened projects, most recent first, listed by `devcon
+    /// projects` besides the discovered ones and managed with `devcon recent`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_paths: Vec<RecentPath>,
 
-        // Check for old config format fields
-        if content.contains("agentBinaryUrl")
-            || content.contains("agentGitRepository")
-            || content.contains("agentGitBranch")
-            || content.contains("agentDisable")
-        {
-            anyhow::bail!(
-                "Old config format detected in {}. Please manually migrate agent_* fields to the new agents.* hierarchy. \
-                See 'devcon config list' for available properties.",
-                config_path.display()
-            );
-        }
+    /// Number of unpinned recent projects kept (default: 10).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_limit: Option<u32>,
 
-        let config: Config = yaml_serde::from_str(&content)
-            .with_context(|| format!("Failed to parse config file: {}", config_path.display()))?;
+    /// Maximum number of CPUs of a container (e.g., "2" or "0.5").
+    ///
+    /// Caps the `hostRequirements.cpus` of a project and is used as limit for
+    /// projects without host requirements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_cpus: Option<String>,
 
-        Ok(config)
-    }
+    /// Maximum memory of a container (e.g., "8g").
+    ///
+    /// Caps the `hostRequirements.memory` of a project and is used as limit for
+    /// projects without host requirements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_memory: Option<String>,
 
-    /// Saves the configuration to the XDG config directory.
+    /// Share the host's Docker socket with containers (docker-outside-of-docker).
     ///
-    /// This method creates the config directory if it doesn't exist,
-    /// then writes the configuration in YAML format.
+    /// If true, `/var/run/docker.sock` is bind-mounted into containers and the
+    /// remote user joins a group with the socket's GID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_docker_socket: Option<bool>,
+
+    /// Pass the host's HTTP proxy settings to builds and containers.
     ///
-    /// # Errors
+    /// If unset or true, detected `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`
+    /// values are set as build arguments and container environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub propagate_proxy: Option<bool>,
+
+    /// Work without network access (air-gapped).
     ///
-    /// Returns an error if:
-    /// - The config directory cannot be created
-    /// - The config file cannot be written
-    /// - The configuration cannot be serialized to YAML
+    /// If true, features are resolved from the local cache only, no registry
+    /// tokens are fetched and base images must already be present locally.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+
+    /// Relabel bind mounts for SELinux with `:Z`.
     ///
-    /// # Examples
+    /// If unset, mounts are relabeled when SELinux is enforcing on the host.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selinux_relabel: Option<bool>,
+
+    /// Reuse images with installed features across projects.
     ///
-    /// ```no_run
-    /// # use devcon::config::Config;
-    /// let mut config = Config::default();
-    /// config.dotfiles_repository = Some("https://github.com/user/dotfiles".to_string());
-    /// config.save()?;
-    /// # Ok::<(), anyhow::Error>(())
-    /// ```
-    pub fn save(&self) -> Result<()> {
-        let config_path = Self::get_config_path()?;
+    /// If unset or true, every feature layer is tagged by its content and
+    /// base image, so projects with the same stack share them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feature_cache: Option<bool>,
 
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).with_context(|| {
-                format!("Failed to create config directory: {}", parent.display())
-            })?;
-        }
+    /// Use BuildKit cache mounts for package managers in feature installs
+    /// and embed the build cache in images.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub buildkit: Option<bool>,
 
-        let yaml = yaml_serde::to_string(self)
-            .with_context(|| "Failed to serialize configuration to YAML")?;
+    /// Bake the VS Code extensions and settings of `customizations.vscode`
+    /// into images.
+    ///
+    /// If unset or true, the settings become the machine settings of the VS
+    /// Code server and the extensions are kept in the image metadata, so any
+    /// VS Code attaching to the container installs them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bake_vscode_customizations: Option<bool>,
 
-        fs::write(&config_path, yaml)
-            .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
+    /// Images to import the build cache from, comma-separated
+    /// (e.g., "ghcr.io/acme/devcon-cache:app").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_cache_from: Option<String>,
 
-        Ok(())
-    }
+    /// Registries whose stored credentials are passed to feature installs
+    /// as BuildKit secret, comma-separated (e.g., "ghcr.io").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_secret_registries: Option<String>,
 
-    /// Returns the path to the config file.
+    /// PEM files with additional CA certificates (e.g., a corporate TLS proxy).
     ///
-    /// This uses the XDG Base Directory specification on Unix-like systems
-    /// and the appropriate AppData directory on Windows.
+    /// They are trusted for feature downloads, installed into the trust store
+    /// of built images and mounted into containers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_certificates: Vec<String>,
+
+    /// Whether registry features and base images need a cosign signature
+    /// made with one of the `trusted_keys` (default: false).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_signatures: Option<bool>,
+
+    /// PEM files with the cosign public keys signatures are verified with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<String>,
+
+    /// Mirrors of registries for feature downloads and base images,
+    /// comma-separated (e.g., "ghcr.io=artifactory.corp/ghcr-remote").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry_mirrors: Option<String>,
+
+    /// Ports agents may forward (e.g., "3000-3999,8080").
     ///
-    /// # Errors
+    /// Comma-separated list of ports and ranges. If unset, all ports not
+    /// denied by `forward_deny_ports` may be forwarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_allow_ports: Option<String>,
+
+    /// Ports agents may never forward (e.g., "22,5432").
     ///
-    /// Returns an error if the config directory cannot be determined
-    /// (e.g., if HOME is not set).
-    pub fn get_config_path() -> Result<PathBuf> {
-        let config_dir = dirs::config_dir().context("Failed to determine config directory")?;
+    /// Takes precedence over `forward_allow_ports`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_deny_ports: Option<String>,
 
-        Ok(config_dir.join("devcon").join("config.yaml"))
-    }
+    /// Host ports containers may reach with `devcon-agent forward-host-port`
+    /// (e.g., "5432,11434").
+    ///
+    /// If unset, no host ports are exposed to containers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forward_host_ports: Option<String>,
 
-    /// Merges additional features from the config with existing features.
+    /// Proxy cargo, npm and pip registry traffic of containers through a
+    /// caching proxy of `devcon serve`.
     ///
-    /// This creates a combined map of features, with devcontainer.json
-    /// features taking precedence over config features.
+    /// If true, the agent makes the proxy reachable inside containers and the
+    /// package managers are configured to use it. Defaults to false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry_cache: Option<bool>,
+
+    /// Port of the registry caching proxy, on the host and in containers.
     ///
-    /// # Arguments
+    /// If unset, port 15080 is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry_cache_port: Option<u16>,
+
+    /// Additional names of the host in containers, comma-separated
+    /// (e.g., "db.internal,llm.internal").
     ///
-    /// * `devcontainer_features` - Features from devcontainer.json
+    /// The host is always reachable as `host.devcon.internal`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_aliases: Option<String>,
+
+    /// Which ports detected in containers are forwarded automatically
+    /// ("all", "none" or "allowlist").
     ///
-    /// # Returns
+    /// If unset, all detected ports are forwarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_forward: Option<String>,
+
+    /// Ports forwarded automatically if `auto_forward` is "allowlist"
+    /// (e.g., "3000-3999,8080").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_forward_ports: Option<String>,
+
+    /// Show desktop notifications requested by agents.
     ///
-    /// A HashMap containing all features with their options.
-    #[allow(dead_code)]
-    pub fn merge_features(
-        &self,
-        devcontainer_features: &[(String, serde_json::Value)],
-    ) -> HashMap<String, serde_json::Value> {
-        let mut merged = self.additional_features.clone();
+    /// If unset or true, notifications are shown subject to the rate limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<bool>,
 
-        // Devcontainer features override config features
-        for (key, value) in devcontainer_features {
-            merged.insert(key.clone(), value.clone());
-        }
+    /// Propagate the host's timezone as `TZ` into containers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub propagate_timezone: Option<bool>,
 
-        merged
-    }
+    /// Propagate the host's locale (`LANG`, `LC_*`) into containers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub propagate_locale: Option<bool>,
 
-    /// Detects which container runtime is available.
+    /// Bind-mount the host's `/etc/localtime` read-only into containers.
     ///
-    /// Checks for Docker and Apple's container CLI in order.
-    /// Returns "docker" if docker is available, "apple" if container is available,
-    /// or an error if neither is found.
-    pub fn detect_runtime() -> Result<String> {
-        // Check for docker
-        if Command::new("docker")
-            .arg("--version")
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
-        {
-            return Ok("docker".to_string());
-        }
+    /// Covers tools which ignore `TZ` and only read the system zone file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub propagate_localtime: Option<bool>,
 
-        // Check for Apple container CLI
-        if Command::new("container")
-            .arg("--version")
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
-        {
-            return Ok("apple".to_string());
+    /// Host git config keys copied into containers (e.g., "user.name,user.email").
+    ///
+    /// Comma-separated allowlist of keys read from the host's global git
+    /// config. Nothing is copied if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_config_allowlist: Option<String>,
+
+    /// Run `postCreateCommand` and `postStartCommand` in the background.
+    ///
+    /// `devcon start` returns once the lifecycle hook of `waitFor` completed,
+    /// later hooks keep running detached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_lifecycle: Option<bool>,
+
+    /// Agent configuration settings.
+    ///
+    /// Contains all agent-related options like binary URL, git repository, etc.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agents: Option<AgentConfig>,
+
+    /// Runtime-specific configuration settings.
+    ///
+    /// Contains runtime-specific options for Docker and Apple container runtimes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_config: Option<RuntimeConfig>,
+
+    /// Host hooks run on devcon events.
+    ///
+    /// Contains the user scripts run before and after `up`, before `shell`
+    /// and when a port is forwarded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<HooksConfig>,
+}
+
+/// Dotfiles repository of the `dotfiles` list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DotfilesRepository {
+    /// URL of the repository
+    pub repo: String,
+
+    /// Install command, auto-detected if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_command: Option<String>,
+
+    /// Directory the repository is cloned into
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_dir: Option<String>,
+
+    /// Branch, `dotfiles_branch` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+/// Result of migrating the config file, see [`Config::migrate_file`].
+#[derive(Debug)]
+pub struct MigrationOutcome {
+    /// Version of the file before the migration
+    pub from: u64,
+    /// Transformations applied, empty if the file was current
+    pub changes: Vec<String>,
+    /// Copy of the original file, if it was rewritten
+    pub backup: Option<PathBuf>,
+    /// The migrated YAML document
+    pub document: yaml_serde::Value,
+}
+
+/// Entry of the recent projects list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentPath {
+    /// Path of the project
+    pub path: String,
+
+    /// Label shown next to the path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Pinned entries are never dropped for newer ones
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+
+    /// Unix time the project was last opened, unknown for imported ones
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<i64>,
+}
+
+impl RecentPath {
+    /// Creates an unpinned entry without label.
+    pub fn new(path: impl Into<String>, last_opened: Option<i64>) -> Self {
+        Self {
+            path: path.into(),
+            label: None,
+            pinned: false,
+            last_opened,
         }
+    }
+}
 
-        anyhow::bail!("No container runtime found. Please install Docker or Apple's container CLI.")
+/// Default number of unpinned recent projects kept.
+const DEFAULT_RECENT_LIMIT: u32 = 10;
+
+/// Default port of the registry caching proxy.
+const DEFAULT_REGISTRY_CACHE_PORT: u16 = 15080;
+
+/// Default clone directory of the dotfiles repository at `index`.
+///
+/// The first repository uses `~/.dotfiles` like a single one, later ones a
+/// directory named after the repository, e.g. `~/.dotfiles-nvim`.
+fn default_dotfiles_dir(index: usize, repo: &str) -> String {
+    if index == 0 {
+        return "~/.dotfiles".to_string();
+    }
+    let name = repo
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    if name.is_empty() {
+        format!("~/.dotfiles-{}", index + 1)
+    } else {
+        format!("~/.dotfiles-{}", name)
     }
+}
 
-    /// Gets the runtime to use, resolving "auto" to a specific runtime.
-    pub fn resolve_runtime(&self) -> Result<String> {
-        if self.runtime == "auto" {
-            Self::detect_runtime()
-        } else {
-            Ok(self.runtime.clone())
+fn default_runtime() -> String {
+    "auto".to_string()
+}
+
+fn is_default_runtime(runtime: &str) -> bool {
+    runtime == "auto"
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            dotfiles_repository: None,
+            dotfiles_install_command: None,
+            dotfiles_branch: None,
+            dotfiles_depth: None,
+            dotfiles_target_dir: None,
+            dotfiles_rerun: None,
+            dotfiles: Vec::new(),
+            default_shell: None,
+            fallback_shells: Vec::new(),
+            additional_features: HashMap::new(),
+            env_variables: Vec::new(),
+            runtime: default_runtime(),
+            build_path: None,
+            dockerfile_template: None,
+            build_progress: None,
+            max_bandwidth: None,
+            tunnel_compression: None,
+            control_scheme: None,
+            download_bandwidth: None,
+            download_windows: None,
+            download_retries: None,
+            download_size_limit: None,
+            open_editor: None,
+            open_allowlist: None,
+            project_roots: None,
+            recent_paths: Vec::new(),
+            recent_limit: None,
+            container_cpus: None,
+            container_memory: None,
+            forward_docker_socket: None,
+            propagate_proxy: None,
+            offline: None,
+            selinux_relabel: None,
+            feature_cache: None,
+            buildkit: None,
+            bake_vscode_customizations: None,
+            build_cache_from: None,
+            build_secret_registries: None,
+            ca_certificates: Vec::new(),
+            verify_signatures: None,
+            trusted_keys: Vec::new(),
+            registry_mirrors: None,
+            forward_allow_ports: None,
+            forward_host_ports: None,
+            registry_cache: None,
+            registry_cache_port: None,
+            host_aliases: None,
+            auto_forward: None,
+            auto_forward_ports: None,
+            forward_deny_ports: None,
+            notifications: None,
+            propagate_timezone: None,
+            propagate_locale: None,
+            propagate_localtime: None,
+            git_config_allowlist: None,
+            background_lifecycle: None,
+            agents: None,
+            runtime_config: None,
+            hooks: None,
         }
     }
+}
 
-    /// Gets the agent binary URL if configured.
-    pub fn get_agent_binary_url(&self) -> Option<&String> {
-        self.agents.as_ref().and_then(|a| a.binary_url.as_ref())
-    }
+/// Oldest Apple container CLI release selected by runtime auto-detection.
+///
+/// Earlier releases cannot publish ports or allocate a TTY for `exec`.
+pub const MIN_APPLE_CONTAINER_VERSION: (u32, u32, u32) = (0, 5, 0);
 
-    /// Gets the agent git repository if configured.
-    pub fn get_agent_git_repository(&self) -> Option<&String> {
-        self.agents.as_ref().and_then(|a| a.git_repository.as_ref())
+/// Parses the first `major.minor.patch` version of a `--version` output,
+/// e.g. `container CLI version 0.5.0 (build: release)`.
+pub fn parse_cli_version(output: &str) -> Option<(u32, u32, u32)> {
+    output.split_whitespace().find_map(|word| {
+        let mut parts = word.trim_start_matches('v').splitn(3, '.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        let patch = parts
+            .next()?
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()?;
+        Some((major, minor, patch))
+    })
+}
+
+/// Result of probing a container runtime on this host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeProbe {
+    /// Runtime name as used by the `runtime` setting.
+    pub name: &'static str,
+    /// Version reported by the runtime, if it is installed.
+    pub version: Option<String>,
+    /// Whether devcon can use the runtime.
+    pub usable: bool,
+    /// Why the runtime is usable or not.
+    pub reason: String,
+}
+
+impl RuntimeProbe {
+    fn not_installed(name: &'static str, program: &str) -> Self {
+        Self {
+            name,
+            version: None,
+            usable: false,
+            reason: format!("`{}` not found", program),
+        }
     }
+}
 
-    /// Gets the agent git branch if configured.
-    pub fn get_agent_git_branch(&self) -> Option<&String> {
-        self.agents.as_ref().and_then(|a| a.git_branch.as_ref())
+/// Probes Docker, Podman and Apple's container CLI, in auto-detection order.
+///
+/// Docker and Podman are usable if their daemon or socket answers, Apple's
+/// container CLI if it is at least [`MIN_APPLE_CONTAINER_VERSION`] and its
+/// system service is running.
+pub fn probe_runtimes() -> Vec<RuntimeProbe> {
+    vec![
+        probe_service(
+            "docker",
+            &["version", "--format", "{{.Server.Version}}"],
+            "daemon",
+        ),
+        probe_service(
+            "podman",
+            &["info", "--format", "{{.Version.Version}}"],
+            "socket",
+        ),
+        probe_apple(),
+    ]
+}
+
+/// Runs a probe command, `None` if the program is not installed.
+fn probe_command(program: &str, args: &[&str]) -> Option<std::result::Result<String, String>> {
+    let output = Command::new(program).args(args).output().ok()?;
+    Some(if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    })
+}
+
+/// Probes a Docker compatible CLI, whose `args` only succeed with a reachable `service`.
+fn probe_service(name: &'static str, args: &[&str], service: &str) -> RuntimeProbe {
+    match probe_command(name, args) {
+        None => RuntimeProbe::not_installed(name, name),
+        Some(Ok(version)) => RuntimeProbe {
+            name,
+            version: Some(version),
+            usable: true,
+            reason: format!("{} reachable", service),
+        },
+        Some(Err(_)) => RuntimeProbe {
+            name,
+            version: probe_command(name, &["--version"])
+                .and_then(|output| output.ok())
+                .and_then(|output| parse_cli_version(&output))
+                .map(|(major, minor, patch)| format!("{}.{}.{}", major, minor, patch)),
+            usable: false,
+            reason: format!("installed, but the {} is not reachable", service),
+        },
     }
+}
 
-    /// Checks if the agent is disabled.
-    pub fn is_agent_disabled(&self) -> bool {
-        self.agents
-            .as_ref()
-            .and_then(|a| a.disable)
-            .unwrap_or(false)
+fn probe_apple() -> RuntimeProbe {
+    match probe_command("container", &["--version"]) {
+        None => RuntimeProbe::not_installed("apple", "container"),
+        Some(Err(_)) => RuntimeProbe {
+            name: "apple",
+            version: None,
+            usable: false,
+            reason: "`container --version` failed".to_string(),
+        },
+        Some(Ok(output)) => {
+            let running = probe_command("container", &["system", "status"])
+                .is_some_and(|status| status.is_ok());
+            apple_probe(&output, running)
+        }
     }
+}
 
-    /// Gets the runtime config, using defaults if not configured.
-    pub fn get_runtime_config(&self) -> RuntimeConfig {
-        self.runtime_config.clone().unwrap_or_default()
+/// Evaluates the `container --version` output and system service state.
+fn apple_probe(version_output: &str, running: bool) -> RuntimeProbe {
+    let version = parse_cli_version(version_output);
+    let (usable, reason) = match version {
+        None => (false, "unknown version".to_string()),
+        Some(found) if found < MIN_APPLE_CONTAINER_VERSION => (
+            false,
+            format!(
+                "version {}.{}.{} or newer is required",
+                MIN_APPLE_CONTAINER_VERSION.0,
+                MIN_APPLE_CONTAINER_VERSION.1,
+                MIN_APPLE_CONTAINER_VERSION.2
+            ),
+        ),
+        Some(_) if !running => (
+            false,
+            "installed, but the system service is not running (`container system start`)"
+                .to_string(),
+        ),
+        Some(_) => (true, "system service running".to_string()),
+    };
+
+    RuntimeProbe {
+        name: "apple",
+        version: version.map(|(major, minor, patch)| format!("{}.{}.{}", major, minor, patch)),
+        usable,
+        reason,
     }
+}
 
-    /// Gets the value of a configuration property by path.
+impl Config {
+    /// Loads the configuration from the XDG config directory.
     ///
-    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
-    pub fn get_value(&self, property: &str) -> Option<String> {
-        // Handle direct Config properties
-        match property {
-            "dotfilesRepository" => return self.dotfiles_repository.clone(),
-            "dotfilesInstallCommand" => return self.dotfiles_install_command.clone(),
-            "defaultShell" => return self.default_shell.clone(),
-            "buildPath" => return self.build_path.clone(),
-            "runtime" => return Some(self.runtime.clone()),
-            _ => {}
-        }
+    /// This method looks for the config file at:
+    /// - `$XDG_CONFIG_HOME/devcon/config.yaml` (if XDG_CONFIG_HOME is set)
+    /// - `~/.config/devcon/config.yaml` (default on Linux/macOS)
+    /// - `%APPDATA%/devcon/config.yaml` (on Windows)
+    ///
+    /// If no config file exists, returns a default empty configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The config file exists but cannot be read
+    /// - The config file contains invalid YAML
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use devcon::config::Config;
+    /// let config = Config::load()?;
+    /// if let Some(dotfiles) = &config.dotfiles_repository {
+    ///     println!("Dotfiles repo: {}", dotfiles);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn load() -> Result<Self> {
+        let config_path = Self::get_config_path()?;
 
-        // Handle nested agents properties
-        if let Some(rest) = property.strip_prefix("agents.") {
-            return self.agents.as_ref()?.get_property(rest);
+        if !config_path.exists() {
+            return Ok(Self::default());
         }
 
-        // Handle nested runtimeConfig.apple properties
-        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.") {
-            return self
-                .runtime_config
-                .as_ref()?
-                .apple
-                .as_ref()?
-                .get_property(rest);
-        }
+        let document = Self::migrate_file(&config_path, false)?.document;
+        let config: Config =
+            yaml_serde::from_value(document).map_err(|source| ConfigError::Parse {
+                path: config_path,
+                source,
+            })?;
 
-        None
+        Ok(config)
     }
 
-    /// Sets the value of a configuration property by path.
+    /// Parses the content of a config file, migrating an old layout in memory.
     ///
-    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
-    /// Auto-creates parent structures as needed.
-    /// Values are validated and normalized before being set.
-    pub fn set_value(&mut self, property: &str, value: String) -> Result<()> {
-        // Handle direct Config properties
-        match property {
-            "dotfilesRepository" => {
-                let validated = validate_property_value(&PropertyValidator::Url, &value)?;
-                self.dotfiles_repository = Some(validated);
-                return Ok(());
-            }
-            "dotfilesInstallCommand" => {
-                self.dotfiles_install_command = Some(value);
-                return Ok(());
-            }
-            "defaultShell" => {
-                self.default_shell = Some(value);
-                return Ok(());
-            }
-            "buildPath" => {
-                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
-                self.build_path = Some(validated);
-                return Ok(());
-            }
-            "runtime" => {
-                let validated = validate_property_value(
-                    &PropertyValidator::Enum(&["auto", "docker", "apple"]),
-                    &value,
-                )?;
-                self.runtime = validated;
-                return Ok(());
-            }
-            _ => {}
-        }
-
-        // Handle nested agents properties
-        if let Some(rest) = property.strip_prefix("agents.") {
-            let agents = self.agents.get_or_insert_with(Default::default);
-            return agents.set_property(rest, value);
+    /// # Errors
+    ///
+    /// Returns an error if the content is not valid YAML, has values of the
+    /// wrong type or was written by a newer devcon.
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let mut document: yaml_serde::Value = yaml_serde::from_str(content)?;
+        let version = config_migration::version(&document);
+        if version > CONFIG_VERSION {
+            anyhow::bail!(
+                "Version {} is newer than this devcon supports ({})",
+                version,
+                CONFIG_VERSION
+            );
         }
-
-        // Handle nested runtimeConfig.apple properties
-        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.") {
-            let runtime_config = self.runtime_config.get_or_insert_with(Default::default);
-            let apple = runtime_config.apple.get_or_insert_with(Default::default);
-            return apple.set_property(rest, value);
+        let changes = config_migration::migrate(&mut document);
+        if changes.iter().all(|change| change.starts_with("version:")) {
+            // Without transformations the text is parsed, which keeps the
+            // line and column of errors
+            Ok(yaml_serde::from_str(content)?)
+        } else {
+            Ok(yaml_serde::from_value(document)?)
         }
-
-        anyhow::bail!("Unknown config property: {}", property)
     }
 
-    /// Unsets (removes) the value of a configuration property by path.
+    /// Migrates the config file at `config_path` to the current version.
     ///
-    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
-    pub fn unset_value(&mut self, property: &str) -> Result<()> {
-        // Handle direct Config properties
-        match property {
-            "dotfilesRepository" => {
-                self.dotfiles_repository = None;
-                return Ok(());
-            }
-            "dotfilesInstallCommand" => {
-                self.dotfiles_install_command = None;
-                return Ok(());
-            }
-            "defaultShell" => {
-                self.default_shell = None;
-                return Ok(());
-            }
-            "buildPath" => {
-                self.build_path = None;
-                return Ok(());
-            }
-            "runtime" => {
-                self.runtime = "auto".to_string();
-                return Ok(());
-            }
-            _ => {}
-        }
+    /// Unless `dry_run` is set, a migrated file is written back after a copy
+    /// of the original was saved next to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, parsed or written, or was
+    /// written by a newer devcon.
+    pub fn migrate_file(config_path: &Path, dry_run: bool) -> Result<MigrationOutcome> {
+        let content = fs::read_to_string(config_path).map_err(|source| ConfigError::Read {
+            path: config_path.to_path_buf(),
+            source,
+        })?;
+        let mut document: yaml_serde::Value =
+            yaml_serde::from_str(&content).map_err(|source| ConfigError::Parse {
+                path: config_path.to_path_buf(),
+                source,
+            })?;
 
-        // Handle nested agents properties
-        if let Some(rest) = property.strip_prefix("agents.") {
-            if let Some(agents) = self.agents.as_mut() {
-                return agents.unset_property(rest);
+        let from = config_migration::version(&document);
+        if from > CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion {
+                path: config_path.to_path_buf(),
+                version: from,
             }
-            return Ok(());
+            .into());
         }
 
-        // Handle nested runtimeConfig.apple properties
-        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.")
-            && let Some(runtime_config) = self.runtime_config.as_mut()
-        {
-            if let Some(apple) = runtime_config.apple.as_mut() {
-                return apple.unset_property(rest);
-            }
-            return Ok(());
+        let changes = config_migration::migrate(&mut document);
+        let mut backup = None;
+        // A file without content needs no version
+        if !dry_run && !changes.is_empty() && !content.trim().is_empty() {
+            let backup_path = config_migration::backup_path(config_path, from);
+            fs::copy(config_path, &backup_path).with_context(|| {
+                format!("Failed to back up config file to {}", backup_path.display())
+            })?;
+            let yaml = yaml_serde::to_string(&document)
+                .with_context(|| "Failed to serialize configuration to YAML")?;
+            fs::write(config_path, yaml).with_context(|| {
+                format!("Failed to write config file: {}", config_path.display())
+            })?;
+            tracing::warn!(
+                "Migrated config file {} from version {} to {}, the original is saved as {}",
+                config_path.display(),
+                from,
+                CONFIG_VERSION,
+                backup_path.display()
+            );
+            backup = Some(backup_path);
         }
 
-        anyhow::bail!("Unknown config property: {}", property)
+        Ok(MigrationOutcome {
+            from,
+            changes,
+            backup,
+            document,
+        })
     }
 
-    /// Lists all available configuration properties.
+    /// Saves the configuration to the XDG config directory.
     ///
-    /// Returns a vector of tuples: (property_path, type, description).
-    /// Can be filtered by a substring match on the property path.
-    pub fn list_properties(filter: Option<&str>) -> Vec<(String, String, String)> {
-        let mut all_properties = vec![
-            // Direct Config properties
-            (
-                "dotfilesRepository".to_string(),
-                "string".to_string(),
-                "URL to dotfiles repository to clone into containers".to_string(),
-            ),
-            (
-                "dotfilesInstallCommand".to_string(),
-                "string".to_string(),
-                "Custom install command for dotfiles (auto-detected if unset)".to_string(),
-            ),
-            (
-                "defaultShell".to_string(),
-                "string".to_string(),
-                "Default shell for shell command (e.g., /bin/zsh)".to_string(),
-            ),
-            (
-                "buildPath".to_string(),
-                "string".to_string(),
-                "Default build path for container builds".to_string(),
-            ),
-            (
-                "runtime".to_string(),
-                "string".to_string(),
-                "Container runtime: auto, docker, or apple (default: auto)".to_string(),
-            ),
-        ];
+    /// This method creates the config directory if it doesn't exist,
+    /// then writes the configuration in YAML format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The config directory cannot be created
+    /// - The config file cannot be written
+    /// - The configuration cannot be serialized to YAML
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use devcon::config::Config;
+    /// let mut config = Config::default();
+    /// config.dotfiles_repository = Some("https://github.com/user/dotfiles".to_string());
+    /// config.save()?;
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn save(&self) -> Result<()> {
+        let config_path = Self::get_config_path()?;
 
-        // Add agents properties with prefix
-        for meta in AgentConfig::PROPERTIES {
-            all_properties.push((
-                format!("agents.{}", meta.path),
-                match meta.property_type {
-                    PropertyType::String => "string".to_string(),
-                    PropertyType::Boolean => "boolean".to_string(),
-                },
-                meta.description.to_string(),
-            ));
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create config directory: {}", parent.display())
+            })?;
         }
 
-        // Add runtimeConfig.apple properties with prefix
-        for meta in AppleRuntimeConfig::PROPERTIES {
-            all_properties.push((
-                format!("runtimeConfig.apple.{}", meta.path),
-                match meta.property_type {
-                    PropertyType::String => "string".to_string(),
-                    PropertyType::Boolean => "boolean".to_string(),
-                },
-                meta.description.to_string(),
-            ));
-        }
+        let yaml = yaml_serde::to_string(self)
+            .with_context(|| "Failed to serialize configuration to YAML")?;
 
-        if let Some(filter_str) = filter {
-            all_properties
-                .into_iter()
-                .filter(|(path, _, _)| path.contains(filter_str))
-                .collect()
-        } else {
-            all_properties
-        }
+        fs::write(&config_path, yaml)
+            .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
+
+        Ok(())
     }
 
-    /// Validates the entire configuration.
+    /// Returns the path to the config file.
     ///
-    /// Returns an error if any configuration values are invalid.
-    pub fn validate(&self) -> Result<()> {
-        // Validate URLs
-        if let Some(url) = &self.dotfiles_repository {
-            validate_property_value(&PropertyValidator::Url, url)?;
+    /// This uses the XDG Base Directory specification on Unix-like systems
+    /// and the appropriate AppData directory on Windows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config directory cannot be determined
+    /// (e.g., if HOME is not set).
+    pub fn get_config_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir().context("Failed to determine config directory")?;
+
+        Ok(config_dir.join("devcon").join("config.yaml"))
+    }
+
+    /// Returns the devcon state directory, creating it if needed.
+    ///
+    /// Uses `$XDG_STATE_HOME/devcon` where available and falls back to the
+    /// local data directory on platforms without a state directory (macOS, Windows).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no suitable directory can be determined or created.
+    pub fn get_state_dir() -> Result<PathBuf> {
+        let base_dir = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .context("Failed to determine state directory")?;
+        let state_dir = base_dir.join("devcon");
+
+        fs::create_dir_all(&state_dir).with_context(|| {
+            format!("Failed to create state directory: {}", state_dir.display())
+        })?;
+
+        Ok(state_dir)
+    }
+
+    /// Returns the name of the host user invoking devcon.
+    ///
+    /// On Unix, the name is looked up for the real uid, which unlike `USER`
+    /// cannot be changed by the caller and is set under cron and systemd as
+    /// well. Users without a passwd entry are named `uid-<uid>`. Windows reads
+    /// `USERNAME` and falls back to "unknown".
+    #[cfg(unix)]
+    pub fn current_user() -> String {
+        let uid = unsafe { libc::getuid() };
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+        let mut result = std::ptr::null_mut();
+        let status = unsafe {
+            libc::getpwuid_r(
+                uid,
+                &mut passwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut result,
+            )
+        };
+        if status != 0 || result.is_null() || passwd.pw_name.is_null() {
+            return format!("uid-{}", uid);
         }
-        if let Some(url) = self.get_agent_binary_url() {
-            validate_property_value(&PropertyValidator::Url, url)?;
+        unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Returns the name of the host user invoking devcon.
+    ///
+    /// Reads `USERNAME` and falls back to "unknown".
+    #[cfg(not(unix))]
+    pub fn current_user() -> String {
+        std::env::var("USERNAME").unwrap_or_else(|_| "unknown".to_string())
+    }
+
+    /// Merges additional features from the config with existing features.
+    ///
+    /// This creates a combined map of features, with devcontainer.json
+    /// features taking precedence over config features.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_features` - Features from devcontainer.json
+    ///
+    /// # Returns
+    ///
+    /// A HashMap containing all features with their options.
+    #[allow(dead_code)]
+    pub fn merge_features(
+        &self,
+        devcontainer_features: &[(String, serde_json::Value)],
+    ) -> HashMap<String, serde_json::Value> {
+        let mut merged = self.additional_features.clone();
+
+        // Devcontainer features override config features
+        for (key, value) in devcontainer_features {
+            merged.insert(key.clone(), value.clone());
         }
-        if let Some(url) = self.get_agent_git_repository() {
-            validate_property_value(&PropertyValidator::Url, url)?;
+
+        merged
+    }
+
+    /// Adds a feature to the additional features, replacing existing options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature reference cannot be parsed.
+    pub fn add_feature(&mut self, reference: &str, options: serde_json::Value) -> Result<()> {
+        crate::devcontainer::parse_feature::<serde::de::value::Error>(reference, options.clone())
+            .map_err(|e| anyhow::anyhow!("Invalid feature reference '{}': {}", reference, e))?;
+        self.additional_features
+            .insert(reference.to_string(), options);
+        Ok(())
+    }
+
+    /// Removes a feature from the additional features.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature is not configured.
+    pub fn remove_feature(&mut self, reference: &str) -> Result<()> {
+        if self.additional_features.remove(reference).is_none() {
+            anyhow::bail!("Feature '{}' is not configured", reference);
         }
+        Ok(())
+    }
 
-        // Validate runtime
-        validate_property_value(
-            &PropertyValidator::Enum(&["auto", "docker", "apple"]),
-            &self.runtime,
-        )?;
+    /// Returns the dotfiles repositories to install, in order.
+    ///
+    /// `dotfilesRepository` with `dotfilesInstallCommand` and
+    /// `dotfilesTargetDir` comes first, followed by the `dotfiles` list. Unset
+    /// branches and target directories are filled in with their defaults.
+    pub fn dotfiles_repositories(&self) -> Vec<DotfilesRepository> {
+        let legacy = self
+            .dotfiles_repository
+            .as_ref()
+            .map(|repo| DotfilesRepository {
+                repo: repo.clone(),
+                install_command: self.dotfiles_install_command.clone(),
+                target_dir: self.dotfiles_target_dir.clone(),
+                branch: None,
+            });
 
-        // Validate runtime config
-        if let Some(rc) = &self.runtime_config
-            && let Some(apple) = &rc.apple
+        legacy
+            .into_iter()
+            .chain(self.dotfiles.iter().cloned())
+            .enumerate()
+            .map(|(index, mut entry)| {
+                entry.target_dir = entry
+                    .target_dir
+                    .or_else(|| Some(default_dotfiles_dir(index, &entry.repo)));
+                entry.branch = entry.branch.or_else(|| self.dotfiles_branch.clone());
+                entry
+            })
+            .collect()
+    }
+
+    /// Appends a repository to the `dotfiles` list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or the repository is already configured.
+    pub fn add_dotfiles(&mut self, entry: DotfilesRepository) -> Result<()> {
+        validate_property_value(&PropertyValidator::Url, &entry.repo)?;
+        if self
+            .dotfiles_repositories()
+            .iter()
+            .any(|configured| configured.repo == entry.repo)
         {
-            if let Some(mem) = &apple.build_memory {
-                validate_property_value(&PropertyValidator::Memory, mem)?;
-            }
-            if let Some(cpu) = &apple.build_cpu {
-                validate_property_value(&PropertyValidator::Cpu, cpu)?;
-            }
+            anyhow::bail!("Dotfiles repository '{}' is already configured", entry.repo);
         }
+        self.dotfiles.push(entry);
+        Ok(())
+    }
 
+    /// Removes a dotfiles repository, also one set as `dotfilesRepository`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the repository is not configured.
+    pub fn remove_dotfiles(&mut self, repo: &str) -> Result<()> {
+        if self.dotfiles_repository.as_deref() == Some(repo) {
+            self.dotfiles_repository = None;
+            self.dotfiles_install_command = None;
+            return Ok(());
+        }
+        let count = self.dotfiles.len();
+        self.dotfiles.retain(|entry| entry.repo != repo);
+        if self.dotfiles.len() == count {
+            anyhow::bail!("Dotfiles repository '{}' is not configured", repo);
+        }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Detects which container runtime is available.
+    ///
+    /// Returns the first usable runtime of [`probe_runtimes`], i.e. Docker,
+    /// Podman and Apple's container CLI in that order, or an error if none is usable.
+    pub fn detect_runtime() -> Result<String> {
+        Self::select_runtime(&probe_runtimes())
+            .map(|probe| {
+                tracing::debug!("Auto-detected runtime {}: {}", probe.name, probe.reason);
+                probe.name.to_string()
+            })
+            .ok_or_else(|| RuntimeError::NotAvailable.into())
+    }
 
-    #[test]
-    fn test_default_config() {
-        let config = Config::default();
-        assert!(config.dotfiles_repository.is_none());
-        assert!(config.additional_features.is_empty());
-        assert!(config.env_variables.is_empty());
+    /// Returns the runtime `auto` selects among the probes.
+    pub fn select_runtime(probes: &[RuntimeProbe]) -> Option<&RuntimeProbe> {
+        probes.iter().find(|probe| probe.usable)
     }
 
-    #[test]
-    fn test_config_serialization() {
-        let config = Config {
-            dotfiles_repository: Some("https://github.com/user/dotfiles".to_string()),
+    /// Gets the runtime to use, resolving "auto" to a specific runtime.
+    pub fn resolve_runtime(&self) -> Result<String> {
+        if self.runtime == "auto" {
+            Self::detect_runtime()
+        } else {
+            Ok(self.runtime.clone())
+        }
+    }
+
+    /// Gets the runtime to plan with, without probing for "auto".
+    ///
+    /// Plans and dry runs never contact the runtime, so "auto" falls back to
+    /// Docker instead of failing when no runtime is reachable.
+    pub fn planned_runtime(&self) -> String {
+        if self.runtime == "auto" {
+            "docker".to_string()
+        } else {
+            self.runtime.clone()
+        }
+    }
+
+    /// Gets the agent binary URL if configured.
+    pub fn get_agent_binary_url(&self) -> Option<&String> {
+        self.agents.as_ref().and_then(|a| a.binary_url.as_ref())
+    }
+
+    /// Gets the agent git repository if configured.
+    pub fn get_agent_git_repository(&self) -> Option<&String> {
+        self.agents.as_ref().and_then(|a| a.git_repository.as_ref())
+    }
+
+    /// Gets the agent git branch if configured.
+    pub fn get_agent_git_branch(&self) -> Option<&String> {
+        self.agents.as_ref().and_then(|a| a.git_branch.as_ref())
+    }
+
+    /// Gets the base URL of the prebuilt agent binaries if configured.
+    pub fn get_agent_release_url(&self) -> Option<&String> {
+        self.agents.as_ref().and_then(|a| a.release_url.as_ref())
+    }
+
+    /// Checks if prebuilt agent binaries are used.
+    ///
+    /// A configured binary URL, git repository or git branch takes precedence.
+    pub fn agent_prebuilt_enabled(&self) -> bool {
+        self.agents.as_ref().is_none_or(|a| {
+            a.prebuilt.unwrap_or(true)
+                && a.binary_url.is_none()
+                && a.git_repository.is_none()
+                && a.git_branch.is_none()
+        })
+    }
+
+    /// Checks if the agent is disabled.
+    pub fn is_agent_disabled(&self) -> bool {
+        self.agents
+            .as_ref()
+            .and_then(|a| a.disable)
+            .unwrap_or(false)
+    }
+
+    /// Returns the shells to try for the shell command, in order of preference.
+    ///
+    /// The configured default shell comes first, followed by the fallback shells
+    /// (zsh, bash and sh unless configured). Duplicates are removed.
+    pub fn shell_candidates(&self) -> Vec<String> {
+        let fallbacks = if self.fallback_shells.is_empty() {
+            vec!["zsh".to_string(), "bash".to_string(), "sh".to_string()]
+        } else {
+            self.fallback_shells.clone()
+        };
+
+        let mut candidates: Vec<String> = Vec::new();
+        for shell in self.default_shell.iter().chain(fallbacks.iter()) {
+            if !candidates.contains(shell) {
+                candidates.push(shell.clone());
+            }
+        }
+        candidates
+    }
+
+    /// Gets the bandwidth limit for forwarded ports in bytes per second.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured value is not a valid bandwidth.
+    pub fn get_max_bandwidth(&self) -> Result<Option<u64>> {
+        self.max_bandwidth
+            .as_deref()
+            .map(devcon_proto::bandwidth::parse_bandwidth)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    /// Returns whether tunnel compression is offered to agents (default: true).
+    pub fn tunnel_compression_enabled(&self) -> bool {
+        self.tunnel_compression.unwrap_or(true)
+    }
+
+    /// Returns the retries of a failing feature download (default: 5).
+    pub fn get_download_retries(&self) -> u32 {
+        self.download_retries
+            .unwrap_or(crate::download::DEFAULT_RETRIES)
+    }
+
+    /// Returns whether the host's Docker socket is shared with containers (default: false).
+    pub fn docker_socket_forwarded(&self) -> bool {
+        self.forward_docker_socket.unwrap_or(false)
+    }
+
+    /// Returns whether host proxy settings are passed to builds and containers (default: true).
+    pub fn proxy_propagated(&self) -> bool {
+        self.propagate_proxy.unwrap_or(true)
+    }
+
+    /// Returns whether devcon works without network access (default: false).
+    pub fn is_offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
+    /// Returns whether bind mounts are relabeled for SELinux (default: if
+    /// SELinux is enforcing on the host).
+    pub fn selinux_relabeled(&self) -> bool {
+        self.selinux_relabel.unwrap_or_else(|| {
+            std::fs::read_to_string("/sys/fs/selinux/enforce")
+                .is_ok_and(|enforce| enforce.trim() == "1")
+        })
+    }
+
+    /// Returns whether feature layers are shared across projects (default: true).
+    pub fn feature_cache_enabled(&self) -> bool {
+        self.feature_cache.unwrap_or(true)
+    }
+
+    /// Returns whether builds use BuildKit cache mounts and inline cache (default: true).
+    pub fn buildkit_enabled(&self) -> bool {
+        self.buildkit.unwrap_or(true)
+    }
+
+    /// Returns whether VS Code customizations are baked into images (default: true).
+    pub fn vscode_customizations_baked(&self) -> bool {
+        self.bake_vscode_customizations.unwrap_or(true)
+    }
+
+    /// Returns the images the build cache is imported from.
+    pub fn get_build_cache_from(&self) -> Vec<String> {
+        split_list(self.build_cache_from.as_deref())
+    }
+
+    /// Returns the registries whose credentials are passed to feature installs.
+    pub fn get_build_secret_registries(&self) -> Vec<String> {
+        split_list(self.build_secret_registries.as_deref())
+    }
+
+    /// Returns the configured CA certificate files.
+    pub fn get_ca_certificates(&self) -> Vec<PathBuf> {
+        self.ca_certificates.iter().map(PathBuf::from).collect()
+    }
+
+    /// Returns true if features and base images need a trusted signature.
+    pub fn signatures_verified(&self) -> bool {
+        self.verify_signatures.unwrap_or(false)
+    }
+
+    /// Returns the configured trusted key files.
+    pub fn get_trusted_keys(&self) -> Vec<PathBuf> {
+        self.trusted_keys.iter().map(PathBuf::from).collect()
+    }
+
+    /// Gets the ports agents may forward, empty if all ports are allowed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured value is not a valid port list.
+    pub fn get_forward_allow_ports(&self) -> Result<Vec<RangeInclusive<u16>>> {
+        parse_port_ranges(self.forward_allow_ports.as_deref().unwrap_or_default())
+    }
+
+    /// Gets the ports agents may never forward.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured value is not a valid port list.
+    pub fn get_forward_deny_ports(&self) -> Result<Vec<RangeInclusive<u16>>> {
+        parse_port_ranges(self.forward_deny_ports.as_deref().unwrap_or_default())
+    }
+
+    /// Gets the policy for forwarding ports detected in containers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured mode or port list is invalid.
+    pub fn get_auto_forward_policy(&self) -> Result<AutoForwardPolicy> {
+        let mode = match &self.auto_forward {
+            Some(mode) => mode.parse().map_err(anyhow::Error::msg)?,
+            None => AutoForward::All,
+        };
+        Ok(AutoForwardPolicy {
+            mode,
+            allowed: parse_port_ranges(self.auto_forward_ports.as_deref().unwrap_or_default())?,
+            denied: Vec::new(),
+        })
+    }
+
+    /// Gets the host ports containers may reach, empty if none are exposed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured value is not a valid port list.
+    pub fn get_forward_host_ports(&self) -> Result<Vec<RangeInclusive<u16>>> {
+        parse_port_ranges(self.forward_host_ports.as_deref().unwrap_or_default())
+    }
+
+    /// Returns whether registry traffic goes through the caching proxy (default: false).
+    pub fn registry_cache_enabled(&self) -> bool {
+        self.registry_cache.unwrap_or(false)
+    }
+
+    /// Returns the additional names of the host in containers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a configured name is not a valid host name.
+    pub fn get_host_aliases(&self) -> Result<Vec<String>> {
+        hosts::parse_host_names(self.host_aliases.as_deref().unwrap_or_default())
+            .map_err(anyhow::Error::msg)
+    }
+
+    /// Returns the port of the registry caching proxy (default: 15080).
+    pub fn get_registry_cache_port(&self) -> u16 {
+        self.registry_cache_port
+            .unwrap_or(DEFAULT_REGISTRY_CACHE_PORT)
+    }
+
+    /// Returns whether desktop notifications of agents are shown (default: true).
+    pub fn notifications_enabled(&self) -> bool {
+        self.notifications.unwrap_or(true)
+    }
+
+    /// Returns whether the host's timezone is set as `TZ` in containers (default: false).
+    pub fn timezone_propagated(&self) -> bool {
+        self.propagate_timezone.unwrap_or(false)
+    }
+
+    /// Returns whether the host's locale is set in containers (default: false).
+    pub fn locale_propagated(&self) -> bool {
+        self.propagate_locale.unwrap_or(false)
+    }
+
+    /// Returns whether the host's `/etc/localtime` is mounted into containers (default: false).
+    pub fn localtime_propagated(&self) -> bool {
+        self.propagate_localtime.unwrap_or(false)
+    }
+
+    /// Returns whether late lifecycle hooks run in the background (default: false).
+    pub fn lifecycle_in_background(&self) -> bool {
+        self.background_lifecycle.unwrap_or(false)
+    }
+
+    /// Returns the host git config keys copied into containers.
+    pub fn get_git_config_allowlist(&self) -> Vec<String> {
+        self.git_config_allowlist
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Returns the host directories besides workspaces in which files may be opened.
+    pub fn get_open_allowlist(&self) -> Vec<PathBuf> {
+        self.open_allowlist
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    /// Returns the number of unpinned recent projects kept (default: 10).
+    pub fn get_recent_limit(&self) -> usize {
+        self.recent_limit.unwrap_or(DEFAULT_RECENT_LIMIT) as usize
+    }
+
+    /// Moves `path` to the front of the recent projects, opened at `now`.
+    ///
+    /// Label and pin of an existing entry are kept. Unpinned entries beyond
+    /// the limit are dropped, oldest first.
+    pub fn record_recent(&mut self, path: &str, now: i64) {
+        let mut entry = match self.recent_paths.iter().position(|e| e.path == path) {
+            Some(index) => self.recent_paths.remove(index),
+            None => RecentPath::new(path, None),
+        };
+        entry.last_opened = Some(now);
+        self.recent_paths.insert(0, entry);
+        self.truncate_recent();
+    }
+
+    /// Adds `path` at the end of the recent projects if it isn't listed yet.
+    ///
+    /// Returns whether it was added.
+    pub fn add_recent(&mut self, path: &str) -> bool {
+        if self.recent_paths.iter().any(|e| e.path == path) {
+            return false;
+        }
+        self.recent_paths.push(RecentPath::new(path, None));
+        self.truncate_recent();
+        self.recent_paths.iter().any(|e| e.path == path)
+    }
+
+    /// Drops unpinned recent projects beyond the limit.
+    pub fn truncate_recent(&mut self) {
+        let limit = self.get_recent_limit();
+        let mut unpinned = 0;
+        self.recent_paths.retain(|entry| {
+            if entry.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= limit
+        });
+    }
+
+    /// Finds a recent project by its 1-based number in the list, its label
+    /// or its path.
+    pub fn find_recent(&self, entry: &str) -> Option<usize> {
+        if let Ok(number) = entry.parse::<usize>() {
+            return (1..=self.recent_paths.len())
+                .contains(&number)
+                .then(|| number - 1);
+        }
+        let canonical = fs::canonicalize(entry)
+            .ok()
+            .map(|p| p.to_string_lossy().to_string());
+        self.recent_paths.iter().position(|recent| {
+            recent.label.as_deref() == Some(entry)
+                || recent.path == entry
+                || canonical.as_deref() == Some(recent.path.as_str())
+        })
+    }
+
+    /// Returns the directories scanned for projects, with `~` expanded.
+    pub fn get_project_roots(&self) -> Vec<PathBuf> {
+        let home = dirs::home_dir().unwrap_or_default();
+        self.project_roots
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| match dir.strip_prefix('~') {
+                Some(rest) => home.join(rest.trim_start_matches('/')),
+                None => PathBuf::from(dir),
+            })
+            .collect()
+    }
+
+    /// Returns how build output is shown (default: "auto").
+    pub fn get_build_progress(&self) -> &str {
+        self.build_progress.as_deref().unwrap_or("auto")
+    }
+
+    /// Returns the control connection scheme, "tcp" or "grpc" (default: "tcp").
+    pub fn get_control_scheme(&self) -> &str {
+        self.control_scheme.as_deref().unwrap_or("tcp")
+    }
+
+    /// Gets the runtime config, using defaults if not configured.
+    pub fn get_runtime_config(&self) -> RuntimeConfig {
+        self.runtime_config.clone().unwrap_or_default()
+    }
+
+    /// Gets the value of a configuration property by path.
+    ///
+    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
+    pub fn get_value(&self, property: &str) -> Option<String> {
+        // Handle direct Config properties
+        match property {
+            "dotfilesRepository" => return self.dotfiles_repository.clone(),
+            "dotfilesInstallCommand" => return self.dotfiles_install_command.clone(),
+            "dotfilesBranch" => return self.dotfiles_branch.clone(),
+            "dotfilesDepth" => return self.dotfiles_depth.map(|n| n.to_string()),
+            "dotfilesTargetDir" => return self.dotfiles_target_dir.clone(),
+            "dotfilesRerun" => return self.dotfiles_rerun.clone(),
+            "defaultShell" => return self.default_shell.clone(),
+            "fallbackShells" => {
+                return (!self.fallback_shells.is_empty()).then(|| self.fallback_shells.join(","));
+            }
+            "buildPath" => return self.build_path.clone(),
+            "dockerfileTemplate" => return self.dockerfile_template.clone(),
+            "buildProgress" => return self.build_progress.clone(),
+            "maxBandwidth" => return self.max_bandwidth.clone(),
+            "tunnelCompression" => return self.tunnel_compression.map(|b| b.to_string()),
+            "controlScheme" => return self.control_scheme.clone(),
+            "downloadBandwidth" => return self.download_bandwidth.clone(),
+            "downloadWindows" => return self.download_windows.clone(),
+            "downloadRetries" => return self.download_retries.map(|n| n.to_string()),
+            "downloadSizeLimit" => return self.download_size_limit.clone(),
+            "openEditor" => return self.open_editor.clone(),
+            "openAllowlist" => return self.open_allowlist.clone(),
+            "projectRoots" => return self.project_roots.clone(),
+            "recentLimit" => return self.recent_limit.map(|n| n.to_string()),
+            "containerCpus" => return self.container_cpus.clone(),
+            "containerMemory" => return self.container_memory.clone(),
+            "forwardDockerSocket" => return self.forward_docker_socket.map(|b| b.to_string()),
+            "propagateProxy" => return self.propagate_proxy.map(|b| b.to_string()),
+            "offline" => return self.offline.map(|b| b.to_string()),
+            "selinuxRelabel" => return self.selinux_relabel.map(|b| b.to_string()),
+            "featureCache" => return self.feature_cache.map(|b| b.to_string()),
+            "buildkit" => return self.buildkit.map(|b| b.to_string()),
+            "bakeVscodeCustomizations" => {
+                return self.bake_vscode_customizations.map(|b| b.to_string());
+            }
+            "buildCacheFrom" => return self.build_cache_from.clone(),
+            "buildSecretRegistries" => return self.build_secret_registries.clone(),
+            "caCertificates" => {
+                return (!self.ca_certificates.is_empty()).then(|| self.ca_certificates.join(","));
+            }
+            "verifySignatures" => return self.verify_signatures.map(|b| b.to_string()),
+            "trustedKeys" => {
+                return (!self.trusted_keys.is_empty()).then(|| self.trusted_keys.join(","));
+            }
+            "registryMirrors" => return self.registry_mirrors.clone(),
+            "forwardAllowPorts" => return self.forward_allow_ports.clone(),
+            "forwardDenyPorts" => return self.forward_deny_ports.clone(),
+            "forwardHostPorts" => return self.forward_host_ports.clone(),
+            "registryCache" => return self.registry_cache.map(|b| b.to_string()),
+            "registryCachePort" => return self.registry_cache_port.map(|p| p.to_string()),
+            "hostAliases" => return self.host_aliases.clone(),
+            "autoForward" => return self.auto_forward.clone(),
+            "autoForwardPorts" => return self.auto_forward_ports.clone(),
+            "notifications" => return self.notifications.map(|b| b.to_string()),
+            "propagateTimezone" => return self.propagate_timezone.map(|b| b.to_string()),
+            "propagateLocale" => return self.propagate_locale.map(|b| b.to_string()),
+            "propagateLocaltime" => return self.propagate_localtime.map(|b| b.to_string()),
+            "gitConfigAllowlist" => return self.git_config_allowlist.clone(),
+            "backgroundLifecycle" => return self.background_lifecycle.map(|b| b.to_string()),
+            "runtime" => return Some(self.runtime.clone()),
+            _ => {}
+        }
+
+        // Handle nested agents properties
+        if let Some(rest) = property.strip_prefix("agents.") {
+            return self.agents.as_ref()?.get_property(rest);
+        }
+
+        // Handle nested runtimeConfig.apple properties
+        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.") {
+            return self
+                .runtime_config
+                .as_ref()?
+                .apple
+                .as_ref()?
+                .get_property(rest);
+        }
+
+        // Handle nested hooks properties
+        if let Some(rest) = property.strip_prefix("hooks.") {
+            return self.hooks.as_ref()?.get_property(rest);
+        }
+
+        None
+    }
+
+    /// Sets the value of a configuration property by path.
+    ///
+    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
+    /// Auto-creates parent structures as needed.
+    /// Values are validated and normalized before being set.
+    pub fn set_value(&mut self, property: &str, value: String) -> Result<()> {
+        // Handle direct Config properties
+        match property {
+            "dotfilesRepository" => {
+                let validated = validate_property_value(&PropertyValidator::Url, &value)?;
+                self.dotfiles_repository = Some(validated);
+                return Ok(());
+            }
+            "dotfilesInstallCommand" => {
+                self.dotfiles_install_command = Some(value);
+                return Ok(());
+            }
+            "dotfilesBranch" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                self.dotfiles_branch = Some(validated);
+                return Ok(());
+            }
+            "dotfilesDepth" => {
+                let validated = validate_property_value(&PropertyValidator::Count, &value)?;
+                if validated == "0" {
+                    anyhow::bail!("Clone depth must be at least 1");
+                }
+                self.dotfiles_depth = Some(validated.parse()?);
+                return Ok(());
+            }
+            "dotfilesTargetDir" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                self.dotfiles_target_dir = Some(validated);
+                return Ok(());
+            }
+            "dotfilesRerun" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["once", "always"]), &value)?;
+                self.dotfiles_rerun = Some(validated);
+                return Ok(());
+            }
+            "defaultShell" => {
+                self.default_shell = Some(value);
+                return Ok(());
+            }
+            "fallbackShells" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                self.fallback_shells = validated
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                return Ok(());
+            }
+            "buildPath" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                self.build_path = Some(validated);
+                return Ok(());
+            }
+            "dockerfileTemplate" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                if !Path::new(&validated).is_file() {
+                    anyhow::bail!("Dockerfile template '{}' does not exist", validated);
+                }
+                self.dockerfile_template = Some(validated);
+                return Ok(());
+            }
+            "buildProgress" => {
+                let validated = validate_property_value(
+                    &PropertyValidator::Enum(&["auto", "plain", "tty", "quiet"]),
+                    &value,
+                )?;
+                self.build_progress = Some(validated);
+                return Ok(());
+            }
+            "maxBandwidth" => {
+                let validated = validate_property_value(&PropertyValidator::Bandwidth, &value)?;
+                self.max_bandwidth = Some(validated);
+                return Ok(());
+            }
+            "tunnelCompression" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.tunnel_compression = Some(validated == "true");
+                return Ok(());
+            }
+            "controlScheme" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["tcp", "grpc"]), &value)?;
+                self.control_scheme = Some(validated);
+                return Ok(());
+            }
+            "downloadBandwidth" => {
+                let validated = validate_property_value(&PropertyValidator::Bandwidth, &value)?;
+                self.download_bandwidth = Some(validated);
+                return Ok(());
+            }
+            "downloadWindows" => {
+                let validated = validate_property_value(&PropertyValidator::TimeWindows, &value)?;
+                self.download_windows = Some(validated);
+                return Ok(());
+            }
+            "downloadRetries" => {
+                let validated = validate_property_value(&PropertyValidator::Count, &value)?;
+                self.download_retries = Some(validated.parse()?);
+                return Ok(());
+            }
+            "downloadSizeLimit" => {
+                let validated = validate_property_value(&PropertyValidator::Bandwidth, &value)?;
+                self.download_size_limit = Some(validated);
+                return Ok(());
+            }
+            "openEditor" => {
+                let validated = validate_property_value(&PropertyValidator::NonEmpty, &value)?;
+                self.open_editor = Some(validated);
+                return Ok(());
+            }
+            "openAllowlist" => {
+                if let Some(dir) = value
+                    .split(',')
+                    .map(str::trim)
+                    .find(|dir| !dir.is_empty() && !Path::new(dir).is_absolute())
+                {
+                    anyhow::bail!("Allowed directory '{}' must be an absolute path", dir);
+                }
+                self.open_allowlist = Some(value);
+                return Ok(());
+            }
+            "projectRoots" => {
+                if let Some(dir) = value.split(',').map(str::trim).find(|dir| {
+                    !dir.is_empty() && !dir.starts_with('~') && !Path::new(dir).is_absolute()
+                }) {
+                    anyhow::bail!("Project root '{}' must be an absolute path", dir);
+                }
+                self.project_roots = Some(value);
+                return Ok(());
+            }
+            "recentLimit" => {
+                let validated = validate_property_value(&PropertyValidator::Count, &value)?;
+                self.recent_limit = Some(validated.parse()?);
+                self.truncate_recent();
+                return Ok(());
+            }
+            "containerCpus" => {
+                let validated = validate_property_value(&PropertyValidator::Cpu, &value)?;
+                self.container_cpus = Some(validated);
+                return Ok(());
+            }
+            "containerMemory" => {
+                let validated = validate_property_value(&PropertyValidator::Memory, &value)?;
+                self.container_memory = Some(validated);
+                return Ok(());
+            }
+            "forwardDockerSocket" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.forward_docker_socket = Some(validated == "true");
+                return Ok(());
+            }
+            "propagateProxy" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.propagate_proxy = Some(validated == "true");
+                return Ok(());
+            }
+            "offline" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.offline = Some(validated == "true");
+                return Ok(());
+            }
+            "selinuxRelabel" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.selinux_relabel = Some(validated == "true");
+                return Ok(());
+            }
+            "featureCache" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.feature_cache = Some(validated == "true");
+                return Ok(());
+            }
+            "buildkit" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.buildkit = Some(validated == "true");
+                return Ok(());
+            }
+            "bakeVscodeCustomizations" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.bake_vscode_customizations = Some(validated == "true");
+                return Ok(());
+            }
+            "buildCacheFrom" => {
+                self.build_cache_from = Some(value);
+                return Ok(());
+            }
+            "buildSecretRegistries" => {
+                validate_registries(&value)?;
+                self.build_secret_registries = Some(value);
+                return Ok(());
+            }
+            "caCertificates" => {
+                let paths = split_list(Some(&value));
+                validate_ca_certificates(&paths)?;
+                self.ca_certificates = paths;
+                return Ok(());
+            }
+            "verifySignatures" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.verify_signatures = Some(validated == "true");
+                return Ok(());
+            }
+            "trustedKeys" => {
+                let paths = split_list(Some(&value));
+                validate_trusted_keys(&paths)?;
+                self.trusted_keys = paths;
+                return Ok(());
+            }
+            "registryMirrors" => {
+                RegistryMirrors::parse(&value)?;
+                self.registry_mirrors = Some(value);
+                return Ok(());
+            }
+            "forwardAllowPorts" => {
+                let validated = validate_property_value(&PropertyValidator::Ports, &value)?;
+                self.forward_allow_ports = Some(validated);
+                return Ok(());
+            }
+            "forwardDenyPorts" => {
+                let validated = validate_property_value(&PropertyValidator::Ports, &value)?;
+                self.forward_deny_ports = Some(validated);
+                return Ok(());
+            }
+            "forwardHostPorts" => {
+                let validated = validate_property_value(&PropertyValidator::Ports, &value)?;
+                self.forward_host_ports = Some(validated);
+                return Ok(());
+            }
+            "registryCache" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.registry_cache = Some(validated == "true");
+                return Ok(());
+            }
+            "registryCachePort" => {
+                let port = value
+                    .trim()
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|port| *port != 0)
+                    .with_context(|| format!("Invalid port '{}'", value))?;
+                self.registry_cache_port = Some(port);
+                return Ok(());
+            }
+            "hostAliases" => {
+                hosts::parse_host_names(&value).map_err(anyhow::Error::msg)?;
+                self.host_aliases = Some(value);
+                return Ok(());
+            }
+            "autoForward" => {
+                let validated = validate_property_value(
+                    &PropertyValidator::Enum(&["all", "none", "allowlist"]),
+                    &value,
+                )?;
+                self.auto_forward = Some(validated);
+                return Ok(());
+            }
+            "autoForwardPorts" => {
+                let validated = validate_property_value(&PropertyValidator::Ports, &value)?;
+                self.auto_forward_ports = Some(validated);
+                return Ok(());
+            }
+            "notifications" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.notifications = Some(validated == "true");
+                return Ok(());
+            }
+            "propagateTimezone" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.propagate_timezone = Some(validated == "true");
+                return Ok(());
+            }
+            "propagateLocale" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.propagate_locale = Some(validated == "true");
+                return Ok(());
+            }
+            "propagateLocaltime" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.propagate_localtime = Some(validated == "true");
+                return Ok(());
+            }
+            "gitConfigAllowlist" => {
+                validate_git_config_keys(&value)?;
+                self.git_config_allowlist = Some(value);
+                return Ok(());
+            }
+            "backgroundLifecycle" => {
+                let validated =
+                    validate_property_value(&PropertyValidator::Enum(&["true", "false"]), &value)?;
+                self.background_lifecycle = Some(validated == "true");
+                return Ok(());
+            }
+            "runtime" => {
+                let validated = validate_property_value(
+                    &PropertyValidator::Enum(&["auto", "docker", "podman", "apple"]),
+                    &value,
+                )?;
+                self.runtime = validated;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Handle nested agents properties
+        if let Some(rest) = property.strip_prefix("agents.") {
+            let agents = self.agents.get_or_insert_with(Default::default);
+            return agents.set_property(rest, value);
+        }
+
+        // Handle nested runtimeConfig.apple properties
+        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.") {
+            let runtime_config = self.runtime_config.get_or_insert_with(Default::default);
+            let apple = runtime_config.apple.get_or_insert_with(Default::default);
+            return apple.set_property(rest, value);
+        }
+
+        // Handle nested hooks properties
+        if let Some(rest) = property.strip_prefix("hooks.") {
+            let hooks = self.hooks.get_or_insert_with(Default::default);
+            return hooks.set_property(rest, value);
+        }
+
+        Err(ConfigError::UnknownProperty(property.to_string()).into())
+    }
+
+    /// Unsets (removes) the value of a configuration property by path.
+    ///
+    /// Uses camelCase dot-notation (e.g., "agents.binaryUrl").
+    pub fn unset_value(&mut self, property: &str) -> Result<()> {
+        // Handle direct Config properties
+        match property {
+            "dotfilesRepository" => {
+                self.dotfiles_repository = None;
+                return Ok(());
+            }
+            "dotfilesInstallCommand" => {
+                self.dotfiles_install_command = None;
+                return Ok(());
+            }
+            "dotfilesBranch" => {
+                self.dotfiles_branch = None;
+                return Ok(());
+            }
+            "dotfilesDepth" => {
+                self.dotfiles_depth = None;
+                return Ok(());
+            }
+            "dotfilesTargetDir" => {
+                self.dotfiles_target_dir = None;
+                return Ok(());
+            }
+            "dotfilesRerun" => {
+                self.dotfiles_rerun = None;
+                return Ok(());
+            }
+            "defaultShell" => {
+                self.default_shell = None;
+                return Ok(());
+            }
+            "fallbackShells" => {
+                self.fallback_shells.clear();
+                return Ok(());
+            }
+            "buildPath" => {
+                self.build_path = None;
+                return Ok(());
+            }
+            "dockerfileTemplate" => {
+                self.dockerfile_template = None;
+                return Ok(());
+            }
+            "buildProgress" => {
+                self.build_progress = None;
+                return Ok(());
+            }
+            "maxBandwidth" => {
+                self.max_bandwidth = None;
+                return Ok(());
+            }
+            "tunnelCompression" => {
+                self.tunnel_compression = None;
+                return Ok(());
+            }
+            "controlScheme" => {
+                self.control_scheme = None;
+                return Ok(());
+            }
+            "downloadBandwidth" => {
+                self.download_bandwidth = None;
+                return Ok(());
+            }
+            "downloadWindows" => {
+                self.download_windows = None;
+                return Ok(());
+            }
+            "downloadRetries" => {
+                self.download_retries = None;
+                return Ok(());
+            }
+            "downloadSizeLimit" => {
+                self.download_size_limit = None;
+                return Ok(());
+            }
+            "openEditor" => {
+                self.open_editor = None;
+                return Ok(());
+            }
+            "projectRoots" => {
+                self.project_roots = None;
+                return Ok(());
+            }
+            "recentLimit" => {
+                self.recent_limit = None;
+                return Ok(());
+            }
+            "openAllowlist" => {
+                self.open_allowlist = None;
+                return Ok(());
+            }
+            "containerCpus" => {
+                self.container_cpus = None;
+                return Ok(());
+            }
+            "containerMemory" => {
+                self.container_memory = None;
+                return Ok(());
+            }
+            "forwardDockerSocket" => {
+                self.forward_docker_socket = None;
+                return Ok(());
+            }
+            "propagateProxy" => {
+                self.propagate_proxy = None;
+                return Ok(());
+            }
+            "offline" => {
+                self.offline = None;
+                return Ok(());
+            }
+            "selinuxRelabel" => {
+                self.selinux_relabel = None;
+                return Ok(());
+            }
+            "featureCache" => {
+                self.feature_cache = None;
+                return Ok(());
+            }
+            "buildkit" => {
+                self.buildkit = None;
+                return Ok(());
+            }
+            "bakeVscodeCustomizations" => {
+                self.bake_vscode_customizations = None;
+                return Ok(());
+            }
+            "buildCacheFrom" => {
+                self.build_cache_from = None;
+                return Ok(());
+            }
+            "buildSecretRegistries" => {
+                self.build_secret_registries = None;
+                return Ok(());
+            }
+            "caCertificates" => {
+                self.ca_certificates.clear();
+                return Ok(());
+            }
+            "verifySignatures" => {
+                self.verify_signatures = None;
+                return Ok(());
+            }
+            "trustedKeys" => {
+                self.trusted_keys.clear();
+                return Ok(());
+            }
+            "registryMirrors" => {
+                self.registry_mirrors = None;
+                return Ok(());
+            }
+            "forwardAllowPorts" => {
+                self.forward_allow_ports = None;
+                return Ok(());
+            }
+            "forwardDenyPorts" => {
+                self.forward_deny_ports = None;
+                return Ok(());
+            }
+            "forwardHostPorts" => {
+                self.forward_host_ports = None;
+                return Ok(());
+            }
+            "registryCache" => {
+                self.registry_cache = None;
+                return Ok(());
+            }
+            "registryCachePort" => {
+                self.registry_cache_port = None;
+                return Ok(());
+            }
+            "hostAliases" => {
+                self.host_aliases = None;
+                return Ok(());
+            }
+            "autoForward" => {
+                self.auto_forward = None;
+                return Ok(());
+            }
+            "autoForwardPorts" => {
+                self.auto_forward_ports = None;
+                return Ok(());
+            }
+            "notifications" => {
+                self.notifications = None;
+                return Ok(());
+            }
+            "propagateTimezone" => {
+                self.propagate_timezone = None;
+                return Ok(());
+            }
+            "propagateLocale" => {
+                self.propagate_locale = None;
+                return Ok(());
+            }
+            "propagateLocaltime" => {
+                self.propagate_localtime = None;
+                return Ok(());
+            }
+            "gitConfigAllowlist" => {
+                self.git_config_allowlist = None;
+                return Ok(());
+            }
+            "backgroundLifecycle" => {
+                self.background_lifecycle = None;
+                return Ok(());
+            }
+            "runtime" => {
+                self.runtime = "auto".to_string();
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        // Handle nested agents properties
+        if let Some(rest) = property.strip_prefix("agents.") {
+            if let Some(agents) = self.agents.as_mut() {
+                return agents.unset_property(rest);
+            }
+            return Ok(());
+        }
+
+        // Handle nested runtimeConfig.apple properties
+        if let Some(rest) = property.strip_prefix("runtimeConfig.apple.")
+            && let Some(runtime_config) = self.runtime_config.as_mut()
+        {
+            if let Some(apple) = runtime_config.apple.as_mut() {
+                return apple.unset_property(rest);
+            }
+            return Ok(());
+        }
+
+        // Handle nested hooks properties
+        if let Some(rest) = property.strip_prefix("hooks.") {
+            if let Some(hooks) = self.hooks.as_mut() {
+                return hooks.unset_property(rest);
+            }
+            return Ok(());
+        }
+
+        Err(ConfigError::UnknownProperty(property.to_string()).into())
+    }
+
+    /// Lists all available configuration properties.
+    ///
+    /// Returns a vector of tuples: (property_path, type, description).
+    /// Can be filtered by a substring match on the property path.
+    pub fn list_properties(filter: Option<&str>) -> Vec<(String, String, String)> {
+        let mut all_properties = vec![
+            // Direct Config properties
+            (
+                "dotfilesRepository".to_string(),
+                "string".to_string(),
+                "URL to dotfiles repository to clone into containers".to_string(),
+            ),
+            (
+                "dotfilesInstallCommand".to_string(),
+                "string".to_string(),
+                "Custom install command for dotfiles (auto-detected if unset)".to_string(),
+            ),
+            (
+                "dotfilesBranch".to_string(),
+                "string".to_string(),
+                "Branch of the dotfiles repository (default branch if unset)".to_string(),
+            ),
+            (
+                "dotfilesDepth".to_string(),
+                "number".to_string(),
+                "Shallow clone depth of the dotfiles (full history if unset)".to_string(),
+            ),
+            (
+                "dotfilesTargetDir".to_string(),
+                "string".to_string(),
+                "Directory the dotfiles are cloned into (default: ~/.dotfiles)".to_string(),
+            ),
+            (
+                "dotfilesRerun".to_string(),
+                "string".to_string(),
+                "Install dotfiles once or always (default: once)".to_string(),
+            ),
+            (
+                "defaultShell".to_string(),
+                "string".to_string(),
+                "Default shell for shell command (e.g., /bin/zsh)".to_string(),
+            ),
+            (
+                "fallbackShells".to_string(),
+                "string".to_string(),
+                "Comma-separated shells to try if the default shell is missing (default: zsh,bash,sh)"
+                    .to_string(),
+            ),
+            (
+                "buildPath".to_string(),
+                "string".to_string(),
+                "Default build path for container builds".to_string(),
+            ),
+            (
+                "dockerfileTemplate".to_string(),
+                "string".to_string(),
+                "Path of a custom Dockerfile template".to_string(),
+            ),
+            (
+                "buildProgress".to_string(),
+                "string".to_string(),
+                "How build output is shown (auto, plain, tty, quiet)".to_string(),
+            ),
+            (
+                "maxBandwidth".to_string(),
+                "string".to_string(),
+                "Bandwidth limit per forwarded tunnel in bytes/s (e.g., 512K, 10M)".to_string(),
+            ),
+            (
+                "tunnelCompression".to_string(),
+                "boolean".to_string(),
+                "Compress multiplexed tunnel traffic (default: true)".to_string(),
+            ),
+            (
+                "controlScheme".to_string(),
+                "string".to_string(),
+                "Agent control connection scheme: tcp or grpc (default: tcp)".to_string(),
+            ),
+            (
+                "downloadBandwidth".to_string(),
+                "string".to_string(),
+                "Bandwidth limit for feature downloads in bytes/s (e.g., 1M)".to_string(),
+            ),
+            (
+                "downloadWindows".to_string(),
+                "string".to_string(),
+                "Local time windows for large downloads (e.g., 22:00-06:00,12:00-13:00)"
+                    .to_string(),
+            ),
+            (
+                "downloadRetries".to_string(),
+                "number".to_string(),
+                "Retries of a failing feature download (default: 5)".to_string(),
+            ),
+            (
+                "downloadSizeLimit".to_string(),
+                "string".to_string(),
+                "Largest accepted download, e.g. a feature layer (e.g., 512M)".to_string(),
+            ),
+            (
+                "openEditor".to_string(),
+                "string".to_string(),
+                "Editor command for files opened from containers (e.g., code)".to_string(),
+            ),
+            (
+                "openAllowlist".to_string(),
+                "string".to_string(),
+                "Host directories besides the workspace where files may be opened".to_string(),
+            ),
+            (
+                "projectRoots".to_string(),
+                "string".to_string(),
+                "Directories scanned for projects by devcon projects (e.g., ~/src)".to_string(),
+            ),
+            (
+                "recentLimit".to_string(),
+                "number".to_string(),
+                "Number of unpinned recent projects kept (default: 10)".to_string(),
+            ),
+            (
+                "containerCpus".to_string(),
+                "string".to_string(),
+                "Maximum CPUs of a running container (e.g., 2, 0.5)".to_string(),
+            ),
+            (
+                "containerMemory".to_string(),
+                "string".to_string(),
+                "Maximum memory of a running container (e.g., 8g, 512m)".to_string(),
+            ),
+            (
+                "forwardDockerSocket".to_string(),
+                "boolean".to_string(),
+                "Share the host's Docker socket with containers (default: false)".to_string(),
+            ),
+            (
+                "propagateProxy".to_string(),
+                "boolean".to_string(),
+                "Pass the host's HTTP proxy settings to builds and containers (default: true)"
+                    .to_string(),
+            ),
+            (
+                "offline".to_string(),
+                "boolean".to_string(),
+                "Use only cached features and local base images (default: false)".to_string(),
+            ),
+            (
+                "selinuxRelabel".to_string(),
+                "boolean".to_string(),
+                "Relabel bind mounts for SELinux with :Z (default: if SELinux is enforcing)"
+                    .to_string(),
+            ),
+            (
+                "featureCache".to_string(),
+                "boolean".to_string(),
+                "Share feature layers across projects (default: true)".to_string(),
+            ),
+            (
+                "buildkit".to_string(),
+                "boolean".to_string(),
+                "Use BuildKit cache mounts and inline build cache (default: true)".to_string(),
+            ),
+            (
+                "bakeVscodeCustomizations".to_string(),
+                "boolean".to_string(),
+                "Bake VS Code extensions and settings into images (default: true)".to_string(),
+            ),
+            (
+                "buildCacheFrom".to_string(),
+                "string".to_string(),
+                "Images to import the build cache from, comma-separated".to_string(),
+            ),
+            (
+                "buildSecretRegistries".to_string(),
+                "string".to_string(),
+                "Registries whose credentials are passed to feature installs (e.g., ghcr.io)"
+                    .to_string(),
+            ),
+            (
+                "caCertificates".to_string(),
+                "string".to_string(),
+                "Comma-separated PEM files with additional CA certificates".to_string(),
+            ),
+            (
+                "verifySignatures".to_string(),
+                "boolean".to_string(),
+                "Require cosign signatures of features and base images (default: false)"
+                    .to_string(),
+            ),
+            (
+                "trustedKeys".to_string(),
+                "string".to_string(),
+                "Comma-separated PEM files with trusted cosign public keys".to_string(),
+            ),
+            (
+                "registryMirrors".to_string(),
+                "string".to_string(),
+                "Registry mirrors for features and base images (e.g., ghcr.io=mirror.corp/ghcr)"
+                    .to_string(),
+            ),
+            (
+                "forwardAllowPorts".to_string(),
+                "string".to_string(),
+                "Ports agents may forward (e.g., 3000-3999,8080), all if unset".to_string(),
+            ),
+            (
+                "forwardDenyPorts".to_string(),
+                "string".to_string(),
+                "Ports agents may never forward (e.g., 22,5432)".to_string(),
+            ),
+            (
+                "forwardHostPorts".to_string(),
+                "string".to_string(),
+                "Host ports containers may reach (e.g., 5432,11434), none if unset".to_string(),
+            ),
+            (
+                "registryCache".to_string(),
+                "boolean".to_string(),
+                "Proxy cargo, npm and pip registries through a host cache (default: false)"
+                    .to_string(),
+            ),
+            (
+                "registryCachePort".to_string(),
+                "number".to_string(),
+                "Port of the registry caching proxy (default: 15080)".to_string(),
+            ),
+            (
+                "hostAliases".to_string(),
+                "string".to_string(),
+                "Additional names of the host in containers (e.g., db.internal,llm.internal)"
+                    .to_string(),
+            ),
+            (
+                "autoForward".to_string(),
+                "string".to_string(),
+                "Which detected ports are forwarded (all, none, allowlist)".to_string(),
+            ),
+            (
+                "autoForwardPorts".to_string(),
+                "string".to_string(),
+                "Ports forwarded automatically in allowlist mode (e.g., 3000-3999)".to_string(),
+            ),
+            (
+                "notifications".to_string(),
+                "boolean".to_string(),
+                "Show desktop notifications requested by agents (default: true)".to_string(),
+            ),
+            (
+                "propagateTimezone".to_string(),
+                "boolean".to_string(),
+                "Set the host's timezone as TZ in containers (default: false)".to_string(),
+            ),
+            (
+                "propagateLocale".to_string(),
+                "boolean".to_string(),
+                "Set the host's LANG and LC_* in containers (default: false)".to_string(),
+            ),
+            (
+                "propagateLocaltime".to_string(),
+                "boolean".to_string(),
+                "Mount the host's /etc/localtime into containers (default: false)".to_string(),
+            ),
+            (
+                "gitConfigAllowlist".to_string(),
+                "string".to_string(),
+                "Host git config keys copied into containers (e.g., user.name,user.email)"
+                    .to_string(),
+            ),
+            (
+                "backgroundLifecycle".to_string(),
+                "boolean".to_string(),
+                "Run postCreateCommand and postStartCommand in the background (default: false)"
+                    .to_string(),
+            ),
+            (
+                "runtime".to_string(),
+                "string".to_string(),
+                "Container runtime: auto, docker, podman, or apple (default: auto)".to_string(),
+            ),
+        ];
+
+        // Add agents properties with prefix
+        for meta in AgentConfig::PROPERTIES {
+            all_properties.push((
+                format!("agents.{}", meta.path),
+                match meta.property_type {
+                    PropertyType::String => "string".to_string(),
+                    PropertyType::Boolean => "boolean".to_string(),
+                },
+                meta.description.to_string(),
+            ));
+        }
+
+        // Add runtimeConfig.apple properties with prefix
+        for meta in AppleRuntimeConfig::PROPERTIES {
+            all_properties.push((
+                format!("runtimeConfig.apple.{}", meta.path),
+                match meta.property_type {
+                    PropertyType::String => "string".to_string(),
+                    PropertyType::Boolean => "boolean".to_string(),
+                },
+                meta.description.to_string(),
+            ));
+        }
+
+        // Add hooks properties with prefix
+        for meta in HooksConfig::PROPERTIES {
+            all_properties.push((
+                format!("hooks.{}", meta.path),
+                match meta.property_type {
+                    PropertyType::String => "string".to_string(),
+                    PropertyType::Boolean => "boolean".to_string(),
+                },
+                meta.description.to_string(),
+            ));
+        }
+
+        if let Some(filter_str) = filter {
+            all_properties
+                .into_iter()
+                .filter(|(path, _, _)| path.contains(filter_str))
+                .collect()
+        } else {
+            all_properties
+        }
+    }
+
+    /// Validates the entire configuration.
+    ///
+    /// Returns an error if any configuration values are invalid.
+    pub fn validate(&self) -> Result<()> {
+        // Validate URLs
+        if let Some(url) = &self.dotfiles_repository {
+            validate_property_value(&PropertyValidator::Url, url)?;
+        }
+        for entry in &self.dotfiles {
+            validate_property_value(&PropertyValidator::Url, &entry.repo)?;
+        }
+        if let Some(url) = self.get_agent_binary_url() {
+            validate_property_value(&PropertyValidator::Url, url)?;
+        }
+        if let Some(url) = self.get_agent_git_repository() {
+            validate_property_value(&PropertyValidator::Url, url)?;
+        }
+        if let Some(url) = self.get_agent_release_url() {
+            validate_property_value(&PropertyValidator::Url, url)?;
+        }
+
+        // Validate runtime
+        validate_property_value(
+            &PropertyValidator::Enum(&["auto", "docker", "podman", "apple"]),
+            &self.runtime,
+        )?;
+
+        // Validate bandwidth limit
+        if let Some(bandwidth) = &self.max_bandwidth {
+            validate_property_value(&PropertyValidator::Bandwidth, bandwidth)?;
+        }
+
+        // Validate download settings
+        if let Some(bandwidth) = &self.download_bandwidth {
+            validate_property_value(&PropertyValidator::Bandwidth, bandwidth)?;
+        }
+        if let Some(windows) = &self.download_windows {
+            validate_property_value(&PropertyValidator::TimeWindows, windows)?;
+        }
+        if let Some(limit) = &self.download_size_limit {
+            validate_property_value(&PropertyValidator::Bandwidth, limit)?;
+        }
+
+        // Validate container limits
+        if let Some(cpus) = &self.container_cpus {
+            validate_property_value(&PropertyValidator::Cpu, cpus)?;
+        }
+        if let Some(memory) = &self.container_memory {
+            validate_property_value(&PropertyValidator::Memory, memory)?;
+        }
+
+        // Validate port lists
+        if let Some(ports) = &self.forward_allow_ports {
+            validate_property_value(&PropertyValidator::Ports, ports)?;
+        }
+        if let Some(ports) = &self.forward_deny_ports {
+            validate_property_value(&PropertyValidator::Ports, ports)?;
+        }
+        if let Some(ports) = &self.forward_host_ports {
+            validate_property_value(&PropertyValidator::Ports, ports)?;
+        }
+        self.get_host_aliases()?;
+        self.get_auto_forward_policy()?;
+
+        // Validate git config allowlist
+        if let Some(keys) = &self.git_config_allowlist {
+            validate_git_config_keys(keys)?;
+        }
+
+        if let Some(registries) = &self.build_secret_registries {
+            validate_registries(registries)?;
+        }
+
+        validate_ca_certificates(&self.ca_certificates)?;
+
+        validate_trusted_keys(&self.trusted_keys)?;
+        if self.signatures_verified() && self.trusted_keys.is_empty() {
+            anyhow::bail!("verifySignatures is enabled, but no trustedKeys are configured");
+        }
+
+        if let Some(mirrors) = &self.registry_mirrors {
+            RegistryMirrors::parse(mirrors)?;
+        }
+
+        if let Some(path) = &self.dockerfile_template
+            && !Path::new(path).is_file()
+        {
+            anyhow::bail!("Dockerfile template '{}' does not exist", path);
+        }
+
+        // Validate control scheme
+        if let Some(scheme) = &self.control_scheme {
+            validate_property_value(&PropertyValidator::Enum(&["tcp", "grpc"]), scheme)?;
+        }
+
+        // Validate runtime config
+        if let Some(rc) = &self.runtime_config
+            && let Some(apple) = &rc.apple
+        {
+            if let Some(mem) = &apple.build_memory {
+                validate_property_value(&PropertyValidator::Memory, mem)?;
+            }
+            if let Some(cpu) = &apple.build_cpu {
+                validate_property_value(&PropertyValidator::Cpu, cpu)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apple_probe() {
+        let probe = apple_probe("container CLI version 0.5.0 (build: release)", true);
+        assert!(probe.usable);
+        assert_eq!(probe.version.as_deref(), Some("0.5.0"));
+
+        assert!(!apple_probe("container CLI version 0.5.0", false).usable);
+        let outdated = apple_probe("container CLI version 0.4.1", true);
+        assert!(!outdated.usable);
+        assert!(outdated.reason.contains("0.5.0 or newer"));
+    }
+
+    #[test]
+    fn test_select_runtime() {
+        let probe = |name, usable| RuntimeProbe {
+            name,
+            version: None,
+            usable,
+            reason: String::new(),
+        };
+        let probes = vec![
+            probe("docker", false),
+            probe("podman", true),
+            probe("apple", true),
+        ];
+        assert_eq!(Config::select_runtime(&probes).unwrap().name, "podman");
+        assert!(Config::select_runtime(&probes[..1]).is_none());
+    }
+
+    #[test]
+    fn test_planned_runtime() {
+        let mut config = Config {
+            runtime: "auto".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.planned_runtime(), "docker");
+        config.runtime = "podman".to_string();
+        assert_eq!(config.planned_runtime(), "podman");
+    }
+
+    #[test]
+    fn test_parse_cli_version() {
+        assert_eq!(
+            parse_cli_version("container CLI version 0.5.0 (build: release, commit: 1a2b3c)"),
+            Some((0, 5, 0))
+        );
+        assert_eq!(parse_cli_version("container v1.2.3-beta"), Some((1, 2, 3)));
+        assert_eq!(parse_cli_version("container version unknown"), None);
+        assert!(
+            parse_cli_version("container CLI version 0.4.1").unwrap() < MIN_APPLE_CONTAINER_VERSION
+        );
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::default();
+        assert!(config.dotfiles_repository.is_none());
+        assert!(config.additional_features.is_empty());
+        assert!(config.env_variables.is_empty());
+    }
+
+    #[test]
+    fn test_config_serialization() {
+        let config = Config {
+            dotfiles_repository: Some("https://github.com/user/dotfiles".to_string()),
             env_variables: vec!["EDITOR=vim".to_string()],
             ..Default::default()
         };
 
-        let yaml = yaml_serde::to_string(&config).unwrap();
-        assert!(yaml.contains("dotfilesRepository"));
-        assert!(yaml.contains("https://github.com/user/dotfiles"));
-        assert!(yaml.contains("envVariables"));
+        let yaml = yaml_serde::to_string(&config).unwrap();
+        assert!(yaml.contains("dotfilesRepository"));
+        assert!(yaml.contains("https://github.com/user/dotfiles"));
+        assert!(yaml.contains("envVariables"));
+    }
+
+    #[test]
+    fn test_config_deserialization() {
+        let yaml = r#"
+dotfilesRepository: https://github.com/user/dotfiles
+additionalFeatures:
+  ghcr.io/devcontainers/features/git:1:
+    version: latest
+envVariables:
+  - EDITOR=vim
+  - LANG=en_US.UTF-8
+"#;
+
+        let config: Config = yaml_serde::from_str(yaml).unwrap();
+        assert_eq!(
+            config.dotfiles_repository,
+            Some("https://github.com/user/dotfiles".to_string())
+        );
+        assert_eq!(config.additional_features.len(), 1);
+        assert_eq!(config.env_variables.len(), 2);
+    }
+
+    #[test]
+    fn test_shell_candidates() {
+        let config = Config::default();
+        assert_eq!(config.shell_candidates(), vec!["zsh", "bash", "sh"]);
+
+        let config = Config {
+            default_shell: Some("bash".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.shell_candidates(), vec!["bash", "zsh", "sh"]);
+
+        let mut config = Config {
+            default_shell: Some("fish".to_string()),
+            ..Default::default()
+        };
+        config
+            .set_value("fallbackShells", "bash, sh".to_string())
+            .unwrap();
+        assert_eq!(config.shell_candidates(), vec!["fish", "bash", "sh"]);
+        assert_eq!(
+            config.get_value("fallbackShells"),
+            Some("bash,sh".to_string())
+        );
+    }
+
+    #[test]
+    fn test_max_bandwidth() {
+        let mut config = Config::default();
+        assert_eq!(config.get_max_bandwidth().unwrap(), None);
+
+        config.set_value("maxBandwidth", "10M".to_string()).unwrap();
+        assert_eq!(config.get_max_bandwidth().unwrap(), Some(10 * 1024 * 1024));
+        assert!(
+            config
+                .set_value("maxBandwidth", "fast".to_string())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_tunnel_compression() {
+        let mut config = Config::default();
+        assert!(config.tunnel_compression_enabled());
+
+        config
+            .set_value("tunnelCompression", "false".to_string())
+            .unwrap();
+        assert!(!config.tunnel_compression_enabled());
+        assert_eq!(
+            config.get_value("tunnelCompression"),
+            Some("false".to_string())
+        );
+        assert!(
+            config
+                .set_value("tunnelCompression", "maybe".to_string())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_forward_port_lists() {
+        let mut config = Config::default();
+        assert!(config.get_forward_allow_ports().unwrap().is_empty());
+        assert!(config.get_forward_deny_ports().unwrap().is_empty());
+
+        config
+            .set_value("forwardAllowPorts", "3000-3999, 8080".to_string())
+            .unwrap();
+        config
+            .set_value("forwardDenyPorts", "3306".to_string())
+            .unwrap();
+        assert_eq!(
+            config.get_forward_allow_ports().unwrap(),
+            vec![3000..=3999, 8080..=8080]
+        );
+        assert_eq!(config.get_forward_deny_ports().unwrap(), vec![3306..=3306]);
+
+        for invalid in ["0", "70000", "4000-3000", "http"] {
+            assert!(
+                config
+                    .set_value("forwardDenyPorts", invalid.to_string())
+                    .is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+
+        config.unset_value("forwardAllowPorts").unwrap();
+        assert!(config.get_forward_allow_ports().unwrap().is_empty());
+
+        assert!(config.get_forward_host_ports().unwrap().is_empty());
+        config
+            .set_value("forwardHostPorts", "5432,11434".to_string())
+            .unwrap();
+        assert_eq!(
+            config.get_forward_host_ports().unwrap(),
+            vec![5432..=5432, 11434..=11434]
+        );
+        assert!(
+            config
+                .set_value("forwardHostPorts", "70000".to_string())
+                .is_err()
+        );
     }
 
     #[test]
-    fn test_config_deserialization() {
+    fn test_registry_cache() {
+        let mut config = Config::default();
+        assert!(!config.registry_cache_enabled());
+        assert_eq!(config.get_registry_cache_port(), 15080);
+
+        config
+            .set_value("registryCache", "true".to_string())
+            .unwrap();
+        config
+            .set_value("registryCachePort", "4873".to_string())
+            .unwrap();
+        assert!(config.registry_cache_enabled());
+        assert_eq!(config.get_registry_cache_port(), 4873);
+        assert_eq!(
+            config.get_value("registryCachePort"),
+            Some("4873".to_string())
+        );
+        for invalid in ["0", "70000", "npm"] {
+            assert!(
+                config
+                    .set_value("registryCachePort", invalid.to_string())
+                    .is_err()
+            );
+        }
+
+        config.unset_value("registryCachePort").unwrap();
+        assert_eq!(config.get_registry_cache_port(), 15080);
+    }
+
+    #[test]
+    fn test_host_aliases() {
+        let mut config = Config::default();
+        assert!(config.get_host_aliases().unwrap().is_empty());
+
+        config
+            .set_value("hostAliases", "db.internal, llm".to_string())
+            .unwrap();
+        assert_eq!(
+            config.get_host_aliases().unwrap(),
+            vec!["db.internal", "llm"]
+        );
+        assert!(
+            config
+                .set_value("hostAliases", "db_internal".to_string())
+                .is_err()
+        );
+        assert!(config.validate().is_ok());
+
+        config.unset_value("hostAliases").unwrap();
+        assert!(config.get_host_aliases().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_auto_forward_policy() {
+        let mut config = Config::default();
+        assert_eq!(
+            config.get_auto_forward_policy().unwrap(),
+            AutoForwardPolicy::default()
+        );
+
+        config
+            .set_value("autoForward", "allowlist".to_string())
+            .unwrap();
+        config
+            .set_value("autoForwardPorts", "3000-3999".to_string())
+            .unwrap();
+        let policy = config.get_auto_forward_policy().unwrap();
+        assert_eq!(policy.mode, AutoForward::Allowlist);
+        assert!(policy.permits(3000));
+        assert!(!policy.permits(9229));
+
+        assert!(config.set_value("autoForward", "some".to_string()).is_err());
+        config.unset_value("autoForward").unwrap();
+        assert!(config.get_auto_forward_policy().unwrap().permits(9229));
+    }
+
+    #[test]
+    fn test_propagate_proxy() {
+        let mut config = Config::default();
+        assert!(config.proxy_propagated());
+
+        config
+            .set_value("propagateProxy", "false".to_string())
+            .unwrap();
+        assert!(!config.proxy_propagated());
+        assert!(
+            config
+                .set_value("propagateProxy", "no".to_string())
+                .is_err()
+        );
+
+        config.unset_value("propagateProxy").unwrap();
+        assert!(config.proxy_propagated());
+    }
+
+    #[test]
+    fn test_build_progress() {
+        let mut config = Config::default();
+        assert_eq!(config.get_build_progress(), "auto");
+
+        config
+            .set_value("buildProgress", "quiet".to_string())
+            .unwrap();
+        assert_eq!(config.get_build_progress(), "quiet");
+        assert!(
+            config
+                .set_value("buildProgress", "verbose".to_string())
+                .is_err()
+        );
+
+        config.unset_value("buildProgress").unwrap();
+        assert_eq!(config.get_build_progress(), "auto");
+    }
+
+    #[test]
+    fn test_offline() {
+        let mut config = Config::default();
+        assert!(!config.is_offline());
+
+        config.set_value("offline", "true".to_string()).unwrap();
+        assert!(config.is_offline());
+        assert_eq!(config.get_value("offline").as_deref(), Some("true"));
+        assert!(config.set_value("offline", "yes".to_string()).is_err());
+
+        config.unset_value("offline").unwrap();
+        assert!(!config.is_offline());
+    }
+
+    #[test]
+    fn test_selinux_relabel() {
+        let mut config = Config::default();
+
+        config
+            .set_value("selinuxRelabel", "true".to_string())
+            .unwrap();
+        assert!(config.selinux_relabeled());
+        assert_eq!(config.get_value("selinuxRelabel").as_deref(), Some("true"));
+
+        config
+            .set_value("selinuxRelabel", "false".to_string())
+            .unwrap();
+        assert!(!config.selinux_relabeled());
+        assert!(
+            config
+                .set_value("selinuxRelabel", "yes".to_string())
+                .is_err()
+        );
+
+        config.unset_value("selinuxRelabel").unwrap();
+        assert_eq!(config.get_value("selinuxRelabel"), None);
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut config = Config::default();
+        assert!(config.notifications_enabled());
+
+        config
+            .set_value("notifications", "false".to_string())
+            .unwrap();
+        assert!(!config.notifications_enabled());
+        assert_eq!(config.get_value("notifications"), Some("false".to_string()));
+
+        config.unset_value("notifications").unwrap();
+        assert!(config.notifications_enabled());
+    }
+
+    #[test]
+    fn test_dotfiles_repositories() {
+        let mut config = Config::default();
+        assert!(config.dotfiles_repositories().is_empty());
+
+        config.dotfiles_repository = Some("https://github.com/me/dotfiles".to_string());
+        config.dotfiles_install_command = Some("setup.sh".to_string());
+        config.dotfiles_branch = Some("work".to_string());
+        config
+            .add_dotfiles(DotfilesRepository {
+                repo: "https://github.com/me/nvim.git".to_string(),
+                install_command: None,
+                target_dir: None,
+                branch: Some("main".to_string()),
+            })
+            .unwrap();
+
+        let repositories = config.dotfiles_repositories();
+        assert_eq!(repositories.len(), 2);
+        assert_eq!(repositories[0].repo, "https://github.com/me/dotfiles");
+        assert_eq!(repositories[0].install_command.as_deref(), Some("setup.sh"));
+        assert_eq!(repositories[0].target_dir.as_deref(), Some("~/.dotfiles"));
+        assert_eq!(repositories[0].branch.as_deref(), Some("work"));
+        assert_eq!(
+            repositories[1].target_dir.as_deref(),
+            Some("~/.dotfiles-nvim")
+        );
+        assert_eq!(repositories[1].branch.as_deref(), Some("main"));
+
+        // Duplicates, also of the old key, and invalid URLs are rejected
+        let duplicate = repositories[0].clone();
+        assert!(config.add_dotfiles(duplicate).is_err());
+        let mut invalid = repositories[1].clone();
+        invalid.repo = "git@github.com:me/zsh".to_string();
+        assert!(config.add_dotfiles(invalid).is_err());
+
+        config
+            .remove_dotfiles("https://github.com/me/dotfiles")
+            .unwrap();
+        assert!(config.dotfiles_repository.is_none());
+        assert_eq!(
+            config.dotfiles_repositories()[0].target_dir.as_deref(),
+            Some("~/.dotfiles")
+        );
+        assert!(config.remove_dotfiles("https://github.com/me/zsh").is_err());
+    }
+
+    #[test]
+    fn test_dotfiles_yaml() {
         let yaml = r#"
-dotfilesRepository: https://github.com/user/dotfiles
-additionalFeatures:
-  ghcr.io/devcontainers/features/git:1:
-    version: latest
-envVariables:
-  - EDITOR=vim
-  - LANG=en_US.UTF-8
+dotfilesRepository: https://github.com/me/dotfiles
+dotfiles:
+  - repo: https://github.com/me/nvim
+    installCommand: install.sh
+    targetDir: ~/.config/nvim
 "#;
-
         let config: Config = yaml_serde::from_str(yaml).unwrap();
+        let repositories = config.dotfiles_repositories();
+        assert_eq!(repositories.len(), 2);
         assert_eq!(
-            config.dotfiles_repository,
-            Some("https://github.com/user/dotfiles".to_string())
+            repositories[1].target_dir.as_deref(),
+            Some("~/.config/nvim")
+        );
+        assert_eq!(
+            repositories[1].install_command.as_deref(),
+            Some("install.sh")
+        );
+    }
+
+    #[test]
+    fn test_dotfiles_settings() {
+        let mut config = Config::default();
+        config
+            .set_value("dotfilesBranch", "work".to_string())
+            .unwrap();
+        config.set_value("dotfilesDepth", "1".to_string()).unwrap();
+        config
+            .set_value("dotfilesTargetDir", "~/dotfiles".to_string())
+            .unwrap();
+        config
+            .set_value("dotfilesRerun", "always".to_string())
+            .unwrap();
+        assert_eq!(config.get_value("dotfilesDepth"), Some("1".to_string()));
+        assert_eq!(config.dotfiles_rerun.as_deref(), Some("always"));
+
+        assert!(config.set_value("dotfilesDepth", "0".to_string()).is_err());
+        assert!(config.set_value("dotfilesDepth", "-1".to_string()).is_err());
+        assert!(
+            config
+                .set_value("dotfilesRerun", "never".to_string())
+                .is_err()
+        );
+
+        config.unset_value("dotfilesBranch").unwrap();
+        assert!(config.dotfiles_branch.is_none());
+    }
+
+    #[test]
+    fn test_background_lifecycle() {
+        let mut config = Config::default();
+        assert!(!config.lifecycle_in_background());
+
+        config
+            .set_value("backgroundLifecycle", "true".to_string())
+            .unwrap();
+        assert!(config.lifecycle_in_background());
+        assert_eq!(
+            config.get_value("backgroundLifecycle"),
+            Some("true".to_string())
+        );
+        assert!(
+            config
+                .set_value("backgroundLifecycle", "yes".to_string())
+                .is_err()
+        );
+
+        config.unset_value("backgroundLifecycle").unwrap();
+        assert!(!config.lifecycle_in_background());
+    }
+
+    #[test]
+    fn test_host_propagation() {
+        let mut config = Config::default();
+        assert!(!config.timezone_propagated());
+        assert!(!config.locale_propagated());
+        assert!(!config.localtime_propagated());
+        assert!(config.get_git_config_allowlist().is_empty());
+
+        config
+            .set_value("propagateTimezone", "true".to_string())
+            .unwrap();
+        config
+            .set_value("propagateLocale", "true".to_string())
+            .unwrap();
+        config
+            .set_value("propagateLocaltime", "true".to_string())
+            .unwrap();
+        config
+            .set_value("gitConfigAllowlist", "user.name, user.email".to_string())
+            .unwrap();
+        assert!(config.timezone_propagated());
+        assert!(config.locale_propagated());
+        assert!(config.localtime_propagated());
+        assert_eq!(
+            config.get_git_config_allowlist(),
+            vec!["user.name".to_string(), "user.email".to_string()]
+        );
+        assert!(config.validate().is_ok());
+
+        assert!(
+            config
+                .set_value("gitConfigAllowlist", "user".to_string())
+                .is_err()
+        );
+        assert!(
+            config
+                .set_value("gitConfigAllowlist", "user.name;rm".to_string())
+                .is_err()
+        );
+
+        config.unset_value("propagateTimezone").unwrap();
+        config.unset_value("gitConfigAllowlist").unwrap();
+        assert!(!config.timezone_propagated());
+        assert!(config.get_git_config_allowlist().is_empty());
+    }
+
+    #[test]
+    fn test_build_settings() {
+        let mut config = Config::default();
+        assert!(config.buildkit_enabled());
+        assert!(config.get_build_cache_from().is_empty());
+
+        config
+            .set_value(
+                "buildCacheFrom",
+                "ghcr.io/acme/cache:app, ghcr.io/acme/cache:base".to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            config.get_build_cache_from(),
+            vec!["ghcr.io/acme/cache:app", "ghcr.io/acme/cache:base"]
+        );
+        config
+            .set_value(
+                "buildSecretRegistries",
+                "ghcr.io,registry.acme.dev".to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            config.get_build_secret_registries(),
+            vec!["ghcr.io", "registry.acme.dev"]
+        );
+        assert!(
+            config
+                .set_value("buildSecretRegistries", "ghcr.io/acme".to_string())
+                .is_err()
+        );
+        config.set_value("buildkit", "false".to_string()).unwrap();
+        assert!(!config.buildkit_enabled());
+
+        assert!(config.vscode_customizations_baked());
+        config
+            .set_value("bakeVscodeCustomizations", "false".to_string())
+            .unwrap();
+        assert!(!config.vscode_customizations_baked());
+        config.unset_value("bakeVscodeCustomizations").unwrap();
+        assert!(config.vscode_customizations_baked());
+    }
+
+    #[test]
+    fn test_ca_certificates() {
+        let temp = tempfile::tempdir().unwrap();
+        let cert = temp.path().join("corp.pem");
+        fs::write(
+            &cert,
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+        let not_a_cert = temp.path().join("notes.txt");
+        fs::write(&not_a_cert, "hello").unwrap();
+
+        let mut config = Config::default();
+        assert!(config.get_ca_certificates().is_empty());
+
+        let value = cert.to_string_lossy().to_string();
+        config.set_value("caCertificates", value.clone()).unwrap();
+        assert_eq!(config.get_ca_certificates(), vec![cert.clone()]);
+        assert_eq!(config.get_value("caCertificates"), Some(value));
+        assert!(config.validate().is_ok());
+
+        assert!(
+            config
+                .set_value("caCertificates", not_a_cert.to_string_lossy().to_string())
+                .is_err()
+        );
+        assert!(
+            config
+                .set_value("caCertificates", "/does/not/exist.pem".to_string())
+                .is_err()
+        );
+
+        config.unset_value("caCertificates").unwrap();
+        assert!(config.get_ca_certificates().is_empty());
+    }
+
+    #[test]
+    fn test_trusted_keys() {
+        let temp = tempfile::tempdir().unwrap();
+        let key = temp.path().join("cosign.pub");
+        fs::write(
+            &key,
+            crate::driver::oci::mock::SigningKey::generate().public_key_pem(),
+        )
+        .unwrap();
+        let cert = temp.path().join("corp.pem");
+        fs::write(
+            &cert,
+            "-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        assert!(!config.signatures_verified());
+        config
+            .set_value("verifySignatures", "true".to_string())
+            .unwrap();
+        assert!(config.signatures_verified());
+        // Verification needs a key to verify with
+        assert!(config.validate().is_err());
+
+        let value = key.to_string_lossy().to_string();
+        config.set_value("trustedKeys", value.clone()).unwrap();
+        assert_eq!(config.get_trusted_keys(), vec![key.clone()]);
+        assert_eq!(config.get_value("trustedKeys"), Some(value));
+        assert!(config.validate().is_ok());
+
+        assert!(
+            config
+                .set_value("trustedKeys", cert.to_string_lossy().to_string())
+                .is_err()
+        );
+        assert!(
+            config
+                .set_value("verifySignatures", "yes".to_string())
+                .is_err()
+        );
+
+        config.unset_value("trustedKeys").unwrap();
+        config.unset_value("verifySignatures").unwrap();
+        assert!(config.get_trusted_keys().is_empty());
+        assert!(!config.signatures_verified());
+    }
+
+    #[test]
+    fn test_registry_mirrors() {
+        let mut config = Config::default();
+        let value = "ghcr.io=artifactory.corp/ghcr-remote,docker.io=mirror.corp".to_string();
+        config.set_value("registryMirrors", value.clone()).unwrap();
+        assert_eq!(config.get_value("registryMirrors"), Some(value));
+        assert!(config.validate().is_ok());
+
+        assert!(
+            config
+                .set_value("registryMirrors", "ghcr.io".to_string())
+                .is_err()
+        );
+
+        config.unset_value("registryMirrors").unwrap();
+        assert_eq!(config.get_value("registryMirrors"), None);
+    }
+
+    #[test]
+    fn test_feature_cache() {
+        let mut config = Config::default();
+        assert!(config.feature_cache_enabled());
+
+        config
+            .set_value("featureCache", "false".to_string())
+            .unwrap();
+        assert!(!config.feature_cache_enabled());
+        assert_eq!(config.get_value("featureCache"), Some("false".to_string()));
+
+        config.unset_value("featureCache").unwrap();
+        assert!(config.feature_cache_enabled());
+    }
+
+    #[test]
+    fn test_forward_docker_socket() {
+        let mut config = Config::default();
+        assert!(!config.docker_socket_forwarded());
+
+        config
+            .set_value("forwardDockerSocket", "true".to_string())
+            .unwrap();
+        assert!(config.docker_socket_forwarded());
+        assert_eq!(
+            config.get_value("forwardDockerSocket"),
+            Some("true".to_string())
+        );
+
+        config.unset_value("forwardDockerSocket").unwrap();
+        assert!(!config.docker_socket_forwarded());
+    }
+
+    #[test]
+    fn test_control_scheme() {
+        let mut config = Config::default();
+        assert_eq!(config.get_control_scheme(), "tcp");
+
+        config
+            .set_value("controlScheme", "grpc".to_string())
+            .unwrap();
+        assert_eq!(config.get_control_scheme(), "grpc");
+        assert!(
+            config
+                .set_value("controlScheme", "http".to_string())
+                .is_err()
+        );
+
+        config.unset_value("controlScheme").unwrap();
+        assert_eq!(config.get_control_scheme(), "tcp");
+    }
+
+    #[test]
+    fn test_download_settings() {
+        let mut config = Config::default();
+
+        config
+            .set_value("downloadWindows", "22:00-6:00, 12:00-13:00".to_string())
+            .unwrap();
+        assert_eq!(
+            config.get_value("downloadWindows"),
+            Some("22:00-06:00,12:00-13:00".to_string())
+        );
+        assert!(
+            config
+                .set_value("downloadWindows", "tonight".to_string())
+                .is_err()
+        );
+        assert!(
+            config
+                .set_value("downloadBandwidth", "0".to_string())
+                .is_err()
+        );
+
+        config
+            .set_value("downloadBandwidth", "1M".to_string())
+            .unwrap();
+        let policy = crate::download::DownloadPolicy::from_config(&config).unwrap();
+        assert_eq!(policy.bandwidth, Some(1024 * 1024));
+        assert_eq!(policy.windows.len(), 2);
+        assert_eq!(policy.retries, crate::download::DEFAULT_RETRIES);
+
+        config
+            .set_value("downloadRetries", "2".to_string())
+            .unwrap();
+        assert_eq!(config.get_value("downloadRetries").as_deref(), Some("2"));
+        assert_eq!(config.get_download_retries(), 2);
+        assert!(
+            config
+                .set_value("downloadRetries", "-1".to_string())
+                .is_err()
+        );
+        config.unset_value("downloadRetries").unwrap();
+        assert_eq!(config.get_download_retries(), 5);
+
+        config
+            .set_value("downloadSizeLimit", "512M".to_string())
+            .unwrap();
+        let policy = crate::download::DownloadPolicy::from_config(&config).unwrap();
+        assert_eq!(policy.max_size, Some(512 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_open_allowlist() {
+        let mut config = Config::default();
+        assert!(config.get_open_allowlist().is_empty());
+
+        config
+            .set_value("openAllowlist", "/opt/shared, /srv/data".to_string())
+            .unwrap();
+        assert_eq!(
+            config.get_open_allowlist(),
+            vec![PathBuf::from("/opt/shared"), PathBuf::from("/srv/data")]
+        );
+        assert!(
+            config
+                .set_value("openAllowlist", "relative/dir".to_string())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_project_roots() {
+        let mut config = Config::default();
+        assert!(config.get_project_roots().is_empty());
+
+        config
+            .set_value("projectRoots", "~/src, /srv/projects".to_string())
+            .unwrap();
+        let home = dirs::home_dir().unwrap_or_default();
+        assert_eq!(
+            config.get_project_roots(),
+            vec![home.join("src"), PathBuf::from("/srv/projects")]
+        );
+        assert!(config.set_value("projectRoots", "src".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let config = Config::from_yaml("runtime: podman\nagentGitBranch: dev\n").unwrap();
+        assert_eq!(config.runtime, "podman");
+        assert_eq!(config.get_value("agents.gitBranch").as_deref(), Some("dev"));
+
+        let error = Config::from_yaml("runtime: podman\noffline: maybe\n").unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+        assert!(Config::from_yaml("version: 99\n").is_err());
+    }
+
+    #[test]
+    fn test_recent_paths() {
+        let mut config = Config::default();
+        config.set_value("recentLimit", "2".to_string()).unwrap();
+        assert_eq!(config.get_recent_limit(), 2);
+
+        config.record_recent("/src/a", 1);
+        config.recent_paths[0].pinned = true;
+        config.recent_paths[0].label = Some("main".to_string());
+        config.record_recent("/src/b", 2);
+        config.record_recent("/src/c", 3);
+        config.record_recent("/src/d", 4);
+        let paths: Vec<&str> = config
+            .recent_paths
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["/src/d", "/src/c", "/src/a"]);
+
+        // Reopening keeps the pin and label
+        config.record_recent("/src/a", 5);
+        assert_eq!(config.recent_paths[0].path, "/src/a");
+        assert!(config.recent_paths[0].pinned);
+        assert_eq!(config.recent_paths[0].last_opened, Some(5));
+
+        assert!(!config.add_recent("/src/c"));
+        assert_eq!(config.find_recent("main"), Some(0));
+        assert_eq!(config.find_recent("2"), Some(1));
+        assert_eq!(config.find_recent("/src/c"), Some(2));
+        assert_eq!(config.find_recent("4"), None);
+
+        config.set_value("recentLimit", "0".to_string()).unwrap();
+        assert_eq!(config.recent_paths.len(), 1);
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut config = Config::default();
+        assert_eq!(config.get_value("hooks.postUp"), None);
+
+        config
+            .set_value(
+                "hooks.postUp",
+                "tmux new -d -s \"$DEVCON_WORKSPACE\"".to_string(),
+            )
+            .unwrap();
+        assert_eq!(
+            config.get_value("hooks.postUp").as_deref(),
+            Some("tmux new -d -s \"$DEVCON_WORKSPACE\"")
+        );
+        assert!(config.set_value("hooks.preUp", String::new()).is_err());
+        assert!(
+            config
+                .set_value("hooks.unknown", "true".to_string())
+                .is_err()
+        );
+
+        config.unset_value("hooks.postUp").unwrap();
+        assert_eq!(config.get_value("hooks.postUp"), None);
+        assert!(
+            Config::list_properties(Some("hooks."))
+                .iter()
+                .any(|(path, _, _)| path == "hooks.portForwarded")
+        );
+    }
+
+    #[test]
+    fn test_container_limits() {
+        let mut config = Config::default();
+        config
+            .set_value("containerCpus", "1.5".to_string())
+            .unwrap();
+        config
+            .set_value("containerMemory", "8192".to_string())
+            .unwrap();
+        assert_eq!(config.get_value("containerCpus"), Some("1.5".to_string()));
+        assert_eq!(
+            config.get_value("containerMemory"),
+            Some("8192m".to_string())
+        );
+        assert!(
+            config
+                .set_value("containerCpus", "many".to_string())
+                .is_err()
+        );
+
+        config.unset_value("containerMemory").unwrap();
+        assert_eq!(config.get_value("containerMemory"), None);
+    }
+
+    #[test]
+    fn test_add_and_remove_feature() {
+        let mut config = Config::default();
+        config
+            .add_feature(
+                "ghcr.io/devcontainers/features/node:1",
+                serde_json::json!({"version": "lts"}),
+            )
+            .unwrap();
+        assert_eq!(
+            config.additional_features["ghcr.io/devcontainers/features/node:1"]["version"],
+            "lts"
+        );
+
+        assert!(
+            config
+                .add_feature("docker.io/owner/repo/feature:1", serde_json::json!({}))
+                .is_err()
+        );
+
+        config
+            .remove_feature("ghcr.io/devcontainers/features/node:1")
+            .unwrap();
+        assert!(config.additional_features.is_empty());
+        assert!(
+            config
+                .remove_feature("ghcr.io/devcontainers/features/node:1")
+                .is_err()
         );
-        assert_eq!(config.additional_features.len(), 1);
-        assert_eq!(config.env_variables.len(), 2);
     }
 
     #[test]
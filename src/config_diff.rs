@@ -0,0 +1,229 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Config Diff
+//!
+//! Renders the change of a config file as a unified diff, e.g. before
+//! `devcon config set` saves it. Files are compared line by line along their
+//! longest common subsequence, which is fast enough for config files of a
+//! few hundred lines.
+
+use console::Style;
+
+/// A line of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    /// A line present in both files
+    Same(&'a str),
+    /// A line only present in the old file
+    Removed(&'a str),
+    /// A line only present in the new file
+    Added(&'a str),
+}
+
+/// Compares two texts line by line.
+pub fn diff_lines<'a>(before: &'a str, after: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = before.lines().collect();
+    let new: Vec<&str> = after.lines().collect();
+
+    // lengths[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    lines
+}
+
+/// Renders the unified diff of two texts with `context` unchanged lines
+/// around each change, or nothing if they are equal.
+///
+/// # Arguments
+///
+/// * `before` - The old text
+/// * `after` - The new text
+/// * `name` - Name of the file in the `---` and `+++` headers
+/// * `context` - Number of unchanged lines shown around changes
+pub fn unified_diff(before: &str, after: &str, name: &str, context: usize) -> Vec<String> {
+    let lines = diff_lines(before, after);
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return Vec::new();
+    }
+
+    // Group changes whose context overlaps into hunks of line ranges
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = vec![format!("--- a/{}", name), format!("+++ b/{}", name)];
+    for (start, end) in hunks {
+        // Line numbers of both files at the start of the hunk, counted from 1
+        let old_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        output.push(format!(
+            "@@ -{},{} +{},{} @@",
+            if old_count == 0 {
+                old_start - 1
+            } else {
+                old_start
+            },
+            old_count,
+            if new_count == 0 {
+                new_start - 1
+            } else {
+                new_start
+            },
+            new_count
+        ));
+        output.extend(hunk.iter().map(|line| match line {
+            DiffLine::Same(text) => format!(" {}", text),
+            DiffLine::Removed(text) => format!("-{}", text),
+            DiffLine::Added(text) => format!("+{}", text),
+        }));
+    }
+    output
+}
+
+/// Prints the lines of a unified diff, colored if the terminal supports it.
+pub fn print_diff(lines: &[String]) {
+    let header = Style::new().bold();
+    let hunk = Style::new().cyan();
+    let removed = Style::new().red();
+    let added = Style::new().green();
+    for line in lines {
+        let style = if line.starts_with("---") || line.starts_with("+++") {
+            &header
+        } else if line.starts_with("@@") {
+            &hunk
+        } else if line.starts_with('-') {
+            &removed
+        } else if line.starts_with('+') {
+            &added
+        } else {
+            println!("{}", line);
+            continue;
+        };
+        println!("{}", style.apply_to(line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let lines = diff_lines("a\nb\nc\n", "a\nx\nc\nd\n");
+        assert_eq!(
+            lines,
+            vec![
+                DiffLine::Same("a"),
+                DiffLine::Removed("b"),
+                DiffLine::Added("x"),
+                DiffLine::Same("c"),
+                DiffLine::Added("d"),
+            ]
+        );
+        assert!(unified_diff("a\n", "a\n", "config.yaml", 3).is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let before: String = (1..=20).map(|n| format!("line{}\n", n)).collect();
+        let after = before
+            .replace("line2\n", "line2\nnew\n")
+            .replace("line18\n", "");
+
+        let diff = unified_diff(&before, &after, "config.yaml", 1);
+        assert_eq!(
+            diff,
+            vec![
+                "--- a/config.yaml",
+                "+++ b/config.yaml",
+                "@@ -2,2 +2,3 @@",
+                " line2",
+                "+new",
+                " line3",
+                "@@ -17,3 +18,2 @@",
+                " line17",
+                "-line18",
+                " line19",
+            ]
+        );
+
+        // A new file is a single hunk of additions
+        assert_eq!(
+            unified_diff("", "a\n", "config.yaml", 3)[2..],
+            ["@@ -0,0 +1,1 @@", "+a"]
+        );
+    }
+}
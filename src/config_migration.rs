@@ -0,0 +1,190 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Config Migration
+//!
+//! The config file carries a `version`. When devcon changes the layout of the
+//! file, it adds a migration step here instead of rejecting old files: on
+//! load, the steps from the version of the file up to [`CONFIG_VERSION`] are
+//! applied to the YAML document before it is parsed. Files without `version`
+//! are version 0.
+//!
+//! Every step returns a description of its transformations, which `devcon
+//! config migrate --dry-run` shows without changing the file.
+
+use std::path::{Path, PathBuf};
+
+use yaml_serde::{Mapping, Value};
+
+/// Version of the config layout written by this release.
+pub const CONFIG_VERSION: u64 = 1;
+
+/// Migration of a config document to version `to` from the version before.
+struct Migration {
+    to: u64,
+    migrate: fn(&mut Mapping) -> Vec<String>,
+}
+
+/// All migration steps, in version order.
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 1,
+    migrate: migrate_to_v1,
+}];
+
+/// Returns the version of a config document, 0 if it has none.
+pub fn version(document: &Value) -> u64 {
+    document
+        .get("version")
+        .and_then(Value::as_u64)
+        .unwrap_or_default()
+}
+
+/// Migrates a config document to [`CONFIG_VERSION`].
+///
+/// Returns the transformations applied, empty if the document is current.
+/// Documents of newer versions are left unchanged.
+pub fn migrate(document: &mut Value) -> Vec<String> {
+    if document.is_null() {
+        *document = Value::Mapping(Mapping::new());
+    }
+    let from = version(document);
+    let Some(mapping) = document.as_mapping_mut() else {
+        return Vec::new();
+    };
+
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.to > from) {
+        changes.extend((migration.migrate)(mapping));
+        mapping.insert("version".into(), migration.to.into());
+        changes.push(format!("version: {}", migration.to));
+    }
+    changes
+}
+
+/// Path of the backup of a config file taken before migrating it from
+/// version `from`, e.g. `config.yaml.v0.bak`.
+pub fn backup_path(config_path: &Path, from: u64) -> PathBuf {
+    let mut name = config_path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", from));
+    config_path.with_file_name(name)
+}
+
+/// Version 1 moves the `agent*` keys into the `agents` section and turns
+/// plain `recentPaths` entries into objects.
+fn migrate_to_v1(config: &mut Mapping) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    const AGENT_KEYS: [(&str, &str); 4] = [
+        ("agentBinaryUrl", "binaryUrl"),
+        ("agentGitRepository", "gitRepository"),
+        ("agentGitBranch", "gitBranch"),
+        ("agentDisable", "disable"),
+    ];
+    for (old, new) in AGENT_KEYS {
+        let Some(value) = config.remove(old) else {
+            continue;
+        };
+        let agents = config
+            .entry("agents".into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Some(agents) = agents.as_mapping_mut()
+            && !agents.contains_key(new)
+        {
+            agents.insert(new.into(), value);
+            changes.push(format!("{} -> agents.{}", old, new));
+        } else {
+            changes.push(format!("{} removed, agents.{} is already set", old, new));
+        }
+    }
+
+    if let Some(Value::Sequence(entries)) = config.get_mut("recentPaths") {
+        let mut converted = 0;
+        for entry in entries.iter_mut() {
+            if let Value::String(path) = entry {
+                let mut object = Mapping::new();
+                object.insert("path".into(), Value::String(std::mem::take(path)));
+                *entry = Value::Mapping(object);
+                converted += 1;
+            }
+        }
+        if converted > 0 {
+            changes.push(format!("recentPaths: {} path(s) -> entries", converted));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_to_v1() {
+        let mut document: Value = yaml_serde::from_str(
+            "agentBinaryUrl: https://example.com/agent\nagentDisable: true\nagents:\n  disable: false\nrecentPaths:\n  - /src/app\n  - path: /src/api\n    pinned: true\n",
+        )
+        .unwrap();
+
+        let changes = migrate(&mut document);
+        assert_eq!(
+            changes,
+            vec![
+                "agentBinaryUrl -> agents.binaryUrl",
+                "agentDisable removed, agents.disable is already set",
+                "recentPaths: 1 path(s) -> entries",
+                "version: 1",
+            ]
+        );
+        assert_eq!(version(&document), 1);
+        assert_eq!(
+            document["agents"]["binaryUrl"].as_str(),
+            Some("https://example.com/agent")
+        );
+        assert_eq!(document["agents"]["disable"].as_bool(), Some(false));
+        assert_eq!(
+            document["recentPaths"][0]["path"].as_str(),
+            Some("/src/app")
+        );
+        assert!(document.get("agentBinaryUrl").is_none());
+
+        // A current document stays as it is
+        assert!(migrate(&mut document).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_empty_and_newer() {
+        let mut empty = Value::Null;
+        assert_eq!(migrate(&mut empty), vec!["version: 1"]);
+
+        let mut newer: Value = yaml_serde::from_str("version: 99\nfoo: bar\n").unwrap();
+        assert!(migrate(&mut newer).is_empty());
+    }
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            backup_path(Path::new("/home/me/.config/devcon/config.yaml"), 0),
+            PathBuf::from("/home/me/.config/devcon/config.yaml.v0.bak")
+        );
+    }
+}
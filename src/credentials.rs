@@ -0,0 +1,142 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Credentials
+//!
+//! Registry credentials are kept in the OS keychain (macOS Keychain, the
+//! Secret Service/libsecret on Linux, Windows Credential Manager) instead of
+//! the plaintext config file. Every registry is one entry of the `devcon`
+//! service, holding the username and secret as JSON.
+//!
+//! Credentials are managed with `devcon login <registry>` and
+//! `devcon logout <registry>`.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Keychain service name of all devcon entries.
+const SERVICE: &str = "devcon";
+
+/// Username and secret (password or token) for a registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credential {
+    pub username: String,
+    pub secret: String,
+}
+
+/// Normalizes a registry reference to its host, e.g. `https://GHCR.io/` to `ghcr.io`.
+///
+/// # Errors
+///
+/// Returns an error if the reference is empty or contains a path.
+pub fn normalize_registry(registry: &str) -> Result<String> {
+    let host = registry.trim();
+    let host = host
+        .strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host)
+        .trim_end_matches('/')
+        .to_ascii_lowercase();
+
+    if host.is_empty() {
+        bail!("Registry must not be empty");
+    }
+    if host.contains('/') {
+        bail!("Registry '{}' must be a host name, e.g. ghcr.io", registry);
+    }
+    Ok(host)
+}
+
+/// Stores the credential of a registry in the keychain, replacing any previous one.
+///
+/// # Errors
+///
+/// Returns an error if the registry is invalid or the keychain is unavailable.
+pub fn store(registry: &str, credential: &Credential) -> Result<()> {
+    entry(registry)?
+        .set_password(&serde_json::to_string(credential)?)
+        .context("Failed to store credentials in the system keychain")
+}
+
+/// Loads the credential of a registry, `None` if none is stored.
+///
+/// # Errors
+///
+/// Returns an error if the registry is invalid, the keychain is unavailable
+/// or the entry is not a devcon credential.
+pub fn load(registry: &str) -> Result<Option<Credential>> {
+    match entry(registry)?.get_password() {
+        Ok(value) => Ok(Some(
+            serde_json::from_str(&value).context("Invalid credential in the system keychain")?,
+        )),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).context("Failed to read credentials from the system keychain"),
+    }
+}
+
+/// Removes the credential of a registry. Returns whether one was stored.
+///
+/// # Errors
+///
+/// Returns an error if the registry is invalid or the keychain is unavailable.
+pub fn delete(registry: &str) -> Result<bool> {
+    match entry(registry)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context("Failed to remove credentials from the system keychain"),
+    }
+}
+
+fn entry(registry: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, &normalize_registry(registry)?)
+        .context("Failed to access the system keychain")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_registry() {
+        assert_eq!(normalize_registry("ghcr.io").unwrap(), "ghcr.io");
+        assert_eq!(normalize_registry(" https://GHCR.io/ ").unwrap(), "ghcr.io");
+        assert_eq!(
+            normalize_registry("registry.example.com:5000").unwrap(),
+            "registry.example.com:5000"
+        );
+        assert!(normalize_registry("").is_err());
+        assert!(normalize_registry("ghcr.io/devcontainers").is_err());
+    }
+
+    #[test]
+    fn test_credential_serialization() {
+        let credential = Credential {
+            username: "octocat".to_string(),
+            secret: "ghp_\"quoted\"".to_string(),
+        };
+        let json = serde_json::to_string(&credential).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Credential>(&json).unwrap(),
+            credential
+        );
+    }
+}
@@ -48,11 +48,11 @@
 
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::bail;
-use serde::Deserialize;
 use serde::de;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 /// Represents a lifecycle command that can be a string, array, or object.
@@ -61,7 +61,7 @@ use serde_json::Value;
 /// - String: A single command to execute
 /// - Array: Multiple commands to execute in sequence
 /// - Object: Named commands with their execution strings
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum LifecycleCommand {
     /// Single command as a string
@@ -73,7 +73,7 @@ pub enum LifecycleCommand {
 }
 
 /// Represents a value in a lifecycle command object that can be a string or array
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum LifecycleCommandValue {
     String(String),
@@ -119,6 +119,10 @@ pub struct PortAttributes {
     pub label: Option<String>,
     pub require_local_port: Option<bool>,
     pub protocol: Option<PortProtocol>,
+    /// Bandwidth limit for tunnels of the port (e.g., "512K" or "10M" bytes per second)
+    pub max_bandwidth: Option<String>,
+    /// Set to false to never compress tunnels of the port (e.g., for already compressed protocols)
+    pub compress: Option<bool>,
 }
 
 /// Action to take when a port is auto-forwarded
@@ -142,7 +146,7 @@ pub enum PortProtocol {
 }
 
 /// Mount configuration
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Mount {
     /// String format for mount
@@ -152,17 +156,18 @@ pub enum Mount {
 }
 
 /// Structured mount configuration
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StructuredMount {
     #[serde(rename = "type")]
     pub mount_type: MountType,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
     pub target: String,
 }
 
 /// Type of mount
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MountType {
     Bind,
@@ -213,7 +218,6 @@ pub enum ComposeFile {
 /// Application port configuration
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
-#[allow(dead_code)]
 pub enum AppPort {
     Single(AppPortValue),
     Multiple(Vec<AppPortValue>),
@@ -222,7 +226,6 @@ pub enum AppPort {
 /// Individual app port value
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
-#[allow(dead_code)]
 pub enum AppPortValue {
     Port(u16),
     Mapping(String),
@@ -267,6 +270,19 @@ pub enum WaitFor {
     PostStart,
 }
 
+impl WaitFor {
+    /// Name of the lifecycle command to wait for, e.g. `postCreateCommand`.
+    pub fn hook_name(&self) -> &'static str {
+        match self {
+            WaitFor::Initialize => "initializeCommand",
+            WaitFor::OnCreate => "onCreateCommand",
+            WaitFor::UpdateContent => "updateContentCommand",
+            WaitFor::PostCreate => "postCreateCommand",
+            WaitFor::PostStart => "postStartCommand",
+        }
+    }
+}
+
 /// Host requirements
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -601,6 +617,26 @@ where
 }
 
 impl Devcontainer {
+    /// Returns the ports of the legacy `appPort` property, which are published
+    /// when the container starts rather than forwarded by the agent. Plain
+    /// ports are published on the same port of the host's loopback interface.
+    pub fn published_ports(&self) -> Vec<ForwardPort> {
+        let values = match &self.app_port {
+            None => return Vec::new(),
+            Some(AppPort::Single(value)) => std::slice::from_ref(value),
+            Some(AppPort::Multiple(values)) => values.as_slice(),
+        };
+        values
+            .iter()
+            .map(|value| match value {
+                AppPortValue::Port(port) => {
+                    ForwardPort::HostPort(format!("127.0.0.1:{}:{}", port, port))
+                }
+                AppPortValue::Mapping(mapping) => ForwardPort::HostPort(mapping.clone()),
+            })
+            .collect()
+    }
+
     /// Merges additional features from configuration into this devcontainer.
     ///
     /// This method adds features from the config that aren't already present
@@ -627,6 +663,7 @@ impl Devcontainer {
                     registry.owner, registry.repository, registry.name, registry.version
                 ),
                 FeatureSource::Local { path } => path.to_string_lossy().to_string(),
+                FeatureSource::Tarball { url } => url.clone(),
             })
             .collect();
 
@@ -642,80 +679,287 @@ impl Devcontainer {
 
         Ok(return_features)
     }
+
+    /// Loads the devcontainer definition of a project directory, optionally
+    /// a named configuration of a multi-config project.
+    ///
+    /// Without a `name` in the definition, the directory name is used,
+    /// suffixed with the configuration name if one is selected. Definitions
+    /// extending a base definition are merged with it, see
+    /// [`extended_definition`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the definition or one of its bases cannot be
+    /// found, read or parsed.
+    pub fn load(path: &Path, config: Option<&str>) -> anyhow::Result<Self> {
+        let final_path = find_devcontainer_file(path, config)?;
+
+        let mut chain = Vec::new();
+        let definition = extended_definition(&final_path, &mut chain)?;
+
+        let result = serde_json::from_value::<Self>(definition);
+        if result.is_err() {
+            bail!("Devcontainer content could not be parsed")
+        }
+
+        // Fix name of container if not present
+        let mut result = result?;
+        if let Some(dir) = final_path.parent() {
+            result.features = result
+                .features
+                .into_iter()
+                .map(|feature| feature.relative_to(dir))
+                .collect();
+        }
+        if result.name.is_none() {
+            let name = fs::canonicalize(path)?
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid path for devcontainer"))?
+                .to_string_lossy()
+                .to_string();
+            result.name = Some(match config {
+                Some(config) => format!("{}-{}", name, config),
+                None => name,
+            });
+        }
+
+        Ok(result)
+    }
 }
 
-impl TryFrom<PathBuf> for Devcontainer {
-    type Error = anyhow::Error;
+/// Reads a definition file and merges it over the definition it extends.
+///
+/// The base is named by a top-level `"extends"` property or by
+/// `customizations.devcon.extends`, as a path relative to the file. A folder
+/// path uses the definition found in it like [`find_devcontainer_file`].
+/// Bases can extend further definitions; objects are merged recursively and
+/// every other value of the extending file replaces the one of its base.
+/// Relative local feature paths of a base stay relative to the base.
+///
+/// The canonical paths of all files read are appended to `chain`, starting
+/// with `path`.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or parsed, a base is an OCI
+/// template reference, or the definitions extend each other in a cycle.
+pub fn extended_definition(path: &Path, chain: &mut Vec<PathBuf>) -> anyhow::Result<Value> {
+    let file = fs::canonicalize(path).map_err(|_| {
+        anyhow::anyhow!(
+            "Devcontainer definition cannot be read {}",
+            path.to_string_lossy()
+        )
+    })?;
+    if chain.contains(&file) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .chain([&file])
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        bail!(
+            "Devcontainer definitions extend each other: {}",
+            cycle.join(" -> ")
+        );
+    }
+    chain.push(file.clone());
+
+    let Ok(mut content) = fs::read_to_string(&file) else {
+        bail!(
+            "Devcontainer definition cannot be read {}",
+            file.to_string_lossy()
+        )
+    };
+    json_strip_comments::strip(&mut content)?;
+    let mut definition: Value = serde_json::from_str(&content).map_err(|e| {
+        anyhow::anyhow!(
+            "Devcontainer content of {} could not be parsed: {}",
+            file.to_string_lossy(),
+            e
+        )
+    })?;
+
+    let Some(base) = take_extends(&mut definition) else {
+        return Ok(definition);
+    };
+    let dir = file.parent().unwrap_or(Path::new("/"));
+    if is_template_reference(&base) {
+        bail!(
+            "Devcontainer definition {} extends the OCI template {}, only local paths are supported",
+            file.to_string_lossy(),
+            base
+        );
+    }
+    let mut base_path = dir.join(&base);
+    if base_path.is_dir() {
+        base_path = find_devcontainer_file(&base_path, None)?;
+    }
+    let mut merged = extended_definition(&base_path, chain)?;
+    if let Some(base_dir) = fs::canonicalize(&base_path)?.parent() {
+        anchor_local_features(&mut merged, base_dir);
+    }
+    merge_definition(&mut merged, definition);
+    Ok(merged)
+}
 
-    fn try_from(path: PathBuf) -> std::result::Result<Self, Self::Error> {
-        // Check locations in order of precedence per devcontainer spec:
-        // 1. .devcontainer/devcontainer.json
-        // 2. .devcontainer.json
-        // 3. .devcontainer/<folder>/devcontainer.json (one level deep)
+/// Removes the `extends` property of a definition, returning its value
+fn take_extends(definition: &mut Value) -> Option<String> {
+    let top_level = definition
+        .as_object_mut()
+        .and_then(|object| object.remove("extends"));
+    let customization = definition
+        .pointer_mut("/customizations/devcon")
+        .and_then(Value::as_object_mut)
+        .and_then(|object| object.remove("extends"));
+    top_level
+        .or(customization)
+        .and_then(|value| value.as_str().map(str::to_string))
+}
 
-        let primary_paths = vec![
-            path.join(".devcontainer").join("devcontainer.json"),
-            path.join("devcontainer.json"),
-        ];
-
-        // Find the first existing path from primary locations
-        let mut final_path = None;
-        for p in primary_paths {
-            if fs::exists(&p).unwrap_or(false) {
-                final_path = Some(p);
-                break;
+/// Whether an `extends` value names an OCI template, e.g.
+/// `ghcr.io/devcontainers/templates/rust:1`, rather than a path
+fn is_template_reference(reference: &str) -> bool {
+    !reference.starts_with(['.', '/', '~'])
+        && reference
+            .split('/')
+            .next()
+            .is_some_and(|host| host.contains('.') || host.contains(':'))
+        && reference.contains('/')
+}
+
+/// Resolves relative local feature paths of a base definition against its
+/// folder, as they would be resolved if the base was used on its own
+fn anchor_local_features(definition: &mut Value, dir: &Path) {
+    let Some(features) = definition
+        .get_mut("features")
+        .and_then(Value::as_object_mut)
+    else {
+        return;
+    };
+    let anchored: serde_json::Map<String, Value> = std::mem::take(features)
+        .into_iter()
+        .map(|(reference, options)| {
+            // Mirrors the local feature detection of `parse_feature`
+            let (prefix, path) = match reference
+                .strip_prefix("file://")
+                .map(|path| ("file://", path))
+                .or(reference.strip_prefix("file:").map(|path| ("file:", path)))
+            {
+                Some(local) => local,
+                None if !reference.starts_with("ghcr.io") && !reference.contains(':') => {
+                    ("", reference.as_str())
+                }
+                None => return (reference, options),
+            };
+            if Path::new(path).is_absolute() {
+                return (reference, options);
             }
-        }
+            let path = dir.join(path).to_string_lossy().to_string();
+            (format!("{}{}", prefix, path), options)
+        })
+        .collect();
+    *features = anchored;
+}
 
-        // If not found in primary locations, check .devcontainer subfolders (one level deep)
-        if final_path.is_none() {
-            let devcontainer_dir = path.join(".devcontainer");
-            if let Ok(entries) = fs::read_dir(&devcontainer_dir) {
-                for entry in entries.flatten() {
-                    if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                        let candidate = entry.path().join("devcontainer.json");
-                        if fs::exists(&candidate).unwrap_or(false) {
-                            final_path = Some(candidate);
-                            break;
-                        }
+/// Merges an extending definition over its base. Objects are merged key by
+/// key, any other value replaces the base value.
+fn merge_definition(base: &mut Value, definition: Value) {
+    match (base, definition) {
+        (Value::Object(base), Value::Object(definition)) => {
+            for (key, value) in definition {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_definition(base_value, value),
+                    None => {
+                        base.insert(key, value);
                     }
                 }
             }
         }
+        (base, definition) => *base = definition,
+    }
+}
 
-        let final_path = final_path.ok_or_else(|| {
-            anyhow::anyhow!(
-                "Devcontainer definition not found in any standard location under {}",
-                path.to_string_lossy()
-            )
-        })?;
+/// Lists the named configurations of a project, i.e. the folders
+/// `.devcontainer/<name>/` containing a `devcontainer.json`, sorted by name.
+pub fn list_devcontainer_configs(path: &Path) -> Vec<String> {
+    let mut configs: Vec<String> = fs::read_dir(path.join(".devcontainer"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter(|entry| entry.path().join("devcontainer.json").is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    configs.sort();
+    configs
+}
 
-        let file_result = fs::read_to_string(&final_path);
+/// Finds the devcontainer definition of a project directory.
+///
+/// With a `config` name, `.devcontainer/<config>/devcontainer.json` is used.
+/// Otherwise the standard locations are checked in order, falling back to a
+/// named configuration if the project has exactly one.
+///
+/// # Errors
+///
+/// Returns an error if no definition exists, the named configuration does not
+/// exist, or several named configurations exist and none is selected.
+pub fn find_devcontainer_file(path: &Path, config: Option<&str>) -> anyhow::Result<PathBuf> {
+    let configs = list_devcontainer_configs(path);
 
-        if file_result.is_err() {
+    if let Some(config) = config {
+        if !configs.iter().any(|c| c == config) {
             bail!(
-                "Devcontainer definition cannot be read {}",
-                &final_path.to_string_lossy()
-            )
+                "Devcontainer configuration '{}' not found under {}, available: {}",
+                config,
+                path.to_string_lossy(),
+                if configs.is_empty() {
+                    "none".to_string()
+                } else {
+                    configs.join(", ")
+                }
+            );
         }
+        return Ok(path
+            .join(".devcontainer")
+            .join(config)
+            .join("devcontainer.json"));
+    }
 
-        let result = Self::try_from(file_result.unwrap());
-        if result.is_err() {
-            bail!("Devcontainer content could not be parsed")
-        }
+    // Check locations in order of precedence per devcontainer spec:
+    // 1. .devcontainer/devcontainer.json
+    // 2. .devcontainer.json
+    // 3. .devcontainer/<folder>/devcontainer.json (one level deep)
+    let primary_paths = [
+        path.join(".devcontainer").join("devcontainer.json"),
+        path.join("devcontainer.json"),
+    ];
+    if let Some(primary) = primary_paths.into_iter().find(|p| p.is_file()) {
+        return Ok(primary);
+    }
 
-        // Fix name of container if not present
-        let mut result = result?;
-        if result.name.is_none() {
-            let name = fs::canonicalize(&path)?
-                .file_name()
-                .ok_or_else(|| anyhow::anyhow!("Invalid path for devcontainer"))?
-                .to_string_lossy()
-                .to_string();
-            result.name = Some(name);
-        }
+    match configs.as_slice() {
+        [config] => Ok(path
+            .join(".devcontainer")
+            .join(config)
+            .join("devcontainer.json")),
+        [] => bail!(
+            "Devcontainer definition not found in any standard location under {}",
+            path.to_string_lossy()
+        ),
+        _ => bail!(
+            "Multiple devcontainer configurations found under {}: {}. Select one with --config <name>",
+            path.to_string_lossy(),
+            configs.join(", ")
+        ),
+    }
+}
 
-        Ok(result)
+impl TryFrom<PathBuf> for Devcontainer {
+    type Error = anyhow::Error;
+
+    fn try_from(path: PathBuf) -> std::result::Result<Self, Self::Error> {
+        Self::load(&path, None)
     }
 }
 
@@ -733,8 +977,50 @@ impl TryFrom<String> for Devcontainer {
 /// Defines the source location of a feature.
 #[derive(Debug, Clone)]
 pub enum FeatureSource {
-    Registry { registry: FeatureRegistry },
-    Local { path: PathBuf },
+    Registry {
+        registry: FeatureRegistry,
+    },
+    /// A feature folder or `.tgz` archive on the local filesystem
+    Local {
+        path: PathBuf,
+    },
+    /// A `.tgz` archive downloaded over HTTPS
+    Tarball {
+        url: String,
+    },
+}
+
+impl FeatureSource {
+    /// Returns the short name of the feature: the registry name, the folder
+    /// name of a local feature, or the archive name without the
+    /// `devcontainer-feature-` prefix and `.tgz` extension.
+    pub fn name(&self) -> String {
+        match self {
+            FeatureSource::Registry { registry } => registry.name.clone(),
+            FeatureSource::Local { path } => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+                archive_name(&path.file_name().unwrap_or_default().to_string_lossy())
+            }
+            FeatureSource::Tarball { url } => {
+                archive_name(url.rsplit('/').next().unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Returns true if the feature reference points to a tarball.
+fn is_tarball(reference: &str) -> bool {
+    reference.ends_with(".tgz") || reference.ends_with(".tar.gz") || reference.ends_with(".tar")
+}
+
+fn archive_name(file_name: &str) -> String {
+    let name = [".tgz", ".tar.gz", ".tar"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .unwrap_or(file_name);
+    name.strip_prefix("devcontainer-feature-")
+        .unwrap_or(name)
+        .to_string()
 }
 
 /// Metadata for a feature stored in an OCI registry.
@@ -768,15 +1054,43 @@ impl FeatureRef {
             options: serde_json::json!({}),
         }
     }
+
+    /// Resolves a relative local feature path against `dir`, the folder of
+    /// the devcontainer.json.
+    pub fn relative_to(mut self, dir: &Path) -> Self {
+        if let FeatureSource::Local { path } = &mut self.source
+            && path.is_relative()
+        {
+            *path = dir.join(&*path);
+        }
+        self
+    }
 }
 
 /// Parses a feature URL string and options into a FeatureRef struct.
+///
+/// Besides ghcr.io references, features can be local folders or archives
+/// (`./path`, `../path` or `file:path`) and HTTPS tarball URLs ending in
+/// `.tgz`. Relative paths are resolved with [`FeatureRef::relative_to`].
 pub fn parse_feature<E: de::Error>(
     url: &str,
     user_options: serde_json::Value,
 ) -> Result<FeatureRef, E> {
+    if let Some(path) = url.strip_prefix("file://").or(url.strip_prefix("file:")) {
+        return parse_local_feature(path, user_options);
+    }
+    if url.starts_with("https://") && is_tarball(url) {
+        return Ok(FeatureRef {
+            source: FeatureSource::Tarball {
+                url: url.to_string(),
+            },
+            options: user_options,
+        });
+    }
     if !url.starts_with("ghcr.io") && url.contains(":") {
-        return Err(de::Error::custom("Only ghcr.io features are supported"));
+        return Err(de::Error::custom(
+            "Only ghcr.io features, local paths and HTTPS tarballs are supported",
+        ));
     }
 
     if url.starts_with("ghcr.io") {
@@ -972,6 +1286,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_local_and_tarball_feature_references() {
+        let parse = |url: &str| {
+            parse_feature::<serde::de::value::Error>(url, serde_json::json!({}))
+                .unwrap()
+                .source
+        };
+
+        match parse("file:features/go") {
+            FeatureSource::Local { path } => assert_eq!(PathBuf::from("features/go"), path),
+            _ => panic!("Expected Local feature"),
+        }
+        match parse("./devcontainer-feature-go.tgz") {
+            FeatureSource::Local { path } => {
+                assert_eq!(PathBuf::from("./devcontainer-feature-go.tgz"), path)
+            }
+            _ => panic!("Expected Local feature"),
+        }
+        let tarball = parse("https://example.com/releases/devcontainer-feature-go.tgz");
+        match &tarball {
+            FeatureSource::Tarball { url } => {
+                assert_eq!(
+                    "https://example.com/releases/devcontainer-feature-go.tgz",
+                    url
+                )
+            }
+            _ => panic!("Expected Tarball feature"),
+        }
+        assert_eq!("go", tarball.name());
+        assert!(
+            parse_feature::<serde::de::value::Error>(
+                "https://example.com/feature",
+                serde_json::json!({})
+            )
+            .is_err()
+        );
+
+        let feature = FeatureRef::new(FeatureSource::Local {
+            path: PathBuf::from("./local-feature"),
+        })
+        .relative_to(Path::new("/projects/app/.devcontainer"));
+        match feature.source {
+            FeatureSource::Local { path } => assert_eq!(
+                PathBuf::from("/projects/app/.devcontainer/./local-feature"),
+                path
+            ),
+            _ => panic!("Expected Local feature"),
+        }
+    }
+
     #[test]
     fn test_mixed_features() {
         let feature_json = r#"
@@ -1578,7 +1942,10 @@ mod tests {
         "#;
 
         let devcontainer: Devcontainer = serde_json::from_str(json).unwrap();
-        assert!(devcontainer.wait_for.is_some());
+        assert_eq!(
+            devcontainer.wait_for.unwrap().hook_name(),
+            "postCreateCommand"
+        );
     }
 
     #[test]
@@ -1607,6 +1974,20 @@ mod tests {
 
         let devcontainer: Devcontainer = serde_json::from_str(json).unwrap();
         assert!(devcontainer.app_port.is_some());
+        let published: Vec<String> = devcontainer
+            .published_ports()
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        assert_eq!(published, vec!["127.0.0.1:3000:3000", "8080:8080"]);
+
+        let devcontainer: Devcontainer =
+            serde_json::from_str(r#"{ "image": "ubuntu:20.04", "appPort": "9000:80" }"#).unwrap();
+        assert_eq!(devcontainer.published_ports()[0].to_string(), "9000:80");
+
+        let devcontainer: Devcontainer =
+            serde_json::from_str(r#"{ "image": "ubuntu:20.04" }"#).unwrap();
+        assert!(devcontainer.published_ports().is_empty());
     }
 
     #[test]
@@ -1798,4 +2179,146 @@ mod tests {
         // Verify host requirements
         assert!(devcontainer.host_requirements.is_some());
     }
+
+    #[test]
+    fn test_multiple_configs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("project");
+        for config in ["python", "node"] {
+            let dir = path.join(".devcontainer").join(config);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("devcontainer.json"), r#"{ "image": "ubuntu" }"#).unwrap();
+        }
+
+        assert_eq!(list_devcontainer_configs(&path), vec!["node", "python"]);
+        let error = find_devcontainer_file(&path, None).unwrap_err().to_string();
+        assert!(error.contains("node, python"), "{}", error);
+        assert!(find_devcontainer_file(&path, Some("go")).is_err());
+
+        let devcontainer = Devcontainer::load(&path, Some("node")).unwrap();
+        assert_eq!(devcontainer.name.as_deref(), Some("project-node"));
+
+        // A default configuration takes precedence over the named ones
+        fs::write(
+            path.join(".devcontainer").join("devcontainer.json"),
+            r#"{ "image": "debian" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            find_devcontainer_file(&path, None).unwrap(),
+            path.join(".devcontainer").join("devcontainer.json")
+        );
+    }
+
+    #[test]
+    fn test_extends() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let project = root.join("services").join("api");
+        fs::create_dir_all(project.join(".devcontainer")).unwrap();
+        fs::create_dir_all(root.join("shared")).unwrap();
+        fs::write(
+            root.join("shared").join("devcontainer.json"),
+            r#"{
+                // Shared by all services
+                "image": "ubuntu",
+                "remoteUser": "vscode",
+                "containerEnv": { "LANG": "C.UTF-8", "MODE": "base" },
+                "features": { "./tools": {} },
+                "forwardPorts": [3000]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            project.join(".devcontainer").join("devcontainer.json"),
+            r#"{
+                "extends": "../../../shared",
+                "containerEnv": { "MODE": "api" },
+                "forwardPorts": [8080]
+            }"#,
+        )
+        .unwrap();
+
+        let devcontainer = Devcontainer::load(&project, None).unwrap();
+        assert_eq!(devcontainer.image.as_deref(), Some("ubuntu"));
+        assert_eq!(devcontainer.remote_user.as_deref(), Some("vscode"));
+        let env = devcontainer.container_env.unwrap();
+        assert_eq!(env.get("LANG").map(String::as_str), Some("C.UTF-8"));
+        assert_eq!(env.get("MODE").map(String::as_str), Some("api"));
+        assert_eq!(devcontainer.forward_ports.unwrap().len(), 1);
+        assert!(
+            devcontainer
+                .additional_properties
+                .is_none_or(|p| !p.contains_key("extends"))
+        );
+        // Local features of the base stay relative to the base
+        match &devcontainer.features[0].source {
+            FeatureSource::Local { path } => {
+                assert_eq!(
+                    path,
+                    &fs::canonicalize(root.join("shared"))
+                        .unwrap()
+                        .join("./tools")
+                )
+            }
+            source => panic!("Unexpected feature source {:?}", source),
+        }
+    }
+
+    #[test]
+    fn test_extends_customization_and_chain() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join(".devcontainer");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("root.json"),
+            r#"{ "image": "debian", "init": true }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("base.json"),
+            r#"{ "extends": "root.json", "image": "ubuntu" }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("devcontainer.json"),
+            r#"{ "customizations": { "devcon": { "extends": "./base.json" } } }"#,
+        )
+        .unwrap();
+
+        let mut chain = Vec::new();
+        let definition = extended_definition(&dir.join("devcontainer.json"), &mut chain).unwrap();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(definition["image"], "ubuntu");
+        assert_eq!(definition["init"], true);
+        assert!(
+            definition
+                .pointer("/customizations/devcon/extends")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_extends_rejects_cycles_and_templates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("a.json"), r#"{ "extends": "b.json" }"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{ "extends": "./a.json" }"#).unwrap();
+        let error = extended_definition(&dir.join("a.json"), &mut Vec::new())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("extend each other"), "{}", error);
+
+        fs::write(
+            dir.join("c.json"),
+            r#"{ "extends": "ghcr.io/devcontainers/templates/rust:1" }"#,
+        )
+        .unwrap();
+        let error = extended_definition(&dir.join("c.json"), &mut Vec::new())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("OCI template"), "{}", error);
+        assert!(!is_template_reference("../shared/base.json"));
+        assert!(!is_template_reference("base.json"));
+    }
 }
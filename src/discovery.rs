@@ -0,0 +1,240 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Project Discovery
+//!
+//! Finds devcontainer projects below the configured project roots (e.g.
+//! `~/src`), so they can be selected by name instead of typing their path.
+//!
+//! A directory is a project if it contains a `.devcontainer.json` file or a
+//! `.devcontainer` directory with a `devcontainer.json`, either directly or in
+//! a named configuration folder. Projects are not searched for nested
+//! projects, and hidden directories as well as common build output folders
+//! are skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::devcontainer::list_devcontainer_configs;
+
+/// Default number of directory levels scanned below each project root.
+pub const DEFAULT_DISCOVERY_DEPTH: usize = 3;
+
+/// Directories never scanned, they are large and never contain projects.
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "vendor"];
+
+/// Returns whether `path` contains a devcontainer definition.
+pub fn is_project(path: &Path) -> bool {
+    path.join(".devcontainer.json").is_file()
+        || path
+            .join(".devcontainer")
+            .join("devcontainer.json")
+            .is_file()
+        || !list_devcontainer_configs(path).is_empty()
+}
+
+/// Scans the roots for projects up to `max_depth` levels deep.
+///
+/// Roots that do not exist are ignored. The result is sorted and contains
+/// every project once, even if roots overlap.
+pub fn discover_projects(roots: &[PathBuf], max_depth: usize) -> Vec<PathBuf> {
+    let mut projects = Vec::new();
+    for root in roots {
+        scan(root, max_depth, &mut projects);
+    }
+    projects.sort();
+    projects.dedup();
+    projects
+}
+
+fn scan(dir: &Path, depth: usize, projects: &mut Vec<PathBuf>) {
+    if is_project(dir) {
+        projects.push(dir.to_path_buf());
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        // Symlinks are not followed to avoid cycles
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+        scan(&entry.path(), depth - 1, projects);
+    }
+}
+
+/// Scores how well `candidate` matches a fuzzy `query`.
+///
+/// All characters of the query must appear in the candidate in order, case
+/// insensitive. Consecutive matches and matches at the start of a path
+/// component score higher. Returns `None` if the candidate does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let Some(&first) = query.first() else {
+        return Some(0);
+    };
+
+    // Matching greedily from every occurrence of the first character finds
+    // e.g. "con" in the name of "/src/devcon" rather than in "src"
+    candidate
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| c == first)
+        .filter_map(|(start, _)| score_from(&query, &candidate, start))
+        .max()
+}
+
+fn score_from(query: &[char], candidate: &[char], start: usize) -> Option<u32> {
+    let mut score = 0;
+    let mut position = start;
+    let mut previous: Option<usize> = None;
+
+    for &query_char in query {
+        let index = position
+            + candidate[position..]
+                .iter()
+                .position(|&c| c == query_char)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || matches!(candidate[index - 1], '/' | '\\' | '-' | '_' | '.' | ' ') {
+            score += 3;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
+
+/// Filters projects by a fuzzy query, best matches first.
+///
+/// Ties are broken by the shorter path. An empty query keeps all projects in
+/// their original order.
+pub fn search_projects(projects: &[PathBuf], query: &str) -> Vec<PathBuf> {
+    let mut matches: Vec<(u32, &PathBuf)> = projects
+        .iter()
+        .filter_map(|path| fuzzy_score(query, &path.to_string_lossy()).map(|s| (s, path)))
+        .collect();
+    if !query.trim().is_empty() {
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .cmp(a_score)
+                .then_with(|| a.as_os_str().len().cmp(&b.as_os_str().len()))
+        });
+    }
+    matches.into_iter().map(|(_, path)| path.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_project(path: &Path) {
+        fs::create_dir_all(path.join(".devcontainer")).unwrap();
+        fs::write(
+            path.join(".devcontainer").join("devcontainer.json"),
+            r#"{ "image": "ubuntu" }"#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_discover_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        create_project(&root.join("api"));
+        create_project(&root.join("org").join("web"));
+        // Nested projects, hidden and skipped directories are not reported
+        create_project(&root.join("api").join("tools"));
+        create_project(&root.join(".cache").join("hidden"));
+        create_project(&root.join("web").join("node_modules").join("dep"));
+        // Named configurations and single-file definitions
+        fs::create_dir_all(root.join("multi").join(".devcontainer").join("rust")).unwrap();
+        fs::write(
+            root.join("multi")
+                .join(".devcontainer")
+                .join("rust")
+                .join("devcontainer.json"),
+            "{}",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("single")).unwrap();
+        fs::write(root.join("single").join(".devcontainer.json"), "{}").unwrap();
+        create_project(&root.join("a").join("b").join("c").join("deep"));
+
+        let roots = vec![root.to_path_buf(), root.join("missing")];
+        assert_eq!(
+            discover_projects(&roots, DEFAULT_DISCOVERY_DEPTH),
+            vec![
+                root.join("api"),
+                root.join("multi"),
+                root.join("org").join("web"),
+                root.join("single"),
+            ]
+        );
+        assert_eq!(discover_projects(&roots, 4).len(), 5);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("dc", "devcon").is_some());
+        assert!(fuzzy_score("DEV", "devcon").is_some());
+        assert!(fuzzy_score("cd", "devcon").is_none());
+        assert_eq!(fuzzy_score("", "devcon"), Some(0));
+        assert!(fuzzy_score("con", "/src/devcon") > fuzzy_score("con", "/src/cabin-tonic"));
+        assert!(fuzzy_score("api", "/src/api") > fuzzy_score("api", "/src/rapid"));
+    }
+
+    #[test]
+    fn test_search_projects() {
+        let projects = vec![
+            PathBuf::from("/src/rapid-prototype"),
+            PathBuf::from("/src/org/api-gateway"),
+            PathBuf::from("/src/api"),
+            PathBuf::from("/src/web"),
+        ];
+
+        assert_eq!(
+            search_projects(&projects, "api"),
+            vec![
+                PathBuf::from("/src/api"),
+                PathBuf::from("/src/org/api-gateway"),
+                PathBuf::from("/src/rapid-prototype"),
+            ]
+        );
+        assert_eq!(search_projects(&projects, " "), projects);
+    }
+}
@@ -0,0 +1,653 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Download Scheduling
+//!
+//! Users on metered or slow connections can limit the bandwidth of large
+//! downloads and restrict them to time windows (e.g. at night).
+//!
+//! Feature layers are downloaded through [`DownloadPolicy::download`], which
+//! throttles the transfer, pauses it outside of the allowed windows and resumes
+//! it with a range request once a window opens again. Container builds, which
+//! pull base images through the container runtime, wait for an allowed window
+//! before they start.
+//!
+//! Requests failing with a transient error (connection problems, timeouts,
+//! server errors) are retried with exponential backoff, see
+//! [`DownloadPolicy::retry`].
+//!
+//! In offline mode nothing is downloaded at all: features come from the local
+//! cache and builds use the base images already present in the runtime.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Timelike;
+use devcon_proto::bandwidth::RateLimitedReader;
+use reqwest::StatusCode;
+use reqwest::blocking::RequestBuilder;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::driver::metrics::format_bytes;
+use crate::driver::oci::mirror::RegistryMirrors;
+use crate::driver::signature::TrustedKeys;
+
+/// Interval in which a paused download checks whether a window opened.
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default number of retries of a failing request or transfer.
+pub const DEFAULT_RETRIES: u32 = 5;
+
+/// Delay before the first retry, doubled for every further retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound of the delay between two retries.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Size of the chunks read from the response.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Daily time window in local time, e.g. `22:00-06:00`.
+///
+/// The start is inclusive and the end exclusive. Windows ending before they
+/// start wrap around midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkWindow {
+    /// Minute of the day the window opens
+    start: u16,
+    /// Minute of the day the window closes
+    end: u16,
+}
+
+impl NetworkWindow {
+    /// Returns true if `minute` (minutes since midnight) lies in the window.
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl FromStr for NetworkWindow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse_time = |time: &str| -> Result<u16> {
+            let (hours, minutes) = time
+                .trim()
+                .split_once(':')
+                .with_context(|| format!("Invalid time '{}', expected HH:MM", time.trim()))?;
+            let hours: u16 =
+                hours.parse().ok().filter(|h| *h < 24).with_context(|| {
+                    format!("Invalid hour in '{}', expected 00 to 23", time.trim())
+                })?;
+            let minutes: u16 = minutes.parse().ok().filter(|m| *m < 60).with_context(|| {
+                format!("Invalid minute in '{}', expected 00 to 59", time.trim())
+            })?;
+            Ok(hours * 60 + minutes)
+        };
+
+        let (start, end) = s
+            .split_once('-')
+            .with_context(|| format!("Invalid window '{}', expected HH:MM-HH:MM", s.trim()))?;
+        let window = NetworkWindow {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        };
+        if window.start == window.end {
+            anyhow::bail!(
+                "Window '{}' must not start and end at the same time",
+                s.trim()
+            );
+        }
+        Ok(window)
+    }
+}
+
+impl fmt::Display for NetworkWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+/// Parses a comma-separated list of windows, e.g. `22:00-06:00,12:00-13:00`.
+///
+/// # Errors
+///
+/// Returns an error if the list is empty or contains an invalid window.
+pub fn parse_windows(value: &str) -> Result<Vec<NetworkWindow>> {
+    let windows = value
+        .split(',')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(NetworkWindow::from_str)
+        .collect::<Result<Vec<_>>>()?;
+    if windows.is_empty() {
+        anyhow::bail!("At least one window is required (e.g. '22:00-06:00')");
+    }
+    Ok(windows)
+}
+
+/// Returns the delay before retry number `attempt`, starting at 1.
+pub fn backoff(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Returns true if a failed request may succeed when retried.
+///
+/// Connection problems, timeouts, interrupted transfers, rate limiting and
+/// server errors are transient; client errors such as 401 or 404 are not.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return match error.status() {
+            Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            None => {
+                error.is_timeout() || error.is_connect() || error.is_request() || error.is_body()
+            }
+        };
+    }
+    error.downcast_ref::<std::io::Error>().is_some()
+}
+
+/// Bandwidth limit, allowed time windows and retries for large downloads.
+#[derive(Debug, Clone)]
+pub struct DownloadPolicy {
+    /// Limit in bytes per second
+    pub bandwidth: Option<u64>,
+    /// Windows in which downloads are allowed, any time if empty
+    pub windows: Vec<NetworkWindow>,
+    /// Never access the network, only use locally cached artifacts
+    pub offline: bool,
+    /// Retries of a failing request or transfer
+    pub retries: u32,
+    /// Additional CA certificate files trusted for downloads
+    pub ca_certificates: Vec<PathBuf>,
+    /// Largest accepted download in bytes, unlimited if not set
+    pub max_size: Option<u64>,
+    /// Keys registry features must be signed with, not verified if not set
+    pub trusted_keys: Option<TrustedKeys>,
+    /// Mirrors registry requests go to
+    pub mirrors: RegistryMirrors,
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        Self {
+            bandwidth: None,
+            windows: Vec::new(),
+            offline: false,
+            retries: DEFAULT_RETRIES,
+            ca_certificates: Vec::new(),
+            max_size: None,
+            trusted_keys: None,
+            mirrors: RegistryMirrors::default(),
+        }
+    }
+}
+
+impl DownloadPolicy {
+    /// Creates the policy from the `downloadBandwidth`, `downloadWindows`,
+    /// `downloadRetries`, `downloadSizeLimit`, `offline`, `caCertificates`,
+    /// `verifySignatures`, `trustedKeys` and `registryMirrors` settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the settings is invalid.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let bandwidth = config
+            .download_bandwidth
+            .as_deref()
+            .map(devcon_proto::bandwidth::parse_bandwidth)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let windows = config
+            .download_windows
+            .as_deref()
+            .map(parse_windows)
+            .transpose()?
+            .unwrap_or_default();
+        let max_size = config
+            .download_size_limit
+            .as_deref()
+            .map(devcon_proto::bandwidth::parse_bandwidth)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!(e))?;
+        let trusted_keys = config
+            .signatures_verified()
+            .then(|| TrustedKeys::load(&config.get_trusted_keys()))
+            .transpose()?;
+        let mirrors =
+            RegistryMirrors::parse(config.registry_mirrors.as_deref().unwrap_or_default())?;
+
+        Ok(Self {
+            bandwidth,
+            windows,
+            offline: config.is_offline(),
+            retries: config.get_download_retries(),
+            ca_certificates: config.get_ca_certificates(),
+            max_size,
+            trusted_keys,
+            mirrors,
+        })
+    }
+
+    /// Returns true if downloads are allowed at `minute` of the day.
+    pub fn is_open_at(&self, minute: u16) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(minute))
+    }
+
+    /// Returns true if downloads are allowed now.
+    pub fn is_open(&self) -> bool {
+        let now = chrono::Local::now();
+        self.is_open_at((now.hour() * 60 + now.minute()) as u16)
+    }
+
+    /// Blocks until downloads are allowed, returns at once in offline mode.
+    pub fn wait_for_window(&self) {
+        if self.offline || self.is_open() {
+            return;
+        }
+
+        let windows: Vec<String> = self.windows.iter().map(|w| w.to_string()).collect();
+        println!(
+            "Waiting for the download window ({}) to open..",
+            windows.join(", ")
+        );
+        while !self.is_open() {
+            std::thread::sleep(WINDOW_POLL_INTERVAL);
+        }
+        info!("Download window opened");
+    }
+
+    /// Runs `operation`, retrying transient failures with exponential backoff.
+    ///
+    /// `what` describes the operation in warnings, e.g. "Manifest request".
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt, or the first error which is not
+    /// transient.
+    pub fn retry<T>(&self, what: &str, mut operation: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match operation() {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    attempt += 1;
+                    let delay = backoff(attempt);
+                    warn!(
+                        "{} failed, retrying in {}s ({}/{}): {:#}",
+                        what,
+                        delay.as_secs(),
+                        attempt,
+                        self.retries,
+                        e
+                    );
+                    std::thread::sleep(delay);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Downloads the body of the request created by `request` into memory.
+    ///
+    /// See [`DownloadPolicy::download_to`] for throttling, resuming and retries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the transfer keeps failing or
+    /// the body exceeds the size limit.
+    pub fn download(&self, request: impl Fn() -> RequestBuilder) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.download_to(request, &mut data, |_, _| {})?;
+        Ok(data)
+    }
+
+    /// Streams the body of the request created by `request` into `sink`,
+    /// returning its size.
+    ///
+    /// The transfer is throttled to the bandwidth limit. When the download
+    /// window closes or the connection breaks, the download is resumed with a
+    /// range request, or restarted if the server does not support ranges.
+    /// Failed requests and interrupted transfers are retried with backoff.
+    /// `progress` is called with the bytes received so far and the total
+    /// size, if the server announced it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails, the transfer keeps failing,
+    /// `sink` cannot be written or the body exceeds the size limit.
+    pub fn download_to<S: DownloadSink + ?Sized>(
+        &self,
+        request: impl Fn() -> RequestBuilder,
+        sink: &mut S,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<u64> {
+        let mut written: u64 = 0;
+        let mut failures = 0;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+
+        loop {
+            self.wait_for_window();
+
+            let mut builder = request();
+            if written > 0 {
+                debug!("Resuming download at byte {}", written);
+                builder = builder.header(reqwest::header::RANGE, format!("bytes={}-", written));
+            }
+            let response = match builder.send().and_then(|r| r.error_for_status()) {
+                Ok(response) => response,
+                Err(e) => {
+                    let e = anyhow::Error::from(e);
+                    failures += 1;
+                    if failures > self.retries || !is_transient(&e) {
+                        return Err(e).context("Download failed");
+                    }
+                    let delay = backoff(failures);
+                    warn!(
+                        "Download request failed, retrying in {}s: {:#}",
+                        delay.as_secs(),
+                        e
+                    );
+                    std::thread::sleep(delay);
+                    continue;
+                }
+            };
+            if written > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+                debug!("Server does not support resuming, restarting download");
+                sink.restart()?;
+                written = 0;
+            }
+            let total = response.content_length().map(|length| written + length);
+            self.check_size(total.unwrap_or(0))?;
+
+            let mut reader = RateLimitedReader::new(response, self.bandwidth);
+            loop {
+                if !self.is_open() {
+                    info!("Pausing download outside of the download window");
+                    break;
+                }
+                match reader.read(&mut buf) {
+                    Ok(0) => return Ok(written),
+                    Ok(n) => {
+                        written += n as u64;
+                        self.check_size(written)?;
+                        sink.write_all(&buf[..n])?;
+                        progress(written, total);
+                        failures = 0;
+                    }
+                    Err(e) => {
+                        failures += 1;
+                        if failures > self.retries {
+                            return Err(e).context("Download failed repeatedly");
+                        }
+                        let delay = backoff(failures);
+                        warn!(
+                            "Download interrupted, resuming in {}s: {}",
+                            delay.as_secs(),
+                            e
+                        );
+                        std::thread::sleep(delay);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fails if a download of `size` bytes exceeds the size limit.
+    fn check_size(&self, size: u64) -> Result<()> {
+        match self.max_size {
+            Some(limit) if size > limit => anyhow::bail!(
+                "Download exceeds the size limit of {} (downloadSizeLimit)",
+                format_bytes(limit)
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Destination of a download which can be restarted from scratch.
+pub trait DownloadSink: Write {
+    /// Discards everything written so far.
+    fn restart(&mut self) -> std::io::Result<()>;
+}
+
+impl DownloadSink for Vec<u8> {
+    fn restart(&mut self) -> std::io::Result<()> {
+        self.clear();
+        Ok(())
+    }
+}
+
+impl DownloadSink for File {
+    fn restart(&mut self) -> std::io::Result<()> {
+        self.set_len(0)?;
+        self.rewind()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_window_wraps_around_midnight() {
+        let night: NetworkWindow = "22:00-06:30".parse().unwrap();
+
+        assert!(night.contains(23 * 60));
+        assert!(night.contains(6 * 60 + 29));
+        assert!(!night.contains(6 * 60 + 30));
+        assert!(!night.contains(12 * 60));
+        assert_eq!(night.to_string(), "22:00-06:30");
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        let windows = parse_windows("22:00-06:00, 12:00-13:00").unwrap();
+        assert_eq!(windows.len(), 2);
+
+        assert!(parse_windows("").is_err());
+        assert!(parse_windows("25:00-06:00").is_err());
+        assert!(parse_windows("22:00").is_err());
+        assert!(parse_windows("10:00-10:00").is_err());
+    }
+
+    #[test]
+    fn test_policy_without_windows_is_always_open() {
+        let policy = DownloadPolicy::default();
+        assert!(policy.is_open_at(0));
+
+        let policy = DownloadPolicy {
+            bandwidth: None,
+            windows: parse_windows("12:00-13:00").unwrap(),
+            ..DownloadPolicy::default()
+        };
+        assert!(policy.is_open_at(12 * 60 + 30));
+        assert!(!policy.is_open_at(14 * 60));
+    }
+
+    #[test]
+    fn test_download_resumes_with_range_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/blob", listener.local_addr().unwrap());
+        let body = b"0123456789".repeat(100);
+
+        // The first response breaks off after half of the body
+        let server_body = body.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut range = None;
+                for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        range = value.trim_end_matches('-').parse::<usize>().ok();
+                    }
+                }
+
+                match range {
+                    None => {
+                        write!(
+                            stream,
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                            server_body.len()
+                        )
+                        .unwrap();
+                        stream.write_all(&server_body[..500]).unwrap();
+                    }
+                    Some(start) => {
+                        write!(
+                            stream,
+                            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\n\r\n",
+                            server_body.len() - start
+                        )
+                        .unwrap();
+                        stream.write_all(&server_body[start..]).unwrap();
+                    }
+                }
+            }
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let data = DownloadPolicy::default()
+            .download(|| client.get(&url))
+            .unwrap();
+        assert_eq!(data, body);
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(4));
+        assert_eq!(backoff(10), RETRY_MAX_DELAY);
+        assert_eq!(backoff(u32::MAX), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_retry_stops_on_permanent_errors() {
+        let mut attempts = 0;
+        let result: Result<()> = DownloadPolicy::default().retry("Test request", || {
+            attempts += 1;
+            anyhow::bail!("Feature not found")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_download_retries_server_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/blob", listener.local_addr().unwrap());
+
+        // The first request fails with 503, the second succeeds
+        std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                    if line.unwrap().is_empty() {
+                        break;
+                    }
+                }
+                if i == 0 {
+                    write!(
+                        stream,
+                        "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+                    )
+                    .unwrap();
+                } else {
+                    write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nlayer").unwrap();
+                }
+            }
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let data = DownloadPolicy::default()
+            .download(|| client.get(&url))
+            .unwrap();
+        assert_eq!(data, b"layer");
+    }
+
+    #[test]
+    fn test_download_to_file_with_size_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/blob", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                for line in BufReader::new(stream.try_clone().unwrap()).lines() {
+                    if line.unwrap().is_empty() {
+                        break;
+                    }
+                }
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nlayer").unwrap();
+            }
+        });
+
+        let client = reqwest::blocking::Client::new();
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = File::create(dir.path().join("layer")).unwrap();
+        let mut reported = Vec::new();
+        let size = DownloadPolicy::default()
+            .download_to(
+                || client.get(&url),
+                &mut file,
+                |bytes, total| reported.push((bytes, total)),
+            )
+            .unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(reported.last(), Some(&(5, Some(5))));
+        assert_eq!(std::fs::read(dir.path().join("layer")).unwrap(), b"layer");
+
+        let policy = DownloadPolicy {
+            max_size: Some(4),
+            ..DownloadPolicy::default()
+        };
+        let error = policy.download(|| client.get(&url)).unwrap_err();
+        assert!(error.to_string().contains("size limit"), "{:#}", error);
+    }
+}
@@ -1,9 +1,20 @@
 use anyhow::{Context, Result};
 use minijinja::Environment;
 use serde::{Deserialize, Serialize};
-use std::os::unix::fs::PermissionsExt;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
+use tracing::info;
+
+use crate::download::DownloadPolicy;
+use crate::http_proxy;
+
+/// Release asset suffixes of the prebuilt agent binaries, one per architecture
+const PREBUILT_ARCHITECTURES: &[&str] = &["x86_64", "arm64"];
+
+/// Release asset with the SHA-256 checksums of all binaries
+const CHECKSUMS_ASSET: &str = "checksums.txt";
 
 /// Configuration for generating a devcontainer feature
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +37,9 @@ pub struct AgentConfig {
     pub git_repository: Option<String>,
     /// Git branch to checkout
     pub git_branch: Option<String>,
+    /// Directory with verified prebuilt binaries, see [`prebuilt_binaries`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prebuilt_dir: Option<PathBuf>,
 }
 
 impl Default for AgentConfig {
@@ -41,10 +55,172 @@ impl AgentConfig {
         git_repository: Option<String>,
         git_branch: Option<String>,
     ) -> Self {
-        let env = Environment::new();
-        let template = env
-            .template_from_str(
-                r###"
+        let git_repo =
+            git_repository.unwrap_or_else(|| "https://github.com/kreemer/devcon.git".to_string());
+        let git_br = git_branch.unwrap_or_else(|| "main".to_string());
+        let contents =
+            render_install_script(binary_url.as_deref(), false, &git_repo, &git_br, false);
+
+        Self {
+            id: "devcon-agent".to_string(),
+            version: "1.0.0".to_string(),
+            name: "DevCon Agent".to_string(),
+            description: Some("DevCon Agent for managing devcontainer features".to_string()),
+            install_script: contents,
+            options: None,
+            binary_url,
+            git_repository: Some(git_repo),
+            git_branch: Some(git_br),
+            prebuilt_dir: None,
+        }
+    }
+
+    /// Installs the prebuilt binaries of `dir` instead of compiling the agent.
+    ///
+    /// Has no effect if a binary URL is set.
+    pub fn with_prebuilt(mut self, dir: PathBuf) -> Self {
+        self.install_script = render_install_script(
+            self.binary_url.as_deref(),
+            true,
+            self.git_repository.as_deref().unwrap_or_default(),
+            self.git_branch.as_deref().unwrap_or_default(),
+            false,
+        );
+        self.prebuilt_dir = Some(dir);
+        self
+    }
+
+    /// Builds the agent with the gRPC control plane when compiled from source.
+    ///
+    /// Has no effect on precompiled binaries, which have to include it already.
+    pub fn with_grpc(mut self) -> Self {
+        self.install_script = render_install_script(
+            self.binary_url.as_deref(),
+            self.prebuilt_dir.is_some(),
+            self.git_repository.as_deref().unwrap_or_default(),
+            self.git_branch.as_deref().unwrap_or_default(),
+            true,
+        );
+        self
+    }
+}
+
+/// Base URL of the release assets of the running devcon version.
+pub fn default_release_url() -> String {
+    format!(
+        "https://github.com/kreemer/devcon/releases/download/v{}",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Name of the release asset of the agent for an architecture.
+fn prebuilt_asset(architecture: &str) -> String {
+    format!("devcon-agent-linux-{}", architecture)
+}
+
+/// Downloads the prebuilt agent binaries of all architectures from
+/// `release_url` to the host cache and returns the cache directory.
+///
+/// Every binary is verified against the `checksums.txt` of the release,
+/// including binaries already in the cache.
+///
+/// # Errors
+///
+/// Returns an error if a binary or the checksums cannot be downloaded, or if
+/// a checksum does not match.
+pub fn prebuilt_binaries(release_url: &str, policy: &DownloadPolicy) -> Result<PathBuf> {
+    let release_url = release_url.trim_end_matches('/');
+    let key = format!("{:x}", Sha256::digest(release_url.as_bytes()));
+    let cache_dir = dirs::cache_dir()
+        .context("Could not determine cache directory")?
+        .join("devcon")
+        .join("agent")
+        .join(&key[..12]);
+    std::fs::create_dir_all(&cache_dir)?;
+
+    let fetch = |asset: &str| -> Result<Vec<u8>> {
+        if policy.offline {
+            anyhow::bail!("{} is not in the local cache", asset);
+        }
+        info!("Downloading {}/{}", release_url, asset);
+        let client = http_proxy::http_client(&policy.ca_certificates)?;
+        let url = format!("{}/{}", release_url, asset);
+        policy
+            .download(|| client.get(&url))
+            .with_context(|| format!("Failed to download {}", url))
+    };
+
+    let checksums_path = cache_dir.join(CHECKSUMS_ASSET);
+    let checksums = match std::fs::read_to_string(&checksums_path) {
+        Ok(content) => content,
+        Err(_) => {
+            let content = String::from_utf8(fetch(CHECKSUMS_ASSET)?)
+                .context("Invalid checksums of the agent release")?;
+            std::fs::write(&checksums_path, &content)?;
+            content
+        }
+    };
+    let checksums = parse_checksums(&checksums);
+
+    for architecture in PREBUILT_ARCHITECTURES {
+        let asset = prebuilt_asset(architecture);
+        let expected = checksums
+            .get(&asset)
+            .with_context(|| format!("No checksum for {} in the agent release", asset))?;
+        let path = cache_dir.join(&asset);
+        if let Ok(binary) = std::fs::read(&path)
+            && verify_checksum(&binary, expected).is_ok()
+        {
+            continue;
+        }
+
+        let binary = fetch(&asset)?;
+        verify_checksum(&binary, expected).with_context(|| format!("Invalid {}", asset))?;
+        std::fs::write(&path, binary)?;
+    }
+
+    Ok(cache_dir)
+}
+
+/// Parses `sha256sum` output into a map of file name to checksum.
+fn parse_checksums(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(checksum, name)| {
+            let name = name.trim_start().trim_start_matches('*');
+            (name.to_string(), checksum.to_lowercase())
+        })
+        .collect()
+}
+
+/// Checks the SHA-256 checksum of `data`.
+fn verify_checksum(data: &[u8], expected: &str) -> Result<()> {
+    let actual = format!("{:x}", Sha256::digest(data));
+    if actual != expected {
+        anyhow::bail!(
+            "Checksum mismatch, expected {} but got {}",
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Renders the agent install script, either downloading a precompiled binary,
+/// installing a prebuilt binary of the feature or compiling the agent from
+/// source.
+fn render_install_script(
+    binary_url: Option<&str>,
+    prebuilt: bool,
+    git_repository: &str,
+    git_branch: &str,
+    grpc: bool,
+) -> String {
+    let env = Environment::new();
+    let template = env
+        .template_from_str(
+            r###"
 #!/bin/bash
 
 set -e
@@ -55,6 +231,15 @@ echo "Installing DevCon Agent..."
 echo "Downloading precompiled agent from {{ binary_url }}..."
 curl -L -o /usr/local/bin/devcon-agent "{{ binary_url }}"
 chmod +x /usr/local/bin/devcon-agent
+{% elif prebuilt %}
+# Install the prebuilt binary of the architecture, copied with this feature
+case "$(uname -m)" in
+    x86_64|amd64) arch=x86_64 ;;
+    aarch64|arm64) arch=arm64 ;;
+    *) echo "No prebuilt agent for architecture $(uname -m)"; exit 1 ;;
+esac
+cp "devcon-agent-linux-$arch" /usr/local/bin/devcon-agent
+chmod +x /usr/local/bin/devcon-agent
 {% else %}
 # Compile from source
 echo "Compiling agent from source..."
@@ -66,7 +251,7 @@ echo $PATH
 git clone {{ git_repository }} /tmp/devcon
 cd /tmp/devcon
 git checkout {{ git_branch }}
-cargo b --release --workspace --bin devcon-agent
+cargo b --release --workspace --bin devcon-agent{% if grpc %} --features devcon-agent/grpc{% endif %}
 mv target/release/devcon-agent /usr/local/bin/devcon-agent
 rm -rf /tmp/devcon
 {% endif %}
@@ -77,33 +262,18 @@ chmod +x /usr/local/bin/devcon-browser
 
 echo "DevCon Agent installed successfully."
 "###,
-            )
-            .expect("Failed to create template");
-
-        let git_repo =
-            git_repository.unwrap_or_else(|| "https://github.com/kreemer/devcon.git".to_string());
-        let git_br = git_branch.unwrap_or_else(|| "main".to_string());
-
-        let contents = template
-            .render(minijinja::context! {
-                binary_url => binary_url,
-                git_repository => git_repo,
-                git_branch => git_br,
-            })
-            .expect("Could not create install script");
-
-        Self {
-            id: "devcon-agent".to_string(),
-            version: "1.0.0".to_string(),
-            name: "DevCon Agent".to_string(),
-            description: Some("DevCon Agent for managing devcontainer features".to_string()),
-            install_script: contents,
-            options: None,
-            binary_url,
-            git_repository: Some(git_repo),
-            git_branch: Some(git_br),
-        }
-    }
+        )
+        .expect("Failed to create template");
+
+    template
+        .render(minijinja::context! {
+            binary_url => binary_url,
+            prebuilt => prebuilt,
+            git_repository => git_repository,
+            git_branch => git_branch,
+            grpc => grpc,
+        })
+        .expect("Could not create install script")
 }
 
 /// Agent for generating devcontainer features
@@ -127,7 +297,19 @@ impl Agent {
         std::fs::create_dir_all(&feature_dir).context("Failed to create feature directory")?;
 
         // Generate devcontainer-feature.json
-        self.generate_feature_json(&feature_dir, self.config.binary_url.is_none())?;
+        let compile_needed = self.config.binary_url.is_none() && self.config.prebuilt_dir.is_none();
+        self.generate_feature_json(&feature_dir, compile_needed)?;
+
+        // Copy the prebuilt binaries, installed by install.sh
+        if self.config.binary_url.is_none()
+            && let Some(dir) = &self.config.prebuilt_dir
+        {
+            for architecture in PREBUILT_ARCHITECTURES {
+                let asset = prebuilt_asset(architecture);
+                std::fs::copy(dir.join(&asset), feature_dir.join(&asset))
+                    .with_context(|| format!("Failed to copy {}", asset))?;
+            }
+        }
 
         // Generate install.sh
         self.generate_install_script(&feature_dir)?;
@@ -184,6 +366,7 @@ impl Agent {
         // Make install.sh executable on Unix systems
         #[cfg(unix)]
         {
+            use std::os::unix::fs::PermissionsExt;
             let mut perms = std::fs::metadata(&install_path)?.permissions();
             perms.set_mode(0o755);
             std::fs::set_permissions(&install_path, perms)?;
@@ -209,6 +392,7 @@ mod tests {
             binary_url: None,
             git_repository: None,
             git_branch: None,
+            prebuilt_dir: None,
         };
 
         let mut agent = Agent::new(config);
@@ -254,6 +438,55 @@ mod tests {
         assert!(config.install_script.contains("git checkout develop"));
     }
 
+    #[test]
+    fn test_agent_with_grpc() {
+        let config = AgentConfig::new(None, None, Some("develop".to_string()));
+        assert!(!config.install_script.contains("--features"));
+
+        let config = config.with_grpc();
+        assert!(
+            config
+                .install_script
+                .contains("--features devcon-agent/grpc")
+        );
+        assert!(config.install_script.contains("git checkout develop"));
+    }
+
+    #[test]
+    fn test_agent_with_prebuilt_binaries() {
+        let dir = TempDir::new().unwrap();
+        for architecture in PREBUILT_ARCHITECTURES {
+            std::fs::write(dir.path().join(prebuilt_asset(architecture)), "binary").unwrap();
+        }
+
+        let config = AgentConfig::new(None, None, None).with_prebuilt(dir.path().to_path_buf());
+        assert!(!config.install_script.contains("git clone"));
+        assert!(
+            config
+                .install_script
+                .contains("cp \"devcon-agent-linux-$arch\" /usr/local/bin/devcon-agent")
+        );
+
+        let path = Agent::new(config).generate().unwrap();
+        assert!(path.join("devcon-agent-linux-x86_64").exists());
+        assert!(path.join("devcon-agent-linux-arm64").exists());
+        let content = std::fs::read_to_string(path.join("devcontainer-feature.json")).unwrap();
+        assert!(!content.contains("ghcr.io/devcontainers/features/rust"));
+    }
+
+    #[test]
+    fn test_checksums() {
+        let checksums = parse_checksums(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  devcon-agent-linux-x86_64\n\
+             ABC *devcon-agent-linux-arm64\n",
+        );
+        assert_eq!(checksums.get("devcon-agent-linux-arm64").unwrap(), "abc");
+
+        let expected = checksums.get("devcon-agent-linux-x86_64").unwrap();
+        assert!(verify_checksum(b"hello", expected).is_ok());
+        assert!(verify_checksum(b"tampered", expected).is_err());
+    }
+
     #[test]
     fn test_agent_default_values() {
         let config = AgentConfig::default();
@@ -0,0 +1,159 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Audit Log
+//!
+//! This module records container operations together with the host user who
+//! invoked them. On shared hosts where several users talk to one container
+//! daemon, this makes it possible to attribute every container start and exec.
+//!
+//! Entries are appended as JSON lines to `audit.log` in the devcon state directory.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::Config;
+use crate::driver::runtime::ContainerHandle;
+
+/// Label key storing the host user which started a devcon container.
+pub const OWNER_LABEL: &str = "devcon.user";
+
+/// Label key marking devcon containers with the name of their workspace.
+const PROJECT_LABEL: &str = "devcon.project";
+
+/// A single audit log entry.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Host user which performed the action
+    pub user: String,
+    /// Action performed (e.g. "start", "exec")
+    pub action: String,
+    /// Workspace name the container belongs to
+    pub workspace: String,
+    /// Container ID the action was performed on
+    pub container: String,
+    /// Command executed, if any
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+}
+
+impl AuditEntry {
+    /// Creates a new entry attributed to the current host user.
+    pub fn new(action: &str, workspace: &str, container: &str, command: &[&str]) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            user: Config::current_user(),
+            action: action.to_string(),
+            workspace: workspace.to_string(),
+            container: container.to_string(),
+            command: command.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+/// Appends an entry to the audit log.
+///
+/// Failures are logged as warnings and never abort the audited operation.
+pub fn record(entry: AuditEntry) {
+    if let Err(e) = append(&entry) {
+        warn!("Failed to write audit log entry: {}", e);
+    }
+}
+
+fn append(entry: &AuditEntry) -> anyhow::Result<()> {
+    let path = Config::get_state_dir()?.join("audit.log");
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Returns the owner of a container, if it was labelled with one.
+pub fn owner_of(handle: &dyn ContainerHandle) -> Option<&str> {
+    handle.label(OWNER_LABEL)
+}
+
+/// Checks whether the current user may manage the given container.
+///
+/// Devcon containers without an owner label were started before attribution
+/// and are adopted by the current user, so upgrading keeps them manageable.
+/// Other unlabelled containers are never owned.
+pub fn is_owned_by_current_user(handle: &dyn ContainerHandle) -> bool {
+    match owner_of(handle) {
+        Some(owner) => owner == Config::current_user(),
+        None => handle.label(PROJECT_LABEL).is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct LabelledHandle(Option<String>, bool);
+
+    impl ContainerHandle for LabelledHandle {
+        fn id(&self) -> &str {
+            "abc"
+        }
+
+        fn label(&self, key: &str) -> Option<&str> {
+            match key {
+                OWNER_LABEL => self.0.as_deref(),
+                PROJECT_LABEL if self.1 => Some("project"),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_audit_entry_serialization() {
+        let entry = AuditEntry::new("exec", "project", "abc", &["bash", "-c", "ls"]);
+        let json = serde_json::to_value(&entry).unwrap();
+
+        assert_eq!(json["action"], "exec");
+        assert_eq!(json["workspace"], "project");
+        assert_eq!(json["command"][2], "ls");
+        assert_eq!(json["user"], Config::current_user());
+    }
+
+    #[test]
+    fn test_ownership() {
+        let mine = LabelledHandle(Some(Config::current_user()), true);
+        let theirs = LabelledHandle(Some("someone-else".to_string()), true);
+        let legacy = LabelledHandle(None, true);
+        let foreign = LabelledHandle(None, false);
+
+        assert!(is_owned_by_current_user(&mine));
+        assert!(!is_owned_by_current_user(&theirs));
+        assert!(is_owned_by_current_user(&legacy));
+        assert!(!is_owned_by_current_user(&foreign));
+    }
+}
@@ -0,0 +1,161 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Build Stages
+//!
+//! A devcontainer image is built in stages: the `base` setup on top of the
+//! base image, the `features`, the `env` setup of environment variables and
+//! the Docker group, and the `dotfiles` helper. `devcon build --until` stops
+//! after a stage and the `--skip-*` flags leave stages out, to debug one
+//! component without waiting for the others.
+//!
+//! Images missing a stage are tagged `:partial` and never replace the image
+//! of the workspace.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+
+/// A stage of the image build, ordered as built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BuildStage {
+    Base,
+    Features,
+    Env,
+    Dotfiles,
+}
+
+impl BuildStage {
+    /// All stages in build order.
+    pub const ALL: [BuildStage; 4] = [Self::Base, Self::Features, Self::Env, Self::Dotfiles];
+}
+
+impl fmt::Display for BuildStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Base => "base",
+            Self::Features => "features",
+            Self::Env => "env",
+            Self::Dotfiles => "dotfiles",
+        })
+    }
+}
+
+impl FromStr for BuildStage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Self::ALL.into_iter().find(|stage| stage.to_string() == s) {
+            Some(stage) => Ok(stage),
+            None => bail!(
+                "Unknown build stage '{}', expected base, features, env or dotfiles",
+                s
+            ),
+        }
+    }
+}
+
+/// The stages a build runs, all by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildStages {
+    until: Option<BuildStage>,
+    skip: Vec<BuildStage>,
+}
+
+impl BuildStages {
+    /// Selects the stages up to `until`, without the `skip`ped ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the base stage is skipped, every other stage
+    /// builds on it.
+    pub fn new(until: Option<BuildStage>, skip: Vec<BuildStage>) -> Result<Self> {
+        if skip.contains(&BuildStage::Base) {
+            bail!("The base stage cannot be skipped");
+        }
+        Ok(Self { until, skip })
+    }
+
+    /// Returns true if `stage` is built.
+    pub fn includes(&self, stage: BuildStage) -> bool {
+        !self.skip.contains(&stage) && self.until.is_none_or(|until| stage <= until)
+    }
+
+    /// Returns the stages which are not built.
+    pub fn excluded(&self) -> Vec<BuildStage> {
+        BuildStage::ALL
+            .into_iter()
+            .filter(|stage| !self.includes(*stage))
+            .collect()
+    }
+
+    /// Returns true if a stage is left out.
+    pub fn is_partial(&self) -> bool {
+        !self.excluded().is_empty()
+    }
+
+    /// Returns the tag of the built image: `image_tag` itself for complete
+    /// builds, `image_tag:partial` otherwise.
+    pub fn image_tag(&self, image_tag: &str) -> String {
+        if self.is_partial() {
+            format!("{}:partial", image_tag)
+        } else {
+            image_tag.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_from_str() {
+        for stage in BuildStage::ALL {
+            assert_eq!(stage.to_string().parse::<BuildStage>().unwrap(), stage);
+        }
+        assert!("final".parse::<BuildStage>().is_err());
+    }
+
+    #[test]
+    fn test_until_and_skip() {
+        let all = BuildStages::default();
+        assert!(BuildStage::ALL.into_iter().all(|stage| all.includes(stage)));
+        assert!(!all.is_partial());
+        assert_eq!(all.image_tag("devcon-app"), "devcon-app");
+
+        let until = BuildStages::new(Some(BuildStage::Features), vec![]).unwrap();
+        assert!(until.includes(BuildStage::Features));
+        assert_eq!(
+            until.excluded(),
+            vec![BuildStage::Env, BuildStage::Dotfiles]
+        );
+        assert_eq!(until.image_tag("devcon-app"), "devcon-app:partial");
+
+        let skip = BuildStages::new(None, vec![BuildStage::Dotfiles]).unwrap();
+        assert!(skip.includes(BuildStage::Env));
+        assert_eq!(skip.excluded(), vec![BuildStage::Dotfiles]);
+
+        assert!(BuildStages::new(None, vec![BuildStage::Base]).is_err());
+    }
+}
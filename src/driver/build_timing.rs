@@ -0,0 +1,185 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Build Timings
+//!
+//! `devcon up` reports where the time of a build went: resolving feature
+//! dependencies, downloading each feature, building each Dockerfile stage
+//! and running each lifecycle command. The driver records [`Timing`]s in
+//! [`BuildTimings`] while it works.
+//!
+//! Stage durations are taken from the build output. BuildKit prints the
+//! stage of every step, e.g. `#7 [feature_0 2/4] RUN ...`, and its duration,
+//! e.g. `#7 DONE 12.3s`, which [`stage_durations`] sums per stage. Builders
+//! without these lines are reported as a single stage.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// What a timing was measured for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimingKind {
+    /// Resolving and ordering feature dependencies
+    Resolve,
+    /// Downloading a feature, or finding it in the cache
+    Download,
+    /// Building a Dockerfile stage
+    Stage,
+    /// Running a lifecycle command
+    Lifecycle,
+}
+
+impl std::fmt::Display for TimingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Resolve => "resolve",
+            Self::Download => "download",
+            Self::Stage => "stage",
+            Self::Lifecycle => "lifecycle",
+        })
+    }
+}
+
+/// A measured step of a build.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Timing {
+    pub kind: TimingKind,
+    pub name: String,
+    /// Duration in seconds
+    pub seconds: f64,
+}
+
+/// Timings recorded while building and starting a workspace, in order.
+#[derive(Debug, Default)]
+pub struct BuildTimings {
+    timings: Mutex<Vec<Timing>>,
+}
+
+impl BuildTimings {
+    /// Records the duration of a step.
+    pub fn record(&self, kind: TimingKind, name: impl Into<String>, duration: Duration) {
+        self.timings.lock().unwrap().push(Timing {
+            kind,
+            name: name.into(),
+            seconds: duration.as_secs_f64(),
+        });
+    }
+
+    /// Returns the recorded timings.
+    pub fn timings(&self) -> Vec<Timing> {
+        self.timings.lock().unwrap().clone()
+    }
+
+    /// Returns the sum of all recorded timings in seconds.
+    pub fn total_seconds(&self) -> f64 {
+        self.timings.lock().unwrap().iter().map(|t| t.seconds).sum()
+    }
+}
+
+/// Sums the step durations of a BuildKit build output per stage, in the
+/// order the stages first appear. Steps of BuildKit itself, e.g.
+/// `[internal] load build definition`, are left out.
+pub fn stage_durations(output: &str) -> Vec<(String, Duration)> {
+    let mut step_stages: HashMap<&str, &str> = HashMap::new();
+    let mut stages: Vec<(String, Duration)> = Vec::new();
+
+    for line in output.lines() {
+        let Some((step, rest)) = line.strip_prefix('#').and_then(|line| line.split_once(' '))
+        else {
+            continue;
+        };
+        if !step.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        if let Some(label) = rest.strip_prefix('[') {
+            let stage = label.split([' ', ']']).next().unwrap_or_default();
+            if stage != "internal" && !stage.is_empty() {
+                step_stages.entry(step).or_insert(stage);
+            }
+        } else if let Some(seconds) = rest
+            .strip_prefix("DONE ")
+            .and_then(|done| done.trim().strip_suffix('s'))
+            .and_then(|seconds| seconds.parse::<f64>().ok())
+            && let Some(stage) = step_stages.get(step)
+        {
+            let duration = Duration::from_secs_f64(seconds);
+            match stages.iter_mut().find(|(name, _)| name == stage) {
+                Some((_, total)) => *total += duration,
+                None => stages.push((stage.to_string(), duration)),
+            }
+        }
+    }
+    stages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_durations() {
+        let output = "\
+#1 [internal] load build definition from Dockerfile
+#1 DONE 0.1s
+#4 [base 1/2] FROM docker.io/library/ubuntu@sha256:abc
+#4 DONE 2.5s
+#5 [feature_0 1/3] COPY ./node /tmp/features/node
+#6 [feature_0 2/3] RUN cd /tmp/features/node && ./install.sh
+#6 0.512 installing node
+#5 DONE 0.2s
+#6 DONE 40.0s
+#7 [base 2/2] RUN true
+#7 CACHED
+#7 DONE 0.5s
+";
+        let stages = stage_durations(output);
+        assert_eq!(
+            stages,
+            vec![
+                ("base".to_string(), Duration::from_secs(3)),
+                ("feature_0".to_string(), Duration::from_secs_f64(40.2)),
+            ]
+        );
+        assert!(stage_durations("STEP 1/3: FROM ubuntu\n").is_empty());
+    }
+
+    #[test]
+    fn test_build_timings() {
+        let timings = BuildTimings::default();
+        timings.record(TimingKind::Download, "node", Duration::from_millis(1500));
+        timings.record(TimingKind::Stage, "base", Duration::from_secs(2));
+
+        assert_eq!(timings.total_seconds(), 3.5);
+        assert_eq!(
+            serde_json::to_value(timings.timings()).unwrap(),
+            serde_json::json!([
+                {"kind": "download", "name": "node", "seconds": 1.5},
+                {"kind": "stage", "name": "base", "seconds": 2.0},
+            ])
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Cancellation
+//!
+//! Image builds and feature downloads check a [`CancelToken`] between steps
+//! and stop the runtime build process once it is cancelled. The token can be
+//! cancelled from any thread, e.g. by a UI.
+//!
+//! While an [`InterruptGuard`] is alive, Ctrl+C cancels its token instead of
+//! terminating devcon, so the build context is removed and the feature cache
+//! is not left half written. A second Ctrl+C terminates devcon immediately.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::driver::runtime::RuntimeError;
+
+/// Exit code of devcon when a second Ctrl+C terminates it, as for SIGINT.
+#[cfg(unix)]
+const INTERRUPT_EXIT_CODE: i32 = 130;
+
+/// Number of live interrupt guards.
+static ACTIVE_GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Shared flag to cancel a long running operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the operation, all clones of the token observe it.
+    #[allow(dead_code)]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Checks the token between steps of an operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token was cancelled.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(RuntimeError::Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// Cancels a token on Ctrl+C for as long as it is alive.
+///
+/// Guards may be nested, the default Ctrl+C behavior is restored once the
+/// last one is dropped.
+pub struct InterruptGuard {
+    #[cfg(unix)]
+    id: signal_hook::SigId,
+    #[cfg(unix)]
+    exit_on_interrupt: Arc<AtomicBool>,
+}
+
+impl InterruptGuard {
+    /// Starts cancelling `token` on Ctrl+C.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signal handler cannot be installed.
+    pub fn new(token: &CancelToken) -> anyhow::Result<Self> {
+        #[cfg(unix)]
+        {
+            let exit_on_interrupt = exit_on_interrupt()?;
+            let id = signal_hook::flag::register(signal_hook::consts::SIGINT, token.0.clone())?;
+            if ACTIVE_GUARDS.fetch_add(1, Ordering::SeqCst) == 0 {
+                exit_on_interrupt.store(false, Ordering::SeqCst);
+            }
+            Ok(Self {
+                id,
+                exit_on_interrupt,
+            })
+        }
+
+        // Without signal handling Ctrl+C keeps terminating devcon
+        #[cfg(not(unix))]
+        {
+            let _ = token;
+            ACTIVE_GUARDS.fetch_add(1, Ordering::SeqCst);
+            Ok(Self {})
+        }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        signal_hook::low_level::unregister(self.id);
+        if ACTIVE_GUARDS.fetch_sub(1, Ordering::SeqCst) == 1 {
+            #[cfg(unix)]
+            self.exit_on_interrupt.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Installs the process wide Ctrl+C handling once and returns its flag.
+///
+/// Signal handlers cannot be removed again, so Ctrl+C terminates devcon
+/// through this handler whenever the flag is set. It is checked before it is
+/// set, so within a guard only the second Ctrl+C terminates.
+#[cfg(unix)]
+fn exit_on_interrupt() -> anyhow::Result<Arc<AtomicBool>> {
+    use signal_hook::consts::SIGINT;
+    use std::sync::Mutex;
+
+    static FLAG: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+    let mut installed = FLAG.lock().unwrap();
+    if let Some(flag) = installed.as_ref() {
+        return Ok(flag.clone());
+    }
+
+    let flag = Arc::new(AtomicBool::new(true));
+    signal_hook::flag::register_conditional_shutdown(SIGINT, INTERRUPT_EXIT_CODE, flag.clone())?;
+    signal_hook::flag::register(SIGINT, flag.clone())?;
+    *installed = Some(flag.clone());
+    Ok(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(token.check().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt_cancels_token() {
+        let token = CancelToken::new();
+        let guard = InterruptGuard::new(&token).unwrap();
+        let nested = InterruptGuard::new(&CancelToken::new()).unwrap();
+        drop(nested);
+
+        // Only the first Ctrl+C is handled, a second one would end the tests
+        signal_hook::low_level::raise(signal_hook::consts::SIGINT).unwrap();
+        assert!(token.is_cancelled());
+        drop(guard);
+    }
+}
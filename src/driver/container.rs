@@ -30,887 +30,3572 @@
 //! The `ContainerDriver` handles:
 //! - Building container images from devcontainer configurations
 //! - Generating Dockerfiles with feature installations
+//! - Sharing images with installed features across projects, tagged in
+//!   `devcon-feature-cache` by the base image, feature files and options
 //! - Starting containers with appropriate volume mounts
 //!
 //! ## Usage
 //!
 //! ```no_run
-//! use devcon::devcontainer::Devcontainer;
+//! use devcon::config::Config;
 //! use devcon::driver::container::ContainerDriver;
+//! use devcon::driver::runtime::create_runtime;
+//! use devcon::workspace::Workspace;
 //! use std::path::PathBuf;
 //!
 //! # fn example() -> anyhow::Result<()> {
-//! let config = Devcontainer::try_from(PathBuf::from("/path/to/project"))?;
-//! let driver = ContainerDriver::new(&config);
+//! let config = Config::load()?;
+//! let runtime = create_runtime(&config, &config.resolve_runtime()?)?;
+//! let driver = ContainerDriver::new(config, runtime);
+//! let workspace = Workspace::open(PathBuf::from("/path/to/project"), None)?;
 //!
 //! // Build the container image
-//! driver.build()?;
+//! driver.build(workspace.clone(), &[], None)?;
 //!
 //! // Start the container
-//! driver.start(PathBuf::from("/path/to/project"))?;
+//! driver.start(workspace, &[])?;
 //! # Ok(())
 //! # }
 //! ```
 
+use std::collections::BTreeMap;
 use std::fs::{self, File};
+use std::ops::RangeInclusive;
 use std::path::Path;
 
-use anyhow::bail;
+use anyhow::{Context, bail};
+use devcon_proto::AutoForwardAction;
+use devcon_proto::auto_forward::{self, AutoForward, AutoForwardPolicy, PortActions};
+use devcon_proto::bandwidth::{BandwidthLimits, parse_bandwidth};
+use devcon_proto::noise;
 use minijinja::Environment;
 use sha2::{Digest, Sha256};
 use tempfile::TempDir;
 use tracing::{Level, debug, info, trace, warn};
 
-use crate::devcontainer::{FeatureRef, FeatureSource};
+use crate::credentials;
+use crate::devcontainer::{Devcontainer, FeatureRef, FeatureSource, OnAutoForward};
+use crate::download::DownloadPolicy;
 use crate::driver::agent::{self, AgentConfig};
-use crate::driver::feature_process::FeatureProcessResult;
-use crate::driver::runtime::RuntimeParameters;
+use crate::driver::audit::{self, AuditEntry};
+use crate::driver::build_stage::{BuildStage, BuildStages};
+use crate::driver::build_timing::{self, BuildTimings, TimingKind};
+use crate::driver::cancel::{CancelToken, InterruptGuard};
+use crate::driver::effective::{self, EffectiveConfig};
+use crate::driver::environment;
+use crate::driver::feature_options;
+use crate::driver::feature_process::{self, FeatureProcessResult, missing_cached_features};
+use crate::driver::image_metadata::{self, HookCommand, MergedMetadata, MetadataEntry};
+use crate::driver::oci::HttpOciClient;
+use crate::driver::open;
+use crate::driver::propagation;
+use crate::driver::registry_cache;
+use crate::driver::runtime::{
+    BuildOptions, BuildOutput, BuildSecret, ContainerDetails, ContainerHandle, ResourceLimits,
+    RuntimeError, RuntimeParameters, StreamMessages, stream_output,
+};
+use crate::driver::sbom;
+use crate::driver::signature;
+use crate::driver::snapshot;
+use crate::driver::tunnel_key;
+use crate::driver::vscode::VscodeCustomizations;
+use crate::driver::workspace_state::{self, BackgroundHook, LifecycleProgress, WorkspaceState};
+use crate::http_proxy::ProxySettings;
+use crate::project::ProjectConfig;
+use crate::secrets::{self, SecretRef};
 use crate::{
-    config::Config, devcontainer::LifecycleCommand, driver::feature_process::process_features,
-    driver::runtime::ContainerRuntime, workspace::Workspace,
+    config::{Config, DotfilesRepository},
+    devcontainer::{LifecycleCommand, LifecycleCommandValue},
+    driver::feature_process::process_features,
+    driver::runtime::ContainerRuntime,
+    workspace::Workspace,
 };
 use std::path::PathBuf;
 
-/// Applies a manual override to the feature installation order.
-///
-/// Reorders features according to the specified feature IDs, keeping any
-/// features not mentioned in the override list at the end in their original order.
+/// Converts a feature option id into an environment variable name.
 ///
-/// # Arguments
+/// Following the devcontainer spec, characters which are not valid in a variable
+/// name are replaced with `_`, a leading digit is prefixed with `_` and the
+/// result is upper-cased.
+fn feature_option_env_name(option_id: &str) -> String {
+    let mut name: String = option_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Quotes a string for safe use as a single word in a POSIX shell.
 ///
-/// * `features` - The features in their dependency-sorted order
-/// * `override_order` - List of feature IDs specifying the desired order
+/// The value is wrapped in single quotes, embedded single quotes are written
+/// as `'\''`. Newlines and all other characters are preserved literally.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Substitutes the host variables of `workspaceFolder` and `workspaceMount`,
+/// which cannot refer to the container workspace themselves.
+fn substitute_workspace_variables(value: &str, devcontainer_workspace: &Workspace) -> String {
+    let basename = devcontainer_workspace
+        .path
+        .file_name()
+        .unwrap()
+        .to_string_lossy();
+    let local_workspace = host_mount_path(&devcontainer_workspace.path.to_string_lossy());
+    substitute_local_env(
+        &value
+            .replace("${localWorkspaceFolderBasename}", &basename)
+            .replace("${localWorkspaceFolder}", &local_workspace),
+        |name| std::env::var(name).ok(),
+    )
+}
+
+/// Returns the default target of the workspace mount, `/workspaces/<folder name>`.
+fn default_workspace_folder(devcontainer_workspace: &Workspace) -> String {
+    format!(
+        "/workspaces/{}",
+        devcontainer_workspace
+            .path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+    )
+}
+
+/// Returns the workspace folder in the container: the `workspaceFolder` of
+/// the devcontainer, else the target of its `workspaceMount`, else the
+/// default mount target.
+fn container_workspace_folder(devcontainer_workspace: &Workspace) -> String {
+    let devcontainer = &devcontainer_workspace.devcontainer;
+    if let Some(folder) = &devcontainer.workspace_folder {
+        return substitute_workspace_variables(folder, devcontainer_workspace);
+    }
+    if let Some(mount) = &devcontainer.workspace_mount
+        && let Ok(mount) = WorkspaceMount::parse(&substitute_workspace_variables(
+            mount,
+            devcontainer_workspace,
+        ))
+    {
+        return mount.target;
+    }
+    default_workspace_folder(devcontainer_workspace)
+}
+
+/// The mount of the workspace, `<project>:/workspaces/<folder name>` unless
+/// set by `workspaceMount`.
+#[derive(Debug, PartialEq)]
+struct WorkspaceMount {
+    source: String,
+    target: String,
+    readonly: bool,
+}
+
+impl WorkspaceMount {
+    /// Returns the mount of a workspace with its variables substituted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `workspaceMount` is invalid.
+    fn of(devcontainer_workspace: &Workspace) -> anyhow::Result<Self> {
+        match &devcontainer_workspace.devcontainer.workspace_mount {
+            Some(mount) => Self::parse(&substitute_workspace_variables(
+                mount,
+                devcontainer_workspace,
+            )),
+            None => Ok(Self {
+                source: host_mount_path(&devcontainer_workspace.path.to_string_lossy()),
+                target: default_workspace_folder(devcontainer_workspace),
+                readonly: false,
+            }),
+        }
+    }
+
+    /// Parses a mount in `--mount` syntax, e.g.
+    /// `source=/repo,target=/workspace,type=bind,consistency=cached`, or in
+    /// the `source:target[:ro]` form.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source or target is missing or the mount type
+    /// is neither `bind` nor `volume`.
+    fn parse(mount: &str) -> anyhow::Result<Self> {
+        if !mount.contains('=') {
+            let mut parts = mount.splitn(3, ':');
+            let (Some(source), Some(target)) = (parts.next(), parts.next()) else {
+                bail!("Invalid workspaceMount {}, expected source:target", mount);
+            };
+            return Ok(Self {
+                source: source.to_string(),
+                target: target.to_string(),
+                readonly: parts
+                    .next()
+                    .is_some_and(|options| options.split(',').any(|option| option == "ro")),
+            });
+        }
+
+        let (mut source, mut target, mut readonly) = (None, None, false);
+        for option in mount.split(',') {
+            match option.split_once('=') {
+                Some(("source" | "src", value)) => source = Some(value),
+                Some(("target" | "destination" | "dst", value)) => target = Some(value),
+                Some(("readonly" | "ro", value)) => readonly = value != "false",
+                Some(("type", mount_type)) if mount_type != "bind" && mount_type != "volume" => {
+                    bail!("Unsupported workspaceMount type {}", mount_type)
+                }
+                None if option == "readonly" || option == "ro" => readonly = true,
+                _ => {}
+            }
+        }
+        let (Some(source), Some(target)) = (source, target) else {
+            bail!("workspaceMount {} needs a source and a target", mount);
+        };
+        Ok(Self {
+            source: host_mount_path(source),
+            target: target.to_string(),
+            readonly,
+        })
+    }
+
+    /// Formats the mount as `-v` argument of the runtimes.
+    fn volume(&self, selinux_relabel: bool) -> String {
+        let mut options = Vec::new();
+        if self.readonly {
+            options.push("ro");
+        }
+        if selinux_relabel {
+            options.push("Z");
+        }
+        let mut volume = format!("{}:{}", self.source, self.target);
+        if !options.is_empty() {
+            volume.push(':');
+            volume.push_str(&options.join(","));
+        }
+        volume
+    }
+}
+
+/// Builds the shell script running an alias command in the workspace folder.
+fn alias_script(devcontainer_workspace: &Workspace, command: &str, args: &[String]) -> String {
+    let workspace_folder = container_workspace_folder(devcontainer_workspace);
+
+    let mut script = format!("cd {} && {}", shell_quote(&workspace_folder), command);
+    for arg in args {
+        script.push(' ');
+        script.push_str(&shell_quote(arg));
+    }
+    script
+}
+
+/// Retries removing a volume or image until a stopped container released it.
 ///
-/// # Returns
+/// Containers are started with `--rm`, so their volumes and image can still
+/// be in use for a moment after they were stopped.
+fn retry_until_released(mut remove: impl FnMut() -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let mut attempts = 0;
+    loop {
+        match remove() {
+            Ok(()) => return Ok(()),
+            Err(_) if attempts < 10 => {
+                attempts += 1;
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Formats a feature option as an `export` line for `devcontainer-features.env`.
 ///
-/// Reordered vector of features
+/// Strings are used as-is, `null` becomes an empty value and all other JSON
+/// values (booleans, numbers) use their JSON representation.
+fn format_feature_env_line(option_id: &str, value: &serde_json::Value) -> String {
+    let value = match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    format!(
+        "export {}={}",
+        feature_option_env_name(option_id),
+        shell_quote(&value)
+    )
+}
+
+/// Collects the bandwidth limits from `portsAttributes` and `otherPortsAttributes`.
 ///
-/// # Errors
+/// Attribute keys may be a single port or a `START-END` range. Invalid keys or
+/// bandwidth values are skipped with a warning.
+fn bandwidth_limits(devcontainer: &Devcontainer) -> BandwidthLimits {
+    let mut limits = BandwidthLimits::default();
+
+    let mut attributes: Vec<_> = devcontainer
+        .ports_attributes
+        .iter()
+        .flatten()
+        .filter_map(|(key, attrs)| attrs.max_bandwidth.as_ref().map(|bw| (key, bw)))
+        .collect();
+    attributes.sort_by_key(|(key, _)| key.as_str());
+
+    for (key, bandwidth) in attributes {
+        match format!("{}={}", key, bandwidth).parse::<BandwidthLimits>() {
+            Ok(parsed) => limits.rules.extend(parsed.rules),
+            Err(e) => warn!("Ignoring maxBandwidth for port '{}': {}", key, e),
+        }
+    }
+
+    if let Some(bandwidth) = devcontainer
+        .other_ports_attributes
+        .as_ref()
+        .and_then(|attrs| attrs.max_bandwidth.as_ref())
+    {
+        match parse_bandwidth(bandwidth) {
+            Ok(rate) => limits.default = Some(rate),
+            Err(e) => warn!("Ignoring maxBandwidth of otherPortsAttributes: {}", e),
+        }
+    }
+
+    limits
+}
+
+/// Reads a port list of `customizations.devcon`, either a string like
+/// "3000-3999,8080" or an array of ports and ranges.
 ///
-/// Returns an error if a feature ID in the override list is not found
-fn apply_feature_order_override(
-    features: Vec<FeatureProcessResult>,
-    override_order: &[String],
-) -> anyhow::Result<Vec<FeatureProcessResult>> {
-    let mut ordered = Vec::new();
-    let mut remaining = features.clone();
-
-    // Process each ID in the override order
-    for feature_id in override_order {
-        if let Some(pos) = remaining.iter().position(|f| &f.feature.id == feature_id) {
-            ordered.push(remaining.remove(pos));
-        } else {
-            warn!(
-                "Feature '{}' specified in overrideFeatureInstallOrder not found",
-                feature_id
-            );
+/// Invalid lists are skipped with a warning.
+fn customization_ports(devcontainer: &Devcontainer, key: &str) -> Option<Vec<RangeInclusive<u16>>> {
+    let value = devcontainer
+        .customizations
+        .as_ref()
+        .and_then(|c| c.get("devcon"))
+        .and_then(|devcon| devcon.get(key))?;
+    let list = match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match auto_forward::parse_port_ranges(&list) {
+        Ok(ranges) => Some(ranges),
+        Err(e) => {
+            warn!("Ignoring customizations.devcon.{}: {}", key, e);
+            None
         }
     }
+}
+
+/// Builds the auto-forward policy of the agent from the config policy and
+/// the project's settings.
+///
+/// The project narrows the config policy with the port lists
+/// `customizations.devcon.autoForwardPorts` and `autoForwardIgnorePorts`, and
+/// with `onAutoForward` of `portsAttributes` and `otherPortsAttributes`: ports
+/// attributed with "ignore" are never forwarded automatically, and if other
+/// ports are ignored, only the attributed ports are.
+fn auto_forward_policy(
+    config: &AutoForwardPolicy,
+    devcontainer: &Devcontainer,
+) -> AutoForwardPolicy {
+    let attributed: Vec<(RangeInclusive<u16>, bool)> = devcontainer
+        .ports_attributes
+        .iter()
+        .flatten()
+        .filter_map(|(key, attrs)| {
+            let (start, end) = key.split_once('-').unwrap_or((key, key));
+            let range = start.trim().parse::<u16>().ok()?..=end.trim().parse::<u16>().ok()?;
+            let ignored = matches!(attrs.on_auto_forward, Some(OnAutoForward::Ignore));
+            Some((range, ignored))
+        })
+        .collect();
+    let others_ignored = matches!(
+        devcontainer
+            .other_ports_attributes
+            .as_ref()
+            .and_then(|attrs| attrs.on_auto_forward.as_ref()),
+        Some(OnAutoForward::Ignore)
+    );
 
-    // Append any features not mentioned in the override order
-    ordered.extend(remaining);
+    let mut project_allowed = customization_ports(devcontainer, "autoForwardPorts");
+    if others_ignored || project_allowed.is_some() {
+        let allowed = project_allowed.get_or_insert_with(Vec::new);
+        allowed.extend(
+            attributed
+                .iter()
+                .filter(|(_, ignored)| !ignored)
+                .map(|(range, _)| range.clone()),
+        );
+    }
 
-    debug!(
-        "Applied override order. Final feature order: {:?}",
-        ordered.iter().map(|f| &f.feature.id).collect::<Vec<_>>()
+    let mut denied =
+        customization_ports(devcontainer, "autoForwardIgnorePorts").unwrap_or_default();
+    denied.extend(config.denied.iter().cloned());
+    denied.extend(
+        attributed
+            .iter()
+            .filter(|(_, ignored)| *ignored)
+            .map(|(range, _)| range.clone()),
     );
 
-    Ok(ordered)
+    let (mode, allowed) = match (config.mode, project_allowed) {
+        (AutoForward::None, _) => (AutoForward::None, Vec::new()),
+        (AutoForward::Allowlist, Some(project)) => (
+            AutoForward::Allowlist,
+            auto_forward::intersect_port_ranges(&config.allowed, &project),
+        ),
+        (AutoForward::Allowlist, None) => (AutoForward::Allowlist, config.allowed.clone()),
+        (AutoForward::All, Some(project)) => (AutoForward::Allowlist, project),
+        (AutoForward::All, None) => (AutoForward::All, Vec::new()),
+    };
+    AutoForwardPolicy {
+        mode,
+        allowed,
+        denied,
+    }
 }
 
-/// Driver for managing container build and runtime operations.
+/// Collects the actions of `onAutoForward` in `portsAttributes` and
+/// `otherPortsAttributes` which the host takes for forwarded ports.
 ///
-/// This struct encapsulates the logic for building container images
-/// and starting container instances based on devcontainer configurations.
-pub struct ContainerDriver {
-    config: Config,
-    runtime: Box<dyn ContainerRuntime>,
+/// The browser variants all open the browser once per container, "ignore"
+/// is handled by the auto-forward policy instead.
+fn auto_forward_actions(devcontainer: &Devcontainer) -> PortActions {
+    let action = |on_auto_forward: &OnAutoForward| match on_auto_forward {
+        OnAutoForward::Notify => Some(AutoForwardAction::Notify),
+        OnAutoForward::OpenBrowser
+        | OnAutoForward::OpenBrowserOnce
+        | OnAutoForward::OpenPreview => Some(AutoForwardAction::OpenBrowser),
+        OnAutoForward::Silent => Some(AutoForwardAction::None),
+        OnAutoForward::Ignore => None,
+    };
+
+    let mut rules: Vec<_> = devcontainer
+        .ports_attributes
+        .iter()
+        .flatten()
+        .filter_map(|(key, attrs)| {
+            let (start, end) = key.split_once('-').unwrap_or((key, key));
+            let range = start.trim().parse::<u16>().ok()?..=end.trim().parse::<u16>().ok()?;
+            Some((range, action(attrs.on_auto_forward.as_ref()?)?))
+        })
+        .collect();
+    rules.sort_by_key(|(range, _)| *range.start());
+
+    PortActions {
+        rules,
+        default: devcontainer
+            .other_ports_attributes
+            .as_ref()
+            .and_then(|attrs| attrs.on_auto_forward.as_ref())
+            .and_then(action),
+    }
 }
 
-impl ContainerDriver {
-    /// Creates a new container driver.
-    ///
-    /// # Examples
-    ///
-    /// ```no_run
-    /// # use devcon::driver::ContainerDriver;
-    /// # use devcon::config::Config;
-    /// # use devcon::runtime::docker::DockerRuntime;
-    /// let config = Config::load()?;
-    /// let runtime = Box::new(DockerRuntime::new()?);
-    /// let driver = ContainerDriver::new(config, runtime);
-    /// # Ok::<(), anyhow::Error>(())
-    /// ```
-    pub fn new(config: Config, runtime: Box<dyn ContainerRuntime>) -> Self {
-        Self { config, runtime }
+/// Collects the ports whose `portsAttributes` disable tunnel compression.
+///
+/// Attribute keys may be a single port or a `START-END` range, other keys are ignored.
+fn uncompressed_ports(devcontainer: &Devcontainer) -> Vec<u16> {
+    let mut ports: Vec<u16> = devcontainer
+        .ports_attributes
+        .iter()
+        .flatten()
+        .filter(|(_, attrs)| attrs.compress == Some(false))
+        .filter_map(|(key, _)| {
+            let (start, end) = key.split_once('-').unwrap_or((key, key));
+            Some(start.trim().parse::<u16>().ok()?..=end.trim().parse::<u16>().ok()?)
+        })
+        .flatten()
+        .collect();
+    ports.sort_unstable();
+    ports.dedup();
+    ports
+}
+
+/// Returns the lifecycle hook of `waitFor`, `updateContentCommand` by default
+/// like in the devcontainer spec.
+fn wait_for_hook(devcontainer: &Devcontainer) -> &'static str {
+    devcontainer
+        .wait_for
+        .as_ref()
+        .map_or("updateContentCommand", |wait_for| wait_for.hook_name())
+}
+
+/// Returns the host of a repository URL, e.g. `github.com` of
+/// `https://user@github.com/user/dotfiles`.
+fn repository_host(url: &str) -> Option<&str> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    (!host.is_empty()).then_some(host)
+}
+
+/// Environment of the dotfiles helper for one repository.
+///
+/// The credentials stored with `devcon login <host>` for the repository's
+/// host are passed along, so private repositories can be cloned.
+fn dotfiles_env(config: &Config, dotfiles: &DotfilesRepository) -> Vec<String> {
+    let repo = dotfiles.repo.as_str();
+    let mut env = vec![format!("DOTFILES_REPOSITORY={}", repo)];
+    let settings = [
+        ("DOTFILES_INSTALL_COMMAND", dotfiles.install_command.clone()),
+        ("DOTFILES_BRANCH", dotfiles.branch.clone()),
+        (
+            "DOTFILES_DEPTH",
+            config.dotfiles_depth.map(|depth| depth.to_string()),
+        ),
+        ("DOTFILES_TARGET", dotfiles.target_dir.clone()),
+        ("DOTFILES_RERUN", config.dotfiles_rerun.clone()),
+    ];
+    for (name, value) in settings {
+        if let Some(value) = value {
+            env.push(format!("{}={}", name, value));
+        }
     }
 
-    /// Prepares features for building or starting a container.
-    ///
-    /// This method:
-    /// 1. Merges additional features from config
-    /// 2. Adds agent installation feature (if not disabled)
-    /// 3. Downloads and processes all features (including dependencies)
-    /// 4. Applies override feature install order if specified
-    ///
-    /// # Arguments
-    ///
-    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
-    ///
-    /// # Returns
-    ///
-    /// Returns a tuple of (processed_features, merged_features) where:
-    /// - `processed_features` - Features processed with dependencies resolved
-    /// - `merged_features` - The initial merged feature list
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if:
-    /// - Feature merging fails
-    /// - Agent generation fails
-    /// - Feature processing fails
-    pub fn prepare_features(
-        &self,
-        devcontainer_workspace: &Workspace,
-    ) -> anyhow::Result<(Vec<FeatureProcessResult>, Vec<FeatureRef>)> {
-        trace!(
-            "Using features of devcontainer: {:?}",
-            devcontainer_workspace.devcontainer.features
-        );
-        trace!(
-            "Adding additional features from config: {:?}",
-            self.config.additional_features
-        );
+    if let Some(host) = repository_host(repo) {
+        match credentials::load(host) {
+            Ok(Some(credential)) => {
+                debug!("Using stored credentials of {} for the dotfiles", host);
+                env.push(format!("DOTFILES_USERNAME={}", credential.username));
+                env.push(format!("DOTFILES_PASSWORD={}", credential.secret));
+            }
+            Ok(None) => {}
+            Err(e) => debug!("No credentials for {}: {:#}", host, e),
+        }
+    }
+    env
+}
 
-        // Merge additional features from config
-        let mut features = devcontainer_workspace
-            .devcontainer
-            .merge_additional_features(&self.config.additional_features)?;
+/// Returns the last `lines` lines of a log file, nothing if it can't be read.
+fn log_tail(path: &Path, lines: usize) -> Vec<String> {
+    let content = fs::read(path).unwrap_or_default();
+    let content = String::from_utf8_lossy(&content);
+    let all: Vec<&str> = content.lines().collect();
+    all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
 
-        // Add agent installation feature to the list
-        // The agent's dependencies will be resolved along with all other features
-        if !self.config.is_agent_disabled() {
-            let agent_config = AgentConfig::new(
-                self.config.get_agent_binary_url().cloned(),
-                self.config.get_agent_git_repository().cloned(),
-                self.config.get_agent_git_branch().cloned(),
-            );
-            debug!("Using agent configuration: {:?}", agent_config);
-            let agent_path = agent::Agent::new(agent_config).generate()?;
-            features.push(FeatureRef::new(FeatureSource::Local { path: agent_path }));
+/// Path of the Docker socket shared with `forwardDockerSocket`.
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// Label recording the instance name of a container, see
+/// [`Workspace::with_instance`].
+pub const INSTANCE_LABEL: &str = "devcon.instance";
+
+/// Adds the private SELinux label option `Z` to a bind mount.
+///
+/// Structured bind mounts become `-v` strings, as `--mount` of Docker can't
+/// relabel. Volumes and mounts in `--mount` syntax are returned unchanged.
+fn selinux_relabeled(mount: crate::devcontainer::Mount) -> crate::devcontainer::Mount {
+    use crate::devcontainer::{Mount, MountType};
+    match mount {
+        Mount::String(s) if s.starts_with('/') => {
+            let parts: Vec<&str> = s.split(':').collect();
+            match parts.as_slice() {
+                [_, _] => Mount::String(format!("{}:Z", s)),
+                [_, _, options]
+                    if !options
+                        .split(',')
+                        .any(|option| option == "z" || option == "Z") =>
+                {
+                    Mount::String(format!("{},Z", s))
+                }
+                _ => Mount::String(s),
+            }
         }
-        debug!("Initial feature list: {:?}", features);
+        Mount::Structured(structured) => match (&structured.mount_type, &structured.source) {
+            (MountType::Bind, Some(source)) => {
+                Mount::String(format!("{}:{}:Z", source, structured.target))
+            }
+            _ => Mount::Structured(structured),
+        },
+        mount => mount,
+    }
+}
 
-        // Process all features including dependency resolution and topological sorting
-        let mut processed_features = process_features(&features)?;
+/// Messages around the output of `initializeCommand`.
+const INITIALIZE_MESSAGES: StreamMessages = StreamMessages {
+    start: "Running initializeCommand..",
+    done: "initializeCommand complete",
+    failed: "initializeCommand failed!",
+};
 
-        // Apply override feature install order if specified
-        if let Some(ref override_order) = devcontainer_workspace
-            .devcontainer
-            .override_feature_install_order
-        {
-            debug!(
-                "Applying override feature install order: {:?}",
-                override_order
+/// Process creation flags detaching the background lifecycle from the console.
+#[cfg(windows)]
+const DETACHED_PROCESS_FLAGS: u32 = 0x0000_0008 | 0x0000_0200;
+
+/// Returns a host path in the form runtimes accept in mounts.
+///
+/// Windows paths like `C:\Users\me\project` become `/c/Users/me/project`, as
+/// the colon of the drive would split a `-v` mount. Other paths are unchanged.
+fn host_mount_path(path: &str) -> String {
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    let bytes = path.as_bytes();
+    let is_drive = bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes.len() == 2 || bytes[2] == b'\\' || bytes[2] == b'/');
+    if is_drive {
+        format!(
+            "/{}{}",
+            (bytes[0] as char).to_ascii_lowercase(),
+            path[2..].replace('\\', "/")
+        )
+    } else {
+        path.to_string()
+    }
+}
+
+/// Arguments running a command string in the host shell.
+fn host_shell(command: &str) -> Vec<String> {
+    if cfg!(windows) {
+        vec!["cmd".to_string(), "/C".to_string(), command.to_string()]
+    } else {
+        vec!["sh".to_string(), "-c".to_string(), command.to_string()]
+    }
+}
+
+/// Substitutes `${localEnv:NAME}` and `${localEnv:NAME:default}` with the
+/// value of `lookup(NAME)`, the default or an empty string.
+fn substitute_local_env(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    const PREFIX: &str = "${localEnv:";
+
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(PREFIX) {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let variable = &rest[start + PREFIX.len()..start + end];
+        let (name, default) = variable.split_once(':').unwrap_or((variable, ""));
+        result.push_str(&rest[..start]);
+        result.push_str(&lookup(name).unwrap_or_else(|| default.to_string()));
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Longest time a shell waits for the lifecycle hook of `waitFor`.
+const WAIT_FOR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Interval in which a waiting shell checks the lifecycle progress.
+const WAIT_FOR_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Returns the group ID owning the host's Docker socket.
+#[cfg(unix)]
+fn docker_socket_gid() -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(DOCKER_SOCKET).ok().map(|m| m.gid())
+}
+
+#[cfg(not(unix))]
+fn docker_socket_gid() -> Option<u32> {
+    None
+}
+
+/// Prefixes every output line of a shell command with `[prefix]`, keeping
+/// the exit status of the command.
+fn prefix_output(cmd: &str, prefix: &str) -> String {
+    format!(
+        "set -o pipefail; {{ {}\n}} 2>&1 | while IFS= read -r line; do printf '%s %s\\n' {} \"$line\"; done",
+        cmd,
+        shell_quote(&format!("[{}]", prefix))
+    )
+}
+
+/// Builds the script the container runs as its entrypoint with `/bin/sh -c`.
+///
+/// The script starts the feature `entrypoints`, then execs its arguments, the
+/// image command, if any. Without arguments it idles until stopped.
+fn entrypoint_script(entrypoints: &[String]) -> String {
+    let mut script = String::from("echo Container started\ntrap \"exit 0\" 15\n");
+    for entrypoint in entrypoints {
+        script.push_str(entrypoint);
+        script.push('\n');
+    }
+    script.push_str("exec \"$@\"\nwhile sleep 1 & wait $!; do :; done");
+    script
+}
+
+/// Prints the container a dry run would start and its lifecycle commands.
+fn print_run_plan(
+    devcontainer_workspace: &Workspace,
+    image: &str,
+    volume_mount: &str,
+    labels: &[String],
+    env_vars: &[String],
+    parameters: &RuntimeParameters,
+    metadata: &MergedMetadata,
+) {
+    let devcontainer = &devcontainer_workspace.devcontainer;
+    println!("# Run {}", image);
+    println!(
+        "user: {} (container user {})",
+        devcontainer.remote_user.as_deref().unwrap_or("vscode"),
+        devcontainer.container_user.as_deref().unwrap_or("vscode")
+    );
+    println!("workspace: {}", volume_mount);
+    println!("privileged: {}", parameters.requires_privileged);
+    println!("init: {}", parameters.init);
+    if !parameters.cap_add.is_empty() {
+        println!("capabilities: {}", parameters.cap_add.join(", "));
+    }
+    if let Some(cpus) = &parameters.limits.cpus {
+        println!("cpus: {}", cpus);
+    }
+    if let Some(memory) = &parameters.limits.memory {
+        println!("memory: {}", memory);
+    }
+    let sections: [(&str, Vec<String>); 5] = [
+        (
+            "mounts",
+            parameters
+                .additional_mounts
+                .iter()
+                .map(effective::mount_string)
+                .collect(),
+        ),
+        (
+            "ports",
+            parameters.ports.iter().map(|p| p.to_string()).collect(),
+        ),
+        (
+            "published ports",
+            parameters
+                .published_ports
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        ),
+        ("env", env_vars.to_vec()),
+        ("labels", labels.to_vec()),
+    ];
+    for (name, values) in sections {
+        if !values.is_empty() {
+            println!("{}:", name);
+            for value in values {
+                println!("  {}", value);
+            }
+        }
+    }
+    println!("command: {:?}", parameters.command);
+
+    let hooks = [
+        ("onCreateCommand", &metadata.on_create_commands),
+        ("updateContentCommand", &metadata.update_content_commands),
+        ("postCreateCommand", &metadata.post_create_commands),
+        ("postStartCommand", &metadata.post_start_commands),
+        ("postAttachCommand", &metadata.post_attach_commands),
+    ];
+    for (hook, commands) in hooks {
+        for command in commands {
+            println!(
+                "{} ({}): {}",
+                hook,
+                command.feature.as_deref().unwrap_or("devcontainer.json"),
+                serde_json::to_string(&command.command).unwrap_or_default()
             );
-            processed_features = apply_feature_order_override(processed_features, override_order)?;
         }
+    }
+}
 
-        debug!(
-            "Final feature order: {:?}",
-            processed_features
-                .iter()
-                .map(|f| &f.feature.id)
-                .collect::<Vec<_>>()
-        );
+/// Built-in template of the Dockerfile of workspace images, replaced by the
+/// `dockerfileTemplate` setting.
+pub const DOCKERFILE_TEMPLATE: &str = include_str!("templates/Dockerfile.j2");
 
-        Ok((processed_features, features))
+/// Script cloning and installing a dotfiles repository in the container,
+/// configured by the `DOTFILES_*` environment variables
+const DOTFILES_HELPER: &str = r#"#!/bin/sh
+set -e
+target="${DOTFILES_TARGET:-$HOME/.dotfiles}"
+case "$target" in
+    "~/"*) target="$HOME/${target#\~/}" ;;
+esac
+
+# Credentials of private repositories are only passed to this invocation
+git_auth() {
+    if [ -n "$DOTFILES_PASSWORD" ]; then
+        git -c credential.helper= \
+            -c 'credential.helper=!f() { echo "username=$DOTFILES_USERNAME"; echo "password=$DOTFILES_PASSWORD"; }; f' "$@"
+    else
+        git "$@"
+    fi
+}
+
+if [ -d "$target/.git" ]; then
+    if [ "$DOTFILES_RERUN" != "always" ]; then
+        echo "Dotfiles already installed in $target"
+        exit 0
+    fi
+    git_auth -C "$target" pull --ff-only
+else
+    set -- clone
+    if [ -n "$DOTFILES_BRANCH" ]; then
+        set -- "$@" --branch "$DOTFILES_BRANCH"
+    fi
+    if [ -n "$DOTFILES_DEPTH" ]; then
+        set -- "$@" --depth "$DOTFILES_DEPTH"
+    fi
+    git_auth "$@" "$DOTFILES_REPOSITORY" "$target"
+fi
+cd "$target"
+
+installCommand="$DOTFILES_INSTALL_COMMAND"
+if [ -z "$installCommand" ]; then
+    for f in install.sh setup.sh bootstrap.sh script/install.sh script/setup.sh script/bootstrap.sh
+    do
+        if [ -e "$f" ]
+        then
+            installCommand=$f
+            break
+        fi
+    done
+fi
+
+if [ -n "$installCommand" ]; then
+    chmod +x "$installCommand"
+    "./$installCommand" || true
+fi
+"#;
+
+/// Users of a devcontainer image and their home directories.
+struct BuildUsers<'a> {
+    remote_user: &'a str,
+    container_user: &'a str,
+    remote_user_home: String,
+    container_user_home: String,
+}
+
+impl<'a> BuildUsers<'a> {
+    fn of(devcontainer: &'a Devcontainer) -> Self {
+        let remote_user = devcontainer.remote_user.as_deref().unwrap_or("vscode");
+        let container_user = devcontainer.container_user.as_deref().unwrap_or("vscode");
+        let home = |user: &str| {
+            if user == "root" {
+                "/root".to_string()
+            } else {
+                format!("/home/{}", user)
+            }
+        };
+        Self {
+            remote_user,
+            container_user,
+            remote_user_home: home(remote_user),
+            container_user_home: home(container_user),
+        }
     }
+}
 
-    /// Builds a container image with features installed.
-    ///
-    /// This method:
-    /// 1. Creates a temporary directory for the build context
+/// The base stage of an image build.
+struct BaseStage {
+    /// Instructions all features are installed with
+    setup: String,
+    /// Content identifying the setup in the keys of cached feature layers
+    cache_key_content: String,
+}
+
+/// Instructions of the build stages, rendered into the Dockerfile template.
+/// Skipped stages contribute none.
+struct StageInstructions {
+    base_setup: String,
+    feature_install: String,
+    env_setup: String,
+    docker_socket_setup: String,
+    dotfiles_setup: String,
+}
+
+/// Names of the container of a workspace on the networks of its links: its
+/// sanitized name and `<name>.devcon.internal`.
+pub fn network_aliases(devcontainer_workspace: &Workspace) -> Vec<String> {
+    let name = devcontainer_workspace
+        .get_sanitized_name()
+        .replace(['_', '.'], "-");
+    vec![name.clone(), format!("{}.devcon.internal", name)]
+}
+
+/// Name of the network linking two workspaces, the same in either order.
+fn link_network(first: &str, second: &str) -> String {
+    let (first, second) = if first <= second {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    format!("devcon-link-{}--{}", first, second)
+}
+
+/// Builds the dotfiles stage, which adds the dotfiles helper to the image.
+///
+/// # Errors
+///
+/// Returns an error if the helper cannot be written to the build directory.
+fn dotfiles_stage(directory_path: &Path) -> anyhow::Result<String> {
+    fs::write(directory_path.join("dotfiles_helper.sh"), DOTFILES_HELPER)?;
+    Ok(
+        "COPY dotfiles_helper.sh /dotfiles_helper.sh \nRUN chmod +x /dotfiles_helper.sh"
+            .to_string(),
+    )
+}
+
+/// Name of the build context holding the directory of a custom Dockerfile template.
+const TEMPLATE_CONTEXT: &str = "template";
+
+/// Repository of the images with installed features shared across projects.
+pub const FEATURE_CACHE_REPOSITORY: &str = "devcon-feature-cache";
+
+/// Id of the BuildKit secret holding the credentials of `buildSecretRegistries`.
+const REGISTRY_AUTH_SECRET: &str = "registry_auth";
+
+/// Package manager caches kept between feature installs with BuildKit.
+const PACKAGE_CACHES: &[&str] = &[
+    "/var/cache/apt",
+    "/var/lib/apt/lists",
+    "/var/cache/apk",
+    "/var/cache/dnf",
+];
+
+/// `RUN --mount` options of feature installs: package manager caches and,
+/// with `registry_auth`, the registry credentials as Docker config at
+/// `/run/secrets/docker/config.json`.
+fn feature_run_mounts(buildkit: bool, registry_auth: bool) -> String {
+    if !buildkit {
+        return String::new();
+    }
+    let mut mounts: Vec<String> = PACKAGE_CACHES
+        .iter()
+        .map(|target| format!("--mount=type=cache,target={},sharing=locked", target))
+        .collect();
+    if registry_auth {
+        mounts.push(format!(
+            "--mount=type=secret,id={},target=/run/secrets/docker/config.json",
+            REGISTRY_AUTH_SECRET
+        ));
+    }
+    mounts.iter().map(|mount| format!("{} ", mount)).collect()
+}
+
+/// Writes the stored credentials of the registries as Docker config file,
+/// `None` if there are none.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+fn write_registry_auth(registries: &[String], dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let mut auths = serde_json::Map::new();
+    for registry in registries {
+        match credentials::load(registry) {
+            Ok(Some(credential)) => {
+                auths.insert(
+                    registry.clone(),
+                    serde_json::json!({
+                        "username": credential.username,
+                        "password": credential.secret,
+                    }),
+                );
+            }
+            Ok(None) => warn!("No credentials stored for {}, see `devcon login`", registry),
+            Err(e) => warn!("Ignoring stored credentials of {}: {:#}", registry, e),
+        }
+    }
+    if auths.is_empty() {
+        return Ok(None);
+    }
+    let path = dir.join("config.json");
+    fs::write(&path, serde_json::json!({ "auths": auths }).to_string())?;
+    Ok(Some(path))
+}
+
+/// Directory of the `caCertificates` in images and containers.
+const CA_CERTIFICATES_DIR: &str = "/usr/local/share/devcon/ca-certificates";
+
+/// Bundle of all `caCertificates` in images, used by Node.js.
+const CA_BUNDLE: &str = "/usr/local/share/devcon/ca-bundle.crt";
+
+/// File name of the `index`th CA certificate in images and containers.
+fn ca_certificate_name(index: usize, path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}-{}.crt", index, stem)
+}
+
+/// Copies the CA certificates to `ca-certificates/` in the build directory
+/// and returns the instructions installing them into the trust store of the
+/// image, empty without certificates.
+///
+/// Images without `update-ca-certificates` or `update-ca-trust` get the
+/// certificates appended to `/etc/ssl/certs/ca-certificates.crt`.
+fn ca_certificate_setup(certificates: &[PathBuf], build_dir: &Path) -> anyhow::Result<String> {
+    if certificates.is_empty() {
+        return Ok(String::new());
+    }
+    let target = build_dir.join("ca-certificates");
+    fs::create_dir_all(&target)?;
+    for (index, path) in certificates.iter().enumerate() {
+        fs::copy(path, target.join(ca_certificate_name(index, path)))
+            .with_context(|| format!("Failed to copy CA certificate {}", path.display()))?;
+    }
+
+    Ok(format!(
+        r#"COPY ca-certificates/ {dir}/
+RUN cat {dir}/*.crt > {bundle} \
+    && if command -v update-ca-certificates > /dev/null 2>&1; then \
+        mkdir -p /usr/local/share/ca-certificates && cp {dir}/*.crt /usr/local/share/ca-certificates/ && update-ca-certificates; \
+    elif command -v update-ca-trust > /dev/null 2>&1; then \
+        cp {dir}/*.crt /etc/pki/ca-trust/source/anchors/ && update-ca-trust extract; \
+    else \
+        mkdir -p /etc/ssl/certs && cat {dir}/*.crt >> /etc/ssl/certs/ca-certificates.crt; \
+    fi
+ENV NODE_EXTRA_CA_CERTS={bundle}
+"#,
+        dir = CA_CERTIFICATES_DIR,
+        bundle = CA_BUNDLE
+    ))
+}
+
+/// Read-only mounts of the CA certificates at the paths they are installed
+/// from in the image, so containers see the current files.
+fn ca_certificate_mounts(certificates: &[PathBuf]) -> Vec<String> {
+    certificates
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let source = path.canonicalize().unwrap_or_else(|_| path.clone());
+            format!(
+                "{}:{}/{}:ro",
+                source.to_string_lossy(),
+                CA_CERTIFICATES_DIR,
+                ca_certificate_name(index, path)
+            )
+        })
+        .collect()
+}
+
+/// Builds the Dockerfile instructions installing a feature copied to
+/// `path_name` in the build directory, with `run_mounts` from
+/// [`feature_run_mounts`].
+fn feature_stage(
+    feature_result: &FeatureProcessResult,
+    feature_name: &str,
+    path_name: &str,
+    run_mounts: &str,
+) -> String {
+    let mut stage = String::new();
+    if let Some(env_vars) = &feature_result.feature.container_env {
+        // Sorted, so the instructions and cache keys are stable
+        let env_vars: BTreeMap<_, _> = env_vars.iter().collect();
+        for (name, value) in env_vars {
+            stage.push_str(&format!("ENV {}={} \n", name, value));
+        }
+    }
+    stage.push_str(&format!(
+        "COPY {}/. /tmp/features/{}/ \n",
+        path_name, feature_name
+    ));
+    stage.push_str(&format!(
+        "RUN {1}chmod +x /tmp/features/{0}/install.sh && . /tmp/features/{0}/devcontainer-features.env && cd /tmp/features/{0} && ./install.sh\n",
+        feature_name, run_mounts
+    ));
+    stage
+}
+
+/// Content address of an image built from the parent with the key
+/// `parent_key` and the given instructions and files.
+fn feature_cache_key(parent_key: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(parent_key.as_bytes());
+    hasher.update([0]);
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Tag of a cached feature image, e.g. `devcon-feature-cache:node-0123456789abcdef`.
+fn feature_cache_tag(feature_name: &str, key: &str) -> String {
+    let name: String = feature_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}:{}-{}", FEATURE_CACHE_REPOSITORY, name, &key[..16])
+}
+
+/// Hashes the relative paths and contents of all files below `dir`.
+fn hash_directory(dir: &Path) -> anyhow::Result<String> {
+    fn visit(dir: &Path, root: &Path, hasher: &mut Sha256) -> anyhow::Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if path.is_dir() {
+                visit(&path, root, hasher)?;
+            } else {
+                hasher.update(path.strip_prefix(root)?.to_string_lossy().as_bytes());
+                hasher.update([0]);
+                hasher.update(fs::read(&path)?);
+                hasher.update([0]);
+            }
+        }
+        Ok(())
+    }
+
+    let mut hasher = Sha256::new();
+    visit(dir, dir, &mut hasher)
+        .with_context(|| format!("Failed to hash feature directory {}", dir.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Builds the Dockerfile instruction giving `user` access to a Docker socket
+/// owned by `gid`.
+///
+/// Reuses an existing group with that GID, otherwise a `docker` group is
+/// created (or moved to the GID). Works with both shadow-utils and busybox.
+fn docker_group_setup(gid: u32, user: &str) -> String {
+    if user == "root" {
+        return String::new();
+    }
+
+    format!(
+        "RUN group=$(getent group {gid} | cut -d: -f1); \
+         if [ -z \"$group\" ]; then \
+             group=docker; \
+             if getent group docker > /dev/null; then groupmod -g {gid} docker; \
+             else groupadd -g {gid} docker || addgroup -g {gid} docker; fi; \
+         fi; \
+         usermod -aG \"$group\" {user} || addgroup {user} \"$group\"\n"
+    )
+}
+
+/// Whether the project enables encrypted tunnels with
+/// `customizations.devcon.encryptTunnels`.
+fn encrypt_tunnels(devcontainer: &Devcontainer) -> bool {
+    devcontainer
+        .customizations
+        .as_ref()
+        .and_then(|c| c.get("devcon"))
+        .and_then(|devcon| devcon.get("encryptTunnels"))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Secrets referenced by the project with `customizations.devcon.secrets`.
+///
+/// # Errors
+///
+/// Returns an error if the references are malformed.
+fn secret_refs(devcontainer: &Devcontainer) -> anyhow::Result<Vec<SecretRef>> {
+    let Some(value) = devcontainer
+        .customizations
+        .as_ref()
+        .and_then(|c| c.get("devcon"))
+        .and_then(|devcon| devcon.get("secrets"))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let refs: Vec<SecretRef> = serde_json::from_value(value.clone())
+        .context("Invalid customizations.devcon.secrets in devcontainer.json")?;
+    for secret_ref in &refs {
+        secret_ref.validate()?;
+    }
+    Ok(refs)
+}
+
+/// Loads the values of the referenced secrets from the keychain.
+///
+/// # Errors
+///
+/// Returns an error if a secret is not set or the keychain is unavailable.
+fn resolve_secrets(refs: Vec<SecretRef>) -> anyhow::Result<Vec<(SecretRef, String)>> {
+    refs.into_iter()
+        .map(|secret_ref| {
+            let value = secrets::get(&secret_ref.name)?.with_context(|| {
+                format!(
+                    "Secret '{0}' is not set. Run 'devcon secret set {0}' first.",
+                    secret_ref.name
+                )
+            })?;
+            Ok((secret_ref, value))
+        })
+        .collect()
+}
+
+/// Resolves the CPU and memory limits of a container at run time.
+///
+/// The `hostRequirements` of a project are applied as limits, capped by the
+/// `containerCpus` and `containerMemory` settings. The settings alone limit
+/// projects without host requirements. Invalid values are skipped with a warning.
+fn resource_limits(devcontainer: &Devcontainer, config: &Config) -> ResourceLimits {
+    let requirements = devcontainer.host_requirements.as_ref();
+
+    let requested_cpus = requirements.and_then(|r| r.cpus).map(f64::from);
+    let max_cpus = config
+        .container_cpus
+        .as_deref()
+        .and_then(|cpus| match cpus.parse::<f64>() {
+            Ok(cpus) if cpus > 0.0 => Some(cpus),
+            _ => {
+                warn!("Ignoring invalid containerCpus '{}'", cpus);
+                None
+            }
+        });
+    let cpus = match (requested_cpus, max_cpus) {
+        (Some(requested), Some(max)) => Some(requested.min(max)),
+        (requested, max) => requested.or(max),
+    };
+
+    let memory_setting = |memory: &str, setting: &str| {
+        let bytes = parse_memory(memory);
+        if bytes.is_none() {
+            warn!("Ignoring invalid {} '{}'", setting, memory);
+        }
+        bytes
+    };
+    let requested_memory = requirements
+        .and_then(|r| r.memory.as_deref())
+        .and_then(|memory| memory_setting(memory, "hostRequirements.memory"));
+    let max_memory = config
+        .container_memory
+        .as_deref()
+        .and_then(|memory| memory_setting(memory, "containerMemory"));
+    let memory = match (requested_memory, max_memory) {
+        (Some(requested), Some(max)) => Some(requested.min(max)),
+        (requested, max) => requested.or(max),
+    };
+
+    ResourceLimits {
+        cpus: cpus.map(|cpus| cpus.to_string()),
+        memory: memory.map(format_memory),
+    }
+}
+
+/// Parses a memory size like `"8gb"`, `"512m"` or `"1.5GB"` into bytes.
+///
+/// Accepts the `hostRequirements` units (`kb`, `mb`, `gb`, `tb`) as well as
+/// the Docker style suffixes (`k`, `m`, `g`, `t`), a bare number is in bytes.
+fn parse_memory(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let value = value.strip_suffix('b').unwrap_or(&value);
+    let (number, multiplier) = match value.char_indices().last()? {
+        (i, 'k') => (&value[..i], 1u64 << 10),
+        (i, 'm') => (&value[..i], 1 << 20),
+        (i, 'g') => (&value[..i], 1 << 30),
+        (i, 't') => (&value[..i], 1 << 40),
+        _ => (value, 1),
+    };
+    let number = number.trim().parse::<f64>().ok()?;
+    if !number.is_finite() || number <= 0.0 {
+        return None;
+    }
+    Some((number * multiplier as f64) as u64)
+}
+
+/// Formats a number of bytes with the largest exact unit understood by the
+/// runtimes (e.g. `"8g"`), rounding down to kilobytes.
+fn format_memory(bytes: u64) -> String {
+    if bytes >= 1 << 30 && bytes.is_multiple_of(1 << 30) {
+        format!("{}g", bytes >> 30)
+    } else if bytes >= 1 << 20 && bytes.is_multiple_of(1 << 20) {
+        format!("{}m", bytes >> 20)
+    } else {
+        format!("{}k", (bytes >> 10).max(1))
+    }
+}
+
+/// Status of a workspace container as reported by `devcon status`.
+#[derive(Debug)]
+pub struct ContainerStatus {
+    /// ID of the running container, `None` if it is not running.
+    pub container_id: Option<String>,
+
+    /// Limits of the running container, or of the next start.
+    pub limits: ResourceLimits,
+
+    /// Whether the image of the workspace is built.
+    pub image_built: bool,
+
+    /// Whether the image was built from the current configuration, `None`
+    /// if unknown because the build wasn't recorded.
+    pub image_fresh: Option<bool>,
+
+    /// Lifecycle stage of the running container, `None` if not recorded.
+    pub lifecycle_stage: Option<String>,
+
+    /// Lifecycle hooks which completed in the running container.
+    pub lifecycle_hooks: Vec<String>,
+
+    /// Mounts of the running container as "source:destination".
+    pub mounts: Vec<String>,
+}
+
+/// Details of a workspace as reported by `devcon inspect`.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceInspection {
+    /// Merged configuration with the source of each value.
+    pub config: EffectiveConfig,
+
+    /// Features in installation order.
+    pub features: Vec<InspectedFeature>,
+
+    /// Image of the workspace.
+    pub image: InspectedImage,
+
+    /// Runtime details of the running container, `None` if it is not running.
+    pub container: Option<ContainerDetails>,
+}
+
+/// Resolved feature of a workspace.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedFeature {
+    /// ID of the feature.
+    pub id: String,
+
+    /// Registry reference, local path or tarball URL.
+    pub reference: String,
+
+    /// Version of the resolved feature.
+    pub version: String,
+
+    /// Shortened layer digest of a registry feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+/// Image of a workspace.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedImage {
+    /// Tag of the image.
+    pub tag: String,
+
+    /// Labels of the image, `None` if it is not built.
+    pub labels: Option<BTreeMap<String, String>>,
+}
+
+/// Driver for managing container build and runtime operations.
+///
+/// This struct encapsulates the logic for building container images
+/// and starting container instances based on devcontainer configurations.
+pub struct ContainerDriver {
+    config: Config,
+    runtime: Box<dyn ContainerRuntime>,
+    cancel: CancelToken,
+    dry_run: bool,
+    stages: BuildStages,
+    timings: BuildTimings,
+    interactive: bool,
+}
+
+impl ContainerDriver {
+    /// Creates a new container driver.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use devcon::driver::container::ContainerDriver;
+    /// # use devcon::config::Config;
+    /// # use devcon::driver::runtime::docker::DockerRuntime;
+    /// let config = Config::load()?;
+    /// let runtime = Box::new(DockerRuntime::new(
+    ///     config.get_runtime_config().docker.unwrap_or_default(),
+    /// ));
+    /// let driver = ContainerDriver::new(config, runtime);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn new(config: Config, runtime: Box<dyn ContainerRuntime>) -> Self {
+        Self {
+            config,
+            runtime,
+            cancel: CancelToken::new(),
+            dry_run: false,
+            stages: BuildStages::default(),
+            timings: BuildTimings::default(),
+            interactive: false,
+        }
+    }
+
+    /// Only prints what building and starting would do.
+    ///
+    /// The Dockerfiles and the runtime invocation are printed instead of
+    /// executed, the container runtime is not contacted and nothing is run
+    /// on the host or recorded. Features are still resolved.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Only builds the selected stages of images, see
+    /// [`build_stage`](crate::driver::build_stage).
+    pub fn with_stages(mut self, stages: BuildStages) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Whether missing required feature options are asked for on an
+    /// interactive terminal, see
+    /// [`feature_options`](crate::driver::feature_options). Without
+    /// interaction, which is the default, a missing option that wasn't
+    /// answered before fails.
+    pub fn with_interaction(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Returns the timings of the feature downloads, image builds and
+    /// lifecycle commands run by this driver, see
+    /// [`build_timing`](crate::driver::build_timing).
+    pub fn timings(&self) -> &BuildTimings {
+        &self.timings
+    }
+
+    /// Returns the token cancelling feature downloads and builds of this driver.
+    ///
+    /// Ctrl+C cancels it while features are prepared or an image is built.
+    /// Cancelling stops the runtime build process and removes the build
+    /// context, a cancelled driver does not build again.
+    #[allow(dead_code)]
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Verifies that everything needed is available without network access.
+    ///
+    /// Does nothing unless offline mode is enabled. Otherwise all artifacts
+    /// missing locally are reported at once, instead of failing on the first
+    /// network call.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
+    /// * `building` - Whether an image is built, which needs the base image and agent
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing all missing artifacts.
+    pub fn check_offline_artifacts(
+        &self,
+        devcontainer_workspace: &Workspace,
+        building: bool,
+    ) -> anyhow::Result<()> {
+        if !self.config.is_offline() {
+            return Ok(());
+        }
+
+        let features = devcontainer_workspace
+            .devcontainer
+            .merge_additional_features(&self.config.additional_features)?;
+        let mut missing: Vec<String> = missing_cached_features(&features)?
+            .into_iter()
+            .map(|reference| format!("feature {}", reference))
+            .collect();
+
+        if building {
+            if let Some(image) = self.base_image(devcontainer_workspace)?
+                && !self.runtime.image_exists(&image)?
+            {
+                missing.push(format!("base image {}", image));
+            }
+            // The agent feature downloads or compiles the agent during the build
+            if !self.config.is_agent_disabled() {
+                missing.push(
+                    "devcon agent (disable it with 'devcon config set agents.disable true')"
+                        .to_string(),
+                );
+            }
+        }
+
+        if !missing.is_empty() {
+            bail!(
+                "Offline mode is enabled, but these artifacts are not available locally:\n  - {}",
+                missing.join("\n  - ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Prepares features for building or starting a container.
+    ///
+    /// This method:
+    /// 1. Merges additional features from config
+    /// 2. Adds agent installation feature (if not disabled)
+    /// 3. Downloads and processes all features (including dependencies)
+    /// 4. Applies override feature install order if specified
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
+    ///
+    /// # Returns
+    ///
+    /// Returns a tuple of (processed_features, merged_features) where:
+    /// - `processed_features` - Features processed with dependencies resolved
+    /// - `merged_features` - The initial merged feature list
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Feature merging fails
+    /// - Agent generation fails
+    /// - Feature processing fails
+    pub fn prepare_features(
+        &self,
+        devcontainer_workspace: &Workspace,
+    ) -> anyhow::Result<(Vec<FeatureProcessResult>, Vec<FeatureRef>)> {
+        trace!(
+            "Using features of devcontainer: {:?}",
+            devcontainer_workspace.devcontainer.features
+        );
+        trace!(
+            "Adding additional features from config: {:?}",
+            self.config.additional_features
+        );
+
+        let _interrupt = InterruptGuard::new(&self.cancel)?;
+
+        // Merge additional features from config
+        let mut features = devcontainer_workspace
+            .devcontainer
+            .merge_additional_features(&self.config.additional_features)?;
+
+        let download_policy = DownloadPolicy::from_config(&self.config)?;
+
+        // Add agent installation feature to the list
+        // The agent's dependencies will be resolved along with all other features
+        if !self.config.is_agent_disabled() {
+            let mut agent_config = AgentConfig::new(
+                self.config.get_agent_binary_url().cloned(),
+                self.config.get_agent_git_repository().cloned(),
+                self.config.get_agent_git_branch().cloned(),
+            );
+            // Released binaries are built without the gRPC control plane
+            if self.config.get_control_scheme() == "grpc" {
+                agent_config = agent_config.with_grpc();
+            } else if self.config.agent_prebuilt_enabled() {
+                let release_url = self
+                    .config
+                    .get_agent_release_url()
+                    .cloned()
+                    .unwrap_or_else(agent::default_release_url);
+                match agent::prebuilt_binaries(&release_url, &download_policy) {
+                    Ok(dir) => agent_config = agent_config.with_prebuilt(dir),
+                    Err(e) => warn!(
+                        "Prebuilt agent binaries are not available, compiling the agent from source: {:#}",
+                        e
+                    ),
+                }
+            }
+            debug!("Using agent configuration: {:?}", agent_config);
+            let agent_path = agent::Agent::new(agent_config).generate()?;
+            features.push(FeatureRef::new(FeatureSource::Local { path: agent_path }));
+        }
+        debug!("Initial feature list: {:?}", features);
+
+        // Process all features including dependency resolution and ordering
+        let override_order = devcontainer_workspace
+            .devcontainer
+            .override_feature_install_order
+            .as_deref()
+            .unwrap_or_default();
+        let started = std::time::Instant::now();
+        let mut processed_features = process_features(
+            &features,
+            override_order,
+            &HttpOciClient::new(&download_policy)?,
+            &download_policy,
+            &self.cancel,
+        )?;
+        // Everything but the downloads is spent resolving dependencies
+        let mut resolve_time = started.elapsed();
+        for feature in &processed_features {
+            self.timings
+                .record(TimingKind::Download, feature.name(), feature.fetch_time);
+            resolve_time = resolve_time.saturating_sub(feature.fetch_time);
+        }
+        self.timings
+            .record(TimingKind::Resolve, "feature dependencies", resolve_time);
+
+        // Options answered by an earlier build are not asked for again
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+        let saved = match WorkspaceState::load(&workspace_name) {
+            Ok(state) => state.feature_options,
+            Err(e) => {
+                warn!("Failed to load the saved feature options: {:#}", e);
+                Default::default()
+            }
+        };
+        let term = console::Term::stderr();
+        let interactive = self.interactive && term.is_term();
+        let answers = feature_options::complete_options(
+            &mut processed_features,
+            &saved,
+            &mut |feature, name, option| {
+                if !interactive {
+                    return Ok(None);
+                }
+                feature_options::prompt_option(&term, feature, name, option).map(Some)
+            },
+        )?;
+        if !answers.is_empty() && !self.dry_run {
+            WorkspaceState::update(&workspace_name, |state| {
+                for (feature, options) in answers {
+                    state
+                        .feature_options
+                        .entry(feature)
+                        .or_default()
+                        .extend(options);
+                }
+            })
+            .context("Failed to save the feature options")?;
+        }
+
+        debug!(
+            "Final feature order: {:?}",
+            processed_features
+                .iter()
+                .map(|f| &f.feature.id)
+                .collect::<Vec<_>>()
+        );
+
+        Ok((processed_features, features))
+    }
+
+    /// Builds a container image with features installed.
+    ///
+    /// This method:
+    /// 1. Creates a temporary directory for the build context
     /// 2. Downloads and processes all features (including dependencies)
     /// 3. Generates a multi-stage Dockerfile with feature installations
     /// 4. Builds the image using the runtime's build command
     ///
-    /// The Dockerfile uses multi-stage builds where each feature gets its own
-    /// layer, allowing for efficient caching and rebuild optimization.
+    /// The Dockerfile uses multi-stage builds where each feature gets its own
+    /// layer, allowing for efficient caching and rebuild optimization.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
+    /// * `env_variables` - Environment variables to set in the container
+    /// * `build_path` - Optional path to the build directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The temporary directory cannot be created
+    /// - Feature processing fails
+    /// - The Dockerfile cannot be generated
+    /// - The container build process fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use devcon::driver::container::ContainerDriver;
+    /// # use devcon::workspace::Workspace;
+    /// # use std::path::PathBuf;
+    /// # fn example(driver: ContainerDriver) -> anyhow::Result<()> {
+    /// let workspace = Workspace::open(PathBuf::from("/project"), None)?;
+    /// driver.build(workspace, &["NODE_ENV=production".to_string()], None)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build(
+        &self,
+        devcontainer_workspace: Workspace,
+        env_variables: &[String],
+        build_path: Option<PathBuf>,
+    ) -> anyhow::Result<()> {
+        self.build_with_features(devcontainer_workspace, env_variables, None, build_path)
+    }
+
+    /// Builds a container image with optional pre-processed features.
+    ///
+    /// This is the internal implementation that allows reusing already-processed
+    /// features to avoid redundant processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
+    /// * `env_variables` - Environment variables to set in the container
+    /// * `processed_features` - Optional pre-processed features to use
+    /// * `build_path` - Optional path to the build directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The temporary directory cannot be created
+    /// - Feature processing fails (if features not provided)
+    /// - The Dockerfile cannot be generated
+    /// - The container build process fails
+    pub fn build_with_features(
+        &self,
+        devcontainer_workspace: Workspace,
+        env_variables: &[String],
+        processed_features: Option<Vec<FeatureProcessResult>>,
+        build_path: Option<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let _interrupt = InterruptGuard::new(&self.cancel)?;
+        self.cancel.check()?;
+        self.run_initialize_command(&devcontainer_workspace)?;
+
+        let directory = match build_path {
+            Some(path) => {
+                std::fs::create_dir_all(&path)?;
+                TempDir::new_in(path)?
+            }
+            None => TempDir::new()?,
+        };
+        let directory_path = if tracing::event_enabled!(Level::DEBUG) {
+            directory.keep()
+        } else {
+            directory.path().to_path_buf()
+        };
+        info!(
+            "Building container in temporary directory: {}",
+            directory_path.to_string_lossy()
+        );
+
+        trace!(
+            "Processing features for devcontainer at {:?}",
+            devcontainer_workspace.path
+        );
+
+        // Use provided features or process them, none without the features stage
+        let processed_features = if !self.stages.includes(BuildStage::Features) {
+            info!("Skipping the features stage");
+            Vec::new()
+        } else {
+            match processed_features {
+                Some(features) => features,
+                None => {
+                    self.check_offline_artifacts(&devcontainer_workspace, true)?;
+                    let (features, _) = self.prepare_features(&devcontainer_workspace)?;
+                    features
+                }
+            }
+        };
+
+        // Record the configuration in the image for other devcontainer tools
+        let vscode = VscodeCustomizations::collect(
+            &processed_features,
+            &devcontainer_workspace.devcontainer,
+        );
+        let metadata_label = image_metadata::build_label(
+            &self.base_image_metadata(&devcontainer_workspace),
+            &processed_features,
+            &devcontainer_workspace.devcontainer,
+            self.config.vscode_customizations_baked().then_some(&vscode),
+        )?;
+
+        // Build on the mirror and verified digest of the base image, so the
+        // runtime cannot pull another image
+        let mut devcontainer_workspace = devcontainer_workspace;
+        if let Some(image) = self.base_image(&devcontainer_workspace)? {
+            devcontainer_workspace.devcontainer.image = Some(self.verified_base_image(&image)?);
+        }
+
+        let users = BuildUsers::of(&devcontainer_workspace.devcontainer);
+        let base = self.base_stage(&users, &directory_path)?;
+
+        let buildkit = self.config.buildkit_enabled();
+        let mut build_options = BuildOptions {
+            build_args: if self.config.proxy_propagated() {
+                ProxySettings::detect().env_vars()
+            } else {
+                Vec::new()
+            },
+            secrets: Vec::new(),
+            cache_from: self.config.get_build_cache_from(),
+            inline_cache: buildkit,
+            build_contexts: Vec::new(),
+        };
+        // Files next to a custom template are available with `COPY --from=template`
+        if let Some(dir) = self
+            .config
+            .dockerfile_template
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+        {
+            build_options
+                .build_contexts
+                .push((TEMPLATE_CONTEXT.to_string(), dir.to_path_buf()));
+        }
+        // Kept outside the build context, so the credentials never end up in an image
+        let secrets_dir = TempDir::new()?;
+        let registries = self.config.get_build_secret_registries();
+        if buildkit
+            && !registries.is_empty()
+            && let Some(path) = write_registry_auth(&registries, secrets_dir.path())?
+        {
+            build_options.secrets.push(BuildSecret {
+                id: REGISTRY_AUTH_SECRET.to_string(),
+                path,
+            });
+        }
+        let build_output = BuildOutput {
+            progress: self.config.get_build_progress().parse()?,
+            log_path: workspace_state::build_log_path(&devcontainer_workspace.get_sanitized_name())
+                .ok(),
+            cancel: self.cancel.clone(),
+        };
+
+        // The runtime pulls base images itself, so only the start can be scheduled
+        if !self.dry_run {
+            DownloadPolicy::from_config(&self.config)?.wait_for_window();
+        }
+
+        let feature_names: Vec<String> = processed_features
+            .iter()
+            .map(FeatureProcessResult::name)
+            .collect();
+        let feature_install = self.features_stage(
+            processed_features,
+            devcontainer_workspace.devcontainer.image.as_deref(),
+            &base,
+            &directory_path,
+            &build_options,
+            &build_output,
+        )?;
+        let (env_setup, docker_socket_setup) = if self.stages.includes(BuildStage::Env) {
+            let (mut env_setup, docker_socket_setup) =
+                self.env_stage(env_variables, users.remote_user);
+            if self.config.vscode_customizations_baked() {
+                env_setup.push_str(&vscode.settings_stage(
+                    users.remote_user,
+                    &users.remote_user_home,
+                    &directory_path,
+                )?);
+            }
+            (env_setup, docker_socket_setup)
+        } else {
+            info!("Skipping the env stage");
+            (String::new(), String::new())
+        };
+        let dotfiles_setup = if self.stages.includes(BuildStage::Dotfiles) {
+            dotfiles_stage(&directory_path)?
+        } else {
+            info!("Skipping the dotfiles stage");
+            String::new()
+        };
+
+        let instructions = StageInstructions {
+            base_setup: base.setup,
+            feature_install,
+            env_setup,
+            docker_socket_setup,
+            dotfiles_setup,
+        };
+        let dockerfile = self.render_dockerfile(
+            &devcontainer_workspace,
+            &users,
+            &instructions,
+            &metadata_label,
+            &directory_path,
+        )?;
+
+        let image_tag = self
+            .stages
+            .image_tag(&self.get_image_tag(&devcontainer_workspace));
+        let started = std::time::Instant::now();
+        self.build_image(
+            &dockerfile,
+            &directory_path,
+            &image_tag,
+            &build_options,
+            &build_output,
+        )?;
+        if self.dry_run {
+            return Ok(());
+        }
+        self.record_stage_timings(&build_output, started.elapsed(), &feature_names);
+
+        // A partial image is only for debugging, the workspace keeps its image
+        if self.stages.is_partial() {
+            let skipped: Vec<String> = self
+                .stages
+                .excluded()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            println!(
+                "Built {} without the {} stage(s)",
+                image_tag,
+                skipped.join(", ")
+            );
+            return Ok(());
+        }
+
+        let config_hash = workspace_state::config_hash(&devcontainer_workspace, &self.config);
+        if let Err(e) =
+            WorkspaceState::update(&devcontainer_workspace.get_sanitized_name(), |state| {
+                state.config_hash = Some(config_hash);
+            })
+        {
+            warn!("Failed to record the configuration of the build: {:#}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Builds the base stage: the environment and user setup all features
+    /// are installed with, and the CA certificates.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CA certificates cannot be copied.
+    fn base_stage(&self, users: &BuildUsers, directory_path: &Path) -> anyhow::Result<BaseStage> {
+        // Everything the features are installed on top of, the same for all
+        // projects with the same image and users so feature layers are shared
+        let mut setup = Environment::new().render_str(
+            r#"ENV DEVCON=true
+ENV _REMOTE_USER={{ remote_user }}
+ENV _CONTAINER_USER={{ container_user }}
+ENV _REMOTE_USER_HOME={{ remote_user_home }}
+ENV _CONTAINER_USER_HOME={{ container_user_home }}
+ENV DEVCON_CONTROL_HOST={{ runtime_host_address }}
+
+USER root
+RUN mkdir -p /tmp/features
+"#,
+            minijinja::context! {
+                remote_user => users.remote_user,
+                container_user => users.container_user,
+                remote_user_home => &users.remote_user_home,
+                container_user_home => &users.container_user_home,
+                runtime_host_address => self.runtime.get_host_address(),
+            },
+        )?;
+        let ca_certificates = self.config.get_ca_certificates();
+        setup.push_str(&ca_certificate_setup(&ca_certificates, directory_path)?);
+        // Changed certificates have to invalidate all cached feature layers
+        let cache_key_content = if ca_certificates.is_empty() {
+            setup.clone()
+        } else {
+            format!(
+                "{}{}",
+                setup,
+                hash_directory(&directory_path.join("ca-certificates"))?
+            )
+        };
+        Ok(BaseStage {
+            setup,
+            cache_key_content,
+        })
+    }
+
+    /// Builds the features stage, returning the instructions installing the
+    /// features and ending in the `feature_last` stage.
+    ///
+    /// Features are installed one stage each. With the feature cache, every
+    /// stage is built as an image tagged by its content instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a feature cannot be copied or its layer fails to
+    /// build.
+    fn features_stage(
+        &self,
+        processed_features: Vec<FeatureProcessResult>,
+        image: Option<&str>,
+        base: &BaseStage,
+        directory_path: &Path,
+        build_options: &BuildOptions,
+        build_output: &BuildOutput,
+    ) -> anyhow::Result<String> {
+        let run_mounts = feature_run_mounts(
+            self.config.buildkit_enabled(),
+            !build_options.secrets.is_empty(),
+        );
+
+        // A custom template may change the base, so its layers aren't shared.
+        let cached_base = image.filter(|_| {
+            self.config.feature_cache_enabled() && self.config.dockerfile_template.is_none()
+        });
+        let mut cache_parent = cached_base.map(|image| {
+            (
+                image.to_string(),
+                feature_cache_key(image, &base.cache_key_content),
+            )
+        });
+        let mut feature_install = String::new();
+
+        let mut i = 0;
+        for feature_result in processed_features {
+            let feature_path_name = self.copy_feature_to_build(&feature_result, directory_path)?;
+            let feature_name = &feature_result.feature_ref.source.name();
+            let stage = feature_stage(
+                &feature_result,
+                feature_name,
+                &feature_path_name,
+                &run_mounts,
+            );
+
+            if let Some((parent, parent_key)) = &cache_parent {
+                let key = feature_cache_key(
+                    parent_key,
+                    &format!(
+                        "{}{}",
+                        stage,
+                        hash_directory(&directory_path.join(&feature_path_name))?
+                    ),
+                );
+                let tag = feature_cache_tag(feature_name, &key);
+                if !self.dry_run && self.runtime.image_exists(&tag).unwrap_or(false) {
+                    info!("Using cached feature layer {}", tag);
+                } else {
+                    info!("Building feature layer {}", tag);
+                    let setup = if i == 0 {
+                        format!("{}\n", base.setup)
+                    } else {
+                        String::new()
+                    };
+                    let dockerfile = directory_path.join(format!("Dockerfile.feature_{}", i));
+                    fs::write(&dockerfile, format!("FROM {}\n{}{}", parent, setup, stage))?;
+                    let started = std::time::Instant::now();
+                    self.build_image(
+                        &dockerfile,
+                        directory_path,
+                        &tag,
+                        build_options,
+                        build_output,
+                    )?;
+                    if !self.dry_run {
+                        self.timings.record(
+                            TimingKind::Stage,
+                            format!("feature {} (cached layer)", feature_name),
+                            started.elapsed(),
+                        );
+                    }
+                }
+                cache_parent = Some((tag, key));
+            } else {
+                if i == 0 {
+                    feature_install.push_str(&format!("FROM {} AS feature_0 \n", "base"));
+                } else {
+                    feature_install.push_str(&format!(
+                        "FROM feature_{} AS feature_{} \n",
+                        i - 1,
+                        i
+                    ));
+                }
+                feature_install.push_str(&stage);
+            }
+
+            i += 1;
+        }
+        feature_install.push_str(&match (&cache_parent, i) {
+            (_, 0) => "FROM base AS feature_last \n".to_string(),
+            (Some((tag, _)), _) => format!("FROM {} AS feature_last \n", tag),
+            (None, _) => format!("FROM feature_{} AS feature_last \n", i - 1),
+        });
+        Ok(feature_install)
+    }
+
+    /// Builds the env stage: the additional environment variables and the
+    /// Docker group of a forwarded Docker socket.
+    fn env_stage(&self, env_variables: &[String], remote_user: &str) -> (String, String) {
+        let mut env_setup = String::new();
+        for env_var in env_variables {
+            env_setup.push_str(&format!("ENV {}\n", env_var));
+        }
+
+        // Let the remote user access a forwarded Docker socket
+        let docker_socket_setup = if self.config.docker_socket_forwarded() {
+            match docker_socket_gid() {
+                Some(gid) => docker_group_setup(gid, remote_user),
+                None => {
+                    warn!(
+                        "Docker socket {} not found, skipping docker group setup",
+                        DOCKER_SOCKET
+                    );
+                    String::new()
+                }
+            }
+        } else {
+            String::new()
+        };
+        (env_setup, docker_socket_setup)
+    }
+
+    /// Renders the Dockerfile template with the instructions of the stages
+    /// into the build directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a custom template cannot be read or rendered.
+    fn render_dockerfile(
+        &self,
+        devcontainer_workspace: &Workspace,
+        users: &BuildUsers,
+        instructions: &StageInstructions,
+        metadata_label: &str,
+        directory_path: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        let template_source = match &self.config.dockerfile_template {
+            Some(path) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read the Dockerfile template {}", path))?,
+            None => DOCKERFILE_TEMPLATE.to_string(),
+        };
+        let env = Environment::new();
+        let template = env
+            .template_from_str(&template_source)
+            .context("Invalid Dockerfile template")?;
+
+        let contents = template.render(minijinja::context! {
+            image => &devcontainer_workspace.devcontainer.image,
+            base_setup => &instructions.base_setup,
+            remote_user => users.remote_user,
+            container_user => users.container_user,
+            remote_user_home => &users.remote_user_home,
+            container_user_home => &users.container_user_home,
+            runtime_host_address => self.runtime.get_host_address(),
+            feature_install => &instructions.feature_install,
+            dotfiles_setup => &instructions.dotfiles_setup,
+            env_setup => &instructions.env_setup,
+            docker_socket_setup => &instructions.docker_socket_setup,
+            workspace_name => devcontainer_workspace.path.file_name().unwrap().to_string_lossy(),
+            workspace_folder => container_workspace_folder(devcontainer_workspace),
+            metadata_label_name => image_metadata::METADATA_LABEL,
+            metadata_label => image_metadata::dockerfile_quote(metadata_label),
+        })?;
+
+        let dockerfile = directory_path.join("Dockerfile");
+        fs::write(&dockerfile, contents)?;
+        Ok(dockerfile)
+    }
+
+    /// Builds an image with the runtime, or prints its Dockerfile in dry-run mode.
+    fn build_image(
+        &self,
+        dockerfile: &Path,
+        context: &Path,
+        tag: &str,
+        options: &BuildOptions,
+        output: &BuildOutput,
+    ) -> anyhow::Result<()> {
+        if self.dry_run {
+            println!("# Build {}", tag);
+            // Only the names, proxy URLs may hold credentials
+            let names = |values: &[String]| {
+                values
+                    .iter()
+                    .map(|value| value.split('=').next().unwrap_or(value).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            if !options.build_args.is_empty() {
+                println!("# Build arguments: {}", names(&options.build_args));
+            }
+            if !options.secrets.is_empty() {
+                let ids: Vec<_> = options.secrets.iter().map(|s| s.id.clone()).collect();
+                println!("# Secrets: {}", ids.join(", "));
+            }
+            if !options.cache_from.is_empty() {
+                println!("# Cache from: {}", options.cache_from.join(", "));
+            }
+            for (name, path) in &options.build_contexts {
+                println!("# Build context {}: {}", name, path.display());
+            }
+            println!("{}", fs::read_to_string(dockerfile)?.trim());
+            println!();
+            return Ok(());
+        }
+        self.cancel.check()?;
+        self.runtime
+            .build(dockerfile, context, tag, options, output)
+    }
+
+    /// Records the durations of the Dockerfile stages of a build from its log,
+    /// or the whole build as one stage if the builder doesn't report stages.
+    /// Feature stages are named after their feature.
+    fn record_stage_timings(
+        &self,
+        output: &BuildOutput,
+        total: std::time::Duration,
+        feature_names: &[String],
+    ) {
+        let log = output
+            .log_path
+            .as_deref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        let stages = build_timing::stage_durations(&log);
+        if stages.is_empty() {
+            self.timings.record(TimingKind::Stage, "image", total);
+            return;
+        }
+        for (stage, duration) in stages {
+            let name = stage
+                .strip_prefix("feature_")
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| feature_names.get(index))
+                .map(|feature| format!("feature {}", feature))
+                .unwrap_or(stage);
+            self.timings.record(TimingKind::Stage, name, duration);
+        }
+    }
+
+    fn copy_feature_to_build(
+        &self,
+        process: &FeatureProcessResult,
+        build_directory: &Path,
+    ) -> anyhow::Result<String> {
+        let feature_dest = build_directory.join(process.directory_name());
+
+        let mut options = fs_extra::dir::CopyOptions::new();
+        options.overwrite = true;
+        options.copy_inside = true;
+        fs_extra::dir::copy(&process.path, &feature_dest, &options)
+            .map_err(|e| anyhow::anyhow!("Failed to copy feature directory: {}", e))?;
+
+        // Create env variable file with merged options (defaults + user overrides)
+        let mut feature_options = serde_json::json!({});
+
+        // Start with default values from feature definition
+        if let Some(options_map) = &process.feature.options {
+            for (key, option) in options_map {
+                feature_options
+                    .as_object_mut()
+                    .unwrap()
+                    .insert(key.clone(), option.default.clone());
+            }
+        }
+
+        // Override with user-specified options from feature_ref
+        if let Some(user_opts) = process.feature_ref.options.as_object() {
+            for (key, value) in user_opts {
+                feature_options
+                    .as_object_mut()
+                    .unwrap()
+                    .insert(key.clone(), value.clone());
+            }
+        }
+
+        // Create env variable file for feature installation
+        let env_file_path = feature_dest.join("devcontainer-features.env");
+        let mut env_file = File::create(&env_file_path)?;
+        for (key, value) in feature_options.as_object().unwrap() {
+            use std::io::Write;
+            writeln!(env_file, "{}", format_feature_env_line(key, value))?;
+        }
+
+        Ok(feature_dest
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string())
+    }
+
+    /// Starts a container from a built image.
+    ///
+    /// This method:
+    /// 1. Starts the container with the project directory mounted
+    /// 2. Executes lifecycle commands in order:
+    ///    - `onCreateCommand`
+    ///    - Dotfiles setup (if configured)
+    ///    - `postCreateCommand`
+    ///    - `postStartCommand`
+    /// 3. Starts the agent listener in a background thread
+    ///
+    /// # Returns
+    ///
+    /// Returns a `JoinHandle` for the agent listener thread. The caller should
+    /// wait on this handle to keep the process alive and maintain the listener.
     ///
     /// # Arguments
     ///
     /// * `devcontainer_workspace` - The workspace with devcontainer configuration
-    /// * `env_variables` - Environment variables to set in the container
-    /// * `build_path` - Optional path to the build directory
+    /// * `env_variables` - Additional environment variables to pass to the container
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The temporary directory cannot be created
-    /// - Feature processing fails
-    /// - The Dockerfile cannot be generated
-    /// - The container build process fails
+    /// - The container image doesn't exist (must run `build()` first)
+    /// - The container CLI command fails
+    /// - The path is invalid
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use devcon::devcontainer::Devcontainer;
     /// # use devcon::driver::container::ContainerDriver;
+    /// # use devcon::workspace::Workspace;
     /// # use std::path::PathBuf;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let config = Devcontainer::try_from(PathBuf::from("/project"))?;
-    /// let driver = ContainerDriver::new(&config);
-    /// driver.build(&[\"NODE_ENV=production\"])?;
+    /// # fn example(driver: ContainerDriver) -> anyhow::Result<()> {
+    /// let workspace = Workspace::open(PathBuf::from("/project"), None)?;
+    /// driver.build(workspace.clone(), &[], None)?;
+    /// driver.start(workspace, &["EDITOR=vim".to_string()])?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn build(
+    pub fn start(
         &self,
         devcontainer_workspace: Workspace,
         env_variables: &[String],
-        build_path: Option<PathBuf>,
     ) -> anyhow::Result<()> {
-        self.build_with_features(devcontainer_workspace, env_variables, None, build_path)
+        self.start_with_features(devcontainer_workspace, env_variables, None)
+    }
+
+    /// Starts a container from a built image with optional pre-processed features.
+    ///
+    /// This is the internal implementation that allows reusing already-processed
+    /// features to avoid redundant processing.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
+    /// * `env_variables` - Additional environment variables to pass to the container
+    /// * `processed_features` - Optional pre-processed features to use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The container image doesn't exist (must run `build()` first)
+    /// - Feature processing fails (if features not provided)
+    /// - The container CLI command fails
+    pub fn start_with_features(
+        &self,
+        devcontainer_workspace: Workspace,
+        env_variables: &[String],
+        processed_features: Option<Vec<FeatureProcessResult>>,
+    ) -> anyhow::Result<()> {
+        if !self.dry_run {
+            let handles = self.runtime.list()?;
+            let container_name = self.get_container_name(&devcontainer_workspace);
+            if let Some((_, handle)) = handles.iter().find(|(name, _)| name == &container_name) {
+                // Container names are unique, another user's container blocks ours
+                if !audit::is_owned_by_current_user(handle.as_ref()) {
+                    bail!(
+                        "Container {} belongs to {}, stop it or remove it with the runtime's CLI",
+                        container_name,
+                        audit::owner_of(handle.as_ref()).unwrap_or("another user")
+                    );
+                }
+                info!("Container already running");
+                return Ok(());
+            }
+
+            debug!("Checking for existing images");
+            let images = self.runtime.images()?;
+            trace!("Images found: {:?}", images);
+            let already_built = images.iter().any(|image| {
+                image == &format!("{}:latest", self.get_image_tag(&devcontainer_workspace))
+            });
+            debug!("Image found: {}", already_built);
+
+            if !already_built {
+                return Err(RuntimeError::ImageNotFound.into());
+            }
+        }
+
+        let selinux_relabel = self.config.selinux_relabeled();
+        let workspace_mount = WorkspaceMount::of(&devcontainer_workspace)?;
+        let volume_mount = workspace_mount.volume(selinux_relabel);
+
+        let resource_limits = resource_limits(&devcontainer_workspace.devcontainer, &self.config);
+        debug!("Resource limits: {:?}", resource_limits);
+
+        let mut labels = vec![
+            self.get_container_label(&devcontainer_workspace),
+            format!("{}={}", audit::OWNER_LABEL, Config::current_user()),
+            format!(
+                "{}={}",
+                open::WORKSPACE_PATH_LABEL,
+                devcontainer_workspace.path.to_string_lossy()
+            ),
+            // Lets the host translate container paths of the workspace
+            format!(
+                "{}={}",
+                open::WORKSPACE_TARGET_LABEL,
+                workspace_mount.target
+            ),
+        ];
+        if devcontainer_workspace
+            .devcontainer
+            .workspace_mount
+            .is_some()
+        {
+            labels.push(format!(
+                "{}={}",
+                open::WORKSPACE_SOURCE_LABEL,
+                workspace_mount.source
+            ));
+        }
+        if let Some(instance) = &devcontainer_workspace.instance {
+            labels.push(format!("{}={}", INSTANCE_LABEL, instance));
+        }
+        labels.extend(resource_limits.labels());
+
+        // Use provided features or process them
+        let processed_features = match processed_features {
+            Some(features) => features,
+            None => {
+                self.check_offline_artifacts(&devcontainer_workspace, false)?;
+                let (features, _) = self.prepare_features(&devcontainer_workspace)?;
+                features
+            }
+        };
+        let metadata = self.merged_metadata(&devcontainer_workspace, &processed_features)?;
+
+        // Collect all mounts of the image metadata, features and devcontainer
+        // configuration with variable substitution
+        let mut all_mounts = Vec::new();
+        for mount in &metadata.mounts {
+            let substituted_mount = match mount {
+                crate::devcontainer::Mount::String(s) => crate::devcontainer::Mount::String(
+                    self.substitute_mount_variables(s, &devcontainer_workspace),
+                ),
+                crate::devcontainer::Mount::Structured(structured) => {
+                    let mut new_mount = structured.clone();
+                    if let Some(ref source) = structured.source {
+                        new_mount.source = Some(host_mount_path(
+                            &self.substitute_mount_variables(source, &devcontainer_workspace),
+                        ));
+                    }
+                    new_mount.target =
+                        self.substitute_variables(&structured.target, &devcontainer_workspace);
+                    crate::devcontainer::Mount::Structured(new_mount)
+                }
+            };
+            if selinux_relabel {
+                all_mounts.push(selinux_relabeled(substituted_mount));
+            } else {
+                all_mounts.push(substituted_mount);
+            }
+        }
+
+        // Share the host's Docker socket (docker-outside-of-docker)
+        if self.config.docker_socket_forwarded() {
+            all_mounts.push(crate::devcontainer::Mount::String(format!(
+                "{0}:{0}",
+                DOCKER_SOCKET
+            )));
+        }
+        if let Some(mount) = propagation::localtime_mount(&self.config) {
+            all_mounts.push(crate::devcontainer::Mount::String(mount));
+        }
+        for mount in ca_certificate_mounts(&self.config.get_ca_certificates()) {
+            all_mounts.push(crate::devcontainer::Mount::String(mount));
+        }
+
+        // Check if container needs to run in privileged mode
+        let requires_privileged = metadata.privileged;
+
+        // Feature entrypoints start before the command, in installation order.
+        // The image command only runs if the devcontainer.json doesn't
+        // override it, otherwise the container idles.
+        let mut command = vec![
+            "-c".to_string(),
+            entrypoint_script(&metadata.entrypoints),
+            "-".to_string(),
+        ];
+        if !devcontainer_workspace
+            .devcontainer
+            .override_command
+            .unwrap_or(true)
+        {
+            if self.dry_run {
+                command.push("<command of the image>".to_string());
+            } else {
+                command.extend(
+                    self.runtime
+                        .image_command(&self.get_image_tag(&devcontainer_workspace))?,
+                );
+            }
+        }
+
+        // Process environment variables, explicitly configured ones take precedence
+        let mut processed_env_vars = propagation::propagated_env(&self.config);
+        if self.config.proxy_propagated() {
+            processed_env_vars.extend(ProxySettings::detect().env_vars());
+        }
+        processed_env_vars.extend(
+            metadata
+                .container_env
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value)),
+        );
+
+        for env_var in env_variables {
+            if env_var.contains("=") {
+                processed_env_vars.push(env_var.clone());
+            } else {
+                // Read host env variable
+                let host_value = std::env::var(env_var).unwrap_or_default();
+                processed_env_vars.push(format!("{}={}", env_var, host_value));
+            }
+        }
+
+        // Secrets are resolved before starting so a missing one fails early
+        let secret_refs = secret_refs(&devcontainer_workspace.devcontainer)?;
+        let secrets = if self.dry_run {
+            secret_refs
+                .into_iter()
+                .map(|secret_ref| {
+                    let value = format!("<secret {}>", secret_ref.name);
+                    (secret_ref, value)
+                })
+                .collect()
+        } else {
+            resolve_secrets(secret_refs)?
+        };
+        for (secret_ref, value) in &secrets {
+            if let Some(env) = &secret_ref.env {
+                processed_env_vars.push(format!("{}={}", env, value));
+            }
+        }
+
+        // Handle port forward requests
+        let ports = devcontainer_workspace
+            .devcontainer
+            .forward_ports
+            .clone()
+            .unwrap_or_default();
+
+        debug!("Starting container with ports: {:?}", ports);
+
+        // Pass bandwidth limits to the agent, which requests them for its forwards
+        let limits = bandwidth_limits(&devcontainer_workspace.devcontainer);
+        if !limits.is_empty() {
+            processed_env_vars.push(format!("DEVCON_MAX_BANDWIDTH={}", limits));
+        }
+        let scheme = self.config.get_control_scheme();
+        if scheme != "tcp" {
+            processed_env_vars.push(format!("DEVCON_CONTROL_SCHEME={}", scheme));
+        }
+        // Instances report their own name, so their forwards and events are
+        // kept apart from the other containers of the workspace
+        if devcontainer_workspace.instance.is_some() {
+            processed_env_vars.push(format!(
+                "DEVCON_WORKSPACE_NAME={}",
+                devcontainer_workspace.agent_name()
+            ));
+        }
+        let encrypted = encrypt_tunnels(&devcontainer_workspace.devcontainer);
+        if encrypted && scheme == "grpc" {
+            bail!(
+                "Encrypted tunnels require the tcp control scheme, set controlScheme to tcp or disable customizations.devcon.encryptTunnels"
+            );
+        }
+        if !self.dry_run {
+            tunnel_key::set_encrypted(&devcontainer_workspace.agent_name(), encrypted)?;
+        }
+        if encrypted {
+            let key = if self.dry_run {
+                "<tunnel key>".to_string()
+            } else {
+                noise::format_key(&tunnel_key::workspace_key(
+                    &devcontainer_workspace.agent_name(),
+                )?)
+            };
+            processed_env_vars.push(format!("DEVCON_TUNNEL_KEY={}", key));
+        }
+        let auto_forward = auto_forward_policy(
+            &self.config.get_auto_forward_policy()?,
+            &devcontainer_workspace.devcontainer,
+        );
+        if auto_forward.mode != AutoForward::All {
+            processed_env_vars.push(format!("DEVCON_AUTO_FORWARD={}", auto_forward.mode));
+        }
+        if !auto_forward.allowed.is_empty() {
+            processed_env_vars.push(format!(
+                "DEVCON_AUTO_FORWARD_PORTS={}",
+                auto_forward::format_port_ranges(&auto_forward.allowed)
+            ));
+        }
+        if !auto_forward.denied.is_empty() {
+            processed_env_vars.push(format!(
+                "DEVCON_AUTO_FORWARD_IGNORE_PORTS={}",
+                auto_forward::format_port_ranges(&auto_forward.denied)
+            ));
+        }
+        let actions = auto_forward_actions(&devcontainer_workspace.devcontainer);
+        if !actions.is_empty() {
+            processed_env_vars.push(format!("DEVCON_ON_AUTO_FORWARD={}", actions));
+        }
+        let uncompressed = uncompressed_ports(&devcontainer_workspace.devcontainer);
+        if !uncompressed.is_empty() {
+            let ports: Vec<String> = uncompressed.iter().map(|p| p.to_string()).collect();
+            processed_env_vars.push(format!("DEVCON_UNCOMPRESSED_PORTS={}", ports.join(",")));
+        }
+        let host_aliases = self.config.get_host_aliases()?;
+        if !host_aliases.is_empty() {
+            processed_env_vars.push(format!("DEVCON_HOST_ALIASES={}", host_aliases.join(",")));
+        }
+        // The agent serves the registry cache on localhost of the container
+        if self.config.registry_cache_enabled() {
+            processed_env_vars.extend(registry_cache::container_env(
+                self.config.get_registry_cache_port(),
+            ));
+        }
+
+        let runtime_parameters = RuntimeParameters {
+            additional_mounts: all_mounts,
+            ports,
+            published_ports: devcontainer_workspace.devcontainer.published_ports(),
+            requires_privileged,
+            cap_add: metadata.cap_add.clone(),
+            init: metadata.init,
+            entrypoint: Some("/bin/sh".to_string()),
+            command,
+            limits: resource_limits,
+        };
+        if self.dry_run {
+            print_run_plan(
+                &devcontainer_workspace,
+                &self.get_image_tag(&devcontainer_workspace),
+                &volume_mount,
+                &labels,
+                &processed_env_vars,
+                &runtime_parameters,
+                &metadata,
+            );
+            for secret_ref in secrets.iter().map(|(secret_ref, _)| secret_ref) {
+                if let Some(file) = &secret_ref.file {
+                    println!("# Secret '{}' is written to {}", secret_ref.name, file);
+                }
+            }
+            return Ok(());
+        }
+
+        let handle = self.runtime.run(
+            &self.get_image_tag(&devcontainer_workspace),
+            &volume_mount,
+            &labels,
+            &processed_env_vars,
+            runtime_parameters,
+        )?;
+
+        audit::record(AuditEntry::new(
+            "start",
+            &devcontainer_workspace.get_sanitized_name(),
+            handle.id(),
+            &[],
+        ));
+
+        // Shells of the new container wait for its lifecycle from here on
+        if let Err(e) =
+            WorkspaceState::update(&devcontainer_workspace.get_sanitized_name(), |state| {
+                state.reset_lifecycle(handle.id())
+            })
+        {
+            warn!("Failed to reset the lifecycle progress: {:#}", e);
+        }
+
+        // Containers of linked workspaces keep reaching each other
+        let networks = WorkspaceState::load(&devcontainer_workspace.get_sanitized_name())
+            .map(|state| state.networks)
+            .unwrap_or_default();
+        for network in networks {
+            let joined = self.runtime.create_network(&network).and_then(|_| {
+                self.runtime.connect_network(
+                    handle.as_ref(),
+                    &network,
+                    &network_aliases(&devcontainer_workspace),
+                )
+            });
+            if let Err(e) = joined {
+                warn!("Failed to join the linked network {}: {:#}", network, e);
+            }
+        }
+
+        // Write file secrets before the lifecycle commands, which may need them.
+        // The value is passed in the environment, so it is not audited.
+        for (secret_ref, value) in &secrets {
+            if let Some(file) = &secret_ref.file {
+                debug!("Writing secret '{}' to {}", secret_ref.name, file);
+                self.exec(
+                    &devcontainer_workspace,
+                    handle.as_ref(),
+                    vec![
+                        "/bin/sh",
+                        "-c",
+                        "umask 077 && mkdir -p \"$(dirname \"$1\")\" && printf '%s' \"$DEVCON_SECRET\" > \"$1\"",
+                        "sh",
+                        file,
+                    ],
+                    &[format!("DEVCON_SECRET={}", value)],
+                    false,
+                )
+                .with_context(|| format!("Failed to write secret '{}'", secret_ref.name))?;
+            }
+        }
+
+        self.run_lifecycle_hook(
+            &devcontainer_workspace,
+            handle.as_ref(),
+            "onCreateCommand",
+            &metadata.on_create_commands,
+        )?;
+        self.run_lifecycle_hook(
+            &devcontainer_workspace,
+            handle.as_ref(),
+            "updateContentCommand",
+            &metadata.update_content_commands,
+        )?;
+
+        // Apply the dotfiles repositories in order, cloning needs the network
+        for dotfiles in self.config.dotfiles_repositories() {
+            if self.config.is_offline() {
+                warn!(
+                    "Skipping dotfiles setup of {} in offline mode",
+                    dotfiles.repo
+                );
+                continue;
+            }
+            // Runs as the remote user, the user of the image
+            self.exec(
+                &devcontainer_workspace,
+                handle.as_ref(),
+                vec!["/bin/sh", "/dotfiles_helper.sh"],
+                &dotfiles_env(&self.config, &dotfiles),
+                false,
+            )
+            .with_context(|| format!("Failed to set up the dotfiles of {}", dotfiles.repo))?;
+        }
+
+        let late_hooks = vec![
+            BackgroundHook {
+                hook: "postCreateCommand".to_string(),
+                commands: metadata.post_create_commands.clone(),
+            },
+            BackgroundHook {
+                hook: "postStartCommand".to_string(),
+                commands: metadata.post_start_commands.clone(),
+            },
+        ];
+        // Hooks after the one of `waitFor` may be left to the background
+        let foreground = if self.config.lifecycle_in_background() {
+            let wait_for = wait_for_hook(&devcontainer_workspace.devcontainer);
+            late_hooks
+                .iter()
+                .position(|late| late.hook == wait_for)
+                .map_or(0, |i| i + 1)
+        } else {
+            late_hooks.len()
+        };
+        let (foreground_hooks, background_hooks) = late_hooks.split_at(foreground);
+
+        if background_hooks.iter().all(|late| late.commands.is_empty()) {
+            for late in &late_hooks {
+                self.run_lifecycle_hook(
+                    &devcontainer_workspace,
+                    handle.as_ref(),
+                    &late.hook,
+                    &late.commands,
+                )?;
+            }
+        } else {
+            for late in foreground_hooks {
+                self.run_lifecycle_hook(
+                    &devcontainer_workspace,
+                    handle.as_ref(),
+                    &late.hook,
+                    &late.commands,
+                )?;
+            }
+            self.spawn_background_lifecycle(
+                &devcontainer_workspace,
+                handle.as_ref(),
+                background_hooks,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Leaves lifecycle hooks to a detached `devcon run-lifecycle` process,
+    /// which keeps running after `devcon start` returned. Its output goes to
+    /// the lifecycle log of the workspace.
+    fn spawn_background_lifecycle(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+        hooks: &[BackgroundHook],
+    ) -> anyhow::Result<()> {
+        let workspace = devcontainer_workspace.get_sanitized_name();
+        WorkspaceState::update(&workspace, |state| {
+            if !state.is_container(handle.id()) {
+                state.reset_lifecycle(handle.id());
+            }
+            state.background_hooks = hooks.to_vec();
+        })?;
+
+        let log_path = workspace_state::lifecycle_log_path(&workspace)?;
+        let log = File::create(&log_path)
+            .with_context(|| format!("Failed to create {}", log_path.display()))?;
+        let mut cmd = std::process::Command::new(std::env::current_exe()?);
+        cmd.arg("run-lifecycle")
+            .arg("--path")
+            .arg(&devcontainer_workspace.path);
+        if let Some(config) = &devcontainer_workspace.config {
+            cmd.arg("--config").arg(config);
+        }
+        if let Some(instance) = &devcontainer_workspace.instance {
+            cmd.arg("--name").arg(instance);
+        }
+        cmd.stdin(std::process::Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        // Its own process group, so signals of the terminal don't stop the hooks
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+        #[cfg(windows)]
+        std::os::windows::process::CommandExt::creation_flags(&mut cmd, DETACHED_PROCESS_FLAGS);
+        cmd.spawn()
+            .context("Failed to start the background lifecycle hooks")?;
+
+        let names: Vec<&str> = hooks.iter().map(|late| late.hook.as_str()).collect();
+        info!(
+            "Running {} in the background, output in {}",
+            names.join(" and "),
+            log_path.display()
+        );
+        Ok(())
     }
 
-    /// Builds a container image with optional pre-processed features.
-    ///
-    /// This is the internal implementation that allows reusing already-processed
-    /// features to avoid redundant processing.
-    ///
-    /// # Arguments
-    ///
-    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
-    /// * `env_variables` - Environment variables to set in the container
-    /// * `processed_features` - Optional pre-processed features to use
-    /// * `build_path` - Optional path to the build directory
+    /// Runs the lifecycle hooks `devcon start` left to the background.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The temporary directory cannot be created
-    /// - Feature processing fails (if features not provided)
-    /// - The Dockerfile cannot be generated
-    /// - The container build process fails
-    pub fn build_with_features(
+    /// Returns an error if the container isn't running anymore or a hook fails.
+    pub fn run_background_lifecycle(
         &self,
         devcontainer_workspace: Workspace,
-        env_variables: &[String],
-        processed_features: Option<Vec<FeatureProcessResult>>,
-        build_path: Option<PathBuf>,
     ) -> anyhow::Result<()> {
-        let directory = match build_path {
-            Some(path) => {
-                std::fs::create_dir_all(&path)?;
-                TempDir::new_in(path)?
-            }
-            None => TempDir::new()?,
-        };
-        let directory_path = if tracing::event_enabled!(Level::DEBUG) {
-            directory.keep()
-        } else {
-            directory.path().to_path_buf()
+        let state = WorkspaceState::load(&devcontainer_workspace.get_sanitized_name())?;
+        let containers = self.runtime.list()?;
+        let Some((_, handle)) = containers
+            .iter()
+            .find(|(_, handle)| state.is_container(handle.id()))
+        else {
+            bail!("The container of the background lifecycle hooks is not running");
         };
-        info!(
-            "Building container in temporary directory: {}",
-            directory_path.to_string_lossy()
-        );
-
-        trace!(
-            "Processing features for devcontainer at {:?}",
-            devcontainer_workspace.path
-        );
 
-        // Use provided features or process them
-        let processed_features = match processed_features {
-            Some(features) => features,
-            None => {
-                let (features, _) = self.prepare_features(&devcontainer_workspace)?;
-                features
-            }
-        };
+        for background in &state.background_hooks {
+            self.run_lifecycle_hook(
+                &devcontainer_workspace,
+                handle.as_ref(),
+                &background.hook,
+                &background.commands,
+            )?;
+        }
+        info!("Background lifecycle hooks completed");
+        Ok(())
+    }
 
-        let mut feature_install = String::new();
+    /// Resolves the configuration the workspace container is started with,
+    /// with the source of every value.
+    ///
+    /// Features are prepared like for a build; if that fails, e.g. offline
+    /// without cached features, their metadata is left out with a warning.
+    /// Mount variables are substituted like when starting the container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project configuration cannot be read.
+    pub fn effective_config(
+        &self,
+        devcontainer_workspace: &Workspace,
+    ) -> anyhow::Result<EffectiveConfig> {
+        let features = self.resolved_features(devcontainer_workspace);
+        self.resolve_effective_config(devcontainer_workspace, &features)
+    }
 
-        let mut i = 0;
-        for feature_result in processed_features {
-            let feature_path_name = self.copy_feature_to_build(&feature_result, &directory_path)?;
-            let feature_name = match &feature_result.feature_ref.source {
-                FeatureSource::Registry { registry } => &registry.name,
-                FeatureSource::Local { path } => &path
-                    .canonicalize()?
-                    .file_name()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string(),
-            };
-            if i == 0 {
-                feature_install.push_str(&format!("FROM {} AS feature_0 \n", "base"));
-            } else {
-                feature_install.push_str(&format!("FROM feature_{} AS feature_{} \n", i - 1, i));
-            }
-            if let Some(env_vars) = &feature_result.feature.container_env {
-                for env_var in env_vars {
-                    feature_install.push_str(&format!("ENV {}={} \n", env_var.0, env_var.1));
-                }
+    /// Resolves the features of a workspace, empty with a warning if they
+    /// cannot be resolved.
+    fn resolved_features(&self, devcontainer_workspace: &Workspace) -> Vec<FeatureProcessResult> {
+        match self.prepare_features(devcontainer_workspace) {
+            Ok((features, _)) => features,
+            Err(e) => {
+                warn!("Leaving out the feature metadata: {:#}", e);
+                Vec::new()
             }
-            feature_install.push_str(&format!(
-                "COPY {}/. /tmp/features/{}/ \n",
-                feature_path_name, feature_name
-            ));
-
-            feature_install.push_str(&format!(
-                "RUN chmod +x /tmp/features/{}/install.sh && . /tmp/features/{}/devcontainer-features.env && cd /tmp/features/{} && ./install.sh\n",
-                feature_name, feature_name, feature_name
-            ));
+        }
+    }
 
-            i += 1;
+    fn resolve_effective_config(
+        &self,
+        devcontainer_workspace: &Workspace,
+        features: &[FeatureProcessResult],
+    ) -> anyhow::Result<EffectiveConfig> {
+        let mut entries = self.base_image_metadata(devcontainer_workspace);
+        for feature_result in features {
+            entries.push(MetadataEntry::from_feature(feature_result)?);
         }
-        if i > 0 {
-            feature_install.push_str(&format!("FROM feature_{} AS feature_last \n", i - 1));
-        } else {
-            feature_install.push_str("FROM base AS feature_last \n");
+        let project = ProjectConfig::load(&devcontainer_workspace.path)?;
+
+        let mut effective =
+            EffectiveConfig::resolve(&entries, &devcontainer_workspace.devcontainer, &project);
+        for mount in &mut effective.mounts {
+            mount.value = self.substitute_mount_variables(&mount.value, devcontainer_workspace);
         }
 
-        // Add environment variables
-        let mut env_setup = String::new();
-        for env_var in env_variables {
-            env_setup.push_str(&format!("ENV {}\n", env_var));
+        // Applied on top of the metadata in the same order as when starting
+        let mut host_env = propagation::propagated_env(&self.config);
+        if self.config.proxy_propagated() {
+            host_env.extend(ProxySettings::detect().env_vars());
         }
+        let merged_env = std::mem::take(&mut effective.container_env);
+        effective.add_container_env(&host_env, effective::SOURCE_HOST);
+        effective.container_env.extend(merged_env);
+        effective.add_remote_env(&self.config.env_variables, effective::SOURCE_CONFIG);
 
-        // Add dotfiles setup if repository is provided
-        let dotfiles_setup = {
-            let dotfiles_helper_path = directory_path.join("dotfiles_helper.sh");
-            let dotfiles_helper_content = r#"
-#!/bin/sh
-set -e
-cd && git clone $1 .dotfiles && cd .dotfiles
-if [ -n "$2" ]; then
-    chmod +x $2
-    ./$2 || true
-else
-    for f in install.sh setup.sh bootstrap.sh script/install.sh script/setup.sh script/bootstrap.sh
-    do
-        if [ -e $f ]
-        then
-            installCommand=$f
-            break
-        fi
-    done
+        Ok(effective)
+    }
 
-    if [ -n "$installCommand" ]; then
-        chmod +x $installCommand
-        ./$installCommand || true
-    fi
-fi
-"#;
+    /// Collects the details of a workspace for `devcon inspect`.
+    ///
+    /// Combines the effective configuration and resolved features with the
+    /// labels of the built image and the runtime's inspect data of the
+    /// running container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be resolved or the
+    /// runtime fails to list containers or images.
+    pub fn inspect(
+        &self,
+        devcontainer_workspace: &Workspace,
+    ) -> anyhow::Result<WorkspaceInspection> {
+        let features = self.resolved_features(devcontainer_workspace);
+        let config = self.resolve_effective_config(devcontainer_workspace, &features)?;
 
-            fs::write(&dotfiles_helper_path, dotfiles_helper_content)?;
-            "COPY dotfiles_helper.sh /dotfiles_helper.sh \nRUN chmod +x /dotfiles_helper.sh"
-                .to_string()
+        let image_tag = format!("{}:latest", self.get_image_tag(devcontainer_workspace));
+        let image_labels = if self.runtime.images()?.contains(&image_tag) {
+            Some(self.runtime.image_labels(&image_tag)?.into_iter().collect())
+        } else {
+            None
         };
 
-        let dockerfile = directory_path.join("Dockerfile");
-        File::create(&dockerfile)?;
+        let containers = self.runtime.list()?;
+        let container = match containers.iter().find(|(container_name, handle)| {
+            container_name == &self.get_container_name(devcontainer_workspace)
+                && audit::is_owned_by_current_user(handle.as_ref())
+        }) {
+            Some((_, handle)) => Some(self.runtime.inspect(handle.as_ref())?),
+            None => None,
+        };
 
-        let env = Environment::new();
-        let template = env.template_from_str(
-            r#"
-FROM {{ image }} AS base
+        Ok(WorkspaceInspection {
+            config,
+            features: features
+                .iter()
+                .map(|feature_result| InspectedFeature {
+                    id: feature_result.feature.id.clone(),
+                    reference: feature_result.reference(),
+                    version: feature_result.feature.version.clone(),
+                    digest: feature_result.digest(),
+                })
+                .collect(),
+            image: InspectedImage {
+                tag: image_tag,
+                labels: image_labels,
+            },
+            container,
+        })
+    }
 
-ENV DEVCON=true
-ENV DEVCON_WORKSPACE_NAME={{ workspace_name }}
-ENV _REMOTE_USER={{ remote_user }}
-ENV _CONTAINER_USER={{ container_user }}
-ENV _REMOTE_USER_HOME={{ remote_user_home }}
-ENV _CONTAINER_USER_HOME={{ container_user_home }}
-ENV DEVCON_CONTROL_HOST={{ runtime_host_address }}
+    /// Returns the status of the workspace container.
+    ///
+    /// For a running container the limits are read from its labels, otherwise
+    /// the limits which would be applied on the next start are returned. Image
+    /// freshness and lifecycle hooks come from the recorded workspace state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime fails to list containers or images.
+    pub fn status(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<ContainerStatus> {
+        let containers = self.runtime.list()?;
 
-USER root
-RUN mkdir /tmp/features
-{{ feature_install }}
-{{ env_setup }}
-
-FROM feature_last AS dotfiles_setup
-{{ dotfiles_setup }}
-
-FROM dotfiles_setup
-USER {{ remote_user }}
-WORKDIR /workspaces/{{ workspace_name }}
-ENTRYPOINT [ "/bin/sh" ]
-CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sleep 1 \u0026 wait $!; do :; done", "-"]
-"#,
-        )?;
+        let handle = containers.iter().find(|(container_name, handle)| {
+            container_name == &self.get_container_name(devcontainer_workspace)
+                && audit::is_owned_by_current_user(handle.as_ref())
+        });
 
-        let remote_user_val = devcontainer_workspace
-            .devcontainer
-            .remote_user
-            .as_deref()
-            .unwrap_or("vscode");
-        let container_user_val = devcontainer_workspace
-            .devcontainer
-            .container_user
-            .as_deref()
-            .unwrap_or("vscode");
-        let container_user_home = if container_user_val == "root" {
-            "/root".to_string()
-        } else {
-            format!("/home/{}", container_user_val)
-        };
-        let remote_user_home = if remote_user_val == "root" {
-            "/root".to_string()
-        } else {
-            format!("/home/{}", remote_user_val)
+        let image_tag = format!("{}:latest", self.get_image_tag(devcontainer_workspace));
+        let image_built = self.runtime.images()?.contains(&image_tag);
+
+        let state = WorkspaceState::load(&devcontainer_workspace.get_sanitized_name())
+            .unwrap_or_else(|e| {
+                warn!("Ignoring workspace state: {:#}", e);
+                WorkspaceState::default()
+            });
+        let image_fresh = match &state.config_hash {
+            Some(hash) if image_built => {
+                Some(hash == &workspace_state::config_hash(devcontainer_workspace, &self.config))
+            }
+            _ => None,
         };
 
-        let contents = template.render(minijinja::context! {
-            image => &devcontainer_workspace.devcontainer.image,
-            remote_user => remote_user_val,
-            container_user => container_user_val,
-            remote_user_home => remote_user_home,
-            container_user_home => container_user_home,
-            feature_install => &feature_install,
-            dotfiles_setup => &dotfiles_setup,
-            env_setup => &env_setup,
-            workspace_name => devcontainer_workspace.path.file_name().unwrap().to_string_lossy(),
-            runtime_host_address => self.runtime.get_host_address(),
-        })?;
-
-        fs::write(&dockerfile, contents)?;
+        Ok(match handle {
+            Some((_, handle)) => ContainerStatus {
+                container_id: Some(handle.id().to_string()),
+                limits: ResourceLimits::from_handle(handle.as_ref()),
+                image_built,
+                image_fresh,
+                lifecycle_stage: state
+                    .is_container(handle.id())
+                    .then(|| state.lifecycle_stage()),
+                lifecycle_hooks: if state.is_container(handle.id()) {
+                    state.lifecycle_hooks
+                } else {
+                    Vec::new()
+                },
+                mounts: self.runtime.mounts(handle.as_ref()).unwrap_or_else(|e| {
+                    warn!("Failed to inspect container mounts: {:#}", e);
+                    Vec::new()
+                }),
+            },
+            None => ContainerStatus {
+                container_id: None,
+                limits: resource_limits(&devcontainer_workspace.devcontainer, &self.config),
+                image_built,
+                image_fresh,
+                lifecycle_stage: None,
+                lifecycle_hooks: Vec::new(),
+                mounts: Vec::new(),
+            },
+        })
+    }
 
-        self.runtime.build(
-            &dockerfile,
-            &directory_path,
-            &self.get_image_tag(&devcontainer_workspace),
-        )?;
+    /// Stops the running container of a workspace.
+    ///
+    /// Only the container of the workspace instance is stopped, other
+    /// instances of the same project keep running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no container of the workspace is running or the
+    /// runtime fails to stop it.
+    pub fn stop(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<()> {
+        let containers = self.runtime.list()?;
 
-        Ok(())
+        let (_, handle) = containers
+            .iter()
+            .find(|(container_name, handle)| {
+                container_name == &self.get_container_name(devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .ok_or(RuntimeError::ContainerNotRunning)?;
+
+        audit::record(AuditEntry::new(
+            "stop",
+            &devcontainer_workspace.get_sanitized_name(),
+            handle.id(),
+            &[],
+        ));
+        self.runtime.stop(handle.as_ref())
     }
 
-    fn copy_feature_to_build(
-        &self,
-        process: &FeatureProcessResult,
-        build_directory: &Path,
-    ) -> anyhow::Result<String> {
-        let feature_dest = build_directory.join(process.directory_name());
+    /// Pauses the running container of a workspace.
+    ///
+    /// All processes of the container are suspended, keeping their state
+    /// until [`Self::resume`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no container of the workspace is running or the
+    /// runtime fails to pause it.
+    pub fn pause(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<()> {
+        let containers = self.runtime.list()?;
 
-        let mut options = fs_extra::dir::CopyOptions::new();
-        options.overwrite = true;
-        options.copy_inside = true;
-        fs_extra::dir::copy(&process.path, &feature_dest, &options)
-            .map_err(|e| anyhow::anyhow!("Failed to copy feature directory: {}", e))?;
+        let (_, handle) = containers
+            .iter()
+            .find(|(container_name, handle)| {
+                container_name == &self.get_container_name(devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .ok_or(RuntimeError::ContainerNotRunning)?;
+
+        audit::record(AuditEntry::new(
+            "pause",
+            &devcontainer_workspace.get_sanitized_name(),
+            handle.id(),
+            &[],
+        ));
+        self.runtime.pause(handle.as_ref())
+    }
 
-        // Create env variable file with merged options (defaults + user overrides)
-        let mut feature_options = serde_json::json!({});
+    /// Resumes the container of a workspace paused with [`Self::pause`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no container of the workspace is running or the
+    /// runtime fails to resume it.
+    pub fn resume(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<()> {
+        let containers = self.runtime.list()?;
 
-        // Start with default values from feature definition
-        if let Some(options_map) = &process.feature.options {
-            for (key, option) in options_map {
-                feature_options
-                    .as_object_mut()
-                    .unwrap()
-                    .insert(key.clone(), option.default.clone());
-            }
-        }
+        let (_, handle) = containers
+            .iter()
+            .find(|(container_name, handle)| {
+                container_name == &self.get_container_name(devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .ok_or(RuntimeError::ContainerNotRunning)?;
+
+        audit::record(AuditEntry::new(
+            "resume",
+            &devcontainer_workspace.get_sanitized_name(),
+            handle.id(),
+            &[],
+        ));
+        self.runtime.unpause(handle.as_ref())
+    }
 
-        // Override with user-specified options from feature_ref
-        if let Some(user_opts) = process.feature_ref.options.as_object() {
-            for (key, value) in user_opts {
-                feature_options
-                    .as_object_mut()
-                    .unwrap()
-                    .insert(key.clone(), value.clone());
-            }
+    /// Links the running containers of two workspaces on a shared network,
+    /// where each reaches the other by its [`network_aliases`]. New
+    /// containers of either workspace join the network again.
+    ///
+    /// # Returns
+    ///
+    /// The name of the network.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if both are the same workspace, a container isn't
+    /// running or the runtime has no networks.
+    pub fn link(&self, first: &Workspace, second: &Workspace) -> anyhow::Result<String> {
+        if first.get_sanitized_name() == second.get_sanitized_name() {
+            bail!("A workspace cannot be linked to itself");
         }
+        let network = link_network(&first.get_sanitized_name(), &second.get_sanitized_name());
 
-        // Create env variable file for feature installation
-        let env_file_path = feature_dest.join("devcontainer-features.env");
-        let mut env_file = File::create(&env_file_path)?;
-        for (key, value) in feature_options.as_object().unwrap() {
-            use std::io::Write;
-            writeln!(
-                env_file,
-                "export {}={}",
-                key.to_uppercase(),
-                value.as_str().unwrap_or("")
-            )?;
+        let mut containers = self.runtime.list()?;
+        let mut handles = Vec::new();
+        for workspace in [first, second] {
+            let container_name = self.get_container_name(workspace);
+            let index = containers
+                .iter()
+                .position(|(name, handle)| {
+                    name == &container_name && audit::is_owned_by_current_user(handle.as_ref())
+                })
+                .ok_or(RuntimeError::ContainerNotRunning)
+                .with_context(|| format!("{} is not running", workspace.agent_name()))?;
+            handles.push(containers.swap_remove(index).1);
         }
 
-        Ok(feature_dest
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string())
+        self.runtime.create_network(&network)?;
+        for (workspace, handle) in [first, second].into_iter().zip(&handles) {
+            self.runtime
+                .connect_network(handle.as_ref(), &network, &network_aliases(workspace))?;
+            audit::record(AuditEntry::new(
+                "link",
+                &workspace.get_sanitized_name(),
+                handle.id(),
+                &[network.as_str()],
+            ));
+            WorkspaceState::update(&workspace.get_sanitized_name(), |state| {
+                if !state.networks.contains(&network) {
+                    state.networks.push(network.clone());
+                }
+            })?;
+        }
+        Ok(network)
     }
 
-    /// Starts a container from a built image.
+    /// Takes a snapshot of the running container of a workspace.
     ///
-    /// This method:
-    /// 1. Starts the container with the project directory mounted
-    /// 2. Executes lifecycle commands in order:
-    ///    - `onCreateCommand`
-    ///    - Dotfiles setup (if configured)
-    ///    - `postCreateCommand`
-    ///    - `postStartCommand`
-    /// 3. Starts the agent listener in a background thread
+    /// The container filesystem is committed to the image
+    /// `devcon-<name>:snapshot-<timestamp>` and the contents of its named
+    /// volumes are exported into the snapshot directory, see
+    /// [`snapshot`].
     ///
     /// # Returns
     ///
-    /// Returns a `JoinHandle` for the agent listener thread. The caller should
-    /// wait on this handle to keep the process alive and maintain the listener.
+    /// The name of the snapshot.
     ///
-    /// # Arguments
+    /// # Errors
     ///
-    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
-    /// * `env_variables` - Additional environment variables to pass to the container
+    /// Returns an error if no container of the workspace is running, or the
+    /// runtime fails to export a volume or commit the container.
+    pub fn create_snapshot(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<String> {
+        let containers = self.runtime.list()?;
+
+        let (_, handle) = containers
+            .iter()
+            .find(|(container_name, handle)| {
+                container_name == &self.get_container_name(devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .ok_or(RuntimeError::ContainerNotRunning)?;
+
+        let name = snapshot::name_at(chrono::Local::now());
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+        let dir = snapshot::snapshot_dir(&workspace_name, &name)?;
+        let image = format!("{}:{}", self.get_image_tag(devcontainer_workspace), name);
+
+        let result = (|| -> anyhow::Result<()> {
+            let details = self.runtime.inspect(handle.as_ref())?;
+            let manifest = snapshot::Manifest {
+                volumes: details.volumes,
+            };
+            fs::create_dir_all(&dir)?;
+            for (volume, destination) in &manifest.volumes {
+                debug!("Exporting volume {} from {}", volume, destination);
+                self.runtime.export_path(
+                    handle.as_ref(),
+                    destination,
+                    &snapshot::archive_path(&dir, volume),
+                )?;
+            }
+            manifest.save(&dir)?;
+
+            self.runtime.commit(
+                handle.as_ref(),
+                &image,
+                &[format!("{}={}", snapshot::SNAPSHOT_LABEL, name)],
+            )
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e.context(format!("Failed to take snapshot {}", name)));
+        }
+
+        audit::record(AuditEntry::new(
+            "snapshot",
+            &workspace_name,
+            handle.id(),
+            &[name.as_str()],
+        ));
+        Ok(name)
+    }
+
+    /// Returns the snapshots of a workspace, newest first.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The container image doesn't exist (must run `build()` first)
-    /// - The container CLI command fails
-    /// - The path is invalid
-    ///
-    /// # Examples
-    ///
-    /// ```no_run
-    /// # use devcon::devcontainer::Devcontainer;
-    /// # use devcon::driver::container::ContainerDriver;
-    /// # use std::path::PathBuf;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let config = Devcontainer::try_from(PathBuf::from("/project"))?;
-    /// let driver = ContainerDriver::new(&config);
-    /// driver.build(None, &[])?;
-    /// let handle = driver.start(PathBuf::from("/project"), &["EDITOR=vim".to_string()])?;
-    /// // Wait for the listener (keeps process alive)
-    /// let _ = handle.join();
-    /// # Ok(())
-    /// # }
-    /// ```
-    pub fn start(
-        &self,
-        devcontainer_workspace: Workspace,
-        env_variables: &[String],
-    ) -> anyhow::Result<()> {
-        self.start_with_features(devcontainer_workspace, env_variables, None)
+    /// Returns an error if the runtime fails to list images.
+    pub fn snapshots(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<Vec<String>> {
+        Ok(snapshot::list(
+            &self.runtime.images()?,
+            &self.get_image_tag(devcontainer_workspace),
+        ))
     }
 
-    /// Starts a container from a built image with optional pre-processed features.
+    /// Restores a snapshot of a workspace.
     ///
-    /// This is the internal implementation that allows reusing already-processed
-    /// features to avoid redundant processing.
+    /// A running container of the workspace is stopped, the snapshot image
+    /// becomes the `latest` image of the workspace and the container is
+    /// started again with the exported volume contents.
     ///
     /// # Arguments
     ///
-    /// * `devcontainer_workspace` - The workspace with devcontainer configuration
-    /// * `env_variables` - Additional environment variables to pass to the container
-    /// * `processed_features` - Optional pre-processed features to use
+    /// * `devcontainer_workspace` - The workspace to restore
+    /// * `name` - Name of the snapshot as returned by [`Self::snapshots`]
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The container image doesn't exist (must run `build()` first)
-    /// - Feature processing fails (if features not provided)
-    /// - The container CLI command fails
-    pub fn start_with_features(
+    /// Returns an error if the snapshot does not exist or the runtime fails
+    /// to replace the image, volumes or container.
+    pub fn restore_snapshot(
         &self,
         devcontainer_workspace: Workspace,
-        env_variables: &[String],
-        processed_features: Option<Vec<FeatureProcessResult>>,
+        name: &str,
     ) -> anyhow::Result<()> {
-        let handles = self.runtime.list()?;
-        let existing_handle = handles
+        if !self
+            .snapshots(&devcontainer_workspace)?
             .iter()
-            .find(|(name, _)| name == &self.get_container_name(&devcontainer_workspace));
-
-        if let Some((_, _)) = existing_handle {
-            info!("Container already running");
-            return Ok(());
+            .any(|s| s == name)
+        {
+            bail!(
+                "Snapshot {} of {} does not exist",
+                name,
+                devcontainer_workspace.get_sanitized_name()
+            );
         }
-
-        debug!("Checking for existing images");
-        let images = self.runtime.images()?;
-        trace!("Images found: {:?}", images);
-        let already_built = images.iter().any(|image| {
-            image == &format!("{}:latest", self.get_image_tag(&devcontainer_workspace))
-        });
-        debug!("Image found: {}", already_built);
-
-        if !already_built {
-            bail!("Image not found. Run 'devcon build' or 'devcon up' first.");
+        let image_tag = self.get_image_tag(&devcontainer_workspace);
+        let dir = snapshot::snapshot_dir(&devcontainer_workspace.get_sanitized_name(), name)?;
+        let manifest = snapshot::Manifest::load(&dir)?;
+
+        match self.stop(&devcontainer_workspace) {
+            Ok(()) => {}
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<RuntimeError>(),
+                    Some(RuntimeError::ContainerNotRunning)
+                ) => {}
+            Err(e) => return Err(e),
         }
 
-        let volume_mount = format!(
-            "{}:/workspaces/{}",
-            devcontainer_workspace.path.to_string_lossy(),
-            devcontainer_workspace
-                .path
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-        );
-
-        let label = self.get_container_label(&devcontainer_workspace);
-
-        // Collect all mounts: from devcontainer config and features
-        let mut all_mounts = Vec::new();
+        self.runtime.tag_image(
+            &format!("{}:{}", image_tag, name),
+            &format!("{}:latest", image_tag),
+        )?;
 
-        // Add mounts from devcontainer configuration with variable substitution
-        if let Some(ref mounts) = devcontainer_workspace.devcontainer.mounts {
-            for mount in mounts {
-                let substituted_mount = match mount {
-                    crate::devcontainer::Mount::String(s) => crate::devcontainer::Mount::String(
-                        self.substitute_mount_variables(s, &devcontainer_workspace),
-                    ),
-                    crate::devcontainer::Mount::Structured(structured) => {
-                        let mut new_mount = structured.clone();
-                        if let Some(ref source) = structured.source {
-                            new_mount.source = Some(
-                                self.substitute_mount_variables(source, &devcontainer_workspace),
-                            );
-                        }
-                        new_mount.target = self.substitute_mount_variables(
-                            &structured.target,
-                            &devcontainer_workspace,
-                        );
-                        crate::devcontainer::Mount::Structured(new_mount)
-                    }
-                };
-                all_mounts.push(substituted_mount);
-            }
+        for volume in manifest.volumes.keys() {
+            retry_until_released(|| self.runtime.remove_volume(volume))?;
         }
 
-        // Use provided features or process them
-        let processed_features = match processed_features {
-            Some(features) => features,
-            None => {
-                let (features, _) = self.prepare_features(&devcontainer_workspace)?;
-                features
-            }
-        };
-        for feature_result in &processed_features {
-            if let Some(ref mounts) = feature_result.feature.mounts {
-                // Convert feature::FeatureMount to devcontainer::Mount with variable substitution
-                for mount in mounts {
-                    match mount {
-                        crate::feature::FeatureMount::String(s) => {
-                            let substituted =
-                                self.substitute_mount_variables(s, &devcontainer_workspace);
-                            all_mounts.push(crate::devcontainer::Mount::String(substituted));
-                        }
-                        crate::feature::FeatureMount::Structured(sm) => {
-                            let mount_type = match sm.mount_type {
-                                crate::feature::MountType::Bind => {
-                                    crate::devcontainer::MountType::Bind
-                                }
-                                crate::feature::MountType::Volume => {
-                                    crate::devcontainer::MountType::Volume
-                                }
-                            };
-                            let source = sm.source.as_ref().map(|s| {
-                                self.substitute_mount_variables(s, &devcontainer_workspace)
-                            });
-                            let target = self
-                                .substitute_mount_variables(&sm.target, &devcontainer_workspace);
-                            all_mounts.push(crate::devcontainer::Mount::Structured(
-                                crate::devcontainer::StructuredMount {
-                                    mount_type,
-                                    source,
-                                    target,
-                                },
-                            ));
-                        }
-                    }
-                }
-            }
-        }
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+        let container_name = self.get_container_name(&devcontainer_workspace);
+        self.start(devcontainer_workspace, &[])?;
 
-        // Check if container needs to run in privileged mode
-        let requires_privileged = processed_features
+        if manifest.volumes.is_empty() {
+            return Ok(());
+        }
+        let containers = self.runtime.list()?;
+        let (_, handle) = containers
             .iter()
-            .any(|f| f.feature.privileged.unwrap_or(false));
+            .find(|(n, handle)| {
+                n == &container_name && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .ok_or(RuntimeError::ContainerNotRunning)?;
+        for (volume, destination) in &manifest.volumes {
+            debug!("Importing volume {} into {}", volume, destination);
+            let parent = Path::new(destination)
+                .parent()
+                .and_then(|p| p.to_str())
+                .unwrap_or("/");
+            self.runtime.import_path(
+                handle.as_ref(),
+                &snapshot::archive_path(&dir, volume),
+                parent,
+            )?;
+        }
 
-        // Process environment variables
-        let mut processed_env_vars = Vec::new();
+        audit::record(AuditEntry::new(
+            "restore",
+            &workspace_name,
+            handle.id(),
+            &[name],
+        ));
+        Ok(())
+    }
 
-        for env_var in env_variables {
-            if env_var.contains("=") {
-                processed_env_vars.push(env_var.clone());
-            } else {
-                // Read host env variable
-                let host_value = std::env::var(env_var).unwrap_or_default();
-                processed_env_vars.push(format!("{}={}", env_var, host_value));
+    /// Removes all but the `keep` newest snapshots of every workspace.
+    ///
+    /// # Returns
+    ///
+    /// The removed snapshot images.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime fails to list or remove images.
+    pub fn prune_snapshots(&self, keep: usize) -> anyhow::Result<Vec<String>> {
+        let pruned = snapshot::prune(&self.runtime.images()?, keep);
+        for image in &pruned {
+            self.runtime.remove_image(image)?;
+            if let Some((repository, name)) = image.split_once(':') {
+                let workspace_name = repository.strip_prefix("devcon-").unwrap_or(repository);
+                let dir = snapshot::snapshot_dir(workspace_name, name)?;
+                if dir.exists() {
+                    fs::remove_dir_all(&dir)?;
+                }
             }
         }
+        Ok(pruned)
+    }
 
-        // Handle port forward requests
-        let ports = devcontainer_workspace
-            .devcontainer
-            .forward_ports
-            .clone()
-            .unwrap_or_default();
+    /// Takes the inventory of what the devcontainer image of a workspace is
+    /// made of, see [`sbom`].
+    ///
+    /// Features are resolved like in a build, so their versions and digests
+    /// match the ones an image built now would contain.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace to describe
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the features cannot be resolved.
+    pub fn sbom_inventory(
+        &self,
+        devcontainer_workspace: &Workspace,
+    ) -> anyhow::Result<sbom::Inventory> {
+        let (features, _) = self.prepare_features(devcontainer_workspace)?;
+        Ok(sbom::Inventory::new(
+            &devcontainer_workspace.get_sanitized_name(),
+            devcontainer_workspace.devcontainer.image.as_deref(),
+            &features,
+            &self.config.dotfiles_repositories(),
+        ))
+    }
 
-        debug!("Starting container with ports: {:?}", ports);
+    /// Exports the built image of a workspace with its feature lockfile,
+    /// configuration and cached features into an environment archive, see
+    /// [`environment`].
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The workspace to export
+    /// * `output` - Path of the archive
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image wasn't built, the features cannot be
+    /// resolved or the runtime fails to save the image.
+    pub fn export_environment(
+        &self,
+        devcontainer_workspace: &Workspace,
+        output: &Path,
+    ) -> anyhow::Result<()> {
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+        let image = format!("{}:latest", self.get_image_tag(devcontainer_workspace));
+        if !self.runtime.images()?.contains(&image) {
+            return Err(RuntimeError::ImageNotFound.into());
+        }
 
-        let handle = self.runtime.run(
-            &self.get_image_tag(&devcontainer_workspace),
-            &volume_mount,
-            &label,
-            &processed_env_vars,
-            RuntimeParameters {
-                additional_mounts: all_mounts,
-                ports,
-                requires_privileged,
-            },
-        )?;
+        let config_hash = workspace_state::config_hash(devcontainer_workspace, &self.config);
+        if WorkspaceState::load(&workspace_name)?.config_hash.as_ref() != Some(&config_hash) {
+            warn!(
+                "The image of {} wasn't built from its current configuration, run 'devcon build' to export an up-to-date one",
+                workspace_name
+            );
+        }
 
-        match &devcontainer_workspace.devcontainer.on_create_command {
-            Some(LifecycleCommand::String(cmd)) => {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, cmd);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )?
-            }
-            Some(LifecycleCommand::Array(cmds)) => cmds.iter().try_for_each(|c| {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, c);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            Some(LifecycleCommand::Object(map)) => map.values().try_for_each(|cmd| {
-                let cmd_str = cmd.to_command_string();
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, &cmd_str);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            None => { /* No onCreateCommand specified */ }
-        };
+        let (features, _) = self.prepare_features(devcontainer_workspace)?;
+        let mut cache_entries = Vec::new();
+        for feature_result in &features {
+            cache_entries.extend(feature_result.cache_entries()?);
+        }
 
-        // Add dotfiles setup if repository is provided
-        if let Some(repo) = self.config.dotfiles_repository.as_deref() {
-            self.runtime.exec(
-                handle.as_ref(),
-                vec![
-                    "/bin/sh",
-                    "-c",
-                    &format!(
-                        "/dotfiles_helper.sh {} {}",
-                        repo,
-                        self.config
-                            .dotfiles_install_command
-                            .as_deref()
-                            .unwrap_or("")
-                    )
-                    .trim(),
-                ],
-                &[],
-                false,
-            )?;
+        let devcontainer = devcontainer_workspace
+            .devcontainer_file()?
+            .strip_prefix(&devcontainer_workspace.path)
+            .context("The devcontainer configuration is outside of the project")?
+            .to_path_buf();
+        let manifest = environment::Manifest {
+            version: environment::FORMAT_VERSION,
+            workspace: workspace_name,
+            image: image.clone(),
+            config: devcontainer_workspace.config.clone(),
+            config_hash,
+            devcontainer,
+            created_at: chrono::Local::now().to_rfc3339(),
         };
 
-        match &devcontainer_workspace.devcontainer.post_create_command {
-            Some(LifecycleCommand::String(cmd)) => {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, cmd);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )?
-            }
-            Some(LifecycleCommand::Array(cmds)) => cmds.iter().try_for_each(|c| {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, c);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            Some(LifecycleCommand::Object(map)) => map.values().try_for_each(|cmd| {
-                let cmd_str = cmd.to_command_string();
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, &cmd_str);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            None => { /* No onCreateCommand specified */ }
-        };
+        let temp_dir = TempDir::new()?;
+        let image_archive = temp_dir.path().join("image.tar");
+        info!("Saving image {}", image);
+        self.runtime.save_image(&image, &image_archive)?;
+
+        environment::pack(
+            output,
+            &manifest,
+            &environment::Lockfile::from_features(&features),
+            &image_archive,
+            &devcontainer_workspace.path,
+            &feature_process::get_devcon_cache_dir()?,
+            &cache_entries,
+        )
+    }
 
-        // Check if feature has entrypoint script which should start now
-        processed_features
-            .iter()
-            .try_for_each(|feature_result| -> anyhow::Result<()> {
-                if let Some(entrypoint) = &feature_result.feature.entrypoint {
-                    info!(
-                        "Executing entrypoint script for feature '{}'",
-                        feature_result.feature.id
-                    );
-                    let wrapped_cmd =
-                        self.wrap_lifecycle_command(&devcontainer_workspace, entrypoint);
-                    self.runtime.exec(
-                        handle.as_ref(),
-                        vec!["bash", "-c", "-i", &wrapped_cmd],
-                        &[],
-                        false,
-                    )?;
-                }
-                Ok(())
-            })?;
+    /// Imports an environment archive written by
+    /// [`Self::export_environment`] into a project.
+    ///
+    /// Configuration files missing in the project and cached features are
+    /// restored, then the image is loaded as the image of the workspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - Path of the environment archive
+    /// * `path` - The project directory to import into
+    /// * `instance` - Named container instance of the workspace
+    ///
+    /// # Returns
+    ///
+    /// The imported workspace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is invalid, the project cannot be
+    /// opened or the runtime fails to load the image.
+    pub fn import_environment(
+        &self,
+        archive: &Path,
+        path: PathBuf,
+        instance: Option<&str>,
+    ) -> anyhow::Result<Workspace> {
+        let temp_dir = TempDir::new()?;
+        let (manifest, image_archive) = environment::unpack(archive, temp_dir.path())?;
+
+        let restored =
+            environment::restore_files(&temp_dir.path().join(environment::CONFIG_DIR), &path)?;
+        debug!("Restored {} configuration files", restored);
+        let restored = environment::restore_files(
+            &temp_dir.path().join(environment::CACHE_DIR),
+            &feature_process::get_devcon_cache_dir()?,
+        )?;
+        debug!("Restored {} cached feature files", restored);
+
+        let devcontainer_workspace =
+            Workspace::open(path, manifest.config.as_deref())?.with_instance(instance)?;
+        let lockfile = devcontainer_workspace
+            .devcontainer_file()?
+            .with_file_name(environment::LOCKFILE);
+        if !lockfile.exists() {
+            fs::copy(temp_dir.path().join(environment::LOCKFILE), &lockfile)?;
+        }
 
-        match &devcontainer_workspace.devcontainer.post_start_command {
-            Some(LifecycleCommand::String(cmd)) => {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, cmd);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )?
+        // The manifest is part of the archive, so its image name cannot be
+        // trusted to name an image of this archive or of devcon at all
+        if !manifest.image.starts_with("devcon-") {
+            bail!(
+                "{} names the image {}, which is no devcon image",
+                archive.display(),
+                manifest.image
+            );
+        }
+        let existed = self.runtime.image_exists(&manifest.image)?;
+        info!("Loading image {}", manifest.image);
+        self.runtime.load_image(&image_archive)?;
+        if !self.runtime.image_exists(&manifest.image)? {
+            bail!(
+                "{} does not contain the image {}",
+                archive.display(),
+                manifest.image
+            );
+        }
+        let image = format!("{}:latest", self.get_image_tag(&devcontainer_workspace));
+        if image != manifest.image {
+            self.runtime.tag_image(&manifest.image, &image)?;
+            // Only remove the tag if the load created it
+            if !existed {
+                self.runtime.remove_image(&manifest.image)?;
             }
-            Some(LifecycleCommand::Array(cmds)) => cmds.iter().try_for_each(|c| {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, c);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            Some(LifecycleCommand::Object(map)) => map.values().try_for_each(|cmd| {
-                let cmd_str = cmd.to_command_string();
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, &cmd_str);
-                self.runtime.exec(
-                    handle.as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            None => { /* No onCreateCommand specified */ }
-        };
+        }
 
-        Ok(())
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+        let config_hash = workspace_state::config_hash(&devcontainer_workspace, &self.config);
+        if config_hash == manifest.config_hash {
+            WorkspaceState::update(&workspace_name, |state| {
+                state.config_hash = Some(config_hash);
+            })?;
+        } else {
+            warn!(
+                "The configuration of {} differs from the exported one, the imported image may be outdated",
+                workspace_name
+            );
+        }
+        Ok(devcontainer_workspace)
     }
 
     /// Shells into a started container.
     ///
     /// This method executes a shell within the container. The env variables
-    /// from the config will be passed as shell envs.
+    /// from the config will be passed as shell envs. While the container is
+    /// still being set up, it first waits for the lifecycle hook of `waitFor`.
     ///
     /// # Arguments
     ///
@@ -927,14 +3612,12 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
     /// # Examples
     ///
     /// ```no_run
-    /// # use devcon::devcontainer::Devcontainer;
     /// # use devcon::driver::container::ContainerDriver;
+    /// # use devcon::workspace::Workspace;
     /// # use std::path::PathBuf;
-    /// # fn example() -> anyhow::Result<()> {
-    /// let config = Devcontainer::try_from(PathBuf::from("/project"))?;
-    /// let driver = ContainerDriver::new(&config);
-    /// driver.build(None, &[])?;
-    /// driver.shell(PathBuf::from("/project"), &["EDITOR=vim".to_string()])?;
+    /// # fn example(driver: ContainerDriver) -> anyhow::Result<()> {
+    /// let workspace = Workspace::open(PathBuf::from("/project"), None)?;
+    /// driver.shell(workspace)?;
     /// # Ok(())
     /// # }
     /// ```
@@ -943,67 +3626,264 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
 
         let handle = containers
             .iter()
-            .find(|(container_name, _)| {
+            .find(|(container_name, handle)| {
                 container_name == &self.get_container_name(&devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
             })
             .map(|(_, id)| id);
 
-        if handle.is_none() {
-            bail!("Container not running. Run 'devcon start' or 'devcon up' first.");
+        if handle.is_none() {
+            return Err(RuntimeError::ContainerNotRunning.into());
+        }
+
+        self.wait_for_lifecycle(&devcontainer_workspace, handle.as_ref().unwrap().as_ref())?;
+        self.show_background_progress(&devcontainer_workspace, handle.as_ref().unwrap().as_ref());
+
+        let processed_env_vars = self.processed_env_vars();
+        // The workspace image records the base image and feature entries of its
+        // build, followed by the devcontainer.json which the current one replaces
+        let mut entries = self.image_metadata(&self.get_image_tag(&devcontainer_workspace));
+        entries.pop();
+        let metadata = MergedMetadata::merge(&entries, &devcontainer_workspace.devcontainer);
+
+        self.run_lifecycle_hook(
+            &devcontainer_workspace,
+            handle.as_ref().unwrap().as_ref(),
+            "postAttachCommand",
+            &metadata.post_attach_commands,
+        )?;
+
+        let shell =
+            self.resolve_shell(&devcontainer_workspace, handle.as_ref().unwrap().as_ref())?;
+
+        self.exec(
+            &devcontainer_workspace,
+            handle.as_ref().unwrap().as_ref(),
+            vec![&shell],
+            &processed_env_vars,
+            true,
+        )?;
+
+        Ok(())
+    }
+
+    /// Runs a project alias command in the running container.
+    ///
+    /// The command runs in the container's workspace folder with the same
+    /// environment variables as `shell`. `args` are appended as separately
+    /// quoted words.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the container is not running or the command fails.
+    pub fn run_alias(
+        &self,
+        devcontainer_workspace: &Workspace,
+        command: &str,
+        args: &[String],
+    ) -> anyhow::Result<()> {
+        let containers = self.runtime.list()?;
+        let Some(handle) = containers
+            .iter()
+            .find(|(container_name, handle)| {
+                container_name == &self.get_container_name(devcontainer_workspace)
+                    && audit::is_owned_by_current_user(handle.as_ref())
+            })
+            .map(|(_, handle)| handle)
+        else {
+            return Err(RuntimeError::ContainerNotRunning.into());
+        };
+
+        let script = alias_script(devcontainer_workspace, command, args);
+        debug!("Running alias script: {}", script);
+
+        self.exec(
+            devcontainer_workspace,
+            handle.as_ref(),
+            vec!["bash", "-c", "-i", &script],
+            &self.processed_env_vars(),
+            true,
+        )
+    }
+
+    /// Runs a command in a throwaway container of a workspace.
+    ///
+    /// The image is built, or pulled from `prebuilt_image` instead, a
+    /// container is started and the command runs in its workspace folder
+    /// with the output streamed. Afterwards the container, the image tag and
+    /// the recorded state of the workspace are removed again, also if a step
+    /// failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The throwaway workspace, usually an instance
+    /// * `prebuilt_image` - Image to pull instead of building one
+    /// * `build_path` - Optional build directory
+    /// * `command` - Shell command, followed by separately quoted arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RuntimeError::CommandFailed`] with the exit code of the
+    /// command if it fails, or an error if the image cannot be built or
+    /// pulled or the container cannot be started.
+    pub fn run_ephemeral(
+        &self,
+        devcontainer_workspace: Workspace,
+        prebuilt_image: Option<&str>,
+        build_path: Option<PathBuf>,
+        command: &[String],
+    ) -> anyhow::Result<()> {
+        let Some((script, args)) = command.split_first() else {
+            bail!("No command given");
+        };
+        let image_tag = format!("{}:latest", self.get_image_tag(&devcontainer_workspace));
+        let workspace_name = devcontainer_workspace.get_sanitized_name();
+
+        let result = (|| -> anyhow::Result<()> {
+            let (processed_features, _) = self.prepare_features(&devcontainer_workspace)?;
+            match prebuilt_image {
+                Some(image) => {
+                    info!("Pulling prebuilt image {}", image);
+                    self.runtime.pull_image(image)?;
+                    self.runtime.tag_image(image, &image_tag)?;
+                }
+                None => self.build_with_features(
+                    devcontainer_workspace.clone(),
+                    &[],
+                    Some(processed_features.clone()),
+                    build_path,
+                )?,
+            }
+            self.start_with_features(
+                devcontainer_workspace.clone(),
+                &[],
+                Some(processed_features),
+            )?;
+
+            let containers = self.runtime.list()?;
+            let (_, handle) = containers
+                .iter()
+                .find(|(container_name, handle)| {
+                    container_name == &self.get_container_name(&devcontainer_workspace)
+                        && audit::is_owned_by_current_user(handle.as_ref())
+                })
+                .ok_or(RuntimeError::ContainerNotRunning)?;
+
+            let script = alias_script(&devcontainer_workspace, script, args);
+            debug!("Running CI script: {}", script);
+            self.exec(
+                &devcontainer_workspace,
+                handle.as_ref(),
+                vec!["/bin/sh", "-c", &script],
+                &self.processed_env_vars(),
+                false,
+            )
+        })();
+
+        match self.stop(&devcontainer_workspace) {
+            Ok(()) => {}
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<RuntimeError>(),
+                    Some(RuntimeError::ContainerNotRunning)
+                ) => {}
+            Err(e) => warn!("Failed to remove the container: {:#}", e),
         }
-
-        // Process environment variables
-        let mut processed_env_vars = Vec::new();
-        for env_var in self.config.env_variables.iter() {
-            if env_var.contains("=") {
-                processed_env_vars.push(env_var.clone());
-            } else {
-                // Read host env variable
-                let host_value = std::env::var(env_var).unwrap_or_default();
-                processed_env_vars.push(format!("{}={}", env_var, host_value));
-            }
+        if self.runtime.images()?.contains(&image_tag)
+            && let Err(e) = retry_until_released(|| self.runtime.remove_image(&image_tag))
+        {
+            warn!("Failed to remove the image {}: {:#}", image_tag, e);
         }
+        if let Err(e) = WorkspaceState::remove(&workspace_name) {
+            warn!("{:#}", e);
+        }
+        result
+    }
 
-        match &devcontainer_workspace.devcontainer.post_attach_command {
-            Some(LifecycleCommand::String(cmd)) => {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, cmd);
-                self.runtime.exec(
-                    handle.as_ref().unwrap().as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )?
-            }
-            Some(LifecycleCommand::Array(cmds)) => cmds.iter().try_for_each(|c| {
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, c);
-                self.runtime.exec(
-                    handle.as_ref().unwrap().as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
-                    &[],
-                    false,
-                )
-            })?,
-            Some(LifecycleCommand::Object(map)) => map.values().try_for_each(|cmd| {
-                let cmd_str = cmd.to_command_string();
-                let wrapped_cmd = self.wrap_lifecycle_command(&devcontainer_workspace, &cmd_str);
-                self.runtime.exec(
-                    handle.as_ref().unwrap().as_ref(),
-                    vec!["bash", "-c", "-i", &wrapped_cmd],
+    /// Resolves the configured environment variables for commands in the container.
+    ///
+    /// Entries without `=` take their value from the host environment.
+    fn processed_env_vars(&self) -> Vec<String> {
+        self.config
+            .env_variables
+            .iter()
+            .map(|env_var| {
+                if env_var.contains("=") {
+                    env_var.clone()
+                } else {
+                    // Read host env variable
+                    let host_value = std::env::var(env_var).unwrap_or_default();
+                    format!("{}={}", env_var, host_value)
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the first configured shell which is available in the container.
+    ///
+    /// Candidates are checked with `command -v` and `/etc/shells`. A warning is
+    /// emitted when falling back from the preferred shell.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if none of the candidate shells is available.
+    fn resolve_shell(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+    ) -> anyhow::Result<String> {
+        let candidates = self.config.shell_candidates();
+
+        for shell in &candidates {
+            let probe = format!(
+                "command -v '{0}' >/dev/null 2>&1 && exit 0; \
+                 for p in $(grep '/{0}$' /etc/shells 2>/dev/null); do [ -x \"$p\" ] && exit 0; done; \
+                 exit 1",
+                shell.replace('\'', "")
+            );
+            if self
+                .exec(
+                    devcontainer_workspace,
+                    handle,
+                    vec!["/bin/sh", "-c", &probe],
                     &[],
                     false,
                 )
-            })?,
-            None => { /* No onCreateCommand specified */ }
-        };
+                .is_ok()
+            {
+                if shell != &candidates[0] {
+                    warn!(
+                        "Shell '{}' not found in container, falling back to '{}'",
+                        candidates[0], shell
+                    );
+                }
+                return Ok(shell.clone());
+            }
+            debug!("Shell '{}' not available in container", shell);
+        }
 
-        self.runtime.exec(
-            handle.as_ref().unwrap().as_ref(),
-            vec![&self.config.default_shell.as_deref().unwrap_or("zsh")],
-            &processed_env_vars,
-            true,
-        )?;
+        bail!(
+            "No usable shell found in container (tried: {})",
+            candidates.join(", ")
+        )
+    }
 
-        Ok(())
+    /// Executes a command in the container and records it in the audit log.
+    fn exec(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+        command: Vec<&str>,
+        env_vars: &[String],
+        attach_stdin: bool,
+    ) -> anyhow::Result<()> {
+        audit::record(AuditEntry::new(
+            "exec",
+            &devcontainer_workspace.get_sanitized_name(),
+            handle.id(),
+            &command,
+        ));
+        self.runtime.exec(handle, command, env_vars, attach_stdin)
     }
 
     /// Returns the Docker image tag for this container.
@@ -1057,12 +3937,11 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
         let mut hasher = Sha256::new();
 
         // Try to read and hash the devcontainer.json file content
-        let devcontainer_path = devcontainer_workspace
-            .path
-            .join(".devcontainer")
-            .join("devcontainer.json");
+        let content = devcontainer_workspace
+            .devcontainer_file()
+            .and_then(|path| Ok(fs::read_to_string(path)?));
 
-        match fs::read_to_string(&devcontainer_path) {
+        match content {
             Ok(content) => {
                 // Hash the file content for configuration-specific ID
                 hasher.update(content.as_bytes());
@@ -1072,17 +3951,25 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
                 hasher.update(devcontainer_workspace.path.to_string_lossy().as_bytes());
             }
         }
+        // Instances get their own volumes
+        if let Some(instance) = &devcontainer_workspace.instance {
+            hasher.update(b"\0");
+            hasher.update(instance.as_bytes());
+        }
 
         let result = hasher.finalize();
         format!("{:x}", result)
     }
 
-    /// Performs variable substitution on a mount string.
+    /// Performs variable substitution on a mount string or host command.
     ///
     /// Supports the following variables:
     /// - `${devcontainerId}` - Unique ID for this container
     /// - `${localWorkspaceFolder}` - Path to the workspace folder
+    /// - `${localWorkspaceFolderBasename}` - Name of the workspace folder
     /// - `${containerWorkspaceFolder}` - Path to workspace inside container
+    /// - `${containerWorkspaceFolderBasename}` - Name of the workspace inside container
+    /// - `${localEnv:NAME}` and `${localEnv:NAME:default}` - Host environment variable
     ///
     /// # Arguments
     ///
@@ -1092,11 +3979,7 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
     /// # Returns
     ///
     /// The mount string with all variables substituted.
-    fn substitute_mount_variables(
-        &self,
-        mount_str: &str,
-        devcontainer_workspace: &Workspace,
-    ) -> String {
+    fn substitute_variables(&self, mount_str: &str, devcontainer_workspace: &Workspace) -> String {
         let devcontainer_id = self.get_devcontainer_id(devcontainer_workspace);
         let workspace_name = devcontainer_workspace
             .path
@@ -1104,12 +3987,381 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
             .unwrap()
             .to_string_lossy();
         let local_workspace = devcontainer_workspace.path.to_string_lossy();
-        let container_workspace = format!("/workspaces/{}", workspace_name);
+        let container_workspace = container_workspace_folder(devcontainer_workspace);
+        let container_workspace_name = Path::new(&container_workspace)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
 
-        mount_str
+        let substituted = mount_str
             .replace("${devcontainerId}", &devcontainer_id)
+            .replace("${localWorkspaceFolderBasename}", &workspace_name)
             .replace("${localWorkspaceFolder}", &local_workspace)
-            .replace("${containerWorkspaceFolder}", &container_workspace)
+            .replace(
+                "${containerWorkspaceFolderBasename}",
+                &container_workspace_name,
+            )
+            .replace("${containerWorkspaceFolder}", &container_workspace);
+        substitute_local_env(&substituted, |name| std::env::var(name).ok())
+    }
+
+    /// Substitutes the variables of a mount, with the workspace folder in the
+    /// form of [`host_mount_path`].
+    fn substitute_mount_variables(
+        &self,
+        mount_str: &str,
+        devcontainer_workspace: &Workspace,
+    ) -> String {
+        let local_workspace = host_mount_path(&devcontainer_workspace.path.to_string_lossy());
+        self.substitute_variables(
+            &mount_str.replace("${localWorkspaceFolder}", &local_workspace),
+            devcontainer_workspace,
+        )
+    }
+
+    /// Runs the `initializeCommand` of the devcontainer on the host.
+    ///
+    /// The command runs in the workspace folder with its variables
+    /// substituted, and its output is shown like the build output and saved
+    /// to the initialize log of the workspace. A string runs in a shell, an
+    /// array as program and arguments, the commands of an object one after
+    /// another.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a command cannot be started, fails or is cancelled.
+    fn run_initialize_command(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<()> {
+        let Some(initialize_command) = &devcontainer_workspace.devcontainer.initialize_command
+        else {
+            return Ok(());
+        };
+        if self.dry_run {
+            println!("# initializeCommand (host)");
+            println!("{}\n", serde_json::to_string(initialize_command)?);
+            return Ok(());
+        }
+
+        let commands: Vec<Vec<String>> = match initialize_command {
+            LifecycleCommand::String(cmd) => vec![host_shell(cmd)],
+            LifecycleCommand::Array(args) => vec![args.clone()],
+            LifecycleCommand::Object(map) => {
+                let mut names: Vec<_> = map.keys().collect();
+                names.sort();
+                names
+                    .into_iter()
+                    .map(|name| match &map[name] {
+                        LifecycleCommandValue::String(cmd) => host_shell(cmd),
+                        LifecycleCommandValue::Array(args) => args.clone(),
+                    })
+                    .collect()
+            }
+        };
+
+        let output = BuildOutput {
+            progress: self.config.get_build_progress().parse()?,
+            log_path: workspace_state::initialize_log_path(
+                &devcontainer_workspace.get_sanitized_name(),
+            )
+            .ok(),
+            cancel: self.cancel.clone(),
+        };
+        let started = std::time::Instant::now();
+        for args in commands {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| self.substitute_variables(arg, devcontainer_workspace))
+                .collect();
+            let Some((program, args)) = args.split_first() else {
+                continue;
+            };
+            debug!("Running initializeCommand: {} {:?}", program, args);
+            let child = std::process::Command::new(program)
+                .args(args)
+                .current_dir(&devcontainer_workspace.path)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to run initializeCommand {}", program))?;
+            let status = stream_output(child, &output, &INITIALIZE_MESSAGES)?;
+            if !status.success() {
+                bail!("initializeCommand failed with {}", status);
+            }
+        }
+        self.timings.record(
+            TimingKind::Lifecycle,
+            "initializeCommand",
+            started.elapsed(),
+        );
+        Ok(())
+    }
+
+    /// Runs a lifecycle hook of the devcontainer and records that it ran.
+    ///
+    /// The commands of the image metadata entries and the devcontainer.json
+    /// run in order, those of array and object hooks one after another.
+    ///
+    /// # Arguments
+    ///
+    /// * `devcontainer_workspace` - The devcontainer workspace
+    /// * `handle` - Handle of the container to run the hook in
+    /// * `hook` - Name of the hook, e.g. `postCreateCommand`
+    /// * `commands` - Commands of the hook, nothing runs if empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a command fails.
+    fn run_lifecycle_hook(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+        hook: &str,
+        commands: &[HookCommand],
+    ) -> anyhow::Result<()> {
+        let workspace = devcontainer_workspace.get_sanitized_name();
+        if !commands.is_empty()
+            && let Err(e) = WorkspaceState::update(&workspace, |state| {
+                if !state.is_container(handle.id()) {
+                    state.reset_lifecycle(handle.id());
+                }
+                state.running_hook = Some(hook.to_string());
+            })
+        {
+            warn!("Failed to record lifecycle hook {}: {:#}", hook, e);
+        }
+
+        let result = commands.iter().try_for_each(|hook_command| {
+            let started = std::time::Instant::now();
+            let cmds: Vec<String> = match &hook_command.command {
+                LifecycleCommand::String(cmd) => vec![cmd.clone()],
+                LifecycleCommand::Array(cmds) => cmds.clone(),
+                LifecycleCommand::Object(map) => {
+                    map.values().map(|cmd| cmd.to_command_string()).collect()
+                }
+            };
+            cmds.iter().try_for_each(|cmd| {
+                let mut wrapped_cmd = self.wrap_lifecycle_command(devcontainer_workspace, cmd);
+                // Feature output is prefixed to tell it apart from the project's
+                if let Some(feature) = hook_command.feature_name() {
+                    info!("Running {} of feature {}", hook, feature);
+                    wrapped_cmd = prefix_output(&wrapped_cmd, feature);
+                }
+                self.exec(
+                    devcontainer_workspace,
+                    handle,
+                    vec!["bash", "-c", "-i", &wrapped_cmd],
+                    &[],
+                    false,
+                )
+            })?;
+            let name = match hook_command.feature_name() {
+                Some(feature) => format!("{} ({})", hook, feature),
+                None => hook.to_string(),
+            };
+            self.timings
+                .record(TimingKind::Lifecycle, name, started.elapsed());
+            Ok(())
+        });
+
+        // Hooks are recorded per container, a new container starts over. Hooks
+        // without commands are recorded too, they are progress for `waitFor`.
+        let recorded = WorkspaceState::update(&workspace, |state| {
+            if !state.is_container(handle.id()) {
+                state.reset_lifecycle(handle.id());
+            }
+            state.running_hook = None;
+            if result.is_err() {
+                state.failed_hook = Some(hook.to_string());
+            } else if !state.lifecycle_hooks.iter().any(|h| h == hook) {
+                state.lifecycle_hooks.push(hook.to_string());
+            }
+        });
+        if let Err(e) = recorded {
+            warn!("Failed to record lifecycle hook {}: {:#}", hook, e);
+        }
+        result
+    }
+
+    /// Prints a banner with the tail of the output while lifecycle hooks are
+    /// still running in the background.
+    fn show_background_progress(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+    ) {
+        let workspace = devcontainer_workspace.get_sanitized_name();
+        let Ok(state) = WorkspaceState::load(&workspace) else {
+            return;
+        };
+        if !state.is_container(handle.id()) || state.pending_background_hooks().is_empty() {
+            return;
+        }
+
+        println!("⏳ Setup still in progress: {}", state.lifecycle_stage());
+        if let Ok(log_path) = workspace_state::lifecycle_log_path(&workspace) {
+            for line in log_tail(&log_path, 5) {
+                println!("   │ {}", line);
+            }
+            println!("   Full output: {}", log_path.display());
+        }
+    }
+
+    /// Blocks until the lifecycle hook of `waitFor` completed in the container.
+    ///
+    /// Defaults to `updateContentCommand` like the devcontainer spec. The
+    /// progress is recorded by the `devcon start` running the hooks, so a
+    /// shell opened while the container is being set up waits for it. If a
+    /// hook failed or no progress was recorded, the shell opens right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hook doesn't complete within
+    /// [`WAIT_FOR_TIMEOUT`].
+    fn wait_for_lifecycle(
+        &self,
+        devcontainer_workspace: &Workspace,
+        handle: &dyn ContainerHandle,
+    ) -> anyhow::Result<()> {
+        let hook = wait_for_hook(&devcontainer_workspace.devcontainer);
+        // Runs on the host before the container exists
+        if hook == "initializeCommand" {
+            return Ok(());
+        }
+
+        let deadline = std::time::Instant::now() + WAIT_FOR_TIMEOUT;
+        let mut announced = false;
+        loop {
+            let state = WorkspaceState::load(&devcontainer_workspace.get_sanitized_name())?;
+            match state.lifecycle_progress(handle.id(), hook) {
+                LifecycleProgress::Completed => return Ok(()),
+                LifecycleProgress::Unknown => {
+                    debug!("No lifecycle progress recorded for {}", handle.id());
+                    return Ok(());
+                }
+                LifecycleProgress::Failed(failed) => {
+                    warn!("{} failed, the container may not be fully set up", failed);
+                    return Ok(());
+                }
+                LifecycleProgress::Pending => {
+                    if std::time::Instant::now() >= deadline {
+                        bail!(
+                            "Timed out waiting for {}. If 'devcon start' was interrupted, restart the container.",
+                            hook
+                        );
+                    }
+                    if !announced {
+                        info!("Waiting for {} to complete", hook);
+                        announced = true;
+                    }
+                    std::thread::sleep(WAIT_FOR_POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Returns the base image of a workspace as pulled by the runtime, from
+    /// the mirror of its registry if `registryMirrors` configures one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the download settings are invalid.
+    fn base_image(&self, devcontainer_workspace: &Workspace) -> anyhow::Result<Option<String>> {
+        let Some(image) = devcontainer_workspace.devcontainer.image.as_deref() else {
+            return Ok(None);
+        };
+        let mirrored = DownloadPolicy::from_config(&self.config)?
+            .mirrors
+            .rewrite_image(image);
+        if mirrored != image {
+            debug!("Using base image {} from the mirror {}", image, mirrored);
+        }
+        Ok(Some(mirrored))
+    }
+
+    /// Verifies the cosign signature of a base image if `verifySignatures`
+    /// is enabled, see [`signature`].
+    ///
+    /// # Returns
+    ///
+    /// The image pinned to its verified digest, the image itself without
+    /// verification.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image has no signature made with a trusted key.
+    fn verified_base_image(&self, image: &str) -> anyhow::Result<String> {
+        let policy = DownloadPolicy::from_config(&self.config)?;
+        let Some(keys) = &policy.trusted_keys else {
+            return Ok(image.to_string());
+        };
+        let pinned = signature::verify_image(image, keys, &policy)?;
+        info!("Verified the signature of base image {}", pinned);
+        Ok(pinned)
+    }
+
+    /// Reads the `devcontainer.metadata` label of the base image. An image
+    /// which is not available locally or has no readable label contributes
+    /// nothing.
+    fn base_image_metadata(&self, devcontainer_workspace: &Workspace) -> Vec<MetadataEntry> {
+        let Ok(Some(image)) = self.base_image(devcontainer_workspace) else {
+            return vec![];
+        };
+        let image = image.as_str();
+        if self.dry_run {
+            debug!("Dry run, not reading the metadata of base image {}", image);
+            return vec![];
+        }
+        if !self.runtime.image_exists(image).unwrap_or(false) {
+            debug!("Base image {} not available locally, no metadata", image);
+            return vec![];
+        }
+        self.image_metadata(image)
+    }
+
+    /// Reads the `devcontainer.metadata` label of a local image, empty if it
+    /// has no readable label.
+    fn image_metadata(&self, image: &str) -> Vec<MetadataEntry> {
+        self.runtime
+            .image_labels(image)
+            .and_then(|labels| match labels.get(image_metadata::METADATA_LABEL) {
+                Some(label) => image_metadata::parse(label),
+                None => Ok(vec![]),
+            })
+            .unwrap_or_else(|e| {
+                warn!("Ignoring metadata of image {}: {:#}", image, e);
+                vec![]
+            })
+    }
+
+    /// Merges the `devcontainer.metadata` label of the base image with the
+    /// installed features, in installation order, and the devcontainer.json.
+    ///
+    /// The label of the workspace image is not used, its feature and
+    /// devcontainer.json entries are applied from the current configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the properties of a feature cannot be converted.
+    fn merged_metadata(
+        &self,
+        devcontainer_workspace: &Workspace,
+        features: &[FeatureProcessResult],
+    ) -> anyhow::Result<MergedMetadata> {
+        let mut entries = self.base_image_metadata(devcontainer_workspace);
+        for feature_result in features {
+            entries.push(MetadataEntry::from_feature(feature_result)?);
+        }
+        debug!(
+            "Image metadata entries: {:?}",
+            entries
+                .iter()
+                .map(|entry| entry.id.as_deref().unwrap_or("devcontainer.json"))
+                .collect::<Vec<_>>()
+        );
+        Ok(MergedMetadata::merge(
+            &entries,
+            &devcontainer_workspace.devcontainer,
+        ))
     }
 
     /// Wraps a lifecycle command with proper environment and working directory setup.
@@ -1135,153 +4387,370 @@ CMD ["-c", "echo Container started\ntrap \"exit 0\" 15\n\nexec \"$@\"\nwhile sle
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::DockerRuntimeConfig;
-    use crate::devcontainer::{FeatureRegistry, FeatureRegistryType, FeatureSource};
-    use crate::feature::Feature;
-    use std::path::PathBuf;
-
-    fn create_test_feature_result(id: &str) -> FeatureProcessResult {
-        let feature = Feature {
-            id: id.to_string(),
-            version: "1.0.0".to_string(),
-            name: Some(format!("Test {}", id)),
-            description: None,
-            documentation_url: None,
-            license_url: None,
-            keywords: None,
-            options: None,
-            installs_after: None,
-            depends_on: None,
-            deprecated: None,
-            legacy_ids: None,
-            cap_add: None,
-            security_opt: None,
-            privileged: None,
-            init: None,
-            entrypoint: None,
-            mounts: None,
-            container_env: None,
-            customizations: None,
-            on_create_command: None,
-            update_content_command: None,
-            post_create_command: None,
-            post_start_command: None,
-            post_attach_command: None,
-        };
+    use crate::driver::runtime::mock::{Invocation, MockRuntime};
 
-        let feature_ref = FeatureRef::new(FeatureSource::Registry {
-            registry: FeatureRegistry {
-                owner: "test".to_string(),
-                repository: "features".to_string(),
-                name: id.to_string(),
-                version: "1.0.0".to_string(),
-                registry_type: FeatureRegistryType::Ghcr,
-            },
-        });
+    #[test]
+    fn test_feature_option_env_name() {
+        assert_eq!(feature_option_env_name("version"), "VERSION");
+        assert_eq!(feature_option_env_name("installZsh"), "INSTALLZSH");
+        assert_eq!(feature_option_env_name("node-gyp.deps"), "NODE_GYP_DEPS");
+        assert_eq!(feature_option_env_name("3rdParty"), "_3RDPARTY");
+    }
 
-        FeatureProcessResult {
-            feature_ref,
-            feature,
-            path: PathBuf::from(format!("/tmp/{}", id)),
-        }
+    #[test]
+    fn test_format_feature_env_line_non_string_values() {
+        assert_eq!(
+            format_feature_env_line("installZsh", &serde_json::json!(true)),
+            "export INSTALLZSH='true'"
+        );
+        assert_eq!(
+            format_feature_env_line("port", &serde_json::json!(8080)),
+            "export PORT='8080'"
+        );
+        assert_eq!(
+            format_feature_env_line("empty", &serde_json::Value::Null),
+            "export EMPTY=''"
+        );
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_apply_feature_order_override_complete() {
-        let features = vec![
-            create_test_feature_result("feature-a"),
-            create_test_feature_result("feature-b"),
-            create_test_feature_result("feature-c"),
+    fn test_feature_env_file_roundtrip_nasty_values() {
+        let nasty_values = [
+            "plain",
+            "with spaces  and\ttabs",
+            "single 'quoted' value",
+            "double \"quoted\" value",
+            "it's a mix of \"both\"",
+            "multi\nline\nvalue\n",
+            "$HOME and $(echo injected) and `echo injected`",
+            "back\\slash\\n",
+            "semi; colon && pipe | redirect > /dev/null",
+            "'",
+            "''",
+            "",
+            "unicode ✓ ünïcödé",
         ];
 
-        let override_order = vec![
-            "feature-c".to_string(),
-            "feature-a".to_string(),
-            "feature-b".to_string(),
-        ];
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for value in nasty_values {
+            let env_file = temp_dir.path().join("devcontainer-features.env");
+            fs::write(
+                &env_file,
+                format!(
+                    "{}\n",
+                    format_feature_env_line("option", &serde_json::json!(value))
+                ),
+            )
+            .unwrap();
+
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(r#". "$1" && printf '%s' "$OPTION""#)
+                .arg("sh")
+                .arg(&env_file)
+                .output()
+                .unwrap();
+
+            assert!(output.status.success(), "sourcing failed for {:?}", value);
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_bandwidth_limits() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "portsAttributes": {
+                    "3000": { "maxBandwidth": "1M" },
+                    "8000-8010": { "maxBandwidth": "512K" },
+                    "9000": { "label": "unlimited" },
+                    "web": { "maxBandwidth": "1M" }
+                },
+                "otherPortsAttributes": { "maxBandwidth": "10M" }
+            }"#,
+        )
+        .unwrap();
+
+        let limits = bandwidth_limits(&devcontainer);
+        assert_eq!(limits.for_port(3000), Some(1024 * 1024));
+        assert_eq!(limits.for_port(8005), Some(512 * 1024));
+        assert_eq!(limits.for_port(9000), Some(10 * 1024 * 1024));
+        assert_eq!(limits.rules.len(), 2);
+    }
+
+    #[test]
+    fn test_auto_forward_policy() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "portsAttributes": {
+                    "3000": { "onAutoForward": "notify" },
+                    "9229": { "onAutoForward": "ignore" }
+                },
+                "customizations": { "devcon": { "autoForwardIgnorePorts": [9090] } }
+            }"#,
+        )
+        .unwrap();
+        let policy = auto_forward_policy(&AutoForwardPolicy::default(), &devcontainer);
+        assert_eq!(policy.mode, AutoForward::All);
+        assert!(policy.permits(8080));
+        assert!(!policy.permits(9229));
+        assert!(!policy.permits(9090));
+
+        // Ignoring other ports only forwards the attributed ones
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "portsAttributes": { "3000-3001": { "onAutoForward": "silent" } },
+                "otherPortsAttributes": { "onAutoForward": "ignore" },
+                "customizations": { "devcon": { "autoForwardPorts": "8080" } }
+            }"#,
+        )
+        .unwrap();
+        let policy = auto_forward_policy(&AutoForwardPolicy::default(), &devcontainer);
+        assert_eq!(policy.mode, AutoForward::Allowlist);
+        assert!(policy.permits(3001));
+        assert!(policy.permits(8080));
+        assert!(!policy.permits(5173));
+
+        // The project can only narrow the config's allow list
+        let config = AutoForwardPolicy {
+            mode: AutoForward::Allowlist,
+            allowed: vec![3000..=3000, 5000..=5999],
+            denied: Vec::new(),
+        };
+        let policy = auto_forward_policy(&config, &devcontainer);
+        assert!(policy.permits(3000));
+        assert!(!policy.permits(8080));
+        assert!(!policy.permits(5173));
+
+        let config = AutoForwardPolicy {
+            mode: AutoForward::None,
+            ..Default::default()
+        };
+        assert!(!auto_forward_policy(&config, &devcontainer).permits(3000));
+    }
+
+    #[test]
+    fn test_auto_forward_actions() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "portsAttributes": {
+                    "3000": { "onAutoForward": "openBrowser" },
+                    "5173": { "onAutoForward": "openBrowserOnce" },
+                    "8000-8010": { "onAutoForward": "notify" },
+                    "9229": { "onAutoForward": "ignore" },
+                    "web": { "onAutoForward": "notify" }
+                },
+                "otherPortsAttributes": { "onAutoForward": "silent" }
+            }"#,
+        )
+        .unwrap();
+
+        let actions = auto_forward_actions(&devcontainer);
+        assert_eq!(actions.for_port(3000), AutoForwardAction::OpenBrowser);
+        assert_eq!(actions.for_port(5173), AutoForwardAction::OpenBrowser);
+        assert_eq!(actions.for_port(8005), AutoForwardAction::Notify);
+        assert_eq!(actions.for_port(9229), AutoForwardAction::None);
+        assert_eq!(
+            actions.to_string(),
+            "3000=openBrowser,5173=openBrowser,8000-8010=notify,*=silent"
+        );
+    }
+
+    #[test]
+    fn test_repository_host() {
+        assert_eq!(
+            repository_host("https://github.com/user/dotfiles"),
+            Some("github.com")
+        );
+        assert_eq!(
+            repository_host("https://user@git.example.com:8443/dotfiles.git"),
+            Some("git.example.com:8443")
+        );
+        assert_eq!(repository_host("https:///dotfiles"), None);
+    }
+
+    #[test]
+    fn test_log_tail() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("lifecycle.log");
+        assert!(log_tail(&path, 5).is_empty());
+
+        fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(log_tail(&path, 2), vec!["two", "three"]);
+        assert_eq!(log_tail(&path, 5).len(), 3);
+    }
 
-        let result = apply_feature_order_override(features, &override_order);
-        assert!(result.is_ok());
+    #[test]
+    fn test_prefix_output() {
+        assert_eq!(
+            prefix_output("npm ci", "node"),
+            "set -o pipefail; { npm ci\n} 2>&1 | while IFS= read -r line; do printf '%s %s\\n' '[node]' \"$line\"; done"
+        );
+    }
 
-        let ordered = result.unwrap();
-        assert_eq!(ordered.len(), 3);
-        assert_eq!(ordered[0].feature.id, "feature-c");
-        assert_eq!(ordered[1].feature.id, "feature-a");
-        assert_eq!(ordered[2].feature.id, "feature-b");
+    #[test]
+    fn test_entrypoint_script() {
+        assert_eq!(
+            entrypoint_script(&[]),
+            "echo Container started\ntrap \"exit 0\" 15\nexec \"$@\"\nwhile sleep 1 & wait $!; do :; done"
+        );
+        let script = entrypoint_script(&[
+            "/usr/local/share/docker-init.sh".to_string(),
+            "/usr/local/share/ssh-init.sh".to_string(),
+        ]);
+        assert!(
+            script.contains(
+                "15\n/usr/local/share/docker-init.sh\n/usr/local/share/ssh-init.sh\nexec"
+            )
+        );
     }
 
     #[test]
-    fn test_apply_feature_order_override_partial() {
-        let features = vec![
-            create_test_feature_result("feature-a"),
-            create_test_feature_result("feature-b"),
-            create_test_feature_result("feature-c"),
-            create_test_feature_result("feature-d"),
-        ];
-
-        // Only specify order for some features
-        let override_order = vec!["feature-c".to_string(), "feature-a".to_string()];
+    fn test_docker_group_setup() {
+        let setup = docker_group_setup(998, "vscode");
+        assert!(setup.starts_with("RUN group=$(getent group 998 | cut -d: -f1);"));
+        assert!(setup.contains("groupadd -g 998 docker"));
+        assert!(setup.contains("usermod -aG \"$group\" vscode"));
 
-        let result = apply_feature_order_override(features, &override_order);
-        assert!(result.is_ok());
+        assert_eq!(docker_group_setup(998, "root"), "");
+    }
 
-        let ordered = result.unwrap();
-        assert_eq!(ordered.len(), 4);
+    #[test]
+    fn test_encrypt_tunnels() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "customizations": { "devcon": { "encryptTunnels": true } }
+            }"#,
+        )
+        .unwrap();
+        assert!(encrypt_tunnels(&devcontainer));
 
-        // First two should be in specified order
-        assert_eq!(ordered[0].feature.id, "feature-c");
-        assert_eq!(ordered[1].feature.id, "feature-a");
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "customizations": { "vscode": { "encryptTunnels": true } }
+            }"#,
+        )
+        .unwrap();
+        assert!(!encrypt_tunnels(&devcontainer));
+    }
 
-        // Remaining features should be at the end (b and d)
-        let remaining_ids: Vec<&str> = ordered[2..].iter().map(|f| f.feature.id.as_str()).collect();
-        assert!(remaining_ids.contains(&"feature-b"));
-        assert!(remaining_ids.contains(&"feature-d"));
+    #[test]
+    fn test_secret_refs() {
+        let devcontainer: Devcontainer =
+            serde_json::from_str(r#"{"image": "ubuntu:22.04"}"#).unwrap();
+        assert!(secret_refs(&devcontainer).unwrap().is_empty());
+
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "customizations": { "devcon": { "secrets": [
+                    { "name": "github-token", "env": "GITHUB_TOKEN" },
+                    { "name": "npm-token", "file": "/run/secrets/npm-token" }
+                ] } }
+            }"#,
+        )
+        .unwrap();
+        let refs = secret_refs(&devcontainer).unwrap();
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0].env.as_deref(), Some("GITHUB_TOKEN"));
+        assert_eq!(refs[1].file.as_deref(), Some("/run/secrets/npm-token"));
+
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "customizations": { "devcon": { "secrets": [{ "name": "token" }] } }
+            }"#,
+        )
+        .unwrap();
+        assert!(secret_refs(&devcontainer).is_err());
     }
 
     #[test]
-    fn test_apply_feature_order_override_empty() {
-        let features = vec![
-            create_test_feature_result("feature-a"),
-            create_test_feature_result("feature-b"),
-        ];
+    fn test_resource_limits() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "hostRequirements": { "cpus": 4, "memory": "8gb" }
+            }"#,
+        )
+        .unwrap();
 
-        let override_order: Vec<String> = vec![];
+        let mut config = Config::default();
+        assert_eq!(
+            resource_limits(&devcontainer, &config),
+            ResourceLimits {
+                cpus: Some("4".to_string()),
+                memory: Some("8g".to_string()),
+            }
+        );
 
-        let result = apply_feature_order_override(features, &override_order);
-        assert!(result.is_ok());
+        config.container_cpus = Some("2.5".to_string());
+        config.container_memory = Some("16g".to_string());
+        assert_eq!(
+            resource_limits(&devcontainer, &config),
+            ResourceLimits {
+                cpus: Some("2.5".to_string()),
+                memory: Some("8g".to_string()),
+            }
+        );
 
-        let ordered = result.unwrap();
-        assert_eq!(ordered.len(), 2);
-        // Original order should be preserved
-        assert_eq!(ordered[0].feature.id, "feature-a");
-        assert_eq!(ordered[1].feature.id, "feature-b");
+        let devcontainer: Devcontainer =
+            serde_json::from_str(r#"{ "image": "ubuntu:22.04" }"#).unwrap();
+        assert_eq!(
+            resource_limits(&devcontainer, &config).memory,
+            Some("16g".to_string())
+        );
+        assert_eq!(
+            resource_limits(&devcontainer, &Config::default()),
+            ResourceLimits::default()
+        );
     }
 
     #[test]
-    fn test_apply_feature_order_override_nonexistent() {
-        let features = vec![
-            create_test_feature_result("feature-a"),
-            create_test_feature_result("feature-b"),
-        ];
-
-        let override_order = vec!["feature-nonexistent".to_string(), "feature-a".to_string()];
-
-        let result = apply_feature_order_override(features, &override_order);
-        assert!(result.is_ok());
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("8gb"), Some(8 << 30));
+        assert_eq!(parse_memory("512M"), Some(512 << 20));
+        assert_eq!(parse_memory("1.5GB"), Some(3 << 29));
+        assert_eq!(parse_memory("2048"), Some(2048));
+        assert_eq!(parse_memory("lots"), None);
+        assert_eq!(parse_memory("0gb"), None);
+
+        assert_eq!(format_memory(8 << 30), "8g");
+        assert_eq!(format_memory(3 << 29), "1536m");
+        assert_eq!(format_memory(2048), "2k");
+    }
 
-        let ordered = result.unwrap();
-        assert_eq!(ordered.len(), 2);
+    #[test]
+    fn test_uncompressed_ports() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu:22.04",
+                "portsAttributes": {
+                    "443": { "compress": false },
+                    "8000-8002": { "compress": false },
+                    "3000": { "compress": true },
+                    "web": { "compress": false }
+                }
+            }"#,
+        )
+        .unwrap();
 
-        // feature-a should be first (as it was in override list)
-        assert_eq!(ordered[0].feature.id, "feature-a");
-        // feature-b should be second (not in override list)
-        assert_eq!(ordered[1].feature.id, "feature-b");
+        assert_eq!(
+            uncompressed_ports(&devcontainer),
+            vec![443, 8000, 8001, 8002]
+        );
     }
 
     #[test]
     fn test_devcontainer_id_generation() {
         use crate::config::Config;
-        use crate::driver::runtime::docker::DockerRuntime;
         use std::fs;
         use tempfile::TempDir;
 
@@ -1321,7 +4790,7 @@ mod tests {
         let workspace3 = Workspace::try_from(temp_dir3.path().to_path_buf()).unwrap();
 
         let config = Config::default();
-        let runtime = Box::new(DockerRuntime::new(DockerRuntimeConfig::default()));
+        let runtime = Box::new(MockRuntime::new());
         let driver = ContainerDriver::new(config, runtime);
 
         let id1 = driver.get_devcontainer_id(&workspace1);
@@ -1350,9 +4819,89 @@ mod tests {
     }
 
     #[test]
-    fn test_substitute_mount_variables() {
+    fn test_workspace_folder_and_mount() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("repo");
+        fs::create_dir_all(path.join(".devcontainer")).unwrap();
+        let devcontainer_file = path.join(".devcontainer/devcontainer.json");
+        fs::write(
+            &devcontainer_file,
+            r#"{
+                "image": "ubuntu",
+                "workspaceFolder": "/workspaces/${localWorkspaceFolderBasename}/packages/api"
+            }"#,
+        )
+        .unwrap();
+        let workspace = Workspace::try_from(path.clone()).unwrap();
+        assert_eq!(
+            container_workspace_folder(&workspace),
+            "/workspaces/repo/packages/api"
+        );
+        assert_eq!(
+            WorkspaceMount::of(&workspace).unwrap().volume(true),
+            format!("{}:/workspaces/repo:Z", path.display())
+        );
+        assert!(
+            alias_script(&workspace, "make", &[]).starts_with("cd '/workspaces/repo/packages/api'")
+        );
+
+        // A sub-project mounting the whole repository
+        fs::write(
+            &devcontainer_file,
+            r#"{
+                "image": "ubuntu",
+                "workspaceMount": "source=${localWorkspaceFolder}/..,target=/src,type=bind,consistency=cached,readonly"
+            }"#,
+        )
+        .unwrap();
+        let workspace = Workspace::try_from(path.clone()).unwrap();
+        assert_eq!(container_workspace_folder(&workspace), "/src");
+        assert_eq!(
+            WorkspaceMount::of(&workspace).unwrap().volume(false),
+            format!("{}/..:/src:ro", path.display())
+        );
+
+        assert_eq!(
+            WorkspaceMount::parse("/repo:/workspace:ro").unwrap(),
+            WorkspaceMount {
+                source: "/repo".to_string(),
+                target: "/workspace".to_string(),
+                readonly: true,
+            }
+        );
+        assert!(WorkspaceMount::parse("source=/repo,type=bind").is_err());
+        assert!(WorkspaceMount::parse("source=/repo,target=/w,type=tmpfs").is_err());
+    }
+
+    #[test]
+    fn test_alias_script() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir(temp_dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            temp_dir.path().join(".devcontainer/devcontainer.json"),
+            r#"{"image": "mcr.microsoft.com/devcontainers/base:latest"}"#,
+        )
+        .unwrap();
+        let workspace = Workspace::try_from(temp_dir.path().to_path_buf()).unwrap();
+        let workspace_name = workspace.path.file_name().unwrap().to_string_lossy();
+
+        let script = alias_script(
+            &workspace,
+            "cargo nextest run",
+            &["--no-fail-fast".to_string(), "it's".to_string()],
+        );
+        assert_eq!(
+            script,
+            format!(
+                "cd '/workspaces/{}' && cargo nextest run '--no-fail-fast' 'it'\\''s'",
+                workspace_name
+            )
+        );
+    }
+
+    #[test]
+    fn test_substitute_variables() {
         use crate::config::Config;
-        use crate::driver::runtime::docker::DockerRuntime;
         use std::fs;
         use tempfile::TempDir;
 
@@ -1367,34 +4916,626 @@ mod tests {
 
         let workspace = Workspace::try_from(temp_dir.path().to_path_buf()).unwrap();
         let config = Config::default();
-        let runtime = Box::new(DockerRuntime::new(DockerRuntimeConfig::default()));
+        let runtime = Box::new(MockRuntime::new());
         let driver = ContainerDriver::new(config, runtime);
 
         // Test devcontainerId substitution
         let mount_str = "type=volume,source=myvolume-${devcontainerId},target=/data";
-        let result = driver.substitute_mount_variables(mount_str, &workspace);
+        let result = driver.substitute_variables(mount_str, &workspace);
         let devcontainer_id = driver.get_devcontainer_id(&workspace);
         assert!(result.contains(&devcontainer_id));
         assert!(!result.contains("${devcontainerId}"));
 
         // Test localWorkspaceFolder substitution
         let mount_str = "type=bind,source=${localWorkspaceFolder}/.config,target=/root/.config";
-        let result = driver.substitute_mount_variables(mount_str, &workspace);
+        let result = driver.substitute_variables(mount_str, &workspace);
         assert!(result.contains(&workspace.path.to_string_lossy().to_string()));
         assert!(!result.contains("${localWorkspaceFolder}"));
 
         // Test containerWorkspaceFolder substitution
         let workspace_name = workspace.path.file_name().unwrap().to_string_lossy();
         let mount_str = "type=bind,source=/tmp,target=${containerWorkspaceFolder}/tmp";
-        let result = driver.substitute_mount_variables(mount_str, &workspace);
+        let result = driver.substitute_variables(mount_str, &workspace);
         assert!(result.contains(&format!("/workspaces/{}", workspace_name)));
         assert!(!result.contains("${containerWorkspaceFolder}"));
 
         // Test multiple substitutions
         let mount_str = "${localWorkspaceFolder}:/workspaces/${devcontainerId}";
-        let result = driver.substitute_mount_variables(mount_str, &workspace);
+        let result = driver.substitute_variables(mount_str, &workspace);
         assert!(result.contains(&workspace.path.to_string_lossy().to_string()));
         assert!(result.contains(&devcontainer_id));
         assert!(!result.contains("${"));
+
+        // Test basename substitution
+        let result = driver.substitute_variables("${localWorkspaceFolderBasename}", &workspace);
+        assert_eq!(result, workspace_name);
+    }
+
+    #[test]
+    fn test_substitute_local_env() {
+        let lookup = |name: &str| (name == "USER").then(|| "octocat".to_string());
+        assert_eq!(
+            substitute_local_env("/home/${localEnv:USER}/.ssh", lookup),
+            "/home/octocat/.ssh"
+        );
+        assert_eq!(
+            substitute_local_env("${localEnv:MISSING:fallback}-${localEnv:MISSING}", lookup),
+            "fallback-"
+        );
+        assert_eq!(
+            substitute_local_env("${localEnv:USER", lookup),
+            "${localEnv:USER"
+        );
+    }
+
+    #[test]
+    fn test_dockerfile_template() {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        let template = env.template_from_str(DOCKERFILE_TEMPLATE).unwrap();
+        // All documented variables, rendering fails on any other
+        let contents = template
+            .render(minijinja::context! {
+                image => "ubuntu",
+                base_setup => "USER root",
+                feature_install => "FROM base AS feature_last",
+                env_setup => "",
+                docker_socket_setup => "",
+                dotfiles_setup => "",
+                workspace_name => "app",
+                workspace_folder => "/workspaces/app",
+                remote_user => "vscode",
+                container_user => "vscode",
+                remote_user_home => "/home/vscode",
+                container_user_home => "/home/vscode",
+                runtime_host_address => "host.docker.internal",
+                metadata_label_name => "devcontainer.metadata",
+                metadata_label => "\"[]\"",
+            })
+            .unwrap();
+        assert!(contents.starts_with("FROM ubuntu AS base\n"));
+        assert!(contents.contains("USER vscode\nWORKDIR /workspaces/app\n"));
+    }
+
+    #[test]
+    fn test_feature_run_mounts() {
+        assert_eq!(feature_run_mounts(false, true), "");
+        let mounts = feature_run_mounts(true, false);
+        assert!(mounts.starts_with("--mount=type=cache,target=/var/cache/apt,sharing=locked "));
+        assert!(!mounts.contains("type=secret"));
+        assert!(feature_run_mounts(true, true).ends_with(
+            "--mount=type=secret,id=registry_auth,target=/run/secrets/docker/config.json "
+        ));
+    }
+
+    #[test]
+    fn test_ca_certificates() {
+        let build_dir = tempfile::tempdir().unwrap();
+        assert_eq!(ca_certificate_setup(&[], build_dir.path()).unwrap(), "");
+
+        let certs_dir = tempfile::tempdir().unwrap();
+        let cert = certs_dir.path().join("corp-root.pem");
+        fs::write(&cert, "-----BEGIN CERTIFICATE-----\n").unwrap();
+
+        let setup = ca_certificate_setup(std::slice::from_ref(&cert), build_dir.path()).unwrap();
+        assert!(
+            build_dir
+                .path()
+                .join("ca-certificates/0-corp-root.crt")
+                .is_file()
+        );
+        assert!(
+            setup.starts_with("COPY ca-certificates/ /usr/local/share/devcon/ca-certificates/\n")
+        );
+        assert!(setup.contains("update-ca-certificates"));
+        assert!(setup.contains("ENV NODE_EXTRA_CA_CERTS=/usr/local/share/devcon/ca-bundle.crt"));
+
+        let mounts = ca_certificate_mounts(std::slice::from_ref(&cert));
+        assert_eq!(
+            mounts,
+            vec![format!(
+                "{}:/usr/local/share/devcon/ca-certificates/0-corp-root.crt:ro",
+                cert.canonicalize().unwrap().to_string_lossy()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_feature_cache_key() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("install.sh"), "echo node").unwrap();
+        fs::write(
+            dir.path().join("devcontainer-features.env"),
+            "VERSION=\"20\"",
+        )
+        .unwrap();
+        let first = hash_directory(dir.path()).unwrap();
+        assert_eq!(hash_directory(dir.path()).unwrap(), first);
+
+        // Other options result in another layer
+        fs::write(
+            dir.path().join("devcontainer-features.env"),
+            "VERSION=\"22\"",
+        )
+        .unwrap();
+        assert_ne!(hash_directory(dir.path()).unwrap(), first);
+
+        let base = feature_cache_key("ubuntu:24.04", "ENV DEVCON=true");
+        let key = feature_cache_key(&base, &first);
+        assert_eq!(key, feature_cache_key(&base, &first));
+        assert_ne!(
+            key,
+            feature_cache_key(&feature_cache_key("debian", ""), &first)
+        );
+        assert_eq!(
+            feature_cache_tag("Common Utils", &key),
+            format!("devcon-feature-cache:common-utils-{}", &key[..16])
+        );
+    }
+
+    #[test]
+    fn test_host_mount_path() {
+        assert_eq!(
+            host_mount_path(r"C:\Users\me\project"),
+            "/c/Users/me/project"
+        );
+        assert_eq!(host_mount_path(r"\\?\D:\work"), "/d/work");
+        assert_eq!(host_mount_path("E:/src"), "/e/src");
+        assert_eq!(host_mount_path("/home/me/project"), "/home/me/project");
+        assert_eq!(host_mount_path("cache"), "cache");
+    }
+
+    #[test]
+    fn test_selinux_relabeled() {
+        use crate::devcontainer::{Mount, MountType, StructuredMount};
+
+        let relabeled = |mount: Mount| match selinux_relabeled(mount) {
+            Mount::String(s) => s,
+            Mount::Structured(structured) => format!("structured:{}", structured.target),
+        };
+        assert_eq!(relabeled(Mount::String("/src:/dst".into())), "/src:/dst:Z");
+        assert_eq!(
+            relabeled(Mount::String("/src:/dst:ro".into())),
+            "/src:/dst:ro,Z"
+        );
+        assert_eq!(
+            relabeled(Mount::String("/src:/dst:z".into())),
+            "/src:/dst:z"
+        );
+        assert_eq!(relabeled(Mount::String("cache:/dst".into())), "cache:/dst");
+        assert_eq!(
+            relabeled(Mount::Structured(StructuredMount {
+                mount_type: MountType::Bind,
+                source: Some("/src".into()),
+                target: "/dst".into(),
+            })),
+            "/src:/dst:Z"
+        );
+        assert_eq!(
+            relabeled(Mount::Structured(StructuredMount {
+                mount_type: MountType::Volume,
+                source: Some("cache".into()),
+                target: "/dst".into(),
+            })),
+            "structured:/dst"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_initialize_command() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            temp_dir.path().join(".devcontainer/devcontainer.json"),
+            r#"{
+                "image": "mcr.microsoft.com/devcontainers/base:latest",
+                "initializeCommand": "echo ${localWorkspaceFolderBasename} > initialized.txt"
+            }"#,
+        )
+        .unwrap();
+
+        let workspace = Workspace::try_from(temp_dir.path().to_path_buf()).unwrap();
+        let config = Config {
+            build_progress: Some("quiet".to_string()),
+            ..Config::default()
+        };
+        let runtime = Box::new(MockRuntime::new());
+        let driver = ContainerDriver::new(config, runtime);
+
+        driver.run_initialize_command(&workspace).unwrap();
+        let name = workspace.path.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("initialized.txt")).unwrap(),
+            format!("{}\n", name)
+        );
+    }
+
+    /// Creates a project with the given devcontainer.json and a driver with a
+    /// mock runtime, without the agent.
+    fn mock_driver(
+        devcontainer_json: &str,
+    ) -> (tempfile::TempDir, Workspace, ContainerDriver, MockRuntime) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join(".devcontainer")).unwrap();
+        fs::write(
+            temp_dir.path().join(".devcontainer/devcontainer.json"),
+            devcontainer_json,
+        )
+        .unwrap();
+        let workspace = Workspace::try_from(temp_dir.path().to_path_buf()).unwrap();
+
+        let config = Config {
+            build_progress: Some("quiet".to_string()),
+            agents: Some(crate::config::AgentConfig {
+                disable: Some(true),
+                ..Default::default()
+            }),
+            ..Config::default()
+        };
+        let runtime = MockRuntime::new();
+        let driver = ContainerDriver::new(config, Box::new(runtime.clone()));
+        (temp_dir, workspace, driver, runtime)
+    }
+
+    #[test]
+    fn test_build_generates_dockerfile() {
+        let feature_dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            feature_dir.path().join("devcontainer-feature.json"),
+            r#"{ "id": "hello", "version": "1.0.0", "containerEnv": { "GREETING": "hi" } }"#,
+        )
+        .unwrap();
+        fs::write(feature_dir.path().join("install.sh"), "echo hello").unwrap();
+
+        let (_temp_dir, workspace, driver, runtime) = mock_driver(&format!(
+            r#"{{
+                "image": "debian:bookworm",
+                "features": {{ "{}": {{}} }}
+            }}"#,
+            feature_dir.path().display()
+        ));
+        let image_tag = format!("devcon-{}", workspace.get_sanitized_name());
+        driver
+            .build(workspace.clone(), &["MODE=ci".to_string()], None)
+            .unwrap();
+
+        // The feature is built into a cached layer, the image on top of it
+        let invocations = runtime.invocations();
+        let [
+            Invocation::Build {
+                image_tag: layer_tag,
+                dockerfile: layer,
+            },
+            Invocation::Build {
+                image_tag: tag,
+                dockerfile,
+            },
+        ] = invocations.as_slice()
+        else {
+            panic!("Expected two builds: {:?}", invocations);
+        };
+        assert!(layer.starts_with("FROM debian:bookworm\n"), "{}", layer);
+        assert!(layer.contains("\nENV GREETING=hi"), "{}", layer);
+        assert!(layer.contains("./install.sh"), "{}", layer);
+        assert_eq!(tag, &image_tag);
+        assert!(
+            dockerfile.contains(&format!("FROM {} AS feature_last", layer_tag)),
+            "{}",
+            dockerfile
+        );
+        assert!(dockerfile.contains("ENV MODE=ci"), "{}", dockerfile);
+        assert_eq!(runtime.last_dockerfile().as_ref(), Some(dockerfile));
+        assert!(driver.runtime.image_exists(&image_tag).unwrap());
+
+        WorkspaceState::remove(&workspace.get_sanitized_name()).unwrap();
+    }
+
+    #[test]
+    fn test_build_partial_image() {
+        let (_temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let driver = driver.with_stages(
+            BuildStages::new(Some(BuildStage::Env), vec![BuildStage::Features]).unwrap(),
+        );
+        driver
+            .build(workspace.clone(), &["MODE=ci".to_string()], None)
+            .unwrap();
+
+        let invocations = runtime.invocations();
+        let [
+            Invocation::Build {
+                image_tag,
+                dockerfile,
+            },
+        ] = invocations.as_slice()
+        else {
+            panic!("Expected one build: {:?}", invocations);
+        };
+        assert_eq!(
+            image_tag,
+            &format!("devcon-{}:partial", workspace.get_sanitized_name())
+        );
+        assert!(
+            dockerfile.contains("FROM base AS feature_last"),
+            "{}",
+            dockerfile
+        );
+        assert!(dockerfile.contains("ENV MODE=ci"), "{}", dockerfile);
+        assert!(!dockerfile.contains("dotfiles_helper"), "{}", dockerfile);
+        // The workspace keeps its image
+        assert!(
+            WorkspaceState::load(&workspace.get_sanitized_name())
+                .unwrap()
+                .config_hash
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_build_records_stage_timings() {
+        let (temp_dir, workspace, driver, _runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        driver.build(workspace, &[], None).unwrap();
+        // The mock runtime prints no BuildKit steps
+        let timings = driver.timings().timings();
+        assert!(
+            timings
+                .iter()
+                .any(|t| t.kind == TimingKind::Stage && t.name == "image"),
+            "{:?}",
+            timings
+        );
+
+        let log_path = temp_dir.path().join("build.log");
+        fs::write(
+            &log_path,
+            "#4 [base 1/1] FROM debian\n#4 DONE 1.0s\n#5 [feature_0 1/1] RUN install\n#5 DONE 2.0s\n",
+        )
+        .unwrap();
+        let output = BuildOutput {
+            log_path: Some(log_path),
+            ..Default::default()
+        };
+        let driver = ContainerDriver::new(Config::default(), Box::new(MockRuntime::new()));
+        driver.record_stage_timings(
+            &output,
+            std::time::Duration::from_secs(5),
+            &["node".to_string()],
+        );
+        let names: Vec<String> = driver
+            .timings()
+            .timings()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["base", "feature node"]);
+    }
+
+    #[test]
+    fn test_build_pulls_base_image_from_mirror() {
+        let (_temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let config = Config {
+            registry_mirrors: Some("docker.io=mirror.corp/hub".to_string()),
+            ..driver.config.clone()
+        };
+        let driver = ContainerDriver::new(config, Box::new(runtime.clone()));
+        driver.build(workspace.clone(), &[], None).unwrap();
+
+        let dockerfile = runtime.last_dockerfile().unwrap();
+        assert!(
+            dockerfile.contains("FROM mirror.corp/hub/library/debian:bookworm"),
+            "{}",
+            dockerfile
+        );
+
+        WorkspaceState::remove(&workspace.get_sanitized_name()).unwrap();
+    }
+
+    #[test]
+    fn test_start_runs_lifecycle_in_order() {
+        let (_temp_dir, workspace, driver, runtime) = mock_driver(
+            r#"{
+                "image": "debian:bookworm",
+                "onCreateCommand": "echo onCreate",
+                "updateContentCommand": "echo updateContent",
+                "postCreateCommand": "echo postCreate",
+                "postStartCommand": "echo postStart"
+            }"#,
+        );
+        let runtime = runtime.with_image(&format!("devcon-{}", workspace.get_sanitized_name()));
+
+        driver.start(workspace.clone(), &[]).unwrap();
+
+        let hooks: Vec<String> = runtime
+            .exec_commands()
+            .iter()
+            .filter_map(|command| command.last())
+            .filter_map(|script| {
+                ["onCreate", "updateContent", "postCreate", "postStart"]
+                    .into_iter()
+                    .find(|hook| script.contains(&format!("echo {}", hook)))
+            })
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            hooks,
+            vec!["onCreate", "updateContent", "postCreate", "postStart"]
+        );
+        assert_eq!(driver.runtime.list().unwrap().len(), 1);
+
+        WorkspaceState::remove(&workspace.get_sanitized_name()).unwrap();
+    }
+
+    #[test]
+    fn test_failed_lifecycle_hook_stops_later_hooks() {
+        let (_temp_dir, workspace, driver, runtime) = mock_driver(
+            r#"{
+                "image": "debian:bookworm",
+                "onCreateCommand": "exit 3",
+                "postCreateCommand": "echo postCreate"
+            }"#,
+        );
+        let runtime = runtime
+            .with_image(&format!("devcon-{}", workspace.get_sanitized_name()))
+            .with_exec_exit_codes(&[3]);
+
+        assert!(driver.start(workspace.clone(), &[]).is_err());
+        assert!(
+            !runtime
+                .exec_commands()
+                .iter()
+                .any(|command| command.iter().any(|arg| arg.contains("echo postCreate")))
+        );
+
+        WorkspaceState::remove(&workspace.get_sanitized_name()).unwrap();
+    }
+
+    #[test]
+    fn test_start_requires_built_image() {
+        let (_temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+
+        let error = driver.start(workspace, &[]).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RuntimeError>(),
+            Some(RuntimeError::ImageNotFound)
+        ));
+        assert!(runtime.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_start_reports_container_of_other_user() {
+        let (_temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let runtime = runtime.with_container_of(&workspace.get_sanitized_name(), "abc", "alice");
+
+        let error = driver.start(workspace, &[]).unwrap_err();
+        assert!(error.to_string().contains("belongs to alice"));
+        assert!(
+            !runtime
+                .invocations()
+                .iter()
+                .any(|i| matches!(i, Invocation::Run { .. }))
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_foreign_image() {
+        let (temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let runtime = runtime.with_image("postgres:16");
+        let image = temp_dir.path().join("image.tar");
+        fs::write(&image, "layers").unwrap();
+        let archive = temp_dir.path().join("env.tar");
+        let manifest = environment::Manifest {
+            version: environment::FORMAT_VERSION,
+            workspace: workspace.get_sanitized_name(),
+            image: "postgres:16".to_string(),
+            config: None,
+            config_hash: String::new(),
+            devcontainer: PathBuf::from(".devcontainer/devcontainer.json"),
+            created_at: String::new(),
+        };
+        environment::pack(
+            &archive,
+            &manifest,
+            &environment::Lockfile::default(),
+            &image,
+            &workspace.path,
+            temp_dir.path(),
+            &[],
+        )
+        .unwrap();
+
+        let Err(error) = driver.import_environment(&archive, workspace.path.clone(), None) else {
+            panic!("Importing a foreign image succeeded");
+        };
+        assert!(error.to_string().contains("no devcon image"));
+        assert!(runtime.image_exists("postgres:16").unwrap());
+    }
+
+    #[test]
+    fn test_link_workspaces() {
+        let (_temp_dir, frontend, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let api_dir = tempfile::TempDir::new().unwrap();
+        let api_path = api_dir.path().join("API_server");
+        fs::create_dir_all(api_path.join(".devcontainer")).unwrap();
+        fs::write(
+            api_path.join(".devcontainer/devcontainer.json"),
+            r#"{ "image": "debian:bookworm" }"#,
+        )
+        .unwrap();
+        let api = Workspace::try_from(api_path).unwrap();
+
+        let runtime = runtime.with_container(&frontend.get_sanitized_name(), "front1");
+        let error = driver.link(&frontend, &api).unwrap_err();
+        assert!(format!("{:#}", error).contains("API_server is not running"));
+        assert!(driver.link(&frontend, &frontend).is_err());
+
+        let runtime = runtime.with_container(&api.get_sanitized_name(), "api1");
+        let network = driver.link(&api, &frontend).unwrap();
+        assert_eq!(
+            network,
+            link_network(&frontend.get_sanitized_name(), "api_server")
+        );
+        let invocations = runtime.invocations();
+        assert_eq!(
+            invocations[0],
+            Invocation::CreateNetwork {
+                name: network.clone()
+            }
+        );
+        assert_eq!(
+            invocations[1],
+            Invocation::ConnectNetwork {
+                container_id: "api1".to_string(),
+                network: network.clone(),
+                aliases: vec![
+                    "api-server".to_string(),
+                    "api-server.devcon.internal".to_string()
+                ],
+            }
+        );
+        assert!(matches!(
+            &invocations[2],
+            Invocation::ConnectNetwork { container_id, .. } if container_id == "front1"
+        ));
+        for workspace in [&frontend, &api] {
+            let state = WorkspaceState::load(&workspace.get_sanitized_name()).unwrap();
+            assert_eq!(state.networks, vec![network.clone()]);
+            WorkspaceState::update(&workspace.get_sanitized_name(), |state| {
+                state.networks.clear()
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_inspect_and_stop_running_container() {
+        let (_temp_dir, workspace, driver, runtime) =
+            mock_driver(r#"{ "image": "debian:bookworm" }"#);
+        let name = workspace.get_sanitized_name();
+        let runtime = runtime
+            .with_image_labels(&format!("devcon-{}", name), &[("version", "1")])
+            .with_container(&name, "abc123");
+
+        let inspection = driver.inspect(&workspace).unwrap();
+        assert_eq!(
+            inspection.image.labels,
+            Some(BTreeMap::from([("version".to_string(), "1".to_string())]))
+        );
+        assert_eq!(inspection.container.unwrap().id, "abc123");
+
+        driver.stop(&workspace).unwrap();
+        assert_eq!(
+            runtime.invocations(),
+            vec![Invocation::Stop {
+                container_id: "abc123".to_string()
+            }]
+        );
+        assert!(driver.runtime.list().unwrap().is_empty());
     }
 }
@@ -24,50 +24,633 @@
 //!
 //! This module implements the TCP control server that accepts connections from
 //! container agents and manages port forwarding requests.
+//!
+//! Agents open the connection with a `Hello` announcing their protocol version
+//! and capabilities, which the host answers with a `HelloAck` carrying the
+//! negotiated version and enabled capabilities (or a rejection). Agents which
+//! predate the handshake are treated as protocol version 1 without capabilities.
+//!
+//! Tunnels are carried in one of two ways. Legacy agents dial back to a
+//! per-forward data port for every forwarded connection. Agents which negotiated
+//! the multiplex capability instead have their tunnels multiplexed over the
+//! control connection as `TunnelData`/`TunnelClose` messages, so only a single
+//! container-to-host connection is needed. With the flow control capability,
+//! each direction of a multiplexed tunnel pauses once its window is used up
+//! until the receiver grants credit with `TunnelWindowUpdate`, so a slow
+//! client neither stalls the connection nor has its tunnel dropped.
+//!
+//! Agents of projects with encrypted tunnels negotiate the encrypted tunnels
+//! capability instead of multiplexing. Their data connections then run a Noise
+//! handshake keyed with the workspace tunnel key before any tunnel data is sent.
+//! Forwards of workspaces recorded as encrypted by `devcon start` are refused
+//! unless the agent negotiated encrypted tunnels.
+//!
+//! The workspace an agent announces is chosen by the container. Requests
+//! acting on a workspace of the host, like opening its files, are attributed
+//! by the host instead, mapping the address the agent connects from to one of
+//! the current user's containers and its labels.
+//!
+//! With the gRPC control scheme (`grpc` feature), the control port is served
+//! by a gRPC `ControlPlane` service instead. Each gRPC session is bridged to a
+//! connection on a loopback listener, so the protocol handling is shared.
+//!
+//! The forwarding policy, port allow/deny lists and notification settings are
+//! re-read from the config on `SIGHUP` (sent by `devcon serve reload`). A
+//! reload only applies to new forwards and requests, existing agent
+//! connections and tunnels are kept.
 
-use anyhow::{Context, Result, bail};
-use devcon_proto::AgentMessage;
+use anyhow::{Context, Result};
 use devcon_proto::agent_message::Message as ProtoMessage;
+use devcon_proto::auto_forward::AutoForwardPolicy;
+use devcon_proto::bandwidth::RateLimitedReader;
+use devcon_proto::flow_control::SendWindow;
+use devcon_proto::noise::{self, TunnelKey};
+use devcon_proto::proxy::proxy;
+use devcon_proto::tunnel_writer::TunnelWriter;
+use devcon_proto::{AgentMessage, AutoForwardAction, Capability, TUNNEL_CHUNK_SIZE};
 use prost::Message;
-use std::collections::HashMap;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+use crate::driver::dashboard::{
+    self, AgentInfo, ContainerUsage, EventKind, EventLog, render_dashboard,
+};
+use crate::driver::hooks::{self, Hook, HookContext};
+use crate::driver::metrics::{
+    CountingReader, Direction, ForwardMetrics, ForwardStats, port_label, render_prometheus,
+};
+use crate::driver::notification::{NotificationLimiter, show_notification};
+use crate::driver::open::OpenPolicy;
+use crate::driver::tunnel_key;
+use crate::exit_code;
+
+/// Capabilities supported by this control server
+const SUPPORTED_CAPABILITIES: &[Capability] = &[
+    Capability::Multiplex,
+    Capability::Heartbeat,
+    Capability::Compression,
+    Capability::EncryptedTunnels,
+    Capability::FlowControl,
+];
+
+/// Errors of port forwarding and the agent connection
+#[derive(Debug, thiserror::Error)]
+pub enum TunnelError {
+    /// The forwarding policy does not allow the port
+    #[error("Port {0} is not allowed by the forwarding policy")]
+    PortNotAllowed(u16),
+
+    /// The auto-forward policy excludes the detected port
+    #[error("Port {0} is not forwarded automatically by the auto-forward policy")]
+    NotAutoForwarded(u16),
+
+    /// The workspace enables encrypted tunnels, but the agent did not negotiate them
+    #[error("Tunnels of {0} must be encrypted")]
+    EncryptionRequired(String),
+
+    /// The port is forwarded already
+    #[error("Port {0} is already being forwarded")]
+    PortInUse(u16),
+
+    /// The port is not forwarded
+    #[error("Port {0} is not being forwarded")]
+    NotForwarded(u16),
+
+    /// The agent did not connect the tunnel in time
+    #[error("Tunnel establishment timeout")]
+    Timeout,
+
+    /// The agent closed the connection in the middle of a message
+    #[error("Connection closed while reading {0}")]
+    ConnectionClosed(String),
+
+    /// The agent sent a malformed message
+    #[error("{0}")]
+    InvalidMessage(String),
+}
+
+impl TunnelError {
+    /// Returns the process exit code for this error, all tunnel errors are
+    /// network errors.
+    pub fn exit_code(&self) -> i32 {
+        exit_code::NETWORK
+    }
+}
+
+/// Heartbeat settings for agent connections
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    /// Interval between pings sent to the agent
+    pub interval: Duration,
+    /// Time without any message from the agent after which the connection is considered dead
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Samples the resource usage of the running workspace containers
+pub type UsageLookup = dyn Fn() -> Vec<ContainerUsage> + Send + Sync;
+
+/// Looks up the workspace of the container with an address
+pub type PeerLookup = dyn Fn(IpAddr) -> Option<String> + Send + Sync;
+
+/// Settings of the control server which can be reloaded while it is running
+#[derive(Clone)]
+pub struct ServerPolicy {
+    /// Bandwidth limit in bytes per second applied to every tunnel
+    pub max_bandwidth: Option<u64>,
+    /// Whether compression of multiplexed tunnels is offered to agents
+    pub compression: bool,
+    /// Ports agents may forward, all ports if empty
+    pub allowed_ports: Vec<RangeInclusive<u16>>,
+    /// Ports agents may never forward, takes precedence over `allowed_ports`
+    pub denied_ports: Vec<RangeInclusive<u16>>,
+    /// Host ports agents may connect to, none if empty
+    pub host_ports: Vec<RangeInclusive<u16>>,
+    /// Which ports detected by agents may be forwarded automatically
+    pub auto_forward: AutoForwardPolicy,
+    /// Whether desktop notifications requested by agents are shown
+    pub notifications: bool,
+    /// Policy for opening workspace files on the host
+    pub open: OpenPolicy,
+    /// Host hook run after a port forward was started
+    pub port_forwarded_hook: Option<String>,
+    /// Resource usage of the containers shown on the dashboard
+    pub container_usage: Arc<UsageLookup>,
+    /// Workspace of the container an agent connects from
+    pub peer_workspace: Arc<PeerLookup>,
+}
+
+impl Default for ServerPolicy {
+    fn default() -> Self {
+        Self {
+            max_bandwidth: None,
+            compression: true,
+            allowed_ports: Vec::new(),
+            denied_ports: Vec::new(),
+            host_ports: Vec::new(),
+            auto_forward: AutoForwardPolicy::default(),
+            notifications: true,
+            open: OpenPolicy::default(),
+            port_forwarded_hook: None,
+            container_usage: Arc::new(Vec::new),
+            peer_workspace: Arc::new(|_| None),
+        }
+    }
+}
+
+impl ServerPolicy {
+    /// Whether agents may forward `port`
+    fn permits_port(&self, port: u16) -> bool {
+        let allowed =
+            self.allowed_ports.is_empty() || self.allowed_ports.iter().any(|r| r.contains(&port));
+        allowed && !self.denied_ports.iter().any(|r| r.contains(&port))
+    }
+
+    /// Whether agents may connect to `port` on the host
+    fn permits_host_port(&self, port: u16) -> bool {
+        self.host_ports.iter().any(|r| r.contains(&port))
+    }
+}
+
 /// Type alias for a port forward entry containing the agent stream, container port, tunnel ID counter, and data port
+///
+/// A data port of 0 marks a forward whose tunnels are multiplexed over the agent stream.
 type ForwardEntry = (Arc<Mutex<TcpStream>>, u16, Arc<AtomicU32>, u16);
 
+/// Type alias for the map of tunnel_id -> (pending client stream, accept time)
+type PendingTunnels = Arc<Mutex<HashMap<u32, (TcpStream, Instant)>>>;
+
+/// Host side of a multiplexed tunnel
+struct MuxTunnel {
+    /// Writer of the client stream receiving data from the agent
+    client: TunnelWriter,
+    /// Metrics of the forward the tunnel belongs to
+    metrics: Arc<ForwardMetrics>,
+    /// Accept time of the client, cleared once the first byte arrived from the container
+    started: Option<Instant>,
+}
+
+/// Tunnels multiplexed over a single agent control connection
+struct MuxSession {
+    /// Map of tunnel_id -> host side of the tunnel
+    tunnels: Mutex<HashMap<u32, MuxTunnel>>,
+    /// Map of tunnel_id -> window of the data sent to the agent
+    windows: Mutex<HashMap<u32, Arc<SendWindow>>>,
+    /// Tunnel ID counter shared by all forwards of the connection
+    next_tunnel_id: AtomicU32,
+    /// Whether tunnel payloads may be compressed on this connection
+    compress: bool,
+    /// Whether the tunnels are flow controlled
+    flow_control: bool,
+    /// The agent connection, used to grant credit
+    agent: Arc<Mutex<TcpStream>>,
+}
+
+impl MuxSession {
+    fn new(compress: bool, flow_control: bool, agent: Arc<Mutex<TcpStream>>) -> Self {
+        Self {
+            tunnels: Mutex::new(HashMap::new()),
+            windows: Mutex::new(HashMap::new()),
+            next_tunnel_id: AtomicU32::new(1),
+            compress,
+            flow_control,
+            agent,
+        }
+    }
+
+    /// Register the host side of a new tunnel, returning the window of the
+    /// data sent to the agent
+    fn open(
+        &self,
+        tunnel_id: u32,
+        client: TcpStream,
+        metrics: Arc<ForwardMetrics>,
+    ) -> Result<Arc<SendWindow>> {
+        let (client, window) = if self.flow_control {
+            let agent = self.agent.clone();
+            let client = TunnelWriter::with_credit(client, move |credit| {
+                grant_credit(&agent, tunnel_id, credit);
+            })?;
+            (client, SendWindow::new())
+        } else {
+            (TunnelWriter::spawn(client)?, SendWindow::unlimited())
+        };
+        let window = Arc::new(window);
+        self.tunnels.lock().unwrap().insert(
+            tunnel_id,
+            MuxTunnel {
+                client,
+                metrics,
+                started: Some(Instant::now()),
+            },
+        );
+        self.windows
+            .lock()
+            .unwrap()
+            .insert(tunnel_id, window.clone());
+        Ok(window)
+    }
+
+    /// Add credit granted by the agent to the window of a tunnel
+    fn grant(&self, update: &devcon_proto::TunnelWindowUpdate) {
+        if let Some(window) = self.windows.lock().unwrap().get(&update.tunnel_id) {
+            window.grant(update.credit);
+        }
+    }
+
+    /// Deliver data received from the agent to the host client of a tunnel
+    fn deliver(&self, message: &devcon_proto::TunnelData) {
+        let tunnel_id = message.tunnel_id;
+        let mut tunnels = self.tunnels.lock().unwrap();
+        let Some(tunnel) = tunnels.get_mut(&tunnel_id) else {
+            // Data of a closed tunnel is dropped, but still credited so the
+            // agent doesn't pause the tunnel forever
+            if self.flow_control
+                && let Ok(data) = message.payload()
+            {
+                grant_credit(&self.agent, tunnel_id, data.len() as u32);
+            }
+            return;
+        };
+
+        let data = match message.payload() {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Dropping tunnel_id={}: {}", tunnel_id, e);
+                if let Some(tunnel) = tunnels.remove(&tunnel_id) {
+                    tunnel.client.abort();
+                }
+                return;
+            }
+        };
+
+        tunnel.metrics.add_bytes_from_container(data.len() as u64);
+        if let Some(started) = tunnel.started.take() {
+            tunnel.metrics.record_latency(started.elapsed());
+        }
+
+        // Only queue the data, a slow client must not stall the agent connection
+        if let Err(e) = tunnel.client.send(data) {
+            debug!(
+                "Failed to write to client of tunnel_id={}: {}",
+                tunnel_id, e
+            );
+            if let Some(tunnel) = tunnels.remove(&tunnel_id) {
+                tunnel.client.abort();
+            }
+        }
+    }
+
+    /// Close the agent->client direction of a tunnel
+    fn close(&self, tunnel_id: u32) {
+        if let Some(tunnel) = self.tunnels.lock().unwrap().remove(&tunnel_id) {
+            debug!("Agent closed tunnel_id={}", tunnel_id);
+            tunnel.client.close();
+        }
+    }
+
+    /// Close all tunnels, used when the agent connection goes away
+    fn close_all(&self) {
+        for (_, tunnel) in self.tunnels.lock().unwrap().drain() {
+            tunnel.client.abort();
+        }
+        for (_, window) in self.windows.lock().unwrap().drain() {
+            window.close();
+        }
+    }
+}
+
+/// Grant the agent credit for more data of a multiplexed tunnel
+fn grant_credit(agent: &Mutex<TcpStream>, tunnel_id: u32, credit: u32) {
+    let message = AgentMessage {
+        message: Some(ProtoMessage::TunnelWindowUpdate(
+            devcon_proto::TunnelWindowUpdate { tunnel_id, credit },
+        )),
+    };
+    if let Err(e) = send_message(&mut agent.lock().unwrap(), &message) {
+        debug!("Failed to grant credit to tunnel_id={}: {}", tunnel_id, e);
+    }
+}
+
+/// How the tunnels of a forward reach the agent
+#[derive(Clone)]
+enum TunnelMode {
+    /// The agent dials back to a data port, encrypting the connection if a key is set
+    Dedicated(Option<TunnelKey>),
+    /// Tunnels are multiplexed over the control connection
+    Multiplexed(Arc<MuxSession>),
+}
+
 /// Manages active port forwarding sessions
 #[derive(Clone)]
 struct PortForwardManager {
     /// Map of local_port -> (agent_stream, container_port, tunnel_id_counter, data_port)
     forwards: Arc<Mutex<HashMap<u16, ForwardEntry>>>,
     /// Map of tunnel_id -> pending client stream
-    pending_tunnels: Arc<Mutex<HashMap<u32, TcpStream>>>,
+    pending_tunnels: PendingTunnels,
+    /// Map of local_port -> transfer metrics of the forward
+    metrics: Arc<Mutex<HashMap<u16, Arc<ForwardMetrics>>>>,
+    /// Reloadable settings of the server
+    policy: Arc<RwLock<ServerPolicy>>,
+    /// Map of agent peer address -> information about the connected agent
+    agents: Arc<Mutex<HashMap<String, AgentInfo>>>,
+    /// Recent events shown on the dashboard
+    events: EventLog,
+    /// Rate limit of desktop notifications per container, see [`Self::notification_key`]
+    notifications: NotificationLimiter,
 }
 
 impl PortForwardManager {
-    fn new() -> Self {
+    fn new(policy: ServerPolicy) -> Self {
         Self {
             forwards: Arc::new(Mutex::new(HashMap::new())),
             pending_tunnels: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Arc::new(Mutex::new(HashMap::new())),
+            policy: Arc::new(RwLock::new(policy)),
+            agents: Arc::new(Mutex::new(HashMap::new())),
+            events: EventLog::default(),
+            notifications: NotificationLimiter::default(),
+        }
+    }
+
+    /// Snapshot the current settings
+    fn policy(&self) -> ServerPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    /// Replace the settings, applying to new forwards and requests only
+    fn reload(&self, policy: ServerPolicy) {
+        *self.policy.write().unwrap() = policy;
+        info!("Reloaded control server configuration");
+        self.events.push(
+            EventKind::ConfigReloaded,
+            String::new(),
+            "Reloaded configuration".to_string(),
+        );
+    }
+
+    /// Snapshot the connected agents, ordered by connection time
+    fn agents(&self) -> Vec<AgentInfo> {
+        let mut agents: Vec<AgentInfo> = self.agents.lock().unwrap().values().cloned().collect();
+        agents.sort_by_key(|a| a.connected_at);
+        agents
+    }
+
+    /// Update the information of a connected agent
+    fn update_agent(&self, peer: &str, update: impl FnOnce(&mut AgentInfo)) {
+        if let Some(agent) = self.agents.lock().unwrap().get_mut(peer) {
+            update(agent);
+        }
+    }
+
+    /// Name of an agent for event messages, its workspace if known
+    fn agent_name(&self, peer: &str) -> String {
+        self.agents
+            .lock()
+            .unwrap()
+            .get(peer)
+            .filter(|a| !a.workspace.is_empty())
+            .map(|a| a.workspace.clone())
+            .unwrap_or_else(|| peer.to_string())
+    }
+
+    /// Mark the agents of a workspace as suspended while its container is
+    /// paused, or as running again once it resumed
+    ///
+    /// The forwards of suspended agents are kept and their connections are
+    /// not dropped for missing heartbeats. Returns the number of agents of
+    /// the workspace.
+    fn set_suspended(&self, workspace: &str, suspended: bool) -> usize {
+        let mut count = 0;
+        for agent in self.agents.lock().unwrap().values_mut() {
+            if agent.workspace == workspace {
+                agent.suspended = suspended;
+                count += 1;
+            }
+        }
+
+        let (kind, message) = if suspended {
+            (
+                EventKind::WorkspaceSuspended,
+                format!("Suspended forwards of {}", workspace),
+            )
+        } else {
+            (
+                EventKind::WorkspaceResumed,
+                format!("Resumed forwards of {}", workspace),
+            )
+        };
+        self.events.push(kind, workspace, message);
+        count
+    }
+
+    /// Whether the container of an agent is paused
+    fn is_suspended(&self, peer: &str) -> bool {
+        self.agents
+            .lock()
+            .unwrap()
+            .get(peer)
+            .is_some_and(|a| a.suspended)
+    }
+
+    /// Workspace of an agent, empty if not known
+    fn agent_workspace(&self, peer: &str) -> String {
+        self.agents
+            .lock()
+            .unwrap()
+            .get(peer)
+            .map(|a| a.workspace.clone())
+            .unwrap_or_default()
+    }
+
+    /// Workspace of the container connecting from `peer` as known to the host
+    ///
+    /// Unlike the workspace announced by the agent, it can't be chosen by the
+    /// container.
+    fn peer_workspace(&self, peer: IpAddr) -> Option<String> {
+        (self.policy().peer_workspace)(peer)
+    }
+
+    /// Key of the desktop notification rate limit of an agent
+    ///
+    /// Workspaces named by agents are chosen by the container, so the limit
+    /// applies to the container the host attributes the agent to, or to its
+    /// address. One-shot connections use a new port every time.
+    fn notification_key(&self, peer: IpAddr) -> String {
+        self.peer_workspace(peer)
+            .unwrap_or_else(|| peer.to_string())
+    }
+
+    /// Take the action an agent requested for a newly forwarded port
+    ///
+    /// Opens `http://localhost:<port>` in the browser, or shows a desktop
+    /// notification with the URL subject to the notification settings.
+    fn announce_forward(
+        &self,
+        peer_addr: SocketAddr,
+        port: u16,
+        label: &str,
+        action: AutoForwardAction,
+    ) {
+        let peer = &peer_addr.to_string();
+        let url = format!("http://localhost:{}", port);
+        let workspace = self.agent_workspace(peer);
+        match action {
+            AutoForwardAction::None => {}
+            AutoForwardAction::OpenBrowser => {
+                self.events.push(
+                    EventKind::UrlOpened,
+                    workspace,
+                    format!("{} opened {}", self.agent_name(peer), url),
+                );
+                if let Err(e) = open_url(&url) {
+                    error!("Failed to open URL: {}", e);
+                }
+            }
+            AutoForwardAction::Notify => {
+                let key = self.notification_key(peer_addr.ip());
+                if !self.policy().notifications {
+                    debug!("Not announcing port {}, notifications are disabled", port);
+                } else if self.notifications.allow(&key) {
+                    let title = if workspace.is_empty() {
+                        "DevCon".to_string()
+                    } else {
+                        format!("DevCon - {}", workspace)
+                    };
+                    let body = format!("Port {} is available at {}", label, url);
+                    self.events.push(
+                        EventKind::Notification,
+                        workspace,
+                        format!("{}: {}", title, body),
+                    );
+                    if let Err(e) = show_notification(&title, &body) {
+                        error!("Failed to show notification: {}", e);
+                    }
+                } else {
+                    warn!("Not announcing port {}, rate limit exceeded", port);
+                }
+            }
+        }
+    }
+
+    /// Snapshot the metrics of all active forwards, ordered by local port
+    fn stats(&self) -> Vec<ForwardStats> {
+        let mut stats: Vec<ForwardStats> = self
+            .metrics
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(port, metrics)| metrics.snapshot(*port))
+            .collect();
+        let suspended: HashSet<u16> = self
+            .agents
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| a.suspended)
+            .flat_map(|a| a.ports.iter().copied())
+            .collect();
+        for forward in &mut stats {
+            forward.suspended = suspended.contains(&forward.local_port);
         }
+        stats.sort_by_key(|s| s.local_port);
+        stats
     }
 
     /// Start forwarding a port through the control connection
+    ///
+    /// Multiplexed tunnels are carried over `stream` instead of a dedicated
+    /// data listener, and their payloads are compressed if the session allows
+    /// it and `compress` is set. Tunnels are throttled to
+    /// the lower of `max_bandwidth` and the limit of the policy. Ports which
+    /// the policy does not permit are refused. The `process` listening on the
+    /// container port is reported with the forward's statistics.
+    #[allow(clippy::too_many_arguments)]
     fn start_forward(
         &self,
         local_port: u16,
         container_port: u16,
         stream: Arc<Mutex<TcpStream>>,
+        tunnels: TunnelMode,
+        max_bandwidth: Option<u64>,
+        compress: bool,
+        process: Option<String>,
     ) -> Result<()> {
+        let policy = self.policy();
+        if !policy.permits_port(local_port) {
+            return Err(TunnelError::PortNotAllowed(local_port).into());
+        }
+
         let mut forwards = self.forwards.lock().unwrap();
 
         if forwards.contains_key(&local_port) {
-            bail!("Port {} is already being forwarded", local_port);
+            return Err(TunnelError::PortInUse(local_port).into());
+        }
+
+        let max_bandwidth = match (max_bandwidth, policy.max_bandwidth) {
+            (Some(port_limit), Some(global_limit)) => Some(port_limit.min(global_limit)),
+            (port_limit, global_limit) => port_limit.or(global_limit),
+        };
+        if let Some(limit) = max_bandwidth {
+            info!(
+                "Limiting tunnels of port {} to {} bytes/s",
+                local_port, limit
+            );
         }
 
         // Start the local listener for this port
@@ -79,15 +662,31 @@ impl PortForwardManager {
             local_port, container_port
         );
 
-        // Create dedicated data listener on random port for this forward
-        let data_listener = TcpListener::bind("0.0.0.0:0")
-            .context("Failed to bind data listener on random port")?;
-        let data_port = data_listener.local_addr()?.port();
+        // Create dedicated data listener on random port for this forward,
+        // unless the agent multiplexes tunnels over the control connection
+        let data_listener = if let TunnelMode::Dedicated(_) = tunnels {
+            let data_listener = TcpListener::bind("0.0.0.0:0")
+                .context("Failed to bind data listener on random port")?;
+            info!(
+                "Data listener for port {} started on 0.0.0.0:{}",
+                local_port,
+                data_listener.local_addr()?.port()
+            );
+            Some(data_listener)
+        } else {
+            debug!("Tunnels for port {} are multiplexed", local_port);
+            None
+        };
+        let data_port = match &data_listener {
+            Some(data_listener) => data_listener.local_addr()?.port(),
+            None => 0,
+        };
 
-        info!(
-            "Data listener for port {} started on 0.0.0.0:{}",
-            local_port, data_port
-        );
+        let metrics = Arc::new(ForwardMetrics::new(container_port).with_process(process));
+        self.metrics
+            .lock()
+            .unwrap()
+            .insert(local_port, metrics.clone());
 
         // Store the forward mapping
         let tunnel_id_counter = Arc::new(AtomicU32::new(1));
@@ -104,44 +703,66 @@ impl PortForwardManager {
         // Spawn dedicated data listener thread for this forward
         let pending_tunnels_data = self.pending_tunnels.clone();
         let forwards_clone_data = self.forwards.clone();
-        thread::spawn(move || {
-            for incoming_stream in data_listener.incoming() {
-                match incoming_stream {
-                    Ok(mut agent_stream) => {
-                        // Read tunnel_id from the stream
-                        let mut tunnel_id_buf = [0u8; 4];
-                        if let Err(e) = agent_stream.read_exact(&mut tunnel_id_buf) {
-                            error!("Failed to read tunnel_id from data connection: {}", e);
-                            continue;
-                        }
-                        let tunnel_id = u32::from_be_bytes(tunnel_id_buf);
+        let metrics_data = metrics.clone();
+        let tunnel_key = match &tunnels {
+            TunnelMode::Dedicated(key) => *key,
+            TunnelMode::Multiplexed(_) => None,
+        };
+        if let Some(data_listener) = data_listener {
+            thread::spawn(move || {
+                for incoming_stream in data_listener.incoming() {
+                    match incoming_stream {
+                        Ok(mut agent_stream) => {
+                            // Stop listening if the forward was stopped meanwhile
+                            if !forwards_clone_data
+                                .lock()
+                                .unwrap()
+                                .contains_key(&local_port)
+                            {
+                                break;
+                            }
 
-                        debug!(
-                            "Data listener received tunnel connection with tunnel_id={}",
-                            tunnel_id
-                        );
+                            // Read tunnel_id from the stream
+                            let mut tunnel_id_buf = [0u8; 4];
+                            if let Err(e) = agent_stream.read_exact(&mut tunnel_id_buf) {
+                                error!("Failed to read tunnel_id from data connection: {}", e);
+                                continue;
+                            }
+                            let tunnel_id = u32::from_be_bytes(tunnel_id_buf);
 
-                        let pending_clone = pending_tunnels_data.clone();
-                        thread::spawn(move || {
-                            if let Err(e) =
-                                handle_tunnel_connection(agent_stream, tunnel_id, pending_clone)
-                            {
-                                error!("Error handling tunnel connection: {}", e);
+                            debug!(
+                                "Data listener received tunnel connection with tunnel_id={}",
+                                tunnel_id
+                            );
+
+                            let pending_clone = pending_tunnels_data.clone();
+                            let metrics = metrics_data.clone();
+                            thread::spawn(move || {
+                                if let Err(e) = handle_tunnel_connection(
+                                    agent_stream,
+                                    tunnel_id,
+                                    pending_clone,
+                                    metrics,
+                                    max_bandwidth,
+                                    tunnel_key,
+                                ) {
+                                    error!("Error handling tunnel connection: {}", e);
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            error!("Error accepting data connection: {}", e);
+                            // Check if we should stop listening (forward was stopped)
+                            let forwards = forwards_clone_data.lock().unwrap();
+                            if !forwards.contains_key(&local_port) {
+                                break;
                             }
-                        });
-                    }
-                    Err(e) => {
-                        error!("Error accepting data connection: {}", e);
-                        // Check if we should stop listening (forward was stopped)
-                        let forwards = forwards_clone_data.lock().unwrap();
-                        if !forwards.contains_key(&local_port) {
-                            break;
                         }
                     }
                 }
-            }
-            debug!("Data listener thread for port {} exiting", local_port);
-        });
+                debug!("Data listener thread for port {} exiting", local_port);
+            });
+        }
 
         // Spawn thread to accept connections on the forwarded port
         let stream_clone = stream.clone();
@@ -152,7 +773,33 @@ impl PortForwardManager {
             for incoming_stream in listener.incoming() {
                 match incoming_stream {
                     Ok(client_stream) => {
+                        // Stop listening if the forward was stopped meanwhile
+                        if !forwards_clone.lock().unwrap().contains_key(&local_port) {
+                            break;
+                        }
+
                         let agent_stream = stream_clone.clone();
+                        metrics.connection_opened();
+
+                        if let TunnelMode::Multiplexed(session) = &tunnels {
+                            let session = session.clone();
+                            let metrics = metrics.clone();
+                            thread::spawn(move || {
+                                if let Err(e) = handle_multiplexed_connection(
+                                    client_stream,
+                                    agent_stream,
+                                    container_port,
+                                    session,
+                                    metrics,
+                                    max_bandwidth,
+                                    compress,
+                                ) {
+                                    error!("Error handling multiplexed connection: {}", e);
+                                }
+                            });
+                            continue;
+                        }
+
                         let tunnel_id = tunnel_id_counter.fetch_add(1, Ordering::SeqCst);
                         let pending_clone = pending_tunnels.clone();
 
@@ -163,6 +810,7 @@ impl PortForwardManager {
                         };
 
                         if let Some(data_port) = data_port {
+                            let metrics = metrics.clone();
                             thread::spawn(move || {
                                 if let Err(e) = handle_forwarded_connection(
                                     client_stream,
@@ -171,6 +819,7 @@ impl PortForwardManager {
                                     tunnel_id,
                                     pending_clone,
                                     data_port,
+                                    metrics,
                                 ) {
                                     error!("Error handling forwarded connection: {}", e);
                                 }
@@ -200,14 +849,23 @@ impl PortForwardManager {
     }
 
     /// Stop forwarding a port
+    ///
+    /// The listener threads of the forward are woken up with a dummy
+    /// connection so they notice the removal and release their ports.
     fn stop_forward(&self, local_port: u16) -> Result<()> {
         let mut forwards = self.forwards.lock().unwrap();
 
-        if forwards.remove(&local_port).is_some() {
+        if let Some((_, _, _, data_port)) = forwards.remove(&local_port) {
+            drop(forwards);
+            self.metrics.lock().unwrap().remove(&local_port);
+            let _ = TcpStream::connect(("127.0.0.1", local_port));
+            if data_port != 0 {
+                let _ = TcpStream::connect(("127.0.0.1", data_port));
+            }
             info!("Stopped forwarding port {}", local_port);
             Ok(())
         } else {
-            bail!("Port {} is not being forwarded", local_port);
+            Err(TunnelError::NotForwarded(local_port).into())
         }
     }
 }
@@ -219,8 +877,9 @@ fn handle_forwarded_connection(
     agent_stream: Arc<Mutex<TcpStream>>,
     container_port: u16,
     tunnel_id: u32,
-    pending_tunnels: Arc<Mutex<HashMap<u32, TcpStream>>>,
+    pending_tunnels: PendingTunnels,
     data_port: u16,
+    metrics: Arc<ForwardMetrics>,
 ) -> Result<()> {
     debug!(
         "Handling forwarded connection to container port {}, tunnel_id={}",
@@ -230,7 +889,7 @@ fn handle_forwarded_connection(
     // Store the client stream as pending
     {
         let mut pending = pending_tunnels.lock().unwrap();
-        pending.insert(tunnel_id, client_stream);
+        pending.insert(tunnel_id, (client_stream, Instant::now()));
         debug!(
             "Stored pending client for tunnel_id={}, total pending: {}",
             tunnel_id,
@@ -277,11 +936,108 @@ fn handle_forwarded_connection(
             // Remove from pending to clean up
             let mut pending = pending_tunnels.lock().unwrap();
             pending.remove(&tunnel_id);
-            bail!("Tunnel establishment timeout");
+            metrics.connection_closed();
+            return Err(TunnelError::Timeout.into());
         }
     }
 }
 
+/// Handle a forwarded connection from host to container over a multiplexed agent connection
+///
+/// Data read from the client is relayed to the agent as `TunnelData` messages,
+/// data from the agent is delivered through the `MuxSession`. Only the client
+/// side is throttled here, the agent throttles the container side.
+fn handle_multiplexed_connection(
+    client_stream: TcpStream,
+    agent_stream: Arc<Mutex<TcpStream>>,
+    container_port: u16,
+    session: Arc<MuxSession>,
+    metrics: Arc<ForwardMetrics>,
+    max_bandwidth: Option<u64>,
+    compress: bool,
+) -> Result<()> {
+    let tunnel_id = session.next_tunnel_id.fetch_add(1, Ordering::SeqCst);
+    debug!(
+        "Handling multiplexed connection to container port {}, tunnel_id={}",
+        container_port, tunnel_id
+    );
+
+    let window = session.open(tunnel_id, client_stream.try_clone()?, metrics.clone())?;
+
+    let result = relay_client_to_agent(
+        RateLimitedReader::new(client_stream, max_bandwidth),
+        &agent_stream,
+        container_port,
+        tunnel_id,
+        &window,
+        &metrics,
+        session.compress && compress,
+    );
+    session.windows.lock().unwrap().remove(&tunnel_id);
+    metrics.connection_closed();
+    result
+}
+
+/// Relay a multiplexed tunnel's client data to the agent until the client closes
+///
+/// Reading from the client pauses while `window` is used up.
+fn relay_client_to_agent(
+    client_stream: impl Read,
+    agent_stream: &Arc<Mutex<TcpStream>>,
+    container_port: u16,
+    tunnel_id: u32,
+    window: &SendWindow,
+    metrics: &ForwardMetrics,
+    compress: bool,
+) -> Result<()> {
+    let message = AgentMessage {
+        message: Some(ProtoMessage::TunnelRequest(devcon_proto::TunnelRequest {
+            port: container_port as u32,
+            tunnel_id,
+            data_port: 0,
+        })),
+    };
+    send_message(&mut agent_stream.lock().unwrap(), &message)?;
+
+    // Copy from client to agent in this thread
+    let mut client_read = client_stream;
+    let mut buf = vec![0u8; TUNNEL_CHUNK_SIZE];
+    while let Some(max) = window.wait(buf.len()) {
+        let n = match client_read.read(&mut buf[..max]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) => {
+                debug!(
+                    "Error reading from client of tunnel_id={}: {}",
+                    tunnel_id, e
+                );
+                break;
+            }
+        };
+
+        window.consume(n);
+        metrics.add_bytes_to_container(n as u64);
+        let message = AgentMessage {
+            message: Some(ProtoMessage::TunnelData(devcon_proto::TunnelData::new(
+                tunnel_id,
+                &buf[..n],
+                compress,
+            ))),
+        };
+        send_message(&mut agent_stream.lock().unwrap(), &message)?;
+    }
+
+    let message = AgentMessage {
+        message: Some(ProtoMessage::TunnelClose(devcon_proto::TunnelClose {
+            tunnel_id,
+        })),
+    };
+    send_message(&mut agent_stream.lock().unwrap(), &message)?;
+
+    debug!("Client side of tunnel_id={} closed", tunnel_id);
+    Ok(())
+}
+
 /// Send a protobuf message over a TCP stream with length prefix
 fn send_message(stream: &mut TcpStream, message: &AgentMessage) -> Result<()> {
     let mut buf = Vec::new();
@@ -311,7 +1067,7 @@ fn read_message(stream: &mut TcpStream) -> Result<AgentMessage> {
     match stream.read_exact(&mut len_buf) {
         Ok(_) => {}
         Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-            bail!("Connection closed while reading message length");
+            return Err(TunnelError::ConnectionClosed("message length".to_string()).into());
         }
         Err(e) => return Err(e.into()),
     }
@@ -320,10 +1076,14 @@ fn read_message(stream: &mut TcpStream) -> Result<AgentMessage> {
 
     // Validate message length to prevent excessive memory allocation
     if len == 0 {
-        bail!("Received zero-length message");
+        return Err(TunnelError::InvalidMessage("Received zero-length message".to_string()).into());
     }
     if len > 10 * 1024 * 1024 {
-        bail!("Message too large: {} bytes (max 10MB)", len);
+        return Err(TunnelError::InvalidMessage(format!(
+            "Message too large: {} bytes (max 10MB)",
+            len
+        ))
+        .into());
     }
 
     let mut buf = vec![0u8; len];
@@ -332,10 +1092,11 @@ fn read_message(stream: &mut TcpStream) -> Result<AgentMessage> {
     match stream.read_exact(&mut buf) {
         Ok(_) => {}
         Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-            bail!(
-                "Connection closed while reading message body (expected {} bytes)",
+            return Err(TunnelError::ConnectionClosed(format!(
+                "message body (expected {} bytes)",
                 len
-            );
+            ))
+            .into());
         }
         Err(e) => return Err(e.into()),
     }
@@ -345,13 +1106,27 @@ fn read_message(stream: &mut TcpStream) -> Result<AgentMessage> {
 }
 
 /// Handle a tunnel connection from agent (called by data listener)
+///
+/// With a `tunnel_key`, the agent must complete the encryption handshake
+/// before its connection is matched with the pending client.
 fn handle_tunnel_connection(
     agent_stream: TcpStream,
     tunnel_id: u32,
-    pending_tunnels: Arc<Mutex<HashMap<u32, TcpStream>>>,
+    pending_tunnels: PendingTunnels,
+    metrics: Arc<ForwardMetrics>,
+    max_bandwidth: Option<u64>,
+    tunnel_key: Option<TunnelKey>,
 ) -> Result<()> {
     debug!("Handling tunnel connection for tunnel_id={}", tunnel_id);
 
+    let encrypted = match tunnel_key {
+        Some(key) => Some(
+            noise::handshake(agent_stream.try_clone()?, &key, false)
+                .with_context(|| format!("Encryption handshake for tunnel_id={}", tunnel_id))?,
+        ),
+        None => None,
+    };
+
     // Get the pending client stream for this tunnel_id
     let client_stream = {
         let mut pending = pending_tunnels.lock().unwrap();
@@ -363,81 +1138,546 @@ fn handle_tunnel_connection(
         return Ok(());
     }
 
-    let client_stream = client_stream.unwrap();
+    let (client_stream, started) = client_stream.unwrap();
     info!(
         "Matched tunnel_id={} with pending client, starting bidirectional proxy",
         tunnel_id
     );
 
-    // Proxy data bidirectionally
-    let mut agent_read = agent_stream.try_clone()?;
-    let mut agent_write = agent_stream;
-    let mut client_read = client_stream.try_clone()?;
-    let mut client_write = client_stream;
-
-    // Spawn thread to copy from client to agent
-    let handle = thread::spawn(move || {
-        let result = std::io::copy(&mut client_read, &mut agent_write);
-        let _ = agent_write.shutdown(std::net::Shutdown::Write);
-        result
-    });
-
-    // Copy from agent to client in this thread
-    let result = std::io::copy(&mut agent_read, &mut client_write);
-    let _ = client_write.shutdown(std::net::Shutdown::Write);
+    // Proxy data bidirectionally, accounting transferred bytes
+    let client_read = RateLimitedReader::new(
+        CountingReader::new(
+            client_stream.try_clone()?,
+            metrics.clone(),
+            Direction::ToContainer,
+            None,
+        ),
+        max_bandwidth,
+    );
+    let counted = |reader: Box<dyn Read + Send>| {
+        CountingReader::new(
+            reader,
+            metrics.clone(),
+            Direction::FromContainer,
+            Some(started),
+        )
+    };
 
-    // Wait for the other direction to complete
-    let _ = handle.join();
+    let result = match encrypted {
+        Some((reader, writer)) => {
+            let agent_read = RateLimitedReader::new(counted(Box::new(reader)), max_bandwidth);
+            proxy(client_read, client_stream, agent_read, writer)
+        }
+        None => {
+            let reader = Box::new(agent_stream.try_clone()?);
+            let agent_read = RateLimitedReader::new(counted(reader), max_bandwidth);
+            proxy(client_read, client_stream, agent_read, agent_stream)
+        }
+    };
+    metrics.connection_closed();
 
     debug!("Tunnel closed for tunnel_id={}", tunnel_id);
     result.map(|_| ()).map_err(|e| e.into())
 }
 
-/// Handle a single agent connection
-fn handle_agent_connection(mut stream: TcpStream, manager: PortForwardManager) -> Result<()> {
-    let peer_addr = stream.peer_addr()?;
-    info!("New agent connection from {}", peer_addr);
+/// Connect an agent connection to a local port of the host
+///
+/// The connection is answered with a `HostPortConnected` message and then
+/// carries the raw stream of the host port until either side closes it.
+/// Encrypted connections run the Noise handshake with the workspace tunnel
+/// key after the reply.
+fn serve_host_port(
+    mut stream: TcpStream,
+    request: devcon_proto::ConnectHostPort,
+    workspace: &str,
+    policy: &ServerPolicy,
+) -> Result<()> {
+    let port = request.port as u16;
+    let connected = if port == 0 || !policy.permits_host_port(port) {
+        Err(anyhow::anyhow!(
+            "Host port {} is not exposed to containers (see forwardHostPorts)",
+            request.port
+        ))
+    } else if request.encrypted && workspace.is_empty() {
+        Err(anyhow::anyhow!(
+            "Encrypted connections require a workspace name"
+        ))
+    } else if !request.encrypted && !workspace.is_empty() && tunnel_key::is_encrypted(workspace) {
+        Err(TunnelError::EncryptionRequired(workspace.to_string()).into())
+    } else {
+        let address = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+        TcpStream::connect_timeout(&address, Duration::from_secs(5))
+            .with_context(|| format!("Failed to connect to host port {}", port))
+    };
 
-    let stream_arc = Arc::new(Mutex::new(stream.try_clone()?));
+    let reply = devcon_proto::HostPortConnected {
+        connected: connected.is_ok(),
+        reason: connected
+            .as_ref()
+            .err()
+            .map(|e| format!("{:#}", e))
+            .unwrap_or_default(),
+    };
+    send_message(
+        &mut stream,
+        &AgentMessage {
+            message: Some(ProtoMessage::HostPortConnected(reply)),
+        },
+    )?;
+    let host_stream = connected?;
+    info!("Connected agent to host port {}", port);
 
-    loop {
-        match read_message(&mut stream) {
-            Ok(message) => match message.message {
-                Some(ProtoMessage::StartPortForward(fwd)) => {
-                    let port = fwd.port as u16;
-                    info!("Agent requested port forward: {}", port);
+    let host_read = RateLimitedReader::new(host_stream.try_clone()?, policy.max_bandwidth);
+    let result = if request.encrypted {
+        let key = tunnel_key::workspace_key(workspace)?;
+        let (reader, writer) = noise::handshake(stream, &key, false)
+            .with_context(|| format!("Encryption handshake for host port {}", port))?;
+        let agent_read = RateLimitedReader::new(reader, policy.max_bandwidth);
+        proxy(agent_read, host_stream, host_read, writer)
+    } else {
+        let agent_read = RateLimitedReader::new(stream.try_clone()?, policy.max_bandwidth);
+        proxy(agent_read, host_stream, host_read, stream)
+    };
 
-                    if let Err(e) = manager.start_forward(port, port, stream_arc.clone()) {
-                        error!("Failed to start port forward: {}", e);
-                    }
-                }
-                Some(ProtoMessage::StopPortForward(fwd)) => {
-                    let port = fwd.port as u16;
-                    info!("Agent requested stop port forward: {}", port);
+    debug!("Connection to host port {} closed", port);
+    result.map(|_| ()).map_err(|e| e.into())
+}
 
-                    if let Err(e) = manager.stop_forward(port) {
-                        error!("Failed to stop port forward: {}", e);
-                    }
-                }
-                Some(ProtoMessage::OpenUrl(url_msg)) => {
-                    info!("Agent requested to open URL: {}", url_msg.url);
+/// Spawn a thread sending pings to the agent and closing the connection
+/// once no message was received within the heartbeat timeout
+///
+/// While the container of the agent is paused, no pings are sent and the
+/// silence of the agent is expected.
+fn spawn_heartbeat(
+    stream: Arc<Mutex<TcpStream>>,
+    last_seen: Arc<Mutex<Instant>>,
+    closed: Arc<AtomicBool>,
+    config: HeartbeatConfig,
+    manager: PortForwardManager,
+    peer: String,
+) {
+    thread::spawn(move || {
+        let mut nonce = 0u64;
+        loop {
+            thread::sleep(config.interval);
+            if closed.load(Ordering::SeqCst) {
+                break;
+            }
+            if manager.is_suspended(&peer) {
+                *last_seen.lock().unwrap() = Instant::now();
+                continue;
+            }
+
+            let silence = last_seen.lock().unwrap().elapsed();
+            if silence > config.timeout {
+                warn!(
+                    "No message from agent for {:?}, closing stale connection",
+                    silence
+                );
+                let _ = stream.lock().unwrap().shutdown(std::net::Shutdown::Both);
+                break;
+            }
+
+            nonce += 1;
+            let ping = AgentMessage {
+                message: Some(ProtoMessage::Ping(devcon_proto::Ping { nonce })),
+            };
+            if let Err(e) = send_message(&mut stream.lock().unwrap(), &ping) {
+                debug!("Failed to send ping to agent: {}", e);
+                break;
+            }
+        }
+    });
+}
+
+/// Handle a single agent connection
+///
+/// When the connection ends, all forwards requested over it are stopped.
+fn handle_agent_connection(
+    mut stream: TcpStream,
+    manager: PortForwardManager,
+    heartbeat: HeartbeatConfig,
+) -> Result<()> {
+    let peer_addr = stream.peer_addr()?;
+    info!("New agent connection from {}", peer_addr);
+
+    let peer = peer_addr.to_string();
+    manager.agents.lock().unwrap().insert(
+        peer.clone(),
+        AgentInfo {
+            peer: peer.clone(),
+            workspace: String::new(),
+            protocol_version: 1,
+            capabilities: Vec::new(),
+            connected_at: dashboard::now(),
+            ports: Vec::new(),
+            suspended: false,
+        },
+    );
+    manager.events.push(
+        EventKind::AgentConnected,
+        "",
+        format!("Agent {} connected", peer),
+    );
+
+    let stream_arc = Arc::new(Mutex::new(stream.try_clone()?));
+    let mut mux: Option<Arc<MuxSession>> = None;
+    let mut tunnel_key: Option<TunnelKey> = None;
+    let mut forwarded_ports: HashSet<u16> = HashSet::new();
+    let last_seen = Arc::new(Mutex::new(Instant::now()));
+    let closed = Arc::new(AtomicBool::new(false));
+
+    loop {
+        let message = read_message(&mut stream);
+        if message.is_ok() {
+            *last_seen.lock().unwrap() = Instant::now();
+        }
+
+        match message {
+            Ok(message) => match message.message {
+                Some(ProtoMessage::StartPortForward(fwd)) => {
+                    let port = fwd.port as u16;
+                    let process = (!fwd.process.is_empty()).then(|| fwd.process.clone());
+                    let label = port_label(port, process.as_deref());
+                    info!("Agent requested port forward: {}", label);
+
+                    let max_bandwidth = (fwd.max_bandwidth > 0).then_some(fwd.max_bandwidth);
+                    let workspace = manager
+                        .peer_workspace(peer_addr.ip())
+                        .unwrap_or_else(|| manager.agent_workspace(&peer));
+                    let started = if fwd.auto && !manager.policy().auto_forward.permits(port) {
+                        Err(TunnelError::NotAutoForwarded(port).into())
+                    } else if tunnel_key.is_none()
+                        && !workspace.is_empty()
+                        && tunnel_key::is_encrypted(&workspace)
+                    {
+                        Err(TunnelError::EncryptionRequired(workspace).into())
+                    } else {
+                        manager.start_forward(
+                            port,
+                            port,
+                            stream_arc.clone(),
+                            match &mux {
+                                Some(session) => TunnelMode::Multiplexed(session.clone()),
+                                None => TunnelMode::Dedicated(tunnel_key),
+                            },
+                            max_bandwidth,
+                            !fwd.disable_compression,
+                            process,
+                        )
+                    };
+                    match started {
+                        Ok(()) => {
+                            forwarded_ports.insert(port);
+                            manager.update_agent(&peer, |a| a.ports.push(port));
+                            manager.events.push(
+                                EventKind::ForwardStarted,
+                                manager.agent_workspace(&peer),
+                                format!(
+                                    "Forwarding port {} for {}",
+                                    label,
+                                    manager.agent_name(&peer)
+                                ),
+                            );
+                            if let Some(command) = manager.policy().port_forwarded_hook {
+                                hooks::spawn(
+                                    command,
+                                    Hook::PortForwarded,
+                                    HookContext {
+                                        workspace: manager.agent_workspace(&peer),
+                                        port: Some(port),
+                                        ..Default::default()
+                                    },
+                                );
+                            }
+                            manager.announce_forward(peer_addr, port, &label, fwd.action());
+                        }
+                        Err(e) => {
+                            error!("Failed to start port forward: {}", e);
+                            manager.events.push(
+                                EventKind::ForwardFailed,
+                                manager.agent_workspace(&peer),
+                                format!("Failed to forward port {}: {}", label, e),
+                            );
+                        }
+                    }
+                }
+                Some(ProtoMessage::StopPortForward(fwd)) => {
+                    let port = fwd.port as u16;
+                    info!("Agent requested stop port forward: {}", port);
+
+                    forwarded_ports.remove(&port);
+                    manager.update_agent(&peer, |a| a.ports.retain(|p| *p != port));
+                    if let Err(e) = manager.stop_forward(port) {
+                        error!("Failed to stop port forward: {}", e);
+                    } else {
+                        manager.events.push(
+                            EventKind::ForwardStopped,
+                            manager.agent_workspace(&peer),
+                            format!(
+                                "Stopped forwarding port {} for {}",
+                                port,
+                                manager.agent_name(&peer)
+                            ),
+                        );
+                    }
+                }
+                Some(ProtoMessage::OpenUrl(url_msg)) => {
+                    info!("Agent requested to open URL: {}", url_msg.url);
+                    manager.events.push(
+                        EventKind::UrlOpened,
+                        manager.agent_workspace(&peer),
+                        format!("{} opened {}", manager.agent_name(&peer), url_msg.url),
+                    );
                     if let Err(e) = open_url(&url_msg.url) {
                         error!("Failed to open URL: {}", e);
                     }
                 }
+                Some(ProtoMessage::Notify(notify)) => {
+                    let key = manager.notification_key(peer_addr.ip());
+                    // The workspace is only displayed, it doesn't select the limit
+                    let workspace = manager
+                        .peer_workspace(peer_addr.ip())
+                        .or_else(|| Some(manager.agent_workspace(&peer)).filter(|w| !w.is_empty()))
+                        .unwrap_or(notify.workspace);
+
+                    if !manager.policy().notifications {
+                        debug!(
+                            "Dropping notification from {}, notifications are disabled",
+                            key
+                        );
+                    } else if manager.notifications.allow(&key) {
+                        let title = if !notify.title.is_empty() {
+                            notify.title
+                        } else if !workspace.is_empty() {
+                            format!("DevCon - {}", workspace)
+                        } else {
+                            "DevCon".to_string()
+                        };
+                        manager.events.push(
+                            EventKind::Notification,
+                            workspace,
+                            format!("{}: {}", title, notify.body),
+                        );
+                        if let Err(e) = show_notification(&title, &notify.body) {
+                            error!("Failed to show notification: {}", e);
+                        }
+                    } else {
+                        warn!("Dropping notification from {}, rate limit exceeded", key);
+                    }
+                }
+                Some(ProtoMessage::OpenFile(request)) => {
+                    // Files are only opened from the workspace the host attributes
+                    // the agent to, never from the one it names
+                    let workspace = manager.peer_workspace(peer_addr.ip());
+                    let opened = match &workspace {
+                        Some(workspace) => {
+                            manager
+                                .policy()
+                                .open
+                                .open(workspace, &request.path, request.editor)
+                        }
+                        None => Err(anyhow::anyhow!(
+                            "{} is not the address of a devcon container",
+                            peer_addr.ip()
+                        )),
+                    };
+                    let workspace = workspace.unwrap_or_default();
+                    match opened {
+                        Ok(path) => manager.events.push(
+                            EventKind::FileOpened,
+                            workspace,
+                            format!("{} opened {}", manager.agent_name(&peer), path.display()),
+                        ),
+                        Err(e) => {
+                            warn!("Refusing to open {}: {:#}", request.path, e);
+                            manager.events.push(
+                                EventKind::FileRejected,
+                                workspace,
+                                format!("Refused to open {}: {:#}", request.path, e),
+                            );
+                        }
+                    }
+                }
+                Some(ProtoMessage::ConnectHostPort(request)) => {
+                    // The workspace selects the tunnel key, prefer the one the host
+                    // attributes the agent to over the ones the container names
+                    let workspace = manager
+                        .peer_workspace(peer_addr.ip())
+                        .or_else(|| Some(manager.agent_workspace(&peer)).filter(|w| !w.is_empty()))
+                        .unwrap_or_else(|| request.workspace.clone());
+                    let port = request.port;
+                    info!("Agent requested connection to host port {}", port);
+                    // The connection carries the host port's stream from now on
+                    match serve_host_port(
+                        stream.try_clone()?,
+                        request,
+                        &workspace,
+                        &manager.policy(),
+                    ) {
+                        Ok(()) => manager.events.push(
+                            EventKind::HostPortConnected,
+                            workspace,
+                            format!(
+                                "{} connected to host port {}",
+                                manager.agent_name(&peer),
+                                port
+                            ),
+                        ),
+                        Err(e) => {
+                            warn!("Refusing connection to host port {}: {:#}", port, e);
+                            manager.events.push(
+                                EventKind::HostPortRejected,
+                                workspace,
+                                format!("Refused connection to host port {}: {:#}", port, e),
+                            );
+                        }
+                    }
+                    break;
+                }
+                Some(ProtoMessage::HostPortConnected(_)) => {
+                    warn!(
+                        "Received unexpected HostPortConnected from agent (this should only go host->agent)"
+                    );
+                }
                 Some(ProtoMessage::TunnelRequest(_)) => {
                     warn!(
                         "Received unexpected TunnelRequest from agent (this should only go agent->host)"
                     );
                 }
+                Some(ProtoMessage::Hello(hello)) => {
+                    // Tunnel keys are per workspace, anonymous agents can't encrypt
+                    let key = if hello
+                        .capabilities()
+                        .any(|c| c == Capability::EncryptedTunnels)
+                        && !hello.workspace.is_empty()
+                    {
+                        tunnel_key::workspace_key(&hello.workspace)
+                            .inspect_err(|e| error!("Failed to load tunnel key: {:#}", e))
+                            .ok()
+                    } else {
+                        None
+                    };
+                    let compression = manager.policy().compression;
+                    let supported: Vec<Capability> = SUPPORTED_CAPABILITIES
+                        .iter()
+                        .copied()
+                        .filter(|c| compression || *c != Capability::Compression)
+                        .filter(|c| key.is_some() || *c != Capability::EncryptedTunnels)
+                        // Multiplexed tunnels would bypass the encryption
+                        .filter(|c| key.is_none() || *c != Capability::Multiplex)
+                        .collect();
+                    let ack = devcon_proto::negotiate(&hello, &supported);
+                    let accepted = ack.accepted;
+                    if ack
+                        .capabilities()
+                        .any(|c| c == Capability::EncryptedTunnels)
+                    {
+                        tunnel_key = key;
+                    }
+                    let multiplex = ack.capabilities().any(|c| c == Capability::Multiplex);
+                    let compression = ack.capabilities().any(|c| c == Capability::Compression);
+                    let heartbeat_enabled = ack.capabilities().any(|c| c == Capability::Heartbeat);
+                    let flow_control = ack.capabilities().any(|c| c == Capability::FlowControl);
+
+                    if accepted {
+                        info!(
+                            "Agent {} negotiated protocol version {} with capabilities {:?}",
+                            peer_addr,
+                            ack.protocol_version,
+                            ack.capabilities().collect::<Vec<_>>()
+                        );
+                        manager.update_agent(&peer, |a| {
+                            a.workspace = hello.workspace.clone();
+                            a.protocol_version = ack.protocol_version;
+                            a.capabilities =
+                                ack.capabilities().map(|c| format!("{:?}", c)).collect();
+                        });
+                        let message = if hello.workspace.is_empty() {
+                            format!("Agent {} is ready", peer)
+                        } else {
+                            format!("Agent {} belongs to {}", peer, hello.workspace)
+                        };
+                        manager.events.push(
+                            EventKind::AgentReady,
+                            hello.workspace.clone(),
+                            message,
+                        );
+                    } else {
+                        warn!("Rejecting agent {}: {}", peer_addr, ack.reason);
+                        manager.events.push(
+                            EventKind::AgentRejected,
+                            hello.workspace.clone(),
+                            format!("Rejected agent {}: {}", peer, ack.reason),
+                        );
+                    }
+
+                    send_message(
+                        &mut stream_arc.lock().unwrap(),
+                        &AgentMessage {
+                            message: Some(ProtoMessage::HelloAck(ack)),
+                        },
+                    )?;
+
+                    if !accepted {
+                        break;
+                    }
+                    if multiplex && mux.is_none() {
+                        mux = Some(Arc::new(MuxSession::new(
+                            compression,
+                            flow_control,
+                            stream_arc.clone(),
+                        )));
+                    }
+                    if heartbeat_enabled && !heartbeat.interval.is_zero() {
+                        spawn_heartbeat(
+                            stream_arc.clone(),
+                            last_seen.clone(),
+                            closed.clone(),
+                            heartbeat,
+                            manager.clone(),
+                            peer.clone(),
+                        );
+                    }
+                }
+                Some(ProtoMessage::Ping(ping)) => {
+                    let pong = AgentMessage {
+                        message: Some(ProtoMessage::Pong(devcon_proto::Pong { nonce: ping.nonce })),
+                    };
+                    send_message(&mut stream_arc.lock().unwrap(), &pong)?;
+                }
+                Some(ProtoMessage::Pong(pong)) => {
+                    debug!("Received pong {} from agent {}", pong.nonce, peer_addr);
+                }
+                Some(ProtoMessage::HelloAck(_)) => {
+                    warn!(
+                        "Received unexpected HelloAck from agent (this should only go host->agent)"
+                    );
+                }
+                Some(ProtoMessage::TunnelData(data)) => match &mux {
+                    Some(session) => session.deliver(&data),
+                    None => warn!("Received TunnelData on a non-multiplexed connection"),
+                },
+                Some(ProtoMessage::TunnelWindowUpdate(update)) => {
+                    if let Some(session) = &mux {
+                        session.grant(&update);
+                    }
+                }
+                Some(ProtoMessage::TunnelClose(close)) => {
+                    if let Some(session) = &mux {
+                        session.close(close.tunnel_id);
+                    }
+                }
                 None => {
                     warn!("Received message with no content");
                 }
             },
             Err(e) => {
                 let err_str = e.to_string();
-                if err_str.contains("Connection closed")
-                    || err_str.contains("UnexpectedEof")
+                if matches!(
+                    e.downcast_ref::<TunnelError>(),
+                    Some(TunnelError::ConnectionClosed(_))
+                ) || err_str.contains("UnexpectedEof")
                     || err_str.contains("connection reset")
                     || err_str.contains("Connection reset")
                 {
@@ -451,24 +1691,264 @@ fn handle_agent_connection(mut stream: TcpStream, manager: PortForwardManager) -
         }
     }
 
+    closed.store(true, Ordering::SeqCst);
+
+    manager.events.push(
+        EventKind::AgentDisconnected,
+        manager.agent_workspace(&peer),
+        format!("Agent {} disconnected", manager.agent_name(&peer)),
+    );
+    manager.agents.lock().unwrap().remove(&peer);
+
+    if let Some(session) = mux {
+        session.close_all();
+    }
+
+    // Tear down forwards of this agent, they can no longer be served
+    for port in forwarded_ports {
+        if let Err(e) = manager.stop_forward(port) {
+            debug!("Failed to stop port forward {}: {}", port, e);
+        }
+    }
+
     Ok(())
 }
 
-/// Start the control server on the specified port
-pub fn start_control_server(port: u16) -> Result<()> {
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+/// Serve forward statistics and the dashboard over HTTP on localhost
+///
+/// Exposes the dashboard on `/`, `/metrics` in the Prometheus text format and
+/// `/api/ports`, `/api/agents`, `/api/containers` and `/api/events` as JSON.
+/// `POST /api/workspaces/<workspace>/suspend` and `.../resume` mark the
+/// forwards of a paused workspace as suspended.
+fn start_metrics_server(port: u16, manager: PortForwardManager) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .context(format!("Failed to bind metrics endpoint to port {}", port))?;
+
+    info!(
+        "Metrics endpoint listening on http://127.0.0.1:{}/metrics",
+        port
+    );
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle_http_request(stream, &manager) {
+                        debug!("Error handling metrics request: {}", e);
+                    }
+                }
+                Err(e) => {
+                    error!("Error accepting metrics connection: {}", e);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Answer a single HTTP request on the metrics endpoint
+fn handle_http_request(mut stream: TcpStream, manager: &PortForwardManager) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Skip the request headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    let mut request = request_line.split_whitespace();
+    let method = request.next().unwrap_or("GET");
+    let path = request
+        .next()
+        .and_then(|target| target.split('?').next())
+        .unwrap_or("/");
+    debug!("Metrics endpoint request for {} {}", method, path);
+
+    let (status, content_type, body) = match path {
+        "/" => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            render_dashboard(
+                &manager.agents(),
+                &manager.stats(),
+                &(manager.policy().container_usage)(),
+                &manager.events.recent(),
+            )?,
+        ),
+        "/metrics" => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            render_prometheus(&manager.stats()),
+        ),
+        "/api/ports" => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&manager.stats())?,
+        ),
+        "/api/agents" => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&manager.agents())?,
+        ),
+        "/api/containers" => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&(manager.policy().container_usage)())?,
+        ),
+        "/api/events" => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&manager.events.recent())?,
+        ),
+        _ if path.starts_with("/api/workspaces/") => workspace_action(manager, method, path),
+        _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Suspend or resume the forwards of a workspace on
+/// `POST /api/workspaces/<workspace>/suspend` and `.../resume`
+fn workspace_action(
+    manager: &PortForwardManager,
+    method: &str,
+    path: &str,
+) -> (&'static str, &'static str, String) {
+    let not_found = ("404 Not Found", "text/plain", "Not Found\n".to_string());
+    let Some((workspace, action)) = path
+        .strip_prefix("/api/workspaces/")
+        .and_then(|rest| rest.rsplit_once('/'))
+        .filter(|(workspace, _)| !workspace.is_empty())
+    else {
+        return not_found;
+    };
+    let suspended = match action {
+        "suspend" => true,
+        "resume" => false,
+        _ => return not_found,
+    };
+    if method != "POST" {
+        return (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method Not Allowed\n".to_string(),
+        );
+    }
+
+    let agents = manager.set_suspended(workspace, suspended);
+    let body = serde_json::json!({
+        "workspace": workspace,
+        "suspended": suspended,
+        "agents": agents,
+    });
+    ("200 OK", "application/json", body.to_string())
+}
+
+/// Binds the agent listener on loopback behind a gRPC server on `port`
+#[cfg(feature = "grpc")]
+fn bind_grpc(port: u16) -> Result<TcpListener> {
+    let listener =
+        TcpListener::bind("127.0.0.1:0").context("Failed to bind gRPC session listener")?;
+    devcon_proto::grpc::spawn_server(([0, 0, 0, 0], port).into(), listener.local_addr()?)
         .context(format!("Failed to bind to port {}", port))?;
 
+    info!("gRPC control server listening on 0.0.0.0:{}", port);
+    Ok(listener)
+}
+
+#[cfg(not(feature = "grpc"))]
+fn bind_grpc(_port: u16) -> Result<TcpListener> {
+    anyhow::bail!("The gRPC control scheme requires devcon to be built with the 'grpc' feature")
+}
+
+/// Reload the policy with `reload` whenever the process receives `SIGHUP`
+///
+/// If reloading fails, e.g. because of an invalid config, the previous policy
+/// stays in effect.
+#[cfg(unix)]
+fn spawn_reload_handler(
+    manager: PortForwardManager,
+    reload: impl Fn() -> Result<ServerPolicy> + Send + 'static,
+) -> Result<()> {
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])
+        .context("Failed to register SIGHUP handler")?;
+
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            info!("Received SIGHUP, reloading configuration");
+            match reload() {
+                Ok(policy) => manager.reload(policy),
+                Err(e) => error!(
+                    "Failed to reload configuration, keeping the previous one: {:#}",
+                    e
+                ),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn spawn_reload_handler(
+    _manager: PortForwardManager,
+    _reload: impl Fn() -> Result<ServerPolicy> + Send + 'static,
+) -> Result<()> {
+    debug!("Configuration reload is not supported on this platform");
+    Ok(())
+}
+
+/// Bind the listener of the control server on the specified port
+///
+/// If `grpc` is set, agents connect through the gRPC control plane instead of
+/// raw TCP, and the returned listener accepts its bridged sessions.
+pub fn bind_control_server(port: u16, grpc: bool) -> Result<TcpListener> {
+    if grpc {
+        return bind_grpc(port);
+    }
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+        .context(format!("Failed to bind to port {}", port))?;
     info!("Control server listening on 0.0.0.0:{}", port);
+    Ok(listener)
+}
 
-    let manager = PortForwardManager::new();
+/// Start the control server on a listener of [`bind_control_server`]
+///
+/// If `metrics_port` is set, forward statistics are served over HTTP on that
+/// port of localhost. Forwards and agent requests are subject to `policy`,
+/// which is replaced by the result of `reload` on `SIGHUP`.
+pub fn start_control_server(
+    listener: TcpListener,
+    heartbeat: HeartbeatConfig,
+    metrics_port: Option<u16>,
+    policy: ServerPolicy,
+    reload: impl Fn() -> Result<ServerPolicy> + Send + 'static,
+) -> Result<()> {
+    let manager = PortForwardManager::new(policy);
+    spawn_reload_handler(manager.clone(), reload)?;
+
+    if let Some(metrics_port) = metrics_port {
+        start_metrics_server(metrics_port, manager.clone())?;
+    }
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
                 let manager_clone = manager.clone();
                 thread::spawn(move || {
-                    if let Err(e) = handle_agent_connection(stream, manager_clone) {
+                    if let Err(e) = handle_agent_connection(stream, manager_clone, heartbeat) {
                         error!("Error handling connection: {}", e);
                     }
                 });
@@ -481,3 +1961,890 @@ pub fn start_control_server(port: u16) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod harness;
+
+#[cfg(test)]
+mod tests {
+    use super::harness::{FixtureServer, connect_client, is_released, wait_until};
+    use super::*;
+
+    fn free_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    fn send(stream: &mut TcpStream, message: ProtoMessage) {
+        send_message(
+            stream,
+            &AgentMessage {
+                message: Some(message),
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stale_agent_forwards_are_torn_down() {
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(
+                stream,
+                PortForwardManager::new(ServerPolicy::default()),
+                HeartbeatConfig {
+                    interval: Duration::from_millis(50),
+                    timeout: Duration::from_millis(200),
+                },
+            );
+        });
+
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let port = free_port();
+        send(
+            &mut agent,
+            ProtoMessage::Hello(devcon_proto::Hello {
+                protocol_version: devcon_proto::PROTOCOL_VERSION,
+                min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+                capabilities: vec![Capability::Heartbeat as i32],
+                workspace: String::new(),
+            }),
+        );
+        send(
+            &mut agent,
+            ProtoMessage::StartPortForward(devcon_proto::StartPortForward {
+                port: port as u32,
+                max_bandwidth: 0,
+                disable_compression: false,
+                ..Default::default()
+            }),
+        );
+
+        // The agent never answers pings, so the host must drop the connection
+        let mut pings = 0;
+        while let Ok(message) = read_message(&mut agent) {
+            if let Some(ProtoMessage::Ping(_)) = message.message {
+                pings += 1;
+            }
+        }
+        assert!(pings > 0);
+
+        // ...and release the forwarded port
+        let released = (0..50).any(|_| {
+            TcpListener::bind(("0.0.0.0", port)).is_ok() || {
+                thread::sleep(Duration::from_millis(20));
+                false
+            }
+        });
+        assert!(released, "forwarded port {} was not released", port);
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        let manager = PortForwardManager::new(ServerPolicy::default());
+        let metrics = Arc::new(ForwardMetrics::new(3000));
+        metrics.add_bytes_from_container(128);
+        manager.metrics.lock().unwrap().insert(8080, metrics);
+
+        let port = free_port();
+        start_metrics_server(port, manager).unwrap();
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("direction=\"from_container\"} 128"));
+
+        let response = get("/api/ports");
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let stats: Vec<ForwardStats> = serde_json::from_str(body).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].local_port, 8080);
+        assert_eq!(stats[0].bytes_from_container, 128);
+
+        assert!(get("/unknown").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_dashboard_tracks_agents() {
+        let manager = PortForwardManager::new(ServerPolicy::default());
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        let manager_clone = manager.clone();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(stream, manager_clone, HeartbeatConfig::default());
+        });
+
+        let port = free_port();
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        send(
+            &mut agent,
+            ProtoMessage::Hello(devcon_proto::Hello {
+                protocol_version: devcon_proto::PROTOCOL_VERSION,
+                min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+                capabilities: vec![Capability::Multiplex as i32],
+                workspace: "my-project".to_string(),
+            }),
+        );
+        read_message(&mut agent).unwrap();
+        send(
+            &mut agent,
+            ProtoMessage::StartPortForward(devcon_proto::StartPortForward {
+                port: port as u32,
+                max_bandwidth: 0,
+                disable_compression: false,
+                process: "vite".to_string(),
+                pid: 42,
+                auto: true,
+                action: AutoForwardAction::None as i32,
+            }),
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.agents().first().is_none_or(|a| a.ports.is_empty()) {
+            assert!(Instant::now() < deadline, "forward was not registered");
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let agents = manager.agents();
+        assert_eq!(agents[0].workspace, "my-project");
+        assert_eq!(agents[0].ports, vec![port]);
+
+        let html =
+            render_dashboard(&agents, &manager.stats(), &[], &manager.events.recent()).unwrap();
+        assert!(html.contains(&format!("http://localhost:{}", port)));
+        assert!(html.contains(&format!("Forwarding port {} (vite)", port)));
+        assert_eq!(manager.stats()[0].process.as_deref(), Some("vite"));
+
+        drop(agent);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !manager.agents().is_empty() {
+            assert!(Instant::now() < deadline, "agent was not removed");
+            thread::sleep(Duration::from_millis(20));
+        }
+        let events = manager.events.recent();
+        assert!(events[0].message.contains("disconnected"));
+        assert_eq!(events[0].kind, EventKind::AgentDisconnected);
+        assert_eq!(events[0].workspace, "my-project");
+    }
+
+    #[test]
+    fn test_incompatible_agent_is_rejected() {
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(
+                stream,
+                PortForwardManager::new(ServerPolicy::default()),
+                HeartbeatConfig::default(),
+            );
+        });
+
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        send(
+            &mut agent,
+            ProtoMessage::Hello(devcon_proto::Hello {
+                protocol_version: devcon_proto::PROTOCOL_VERSION + 2,
+                min_protocol_version: devcon_proto::PROTOCOL_VERSION + 1,
+                capabilities: vec![],
+                workspace: String::new(),
+            }),
+        );
+
+        match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::HelloAck(ack)) => assert!(!ack.accepted),
+            other => panic!("Expected HelloAck, got {:?}", other),
+        }
+        assert!(read_message(&mut agent).is_err());
+    }
+
+    #[cfg(feature = "grpc")]
+    #[test]
+    fn test_grpc_control_plane() {
+        let port = free_port();
+        thread::spawn(move || {
+            let _ = start_control_server(
+                bind_control_server(port, true).unwrap(),
+                HeartbeatConfig::default(),
+                None,
+                ServerPolicy::default(),
+                || Ok(ServerPolicy::default()),
+            );
+        });
+        let bridge = devcon_proto::grpc::spawn_client(
+            &format!("http://127.0.0.1:{}", port),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+
+        // The gRPC server may not be up yet, the bridge then drops the connection
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let ack = loop {
+            assert!(
+                Instant::now() < deadline,
+                "gRPC control plane did not answer"
+            );
+            let mut agent = TcpStream::connect(bridge).unwrap();
+            let hello = AgentMessage {
+                message: Some(ProtoMessage::Hello(devcon_proto::Hello {
+                    protocol_version: devcon_proto::PROTOCOL_VERSION,
+                    min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+                    capabilities: vec![Capability::Multiplex as i32],
+                    workspace: "my-project".to_string(),
+                })),
+            };
+            let _ = send_message(&mut agent, &hello);
+            match read_message(&mut agent).map(|m| m.message) {
+                Ok(Some(ProtoMessage::HelloAck(ack))) => break ack,
+                _ => thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        assert!(ack.accepted);
+        assert_eq!(ack.capabilities, vec![Capability::Multiplex as i32]);
+    }
+
+    #[test]
+    fn test_auto_forward_policy_is_enforced() {
+        let manager = PortForwardManager::new(ServerPolicy {
+            auto_forward: AutoForwardPolicy {
+                mode: devcon_proto::auto_forward::AutoForward::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        let manager_clone = manager.clone();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(stream, manager_clone, HeartbeatConfig::default());
+        });
+
+        let (detected, requested) = (free_port(), free_port());
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        for (port, auto) in [(detected, true), (requested, false)] {
+            send(
+                &mut agent,
+                ProtoMessage::StartPortForward(devcon_proto::StartPortForward {
+                    port: port as u32,
+                    auto,
+                    ..Default::default()
+                }),
+            );
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while manager.stats().is_empty() {
+            assert!(Instant::now() < deadline, "forward was not registered");
+            thread::sleep(Duration::from_millis(20));
+        }
+        let ports: Vec<u16> = manager.stats().iter().map(|s| s.local_port).collect();
+        assert_eq!(ports, vec![requested]);
+        assert!(
+            manager
+                .events
+                .recent()
+                .iter()
+                .any(|e| e.kind == EventKind::ForwardFailed && e.message.contains("auto-forward"))
+        );
+        manager.stop_forward(requested).unwrap();
+    }
+
+    #[test]
+    fn test_connect_host_port() {
+        // Host service echoing a greeting
+        let service = TcpListener::bind("127.0.0.1:0").unwrap();
+        let service_port = service.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = service.accept().unwrap();
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        let manager = PortForwardManager::new(ServerPolicy {
+            host_ports: vec![service_port..=service_port],
+            ..Default::default()
+        });
+        thread::spawn(move || {
+            for stream in control.incoming().take(2) {
+                let _ = handle_agent_connection(
+                    stream.unwrap(),
+                    manager.clone(),
+                    HeartbeatConfig::default(),
+                );
+            }
+        });
+
+        let connect = |port: u16| {
+            let mut agent = TcpStream::connect(control_addr).unwrap();
+            agent
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            send(
+                &mut agent,
+                ProtoMessage::ConnectHostPort(devcon_proto::ConnectHostPort {
+                    port: port as u32,
+                    workspace: String::new(),
+                    encrypted: false,
+                }),
+            );
+            match read_message(&mut agent).unwrap().message {
+                Some(ProtoMessage::HostPortConnected(reply)) => (agent, reply),
+                other => panic!("Expected HostPortConnected, got {:?}", other),
+            }
+        };
+
+        // Ports not listed in the policy are refused
+        let (_, reply) = connect(free_port());
+        assert!(!reply.connected);
+        assert!(reply.reason.contains("not exposed"), "{}", reply.reason);
+
+        let (mut agent, reply) = connect(service_port);
+        assert!(reply.connected, "{}", reply.reason);
+        agent.write_all(b"ping").unwrap();
+        let mut buf = [0u8; 4];
+        agent.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[test]
+    fn test_open_file_uses_workspace_of_peer() {
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        let manager = PortForwardManager::new(ServerPolicy {
+            peer_workspace: Arc::new(|peer: IpAddr| {
+                peer.is_loopback().then(|| "project".to_string())
+            }),
+            ..Default::default()
+        });
+        let server = manager.clone();
+        thread::spawn(move || {
+            for stream in control.incoming() {
+                let _ = handle_agent_connection(
+                    stream.unwrap(),
+                    server.clone(),
+                    HeartbeatConfig::default(),
+                );
+            }
+        });
+
+        // One-shot request naming another workspace without a handshake
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        send(
+            &mut agent,
+            ProtoMessage::OpenFile(devcon_proto::OpenFile {
+                path: "/workspaces/other/missing.txt".to_string(),
+                workspace: "other".to_string(),
+                editor: false,
+            }),
+        );
+        drop(agent);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let rejected = loop {
+            let events = manager.events.recent();
+            if let Some(event) = events.iter().find(|e| e.kind == EventKind::FileRejected) {
+                break event.clone();
+            }
+            assert!(Instant::now() < deadline, "request was not handled");
+            thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(rejected.workspace, "project");
+    }
+
+    #[test]
+    fn test_notification_key_is_attributed_by_host() {
+        let peer: IpAddr = "172.17.0.2".parse().unwrap();
+        let manager = PortForwardManager::new(ServerPolicy::default());
+        assert_eq!(manager.notification_key(peer), "172.17.0.2");
+
+        let manager = PortForwardManager::new(ServerPolicy {
+            peer_workspace: Arc::new(|_| Some("project".to_string())),
+            ..Default::default()
+        });
+        assert_eq!(manager.notification_key(peer), "project");
+    }
+
+    #[test]
+    fn test_multiplexed_tunnel_roundtrip() {
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(
+                stream,
+                PortForwardManager::new(ServerPolicy::default()),
+                HeartbeatConfig::default(),
+            );
+        });
+
+        // Fake agent negotiating multiplexing and requesting a forward
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let port = free_port();
+        send(
+            &mut agent,
+            ProtoMessage::Hello(devcon_proto::Hello {
+                protocol_version: devcon_proto::PROTOCOL_VERSION,
+                min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+                capabilities: vec![Capability::Multiplex as i32, Capability::Udp as i32],
+                workspace: String::new(),
+            }),
+        );
+        match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::HelloAck(ack)) => {
+                assert!(ack.accepted);
+                assert_eq!(
+                    ack.capabilities().collect::<Vec<_>>(),
+                    vec![Capability::Multiplex]
+                );
+            }
+            other => panic!("Expected HelloAck, got {:?}", other),
+        }
+        send(
+            &mut agent,
+            ProtoMessage::StartPortForward(devcon_proto::StartPortForward {
+                port: port as u32,
+                max_bandwidth: 0,
+                disable_compression: false,
+                ..Default::default()
+            }),
+        );
+
+        // Connect a client to the forwarded port, retrying until the listener is up
+        let mut client = (0..50)
+            .find_map(|_| {
+                TcpStream::connect(("127.0.0.1", port)).ok().or_else(|| {
+                    thread::sleep(Duration::from_millis(20));
+                    None
+                })
+            })
+            .expect("forwarded port never opened");
+        client.write_all(b"ping").unwrap();
+
+        let tunnel_id = match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::TunnelRequest(req)) => {
+                assert_eq!(req.data_port, 0);
+                assert_eq!(req.port, port as u32);
+                req.tunnel_id
+            }
+            other => panic!("Expected TunnelRequest, got {:?}", other),
+        };
+
+        match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::TunnelData(data)) => {
+                assert_eq!(data.tunnel_id, tunnel_id);
+                assert_eq!(data.data, b"ping");
+            }
+            other => panic!("Expected TunnelData, got {:?}", other),
+        }
+
+        send(
+            &mut agent,
+            ProtoMessage::TunnelData(devcon_proto::TunnelData::new(tunnel_id, b"pong", false)),
+        );
+        send(
+            &mut agent,
+            ProtoMessage::TunnelClose(devcon_proto::TunnelClose { tunnel_id }),
+        );
+
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, b"pong");
+    }
+
+    #[test]
+    fn test_compressed_multiplexed_tunnel() {
+        let control = TcpListener::bind("127.0.0.1:0").unwrap();
+        let control_addr = control.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = control.accept().unwrap();
+            let _ = handle_agent_connection(
+                stream,
+                PortForwardManager::new(ServerPolicy::default()),
+                HeartbeatConfig::default(),
+            );
+        });
+
+        let mut agent = TcpStream::connect(control_addr).unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let port = free_port();
+        send(
+            &mut agent,
+            ProtoMessage::Hello(devcon_proto::Hello {
+                protocol_version: devcon_proto::PROTOCOL_VERSION,
+                min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+                capabilities: vec![Capability::Multiplex as i32, Capability::Compression as i32],
+                workspace: String::new(),
+            }),
+        );
+        match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::HelloAck(ack)) => assert_eq!(
+                ack.capabilities().collect::<Vec<_>>(),
+                vec![Capability::Multiplex, Capability::Compression]
+            ),
+            other => panic!("Expected HelloAck, got {:?}", other),
+        }
+        send(
+            &mut agent,
+            ProtoMessage::StartPortForward(devcon_proto::StartPortForward {
+                port: port as u32,
+                max_bandwidth: 0,
+                disable_compression: false,
+                ..Default::default()
+            }),
+        );
+
+        let mut client = (0..50)
+            .find_map(|_| {
+                TcpStream::connect(("127.0.0.1", port)).ok().or_else(|| {
+                    thread::sleep(Duration::from_millis(20));
+                    None
+                })
+            })
+            .expect("forwarded port never opened");
+        let request = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".repeat(16);
+        client.write_all(&request).unwrap();
+
+        let tunnel_id = match read_message(&mut agent).unwrap().message {
+            Some(ProtoMessage::TunnelRequest(req)) => req.tunnel_id,
+            other => panic!("Expected TunnelRequest, got {:?}", other),
+        };
+
+        let mut received = Vec::new();
+        let mut compressed = false;
+        while received.len() < request.len() {
+            match read_message(&mut agent).unwrap().message {
+                Some(ProtoMessage::TunnelData(data)) => {
+                    compressed |= data.compression() == devcon_proto::Compression::Lz4;
+                    received.extend(data.payload().unwrap());
+                }
+                other => panic!("Expected TunnelData, got {:?}", other),
+            }
+        }
+        assert_eq!(received, request);
+        assert!(compressed);
+
+        let response = b"HTTP/1.1 200 OK\r\n\r\n".repeat(32);
+        send(
+            &mut agent,
+            ProtoMessage::TunnelData(devcon_proto::TunnelData::new(tunnel_id, &response, true)),
+        );
+        send(
+            &mut agent,
+            ProtoMessage::TunnelClose(devcon_proto::TunnelClose { tunnel_id }),
+        );
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(received, response);
+    }
+
+    #[test]
+    fn test_suspended_workspace_keeps_forwards() {
+        let manager = PortForwardManager::new(ServerPolicy::default());
+        manager.agents.lock().unwrap().insert(
+            "127.0.0.1:50000".to_string(),
+            AgentInfo {
+                peer: "127.0.0.1:50000".to_string(),
+                workspace: "my-project".to_string(),
+                protocol_version: devcon_proto::PROTOCOL_VERSION,
+                capabilities: Vec::new(),
+                connected_at: dashboard::now(),
+                ports: vec![3000],
+                suspended: false,
+            },
+        );
+        manager
+            .metrics
+            .lock()
+            .unwrap()
+            .insert(3000, Arc::new(ForwardMetrics::new(3000)));
+
+        let (status, _, body) =
+            workspace_action(&manager, "POST", "/api/workspaces/my-project/suspend");
+        assert_eq!(status, "200 OK");
+        assert!(body.contains(r#""agents":1"#));
+        assert!(manager.is_suspended("127.0.0.1:50000"));
+        assert!(manager.stats()[0].suspended);
+        assert_eq!(
+            manager.events.recent()[0].kind,
+            EventKind::WorkspaceSuspended
+        );
+
+        workspace_action(&manager, "POST", "/api/workspaces/my-project/resume");
+        assert!(!manager.is_suspended("127.0.0.1:50000"));
+        assert!(!manager.stats()[0].suspended);
+
+        let (status, _, _) = workspace_action(&manager, "GET", "/api/workspaces/my-project/resume");
+        assert_eq!(status, "405 Method Not Allowed");
+        let (status, _, _) = workspace_action(&manager, "POST", "/api/workspaces/my-project/stop");
+        assert_eq!(status, "404 Not Found");
+    }
+
+    #[test]
+    fn test_server_policy_permits_port() {
+        let policy = ServerPolicy::default();
+        assert!(policy.permits_port(22));
+
+        let policy = ServerPolicy {
+            allowed_ports: vec![3000..=3999, 8080..=8080],
+            denied_ports: vec![3306..=3306],
+            ..Default::default()
+        };
+        assert!(policy.permits_port(3000));
+        assert!(policy.permits_port(8080));
+        assert!(!policy.permits_port(3306));
+        assert!(!policy.permits_port(22));
+        assert!(!policy.permits_host_port(5432));
+
+        let policy = ServerPolicy {
+            host_ports: vec![5432..=5432],
+            ..Default::default()
+        };
+        assert!(policy.permits_host_port(5432));
+        assert!(!policy.permits_host_port(5433));
+    }
+
+    #[test]
+    fn test_reload_applies_to_new_forwards() {
+        let port = free_port();
+        let manager = PortForwardManager::new(ServerPolicy {
+            denied_ports: vec![port..=port],
+            ..Default::default()
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let agent = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let stream = Arc::new(Mutex::new(agent));
+        let forward = || {
+            manager.start_forward(
+                port,
+                port,
+                stream.clone(),
+                TunnelMode::Dedicated(None),
+                None,
+                true,
+                None,
+            )
+        };
+
+        let error = forward().unwrap_err();
+        assert!(error.to_string().contains("not allowed"), "{}", error);
+
+        manager.reload(ServerPolicy::default());
+        forward().unwrap();
+        manager.stop_forward(port).unwrap();
+
+        assert!(
+            manager
+                .events
+                .recent()
+                .iter()
+                .any(|e| e.kind == EventKind::ConfigReloaded)
+        );
+    }
+
+    #[test]
+    fn test_dedicated_tunnel_flow() {
+        let server = FixtureServer::start(ServerPolicy::default());
+        let mut agent = server.agent();
+        assert!(agent.hello(&[], "my-project").accepted);
+
+        let port = free_port();
+        agent.start_forward(port);
+        let mut client = connect_client(port);
+        client.write_all(b"ping").unwrap();
+
+        // The agent dials the data listener named in the tunnel request
+        let request = agent.expect_tunnel_request();
+        assert_eq!(request.port, port as u32);
+        let mut tunnel = agent.dial_tunnel(&request);
+        let mut received = [0u8; 4];
+        tunnel.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"ping");
+
+        tunnel.write_all(b"pong").unwrap();
+        drop(tunnel);
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, b"pong");
+        drop(client);
+
+        server.wait_for("the tunnel to close", |m| {
+            m.stats()
+                .first()
+                .is_some_and(|s| s.active_connections == 0 && s.bytes_from_container == 4)
+        });
+        assert_eq!(server.manager.stats()[0].bytes_to_container, 4);
+
+        // Stopping the forward releases the forwarded port and the data listener
+        agent.stop_forward(port);
+        server.wait_for("the forward to stop", |m| {
+            m.stats().is_empty() && m.agents()[0].ports.is_empty()
+        });
+        wait_until("the ports to be released", || {
+            is_released(port) && is_released(request.data_port as u16)
+        });
+        let events = server.manager.events.recent();
+        assert_eq!(events[0].kind, EventKind::ForwardStopped);
+        assert_eq!(events[0].workspace, "my-project");
+    }
+
+    #[test]
+    fn test_multiplexed_tunnel_flow() {
+        let server = FixtureServer::start(ServerPolicy::default());
+        let mut agent = server.agent();
+        assert!(agent.hello(&[Capability::Multiplex], "").accepted);
+
+        let port = free_port();
+        agent.start_forward(port);
+        let mut client = connect_client(port);
+        client.write_all(b"ping").unwrap();
+
+        let request = agent.expect_tunnel_request();
+        assert_eq!(request.data_port, 0);
+        match agent.recv() {
+            ProtoMessage::TunnelData(data) => {
+                assert_eq!(data.tunnel_id, request.tunnel_id);
+                assert_eq!(data.data, b"ping");
+            }
+            other => panic!("Expected TunnelData, got {:?}", other),
+        }
+
+        agent.send(ProtoMessage::TunnelData(devcon_proto::TunnelData::new(
+            request.tunnel_id,
+            b"pong",
+            false,
+        )));
+        agent.send(ProtoMessage::TunnelClose(devcon_proto::TunnelClose {
+            tunnel_id: request.tunnel_id,
+        }));
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, b"pong");
+
+        // Closing the client closes the tunnel on the agent too
+        drop(client);
+        match agent.recv() {
+            ProtoMessage::TunnelClose(close) => assert_eq!(close.tunnel_id, request.tunnel_id),
+            other => panic!("Expected TunnelClose, got {:?}", other),
+        }
+
+        agent.stop_forward(port);
+        server.wait_for("the forward to stop", |m| m.stats().is_empty());
+        wait_until("the port to be released", || is_released(port));
+    }
+
+    #[test]
+    fn test_multiplexed_tunnel_flow_control() {
+        use devcon_proto::flow_control::{TUNNEL_WINDOW, WINDOW_UPDATE_THRESHOLD};
+
+        let server = FixtureServer::start(ServerPolicy::default());
+        let mut agent = server.agent();
+        let ack = agent.hello(&[Capability::Multiplex, Capability::FlowControl], "");
+        assert_eq!(
+            ack.capabilities().collect::<Vec<_>>(),
+            vec![Capability::Multiplex, Capability::FlowControl]
+        );
+
+        let port = free_port();
+        agent.start_forward(port);
+        let mut client = connect_client(port);
+        let request = agent.expect_tunnel_request();
+
+        // The host pauses the tunnel once the window is used up
+        client
+            .write_all(&vec![0u8; TUNNEL_WINDOW as usize + 1024])
+            .unwrap();
+        let mut received = 0;
+        while received < TUNNEL_WINDOW as usize {
+            match agent.recv() {
+                ProtoMessage::TunnelData(data) => received += data.data.len(),
+                other => panic!("Expected TunnelData, got {:?}", other),
+            }
+        }
+        assert_eq!(received, TUNNEL_WINDOW as usize);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            server.manager.stats()[0].bytes_to_container,
+            TUNNEL_WINDOW as u64
+        );
+
+        // and resumes once the agent grants credit
+        agent.send(ProtoMessage::TunnelWindowUpdate(
+            devcon_proto::TunnelWindowUpdate {
+                tunnel_id: request.tunnel_id,
+                credit: TUNNEL_WINDOW,
+            },
+        ));
+        match agent.recv() {
+            ProtoMessage::TunnelData(data) => assert_eq!(data.data.len(), 1024),
+            other => panic!("Expected TunnelData, got {:?}", other),
+        }
+
+        // The host grants credit for data written to the client
+        agent.send(ProtoMessage::TunnelData(devcon_proto::TunnelData::new(
+            request.tunnel_id,
+            &vec![0u8; WINDOW_UPDATE_THRESHOLD as usize],
+            false,
+        )));
+        let mut response = vec![0u8; WINDOW_UPDATE_THRESHOLD as usize];
+        client.read_exact(&mut response).unwrap();
+        match agent.recv() {
+            ProtoMessage::TunnelWindowUpdate(update) => {
+                assert_eq!(update.tunnel_id, request.tunnel_id);
+                assert_eq!(update.credit, WINDOW_UPDATE_THRESHOLD);
+            }
+            other => panic!("Expected TunnelWindowUpdate, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_disconnected_agent_forwards_are_cleaned_up() {
+        let server = FixtureServer::start(ServerPolicy::default());
+        let mut agent = server.agent();
+        assert!(agent.hello(&[Capability::Multiplex], "my-project").accepted);
+
+        let ports = [free_port(), free_port()];
+        for port in ports {
+            agent.start_forward(port);
+        }
+        server.wait_for("the forwards to start", |m| m.stats().len() == 2);
+
+        drop(agent);
+        server.wait_for("the agent to be removed", |m| {
+            m.agents().is_empty() && m.stats().is_empty()
+        });
+        wait_until("the ports to be released", || {
+            ports.iter().all(|port| is_released(*port))
+        });
+    }
+}
@@ -0,0 +1,200 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Protocol Test Harness
+//!
+//! Runs the control server on an ephemeral port and drives it with a fake
+//! agent speaking the wire protocol, so complete flows like forwarding a
+//! port, dialing tunnels, proxying data and tearing everything down again
+//! are tested without containers.
+
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use devcon_proto::agent_message::Message as ProtoMessage;
+use devcon_proto::{AgentMessage, Capability};
+
+use super::{
+    HeartbeatConfig, PortForwardManager, ServerPolicy, handle_agent_connection, read_message,
+    send_message,
+};
+
+/// How long the harness waits for the server before failing a test
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A control server accepting agents on an ephemeral localhost port.
+pub(super) struct FixtureServer {
+    /// The manager shared by all agent connections, for assertions on its state
+    pub manager: PortForwardManager,
+    addr: SocketAddr,
+}
+
+impl FixtureServer {
+    /// Start a server with `policy` and the default heartbeat.
+    pub fn start(policy: ServerPolicy) -> Self {
+        Self::start_with_heartbeat(policy, HeartbeatConfig::default())
+    }
+
+    /// Start a server with `policy` and `heartbeat`.
+    pub fn start_with_heartbeat(policy: ServerPolicy, heartbeat: HeartbeatConfig) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let manager = PortForwardManager::new(policy);
+
+        let manager_clone = manager.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let manager = manager_clone.clone();
+                thread::spawn(move || {
+                    let _ = handle_agent_connection(stream, manager, heartbeat);
+                });
+            }
+        });
+
+        Self { manager, addr }
+    }
+
+    /// Connect a new fake agent.
+    pub fn agent(&self) -> FakeAgent {
+        let stream = TcpStream::connect(self.addr).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        FakeAgent { stream }
+    }
+
+    /// Wait until `condition` holds for the manager, failing with `what` on timeout.
+    pub fn wait_for(&self, what: &str, condition: impl Fn(&PortForwardManager) -> bool) {
+        wait_until(what, || condition(&self.manager));
+    }
+}
+
+/// An agent connected to a [`FixtureServer`] over the wire protocol.
+pub(super) struct FakeAgent {
+    stream: TcpStream,
+}
+
+impl FakeAgent {
+    /// Send a message to the server.
+    pub fn send(&mut self, message: ProtoMessage) {
+        send_message(
+            &mut self.stream,
+            &AgentMessage {
+                message: Some(message),
+            },
+        )
+        .unwrap();
+    }
+
+    /// Receive the next message, answering heartbeat pings on the way.
+    pub fn recv(&mut self) -> ProtoMessage {
+        loop {
+            match read_message(&mut self.stream).unwrap().message {
+                Some(ProtoMessage::Ping(ping)) => {
+                    self.send(ProtoMessage::Pong(devcon_proto::Pong { nonce: ping.nonce }))
+                }
+                Some(message) => return message,
+                None => panic!("Received message without content"),
+            }
+        }
+    }
+
+    /// Negotiate the protocol with `capabilities` for `workspace`.
+    pub fn hello(
+        &mut self,
+        capabilities: &[Capability],
+        workspace: &str,
+    ) -> devcon_proto::HelloAck {
+        self.send(ProtoMessage::Hello(devcon_proto::Hello {
+            protocol_version: devcon_proto::PROTOCOL_VERSION,
+            min_protocol_version: devcon_proto::MIN_PROTOCOL_VERSION,
+            capabilities: capabilities.iter().map(|c| *c as i32).collect(),
+            workspace: workspace.to_string(),
+        }));
+        match self.recv() {
+            ProtoMessage::HelloAck(ack) => ack,
+            other => panic!("Expected HelloAck, got {:?}", other),
+        }
+    }
+
+    /// Request forwarding `port` like a process detected by the agent.
+    pub fn start_forward(&mut self, port: u16) {
+        self.send(ProtoMessage::StartPortForward(
+            devcon_proto::StartPortForward {
+                port: port as u32,
+                ..Default::default()
+            },
+        ));
+    }
+
+    /// Request stopping the forward of `port`.
+    pub fn stop_forward(&mut self, port: u16) {
+        self.send(ProtoMessage::StopPortForward(
+            devcon_proto::StopPortForward { port: port as u32 },
+        ));
+    }
+
+    /// Receive the next message, which must be a tunnel request.
+    pub fn expect_tunnel_request(&mut self) -> devcon_proto::TunnelRequest {
+        match self.recv() {
+            ProtoMessage::TunnelRequest(request) => request,
+            other => panic!("Expected TunnelRequest, got {:?}", other),
+        }
+    }
+
+    /// Dial the data listener of a dedicated tunnel like the agent does,
+    /// returning the connection carrying the tunnel's data.
+    pub fn dial_tunnel(&self, request: &devcon_proto::TunnelRequest) -> TcpStream {
+        assert_ne!(request.data_port, 0, "tunnel is multiplexed");
+        let mut stream = TcpStream::connect(("127.0.0.1", request.data_port as u16)).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        stream.write_all(&request.tunnel_id.to_be_bytes()).unwrap();
+        stream
+    }
+}
+
+/// Connect a client to a forwarded port, retrying until its listener is up.
+pub(super) fn connect_client(port: u16) -> TcpStream {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)) {
+            stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+            return stream;
+        }
+        assert!(Instant::now() < deadline, "port {} never opened", port);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Returns true once nothing listens on `port` anymore.
+pub(super) fn is_released(port: u16) -> bool {
+    TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// Wait until `condition` holds, failing with `what` on timeout.
+pub(super) fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + TIMEOUT;
+    while !condition() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,472 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Control Server Dashboard
+//!
+//! This module keeps track of connected agents and recent control server events,
+//! and renders them together with the active forwards as a small HTML page.
+//!
+//! The dashboard is served next to the metrics endpoint on localhost and
+//! refreshes itself every few seconds. The same events are available as JSON
+//! for `devcon events`, tagged with a kind and workspace for editor plugins.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+
+use crate::driver::metrics::ForwardStats;
+use crate::driver::runtime::ContainerStats;
+
+/// Maximum number of events kept in the event log.
+const MAX_EVENTS: usize = 100;
+
+const DASHBOARD_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="5">
+<title>DevCon Control Server</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; min-width: 40em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { background: #e8f5e9; }
+.empty { color: #888; }
+</style>
+</head>
+<body>
+<h1>DevCon Control Server</h1>
+
+<h2>Agents</h2>
+{% if agents %}
+<table>
+<tr><th>Project</th><th>Address</th><th>Protocol</th><th>Capabilities</th><th>Connected</th></tr>
+{% for agent in agents %}
+<tr>
+<td>{{ agent.workspace or "unknown" }}</td>
+<td>{{ agent.peer }}</td>
+<td>v{{ agent.protocolVersion }}</td>
+<td>{{ agent.capabilities | join(", ") }}</td>
+<td>{{ agent.age }} ago{% if agent.suspended %} (suspended){% endif %}</td>
+</tr>
+{% endfor %}
+</table>
+{% else %}
+<p class="empty">No agents connected</p>
+{% endif %}
+
+<h2>Forwards</h2>
+{% if forwards %}
+<table>
+<tr><th>Port</th><th>Container Port</th><th>Project</th><th>Active</th><th>Total</th></tr>
+{% for forward in forwards %}
+<tr>
+<td><a href="http://localhost:{{ forward.localPort }}" target="_blank">localhost:{{ forward.localPort }}</a></td>
+<td>{{ forward.containerPort }}{% if forward.process %} ({{ forward.process }}){% endif %}{% if forward.suspended %}, suspended{% endif %}</td>
+<td>{{ forward.workspace or "unknown" }}</td>
+<td>{{ forward.activeConnections }}</td>
+<td>{{ forward.totalConnections }}</td>
+</tr>
+{% endfor %}
+</table>
+{% else %}
+<p class="empty">No active forwards</p>
+{% endif %}
+
+<h2>Containers</h2>
+{% if containers %}
+<table>
+<tr><th>Name</th><th>Container ID</th><th>CPU</th><th>Memory</th><th>Network (in / out)</th></tr>
+{% for container in containers %}
+<tr>
+<td>{{ container.name }}</td>
+<td>{{ container.container }}</td>
+<td>{{ container.cpu }}</td>
+<td>{{ container.memory }}</td>
+<td>{{ container.network }}</td>
+</tr>
+{% endfor %}
+</table>
+{% else %}
+<p class="empty">No running containers</p>
+{% endif %}
+
+<h2>Recent Events</h2>
+{% if events %}
+<table>
+<tr><th>When</th><th>Event</th></tr>
+{% for event in events %}
+<tr><td>{{ event.age }} ago</td><td>{{ event.message }}</td></tr>
+{% endfor %}
+</table>
+{% else %}
+<p class="empty">No events yet</p>
+{% endif %}
+</body>
+</html>
+"#;
+
+/// Information about a connected agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentInfo {
+    /// Address of the agent connection
+    pub peer: String,
+    /// Workspace name reported by the agent, empty for older agents
+    pub workspace: String,
+    /// Negotiated protocol version
+    pub protocol_version: u32,
+    /// Negotiated capabilities
+    pub capabilities: Vec<String>,
+    /// Seconds since the Unix epoch when the agent connected
+    pub connected_at: u64,
+    /// Ports forwarded on behalf of the agent
+    pub ports: Vec<u16>,
+    /// Whether the container of the agent is paused
+    #[serde(default)]
+    pub suspended: bool,
+}
+
+/// Resource usage of a running container.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerUsage {
+    /// Name of the container, as listed by `devcon list`
+    pub name: String,
+    /// ID of the container
+    pub container: String,
+    /// Usage reported by the container runtime
+    pub stats: ContainerStats,
+}
+
+/// Kind of a control server event.
+///
+/// The container and lifecycle kinds are not recorded by the control server,
+/// `devcon events` reports them from the container runtime and the workspace
+/// state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    /// An agent opened a control connection
+    AgentConnected,
+    /// An agent completed the handshake
+    AgentReady,
+    /// An agent was rejected during the handshake
+    AgentRejected,
+    /// An agent connection ended
+    AgentDisconnected,
+    /// A port forward was started
+    ForwardStarted,
+    /// A port forward was stopped
+    ForwardStopped,
+    /// A port forward could not be started
+    ForwardFailed,
+    /// An agent opened a URL on the host
+    UrlOpened,
+    /// An agent showed a desktop notification
+    Notification,
+    /// An agent opened a workspace file on the host
+    FileOpened,
+    /// A request to open a file was refused
+    FileRejected,
+    /// An agent connected to a host port
+    HostPortConnected,
+    /// A request to connect to a host port was refused
+    HostPortRejected,
+    /// The control server configuration was reloaded
+    ConfigReloaded,
+    /// The container of a workspace was paused
+    WorkspaceSuspended,
+    /// The container of a workspace was resumed
+    WorkspaceResumed,
+    /// The container was started
+    ContainerStarted,
+    /// The container was stopped
+    ContainerStopped,
+    /// The main process of the container exited
+    ContainerDied,
+    /// A process of the container ran out of memory
+    ContainerOom,
+    /// A lifecycle hook completed in the container
+    LifecycleStageCompleted,
+}
+
+/// A single control server event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// Sequence number, increasing with every event
+    pub id: u64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Kind of the event
+    pub kind: EventKind,
+    /// Workspace of the agent, empty if not known
+    pub workspace: String,
+    /// Human-readable description of the event
+    pub message: String,
+}
+
+/// Bounded log of recent control server events, newest first.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    events: Arc<Mutex<VecDeque<Event>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl EventLog {
+    /// Records an event, dropping the oldest one if the log is full.
+    pub fn push(&self, kind: EventKind, workspace: impl Into<String>, message: impl Into<String>) {
+        let mut events = self.events.lock().unwrap();
+        events.push_front(Event {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+            timestamp: now(),
+            kind,
+            workspace: workspace.into(),
+            message: message.into(),
+        });
+        events.truncate(MAX_EVENTS);
+    }
+
+    /// Returns the recorded events, newest first.
+    pub fn recent(&self) -> Vec<Event> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Status of a workspace as seen by the control server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStatus {
+    /// Whether an agent of the workspace is connected
+    pub running: bool,
+    /// Ports forwarded for the workspace, ascending
+    pub ports: Vec<u16>,
+}
+
+impl WorkspaceStatus {
+    /// Derives the status of `workspace` from the connected agents.
+    pub fn of(agents: &[AgentInfo], workspace: &str) -> Self {
+        let agents: Vec<_> = agents.iter().filter(|a| a.workspace == workspace).collect();
+        let mut ports: Vec<u16> = agents.iter().flat_map(|a| a.ports.clone()).collect();
+        ports.sort_unstable();
+        ports.dedup();
+
+        Self {
+            running: !agents.is_empty(),
+            ports,
+        }
+    }
+}
+
+impl fmt::Display for WorkspaceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.running, self.ports.len()) {
+            (false, _) => write!(f, "stopped"),
+            (true, 1) => write!(f, "running, 1 port"),
+            (true, n) => write!(f, "running, {} ports", n),
+        }
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats the time elapsed since `timestamp` (e.g. "42s", "5m", "3h").
+fn format_age(timestamp: u64) -> String {
+    let secs = now().saturating_sub(timestamp);
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Renders the dashboard page.
+///
+/// # Errors
+///
+/// Returns an error if the template cannot be rendered.
+pub fn render_dashboard(
+    agents: &[AgentInfo],
+    forwards: &[ForwardStats],
+    containers: &[ContainerUsage],
+    events: &[Event],
+) -> anyhow::Result<String> {
+    let mut env = Environment::new();
+    env.add_template("dashboard.html", DASHBOARD_TEMPLATE)?;
+
+    let agents: Vec<_> = agents
+        .iter()
+        .map(|agent| {
+            let mut value = serde_json::to_value(agent).unwrap_or_default();
+            value["age"] = format_age(agent.connected_at).into();
+            value
+        })
+        .collect();
+    let forwards: Vec<_> = forwards
+        .iter()
+        .map(|forward| {
+            let mut value = serde_json::to_value(forward).unwrap_or_default();
+            value["workspace"] = agents
+                .iter()
+                .find(|agent| {
+                    agent["ports"]
+                        .as_array()
+                        .is_some_and(|ports| ports.contains(&forward.local_port.into()))
+                })
+                .map(|agent| agent["workspace"].clone())
+                .unwrap_or_default();
+            value
+        })
+        .collect();
+    let containers: Vec<_> = containers
+        .iter()
+        .map(|usage| {
+            minijinja::context! {
+                name => &usage.name,
+                container => &usage.container,
+                cpu => usage.stats.cpu_label(),
+                memory => usage.stats.memory_label(),
+                network => usage.stats.network_label(),
+            }
+        })
+        .collect();
+    let events: Vec<_> = events
+        .iter()
+        .map(|event| {
+            minijinja::context! {
+                age => format_age(event.timestamp),
+                message => &event.message,
+            }
+        })
+        .collect();
+
+    let html = env
+        .get_template("dashboard.html")?
+        .render(minijinja::context! {
+            agents => agents,
+            forwards => forwards,
+            containers => containers,
+            events => events,
+        })?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::metrics::ForwardMetrics;
+
+    #[test]
+    fn test_event_log_is_bounded() {
+        let log = EventLog::default();
+        for i in 0..MAX_EVENTS + 5 {
+            log.push(EventKind::ForwardStarted, "", format!("event {}", i));
+        }
+
+        let events = log.recent();
+        assert_eq!(events.len(), MAX_EVENTS);
+        assert_eq!(events[0].message, format!("event {}", MAX_EVENTS + 4));
+        assert_eq!(events[0].id, (MAX_EVENTS + 5) as u64);
+        assert!(events.windows(2).all(|pair| pair[0].id > pair[1].id));
+    }
+
+    #[test]
+    fn test_workspace_status() {
+        let agent = |workspace: &str, ports: Vec<u16>| AgentInfo {
+            peer: "127.0.0.1:50000".to_string(),
+            workspace: workspace.to_string(),
+            protocol_version: 3,
+            capabilities: Vec::new(),
+            connected_at: now(),
+            ports,
+            suspended: false,
+        };
+        let agents = vec![
+            agent("my-project", vec![8080, 3000]),
+            agent("my-project", vec![3000]),
+            agent("other", vec![5432]),
+        ];
+
+        let status = WorkspaceStatus::of(&agents, "my-project");
+        assert_eq!(status.ports, vec![3000, 8080]);
+        assert_eq!(status.to_string(), "running, 2 ports");
+        assert_eq!(
+            WorkspaceStatus::of(&agents, "missing").to_string(),
+            "stopped"
+        );
+    }
+
+    #[test]
+    fn test_render_dashboard() {
+        let agents = vec![AgentInfo {
+            peer: "127.0.0.1:50000".to_string(),
+            workspace: "my-project".to_string(),
+            protocol_version: 3,
+            capabilities: vec!["Multiplex".to_string()],
+            connected_at: now(),
+            ports: vec![3000],
+            suspended: true,
+        }];
+        let forwards = vec![ForwardMetrics::new(3000).snapshot(3000)];
+        let events = vec![Event {
+            id: 1,
+            timestamp: now(),
+            kind: EventKind::AgentConnected,
+            workspace: String::new(),
+            message: "Agent <script> connected".to_string(),
+        }];
+
+        let containers = vec![ContainerUsage {
+            name: "devcon.my-project".to_string(),
+            container: "abc123".to_string(),
+            stats: ContainerStats {
+                cpu_percent: Some(150.0),
+                memory_usage: 512 * 1024 * 1024,
+                ..Default::default()
+            },
+        }];
+
+        let html = render_dashboard(&agents, &forwards, &containers, &events).unwrap();
+        assert!(html.contains("my-project"));
+        assert!(html.contains(r#"href="http://localhost:3000""#));
+        assert!(html.contains("<td>150.0%</td>"));
+        assert!(html.contains("ago (suspended)</td>"));
+        assert!(html.contains("<td>512.0 MiB</td>"));
+        assert!(html.contains("Agent &lt;script&gt; connected"));
+
+        let html = render_dashboard(&agents, &forwards, &[], &events).unwrap();
+        assert!(html.contains("No running containers"));
+    }
+}
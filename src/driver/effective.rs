@@ -0,0 +1,345 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Effective Configuration
+//!
+//! A workspace container is configured from several sources: the user config,
+//! the `devcontainer.metadata` label of the base image, the installed
+//! features, the devcontainer.json and the `.devcon.yaml` of the project.
+//! [`EffectiveConfig`] holds the result of merging them the same way as when
+//! starting the container, with the source of every value, so it can be shown
+//! with `devcon config effective`.
+//!
+//! Sources are named `config`, `host`, `image`, `feature <id>`,
+//! `devcontainer.json`, `.devcon.yaml` and `default`.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::devcontainer::{Devcontainer, LifecycleCommand, Mount, MountType};
+use crate::driver::image_metadata::{MetadataEntry, mount_target};
+use crate::project::{PROJECT_CONFIG_FILE, ProjectConfig};
+
+/// Source of values from the user config.
+pub const SOURCE_CONFIG: &str = "config";
+/// Source of values propagated from the host environment.
+pub const SOURCE_HOST: &str = "host";
+/// Source of values from the devcontainer.json of the workspace.
+pub const SOURCE_DEVCONTAINER: &str = "devcontainer.json";
+/// Source of values devcon uses when nothing is configured.
+pub const SOURCE_DEFAULT: &str = "default";
+
+/// A value with the source it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Sourced {
+    pub value: String,
+    pub source: String,
+}
+
+impl Sourced {
+    fn new(value: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// Lifecycle command of a stage, in the order it runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LifecycleStep {
+    /// Name of the devcontainer.json property, e.g. `postCreateCommand`
+    pub stage: String,
+    pub command: String,
+    pub source: String,
+}
+
+/// Merged configuration of a workspace with the source of every value.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    /// Environment of the container, later sources override earlier ones
+    pub container_env: BTreeMap<String, Sourced>,
+    /// Environment of shells and aliases started in the container
+    pub remote_env: BTreeMap<String, Sourced>,
+    pub mounts: Vec<Sourced>,
+    pub forward_ports: Vec<Sourced>,
+    pub container_user: Option<Sourced>,
+    pub remote_user: Option<Sourced>,
+    pub lifecycle: Vec<LifecycleStep>,
+    pub aliases: BTreeMap<String, Sourced>,
+}
+
+impl EffectiveConfig {
+    /// Merges the metadata entries of the base image and the features, in
+    /// installation order, with the devcontainer.json and project config.
+    ///
+    /// Follows [`MergedMetadata::merge`](crate::driver::image_metadata::MergedMetadata::merge)
+    /// for the metadata properties; variables are not substituted.
+    pub fn resolve(
+        entries: &[MetadataEntry],
+        devcontainer: &Devcontainer,
+        project: &ProjectConfig,
+    ) -> Self {
+        let workspace_entry = MetadataEntry::from(devcontainer);
+        let mut effective = Self::default();
+        let mut mounts: Vec<(Mount, String)> = Vec::new();
+
+        for entry in entries.iter().chain(std::iter::once(&workspace_entry)) {
+            let source = entry_source(entry, std::ptr::eq(entry, &workspace_entry));
+            for mount in entry.mounts.iter().flatten() {
+                let target = mount_target(mount);
+                if target.is_some() {
+                    mounts.retain(|(existing, _)| mount_target(existing) != target);
+                }
+                mounts.push((mount.clone(), source.clone()));
+            }
+            for (name, value) in entry.container_env.iter().flatten() {
+                effective
+                    .container_env
+                    .insert(name.clone(), Sourced::new(value, &source));
+            }
+            let stages = [
+                ("onCreateCommand", &entry.on_create_command),
+                ("updateContentCommand", &entry.update_content_command),
+                ("postCreateCommand", &entry.post_create_command),
+                ("postStartCommand", &entry.post_start_command),
+                ("postAttachCommand", &entry.post_attach_command),
+            ];
+            for (stage, command) in stages {
+                if let Some(command) = command {
+                    effective.lifecycle.push(LifecycleStep {
+                        stage: stage.to_string(),
+                        command: command_string(command),
+                        source: source.clone(),
+                    });
+                }
+            }
+        }
+        effective.mounts = mounts
+            .iter()
+            .map(|(mount, source)| Sourced::new(mount_string(mount), source))
+            .collect();
+
+        // Stages run one after another, the commands of a stage in entry order
+        let order = |stage: &str| {
+            [
+                "initializeCommand",
+                "onCreateCommand",
+                "updateContentCommand",
+                "postCreateCommand",
+                "postStartCommand",
+                "postAttachCommand",
+            ]
+            .iter()
+            .position(|s| *s == stage)
+        };
+        if let Some(command) = &devcontainer.initialize_command {
+            effective.lifecycle.push(LifecycleStep {
+                stage: "initializeCommand".to_string(),
+                command: command_string(command),
+                source: SOURCE_DEVCONTAINER.to_string(),
+            });
+        }
+        effective.lifecycle.sort_by_key(|step| order(&step.stage));
+
+        effective.forward_ports = devcontainer
+            .forward_ports
+            .iter()
+            .flatten()
+            .map(|port| Sourced::new(port.to_string(), SOURCE_DEVCONTAINER))
+            .collect();
+        effective.container_user = Some(user(&devcontainer.container_user));
+        effective.remote_user = Some(user(&devcontainer.remote_user));
+        effective.aliases = project
+            .aliases
+            .iter()
+            .map(|(name, command)| (name.clone(), Sourced::new(command, PROJECT_CONFIG_FILE)))
+            .collect();
+
+        effective
+    }
+
+    /// Adds `KEY=value` container environment variables of a source,
+    /// overriding the merged ones.
+    pub fn add_container_env(&mut self, env_vars: &[String], source: &str) {
+        add_env(&mut self.container_env, env_vars, source);
+    }
+
+    /// Adds `KEY=value` environment variables of a source for shells and
+    /// aliases. Variables without `=` take their value from the host.
+    pub fn add_remote_env(&mut self, env_vars: &[String], source: &str) {
+        add_env(&mut self.remote_env, env_vars, source);
+    }
+}
+
+fn add_env(env: &mut BTreeMap<String, Sourced>, env_vars: &[String], source: &str) {
+    for env_var in env_vars {
+        let (name, value) = match env_var.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => (env_var.clone(), std::env::var(env_var).unwrap_or_default()),
+        };
+        env.insert(name, Sourced::new(value, source));
+    }
+}
+
+/// Source name of a metadata entry.
+fn entry_source(entry: &MetadataEntry, workspace: bool) -> String {
+    match &entry.id {
+        _ if workspace => SOURCE_DEVCONTAINER.to_string(),
+        Some(id) => format!("feature {}", id),
+        None => "image".to_string(),
+    }
+}
+
+/// The configured user, or the default of devcon.
+fn user(configured: &Option<String>) -> Sourced {
+    match configured {
+        Some(user) => Sourced::new(user, SOURCE_DEVCONTAINER),
+        None => Sourced::new("vscode", SOURCE_DEFAULT),
+    }
+}
+
+/// Formats a lifecycle command on one line, named commands as `name: command`.
+fn command_string(command: &LifecycleCommand) -> String {
+    match command {
+        LifecycleCommand::String(cmd) => cmd.clone(),
+        LifecycleCommand::Array(args) => args.join(" "),
+        LifecycleCommand::Object(map) => {
+            let mut names: Vec<_> = map.keys().collect();
+            names.sort();
+            names
+                .into_iter()
+                .map(|name| format!("{}: {}", name, map[name].to_command_string()))
+                .collect::<Vec<_>>()
+                .join("; ")
+        }
+    }
+}
+
+/// Formats a mount in the `--mount` syntax of the container runtimes.
+pub fn mount_string(mount: &Mount) -> String {
+    match mount {
+        Mount::String(value) => value.clone(),
+        Mount::Structured(structured) => {
+            let mount_type = match structured.mount_type {
+                MountType::Bind => "bind",
+                MountType::Volume => "volume",
+            };
+            match &structured.source {
+                Some(source) => format!(
+                    "type={},source={},target={}",
+                    mount_type, source, structured.target
+                ),
+                None => format!("type={},target={}", mount_type, structured.target),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let devcontainer: Devcontainer = serde_json::from_str(
+            r#"{
+                "image": "ubuntu",
+                "containerEnv": { "EDITOR": "vim" },
+                "remoteUser": "dev",
+                "forwardPorts": [3000],
+                "mounts": ["source=cache,target=/cache,type=volume"],
+                "postCreateCommand": "make setup"
+            }"#,
+        )
+        .unwrap();
+        let feature: MetadataEntry = serde_json::from_str(
+            r#"{
+                "id": "ghcr.io/devcontainers/features/node:1",
+                "containerEnv": { "EDITOR": "nano", "NODE_HOME": "/usr/local/node" },
+                "mounts": [{ "type": "volume", "source": "npm", "target": "/cache" }],
+                "onCreateCommand": "npm ci"
+            }"#,
+        )
+        .unwrap();
+        let mut project = ProjectConfig::default();
+        project
+            .aliases
+            .insert("test".to_string(), "cargo test".to_string());
+
+        let mut effective = EffectiveConfig::resolve(&[feature], &devcontainer, &project);
+        effective.add_container_env(&["TZ=UTC".to_string()], SOURCE_HOST);
+        effective.add_remote_env(&["RUST_LOG=debug".to_string()], SOURCE_CONFIG);
+
+        let feature_source = "feature ghcr.io/devcontainers/features/node:1";
+        assert_eq!(
+            effective.container_env["EDITOR"],
+            Sourced::new("vim", SOURCE_DEVCONTAINER)
+        );
+        assert_eq!(effective.container_env["NODE_HOME"].source, feature_source);
+        assert_eq!(effective.container_env["TZ"].source, SOURCE_HOST);
+        assert_eq!(
+            effective.remote_env["RUST_LOG"],
+            Sourced::new("debug", SOURCE_CONFIG)
+        );
+        // The devcontainer.json mount replaces the feature mount on the same target
+        assert_eq!(
+            effective.mounts,
+            vec![Sourced::new(
+                "source=cache,target=/cache,type=volume",
+                SOURCE_DEVCONTAINER
+            )]
+        );
+        assert_eq!(effective.forward_ports[0].value, "3000");
+        assert_eq!(
+            effective.remote_user,
+            Some(Sourced::new("dev", SOURCE_DEVCONTAINER))
+        );
+        assert_eq!(
+            effective.container_user,
+            Some(Sourced::new("vscode", SOURCE_DEFAULT))
+        );
+        let steps: Vec<_> = effective
+            .lifecycle
+            .iter()
+            .map(|step| (step.stage.as_str(), step.source.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("onCreateCommand", feature_source),
+                ("postCreateCommand", SOURCE_DEVCONTAINER)
+            ]
+        );
+        assert_eq!(effective.aliases["test"].source, PROJECT_CONFIG_FILE);
+    }
+
+    #[test]
+    fn test_mount_string() {
+        let mount: Mount =
+            serde_json::from_str(r#"{ "type": "bind", "source": "/src", "target": "/dst" }"#)
+                .unwrap();
+        assert_eq!(mount_string(&mount), "type=bind,source=/src,target=/dst");
+    }
+}
@@ -0,0 +1,385 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Portable Environments
+//!
+//! `devcon export` bundles everything needed to start a workspace on another
+//! machine without network access into a single tar archive:
+//!
+//! - `manifest.json` - the workspace, its image and configuration hash
+//! - `image.tar` - the built image as saved by the container runtime
+//! - `devcontainer-lock.json` - the versions and digests the features
+//!   resolved to, in the format of the devcontainer spec
+//! - `devcontainer/` - the devcontainer configuration of the project
+//! - `cache/` - the cached features, which `devcon start` processes again
+//!
+//! `devcon import` loads the image under the image name of the importing
+//! workspace, adds missing configuration and cache files and records the
+//! configuration hash, so the image counts as up to date.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::driver::feature_process::FeatureProcessResult;
+
+/// Version of the archive layout, bumped on incompatible changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const IMAGE_FILE: &str = "image.tar";
+/// File name of the lockfile, next to `devcontainer.json`.
+pub const LOCKFILE: &str = "devcontainer-lock.json";
+/// Directory of the devcontainer configuration in the archive.
+pub const CONFIG_DIR: &str = "devcontainer";
+/// Directory of the feature cache in the archive.
+pub const CACHE_DIR: &str = "cache";
+
+/// Describes the exported workspace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Version of the archive layout
+    pub version: u32,
+    /// Sanitized name of the exported workspace
+    pub workspace: String,
+    /// Image of the workspace, e.g. `devcon-app:latest`
+    pub image: String,
+    /// Named configuration of a multi-config project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<String>,
+    /// Hash of the configuration the image was built from
+    pub config_hash: String,
+    /// Path of `devcontainer.json` relative to the project directory
+    pub devcontainer: PathBuf,
+    /// Time of the export in RFC 3339 format
+    pub created_at: String,
+}
+
+/// Features of an image pinned to the versions they resolved to.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub features: BTreeMap<String, LockedFeature>,
+}
+
+/// A feature as recorded in the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedFeature {
+    /// Version of the feature definition
+    pub version: String,
+    /// Reference the feature resolved to, pinned to the digest if known
+    pub resolved: String,
+    /// Digest of the feature layer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+}
+
+impl Lockfile {
+    /// Records the features an image was built with.
+    pub fn from_features(features: &[FeatureProcessResult]) -> Self {
+        let features = features
+            .iter()
+            .map(|feature_result| {
+                let reference = feature_result.reference();
+                let integrity = feature_result.digest();
+                let resolved = match (&integrity, reference.rsplit_once(':')) {
+                    (Some(digest), Some((repository, _))) => {
+                        format!("{}@{}", repository, digest)
+                    }
+                    _ => reference.clone(),
+                };
+                (
+                    reference,
+                    LockedFeature {
+                        version: feature_result.feature.version.clone(),
+                        resolved,
+                        integrity,
+                    },
+                )
+            })
+            .collect();
+        Self { features }
+    }
+}
+
+/// Writes an environment archive.
+///
+/// # Arguments
+///
+/// * `output` - Path of the archive
+/// * `manifest` - Manifest of the exported workspace
+/// * `lockfile` - Features of the image
+/// * `image` - Image archive saved by the container runtime
+/// * `project` - Project directory, containing `manifest.devcontainer`
+/// * `cache_dir` - Cache directory the cache entries are relative to
+/// * `cache_entries` - Cached files and folders of the features
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or the archive cannot be written.
+pub fn pack(
+    output: &Path,
+    manifest: &Manifest,
+    lockfile: &Lockfile,
+    image: &Path,
+    project: &Path,
+    cache_dir: &Path,
+    cache_entries: &[PathBuf],
+) -> Result<()> {
+    let file = File::create(output)
+        .with_context(|| format!("Failed to create archive {}", output.display()))?;
+    let mut builder = tar::Builder::new(file);
+
+    append_bytes(
+        &mut builder,
+        MANIFEST_FILE,
+        &serde_json::to_vec_pretty(manifest)?,
+    )?;
+    append_bytes(
+        &mut builder,
+        LOCKFILE,
+        &serde_json::to_vec_pretty(lockfile)?,
+    )?;
+    builder.append_path_with_name(image, IMAGE_FILE)?;
+
+    // A configuration folder is bundled as a whole for its Dockerfiles and
+    // scripts, a devcontainer.json in the project root alone
+    let config_path = match manifest.devcontainer.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => manifest.devcontainer.clone(),
+    };
+    append_path(
+        &mut builder,
+        &project.join(&config_path),
+        &Path::new(CONFIG_DIR).join(&config_path),
+    )?;
+
+    for entry in cache_entries {
+        append_path(
+            &mut builder,
+            &cache_dir.join(entry),
+            &Path::new(CACHE_DIR).join(entry),
+        )?;
+    }
+
+    builder.into_inner()?;
+    Ok(())
+}
+
+fn append_bytes(builder: &mut tar::Builder<File>, name: &str, content: &[u8]) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, content)?;
+    Ok(())
+}
+
+fn append_path(builder: &mut tar::Builder<File>, path: &Path, name: &Path) -> Result<()> {
+    if path.is_dir() {
+        builder.append_dir_all(name, path)?;
+    } else {
+        builder.append_path_with_name(path, name)?;
+    }
+    Ok(())
+}
+
+/// Extracts an environment archive into `dir`.
+///
+/// # Returns
+///
+/// The manifest and the path of the image archive.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be extracted, has no manifest or
+/// was written by a newer devcon.
+pub fn unpack(archive: &Path, dir: &Path) -> Result<(Manifest, PathBuf)> {
+    let file = File::open(archive)
+        .with_context(|| format!("Failed to open archive {}", archive.display()))?;
+    tar::Archive::new(file)
+        .unpack(dir)
+        .with_context(|| format!("Failed to extract archive {}", archive.display()))?;
+
+    let content = fs::read_to_string(dir.join(MANIFEST_FILE))
+        .with_context(|| format!("{} is no devcon environment archive", archive.display()))?;
+    let manifest: Manifest = serde_json::from_str(&content)?;
+    if manifest.version > FORMAT_VERSION {
+        bail!(
+            "{} was exported by a newer devcon (format {}), update devcon to import it",
+            archive.display(),
+            manifest.version
+        );
+    }
+    Ok((manifest, dir.join(IMAGE_FILE)))
+}
+
+/// Copies the files of `from` into `to` which don't exist there yet.
+///
+/// # Returns
+///
+/// The number of copied files.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or a file cannot be copied.
+pub fn restore_files(from: &Path, to: &Path) -> Result<usize> {
+    if !from.is_dir() {
+        return Ok(0);
+    }
+
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copied += restore_files(&entry.path(), &target)?;
+        } else if !target.exists() {
+            fs::create_dir_all(to)?;
+            fs::copy(entry.path(), &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(devcontainer: &str) -> Manifest {
+        Manifest {
+            version: FORMAT_VERSION,
+            workspace: "app".to_string(),
+            image: "devcon-app:latest".to_string(),
+            config: None,
+            config_hash: "abc".to_string(),
+            devcontainer: PathBuf::from(devcontainer),
+            created_at: "2025-01-02T10:30:05+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let source = tempfile::tempdir().unwrap();
+        let project = source.path().join("app");
+        fs::create_dir_all(project.join(".devcontainer")).unwrap();
+        fs::write(project.join(".devcontainer/devcontainer.json"), "{}").unwrap();
+        fs::write(project.join(".devcontainer/Dockerfile"), "FROM debian").unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}").unwrap();
+        let cache = source.path().join("cache");
+        fs::create_dir_all(cache.join("features/owner/repo/node/abc")).unwrap();
+        fs::write(cache.join("features/owner/repo/node/1.digest"), "abc").unwrap();
+        fs::write(cache.join("features/owner/repo/node/abc/install.sh"), "").unwrap();
+        let image = source.path().join("image.tar");
+        fs::write(&image, "layers").unwrap();
+
+        let archive = source.path().join("env.tar");
+        pack(
+            &archive,
+            &manifest(".devcontainer/devcontainer.json"),
+            &Lockfile::default(),
+            &image,
+            &project,
+            &cache,
+            &[
+                PathBuf::from("features/owner/repo/node/1.digest"),
+                PathBuf::from("features/owner/repo/node/abc"),
+            ],
+        )
+        .unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        let (unpacked, image) = unpack(&archive, target.path()).unwrap();
+        assert_eq!(unpacked, manifest(".devcontainer/devcontainer.json"));
+        assert_eq!(fs::read_to_string(image).unwrap(), "layers");
+        assert!(target.path().join(LOCKFILE).exists());
+        assert!(
+            target
+                .path()
+                .join("devcontainer/.devcontainer/Dockerfile")
+                .exists()
+        );
+        // Only the configuration of the project is bundled
+        assert!(!target.path().join("devcontainer/main.rs").exists());
+        assert!(
+            target
+                .path()
+                .join("cache/features/owner/repo/node/abc/install.sh")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_unpack_rejects_newer_format() {
+        let source = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("devcontainer.json"), "{}").unwrap();
+        let image = source.path().join("image.tar");
+        fs::write(&image, "").unwrap();
+
+        let mut newer = manifest("devcontainer.json");
+        newer.version = FORMAT_VERSION + 1;
+        let archive = source.path().join("env.tar");
+        pack(
+            &archive,
+            &newer,
+            &Lockfile::default(),
+            &image,
+            source.path(),
+            source.path(),
+            &[],
+        )
+        .unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        assert!(unpack(&archive, target.path()).is_err());
+    }
+
+    #[test]
+    fn test_restore_files_keeps_existing() {
+        let from = tempfile::tempdir().unwrap();
+        fs::create_dir_all(from.path().join(".devcontainer")).unwrap();
+        fs::write(from.path().join(".devcontainer/devcontainer.json"), "new").unwrap();
+        fs::write(from.path().join(".devcontainer/Dockerfile"), "new").unwrap();
+
+        let to = tempfile::tempdir().unwrap();
+        fs::create_dir_all(to.path().join(".devcontainer")).unwrap();
+        fs::write(to.path().join(".devcontainer/devcontainer.json"), "old").unwrap();
+
+        assert_eq!(restore_files(from.path(), to.path()).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(to.path().join(".devcontainer/devcontainer.json")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            fs::read_to_string(to.path().join(".devcontainer/Dockerfile")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            restore_files(&from.path().join("missing"), to.path()).unwrap(),
+            0
+        );
+    }
+}
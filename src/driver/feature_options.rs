@@ -0,0 +1,357 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Feature Options
+//!
+//! Options a feature declares without a `default` are required. Before an
+//! image is built, required options missing in the `devcontainer.json` are
+//! asked for on an interactive terminal, offering the `enum` values or
+//! `proposals` of the option. Without a terminal, or with
+//! `--no-interaction`, the build fails listing all missing options instead
+//! of installing the features with empty values.
+//!
+//! Answers are recorded in the workspace state by feature, and reused by
+//! every later command resolving the features, like `start`, `sbom` or
+//! `config effective`. Only `build` and `up` ask for options.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use console::Term;
+use serde_json::Value;
+
+use crate::driver::feature_process::{FeatureError, FeatureProcessResult};
+use crate::feature::{FeatureOption, FeatureOptionType};
+
+/// Asks for the value of an option by feature name, option name and
+/// definition, returning `None` if asking is not possible.
+pub type OptionPrompt<'a> = dyn FnMut(&str, &str, &FeatureOption) -> Result<Option<Value>> + 'a;
+
+/// Answered options by feature name and option name.
+pub type OptionAnswers = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Returns the names of the required options a feature is missing, sorted.
+pub fn missing_options(result: &FeatureProcessResult) -> Vec<String> {
+    let mut missing: Vec<String> = result
+        .feature
+        .options
+        .iter()
+        .flatten()
+        .filter(|(name, option)| {
+            option.is_required()
+                && result
+                    .feature_ref
+                    .options
+                    .get(name.as_str())
+                    .is_none_or(Value::is_null)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    missing.sort();
+    missing
+}
+
+/// Sets the missing required options of features to earlier answers in
+/// `saved`, or to the answers of `ask`.
+///
+/// Saved answers an option doesn't allow anymore are asked for again.
+/// Returns the new answers of `ask`, to be saved for later commands.
+///
+/// # Arguments
+///
+/// * `features` - Processed features, their options are completed in place
+/// * `saved` - Answers recorded by earlier commands
+/// * `ask` - Asks for the value of a missing option
+///
+/// # Errors
+///
+/// Returns [`FeatureError::MissingOptions`] listing all options `ask` cannot
+/// answer, or the error of `ask`.
+pub fn complete_options(
+    features: &mut [FeatureProcessResult],
+    saved: &OptionAnswers,
+    ask: &mut OptionPrompt,
+) -> Result<OptionAnswers> {
+    let mut answers = OptionAnswers::new();
+    let mut unanswered = Vec::new();
+    for result in features.iter_mut() {
+        let feature_name = result.name();
+        for name in missing_options(result) {
+            let Some(option) = result.feature.options.as_ref().and_then(|o| o.get(&name)) else {
+                continue;
+            };
+            let earlier = saved
+                .get(&feature_name)
+                .and_then(|options| options.get(&name))
+                .filter(|value| is_allowed(value, option))
+                .cloned();
+            let value = match earlier {
+                Some(value) => value,
+                None => match ask(&feature_name, &name, option)? {
+                    Some(value) => {
+                        answers
+                            .entry(feature_name.clone())
+                            .or_default()
+                            .insert(name.clone(), value.clone());
+                        value
+                    }
+                    None => {
+                        unanswered.push(format!("{}.{}", feature_name, name));
+                        continue;
+                    }
+                },
+            };
+            if !result.feature_ref.options.is_object() {
+                result.feature_ref.options = Value::Object(serde_json::Map::new());
+            }
+            if let Some(options) = result.feature_ref.options.as_object_mut() {
+                options.insert(name, value);
+            }
+        }
+    }
+    if !unanswered.is_empty() {
+        return Err(FeatureError::MissingOptions(unanswered).into());
+    }
+    Ok(answers)
+}
+
+/// Whether a saved answer is still a valid value of the option.
+fn is_allowed(value: &Value, option: &FeatureOption) -> bool {
+    match (&option.option_type, value) {
+        (FeatureOptionType::Boolean, Value::Bool(_)) => true,
+        (FeatureOptionType::Boolean, _) => false,
+        (_, Value::String(value)) => option
+            .allowed_values
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(value)),
+        _ => false,
+    }
+}
+
+/// Asks for the value of an option on the terminal until a valid one is
+/// given. `enum` values and `proposals` are listed and can be picked by
+/// number, an empty answer takes the first of them.
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be read or written.
+pub fn prompt_option(
+    term: &Term,
+    feature: &str,
+    name: &str,
+    option: &FeatureOption,
+) -> Result<Value> {
+    term.write_line(&format!("Feature {} requires the option {}", feature, name))?;
+    if let Some(description) = &option.description {
+        term.write_line(&format!("  {}", description))?;
+    }
+    let choices = choices(option);
+    for (index, choice) in choices.iter().enumerate() {
+        term.write_line(&format!("  {}) {}", index + 1, choice))?;
+    }
+    let hint = match (&option.option_type, choices.first()) {
+        (FeatureOptionType::Boolean, _) => " [true/false]".to_string(),
+        (_, Some(first)) => format!(" [{}]", first),
+        (_, None) => String::new(),
+    };
+    loop {
+        term.write_str(&format!("{}{}: ", name, hint))?;
+        match parse_answer(&term.read_line()?, option) {
+            Ok(value) => return Ok(value),
+            Err(message) => term.write_line(&message)?,
+        }
+    }
+}
+
+/// Values offered for an option: its `enum` values, else its `proposals`.
+fn choices(option: &FeatureOption) -> Vec<String> {
+    option
+        .allowed_values
+        .clone()
+        .or_else(|| option.proposals.clone())
+        .unwrap_or_default()
+}
+
+/// Parses an answer to [`prompt_option`], returning the message to show if
+/// it is not a valid value of the option.
+fn parse_answer(answer: &str, option: &FeatureOption) -> std::result::Result<Value, String> {
+    let answer = answer.trim();
+    if let FeatureOptionType::Boolean = option.option_type {
+        return match answer {
+            "true" | "y" | "yes" => Ok(Value::Bool(true)),
+            "false" | "n" | "no" => Ok(Value::Bool(false)),
+            _ => Err("Answer true or false".to_string()),
+        };
+    }
+
+    let choices = choices(option);
+    let value = if answer.is_empty() {
+        choices
+            .first()
+            .cloned()
+            .ok_or_else(|| "A value is required".to_string())?
+    } else {
+        answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| choices.get(index.wrapping_sub(1)))
+            .cloned()
+            .unwrap_or_else(|| answer.to_string())
+    };
+    if let Some(allowed) = &option.allowed_values
+        && !allowed.contains(&value)
+    {
+        return Err(format!("Choose one of: {}", allowed.join(", ")));
+    }
+    Ok(Value::String(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::devcontainer::{FeatureRef, FeatureSource};
+
+    fn option(definition: Value) -> FeatureOption {
+        serde_json::from_value(definition).unwrap()
+    }
+
+    fn feature(options: Value, user_options: Value) -> FeatureProcessResult {
+        let feature = serde_json::from_value(serde_json::json!({
+            "id": "node",
+            "version": "1.0.0",
+            "options": options,
+        }))
+        .unwrap();
+        let mut feature_ref = FeatureRef::new(FeatureSource::Local {
+            path: PathBuf::from("/tmp/node"),
+        });
+        feature_ref.options = user_options;
+        FeatureProcessResult {
+            feature_ref,
+            feature,
+            path: PathBuf::from("/tmp/node"),
+            fetch_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_complete_options() {
+        let options = serde_json::json!({
+            "version": { "type": "string", "enum": ["18", "20"] },
+            "token": { "type": "string" },
+            "yarn": { "type": "boolean", "default": true },
+        });
+        let mut features = vec![feature(
+            options.clone(),
+            serde_json::json!({ "token": "x" }),
+        )];
+        assert_eq!(missing_options(&features[0]), vec!["version"]);
+
+        let mut asked = Vec::new();
+        let answers = complete_options(
+            &mut features,
+            &OptionAnswers::new(),
+            &mut |feature, name, _| {
+                asked.push(format!("{}.{}", feature, name));
+                Ok(Some(Value::String("20".to_string())))
+            },
+        )
+        .unwrap();
+        assert_eq!(asked, vec!["node.version"]);
+        assert_eq!(features[0].feature_ref.options["version"], "20");
+        assert!(missing_options(&features[0]).is_empty());
+        assert_eq!(answers["node"]["version"], "20");
+
+        // Without interaction all missing options are reported at once
+        let mut features = vec![feature(options.clone(), serde_json::json!({}))];
+        let error = complete_options(&mut features, &OptionAnswers::new(), &mut |_, _, _| {
+            Ok(None)
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            FeatureError::MissingOptions(vec![
+                "node.token".to_string(),
+                "node.version".to_string()
+            ])
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_saved_answers_are_reused() {
+        let options = serde_json::json!({
+            "version": { "type": "string", "enum": ["18", "20"] },
+            "yarn": { "type": "boolean" },
+        });
+        let saved: OptionAnswers = serde_json::from_value(serde_json::json!({
+            "node": { "version": "20", "yarn": false },
+        }))
+        .unwrap();
+
+        let mut features = vec![feature(options.clone(), serde_json::json!({}))];
+        let answers = complete_options(&mut features, &saved, &mut |_, _, _| Ok(None)).unwrap();
+        assert!(answers.is_empty());
+        assert_eq!(features[0].feature_ref.options["version"], "20");
+        assert_eq!(features[0].feature_ref.options["yarn"], false);
+
+        // Answers the option no longer allows are asked for again
+        let saved: OptionAnswers = serde_json::from_value(serde_json::json!({
+            "node": { "version": "16", "yarn": false },
+        }))
+        .unwrap();
+        let mut features = vec![feature(options, serde_json::json!({}))];
+        let answers = complete_options(&mut features, &saved, &mut |_, _, _| {
+            Ok(Some(Value::from("18")))
+        })
+        .unwrap();
+        assert_eq!(answers["node"]["version"], "18");
+        assert_eq!(features[0].feature_ref.options["version"], "18");
+    }
+
+    #[test]
+    fn test_parse_answer() {
+        let version = option(serde_json::json!({
+            "type": "string",
+            "enum": ["18", "20"],
+        }));
+        assert_eq!(parse_answer("", &version), Ok(Value::from("18")));
+        assert_eq!(parse_answer("2", &version), Ok(Value::from("20")));
+        assert_eq!(parse_answer(" 20\n", &version), Ok(Value::from("20")));
+        assert!(parse_answer("22", &version).is_err());
+
+        let proposed = option(serde_json::json!({
+            "type": "string",
+            "proposals": ["latest"],
+        }));
+        assert_eq!(parse_answer("", &proposed), Ok(Value::from("latest")));
+        assert_eq!(parse_answer("lts", &proposed), Ok(Value::from("lts")));
+        assert!(parse_answer("", &option(serde_json::json!({ "type": "string" }))).is_err());
+
+        let flag = option(serde_json::json!({ "type": "boolean" }));
+        assert_eq!(parse_answer("y", &flag), Ok(Value::Bool(true)));
+        assert!(parse_answer("maybe", &flag).is_err());
+    }
+}
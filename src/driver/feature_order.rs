@@ -0,0 +1,324 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Feature Installation Order
+//!
+//! Orders features as described by the devcontainer specification:
+//!
+//! 1. `dependsOn` are hard dependencies, the referenced features are always
+//!    installed first.
+//! 2. `installsAfter` are soft dependencies, only considered if the
+//!    referenced feature is installed as well.
+//! 3. Features are installed in rounds. Every round, the features whose
+//!    dependencies are installed are candidates. If some candidates are
+//!    listed in `overrideFeatureInstallOrder`, only the one listed first is
+//!    installed in this round, otherwise all candidates are, sorted by ID.
+//!
+//! The override can therefore change the order of independent features but
+//! never install a feature before its dependencies.
+//!
+//! Features are referenced by their ID, a full reference
+//! (`ghcr.io/devcontainers/features/node:1`) or one of their `legacyIds`.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::bail;
+use tracing::{debug, warn};
+
+use crate::driver::feature_process::FeatureProcessResult;
+
+/// Returns the feature ID of a reference, e.g. `node` for
+/// `ghcr.io/devcontainers/features/node:1`.
+pub fn normalize_id(reference: &str) -> &str {
+    let name = reference.rsplit('/').next().unwrap_or(reference);
+    let name = name.split('@').next().unwrap_or(name);
+    name.split(':').next().unwrap_or(name)
+}
+
+/// Groups the features into installation rounds, as indexes into `features`.
+///
+/// # Errors
+///
+/// Returns an error if the dependencies of the features are circular.
+pub fn install_rounds(
+    features: &[FeatureProcessResult],
+    override_order: &[String],
+) -> anyhow::Result<Vec<Vec<usize>>> {
+    // Feature IDs and legacy IDs to the index of the feature
+    let mut index_of: HashMap<&str, usize> = HashMap::new();
+    for (index, feature_result) in features.iter().enumerate() {
+        for legacy_id in feature_result.feature.legacy_ids.iter().flatten() {
+            index_of.insert(legacy_id, index);
+        }
+    }
+    for (index, feature_result) in features.iter().enumerate() {
+        index_of.insert(&feature_result.feature.id, index);
+    }
+    let resolve = |reference: &str| index_of.get(normalize_id(reference)).copied();
+
+    let mut dependencies: Vec<HashSet<usize>> = Vec::with_capacity(features.len());
+    for (index, feature_result) in features.iter().enumerate() {
+        let feature = &feature_result.feature;
+        let mut required = HashSet::new();
+        for reference in feature.depends_on.iter().flat_map(|deps| deps.keys()) {
+            match resolve(reference) {
+                Some(dependency) => {
+                    required.insert(dependency);
+                }
+                None => warn!(
+                    "Dependency {} of feature {} is not installed",
+                    reference, feature.id
+                ),
+            }
+        }
+        for reference in feature.installs_after.iter().flatten() {
+            if let Some(dependency) = resolve(reference) {
+                required.insert(dependency);
+            }
+        }
+        required.remove(&index);
+        dependencies.push(required);
+    }
+
+    let mut priority: HashMap<usize, usize> = HashMap::new();
+    for (position, reference) in override_order.iter().enumerate() {
+        match resolve(reference) {
+            Some(index) => {
+                priority.entry(index).or_insert(position);
+            }
+            None => warn!(
+                "Feature '{}' specified in overrideFeatureInstallOrder not found",
+                reference
+            ),
+        }
+    }
+
+    let mut installed: HashSet<usize> = HashSet::new();
+    let mut rounds = Vec::new();
+    while installed.len() < features.len() {
+        let candidates: Vec<usize> = (0..features.len())
+            .filter(|index| !installed.contains(index))
+            .filter(|index| dependencies[*index].is_subset(&installed))
+            .collect();
+        if candidates.is_empty() {
+            let mut remaining: Vec<&str> = (0..features.len())
+                .filter(|index| !installed.contains(index))
+                .map(|index| features[index].feature.id.as_str())
+                .collect();
+            remaining.sort();
+            bail!(
+                "Circular dependency detected among features: {:?}",
+                remaining
+            );
+        }
+
+        let mut round = match candidates
+            .iter()
+            .filter_map(|index| priority.get(index))
+            .min()
+        {
+            Some(first) => candidates
+                .into_iter()
+                .filter(|index| priority.get(index) == Some(first))
+                .collect(),
+            None => candidates,
+        };
+        round.sort_by(|a, b| features[*a].feature.id.cmp(&features[*b].feature.id));
+        installed.extend(round.iter().copied());
+        rounds.push(round);
+    }
+
+    Ok(rounds)
+}
+
+/// Orders the features for installation, see the module documentation.
+///
+/// # Errors
+///
+/// Returns an error if the dependencies of the features are circular.
+pub fn install_order(
+    features: Vec<FeatureProcessResult>,
+    override_order: &[String],
+) -> anyhow::Result<Vec<FeatureProcessResult>> {
+    let rounds = install_rounds(&features, override_order)?;
+    for (number, round) in rounds.iter().enumerate() {
+        debug!(
+            "Installation round {}: {:?}",
+            number + 1,
+            round
+                .iter()
+                .map(|index| &features[*index].feature.id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    let mut features: Vec<Option<FeatureProcessResult>> = features.into_iter().map(Some).collect();
+    Ok(rounds
+        .into_iter()
+        .flatten()
+        .filter_map(|index| features[index].take())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::devcontainer::{FeatureRef, FeatureSource};
+
+    fn feature(definition: serde_json::Value) -> FeatureProcessResult {
+        let mut definition = definition;
+        definition["version"] = serde_json::json!("1.0.0");
+        let feature: crate::feature::Feature = serde_json::from_value(definition).unwrap();
+        FeatureProcessResult {
+            feature_ref: FeatureRef::new(FeatureSource::Local {
+                path: PathBuf::from(format!("/tmp/{}", feature.id)),
+            }),
+            path: PathBuf::from(format!("/tmp/{}", feature.id)),
+            feature,
+            fetch_time: std::time::Duration::ZERO,
+        }
+    }
+
+    fn ids(features: &[FeatureProcessResult]) -> Vec<&str> {
+        features.iter().map(|f| f.feature.id.as_str()).collect()
+    }
+
+    fn order(features: Vec<FeatureProcessResult>, override_order: &[&str]) -> Vec<String> {
+        let override_order: Vec<String> = override_order.iter().map(|s| s.to_string()).collect();
+        let ordered = install_order(features, &override_order).unwrap();
+        ids(&ordered).into_iter().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_normalize_id() {
+        assert_eq!(
+            normalize_id("ghcr.io/devcontainers/features/node:1"),
+            "node"
+        );
+        assert_eq!(
+            normalize_id("ghcr.io/devcontainers/features/node@sha256:ab"),
+            "node"
+        );
+        assert_eq!(normalize_id("node"), "node");
+    }
+
+    #[test]
+    fn test_depends_on() {
+        let features = vec![
+            feature(serde_json::json!({"id": "c", "dependsOn": {"ghcr.io/test/features/b:1": {}}})),
+            feature(serde_json::json!({"id": "b", "dependsOn": {"a": {}}})),
+            feature(serde_json::json!({"id": "a"})),
+        ];
+        assert_eq!(order(features, &[]), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_diamond_rounds() {
+        let features = vec![
+            feature(serde_json::json!({"id": "d", "dependsOn": {"b": {}, "c": {}}})),
+            feature(serde_json::json!({"id": "c", "dependsOn": {"a": {}}})),
+            feature(serde_json::json!({"id": "b", "dependsOn": {"a": {}}})),
+            feature(serde_json::json!({"id": "a"})),
+        ];
+        assert_eq!(
+            install_rounds(&features, &[]).unwrap(),
+            vec![vec![3], vec![2, 1], vec![0]]
+        );
+    }
+
+    #[test]
+    fn test_installs_after_is_soft() {
+        let features = vec![
+            feature(serde_json::json!({"id": "b", "installsAfter": ["a", "missing"]})),
+            feature(serde_json::json!({"id": "a"})),
+        ];
+        assert_eq!(order(features, &[]), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_legacy_ids() {
+        let features = vec![
+            feature(
+                serde_json::json!({"id": "b", "installsAfter": ["ghcr.io/test/features/old-a"]}),
+            ),
+            feature(serde_json::json!({"id": "a", "legacyIds": ["old-a"]})),
+        ];
+        assert_eq!(order(features, &["old-a"]), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_circular_dependency() {
+        let features = vec![
+            feature(serde_json::json!({"id": "a", "dependsOn": {"b": {}}})),
+            feature(serde_json::json!({"id": "b", "dependsOn": {"a": {}}})),
+        ];
+        let error = install_order(features, &[]).unwrap_err();
+        assert!(error.to_string().contains("Circular dependency"));
+    }
+
+    #[test]
+    fn test_no_special_case_for_common_utils() {
+        let features = vec![
+            feature(serde_json::json!({"id": "node"})),
+            feature(serde_json::json!({"id": "common-utils"})),
+            feature(serde_json::json!({"id": "azure-cli"})),
+        ];
+        assert_eq!(
+            order(features, &[]),
+            vec!["azure-cli", "common-utils", "node"]
+        );
+    }
+
+    #[test]
+    fn test_override_order() {
+        let features = || {
+            vec![
+                feature(serde_json::json!({"id": "a"})),
+                feature(serde_json::json!({"id": "b"})),
+                feature(serde_json::json!({"id": "c"})),
+                feature(serde_json::json!({"id": "d"})),
+            ]
+        };
+        assert_eq!(
+            order(features(), &["c", "a", "b", "d"]),
+            vec!["c", "a", "b", "d"]
+        );
+        assert_eq!(order(features(), &["c", "a"]), vec!["c", "a", "b", "d"]);
+        assert_eq!(
+            order(features(), &["ghcr.io/test/features/d:1", "nonexistent"]),
+            vec!["d", "a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_override_cannot_break_dependencies() {
+        let features = vec![
+            feature(serde_json::json!({"id": "a"})),
+            feature(serde_json::json!({"id": "b", "dependsOn": {"a": {}}})),
+            feature(serde_json::json!({"id": "c"})),
+        ];
+        // c is the only listed candidate of the first round, b has to wait for a
+        assert_eq!(order(features, &["b", "c"]), vec!["c", "a", "b"]);
+    }
+}
@@ -29,30 +29,121 @@
 //!
 //! Features can be sourced from:
 //! - **Registry** - Downloaded from OCI-compliant registries like ghcr.io
-//! - **Local** - Loaded from the local filesystem (not yet implemented)
+//! - **Local** - Loaded from a folder or `.tgz` archive on the local filesystem,
+//!   relative to the folder of the devcontainer.json
+//! - **Tarball** - Downloaded as `.tgz` archive from an HTTPS URL
 
 use std::{
     collections::{HashMap, HashSet, VecDeque},
-    fs::{self, File},
+    fs::{self, File, TryLockError},
+    io::Write,
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use anyhow::{Ok, bail};
+use anyhow::{Context, Ok};
+use indicatif::{ProgressBar, ProgressStyle};
+use oci_spec::image::Descriptor;
+use sha2::{Digest, Sha256};
 use tempfile::TempDir;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::devcontainer::{
     FeatureRef, FeatureRegistry,
-    FeatureSource::{Local, Registry},
+    FeatureSource::{Local, Registry, Tarball},
     parse_feature,
 };
+use crate::download::DownloadPolicy;
+use crate::driver::cancel::CancelToken;
+use crate::driver::feature_order;
+use crate::driver::oci::OciClient;
+use crate::exit_code;
 use crate::feature::Feature;
+use crate::http_proxy;
+
+/// Errors of resolving and downloading features.
+#[derive(Debug, thiserror::Error)]
+pub enum FeatureError {
+    /// Offline, the feature has not been downloaded before.
+    #[error("Feature {0} is not in the local cache. Build once without --offline to download it.")]
+    NotCached(String),
+
+    /// The registry could not be reached.
+    #[error("Failed to download feature: {name}")]
+    Download {
+        name: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// The feature has no `devcontainer-feature.json`.
+    #[error("Feature definition file not found: {}", .0.display())]
+    DefinitionNotFound(PathBuf),
+
+    /// `verifySignatures` is enabled and the feature has no trusted signature.
+    #[error("Signature verification of feature {name} failed")]
+    Signature {
+        name: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// Options without a default are not set, and cannot be asked for.
+    #[error(
+        "Required feature options are not set: {}. Set them in the features of devcontainer.json or answer them in devcon build",
+        .0.join(", ")
+    )]
+    MissingOptions(Vec<String>),
+
+    /// The registry serves the feature in an unknown format.
+    #[error("Unsupported layer media type for feature: {name}, media type: {media_type}")]
+    UnsupportedMediaType { name: String, media_type: String },
+
+    /// Some of the requested features failed, with the error of each.
+    #[error(
+        "Failed to process {} of {} features:\n  - {}",
+        .failures.len(),
+        .total,
+        format_failures(.failures)
+    )]
+    Failed {
+        total: usize,
+        failures: Vec<(String, anyhow::Error)>,
+    },
+}
+
+impl FeatureError {
+    /// Returns the process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotCached(_) | Self::Download { .. } => exit_code::NETWORK,
+            Self::DefinitionNotFound(_) | Self::UnsupportedMediaType { .. } => {
+                exit_code::INVALID_DEFINITION
+            }
+            Self::Signature { .. } => exit_code::BUILD_FAILED,
+            Self::MissingOptions(_) => exit_code::CONFIG,
+            Self::Failed { failures, .. } => failures
+                .first()
+                .map_or(exit_code::FAILURE, |(_, error)| exit_code::exit_code(error)),
+        }
+    }
+}
+
+fn format_failures(failures: &[(String, anyhow::Error)]) -> String {
+    failures
+        .iter()
+        .map(|(name, error)| format!("{}: {:#}", name, error))
+        .collect::<Vec<_>>()
+        .join("\n  - ")
+}
 
 #[derive(Debug, Clone)]
 pub struct FeatureProcessResult {
     pub feature_ref: FeatureRef,
     pub feature: Feature,
     pub path: PathBuf,
+    /// Time spent downloading the feature or finding it in the cache
+    pub fetch_time: Duration,
 }
 
 impl FeatureProcessResult {
@@ -67,16 +158,58 @@ impl FeatureProcessResult {
         }
 
         // Fall back to the feature reference source
+        self.feature_ref.source.name()
+    }
+
+    /// Returns the reference the feature was resolved from: the registry
+    /// reference, the local path or the tarball URL.
+    pub fn reference(&self) -> String {
         match &self.feature_ref.source {
-            Registry { registry } => registry.name.clone(),
-            Local { path } => path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .unwrap_or("unknown")
-                .to_string(),
+            Registry { registry } => registry_reference(registry),
+            Local { path } => path.to_string_lossy().to_string(),
+            Tarball { url } => url.clone(),
         }
     }
 
+    /// Returns the layer digest a registry feature resolved to, `None` for
+    /// local and tarball features.
+    ///
+    /// Features cached before the digest index recorded full digests only
+    /// know the shortened digest of their cache folder.
+    pub fn digest(&self) -> Option<String> {
+        match &self.feature_ref.source {
+            Registry { registry } => {
+                // Registry features are cached in a folder named after the digest
+                let short = self.path.file_name()?.to_string_lossy().to_string();
+                let full = get_feature_digest_index(registry)
+                    .ok()
+                    .and_then(|index| fs::read_to_string(index).ok())
+                    .map(|digest| digest.trim().to_string())
+                    .filter(|digest| digest.starts_with(&short));
+                Some(format!("sha256:{}", full.unwrap_or(short)))
+            }
+            Local { .. } | Tarball { .. } => None,
+        }
+    }
+
+    /// Returns the cache entries which make the feature available offline,
+    /// relative to [`get_devcon_cache_dir`]: the cached feature folder and,
+    /// for registry features, the index of the version's digest. Local
+    /// features have none.
+    pub fn cache_entries(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let cache_dir = get_devcon_cache_dir()?;
+        let paths = match &self.feature_ref.source {
+            Registry { registry } => vec![get_feature_digest_index(registry)?, self.path.clone()],
+            Tarball { .. } => vec![self.path.clone()],
+            Local { .. } => Vec::new(),
+        };
+        Ok(paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(&cache_dir).ok())
+            .map(Path::to_path_buf)
+            .collect())
+    }
+
     /// Returns a directory-safe name for the feature.
     ///
     /// The directory name is constructed from the feature name and version,
@@ -95,49 +228,67 @@ impl FeatureProcessResult {
 /// Processes a list of features, downloading and extracting them as needed.
 ///
 /// This function iterates through all features and processes each one,
-/// resolving transitive dependencies and ordering them topologically.
+/// resolving transitive dependencies and ordering them for installation
+/// with [`feature_order::install_order`].
 ///
 /// # Arguments
 ///
 /// * `features` - Slice of features to process
+/// * `override_order` - The `overrideFeatureInstallOrder` of the devcontainer.json
+/// * `client` - Registry client downloading registry features
+/// * `policy` - Bandwidth limit and time windows for downloads
+/// * `cancel` - Token checked before every feature is downloaded
 ///
 /// # Returns
 ///
-/// A vector of FeatureProcessResult in dependency order (dependencies first)
+/// A vector of FeatureProcessResult in installation order (dependencies first)
 ///
 /// # Errors
 ///
 /// Returns an error if any feature fails to download, extract, or if there are
-/// circular dependencies.
-pub fn process_features(features: &[FeatureRef]) -> anyhow::Result<Vec<FeatureProcessResult>> {
+/// circular dependencies. A failing feature doesn't stop the others from being
+/// downloaded to the cache; all failures are reported together. Cancelling
+/// stops before the next feature.
+pub fn process_features(
+    features: &[FeatureRef],
+    override_order: &[String],
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+    cancel: &CancelToken,
+) -> anyhow::Result<Vec<FeatureProcessResult>> {
     println!("Processing features..");
     let mut initial_results: Vec<FeatureProcessResult> = vec![];
+    let mut failures: Vec<(String, anyhow::Error)> = vec![];
 
     // Process initial features
     for feature_ref in features {
-        match &feature_ref.source {
-            Registry { registry, .. } => {
-                println!("Processing feature {}", registry.name)
+        let name = feature_ref.source.name();
+        cancel.check()?;
+        println!("Processing feature {}", name);
+        match process_feature(feature_ref, client, policy) {
+            Result::Ok(feature_result) => initial_results.push(feature_result),
+            Err(e) => {
+                warn!("Failed to process feature {}: {:#}", name, e);
+                failures.push((name, e));
             }
-            Local { path } => println!(
-                "Processing feature {}",
-                path.canonicalize()?
-                    .file_name()
-                    .ok_or_else(|| anyhow::anyhow!("Could not get basename of directory"))?
-                    .to_string_lossy()
-            ),
         }
-        let feature_result = process_feature(feature_ref)?;
-        initial_results.push(feature_result);
+    }
+
+    if !failures.is_empty() {
+        return Err(FeatureError::Failed {
+            total: features.len(),
+            failures,
+        }
+        .into());
     }
 
     // Resolve all dependencies (transitive)
     println!("Resolving feature dependencies..");
-    let all_features = resolve_all_dependencies(initial_results)?;
+    let all_features = resolve_all_dependencies(initial_results, client, policy, cancel)?;
 
-    // Sort features topologically
     println!("Ordering features by dependencies..");
-    let sorted_features = topological_sort(all_features)?;
+    let sorted_features =
+        feature_order::install_order(all_features.into_values().collect(), override_order)?;
 
     println!(
         "Processed {} features (including dependencies)",
@@ -155,6 +306,9 @@ pub fn process_features(features: &[FeatureRef]) -> anyhow::Result<Vec<FeaturePr
 /// # Arguments
 ///
 /// * `initial_features` - The initial set of features to process
+/// * `client` - Registry client downloading registry features
+/// * `policy` - Bandwidth limit and time windows for downloads
+/// * `cancel` - Token checked before every dependency is downloaded
 ///
 /// # Returns
 ///
@@ -166,8 +320,12 @@ pub fn process_features(features: &[FeatureRef]) -> anyhow::Result<Vec<FeaturePr
 /// - A dependency cannot be downloaded or processed
 /// - A circular dependency is detected
 /// - A dependency reference cannot be parsed
+/// - The token is cancelled
 fn resolve_all_dependencies(
     initial_features: Vec<FeatureProcessResult>,
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+    cancel: &CancelToken,
 ) -> anyhow::Result<HashMap<String, FeatureProcessResult>> {
     let mut all_features: HashMap<String, FeatureProcessResult> = HashMap::new();
     let mut to_process: VecDeque<FeatureProcessResult> = VecDeque::new();
@@ -248,8 +406,9 @@ fn resolve_all_dependencies(
             };
 
             // Process the dependency
+            cancel.check()?;
             println!("Downloading dependency feature: {}", dep_id);
-            let dep_result = process_feature(&dep_ref)?;
+            let dep_result = process_feature(&dep_ref, client, policy)?;
             let dep_feature_id = dep_result.feature.id.clone();
 
             // Add to processing queue
@@ -265,221 +424,207 @@ fn resolve_all_dependencies(
     Ok(all_features)
 }
 
-/// Performs topological sort on features based on their dependencies.
-/// Performs topological sort on features based on their dependencies.
-///
-/// Uses Kahn's algorithm to order features such that dependencies are installed
-/// before features that depend on them.
-///
-/// # Arguments
-///
-/// * `features` - Map of feature ID to FeatureProcessResult
-///
-/// # Returns
-///
-/// An ordered vector of FeatureProcessResult where dependencies come before dependents
-///
-/// # Errors
-///
-/// Returns an error if a circular dependency is detected
-fn topological_sort(
-    features: HashMap<String, FeatureProcessResult>,
-) -> anyhow::Result<Vec<FeatureProcessResult>> {
-    let mut in_degree: HashMap<String, usize> = HashMap::new();
-    let mut adjacency: HashMap<String, Vec<String>> = HashMap::new();
-    let mut feature_map = features;
-
-    // Build the dependency graph
-    for (feature_id, feature_result) in &feature_map {
-        in_degree.entry(feature_id.clone()).or_insert(0);
-        adjacency.entry(feature_id.clone()).or_default();
-
-        let mut dependencies = Vec::new();
-
-        // Add dependsOn dependencies
-        if let Some(ref depends_on) = feature_result.feature.depends_on {
-            dependencies.extend(depends_on.keys().cloned());
-        }
-
-        // Add installsAfter dependencies
-        if let Some(ref installs_after) = feature_result.feature.installs_after {
-            dependencies.extend(installs_after.iter().cloned());
-        }
-
-        debug!(
-            "Feature {} has {} dependencies: {:?}",
-            feature_id,
-            dependencies.len(),
-            dependencies
-        );
-
-        for dep_id in dependencies {
-            // Normalize the dependency ID to match the feature ID format
-            // Dependencies can be full URLs like "ghcr.io/devcontainers/features/common-utils"
-            // but feature IDs are just the name like "common-utils"
-            let normalized_dep_id = if dep_id.contains('/') {
-                // Extract the last component (feature name) from the URL
-                dep_id
-                    .split('/')
-                    .next_back()
-                    .unwrap_or(&dep_id)
-                    .split(':')
-                    .next()
-                    .unwrap_or(&dep_id)
-                    .to_string()
-            } else {
-                dep_id.clone()
-            };
-
-            // Only process dependencies that are in our feature set
-            if feature_map.contains_key(&normalized_dep_id) {
-                debug!(
-                    "  Adding edge: {} -> {} (from dependency: {})",
-                    normalized_dep_id, feature_id, dep_id
-                );
-                adjacency
-                    .entry(normalized_dep_id.clone())
-                    .or_default()
-                    .push(feature_id.clone());
-                *in_degree.entry(feature_id.clone()).or_default() += 1;
-            } else {
-                debug!(
-                    "  Dependency {} (normalized: {}) not found in feature set for {}",
-                    dep_id, normalized_dep_id, feature_id
-                );
-            }
-        }
-    }
-
-    // Kahn's algorithm: start with nodes that have no dependencies
-    // Sort by feature ID for deterministic ordering
-    let mut initial_zero_degree: Vec<String> = in_degree
-        .iter()
-        .filter(|(_, degree)| **degree == 0)
-        .map(|(id, _)| id.clone())
-        .collect();
-    initial_zero_degree.sort();
-
-    let mut queue: VecDeque<String> = initial_zero_degree.into_iter().collect();
-    let mut sorted: Vec<FeatureProcessResult> = Vec::new();
-
-    while let Some(current_id) = queue.pop_front() {
-        // Move the feature from the map to the sorted list
-        if let Some(feature_result) = feature_map.remove(&current_id) {
-            sorted.push(feature_result);
-        }
-
-        // Reduce in-degree for all dependent features
-        if let Some(dependents) = adjacency.get(&current_id) {
-            let mut newly_ready: Vec<String> = Vec::new();
-            for dependent_id in dependents {
-                if let Some(degree) = in_degree.get_mut(dependent_id) {
-                    *degree -= 1;
-                    if *degree == 0 {
-                        newly_ready.push(dependent_id.clone());
-                    }
-                }
-            }
-            // Sort for deterministic ordering
-            newly_ready.sort();
-            for id in newly_ready {
-                queue.push_back(id);
-            }
-        }
-    }
-
-    // Check for circular dependencies
-    if sorted.len() != in_degree.len() {
-        let remaining: Vec<String> = feature_map.keys().cloned().collect();
-        bail!(
-            "Circular dependency detected among features: {:?}",
-            remaining
-        );
-    }
-
-    debug!("Topologically sorted {} features", sorted.len());
-    for (i, feature) in sorted.iter().enumerate() {
-        debug!("  {}. {}", i + 1, feature.feature.id);
-    }
-
-    // Prioritize common-utils to be first if present and no dependencies prevent it
-    // common-utils is a foundational feature that other features often depend on
-    if let Some(common_utils_pos) = sorted
-        .iter()
-        .position(|f| f.feature.id.contains("common-utils"))
-        && common_utils_pos > 0
-    {
-        // Check if common-utils has ANY dependencies in the feature set
-        // If it does, they must all be before it in the sorted order (guaranteed by topo sort)
-        // We can only move it to position 0 if it has NO dependencies
-        let has_any_dependencies = {
-            let mut deps = Vec::new();
-
-            if let Some(ref depends_on) = sorted[common_utils_pos].feature.depends_on {
-                deps.extend(depends_on.keys());
-            }
-
-            if let Some(ref installs_after) = sorted[common_utils_pos].feature.installs_after {
-                deps.extend(installs_after.iter());
-            }
-
-            // Check if any of these dependencies are in our sorted feature list
-            deps.iter()
-                .any(|dep_id| sorted.iter().any(|f| &f.feature.id == *dep_id))
-        };
-
-        if !has_any_dependencies {
-            debug!("Moving common-utils to the beginning of the feature list");
-            let common_utils = sorted.remove(common_utils_pos);
-            sorted.insert(0, common_utils);
-
-            debug!("Reordered feature list with common-utils first:");
-            for (i, feature) in sorted.iter().enumerate() {
-                debug!("  {}. {}", i + 1, feature.feature.id);
-            }
-        } else {
-            debug!("common-utils has dependencies, keeping it in topological order");
-        }
-    }
-
-    Ok(sorted)
-}
-
-pub fn process_feature(feature_ref: &FeatureRef) -> anyhow::Result<FeatureProcessResult> {
+pub fn process_feature(
+    feature_ref: &FeatureRef,
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+) -> anyhow::Result<FeatureProcessResult> {
+    let mut feature_ref = feature_ref.clone();
+    let started = Instant::now();
     let relative_path = match &feature_ref.source {
-        Registry { registry } => download_feature(registry),
+        Registry { registry } => match download_feature(registry, client, policy) {
+            Result::Ok(path) => Ok(path),
+            // A renamed feature may no longer be published under its legacy ID
+            Err(error) => match resolve_legacy_id(registry, client, policy).unwrap_or_else(|e| {
+                debug!("Failed to resolve legacy ID {}: {:#}", registry.name, e);
+                None
+            }) {
+                Some(renamed) => {
+                    warn_renamed(registry, &renamed.name);
+                    let path = download_feature(&renamed, client, policy)?;
+                    feature_ref.source = Registry { registry: renamed };
+                    Ok(path)
+                }
+                None => Err(error),
+            },
+        },
         Local { path } => local_feature(path),
+        Tarball { url } => download_tarball_feature(url, policy),
     }?;
+    let fetch_time = started.elapsed();
 
     // Read devcontainer-feature.json if it exists to parse the Feature metadata
     let feature_json_path = relative_path.join("devcontainer-feature.json");
 
     if !feature_json_path.exists() {
-        bail!(
-            "Feature definition file not found: {}",
-            feature_json_path.display()
-        );
+        return Err(FeatureError::DefinitionNotFound(feature_json_path).into());
     }
 
     let feature_json_content = fs::read_to_string(&feature_json_path)?;
     let parsed_feature: Feature = serde_json::from_str(&feature_json_content)?;
 
+    // Continue with the new ID of a feature referenced by a legacy ID
+    if let Registry { registry } = &mut feature_ref.source
+        && registry.name != parsed_feature.id
+        && parsed_feature
+            .legacy_ids
+            .as_ref()
+            .is_some_and(|ids| ids.contains(&registry.name))
+    {
+        warn_renamed(registry, &parsed_feature.id);
+        registry.name = parsed_feature.id.clone();
+    }
+    if let Some(warning) = deprecation_warning(&parsed_feature) {
+        println!("{}", warning);
+    }
+
     Ok(FeatureProcessResult {
-        feature_ref: feature_ref.clone(),
+        feature_ref,
         feature: parsed_feature,
         path: relative_path,
+        fetch_time,
     })
 }
 
+/// Warn that a feature is referenced by its legacy ID
+fn warn_renamed(registry: &FeatureRegistry, new_name: &str) {
+    let renamed = FeatureRegistry {
+        name: new_name.to_string(),
+        ..registry.clone()
+    };
+    warn!(
+        "Feature {} was renamed to {}, update the reference in devcontainer.json",
+        registry_reference(registry),
+        registry_reference(&renamed)
+    );
+}
+
+/// Build the warning printed before a deprecated feature is installed
+fn deprecation_warning(feature: &Feature) -> Option<String> {
+    if !feature.deprecated.unwrap_or(false) {
+        return None;
+    }
+    let mut warning = format!(
+        "⚠️  Warning: Feature '{}' is deprecated and may stop working or be removed.",
+        feature.id
+    );
+    if let Some(url) = &feature.documentation_url {
+        warning.push_str(&format!("\n   See {} for a replacement.", url));
+    }
+    Some(warning)
+}
+
+/// Find the feature of the collection which was published under the name of
+/// `registry` before, using the `devcontainer-collection.json` published
+/// next to the features
+fn resolve_legacy_id(
+    registry: &FeatureRegistry,
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+) -> anyhow::Result<Option<FeatureRegistry>> {
+    if policy.offline {
+        return Ok(None);
+    }
+    let collection_repository = format!("{}/{}", registry.owner, registry.repository);
+    let token = client.token(&collection_repository)?;
+
+    let manifest = client
+        .manifest(&collection_repository, "latest", &token)
+        .with_context(|| {
+            format!(
+                "Failed to download the collection metadata of {}",
+                collection_repository
+            )
+        })?;
+    let layer = manifest
+        .layers()
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("No layers found in collection manifest"))?;
+    let mut collection = Vec::new();
+    client.blob(
+        &collection_repository,
+        layer.digest().as_ref(),
+        &token,
+        &mut collection,
+        &mut |_, _| {},
+    )?;
+    let collection: serde_json::Value = serde_json::from_slice(&collection)?;
+
+    Ok(
+        legacy_feature_id(&collection, &registry.name).map(|name| FeatureRegistry {
+            name,
+            ..registry.clone()
+        }),
+    )
+}
+
+/// Get the ID of the feature in a `devcontainer-collection.json` which lists
+/// `legacy_id` in its `legacyIds`
+fn legacy_feature_id(collection: &serde_json::Value, legacy_id: &str) -> Option<String> {
+    collection["features"]
+        .as_array()?
+        .iter()
+        .find(|feature| {
+            feature["legacyIds"]
+                .as_array()
+                .is_some_and(|ids| ids.iter().any(|id| id.as_str() == Some(legacy_id)))
+        })
+        .and_then(|feature| feature["id"].as_str())
+        .map(str::to_string)
+}
+
+/// Get the devcon directory in the user cache directory, containing the
+/// feature caches
+pub fn get_devcon_cache_dir() -> anyhow::Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?
+        .join("devcon"))
+}
+
 /// Get the cache directory for devcontainer features
 fn get_feature_cache_dir() -> anyhow::Result<std::path::PathBuf> {
-    let cache_dir =
-        dirs::cache_dir().ok_or_else(|| anyhow::anyhow!("Could not determine cache directory"))?;
-    let devcon_cache = cache_dir.join("devcon").join("features");
+    let devcon_cache = get_devcon_cache_dir()?.join("features");
     fs::create_dir_all(&devcon_cache)?;
     Ok(devcon_cache)
 }
 
+/// List references of all features present in the local feature cache
+///
+/// References are returned as `ghcr.io/{owner}/{repository}/{name}`, sorted
+/// and without version, since the cache is keyed by layer digest.
+pub fn cached_feature_references() -> anyhow::Result<Vec<String>> {
+    let cache_dir = get_feature_cache_dir()?;
+    let mut references = Vec::new();
+
+    let subdirectories = |path: &Path| -> Vec<PathBuf> {
+        fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    for owner in subdirectories(&cache_dir) {
+        for repository in subdirectories(&owner) {
+            for name in subdirectories(&repository) {
+                let segments: Vec<String> = [&owner, &repository, &name]
+                    .iter()
+                    .filter_map(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .collect();
+                references.push(format!("ghcr.io/{}", segments.join("/")));
+            }
+        }
+    }
+
+    references.sort();
+    Ok(references)
+}
+
 /// Get the versioned cache path for a specific feature based on layer SHA
 fn get_cached_feature_path(
     registry: &FeatureRegistry,
@@ -496,27 +641,225 @@ fn get_cached_feature_path(
     Ok(feature_cache)
 }
 
-/// Get local feature path
+/// Get the index file recording the layer digest a feature version resolved to
+///
+/// The index is written whenever a feature is resolved online and acts as a
+/// local lock: offline builds resolve `name:version` through it without
+/// asking the registry.
+fn get_feature_digest_index(registry: &FeatureRegistry) -> anyhow::Result<PathBuf> {
+    let cache_dir = get_feature_cache_dir()?;
+    Ok(cache_dir
+        .join(&registry.owner)
+        .join(&registry.repository)
+        .join(&registry.name)
+        .join(format!("{}.digest", registry.version)))
+}
+
+/// Get the lock file serializing downloads of a feature version
+fn get_feature_lock_path(registry: &FeatureRegistry) -> anyhow::Result<PathBuf> {
+    Ok(get_feature_digest_index(registry)?.with_extension("lock"))
+}
+
+/// Lock an entry of the feature cache, waiting while another devcon process
+/// holds it. The lock is released when the returned file is dropped.
+fn lock_cache_entry(lock_path: &Path, name: &str) -> anyhow::Result<File> {
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::create(lock_path)?;
+    match file.try_lock() {
+        Result::Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            info!(
+                "Waiting for another devcon process to cache feature {}",
+                name
+            );
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+    Ok(file)
+}
+
+/// Write a file by renaming a completed temporary file into place, so
+/// concurrent readers never see it partially written
+fn write_atomically(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid cache path {}", path.display()))?;
+    fs::create_dir_all(parent)?;
+    let mut file = tempfile::NamedTempFile::new_in(parent)?;
+    file.write_all(content)?;
+    file.persist(path)?;
+    Ok(())
+}
+
+/// Get the cached path of a feature version recorded by a previous online build
+fn cached_feature_path(registry: &FeatureRegistry) -> anyhow::Result<Option<PathBuf>> {
+    let Result::Ok(digest) = fs::read_to_string(get_feature_digest_index(registry)?) else {
+        return Ok(None);
+    };
+    // Older indexes record the shortened digest of the cache folder
+    let layer_sha = digest.trim().chars().take(12).collect::<String>();
+    let path = get_cached_feature_path(registry, &layer_sha)?;
+    Ok(path
+        .join("devcontainer-feature.json")
+        .exists()
+        .then_some(path))
+}
+
+/// Format the reference of a registry feature, e.g. `ghcr.io/devcontainers/features/node:1`
+pub fn registry_reference(registry: &FeatureRegistry) -> String {
+    format!(
+        "ghcr.io/{}/{}/{}:{}",
+        registry.owner, registry.repository, registry.name, registry.version
+    )
+}
+
+/// List references of all registry and tarball features which are not available offline
+///
+/// Local features are always available. Dependencies are only checked once
+/// the features declaring them are resolved.
+pub fn missing_cached_features(features: &[FeatureRef]) -> anyhow::Result<Vec<String>> {
+    let mut missing = Vec::new();
+    for feature_ref in features {
+        match &feature_ref.source {
+            Registry { registry } if cached_feature_path(registry)?.is_none() => {
+                missing.push(registry_reference(registry));
+            }
+            Tarball { url }
+                if !get_tarball_cache_path(url.as_bytes())?
+                    .join("devcontainer-feature.json")
+                    .exists() =>
+            {
+                missing.push(url.clone());
+            }
+            _ => {}
+        }
+    }
+    Ok(missing)
+}
+
+/// Get the cache path of a feature tarball, keyed by the SHA of its URL or content
+fn get_tarball_cache_path(key: &[u8]) -> anyhow::Result<PathBuf> {
+    let cache_dir = get_devcon_cache_dir()?.join("feature-tarballs");
+    fs::create_dir_all(&cache_dir)?;
+    let sha = format!("{:x}", Sha256::digest(key));
+    Ok(cache_dir.join(&sha[..12]))
+}
+
+/// Get local feature path, extracting it to the cache if it is an archive
 fn local_feature(path: &Path) -> anyhow::Result<PathBuf> {
     info!("Using local feature from path: {}", path.display());
-    path.canonicalize().map_err(|e| anyhow::anyhow!(e))
+    let path = path
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("Local feature {} not found: {}", path.display(), e))?;
+    if !path.is_file() {
+        return Ok(path);
+    }
+
+    let bytes = fs::read(&path)?;
+    let cache_path = get_tarball_cache_path(&bytes)?;
+    let _lock = lock_cache_entry(&cache_path.with_extension("lock"), &path.to_string_lossy())?;
+    if !cache_path.join("devcontainer-feature.json").exists() {
+        debug!("Extracting feature archive {}", path.display());
+        extract_tarball_to_cache(&bytes, &cache_path)?;
+    }
+    Ok(cache_path)
+}
+
+/// Download a feature tarball over HTTPS to cache, or use cached version if available
+fn download_tarball_feature(url: &str, policy: &DownloadPolicy) -> anyhow::Result<PathBuf> {
+    // Tarballs carry no signature to verify
+    if policy.trusted_keys.is_some() {
+        return Err(FeatureError::Signature {
+            name: url.to_string(),
+            source: anyhow::anyhow!(
+                "tarball features are not signed, reference the feature from an OCI registry"
+            ),
+        }
+        .into());
+    }
+    let cache_path = get_tarball_cache_path(url.as_bytes())?;
+    if cache_path.join("devcontainer-feature.json").exists() {
+        info!("Using cached feature: {}", url);
+        return Ok(cache_path);
+    }
+    if policy.offline {
+        return Err(FeatureError::NotCached(url.to_string()).into());
+    }
+
+    // Another build may have downloaded it while waiting for the lock
+    let _lock = lock_cache_entry(&cache_path.with_extension("lock"), url)?;
+    if cache_path.join("devcontainer-feature.json").exists() {
+        info!("Using cached feature: {}", url);
+        return Ok(cache_path);
+    }
+
+    info!("Downloading feature: {}", url);
+    let client = http_proxy::http_client(&policy.ca_certificates)?;
+    let bytes = policy
+        .download(|| client.get(url))
+        .map_err(|source| FeatureError::Download {
+            name: url.to_string(),
+            source,
+        })?;
+    extract_tarball_to_cache(&bytes, &cache_path)?;
+    Ok(cache_path)
+}
+
+/// Extract a feature tarball, gzip compressed or not, to the cache path
+fn extract_tarball_to_cache(bytes: &[u8], cache_path: &Path) -> anyhow::Result<()> {
+    let temp_directory = TempDir::new()?;
+    let extract_path = temp_directory.path().join("extract");
+    fs::create_dir_all(&extract_path)?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        tar::Archive::new(flate2::read::GzDecoder::new(bytes)).unpack(&extract_path)?;
+    } else {
+        tar::Archive::new(bytes).unpack(&extract_path)?;
+    }
+    move_to_cache(&extract_path, cache_path)
 }
 
 /// Download a feature from registry to cache, or use cached version if available
-fn download_feature(registry: &FeatureRegistry) -> anyhow::Result<PathBuf> {
+fn download_feature(
+    registry: &FeatureRegistry,
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+) -> anyhow::Result<PathBuf> {
+    // Offline, the version resolves through the index of a previous download
+    if policy.offline {
+        let Some(cached_feature_path) = cached_feature_path(registry)? else {
+            return Err(FeatureError::NotCached(registry_reference(registry)).into());
+        };
+        info!(
+            "Using cached feature: {} (version {}, offline)",
+            registry.name, registry.version
+        );
+        return Ok(cached_feature_path);
+    }
+
     // First, fetch the manifest to get the layer SHA
-    let (token, layer_digest) = fetch_manifest_and_layer_digest(registry)?;
+    let (token, layer) = fetch_feature_layer(registry, client, policy)?;
+    let layer_digest = layer.digest().to_string();
 
     // Extract SHA from digest (format: "sha256:abc123...")
-    let layer_sha = layer_digest
+    let full_sha = layer_digest
         .strip_prefix("sha256:")
-        .unwrap_or(&layer_digest)
+        .unwrap_or(&layer_digest);
+    let layer_sha = full_sha
         .chars()
         .take(12) // Use first 12 chars of SHA for shorter paths
         .collect::<String>();
 
     let cached_feature_path = get_cached_feature_path(registry, &layer_sha)?;
 
+    // Concurrent builds download each feature version once, the others wait
+    let _lock = lock_cache_entry(
+        &get_feature_lock_path(registry)?,
+        &registry_reference(registry),
+    )?;
+
     // Check if feature is already cached
     if !cached_feature_path.exists()
         || !cached_feature_path
@@ -527,103 +870,103 @@ fn download_feature(registry: &FeatureRegistry) -> anyhow::Result<PathBuf> {
             "Downloading feature: {} (version {}, SHA: {})",
             registry.name, registry.version, layer_sha
         );
-        download_and_cache_feature(registry, &cached_feature_path, &token, &layer_digest)?;
+        download_and_cache_feature(registry, &cached_feature_path, client, &token, &layer)?;
     } else {
         info!(
             "Using cached feature: {} (version {}, SHA: {})",
             registry.name, registry.version, layer_sha
         );
     }
+    write_atomically(&get_feature_digest_index(registry)?, full_sha.as_bytes())?;
 
     Ok(cached_feature_path)
 }
 
-/// Fetch the manifest and extract the layer digest (SHA)
-fn fetch_manifest_and_layer_digest(registry: &FeatureRegistry) -> anyhow::Result<(String, String)> {
-    let token_url = format!(
-        "https://{}/token?scope=repository:{}/{}:pull",
-        "ghcr.io", registry.owner, registry.repository
-    );
+/// Get the repository of a registry feature, e.g. `devcontainers/features/node`
+fn feature_repository(registry: &FeatureRegistry) -> String {
+    format!(
+        "{}/{}/{}",
+        registry.owner, registry.repository, registry.name
+    )
+}
 
-    let response = reqwest::blocking::get(&token_url)?;
-    if !response.status().is_success() {
-        bail!("Failed to get token for feature: {}", registry.name);
+/// Fetch a pull token and the layer descriptor of a feature version
+fn fetch_feature_layer(
+    registry: &FeatureRegistry,
+    client: &dyn OciClient,
+    policy: &DownloadPolicy,
+) -> anyhow::Result<(String, Descriptor)> {
+    let repository = feature_repository(registry);
+    let token = client.token(&format!("{}/{}", registry.owner, registry.repository))?;
+
+    // With verification, only the manifest the signature was checked for is used
+    let mut reference = registry.version.clone();
+    if let Some(keys) = &policy.trusted_keys {
+        reference = client
+            .manifest_digest(&repository, &registry.version, &token)
+            .with_context(|| {
+                format!("Failed to download manifest for feature: {}", registry.name)
+            })?;
+        keys.verify(client, &repository, &token, &reference)
+            .map_err(|source| FeatureError::Signature {
+                name: registry_reference(registry),
+                source,
+            })?;
     }
-    let json: serde_json::Value = response.json()?;
-    let token = json["token"]
-        .as_str()
-        .ok_or_else(|| {
-            anyhow::anyhow!("Token not found in response for feature: {}", registry.name)
-        })?
-        .to_string();
-
-    let manifest_url = format!(
-        "https://{}/v2/{}/{}/{}/manifests/{}",
-        "ghcr.io", registry.owner, registry.repository, registry.name, registry.version
-    );
 
-    let manifest_response = reqwest::blocking::Client::new()
-        .get(&manifest_url)
-        .bearer_auth(&token)
-        .header("Accept", "application/vnd.oci.image.manifest.v1+json")
-        .send()?;
-
-    if !manifest_response.status().is_success() {
-        bail!("Failed to download manifest for feature: {}", registry.name);
-    }
-    let manifest_json: serde_json::Value = manifest_response.json()?;
-    let manifest_str = serde_json::to_string(&manifest_json)?;
-    let reader = std::io::Cursor::new(manifest_str);
-    let manifest = oci_spec::image::ImageManifest::from_reader(reader)?;
+    let manifest = client
+        .manifest(&repository, &reference, &token)
+        .with_context(|| format!("Failed to download manifest for feature: {}", registry.name))?;
     let layer = manifest.layers().first().ok_or_else(|| {
         anyhow::anyhow!("No layers found in manifest for feature: {}", registry.name)
     })?;
 
-    Ok((token, layer.digest().to_string()))
+    Ok((token, layer.clone()))
 }
 
 /// Download and extract a feature to the cache directory
+///
+/// The layer is streamed to a temporary file and extracted from there, so
+/// large features are never held in memory.
 fn download_and_cache_feature(
     registry: &FeatureRegistry,
     cache_path: &std::path::Path,
+    client: &dyn OciClient,
     token: &str,
-    layer_digest: &str,
+    layer: &Descriptor,
 ) -> anyhow::Result<()> {
     let temp_directory = TempDir::new()?;
-
-    let layer_url = format!(
-        "https://{}/v2/{}/{}/{}/blobs/{}",
-        "ghcr.io", registry.owner, registry.repository, registry.name, layer_digest
-    );
-    let layer_response = reqwest::blocking::Client::new()
-        .get(&layer_url)
-        .bearer_auth(token)
-        .send()?;
-
-    if !layer_response.status().is_success() {
-        bail!("Failed to download layer for feature: {}", registry.name);
-    }
-    let layer_bytes = layer_response.bytes()?;
-
-    // Re-fetch manifest to get media type (we only got the digest earlier)
-    let manifest_url = format!(
-        "https://{}/v2/{}/{}/{}/manifests/{}",
-        "ghcr.io", registry.owner, registry.repository, registry.name, registry.version
+    let layer_path = temp_directory.path().join("feature.layer");
+
+    let bar = ProgressBar::new(layer.size())
+        .with_message(format!("Downloading {}", registry.name))
+        .with_style(ProgressStyle::with_template(
+            "{msg} {bytes}/{total_bytes} {wide_bar}",
+        )?);
+    let mut layer_file = File::create(&layer_path)?;
+    let downloaded = client.blob(
+        &feature_repository(registry),
+        layer.digest().as_ref(),
+        token,
+        &mut layer_file,
+        &mut |bytes, total| {
+            if let Some(total) = total {
+                bar.set_length(total);
+            }
+            bar.set_position(bytes);
+        },
     );
-    let manifest_response = reqwest::blocking::Client::new()
-        .get(&manifest_url)
-        .bearer_auth(token)
-        .header("Accept", "application/vnd.oci.image.manifest.v1+json")
-        .send()?;
-    let manifest_json: serde_json::Value = manifest_response.json()?;
-    let manifest_str = serde_json::to_string(&manifest_json)?;
-    let reader = std::io::Cursor::new(manifest_str);
-    let manifest = oci_spec::image::ImageManifest::from_reader(reader)?;
-    let layer = manifest.layers().first().ok_or_else(|| {
-        anyhow::anyhow!("No layers found in manifest for feature: {}", registry.name)
+    bar.finish_and_clear();
+    downloaded.map_err(|source| FeatureError::Download {
+        name: registry.name.clone(),
+        source,
     })?;
+    drop(layer_file);
 
-    let extract_path = match layer.media_type() {
+    let layer_file = File::open(&layer_path)?;
+    let extract_path = temp_directory.path().join("extract");
+    fs::create_dir_all(&extract_path)?;
+    match layer.media_type() {
         oci_spec::image::MediaType::Other(str) => match str.as_str() {
             "application/vnd.devcontainers.layer.v1+tar"
             | "application/vnd.oci.image.layer.v1.tar" => {
@@ -631,16 +974,7 @@ fn download_and_cache_feature(
                     "Extracting uncompressed layer for feature: {}",
                     registry.name
                 );
-                let temp_file = temp_directory.path().join("feature.tar");
-                fs::write(&temp_file, &layer_bytes)?;
-
-                let feature_archive = File::open(&temp_file)?;
-                let mut archive = tar::Archive::new(feature_archive);
-                let extract_path = temp_directory.path().join("extract");
-                fs::create_dir_all(&extract_path)?;
-                archive.unpack(&extract_path).unwrap();
-
-                extract_path
+                tar::Archive::new(layer_file).unpack(&extract_path)?;
             }
             "application/vnd.devcontainers.layer.v1+tar+gzip"
             | "application/vnd.oci.image.layer.v1.tar+gzip" => {
@@ -648,32 +982,24 @@ fn download_and_cache_feature(
                     "Extracting gzip compressed layer for feature: {}",
                     registry.name
                 );
-                let temp_file = temp_directory.path().join("feature.tar.gz");
-                fs::write(&temp_file, &layer_bytes)?;
-
-                let feature_archive = File::open(&temp_file)?;
-                let decompressor = flate2::read::GzDecoder::new(feature_archive);
-                let mut archive = tar::Archive::new(decompressor);
-                let extract_path = temp_directory.path().join("extract");
-                fs::create_dir_all(&extract_path)?;
-                archive.unpack(&extract_path).unwrap();
-
-                extract_path
+                tar::Archive::new(flate2::read::GzDecoder::new(layer_file))
+                    .unpack(&extract_path)?;
             }
             _ => {
-                bail!(
-                    "Unsupported layer media type for feature: {}, media type: {}",
-                    registry.name,
-                    str
-                );
+                return Err(FeatureError::UnsupportedMediaType {
+                    name: registry.name.clone(),
+                    media_type: str.clone(),
+                }
+                .into());
             }
         },
 
-        _ => {
-            bail!(
-                "Unsupported layer media type for feature: {}",
-                registry.name
-            );
+        media_type => {
+            return Err(FeatureError::UnsupportedMediaType {
+                name: registry.name.clone(),
+                media_type: media_type.to_string(),
+            }
+            .into());
         }
     };
 
@@ -683,8 +1009,17 @@ fn download_and_cache_feature(
         extract_path.display()
     );
 
-    // Move extracted feature to cache path
-    fs::create_dir_all(cache_path)?;
+    move_to_cache(&extract_path, cache_path)
+}
+
+/// Move an extracted feature to its cache path. It is staged next to it and
+/// renamed, so an interrupted download never leaves a partial feature
+fn move_to_cache(extract_path: &Path, cache_path: &Path) -> anyhow::Result<()> {
+    let cache_parent = cache_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid feature cache path"))?;
+    fs::create_dir_all(cache_parent)?;
+    let staging_directory = TempDir::new_in(cache_parent)?;
 
     let mut options = fs_extra::dir::CopyOptions::new();
     options.overwrite = true;
@@ -695,9 +1030,15 @@ fn download_and_cache_feature(
         "Copying extracted feature to cache path: {}",
         cache_path.display()
     );
-    fs_extra::dir::copy(&extract_path, cache_path, &options)
+    fs_extra::dir::copy(extract_path, staging_directory.path(), &options)
         .map_err(|e| anyhow::anyhow!("Failed to copy extracted feature: {}", e))?;
 
+    // Left over by versions which copied into the cache directly
+    if cache_path.exists() {
+        fs::remove_dir_all(cache_path)?;
+    }
+    fs::rename(staging_directory.path(), cache_path)?;
+
     Ok(())
 }
 
@@ -740,263 +1081,328 @@ pub fn clear_feature_cache_for(owner: &str, repository: &str, name: &str) -> any
 #[cfg(test)]
 mod tests {
     use crate::devcontainer::{FeatureRegistryType, FeatureSource};
+    use crate::driver::oci::mock::{MockOciClient, SigningKey};
+    use crate::driver::signature::TrustedKeys;
 
     use super::*;
     use tempfile::tempdir;
 
     #[test]
-    fn test_download_feature() {
-        let registry = FeatureRegistry {
-            owner: "devcontainers".to_string(),
+    fn test_extract_tarball_to_cache() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let definition = br#"{"id": "go", "version": "1.0.0"}"#;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(definition.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "./devcontainer-feature.json", &definition[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let cache = tempdir().unwrap();
+        let cache_path = cache.path().join("0123456789ab");
+        extract_tarball_to_cache(&archive, &cache_path).unwrap();
+        assert_eq!(
+            fs::read(cache_path.join("devcontainer-feature.json")).unwrap(),
+            definition
+        );
+    }
+
+    /// A registry feature of the devcon-test namespace, never published on ghcr.io
+    fn test_registry(name: &str) -> FeatureRegistry {
+        FeatureRegistry {
+            owner: "devcon-test".to_string(),
             repository: "features".to_string(),
-            name: "node".to_string(),
+            name: name.to_string(),
             version: "1.0.0".to_string(),
             registry_type: FeatureRegistryType::Ghcr,
-        };
-        let temp_dir = tempdir().unwrap();
-        let result = download_feature(&registry);
-        assert!(
-            result.is_ok(),
-            "Failed to download feature: {:?}",
-            result.err()
+        }
+    }
+
+    /// A mock registry serving `name` with a `version` option
+    fn mock_feature(client: MockOciClient, name: &str) -> MockOciClient {
+        let definition = serde_json::json!({
+            "id": name,
+            "version": "1.0.0",
+            "options": { "version": { "type": "string", "default": "lts" } }
+        })
+        .to_string();
+        client.with_feature(
+            &format!("devcon-test/features/{}", name),
+            "1.0.0",
+            &[
+                ("./devcontainer-feature.json", &definition),
+                ("./install.sh", "#!/bin/sh\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_download_feature() {
+        let client = mock_feature(MockOciClient::new(), "mock-download");
+        let registry = test_registry("mock-download");
+
+        let path = download_feature(&registry, &client, &DownloadPolicy::default()).unwrap();
+        assert!(path.join("devcontainer-feature.json").exists());
+        assert!(path.join("install.sh").exists());
+        assert_eq!(cached_feature_path(&registry).unwrap(), Some(path));
+
+        let requests = client.requests();
+        assert_eq!(requests[0], "token devcon-test/features");
+        assert_eq!(
+            requests[1],
+            "manifest devcon-test/features/mock-download:1.0.0"
+        );
+    }
+
+    #[test]
+    fn test_download_gzip_feature() {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let definition = br#"{"id": "mock-gzip", "version": "1.0.0"}"#;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(definition.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "./devcontainer-feature.json", &definition[..])
+            .unwrap();
+        let layer = builder.into_inner().unwrap().finish().unwrap();
+        let client = MockOciClient::new().with_artifact(
+            "devcon-test/features/mock-gzip",
+            "1.0.0",
+            "application/vnd.devcontainers.layer.v1+tar+gzip",
+            layer,
+        );
+
+        let path = download_feature(
+            &test_registry("mock-gzip"),
+            &client,
+            &DownloadPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read(path.join("devcontainer-feature.json")).unwrap(),
+            definition
         );
-        let relative_path = result.unwrap();
-        let feature_path = temp_dir.path().join(&relative_path);
-        assert!(feature_path.exists());
+    }
+
+    #[test]
+    fn test_concurrent_downloads_share_cache() {
+        let client = mock_feature(MockOciClient::new(), "mock-concurrent");
+        let registry = test_registry("mock-concurrent");
+
+        let paths: Vec<PathBuf> = std::thread::scope(|scope| {
+            let downloads: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        download_feature(&registry, &client, &DownloadPolicy::default()).unwrap()
+                    })
+                })
+                .collect();
+            downloads.into_iter().map(|d| d.join().unwrap()).collect()
+        });
+
+        assert!(paths.iter().all(|path| path == &paths[0]));
+        assert!(paths[0].join("devcontainer-feature.json").exists());
+        let blobs = client
+            .requests()
+            .iter()
+            .filter(|r| r.starts_with("blob "))
+            .count();
+        assert!(blobs <= 1, "feature was downloaded {} times", blobs);
     }
 
     #[test]
     fn test_process_feature() {
+        let client = mock_feature(MockOciClient::new(), "mock-process");
         let feature_ref = FeatureRef::new(FeatureSource::Registry {
-            registry: FeatureRegistry {
-                owner: "devcontainers".to_string(),
-                repository: "features".to_string(),
-                name: "node".to_string(),
-                version: "1.0.0".to_string(),
-                registry_type: FeatureRegistryType::Ghcr,
-            },
+            registry: test_registry("mock-process"),
         });
-        let result = process_feature(&feature_ref);
-        assert!(
-            result.is_ok(),
-            "Failed to download feature: {:?}",
-            result.err()
-        );
-        let feature_result = result.unwrap();
+
+        let feature_result =
+            process_feature(&feature_ref, &client, &DownloadPolicy::default()).unwrap();
+        // The full layer digest is reported, not the shortened cache folder
+        let digest = feature_result.digest().unwrap();
+        assert_eq!(digest.len(), "sha256:".len() + 64);
+        assert!(digest.contains(&*feature_result.path.file_name().unwrap().to_string_lossy()));
         let feature = feature_result.feature;
-        // Check that default option exists
-        if let Some(ref options) = feature.options {
-            assert!(options.contains_key("version"));
+        assert_eq!(feature.id, "mock-process");
+        assert!(feature.options.unwrap().contains_key("version"));
+    }
+
+    /// A policy verifying signatures with `key`
+    fn verifying_policy(key: &SigningKey) -> DownloadPolicy {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("cosign.pub");
+        fs::write(&path, key.public_key_pem()).unwrap();
+        DownloadPolicy {
+            trusted_keys: Some(TrustedKeys::load(&[path]).unwrap()),
+            ..DownloadPolicy::default()
         }
     }
 
     #[test]
-    fn test_process_feature_default() {
+    fn test_process_feature_signed() {
+        let key = SigningKey::generate();
+        let client = mock_feature(MockOciClient::new(), "mock-signed");
+        let repository = "devcon-test/features/mock-signed";
+        let digest = client.manifest_digest(repository, "1.0.0", "").unwrap();
+        let (payload, signature) = key.sign(&digest);
+        let client = client.with_signature(repository, &digest, payload, &signature);
         let feature_ref = FeatureRef::new(FeatureSource::Registry {
-            registry: FeatureRegistry {
-                owner: "devcontainers".to_string(),
-                repository: "features".to_string(),
-                name: "node".to_string(),
-                version: "1.0.0".to_string(),
-                registry_type: FeatureRegistryType::Ghcr,
-            },
+            registry: test_registry("mock-signed"),
         });
-        let result = process_feature(&feature_ref);
+
+        let feature_result =
+            process_feature(&feature_ref, &client, &verifying_policy(&key)).unwrap();
+        assert_eq!(feature_result.feature.id, "mock-signed");
+        // The layer is taken from the verified manifest
         assert!(
-            result.is_ok(),
-            "Failed to download feature: {:?}",
-            result.err()
+            client
+                .requests()
+                .contains(&format!("manifest {}:{}", repository, digest))
         );
     }
 
     #[test]
-    fn test_topological_sort_simple() {
-        // Create mock features with dependencies
-        let mut features = HashMap::new();
-
-        // Feature A (no dependencies)
-        let feature_a = create_mock_feature("feature-a", None, None);
-        features.insert("feature-a".to_string(), feature_a);
-
-        // Feature B depends on A
-        let mut depends_on_b = HashMap::new();
-        depends_on_b.insert("feature-a".to_string(), serde_json::json!("1.0.0"));
-        let feature_b = create_mock_feature("feature-b", Some(depends_on_b), None);
-        features.insert("feature-b".to_string(), feature_b);
-
-        // Feature C depends on B
-        let mut depends_on_c = HashMap::new();
-        depends_on_c.insert("feature-b".to_string(), serde_json::json!("1.0.0"));
-        let feature_c = create_mock_feature("feature-c", Some(depends_on_c), None);
-        features.insert("feature-c".to_string(), feature_c);
-
-        let result = topological_sort(features);
+    fn test_process_feature_unsigned() {
+        let key = SigningKey::generate();
+        let client = mock_feature(MockOciClient::new(), "mock-unsigned");
+        let feature_ref = FeatureRef::new(FeatureSource::Registry {
+            registry: test_registry("mock-unsigned"),
+        });
+
+        let error = process_feature(&feature_ref, &client, &verifying_policy(&key)).unwrap_err();
+        let message = format!("{:#}", error);
+        assert!(message.contains("Signature verification of feature"));
+        assert!(message.contains("No cosign signature found"));
         assert!(
-            result.is_ok(),
-            "Topological sort failed: {:?}",
-            result.err()
+            !client
+                .requests()
+                .iter()
+                .any(|request| request.starts_with("blob"))
         );
 
-        let sorted = result.unwrap();
-        assert_eq!(sorted.len(), 3);
-
-        // Verify order: A should come before B, B should come before C
-        let ids: Vec<String> = sorted.iter().map(|f| f.feature.id.clone()).collect();
-        let pos_a = ids.iter().position(|id| id == "feature-a").unwrap();
-        let pos_b = ids.iter().position(|id| id == "feature-b").unwrap();
-        let pos_c = ids.iter().position(|id| id == "feature-c").unwrap();
-
-        assert!(pos_a < pos_b, "Feature A should come before B");
-        assert!(pos_b < pos_c, "Feature B should come before C");
+        let tarball = FeatureRef::new(FeatureSource::Tarball {
+            url: "https://example.com/feature.tgz".to_string(),
+        });
+        let error = process_feature(&tarball, &client, &verifying_policy(&key)).unwrap_err();
+        assert!(format!("{:#}", error).contains("tarball features are not signed"));
     }
 
     #[test]
-    fn test_topological_sort_installs_after() {
-        let mut features = HashMap::new();
-
-        // Feature A
-        let feature_a = create_mock_feature("feature-a", None, None);
-        features.insert("feature-a".to_string(), feature_a);
-
-        // Feature B installs after A
-        let installs_after_b = vec!["feature-a".to_string()];
-        let feature_b = create_mock_feature("feature-b", None, Some(installs_after_b));
-        features.insert("feature-b".to_string(), feature_b);
-
-        let result = topological_sort(features);
-        assert!(result.is_ok());
-
-        let sorted = result.unwrap();
-        let ids: Vec<String> = sorted.iter().map(|f| f.feature.id.clone()).collect();
-        let pos_a = ids.iter().position(|id| id == "feature-a").unwrap();
-        let pos_b = ids.iter().position(|id| id == "feature-b").unwrap();
+    fn test_process_feature_not_published() {
+        let feature_ref = FeatureRef::new(FeatureSource::Registry {
+            registry: test_registry("mock-missing"),
+        });
 
-        assert!(pos_a < pos_b, "Feature A should come before B");
+        let error = process_feature(
+            &feature_ref,
+            &MockOciClient::new(),
+            &DownloadPolicy::default(),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Failed to download manifest for feature: mock-missing")
+        );
     }
 
     #[test]
-    fn test_topological_sort_circular_dependency() {
-        let mut features = HashMap::new();
-
-        // Feature A depends on B
-        let mut depends_on_a = HashMap::new();
-        depends_on_a.insert("feature-b".to_string(), serde_json::json!("1.0.0"));
-        let feature_a = create_mock_feature("feature-a", Some(depends_on_a), None);
-        features.insert("feature-a".to_string(), feature_a);
+    fn test_process_feature_legacy_id() {
+        let collection = serde_json::json!({
+            "features": [
+                { "id": "mock-renamed", "version": "1.0.0", "legacyIds": ["mock-legacy"] }
+            ]
+        });
+        let client = mock_feature(MockOciClient::new(), "mock-renamed").with_artifact(
+            "devcon-test/features",
+            "latest",
+            "application/vnd.devcontainers.collection.layer.v1+json",
+            collection.to_string().into_bytes(),
+        );
+        let feature_ref = FeatureRef::new(FeatureSource::Registry {
+            registry: test_registry("mock-legacy"),
+        });
 
-        // Feature B depends on A (circular!)
-        let mut depends_on_b = HashMap::new();
-        depends_on_b.insert("feature-a".to_string(), serde_json::json!("1.0.0"));
-        let feature_b = create_mock_feature("feature-b", Some(depends_on_b), None);
-        features.insert("feature-b".to_string(), feature_b);
+        let feature_result =
+            process_feature(&feature_ref, &client, &DownloadPolicy::default()).unwrap();
+        assert_eq!(feature_result.feature.id, "mock-renamed");
+        assert_eq!(feature_result.feature_ref.source.name(), "mock-renamed");
+    }
 
-        let result = topological_sort(features);
-        assert!(result.is_err(), "Should detect circular dependency");
+    #[test]
+    fn test_offline_feature_not_cached() {
+        let registry = FeatureRegistry {
+            owner: "devcon-test".to_string(),
+            repository: "features".to_string(),
+            name: "never-downloaded".to_string(),
+            version: "1.0.0".to_string(),
+            registry_type: FeatureRegistryType::Ghcr,
+        };
+        let policy = DownloadPolicy {
+            offline: true,
+            ..DownloadPolicy::default()
+        };
 
-        let err_msg = result.unwrap_err().to_string();
+        let error = download_feature(&registry, &MockOciClient::new(), &policy).unwrap_err();
         assert!(
-            err_msg.contains("Circular dependency"),
-            "Error should mention circular dependency"
+            error
+                .to_string()
+                .contains("ghcr.io/devcon-test/features/never-downloaded:1.0.0")
+        );
+
+        let temp_dir = tempdir().unwrap();
+        let features = vec![
+            FeatureRef::new(FeatureSource::Registry { registry }),
+            FeatureRef::new(FeatureSource::Local {
+                path: temp_dir.path().to_path_buf(),
+            }),
+        ];
+        assert_eq!(
+            missing_cached_features(&features).unwrap(),
+            vec!["ghcr.io/devcon-test/features/never-downloaded:1.0.0"]
         );
     }
 
     #[test]
-    fn test_topological_sort_diamond_dependency() {
-        // Diamond pattern: D depends on B and C, both B and C depend on A
-        let mut features = HashMap::new();
-
-        // Feature A (base)
-        let feature_a = create_mock_feature("feature-a", None, None);
-        features.insert("feature-a".to_string(), feature_a);
-
-        // Feature B depends on A
-        let mut depends_on_b = HashMap::new();
-        depends_on_b.insert("feature-a".to_string(), serde_json::json!("1.0.0"));
-        let feature_b = create_mock_feature("feature-b", Some(depends_on_b), None);
-        features.insert("feature-b".to_string(), feature_b);
-
-        // Feature C depends on A
-        let mut depends_on_c = HashMap::new();
-        depends_on_c.insert("feature-a".to_string(), serde_json::json!("1.0.0"));
-        let feature_c = create_mock_feature("feature-c", Some(depends_on_c), None);
-        features.insert("feature-c".to_string(), feature_c);
-
-        // Feature D depends on B and C
-        let mut depends_on_d = HashMap::new();
-        depends_on_d.insert("feature-b".to_string(), serde_json::json!("1.0.0"));
-        depends_on_d.insert("feature-c".to_string(), serde_json::json!("1.0.0"));
-        let feature_d = create_mock_feature("feature-d", Some(depends_on_d), None);
-        features.insert("feature-d".to_string(), feature_d);
-
-        let result = topological_sort(features);
-        assert!(result.is_ok());
-
-        let sorted = result.unwrap();
-        let ids: Vec<String> = sorted.iter().map(|f| f.feature.id.clone()).collect();
-
-        let pos_a = ids.iter().position(|id| id == "feature-a").unwrap();
-        let pos_b = ids.iter().position(|id| id == "feature-b").unwrap();
-        let pos_c = ids.iter().position(|id| id == "feature-c").unwrap();
-        let pos_d = ids.iter().position(|id| id == "feature-d").unwrap();
-
-        // A must come before both B and C
-        assert!(pos_a < pos_b, "A should come before B");
-        assert!(pos_a < pos_c, "A should come before C");
-        // Both B and C must come before D
-        assert!(pos_b < pos_d, "B should come before D");
-        assert!(pos_c < pos_d, "C should come before D");
-    }
-
-    // Helper function to create mock feature results
-    fn create_mock_feature(
-        id: &str,
-        depends_on: Option<HashMap<String, serde_json::Value>>,
-        installs_after: Option<Vec<String>>,
-    ) -> FeatureProcessResult {
-        use std::path::PathBuf;
-
-        let feature = crate::feature::Feature {
-            id: id.to_string(),
-            version: "1.0.0".to_string(),
-            name: Some(format!("Mock {}", id)),
-            description: None,
-            documentation_url: None,
-            license_url: None,
-            keywords: None,
-            options: None,
-            installs_after,
-            depends_on,
-            deprecated: None,
-            legacy_ids: None,
-            cap_add: None,
-            security_opt: None,
-            privileged: None,
-            init: None,
-            entrypoint: None,
-            mounts: None,
-            container_env: None,
-            customizations: None,
-            on_create_command: None,
-            update_content_command: None,
-            post_create_command: None,
-            post_start_command: None,
-            post_attach_command: None,
-        };
-
-        let feature_ref = FeatureRef::new(FeatureSource::Registry {
-            registry: FeatureRegistry {
-                owner: "test".to_string(),
-                repository: "features".to_string(),
-                name: id.to_string(),
-                version: "1.0.0".to_string(),
-                registry_type: FeatureRegistryType::Ghcr,
-            },
+    fn test_legacy_feature_id() {
+        let collection = serde_json::json!({
+            "sourceInformation": { "source": "devcontainer-cli" },
+            "features": [
+                { "id": "node", "version": "1.0.0" },
+                {
+                    "id": "docker-outside-of-docker",
+                    "version": "1.0.0",
+                    "legacyIds": ["docker-from-docker"]
+                }
+            ]
         });
+        assert_eq!(
+            legacy_feature_id(&collection, "docker-from-docker").as_deref(),
+            Some("docker-outside-of-docker")
+        );
+        assert_eq!(legacy_feature_id(&collection, "node"), None);
+        assert_eq!(legacy_feature_id(&serde_json::json!({}), "node"), None);
+    }
 
-        FeatureProcessResult {
-            feature_ref,
-            feature,
-            path: PathBuf::from(format!("/tmp/{}", id)),
-        }
+    #[test]
+    fn test_deprecation_warning() {
+        let mut feature: Feature =
+            serde_json::from_value(serde_json::json!({"id": "feature-a", "version": "1.0.0"}))
+                .unwrap();
+        assert_eq!(deprecation_warning(&feature), None);
+
+        feature.deprecated = Some(true);
+        feature.documentation_url = Some("https://example.com/feature-a".to_string());
+        let warning = deprecation_warning(&feature).unwrap();
+        assert!(warning.contains("Feature 'feature-a' is deprecated"));
+        assert!(warning.contains("https://example.com/feature-a"));
     }
 }
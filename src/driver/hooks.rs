@@ -0,0 +1,240 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Host Hooks
+//!
+//! Users can run their own scripts on the host when devcon does something,
+//! e.g. to connect a VPN before a container starts, create a tmux session for
+//! it or send a notification when a port is forwarded. Hooks are configured as
+//! `hooks.preUp`, `hooks.postUp`, `hooks.preShell` and `hooks.portForwarded`
+//! and run with `sh -c` (`cmd /C` on Windows).
+//!
+//! Hooks run in the workspace folder if it is known. The context of the event
+//! is passed as environment variables:
+//!
+//! - `DEVCON_HOOK`: name of the hook, e.g. `postUp`
+//! - `DEVCON_WORKSPACE`: name of the workspace
+//! - `DEVCON_WORKSPACE_PATH`: host path of the workspace, if known
+//! - `DEVCON_CONTAINER_ID`: ID of the container, if known
+//! - `DEVCON_PORT`: forwarded port (`portForwarded` only)
+//!
+//! A failing `pre` hook aborts the command, failures of the other hooks are
+//! only logged.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+/// Host event a hook is run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Before `devcon up` builds and starts the container
+    PreUp,
+    /// After `devcon up` started the container
+    PostUp,
+    /// Before `devcon shell` opens a shell
+    PreShell,
+    /// After the control server started forwarding a port
+    PortForwarded,
+}
+
+impl Hook {
+    /// Name of the hook in the `hooks` config section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PreUp => "preUp",
+            Self::PostUp => "postUp",
+            Self::PreShell => "preShell",
+            Self::PortForwarded => "portForwarded",
+        }
+    }
+
+    /// Returns the configured command of the hook, if any.
+    pub fn command(self, config: &Config) -> Option<String> {
+        config
+            .hooks
+            .as_ref()
+            .and_then(|hooks| match self {
+                Self::PreUp => hooks.pre_up.clone(),
+                Self::PostUp => hooks.post_up.clone(),
+                Self::PreShell => hooks.pre_shell.clone(),
+                Self::PortForwarded => hooks.port_forwarded.clone(),
+            })
+            .filter(|command| !command.trim().is_empty())
+    }
+}
+
+/// Context of the event a hook is run on.
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    /// Name of the workspace
+    pub workspace: String,
+    /// Host path of the workspace, if known
+    pub workspace_path: Option<PathBuf>,
+    /// ID of the container, if known
+    pub container_id: Option<String>,
+    /// Forwarded port
+    pub port: Option<u16>,
+}
+
+impl HookContext {
+    /// Environment variables passing the context to the hook.
+    fn env(&self, hook: Hook) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("DEVCON_HOOK", hook.name().to_string()),
+            ("DEVCON_WORKSPACE", self.workspace.clone()),
+        ];
+        if let Some(path) = &self.workspace_path {
+            env.push(("DEVCON_WORKSPACE_PATH", path.to_string_lossy().to_string()));
+        }
+        if let Some(container_id) = &self.container_id {
+            env.push(("DEVCON_CONTAINER_ID", container_id.clone()));
+        }
+        if let Some(port) = self.port {
+            env.push(("DEVCON_PORT", port.to_string()));
+        }
+        env
+    }
+}
+
+/// Runs the hook if it is configured and waits for it to finish.
+///
+/// # Errors
+///
+/// Returns an error if the hook cannot be started or exits unsuccessfully.
+pub fn run(config: &Config, hook: Hook, context: &HookContext) -> Result<()> {
+    let Some(command) = hook.command(config) else {
+        return Ok(());
+    };
+    run_command(&command, hook, context)
+}
+
+/// Runs the hook if it is configured, logging a failure instead of failing.
+pub fn run_logged(config: &Config, hook: Hook, context: &HookContext) {
+    if let Err(e) = run(config, hook, context) {
+        warn!("{:#}", e);
+    }
+}
+
+/// Runs `command` as `hook` on a background thread, logging a failure.
+pub fn spawn(command: String, hook: Hook, context: HookContext) {
+    std::thread::spawn(move || {
+        if let Err(e) = run_command(&command, hook, &context) {
+            warn!("{:#}", e);
+        }
+    });
+}
+
+fn run_command(command: &str, hook: Hook, context: &HookContext) -> Result<()> {
+    debug!("Running {} hook: {}", hook.name(), command);
+    let mut shell = shell(command);
+    if let Some(path) = &context.workspace_path {
+        shell.current_dir(path);
+    }
+    let status = shell
+        .envs(context.env(hook))
+        .status()
+        .with_context(|| format!("Failed to run the {} hook", hook.name()))?;
+    if !status.success() {
+        bail!("The {} hook failed with {}", hook.name(), status);
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::HooksConfig;
+
+    use super::*;
+
+    fn config(hooks: HooksConfig) -> Config {
+        Config {
+            hooks: Some(hooks),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_unconfigured_hook_is_skipped() {
+        let config = Config::default();
+        assert!(Hook::PreUp.command(&config).is_none());
+        assert!(run(&config, Hook::PreUp, &HookContext::default()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_receives_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("hook.txt");
+        let config = config(HooksConfig {
+            port_forwarded: Some(format!(
+                "echo \"$DEVCON_HOOK $DEVCON_WORKSPACE $DEVCON_PORT\" > {}",
+                output.display()
+            )),
+            ..Default::default()
+        });
+        let context = HookContext {
+            workspace: "demo".to_string(),
+            port: Some(3000),
+            ..Default::default()
+        };
+
+        run(&config, Hook::PortForwarded, &context).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(output).unwrap(),
+            "portForwarded demo 3000\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_hook() {
+        let config = config(HooksConfig {
+            pre_shell: Some("exit 3".to_string()),
+            ..Default::default()
+        });
+        let error = run(&config, Hook::PreShell, &HookContext::default()).unwrap_err();
+        assert!(
+            error.to_string().contains("preShell hook failed"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,482 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Image Metadata
+//!
+//! Prebuilt images carry the configuration of the features installed into
+//! them, and of the devcontainer.json they were built from, in the
+//! `devcontainer.metadata` label: a JSON array of entries holding a subset of
+//! the devcontainer.json properties.
+//!
+//! When starting a container the entries are merged with the devcontainer.json
+//! of the workspace, which acts as the last entry, following the spec:
+//!
+//! - Mounts are collected, a later mount replaces an earlier one with the same target
+//! - Container environment variables are merged, later values win
+//! - Capabilities are collected without duplicates
+//! - Privileged mode and the init process are enabled if any entry requests them
+//! - Entrypoints are collected and started in entry order
+//! - Lifecycle commands are collected and run in entry order, so feature
+//!   commands run before the devcontainer.json ones
+//!
+//! Images built by devcon carry the label as well, holding the entries of the
+//! base image, one entry per installed feature and the devcontainer.json, so
+//! other tools like the reference CLI can use them. The base image label is
+//! read when starting instead, as the workspace entries come from the current
+//! configuration and features.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::devcontainer::{Devcontainer, LifecycleCommand, Mount};
+use crate::driver::feature_process::FeatureProcessResult;
+use crate::driver::vscode::VscodeCustomizations;
+
+/// Label holding the devcontainer metadata of an image.
+pub const METADATA_LABEL: &str = "devcontainer.metadata";
+
+/// One entry of the metadata label, contributed by a feature or a devcontainer.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataEntry {
+    /// Feature id, `None` for devcontainer.json entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mounts: Option<Vec<Mount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_env: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cap_add: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub init: Option<bool>,
+    /// Script started with the container, only set by features
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_create_command: Option<LifecycleCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_content_command: Option<LifecycleCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_create_command: Option<LifecycleCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_start_command: Option<LifecycleCommand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_attach_command: Option<LifecycleCommand>,
+    /// Tool-specific customizations, e.g. the VS Code extensions to install
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customizations: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl MetadataEntry {
+    /// Creates the entry of an installed feature, identified by its reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the feature properties cannot be converted.
+    pub fn from_feature(feature_result: &FeatureProcessResult) -> anyhow::Result<Self> {
+        // Features use the same property names as metadata entries
+        let mut entry: Self =
+            serde_json::from_value(serde_json::to_value(&feature_result.feature)?)
+                .context("Failed to convert feature properties")?;
+        entry.id = Some(feature_result.reference());
+        Ok(entry)
+    }
+}
+
+impl From<&Devcontainer> for MetadataEntry {
+    fn from(devcontainer: &Devcontainer) -> Self {
+        Self {
+            id: None,
+            mounts: devcontainer.mounts.clone(),
+            container_env: devcontainer.container_env.clone(),
+            cap_add: devcontainer.cap_add.clone(),
+            privileged: devcontainer.privileged,
+            init: devcontainer.init,
+            entrypoint: None,
+            on_create_command: devcontainer.on_create_command.clone(),
+            update_content_command: devcontainer.update_content_command.clone(),
+            post_create_command: devcontainer.post_create_command.clone(),
+            post_start_command: devcontainer.post_start_command.clone(),
+            post_attach_command: devcontainer.post_attach_command.clone(),
+            customizations: devcontainer.customizations.clone(),
+        }
+    }
+}
+
+/// Lifecycle command with the feature contributing it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookCommand {
+    /// Feature id, `None` for devcontainer.json commands
+    pub feature: Option<String>,
+    pub command: LifecycleCommand,
+}
+
+impl HookCommand {
+    fn of(entry: &MetadataEntry, command: &Option<LifecycleCommand>) -> Option<Self> {
+        command.clone().map(|command| Self {
+            feature: entry.id.clone(),
+            command,
+        })
+    }
+
+    /// Short name of the contributing feature used as log prefix, e.g. `node`
+    /// for `ghcr.io/devcontainers/features/node:1`.
+    pub fn feature_name(&self) -> Option<&str> {
+        let id = self.feature.as_deref()?;
+        let name = id.rsplit('/').next().unwrap_or(id);
+        Some(name.split([':', '@']).next().unwrap_or(name))
+    }
+}
+
+/// Configuration of a workspace merged from image metadata and devcontainer.json.
+#[derive(Debug, Clone, Default)]
+pub struct MergedMetadata {
+    pub mounts: Vec<Mount>,
+    /// Environment variables, sorted by name
+    pub container_env: BTreeMap<String, String>,
+    pub cap_add: Vec<String>,
+    pub privileged: bool,
+    pub init: bool,
+    /// Entrypoint scripts in entry order
+    pub entrypoints: Vec<String>,
+    pub on_create_commands: Vec<HookCommand>,
+    pub update_content_commands: Vec<HookCommand>,
+    pub post_create_commands: Vec<HookCommand>,
+    pub post_start_commands: Vec<HookCommand>,
+    pub post_attach_commands: Vec<HookCommand>,
+}
+
+impl MergedMetadata {
+    /// Merges the label entries of an image with the devcontainer.json of the
+    /// workspace, which takes precedence.
+    pub fn merge(entries: &[MetadataEntry], devcontainer: &Devcontainer) -> Self {
+        let workspace_entry = MetadataEntry::from(devcontainer);
+        let mut merged = Self::default();
+
+        for entry in entries.iter().chain(std::iter::once(&workspace_entry)) {
+            for mount in entry.mounts.iter().flatten() {
+                let target = mount_target(mount);
+                if target.is_some() {
+                    merged
+                        .mounts
+                        .retain(|existing| mount_target(existing) != target);
+                }
+                merged.mounts.push(mount.clone());
+            }
+            if let Some(env) = &entry.container_env {
+                merged
+                    .container_env
+                    .extend(env.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+            for cap in entry.cap_add.iter().flatten() {
+                if !merged.cap_add.contains(cap) {
+                    merged.cap_add.push(cap.clone());
+                }
+            }
+            merged.privileged |= entry.privileged.unwrap_or(false);
+            merged.init |= entry.init.unwrap_or(false);
+            merged.entrypoints.extend(entry.entrypoint.clone());
+
+            merged
+                .on_create_commands
+                .extend(HookCommand::of(entry, &entry.on_create_command));
+            merged
+                .update_content_commands
+                .extend(HookCommand::of(entry, &entry.update_content_command));
+            merged
+                .post_create_commands
+                .extend(HookCommand::of(entry, &entry.post_create_command));
+            merged
+                .post_start_commands
+                .extend(HookCommand::of(entry, &entry.post_start_command));
+            merged
+                .post_attach_commands
+                .extend(HookCommand::of(entry, &entry.post_attach_command));
+        }
+
+        merged
+    }
+}
+
+/// Serializes the label of an image built from `base` with the given features
+/// and devcontainer.json.
+///
+/// With `vscode`, the merged extensions replace those of the features and
+/// the devcontainer.json in the devcontainer.json entry, so extensions it
+/// removes are not installed. Without, the VS Code customizations of the
+/// features and the devcontainer.json are left out.
+///
+/// # Errors
+///
+/// Returns an error if a feature cannot be converted to an entry.
+pub fn build_label(
+    base: &[MetadataEntry],
+    features: &[FeatureProcessResult],
+    devcontainer: &Devcontainer,
+    vscode: Option<&VscodeCustomizations>,
+) -> anyhow::Result<String> {
+    let mut added = Vec::new();
+    for feature_result in features {
+        added.push(MetadataEntry::from_feature(feature_result)?);
+    }
+    added.push(MetadataEntry::from(devcontainer));
+    for entry in &mut added {
+        let Some(customizations) = &mut entry.customizations else {
+            continue;
+        };
+        match vscode {
+            Some(_) => {
+                if let Some(Value::Object(settings)) = customizations.get_mut("vscode") {
+                    settings.remove("extensions");
+                }
+            }
+            None => {
+                customizations.remove("vscode");
+            }
+        }
+    }
+    if let Some(vscode) = vscode.filter(|v| !v.extensions.is_empty())
+        && let Some(entry) = added.last_mut()
+    {
+        let settings = entry
+            .customizations
+            .get_or_insert_with(HashMap::new)
+            .entry("vscode".to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(settings) = settings {
+            settings.insert(
+                "extensions".to_string(),
+                serde_json::json!(vscode.extensions),
+            );
+        }
+    }
+
+    let mut entries = base.to_vec();
+    entries.extend(added);
+    Ok(serde_json::to_string(&entries)?)
+}
+
+/// Quotes a label value for a Dockerfile `LABEL` instruction, escaping
+/// quotes, backslashes and variable references.
+pub fn dockerfile_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parses the value of the metadata label, a JSON array of entries or a
+/// single entry.
+///
+/// # Errors
+///
+/// Returns an error if the label is not valid metadata.
+pub fn parse(label: &str) -> anyhow::Result<Vec<MetadataEntry>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Label {
+        Entries(Vec<MetadataEntry>),
+        Entry(Box<MetadataEntry>),
+    }
+
+    let label: Label =
+        serde_json::from_str(label).with_context(|| format!("Invalid {} label", METADATA_LABEL))?;
+    Ok(match label {
+        Label::Entries(entries) => entries,
+        Label::Entry(entry) => vec![*entry],
+    })
+}
+
+/// Returns the container path of a mount, used to detect conflicts.
+pub(crate) fn mount_target(mount: &Mount) -> Option<String> {
+    match mount {
+        Mount::Structured(structured) => Some(structured.target.clone()),
+        Mount::String(value) if value.contains('=') => value.split(',').find_map(|part| {
+            let (key, value) = part.split_once('=')?;
+            matches!(key.trim(), "target" | "dst" | "destination").then(|| value.to_string())
+        }),
+        // Short form `source:target[:options]`
+        Mount::String(value) => value.split(':').nth(1).map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LABEL: &str = r#"[
+        {
+            "id": "ghcr.io/devcontainers/features/docker-in-docker:2",
+            "privileged": true,
+            "mounts": [{ "type": "volume", "source": "dind-var-lib-docker", "target": "/var/lib/docker" }],
+            "containerEnv": { "DOCKER_BUILDKIT": "1", "EDITOR": "nano" },
+            "capAdd": ["SYS_PTRACE"],
+            "postStartCommand": "/usr/local/share/docker-init.sh"
+        },
+        {
+            "remoteUser": "vscode",
+            "mounts": ["source=cache,target=/cache,type=volume"],
+            "postCreateCommand": "echo image"
+        }
+    ]"#;
+
+    fn devcontainer(content: &str) -> Devcontainer {
+        Devcontainer::try_from(content.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_label() {
+        let entries = parse(LABEL).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].id.as_deref(),
+            Some("ghcr.io/devcontainers/features/docker-in-docker:2")
+        );
+
+        let single = parse(r#"{ "capAdd": ["NET_ADMIN"] }"#).unwrap();
+        assert_eq!(single[0].cap_add, Some(vec!["NET_ADMIN".to_string()]));
+
+        assert!(parse("not json").is_err());
+    }
+
+    #[test]
+    fn test_merge_with_devcontainer() {
+        let entries = parse(LABEL).unwrap();
+        let devcontainer = devcontainer(
+            r#"{
+                "image": "prebuilt",
+                "mounts": ["source=/host/cache,target=/cache,type=bind"],
+                "containerEnv": { "EDITOR": "vim" },
+                "capAdd": ["SYS_PTRACE", "NET_ADMIN"],
+                "postCreateCommand": "echo workspace"
+            }"#,
+        );
+
+        let merged = MergedMetadata::merge(&entries, &devcontainer);
+
+        assert!(merged.privileged);
+        assert_eq!(merged.cap_add, vec!["SYS_PTRACE", "NET_ADMIN"]);
+        assert_eq!(merged.container_env["DOCKER_BUILDKIT"], "1");
+        assert_eq!(merged.container_env["EDITOR"], "vim");
+
+        // The workspace mount replaces the image mount of the same target
+        assert_eq!(merged.mounts.len(), 2);
+        assert!(matches!(
+            &merged.mounts[1],
+            Mount::String(s) if s == "source=/host/cache,target=/cache,type=bind"
+        ));
+
+        assert_eq!(merged.post_create_commands.len(), 2);
+        assert!(matches!(
+            &merged.post_create_commands[0].command,
+            LifecycleCommand::String(s) if s == "echo image"
+        ));
+        assert_eq!(merged.post_create_commands[1].feature, None);
+        assert_eq!(merged.post_start_commands.len(), 1);
+        assert_eq!(
+            merged.post_start_commands[0].feature_name(),
+            Some("docker-in-docker")
+        );
+        assert!(merged.on_create_commands.is_empty());
+    }
+
+    #[test]
+    fn test_build_label_roundtrip() {
+        let base = parse(LABEL).unwrap();
+        let devcontainer = devcontainer(
+            r#"{ "image": "prebuilt", "capAdd": ["NET_ADMIN"], "postStartCommand": ["a", "b"] }"#,
+        );
+
+        let label = build_label(&base, &[], &devcontainer, None).unwrap();
+        let entries = parse(&label).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].cap_add, Some(vec!["NET_ADMIN".to_string()]));
+        assert!(matches!(
+            &entries[2].post_start_command,
+            Some(LifecycleCommand::Array(commands)) if commands == &["a", "b"]
+        ));
+        // Unset properties are left out
+        assert!(!label.contains("null"));
+    }
+
+    #[test]
+    fn test_build_label_vscode_customizations() {
+        let devcontainer = devcontainer(
+            r#"{
+                "image": "prebuilt",
+                "customizations": {
+                    "vscode": { "extensions": ["rust-lang.rust-analyzer"] },
+                    "devcon": { "encryptTunnels": true }
+                }
+            }"#,
+        );
+
+        let merged = VscodeCustomizations {
+            extensions: vec!["tamasfe.even-better-toml".to_string()],
+            ..Default::default()
+        };
+        let label = build_label(&[], &[], &devcontainer, Some(&merged)).unwrap();
+        let customizations = parse(&label).unwrap()[0].customizations.clone().unwrap();
+        assert_eq!(
+            customizations["vscode"]["extensions"],
+            serde_json::json!(["tamasfe.even-better-toml"])
+        );
+
+        let label = build_label(&[], &[], &devcontainer, None).unwrap();
+        assert!(!label.contains("rust-lang.rust-analyzer"));
+        let customizations = parse(&label).unwrap()[0].customizations.clone().unwrap();
+        assert!(customizations.contains_key("devcon"));
+    }
+
+    #[test]
+    fn test_dockerfile_quote() {
+        assert_eq!(
+            dockerfile_quote(r#"[{"cmd":"echo $HOME \"x\""}]"#),
+            r#""[{\"cmd\":\"echo \$HOME \\\"x\\\"\"}]""#
+        );
+    }
+
+    #[test]
+    fn test_mount_target() {
+        assert_eq!(
+            mount_target(&Mount::String("type=bind,src=/a,dst=/b".to_string())),
+            Some("/b".to_string())
+        );
+        assert_eq!(
+            mount_target(&Mount::String("/a:/b:ro".to_string())),
+            Some("/b".to_string())
+        );
+    }
+}
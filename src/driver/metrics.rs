@@ -0,0 +1,426 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Port Forward Metrics
+//!
+//! This module collects per-forward transfer statistics in the control server:
+//! byte counters in both directions, active and total connection counts, and a
+//! histogram of the time until the first byte arrives from the container.
+//!
+//! Statistics can be rendered in the Prometheus text exposition format or
+//! snapshotted as [`ForwardStats`] for the `devcon ports --stats` command.
+
+use std::fmt::Write as _;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds (in milliseconds) of the first byte latency histogram buckets.
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 5000];
+
+/// Live metrics of a single port forward.
+#[derive(Debug)]
+pub struct ForwardMetrics {
+    container_port: u16,
+    process: Option<String>,
+    bytes_to_container: AtomicU64,
+    bytes_from_container: AtomicU64,
+    active_connections: AtomicU64,
+    total_connections: AtomicU64,
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS_MS.len()],
+    latency_count: AtomicU64,
+    latency_sum_micros: AtomicU64,
+}
+
+impl ForwardMetrics {
+    /// Creates empty metrics for a forward to the given container port.
+    pub fn new(container_port: u16) -> Self {
+        Self {
+            container_port,
+            process: None,
+            bytes_to_container: AtomicU64::new(0),
+            bytes_from_container: AtomicU64::new(0),
+            active_connections: AtomicU64::new(0),
+            total_connections: AtomicU64::new(0),
+            latency_buckets: Default::default(),
+            latency_count: AtomicU64::new(0),
+            latency_sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Sets the command of the process listening on the container port.
+    pub fn with_process(mut self, process: Option<String>) -> Self {
+        self.process = process;
+        self
+    }
+
+    /// Records a newly accepted client connection.
+    pub fn connection_opened(&self) {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a closed client connection.
+    pub fn connection_closed(&self) {
+        let _ = self
+            .active_connections
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    /// Adds bytes sent from the host client to the container.
+    pub fn add_bytes_to_container(&self, bytes: u64) {
+        self.bytes_to_container.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Adds bytes sent from the container to the host client.
+    pub fn add_bytes_from_container(&self, bytes: u64) {
+        self.bytes_from_container
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Records the time between accepting a connection and the first byte from the container.
+    pub fn record_latency(&self, latency: Duration) {
+        let millis = latency.as_millis() as u64;
+        for (bound, bucket) in LATENCY_BUCKETS_MS.iter().zip(&self.latency_buckets) {
+            if millis <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.latency_count.fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Takes a consistent-enough snapshot of the metrics for reporting.
+    pub fn snapshot(&self, local_port: u16) -> ForwardStats {
+        let latency_count = self.latency_count.load(Ordering::Relaxed);
+        let latency_sum_micros = self.latency_sum_micros.load(Ordering::Relaxed);
+        ForwardStats {
+            local_port,
+            container_port: self.container_port,
+            process: self.process.clone(),
+            bytes_to_container: self.bytes_to_container.load(Ordering::Relaxed),
+            bytes_from_container: self.bytes_from_container.load(Ordering::Relaxed),
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.total_connections.load(Ordering::Relaxed),
+            latency_buckets: self
+                .latency_buckets
+                .iter()
+                .map(|b| b.load(Ordering::Relaxed))
+                .collect(),
+            latency_count,
+            latency_sum_seconds: latency_sum_micros as f64 / 1_000_000.0,
+            suspended: false,
+        }
+    }
+}
+
+/// Point-in-time statistics of a port forward.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardStats {
+    /// Port listening on the host
+    pub local_port: u16,
+    /// Port inside the container
+    pub container_port: u16,
+    /// Command of the process listening on the container port, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    /// Bytes sent from host clients to the container
+    pub bytes_to_container: u64,
+    /// Bytes sent from the container to host clients
+    pub bytes_from_container: u64,
+    /// Currently open client connections
+    pub active_connections: u64,
+    /// Client connections accepted since the forward started
+    pub total_connections: u64,
+    /// Cumulative histogram counts matching [`LATENCY_BUCKETS_MS`]
+    pub latency_buckets: Vec<u64>,
+    /// Number of recorded first byte latencies
+    pub latency_count: u64,
+    /// Sum of recorded first byte latencies in seconds
+    pub latency_sum_seconds: f64,
+    /// Whether the container of the forward is paused
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suspended: bool,
+}
+
+impl ForwardStats {
+    /// Host port with the listening process, e.g. "3000 (vite)".
+    pub fn port_label(&self) -> String {
+        port_label(self.local_port, self.process.as_deref())
+    }
+
+    /// Average first byte latency, if any was recorded.
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.latency_count > 0)
+            .then(|| Duration::from_secs_f64(self.latency_sum_seconds / self.latency_count as f64))
+    }
+}
+
+/// Formats a port with the process listening on it, if known.
+pub fn port_label(port: u16, process: Option<&str>) -> String {
+    match process {
+        Some(process) => format!("{} ({})", port, process),
+        None => port.to_string(),
+    }
+}
+
+/// Formats a byte count with a binary unit suffix (e.g. "1.5 MiB").
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Renders forward statistics in the Prometheus text exposition format.
+pub fn render_prometheus(stats: &[ForwardStats]) -> String {
+    let mut out = String::new();
+
+    out.push_str("# HELP devcon_tunnel_bytes_total Bytes transferred through forwarded ports.\n");
+    out.push_str("# TYPE devcon_tunnel_bytes_total counter\n");
+    for s in stats {
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_bytes_total{{port=\"{}\",container_port=\"{}\",direction=\"to_container\"}} {}",
+            s.local_port, s.container_port, s.bytes_to_container
+        );
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_bytes_total{{port=\"{}\",container_port=\"{}\",direction=\"from_container\"}} {}",
+            s.local_port, s.container_port, s.bytes_from_container
+        );
+    }
+
+    out.push_str("# HELP devcon_tunnel_active_connections Open connections on forwarded ports.\n");
+    out.push_str("# TYPE devcon_tunnel_active_connections gauge\n");
+    for s in stats {
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_active_connections{{port=\"{}\",container_port=\"{}\"}} {}",
+            s.local_port, s.container_port, s.active_connections
+        );
+    }
+
+    out.push_str(
+        "# HELP devcon_tunnel_connections_total Connections accepted on forwarded ports.\n",
+    );
+    out.push_str("# TYPE devcon_tunnel_connections_total counter\n");
+    for s in stats {
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_connections_total{{port=\"{}\",container_port=\"{}\"}} {}",
+            s.local_port, s.container_port, s.total_connections
+        );
+    }
+
+    out.push_str("# HELP devcon_tunnel_first_byte_latency_seconds Time until the container sent the first byte of a connection.\n");
+    out.push_str("# TYPE devcon_tunnel_first_byte_latency_seconds histogram\n");
+    for s in stats {
+        let labels = format!(
+            "port=\"{}\",container_port=\"{}\"",
+            s.local_port, s.container_port
+        );
+        for (bound, count) in LATENCY_BUCKETS_MS.iter().zip(&s.latency_buckets) {
+            let _ = writeln!(
+                out,
+                "devcon_tunnel_first_byte_latency_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels,
+                *bound as f64 / 1000.0,
+                count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_first_byte_latency_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            labels, s.latency_count
+        );
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_first_byte_latency_seconds_sum{{{}}} {}",
+            labels, s.latency_sum_seconds
+        );
+        let _ = writeln!(
+            out,
+            "devcon_tunnel_first_byte_latency_seconds_count{{{}}} {}",
+            labels, s.latency_count
+        );
+    }
+
+    out
+}
+
+/// Direction of data flowing through a [`CountingReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From a host client to the container
+    ToContainer,
+    /// From the container to a host client
+    FromContainer,
+}
+
+/// Reader wrapper which accounts all read bytes to a forward's metrics.
+///
+/// When reading from the container, the latency until the first byte is
+/// recorded relative to `started`.
+pub struct CountingReader<R> {
+    inner: R,
+    metrics: Arc<ForwardMetrics>,
+    direction: Direction,
+    started: Option<Instant>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(
+        inner: R,
+        metrics: Arc<ForwardMetrics>,
+        direction: Direction,
+        started: Option<Instant>,
+    ) -> Self {
+        Self {
+            inner,
+            metrics,
+            direction,
+            started,
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        match self.direction {
+            Direction::ToContainer => self.metrics.add_bytes_to_container(n as u64),
+            Direction::FromContainer => {
+                self.metrics.add_bytes_from_container(n as u64);
+                if n > 0
+                    && let Some(started) = self.started.take()
+                {
+                    self.metrics.record_latency(started.elapsed());
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_latency_histogram_is_cumulative() {
+        let metrics = ForwardMetrics::new(80);
+        metrics.record_latency(Duration::from_millis(3));
+        metrics.record_latency(Duration::from_millis(200));
+        metrics.record_latency(Duration::from_secs(10));
+
+        let stats = metrics.snapshot(8080);
+        assert_eq!(stats.latency_count, 3);
+        assert_eq!(stats.latency_buckets[0], 0); // <= 1ms
+        assert_eq!(stats.latency_buckets[1], 1); // <= 5ms
+        assert_eq!(stats.latency_buckets[6], 2); // <= 250ms
+        assert_eq!(stats.latency_buckets[9], 2); // <= 5s
+    }
+
+    #[test]
+    fn test_counting_reader() {
+        let metrics = Arc::new(ForwardMetrics::new(80));
+        let mut reader = CountingReader::new(
+            &b"hello world"[..],
+            metrics.clone(),
+            Direction::FromContainer,
+            Some(Instant::now()),
+        );
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+
+        let stats = metrics.snapshot(8080);
+        assert_eq!(stats.bytes_from_container, 11);
+        assert_eq!(stats.bytes_to_container, 0);
+        assert_eq!(stats.latency_count, 1);
+    }
+
+    #[test]
+    fn test_port_label() {
+        let stats = ForwardMetrics::new(3000).snapshot(3000);
+        assert_eq!(stats.port_label(), "3000");
+
+        let stats = ForwardMetrics::new(3000)
+            .with_process(Some("vite".to_string()))
+            .snapshot(3000);
+        assert_eq!(stats.port_label(), "3000 (vite)");
+    }
+
+    #[test]
+    fn test_connection_gauge() {
+        let metrics = ForwardMetrics::new(80);
+        metrics.connection_opened();
+        metrics.connection_opened();
+        metrics.connection_closed();
+        metrics.connection_closed();
+        metrics.connection_closed();
+
+        let stats = metrics.snapshot(8080);
+        assert_eq!(stats.active_connections, 0);
+        assert_eq!(stats.total_connections, 2);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = ForwardMetrics::new(3000);
+        metrics.add_bytes_to_container(42);
+        metrics.record_latency(Duration::from_millis(20));
+
+        let output = render_prometheus(&[metrics.snapshot(8080)]);
+        assert!(output.contains(
+            "devcon_tunnel_bytes_total{port=\"8080\",container_port=\"3000\",direction=\"to_container\"} 42"
+        ));
+        assert!(output.contains("# TYPE devcon_tunnel_first_byte_latency_seconds histogram"));
+        assert!(output.contains(
+            "devcon_tunnel_first_byte_latency_seconds_bucket{port=\"8080\",container_port=\"3000\",le=\"0.025\"} 1"
+        ));
+        assert!(output.contains(
+            "devcon_tunnel_first_byte_latency_seconds_count{port=\"8080\",container_port=\"3000\"} 1"
+        ));
+    }
+}
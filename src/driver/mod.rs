@@ -21,7 +21,32 @@
 // SOFTWARE.
 
 pub mod agent;
+pub mod audit;
+pub mod build_stage;
+pub mod build_timing;
+pub mod cancel;
 pub mod container;
 pub mod control_server;
+pub mod dashboard;
+pub mod effective;
+pub mod environment;
+pub mod feature_options;
+pub mod feature_order;
 pub mod feature_process;
+pub mod hooks;
+pub mod image_metadata;
+pub mod metrics;
+pub mod notification;
+pub mod oci;
+pub mod open;
+pub mod propagation;
+pub mod registry_cache;
 pub mod runtime;
+pub mod sbom;
+pub mod service;
+pub mod signature;
+pub mod snapshot;
+pub mod tunnel_key;
+pub mod vscode;
+pub mod workspace_lock;
+pub mod workspace_state;
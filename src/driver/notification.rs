@@ -0,0 +1,162 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Desktop Notifications
+//!
+//! Agents can ask the host to show a desktop notification, e.g. when a
+//! long-running build inside the container finishes. Notifications are shown
+//! with `osascript` on macOS and `notify-send` on other systems.
+//!
+//! To avoid notification storms, each workspace may only show a limited number
+//! of notifications per time window. Notifications beyond that are dropped.
+//! The control server attributes notifications to the container it maps the
+//! agent's address to, or to the address itself, never to the workspace named
+//! by the agent.
+
+use std::collections::{HashMap, VecDeque};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+
+/// Maximum number of notifications per workspace within `NOTIFICATION_WINDOW`.
+pub const MAX_NOTIFICATIONS: usize = 5;
+
+/// Time window of the notification rate limit.
+pub const NOTIFICATION_WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding window rate limit of notifications per workspace.
+#[derive(Debug, Clone)]
+pub struct NotificationLimiter {
+    max: usize,
+    window: Duration,
+    shown: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+}
+
+impl Default for NotificationLimiter {
+    fn default() -> Self {
+        Self::new(MAX_NOTIFICATIONS, NOTIFICATION_WINDOW)
+    }
+}
+
+impl NotificationLimiter {
+    /// Creates a limiter allowing `max` notifications per `window` and workspace.
+    pub fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            shown: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns whether `workspace` may show another notification, and if so
+    /// counts it against the limit.
+    pub fn allow(&self, workspace: &str) -> bool {
+        let now = Instant::now();
+        let mut shown = self.shown.lock().unwrap();
+        let times = shown.entry(workspace.to_string()).or_default();
+
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= self.window)
+        {
+            times.pop_front();
+        }
+        if times.len() >= self.max {
+            return false;
+        }
+
+        times.push_back(now);
+        true
+    }
+}
+
+/// Shows a desktop notification on the host.
+///
+/// # Errors
+///
+/// Returns an error if the notification command is not available or fails.
+pub fn show_notification(title: &str, body: &str) -> Result<()> {
+    let status = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!(
+                "display notification {} with title {}",
+                applescript_string(body),
+                applescript_string(title)
+            ))
+            .status()
+            .context("Failed to run osascript")?
+    } else {
+        Command::new("notify-send")
+            .arg("--app-name=DevCon")
+            .arg("--")
+            .arg(title)
+            .arg(body)
+            .status()
+            .context("Failed to run notify-send, is libnotify installed?")?
+    };
+
+    if !status.success() {
+        bail!("Notification command failed with {}", status);
+    }
+    Ok(())
+}
+
+/// Quotes a value as an AppleScript string literal.
+fn applescript_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_is_per_workspace() {
+        let limiter = NotificationLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.allow("a"));
+        assert!(limiter.allow("a"));
+        assert!(!limiter.allow("a"));
+        assert!(limiter.allow("b"));
+    }
+
+    #[test]
+    fn test_limiter_window_expires() {
+        let limiter = NotificationLimiter::new(1, Duration::from_millis(50));
+
+        assert!(limiter.allow("a"));
+        assert!(!limiter.allow("a"));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.allow("a"));
+    }
+
+    #[test]
+    fn test_applescript_string_escapes_quotes() {
+        assert_eq!(
+            applescript_string(r#"say "hi" \ bye"#),
+            r#""say \"hi\" \\ bye""#
+        );
+    }
+}
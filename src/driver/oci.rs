@@ -0,0 +1,450 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # OCI Registry Client
+//!
+//! Registry features are published as OCI artifacts. Downloading one takes
+//! three requests: a pull token, the manifest of the version and the blob of
+//! its layer. [`OciClient`] abstracts these requests, so feature resolution
+//! runs against [`HttpOciClient`] in the CLI and against the in-memory
+//! `mock::MockOciClient` in tests.
+//!
+//! Base images live on other registries, whose token endpoint is discovered
+//! from the `WWW-Authenticate` challenge of the registry.
+
+use anyhow::bail;
+use oci_spec::image::ImageManifest;
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::credentials;
+use crate::download::{DownloadPolicy, DownloadSink};
+use crate::driver::oci::mirror::Mirror;
+use crate::http_proxy;
+
+pub mod mirror;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+
+/// The registry hosting devcontainer features
+pub const GHCR: &str = "ghcr.io";
+
+/// The registry of images without registry host, e.g. `ubuntu`
+pub const DOCKER_HUB: &str = "docker.io";
+
+/// Host of the Docker Hub registry API
+const DOCKER_HUB_API: &str = "registry-1.docker.io";
+
+/// Media types of single-platform image manifests
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Media types of multi-platform image indexes
+const INDEX_MEDIA_TYPES: &str = "application/vnd.oci.image.index.v1+json, application/vnd.docker.distribution.manifest.list.v2+json";
+
+/// A container image reference split for registry requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+    /// Name of the image as written, without tag or digest
+    pub name: String,
+    /// Registry of the image, e.g. `docker.io` or `mcr.microsoft.com`
+    pub registry: String,
+    /// Repository in the registry, e.g. `library/ubuntu`
+    pub repository: String,
+    /// Tag or digest, `latest` if not given
+    pub reference: String,
+}
+
+impl ImageReference {
+    /// Parses an image reference like `ubuntu:22.04` or
+    /// `mcr.microsoft.com/devcontainers/base@sha256:...`.
+    pub fn parse(image: &str) -> Self {
+        let (name, reference) = match image.split_once('@') {
+            Some((name, digest)) => (name, digest),
+            // A colon after the last slash separates the tag, not a registry port
+            None => match image.rsplit_once(':') {
+                Some((name, tag)) if !tag.contains('/') => (name, tag),
+                _ => (image, "latest"),
+            },
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), path.to_string())
+            }
+            _ => (DOCKER_HUB.to_string(), name.to_string()),
+        };
+        // Official Docker Hub images live in the `library` namespace
+        let repository = if registry == DOCKER_HUB && !repository.contains('/') {
+            format!("library/{}", repository)
+        } else {
+            repository
+        };
+        Self {
+            name: name.to_string(),
+            registry,
+            repository,
+            reference: reference.to_string(),
+        }
+    }
+
+    /// The image pinned to `digest`, e.g. `ubuntu@sha256:...`.
+    pub fn pinned(&self, digest: &str) -> String {
+        format!("{}@{}", self.name, digest)
+    }
+}
+
+/// Requests of an OCI distribution registry.
+///
+/// Repositories are given without the registry host, e.g.
+/// `devcontainers/features/node`.
+pub trait OciClient {
+    /// Fetch a pull token for `repository`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the registry refuses the token.
+    fn token(&self, repository: &str) -> anyhow::Result<String>;
+
+    /// Fetch the image manifest of `reference`, a tag or digest, in `repository`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest does not exist or cannot be parsed.
+    fn manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        token: &str,
+    ) -> anyhow::Result<ImageManifest>;
+
+    /// Fetch the digest of the manifest `reference` in `repository`, which
+    /// may also be an image index. A digest reference is returned as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest does not exist.
+    fn manifest_digest(
+        &self,
+        repository: &str,
+        reference: &str,
+        token: &str,
+    ) -> anyhow::Result<String>;
+
+    /// Stream the blob `digest` of `repository` into `sink`, returning its size.
+    ///
+    /// `progress` is called with the bytes received so far and the size of
+    /// the blob, if known.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the blob cannot be downloaded or exceeds the size limit.
+    fn blob(
+        &self,
+        repository: &str,
+        digest: &str,
+        token: &str,
+        sink: &mut dyn DownloadSink,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> anyhow::Result<u64>;
+}
+
+/// [`OciClient`] of a registry over HTTPS, ghcr.io by default.
+///
+/// Requests are retried, and blobs are downloaded, following the
+/// [`DownloadPolicy`], and go to the mirror of the registry if one is
+/// configured. Tokens use the credentials of `devcon login <host>` if
+/// stored, so private features and images can be pulled.
+pub struct HttpOciClient {
+    client: Client,
+    policy: DownloadPolicy,
+    host: String,
+    mirror: Option<Mirror>,
+}
+
+impl HttpOciClient {
+    /// Create a client of ghcr.io honouring the proxy settings and CA
+    /// certificates of `policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a CA certificate cannot be loaded.
+    pub fn new(policy: &DownloadPolicy) -> anyhow::Result<Self> {
+        Self::for_registry(GHCR, policy)
+    }
+
+    /// Create a client of `registry`, e.g. `mcr.microsoft.com`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a CA certificate cannot be loaded.
+    pub fn for_registry(registry: &str, policy: &DownloadPolicy) -> anyhow::Result<Self> {
+        let mirror = policy.mirrors.get(registry).cloned();
+        let host = match &mirror {
+            Some(mirror) => {
+                debug!("Using mirror {} of {}", mirror, registry);
+                mirror.host.clone()
+            }
+            None if registry == DOCKER_HUB => DOCKER_HUB_API.to_string(),
+            None => registry.to_string(),
+        };
+        Ok(Self {
+            client: http_proxy::http_client(&policy.ca_certificates)?,
+            policy: policy.clone(),
+            host,
+            mirror,
+        })
+    }
+
+    /// Returns the path of `repository` on the host
+    fn path(&self, repository: &str) -> String {
+        match &self.mirror {
+            Some(mirror) => mirror.repository(repository),
+            None => repository.to_string(),
+        }
+    }
+
+    /// Authorize a request with `token`, registries allowing anonymous pulls
+    /// have none
+    fn authorize(request: RequestBuilder, token: &str) -> RequestBuilder {
+        if token.is_empty() {
+            request
+        } else {
+            request.bearer_auth(token)
+        }
+    }
+
+    /// Discover the token endpoint of the registry from the challenge of
+    /// `/v2/`, `None` if it allows anonymous access
+    fn token_endpoint(&self, repository: &str) -> anyhow::Result<Option<String>> {
+        let response = self.send_with_retry("Registry request", || {
+            self.client.get(format!("https://{}/v2/", self.host))
+        })?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(None);
+        }
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let Some(params) = challenge.strip_prefix("Bearer ") else {
+            bail!("{} does not offer token authentication", self.host);
+        };
+        let param = |name: &str| {
+            params.split(',').find_map(|param| {
+                let (key, value) = param.trim().split_once('=')?;
+                (key == name).then(|| value.trim_matches('"').to_string())
+            })
+        };
+        let Some(realm) = param("realm") else {
+            bail!("{} does not name its token endpoint", self.host);
+        };
+        let mut url = format!("{}?scope=repository:{}:pull", realm, repository);
+        if let Some(service) = param("service") {
+            url.push_str(&format!("&service={}", service));
+        }
+        Ok(Some(url))
+    }
+
+    /// Send a registry request, retrying transient failures and server errors
+    fn send_with_retry(
+        &self,
+        what: &str,
+        request: impl Fn() -> RequestBuilder,
+    ) -> anyhow::Result<Response> {
+        self.policy.retry(what, || {
+            let response = request().send()?;
+            let status = response.status();
+            if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                return Err(response.error_for_status().unwrap_err().into());
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl OciClient for HttpOciClient {
+    fn token(&self, repository: &str) -> anyhow::Result<String> {
+        let token_url = if self.host == GHCR {
+            format!(
+                "https://{}/token?scope=repository:{}:pull",
+                GHCR,
+                self.path(repository)
+            )
+        } else {
+            match self.token_endpoint(&self.path(repository))? {
+                Some(url) => url,
+                None => return Ok(String::new()),
+            }
+        };
+
+        // Private artifacts need the credentials of `devcon login <registry>`
+        let credential = credentials::load(&self.host).unwrap_or_else(|e| {
+            warn!("Ignoring stored credentials for {}: {:#}", self.host, e);
+            None
+        });
+        if credential.is_some() {
+            debug!("Using stored credentials for {}", self.host);
+        }
+
+        let response = self.send_with_retry("Token request", || {
+            let request = self.client.get(&token_url);
+            match &credential {
+                Some(credential) => {
+                    request.basic_auth(&credential.username, Some(&credential.secret))
+                }
+                None => request,
+            }
+        })?;
+        if !response.status().is_success() {
+            bail!("Failed to get token for {}", repository);
+        }
+        let json: serde_json::Value = response.json()?;
+        let token = json["token"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Token not found in response for {}", repository))?
+            .to_string();
+        Ok(token)
+    }
+
+    fn manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        token: &str,
+    ) -> anyhow::Result<ImageManifest> {
+        let manifest_url = format!(
+            "https://{}/v2/{}/manifests/{}",
+            self.host,
+            self.path(repository),
+            reference
+        );
+        let response = self.send_with_retry("Manifest request", || {
+            Self::authorize(self.client.get(&manifest_url), token)
+                .header("Accept", MANIFEST_MEDIA_TYPES)
+        })?;
+        if !response.status().is_success() {
+            bail!("Failed to download manifest {}:{}", repository, reference);
+        }
+        let body = response.bytes()?;
+        // Content addressed manifests must match their digest
+        if let Some(expected) = reference.strip_prefix("sha256:") {
+            let actual = format!("{:x}", Sha256::digest(&body));
+            if actual != expected {
+                bail!(
+                    "Manifest {}@{} has the digest sha256:{}",
+                    repository,
+                    reference,
+                    actual
+                );
+            }
+        }
+        Ok(ImageManifest::from_reader(std::io::Cursor::new(body))?)
+    }
+
+    fn manifest_digest(
+        &self,
+        repository: &str,
+        reference: &str,
+        token: &str,
+    ) -> anyhow::Result<String> {
+        if reference.starts_with("sha256:") {
+            return Ok(reference.to_string());
+        }
+        let manifest_url = format!(
+            "https://{}/v2/{}/manifests/{}",
+            self.host,
+            self.path(repository),
+            reference
+        );
+        let response = self.send_with_retry("Manifest request", || {
+            Self::authorize(self.client.head(&manifest_url), token).header(
+                "Accept",
+                format!("{}, {}", MANIFEST_MEDIA_TYPES, INDEX_MEDIA_TYPES),
+            )
+        })?;
+        if !response.status().is_success() {
+            bail!("Failed to find manifest {}:{}", repository, reference);
+        }
+        response
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} did not report the digest of {}:{}",
+                    self.host,
+                    repository,
+                    reference
+                )
+            })
+    }
+
+    fn blob(
+        &self,
+        repository: &str,
+        digest: &str,
+        token: &str,
+        sink: &mut dyn DownloadSink,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> anyhow::Result<u64> {
+        let blob_url = format!(
+            "https://{}/v2/{}/blobs/{}",
+            self.host,
+            self.path(repository),
+            digest
+        );
+        self.policy.download_to(
+            || Self::authorize(self.client.get(&blob_url), token),
+            sink,
+            progress,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_image_reference() {
+        let ubuntu = ImageReference::parse("ubuntu:22.04");
+        assert_eq!(ubuntu.registry, DOCKER_HUB);
+        assert_eq!(ubuntu.repository, "library/ubuntu");
+        assert_eq!(ubuntu.reference, "22.04");
+
+        let base = ImageReference::parse("mcr.microsoft.com/devcontainers/base");
+        assert_eq!(base.registry, "mcr.microsoft.com");
+        assert_eq!(base.repository, "devcontainers/base");
+        assert_eq!(base.reference, "latest");
+
+        let local = ImageReference::parse("localhost:5000/app@sha256:abc");
+        assert_eq!(local.registry, "localhost:5000");
+        assert_eq!(local.repository, "app");
+        assert_eq!(local.reference, "sha256:abc");
+        assert_eq!(local.pinned("sha256:def"), "localhost:5000/app@sha256:def");
+    }
+}
@@ -0,0 +1,204 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Registry Mirrors
+//!
+//! Networks without direct internet access reach public registries through
+//! mirrors or pull-through caches, e.g. an Artifactory remote repository.
+//! The `registryMirrors` setting maps a registry to the host serving its
+//! content, optionally with a path prefix:
+//!
+//! ```text
+//! ghcr.io=artifactory.corp/ghcr-remote,docker.io=mirror.corp
+//! ```
+//!
+//! Feature downloads and the base images of built images go through the
+//! mirror, while the references in devcontainer configurations stay as they
+//! are.
+
+use std::fmt;
+
+use anyhow::{Result, bail};
+
+use super::{DOCKER_HUB, ImageReference};
+use crate::credentials::normalize_registry;
+
+/// The host, and path prefix, serving the content of a registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mirror {
+    /// Host of the mirror, e.g. `artifactory.corp`
+    pub host: String,
+    /// Path prefixed to repositories, e.g. `ghcr-remote`
+    pub prefix: Option<String>,
+}
+
+impl Mirror {
+    /// Returns the path of `repository` on the mirror.
+    pub fn repository(&self, repository: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{}/{}", prefix, repository),
+            None => repository.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.prefix {
+            Some(prefix) => write!(f, "{}/{}", self.host, prefix),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// The configured mirrors by registry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegistryMirrors {
+    mirrors: Vec<(String, Mirror)>,
+}
+
+impl RegistryMirrors {
+    /// Parses a comma-separated list of `registry=mirror` entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry has no mirror or an invalid registry.
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut mirrors = Vec::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((registry, mirror)) = entry.split_once('=') else {
+                bail!(
+                    "Registry mirror '{}' must map a registry to a mirror, e.g. ghcr.io=mirror.corp/ghcr",
+                    entry
+                );
+            };
+            let registry = canonical_registry(&normalize_registry(registry)?);
+            let mirror = mirror
+                .trim()
+                .trim_start_matches("https://")
+                .trim_end_matches('/');
+            let (host, prefix) = match mirror.split_once('/') {
+                Some((host, prefix)) => (host, Some(prefix.to_string())),
+                None => (mirror, None),
+            };
+            if host.is_empty() || mirror.contains("://") {
+                bail!(
+                    "Mirror of {} must be a host with an optional path",
+                    registry
+                );
+            }
+            mirrors.push((
+                registry,
+                Mirror {
+                    host: host.to_ascii_lowercase(),
+                    prefix,
+                },
+            ));
+        }
+        Ok(Self { mirrors })
+    }
+
+    /// Returns the mirror of `registry`, if configured.
+    pub fn get(&self, registry: &str) -> Option<&Mirror> {
+        let registry = canonical_registry(registry);
+        self.mirrors
+            .iter()
+            .find(|(mirrored, _)| *mirrored == registry)
+            .map(|(_, mirror)| mirror)
+    }
+
+    /// Rewrites an image reference to be pulled from the mirror of its
+    /// registry, e.g. `ubuntu:22.04` to `mirror.corp/library/ubuntu:22.04`.
+    /// Images of registries without mirror are returned as is.
+    pub fn rewrite_image(&self, image: &str) -> String {
+        let reference = ImageReference::parse(image);
+        let Some(mirror) = self.get(&reference.registry) else {
+            return image.to_string();
+        };
+        let separator = if reference.reference.starts_with("sha256:") {
+            '@'
+        } else {
+            ':'
+        };
+        format!(
+            "{}/{}{}{}",
+            mirror.host,
+            mirror.repository(&reference.repository),
+            separator,
+            reference.reference
+        )
+    }
+}
+
+/// Names Docker Hub by one name, however it is written
+fn canonical_registry(registry: &str) -> String {
+    match registry {
+        "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB.to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mirrors() {
+        let mirrors = RegistryMirrors::parse(
+            "ghcr.io=artifactory.corp/ghcr-remote, https://index.docker.io/=mirror.corp",
+        )
+        .unwrap();
+        assert_eq!(
+            mirrors.get("ghcr.io"),
+            Some(&Mirror {
+                host: "artifactory.corp".to_string(),
+                prefix: Some("ghcr-remote".to_string()),
+            })
+        );
+        assert_eq!(mirrors.get("docker.io").unwrap().to_string(), "mirror.corp");
+        assert!(mirrors.get("mcr.microsoft.com").is_none());
+        assert!(RegistryMirrors::parse("").unwrap().get("ghcr.io").is_none());
+
+        assert!(RegistryMirrors::parse("ghcr.io").is_err());
+        assert!(RegistryMirrors::parse("ghcr.io=").is_err());
+        assert!(RegistryMirrors::parse("ghcr.io/owner=mirror.corp").is_err());
+    }
+
+    #[test]
+    fn test_rewrite_image() {
+        let mirrors =
+            RegistryMirrors::parse("docker.io=mirror.corp/hub,mcr.microsoft.com=mirror.corp")
+                .unwrap();
+        assert_eq!(
+            mirrors.rewrite_image("ubuntu:22.04"),
+            "mirror.corp/hub/library/ubuntu:22.04"
+        );
+        assert_eq!(
+            mirrors.rewrite_image("mcr.microsoft.com/devcontainers/base@sha256:abc"),
+            "mirror.corp/devcontainers/base@sha256:abc"
+        );
+        assert_eq!(
+            mirrors.rewrite_image("quay.io/app/tool:1"),
+            "quay.io/app/tool:1"
+        );
+    }
+}
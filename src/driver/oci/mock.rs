@@ -0,0 +1,277 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Mock OCI Client
+//!
+//! An in-memory [`OciClient`] for tests of feature resolution without
+//! network access. Features are added as artifacts with a single tar layer,
+//! built from the given files, and every request is recorded.
+//!
+//! Only available in tests of devcon and with the `test-util` feature.
+
+// The binary compiles the module with the feature too, without using it
+#![cfg_attr(not(test), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use oci_spec::image::ImageManifest;
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, KeyPair};
+use sha2::{Digest, Sha256};
+
+use super::OciClient;
+use crate::download::DownloadSink;
+use crate::driver::signature::SIGNATURE_ANNOTATION;
+
+/// Media type of an uncompressed devcontainer feature layer
+pub const FEATURE_LAYER_MEDIA_TYPE: &str = "application/vnd.devcontainers.layer.v1+tar";
+
+/// Digest of the empty `{}` config blob of feature artifacts
+const EMPTY_CONFIG_DIGEST: &str =
+    "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a";
+
+/// Media type of the payload layers of cosign signatures
+pub const SIGNATURE_MEDIA_TYPE: &str = "application/vnd.dev.cosign.simplesigning.v1+json";
+
+/// An [`OciClient`] serving artifacts from memory.
+#[derive(Debug, Default)]
+pub struct MockOciClient {
+    manifests: HashMap<(String, String), Vec<u8>>,
+    blobs: HashMap<String, Vec<u8>>,
+    requests: Mutex<Vec<String>>,
+}
+
+impl MockOciClient {
+    /// Create a client without artifacts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an artifact with a single layer of `media_type` under `repository:reference`.
+    pub fn with_artifact(
+        self,
+        repository: &str,
+        reference: &str,
+        media_type: &str,
+        layer: Vec<u8>,
+    ) -> Self {
+        self.with_layer(
+            repository,
+            reference,
+            media_type,
+            layer,
+            serde_json::json!({}),
+        )
+    }
+
+    /// Add a cosign signature of the manifest `digest` in `repository`: the
+    /// `payload` layer with the base64 `signature` annotated.
+    pub fn with_signature(
+        self,
+        repository: &str,
+        digest: &str,
+        payload: Vec<u8>,
+        signature: &str,
+    ) -> Self {
+        let tag = format!("{}.sig", digest.replace(':', "-"));
+        self.with_layer(
+            repository,
+            &tag,
+            SIGNATURE_MEDIA_TYPE,
+            payload,
+            serde_json::json!({ SIGNATURE_ANNOTATION: signature }),
+        )
+    }
+
+    /// Add an artifact with one annotated layer, addressable by `reference`
+    /// and by its digest
+    fn with_layer(
+        mut self,
+        repository: &str,
+        reference: &str,
+        media_type: &str,
+        layer: Vec<u8>,
+        annotations: serde_json::Value,
+    ) -> Self {
+        let digest = format!("sha256:{:x}", Sha256::digest(&layer));
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.devcontainers",
+                "digest": EMPTY_CONFIG_DIGEST,
+                "size": 2
+            },
+            "layers": [{
+                "mediaType": media_type,
+                "digest": digest,
+                "size": layer.len(),
+                "annotations": annotations
+            }]
+        })
+        .to_string()
+        .into_bytes();
+        let manifest_digest = format!("sha256:{:x}", Sha256::digest(&manifest));
+        self.manifests
+            .insert((repository.to_string(), manifest_digest), manifest.clone());
+        self.manifests
+            .insert((repository.to_string(), reference.to_string()), manifest);
+        self.blobs.insert(digest, layer);
+        self
+    }
+
+    /// Add a feature version whose layer contains `files`, given as path and content.
+    pub fn with_feature(self, repository: &str, version: &str, files: &[(&str, &str)]) -> Self {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .expect("mock layer entry is valid");
+        }
+        let layer = builder.into_inner().expect("mock layer is complete");
+        self.with_artifact(repository, version, FEATURE_LAYER_MEDIA_TYPE, layer)
+    }
+
+    /// The requests made so far, e.g. `manifest devcontainers/features/node:1`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn record(&self, request: String) {
+        self.requests.lock().unwrap().push(request);
+    }
+}
+
+/// A generated ECDSA P-256 key pair signing like cosign.
+pub struct SigningKey {
+    pair: EcdsaKeyPair,
+}
+
+impl SigningKey {
+    /// Generate a new key pair.
+    pub fn generate() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng)
+            .expect("key generation succeeds");
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .expect("generated key is valid");
+        Self { pair }
+    }
+
+    /// The public key in PEM format, as in `cosign.pub`.
+    pub fn public_key_pem(&self) -> String {
+        // DER prefix of a P-256 subject public key info
+        let mut der = vec![
+            0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06,
+            0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+        ];
+        der.extend_from_slice(self.pair.public_key().as_ref());
+        format!(
+            "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+            STANDARD.encode(der)
+        )
+    }
+
+    /// Sign a simple signing payload of the manifest `digest`, returning the
+    /// payload and the base64 signature for [`MockOciClient::with_signature`].
+    pub fn sign(&self, digest: &str) -> (Vec<u8>, String) {
+        let payload = serde_json::json!({
+            "critical": {
+                "identity": { "docker-reference": "mock" },
+                "image": { "docker-manifest-digest": digest },
+                "type": "cosign container image signature"
+            },
+            "optional": null
+        })
+        .to_string()
+        .into_bytes();
+        let signature = self
+            .pair
+            .sign(&SystemRandom::new(), &payload)
+            .expect("signing succeeds");
+        (payload, STANDARD.encode(signature.as_ref()))
+    }
+}
+
+impl OciClient for MockOciClient {
+    fn token(&self, repository: &str) -> anyhow::Result<String> {
+        self.record(format!("token {}", repository));
+        Ok("mock-token".to_string())
+    }
+
+    fn manifest(
+        &self,
+        repository: &str,
+        reference: &str,
+        _token: &str,
+    ) -> anyhow::Result<ImageManifest> {
+        self.record(format!("manifest {}:{}", repository, reference));
+        match self
+            .manifests
+            .get(&(repository.to_string(), reference.to_string()))
+        {
+            Some(manifest) => Ok(ImageManifest::from_reader(manifest.as_slice())?),
+            None => bail!("Failed to download manifest {}:{}", repository, reference),
+        }
+    }
+
+    fn manifest_digest(
+        &self,
+        repository: &str,
+        reference: &str,
+        _token: &str,
+    ) -> anyhow::Result<String> {
+        self.record(format!("digest {}:{}", repository, reference));
+        match self
+            .manifests
+            .get(&(repository.to_string(), reference.to_string()))
+        {
+            Some(manifest) => Ok(format!("sha256:{:x}", Sha256::digest(manifest))),
+            None => bail!("Failed to find manifest {}:{}", repository, reference),
+        }
+    }
+
+    fn blob(
+        &self,
+        repository: &str,
+        digest: &str,
+        _token: &str,
+        sink: &mut dyn DownloadSink,
+        progress: &mut dyn FnMut(u64, Option<u64>),
+    ) -> anyhow::Result<u64> {
+        self.record(format!("blob {}@{}", repository, digest));
+        let Some(blob) = self.blobs.get(digest) else {
+            bail!("Blob {} not found in {}", digest, repository);
+        };
+        sink.write_all(blob)?;
+        let size = blob.len() as u64;
+        progress(size, Some(size));
+        Ok(size)
+    }
+}
@@ -0,0 +1,435 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Opening Workspace Files on the Host
+//!
+//! Agents can ask the host to open a file of the workspace in the host's
+//! default application or editor. The agent only knows the path inside the
+//! container, so the host translates it through the workspace bind mount,
+//! using the host path and mount target recorded in labels when the container
+//! was started.
+//!
+//! Requests are only honored for files inside the workspace. Files reached
+//! through symlinks pointing out of the workspace must lie in one of the
+//! directories configured in `openAllowlist`.
+//!
+//! The container can write the files of its workspace, so the default
+//! application of the host must never be asked to open a file it would run,
+//! such as executables, scripts and launchers like `.command`, `.app`,
+//! `.desktop` or `.jar`. Those are only opened with the configured
+//! `openEditor`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, info};
+
+/// Container label holding the host path of the workspace.
+pub const WORKSPACE_PATH_LABEL: &str = "devcon.workspace";
+
+/// Container label holding the target of the workspace mount.
+pub const WORKSPACE_TARGET_LABEL: &str = "devcon.workspace.target";
+
+/// Container label holding the source of a custom `workspaceMount`, a host
+/// path for bind mounts or the name of a volume.
+pub const WORKSPACE_SOURCE_LABEL: &str = "devcon.workspace.source";
+
+/// Host directory of a workspace and the directory it is mounted at in the
+/// container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMount {
+    pub host: PathBuf,
+    pub container: PathBuf,
+}
+
+impl WorkspaceMount {
+    /// Reads the mount of a workspace from the labels of its container.
+    ///
+    /// Containers started before the mount target was recorded use the
+    /// default `/workspaces/<folder name>`. Volume mounts have no host
+    /// directory and return `None`.
+    pub fn from_labels(label: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let path = PathBuf::from(label(WORKSPACE_PATH_LABEL)?);
+        let host = match label(WORKSPACE_SOURCE_LABEL) {
+            Some(source) if Path::new(&source).is_absolute() => PathBuf::from(source),
+            Some(_) => return None,
+            None => path.clone(),
+        };
+        let container = match label(WORKSPACE_TARGET_LABEL) {
+            Some(target) => PathBuf::from(target),
+            None => Path::new("/workspaces").join(path.file_name()?),
+        };
+        Some(Self { host, container })
+    }
+}
+
+/// Extensions of files and bundles the default application of some host
+/// platform runs rather than shows.
+const LAUNCHER_EXTENSIONS: &[&str] = &[
+    "app",
+    "applescript",
+    "appimage",
+    "bat",
+    "cmd",
+    "com",
+    "command",
+    "cpl",
+    "desktop",
+    "exe",
+    "fileloc",
+    "hta",
+    "inetloc",
+    "jar",
+    "js",
+    "jse",
+    "lnk",
+    "msi",
+    "pif",
+    "pkg",
+    "ps1",
+    "reg",
+    "run",
+    "scpt",
+    "scr",
+    "sh",
+    "terminal",
+    "tool",
+    "url",
+    "vbe",
+    "vbs",
+    "webloc",
+    "workflow",
+    "ws",
+    "wsf",
+    "wsh",
+];
+
+/// Whether the default application would run `path` instead of showing it,
+/// judging by its extension and, on unix, its exec bit.
+fn is_launcher(path: &Path) -> bool {
+    let launcher = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            LAUNCHER_EXTENSIONS
+                .iter()
+                .any(|launcher| launcher.eq_ignore_ascii_case(extension))
+        });
+
+    #[cfg(unix)]
+    let launcher = launcher || {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+
+    launcher
+}
+
+/// Looks up the mount of a workspace by its name.
+type WorkspaceLookup = dyn Fn(&str) -> Option<WorkspaceMount> + Send + Sync;
+
+/// Policy for opening files requested by agents.
+#[derive(Clone)]
+pub struct OpenPolicy {
+    workspaces: Arc<WorkspaceLookup>,
+    allowlist: Vec<PathBuf>,
+    editor: Option<String>,
+}
+
+impl Default for OpenPolicy {
+    /// A policy which knows no workspaces and therefore rejects all requests.
+    fn default() -> Self {
+        Self::new(|_| None, Vec::new(), None)
+    }
+}
+
+impl OpenPolicy {
+    /// Creates a policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `workspaces` - Returns the mount of a workspace by its name
+    /// * `allowlist` - Host directories allowed in addition to the workspace
+    /// * `editor` - Command used to open files in an editor, e.g. `code --reuse-window`
+    pub fn new(
+        workspaces: impl Fn(&str) -> Option<WorkspaceMount> + Send + Sync + 'static,
+        allowlist: Vec<PathBuf>,
+        editor: Option<String>,
+    ) -> Self {
+        Self {
+            workspaces: Arc::new(workspaces),
+            allowlist,
+            editor,
+        }
+    }
+
+    /// Translates a container path of `workspace` to the host path, if allowed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the workspace is unknown, the path lies outside of
+    /// the workspace mount, does not exist, or resolves outside of the allowed
+    /// directories.
+    pub fn resolve(&self, workspace: &str, container_path: &str) -> Result<PathBuf> {
+        let mount = (self.workspaces)(workspace)
+            .with_context(|| format!("Unknown workspace '{}'", workspace))?;
+        let relative = Path::new(container_path)
+            .strip_prefix(&mount.container)
+            .with_context(|| {
+                format!(
+                    "{} is outside of the workspace mount {}",
+                    container_path,
+                    mount.container.display()
+                )
+            })?;
+        let root = mount.host;
+
+        let path = root
+            .join(relative)
+            .canonicalize()
+            .with_context(|| format!("{} does not exist on the host", container_path))?;
+        let root = root.canonicalize()?;
+
+        let allowed = std::iter::once(&root)
+            .chain(&self.allowlist)
+            .any(|dir| path.starts_with(dir));
+        if !allowed {
+            bail!(
+                "{} resolves to {}, which is not in an allowed directory",
+                container_path,
+                path.display()
+            );
+        }
+
+        debug!("Translated {} to {}", container_path, path.display());
+        Ok(path)
+    }
+
+    /// Opens a container path of `workspace` on the host.
+    ///
+    /// The file is opened with the configured editor if `editor` is set, or with
+    /// the default application otherwise. Returns the opened host path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not allowed or cannot be opened, or if
+    /// the default application would run it.
+    pub fn open(&self, workspace: &str, container_path: &str, editor: bool) -> Result<PathBuf> {
+        let path = self.resolve(workspace, container_path)?;
+
+        match self.editor.as_deref().filter(|_| editor) {
+            Some(command) => {
+                info!("Opening {} with {}", path.display(), command);
+                let mut parts = command.split_whitespace();
+                let program = parts.next().context("Editor command is empty")?;
+                let mut child = Command::new(program)
+                    .args(parts)
+                    .arg(&path)
+                    .spawn()
+                    .with_context(|| format!("Failed to start editor '{}'", program))?;
+                std::thread::spawn(move || child.wait());
+            }
+            None if is_launcher(&path) => bail!(
+                "{} would be run by the default application, set openEditor to open it in an editor",
+                path.display()
+            ),
+            None => {
+                info!("Opening {} with the default application", path.display());
+                open::that(&path).context("Failed to open file")?;
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(root: &Path, allowlist: Vec<PathBuf>) -> OpenPolicy {
+        let mount = WorkspaceMount {
+            host: root.to_path_buf(),
+            container: PathBuf::from("/workspaces/project"),
+        };
+        OpenPolicy::new(
+            move |workspace| (workspace == "project").then(|| mount.clone()),
+            allowlist,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_workspace_mount_from_labels() {
+        let labels = |pairs: &[(&str, &str)]| {
+            let pairs: Vec<(String, String)> = pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            WorkspaceMount::from_labels(|name| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.clone())
+            })
+        };
+
+        assert_eq!(
+            labels(&[(WORKSPACE_PATH_LABEL, "/home/me/app")]),
+            Some(WorkspaceMount {
+                host: PathBuf::from("/home/me/app"),
+                container: PathBuf::from("/workspaces/app"),
+            })
+        );
+        assert_eq!(
+            labels(&[
+                (WORKSPACE_PATH_LABEL, "/home/me/app"),
+                (WORKSPACE_SOURCE_LABEL, "/home/me"),
+                (WORKSPACE_TARGET_LABEL, "/src"),
+            ]),
+            Some(WorkspaceMount {
+                host: PathBuf::from("/home/me"),
+                container: PathBuf::from("/src"),
+            })
+        );
+        assert_eq!(
+            labels(&[
+                (WORKSPACE_PATH_LABEL, "/home/me/app"),
+                (WORKSPACE_SOURCE_LABEL, "app-volume"),
+                (WORKSPACE_TARGET_LABEL, "/src"),
+            ]),
+            None
+        );
+        assert_eq!(labels(&[]), None);
+    }
+
+    #[test]
+    fn test_resolve_uses_mount_target() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("main.rs"), "").unwrap();
+        let mount = WorkspaceMount {
+            host: root.path().to_path_buf(),
+            container: PathBuf::from("/src"),
+        };
+        let policy = OpenPolicy::new(move |_| Some(mount.clone()), Vec::new(), None);
+
+        assert!(policy.resolve("project", "/src/main.rs").is_ok());
+        assert!(
+            policy
+                .resolve("project", "/workspaces/project/main.rs")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_resolve_translates_workspace_paths() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("src")).unwrap();
+        std::fs::write(root.path().join("src/main.rs"), "").unwrap();
+        let policy = policy(root.path(), Vec::new());
+
+        let path = policy
+            .resolve("project", "/workspaces/project/src/main.rs")
+            .unwrap();
+        assert_eq!(
+            path,
+            root.path().canonicalize().unwrap().join("src/main.rs")
+        );
+
+        assert!(policy.resolve("other", "/workspaces/other/a").is_err());
+        assert!(policy.resolve("project", "/etc/passwd").is_err());
+        assert!(
+            policy
+                .resolve("project", "/workspaces/project/../../etc/passwd")
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_out_of_the_workspace_need_allowlist() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("notes.txt"), "").unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.path().join("link")).unwrap();
+
+        let container_path = "/workspaces/project/link/notes.txt";
+        assert!(
+            policy(root.path(), Vec::new())
+                .resolve("project", container_path)
+                .is_err()
+        );
+
+        let allowlist = vec![outside.path().canonicalize().unwrap()];
+        assert!(
+            policy(root.path(), allowlist)
+                .resolve("project", container_path)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_launchers_are_not_opened_with_the_default_application() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("run.command"), "").unwrap();
+        std::fs::create_dir(root.path().join("Evil.APP")).unwrap();
+        std::fs::write(root.path().join("README.md"), "").unwrap();
+
+        assert!(is_launcher(&root.path().join("run.command")));
+        assert!(is_launcher(&root.path().join("Evil.APP")));
+        assert!(!is_launcher(&root.path().join("README.md")));
+
+        let policy = policy(root.path(), Vec::new());
+        let error = policy
+            .open("project", "/workspaces/project/run.command", false)
+            .unwrap_err();
+        assert!(error.to_string().contains("openEditor"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_executables_are_launchers() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let script = root.path().join("notes.txt");
+        std::fs::write(&script, "#!/bin/sh").unwrap();
+        assert!(!is_launcher(&script));
+
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_launcher(&script));
+        // Directories are searchable, not executable
+        assert!(!is_launcher(root.path()));
+    }
+
+    #[test]
+    fn test_default_policy_rejects_everything() {
+        assert!(
+            OpenPolicy::default()
+                .resolve("project", "/workspaces/project")
+                .is_err()
+        );
+    }
+}
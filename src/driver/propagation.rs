@@ -0,0 +1,231 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Host Environment Propagation
+//!
+//! Containers otherwise run in UTC with a `POSIX` locale and without a git
+//! identity, so commit authors and log timestamps differ from the host. At
+//! `devcon start` the settings enabled in the config are copied from the host:
+//!
+//! - `propagateTimezone`: `TZ` from the host's `TZ` or `/etc/localtime` link
+//! - `propagateLocale`: `LANG`, `LANGUAGE` and `LC_*` from the host environment
+//! - `propagateLocaltime`: `/etc/localtime` bind-mounted read-only
+//! - `gitConfigAllowlist`: the listed keys of the host's global git config,
+//!   passed as `GIT_CONFIG_KEY_<n>`/`GIT_CONFIG_VALUE_<n>` so the container's
+//!   own `~/.gitconfig` stays untouched
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+/// System zone file of the host and the container.
+pub const LOCALTIME: &str = "/etc/localtime";
+
+/// Locale variables copied with `propagateLocale`.
+const LOCALE_VARS: &[&str] = &[
+    "LANG",
+    "LANGUAGE",
+    "LC_ALL",
+    "LC_COLLATE",
+    "LC_CTYPE",
+    "LC_MESSAGES",
+    "LC_MONETARY",
+    "LC_NUMERIC",
+    "LC_TIME",
+];
+
+/// Returns the `KEY=value` environment variables propagated from the host.
+pub fn propagated_env(config: &Config) -> Vec<String> {
+    let mut env = Vec::new();
+
+    if config.timezone_propagated() {
+        match host_timezone() {
+            Some(timezone) => env.push(format!("TZ={}", timezone)),
+            None => warn!("Could not determine the host timezone, TZ is not propagated"),
+        }
+    }
+
+    if config.locale_propagated() {
+        env.extend(host_locale(|name| std::env::var(name).ok()));
+    }
+
+    let keys = config.get_git_config_allowlist();
+    if !keys.is_empty() {
+        let entries: Vec<(String, String)> = keys
+            .into_iter()
+            .filter_map(|key| host_git_config(&key).map(|value| (key, value)))
+            .collect();
+        env.extend(git_config_env(&entries));
+    }
+
+    debug!("Propagating host environment: {:?}", env);
+    env
+}
+
+/// Returns the read-only bind mount of the host's `/etc/localtime`, if enabled.
+///
+/// The link is resolved on the host, as a symlink would point into the
+/// host's zoneinfo directory, which does not exist in the container.
+pub fn localtime_mount(config: &Config) -> Option<String> {
+    if !config.localtime_propagated() {
+        return None;
+    }
+
+    match fs::canonicalize(LOCALTIME) {
+        Ok(source) => Some(format!("{}:{}:ro", source.display(), LOCALTIME)),
+        Err(e) => {
+            warn!("Not mounting {}: {}", LOCALTIME, e);
+            None
+        }
+    }
+}
+
+/// Determines the host's IANA timezone name, e.g. `Europe/Zurich`.
+fn host_timezone() -> Option<String> {
+    if let Ok(timezone) = std::env::var("TZ")
+        && !timezone.is_empty()
+    {
+        return Some(timezone);
+    }
+
+    if let Ok(target) = fs::read_link(LOCALTIME)
+        && let Some(name) = zone_name(&target)
+    {
+        return Some(name);
+    }
+
+    // Debian-based systems without a symlinked /etc/localtime
+    fs::read_to_string("/etc/timezone")
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Extracts the zone name from a zoneinfo path like `/usr/share/zoneinfo/Europe/Zurich`.
+fn zone_name(target: &Path) -> Option<String> {
+    target
+        .to_string_lossy()
+        .split_once("zoneinfo/")
+        .map(|(_, name)| name.to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Collects the set locale variables using `lookup` for the host environment.
+fn host_locale(lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    LOCALE_VARS
+        .iter()
+        .filter_map(|name| {
+            lookup(name)
+                .filter(|value| !value.is_empty())
+                .map(|value| format!("{}={}", name, value))
+        })
+        .collect()
+}
+
+/// Reads a key of the host's global git config (`~/.gitconfig`).
+fn host_git_config(key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--global", "--get", key])
+        .output()
+        .inspect_err(|e| debug!("Failed to run git config: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        debug!("Git config key '{}' is not set on the host", key);
+        return None;
+    }
+
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Formats git config entries as `GIT_CONFIG_COUNT` environment variables.
+fn git_config_env(entries: &[(String, String)]) -> Vec<String> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+
+    let mut env = vec![format!("GIT_CONFIG_COUNT={}", entries.len())];
+    for (i, (key, value)) in entries.iter().enumerate() {
+        env.push(format!("GIT_CONFIG_KEY_{}={}", i, key));
+        env.push(format!("GIT_CONFIG_VALUE_{}={}", i, value));
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_name() {
+        assert_eq!(
+            zone_name(Path::new("/usr/share/zoneinfo/Europe/Zurich")),
+            Some("Europe/Zurich".to_string())
+        );
+        assert_eq!(
+            zone_name(Path::new("/var/db/timezone/zoneinfo/America/New_York")),
+            Some("America/New_York".to_string())
+        );
+        assert_eq!(zone_name(Path::new("/etc/UTC")), None);
+    }
+
+    #[test]
+    fn test_host_locale() {
+        let env = host_locale(|name| match name {
+            "LANG" => Some("de_CH.UTF-8".to_string()),
+            "LC_TIME" => Some(String::new()),
+            _ => None,
+        });
+        assert_eq!(env, vec!["LANG=de_CH.UTF-8".to_string()]);
+    }
+
+    #[test]
+    fn test_git_config_env() {
+        assert!(git_config_env(&[]).is_empty());
+
+        let env = git_config_env(&[
+            ("user.name".to_string(), "Jane Doe".to_string()),
+            ("user.email".to_string(), "jane@example.com".to_string()),
+        ]);
+        assert_eq!(
+            env,
+            vec![
+                "GIT_CONFIG_COUNT=2",
+                "GIT_CONFIG_KEY_0=user.name",
+                "GIT_CONFIG_VALUE_0=Jane Doe",
+                "GIT_CONFIG_KEY_1=user.email",
+                "GIT_CONFIG_VALUE_1=jane@example.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nothing_propagated_by_default() {
+        let config = Config::default();
+        assert!(propagated_env(&config).is_empty());
+        assert!(localtime_mount(&config).is_none());
+    }
+}
@@ -0,0 +1,534 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Registry Cache
+//!
+//! With `registryCache` enabled, `devcon serve` runs a caching proxy for the
+//! package registries of cargo, npm and pip on the host's loopback interface.
+//! Agents make it reachable on the same port inside their containers over
+//! the host port connections of the control channel, and the package
+//! managers of the containers are configured to use it. All containers share
+//! one warm dependency cache, and rebuilds work offline with the packages
+//! cached before.
+//!
+//! Each registry is served below its own path prefix:
+//!
+//! - `/cargo/index/` and `/cargo/crates/` - the crates.io sparse index and crate files
+//! - `/npm/` - the npm registry
+//! - `/pypi/` and `/pypi-files/` - the PyPI simple index and its files
+//!
+//! Registry URLs in index responses are rewritten to the proxy, so package
+//! files are downloaded through the cache as well. Package files never
+//! change once published and are served from the cache. Index responses are
+//! refreshed from the registry and only served from the cache if the
+//! registry cannot be reached or devcon is offline.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info, warn};
+
+use crate::config::Config;
+use crate::driver::feature_process::get_devcon_cache_dir;
+use crate::http_proxy;
+
+/// Time a package file may take to download.
+const FILE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// A registry served by the proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    /// Path prefix of the registry on the proxy, e.g. `npm`
+    pub prefix: String,
+    /// Base URL of the registry, without trailing slash
+    pub url: String,
+    /// Whether every path is a package file
+    pub files: bool,
+}
+
+impl Upstream {
+    fn new(prefix: &str, url: &str, files: bool) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            url: url.to_string(),
+            files,
+        }
+    }
+
+    /// Registries of crates.io, npm and PyPI.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("cargo/index", "https://index.crates.io", false),
+            Self::new("cargo/crates", "https://static.crates.io/crates", true),
+            Self::new("npm", "https://registry.npmjs.org", false),
+            Self::new("pypi", "https://pypi.org", false),
+            Self::new("pypi-files", "https://files.pythonhosted.org", true),
+        ]
+    }
+
+    /// Whether `path` of the registry is a package file, which never changes.
+    /// npm serves tarballs below `<package>/-/`.
+    fn is_file(&self, path: &str) -> bool {
+        self.files || path.contains("/-/")
+    }
+}
+
+/// Caching proxy of package registries.
+pub struct RegistryCache {
+    upstreams: Vec<Upstream>,
+    client: reqwest::blocking::Client,
+    dir: PathBuf,
+    offline: bool,
+}
+
+/// Response of the proxy.
+struct Response {
+    status: String,
+    content_type: String,
+    body: Body,
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+impl Response {
+    fn text(status: &str, message: &str) -> Self {
+        Self {
+            status: status.to_string(),
+            content_type: "text/plain".to_string(),
+            body: Body::Bytes(format!("{}\n", message).into_bytes()),
+        }
+    }
+}
+
+impl RegistryCache {
+    /// Creates a proxy of `upstreams` caching in `dir`, which only serves
+    /// the cache if `offline` is set.
+    pub fn new(
+        upstreams: Vec<Upstream>,
+        client: reqwest::blocking::Client,
+        dir: PathBuf,
+        offline: bool,
+    ) -> Self {
+        Self {
+            upstreams,
+            client,
+            dir,
+            offline,
+        }
+    }
+
+    /// Creates the proxy of the default registries caching in the devcon
+    /// cache directory, using the proxy and CA settings of the config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created or the cache
+    /// directory cannot be determined.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self::new(
+            Upstream::defaults(),
+            http_proxy::http_client(&config.get_ca_certificates())?,
+            get_devcon_cache_dir()?.join("registry"),
+            config.is_offline(),
+        ))
+    }
+
+    /// Serves the proxy on `port` of the loopback interface in the
+    /// background, returning the bound address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the port cannot be bound.
+    pub fn start(self, port: u16) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .with_context(|| format!("Failed to bind registry cache to port {}", port))?;
+        let address = listener.local_addr()?;
+        info!("Registry cache listening on http://{}", address);
+
+        let cache = std::sync::Arc::new(self);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        // Package managers download in parallel
+                        let cache = cache.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = cache.handle(stream) {
+                                debug!("Error handling registry request: {:#}", e);
+                            }
+                        });
+                    }
+                    Err(e) => error!("Error accepting registry connection: {}", e),
+                }
+            }
+        });
+        Ok(address)
+    }
+
+    /// Answers a single HTTP request.
+    fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut host = None;
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("host")
+            {
+                host = Some(value.trim().to_string());
+            }
+            header.clear();
+        }
+
+        let mut request = request_line.split_whitespace();
+        let method = request.next().unwrap_or("GET");
+        let path = request.next().unwrap_or("/");
+        debug!("Registry cache request for {} {}", method, path);
+
+        // Clients reach the proxy through the agent on their own localhost
+        let base = format!(
+            "http://{}",
+            host.unwrap_or_else(|| format!(
+                "localhost:{}",
+                stream.local_addr().map_or(0, |a| a.port())
+            ))
+        );
+        let response = match method {
+            "GET" | "HEAD" => self.respond(path, &base).unwrap_or_else(|e| {
+                warn!("Registry request for {} failed: {:#}", path, e);
+                Response::text("502 Bad Gateway", &format!("{:#}", e))
+            }),
+            _ => Response::text("405 Method Not Allowed", "Method Not Allowed"),
+        };
+        write_response(&mut stream, response, method == "HEAD")
+    }
+
+    /// Serves `path`, fetching it from its registry unless it is a cached
+    /// package file.
+    fn respond(&self, path: &str, base: &str) -> Result<Response> {
+        let Some((upstream, rest)) = self.route(path) else {
+            return Ok(Response::text("404 Not Found", "Not Found"));
+        };
+        let cached = self.cache_path(upstream, rest);
+        let is_file = upstream.is_file(rest);
+
+        let outdated = !is_file || !cached.exists();
+        if outdated && !self.offline {
+            match self.fetch(upstream, rest, &cached, is_file) {
+                Ok(None) => {}
+                Ok(Some(status)) => return Ok(Response::text(&status, "Not Found upstream")),
+                Err(e) if cached.exists() => {
+                    warn!("{:#}, serving {} from the cache", e, path);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if !cached.exists() {
+            return Ok(Response::text(
+                "503 Service Unavailable",
+                &format!("{} is not cached and devcon is offline", path),
+            ));
+        }
+
+        let content_type = fs::read_to_string(cached.with_extension("type"))
+            .unwrap_or_else(|_| "application/octet-stream".to_string());
+        let body = if is_file {
+            Body::File(cached)
+        } else {
+            let contents = fs::read(&cached)?;
+            match String::from_utf8(contents) {
+                Ok(text) => Body::Bytes(self.rewrite(&text, base).into_bytes()),
+                Err(e) => Body::Bytes(e.into_bytes()),
+            }
+        };
+        Ok(Response {
+            status: "200 OK".to_string(),
+            content_type,
+            body,
+        })
+    }
+
+    /// Returns the registry of a request path and the path on the registry.
+    fn route<'a>(&self, path: &'a str) -> Option<(&Upstream, &'a str)> {
+        self.upstreams.iter().find_map(|upstream| {
+            let rest = path
+                .strip_prefix('/')?
+                .strip_prefix(upstream.prefix.as_str())?;
+            rest.starts_with('/').then_some((upstream, rest))
+        })
+    }
+
+    /// Returns the cache file of a path on a registry.
+    fn cache_path(&self, upstream: &Upstream, path: &str) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(path.as_bytes()));
+        self.dir
+            .join(upstream.prefix.replace('/', "-"))
+            .join(&key[..2])
+            .join(&key[2..])
+    }
+
+    /// Downloads a path of a registry into the cache, returning the status
+    /// of client errors like a missing package, which are passed on.
+    fn fetch(
+        &self,
+        upstream: &Upstream,
+        path: &str,
+        cached: &Path,
+        is_file: bool,
+    ) -> Result<Option<String>> {
+        let url = format!("{}{}", upstream.url, path);
+        let mut request = self.client.get(&url);
+        if is_file {
+            request = request.timeout(FILE_TIMEOUT);
+        }
+        let mut response = request
+            .send()
+            .with_context(|| format!("Failed to reach {}", url))?;
+
+        let status = response.status();
+        if status.is_client_error() {
+            return Ok(Some(format!(
+                "{} {}",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or_default()
+            )));
+        }
+        if !status.is_success() {
+            bail!("{} answered {}", url, status);
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let dir = cached.parent().context("Invalid cache path")?;
+        fs::create_dir_all(dir)?;
+        // Only complete downloads are moved into place
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        response
+            .copy_to(&mut file)
+            .with_context(|| format!("Failed to download {}", url))?;
+        fs::write(cached.with_extension("type"), content_type)?;
+        file.persist(cached)?;
+        debug!("Cached {}", url);
+        Ok(None)
+    }
+
+    /// Replaces the registry URLs in an index response by their proxy URLs.
+    fn rewrite(&self, text: &str, base: &str) -> String {
+        self.upstreams
+            .iter()
+            .fold(text.to_string(), |text, upstream| {
+                text.replace(
+                    &upstream.url,
+                    &format!("{}/{}", base.trim_end_matches('/'), upstream.prefix),
+                )
+            })
+    }
+}
+
+/// Writes a response and closes the connection, without body for `HEAD`.
+fn write_response(stream: &mut TcpStream, response: Response, head: bool) -> Result<()> {
+    let (length, mut body): (u64, Box<dyn Read>) = match response.body {
+        Body::Bytes(bytes) => (bytes.len() as u64, Box::new(io::Cursor::new(bytes))),
+        Body::File(path) => {
+            let file = fs::File::open(&path)?;
+            (file.metadata()?.len(), Box::new(file))
+        }
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status, response.content_type, length
+    )?;
+    if !head {
+        io::copy(&mut body, stream)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Environment variables pointing the package managers of a container to
+/// the proxy on `port`. Cargo has no such variable, the agent writes its
+/// configuration instead.
+pub fn container_env(port: u16) -> Vec<String> {
+    let base = format!("http://localhost:{}", port);
+    vec![
+        format!("DEVCON_REGISTRY_CACHE_PORT={}", port),
+        format!("npm_config_registry={}/npm/", base),
+        format!("YARN_NPM_REGISTRY_SERVER={}/npm", base),
+        format!("PIP_INDEX_URL={}/pypi/simple/", base),
+        "PIP_TRUSTED_HOST=localhost".to_string(),
+        format!("UV_DEFAULT_INDEX={}/pypi/simple/", base),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Starts a registry answering every request with a packument linking a
+    /// tarball, `404` for `/missing` and the tarball itself for `/pkg/-/`.
+    fn registry() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let packument = format!(r#"{{"dist":{{"tarball":"{}/pkg/-/pkg-1.0.0.tgz"}}}}"#, url);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 0 && header.trim_end() != "" {
+                    header.clear();
+                }
+                let (status, body) = match line.split_whitespace().nth(1).unwrap() {
+                    "/missing" => ("404 Not Found", String::new()),
+                    path if path.contains("/-/") => ("200 OK", "tarball".to_string()),
+                    _ => ("200 OK", packument.clone()),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn cache(url: &str, dir: &Path, offline: bool) -> RegistryCache {
+        RegistryCache::new(
+            vec![Upstream::new("npm", url, false)],
+            reqwest::blocking::Client::new(),
+            dir.to_path_buf(),
+            offline,
+        )
+    }
+
+    fn body(response: Response) -> String {
+        match response.body {
+            Body::Bytes(bytes) => String::from_utf8(bytes).unwrap(),
+            Body::File(path) => fs::read_to_string(path).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let temp = tempfile::tempdir().unwrap();
+        let cache = RegistryCache::new(
+            Upstream::defaults(),
+            reqwest::blocking::Client::new(),
+            temp.path().to_path_buf(),
+            false,
+        );
+        let (upstream, rest) = cache.route("/pypi-files/packages/ab/x.whl").unwrap();
+        assert_eq!(upstream.prefix, "pypi-files");
+        assert_eq!(rest, "/packages/ab/x.whl");
+        assert!(upstream.is_file(rest));
+        let (upstream, rest) = cache.route("/pypi/simple/requests/").unwrap();
+        assert_eq!(upstream.prefix, "pypi");
+        assert!(!upstream.is_file(rest));
+        assert!(cache.route("/npmx/left-pad").is_none());
+        assert!(cache.route("/unknown").is_none());
+    }
+
+    #[test]
+    fn test_rewrites_and_caches_packages() {
+        let (url, requests) = registry();
+        let temp = tempfile::tempdir().unwrap();
+        let cache = cache(&url, temp.path(), false);
+
+        let response = cache.respond("/npm/pkg", "http://localhost:4873").unwrap();
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(response.content_type, "application/json");
+        assert_eq!(
+            body(response),
+            r#"{"dist":{"tarball":"http://localhost:4873/npm/pkg/-/pkg-1.0.0.tgz"}}"#
+        );
+
+        // Package files are downloaded once
+        for _ in 0..2 {
+            let response = cache
+                .respond("/npm/pkg/-/pkg-1.0.0.tgz", "http://localhost:4873")
+                .unwrap();
+            assert_eq!(body(response), "tarball");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        let response = cache
+            .respond("/npm/missing", "http://localhost:4873")
+            .unwrap();
+        assert_eq!(response.status, "404 Not Found");
+    }
+
+    #[test]
+    fn test_serves_cache_offline() {
+        let (url, requests) = registry();
+        let temp = tempfile::tempdir().unwrap();
+        cache(&url, temp.path(), false)
+            .respond("/npm/pkg", "http://localhost:4873")
+            .unwrap();
+
+        let offline = cache(&url, temp.path(), true);
+        let response = offline
+            .respond("/npm/pkg", "http://localhost:15080")
+            .unwrap();
+        assert!(body(response).contains("http://localhost:15080/npm/pkg/-/"));
+        let response = offline
+            .respond("/npm/other", "http://localhost:15080")
+            .unwrap();
+        assert_eq!(response.status, "503 Service Unavailable");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_container_env() {
+        let env = container_env(15080);
+        assert!(env.contains(&"DEVCON_REGISTRY_CACHE_PORT=15080".to_string()));
+        assert!(env.contains(&"npm_config_registry=http://localhost:15080/npm/".to_string()));
+        assert!(env.contains(&"PIP_INDEX_URL=http://localhost:15080/pypi/simple/".to_string()));
+    }
+}
@@ -27,140 +27,310 @@
 //! Docker, Podman, etc.).
 
 use std::{
-    collections::VecDeque,
-    io::{BufRead, BufReader},
-    path::Path,
-    process::Child,
+    collections::{BTreeMap, HashMap, VecDeque},
+    fs,
+    io::{BufRead, BufReader, IsTerminal, Read},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus},
+    str::FromStr,
     sync::{Arc, Mutex},
-    time::Duration,
+    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use console::Style;
 use indicatif::{ProgressBar, ProgressStyle};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::driver::cancel::CancelToken;
+use crate::driver::metrics::format_bytes;
+use crate::driver::runtime::{apple::AppleRuntime, docker::DockerRuntime};
+use crate::exit_code;
 
 pub mod apple;
 pub mod docker;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+
+/// Errors of container runtimes.
+#[derive(Debug, thiserror::Error)]
+pub enum RuntimeError {
+    /// Auto-detection found no usable runtime.
+    #[error(
+        "No usable container runtime found. Please install Docker, Podman or Apple's container CLI, see `devcon runtime list`."
+    )]
+    NotAvailable,
+
+    /// The configured runtime name is not known.
+    #[error("Unknown runtime: {0}")]
+    Unknown(String),
+
+    /// The image of the workspace has not been built.
+    #[error("Image not found. Run 'devcon build' or 'devcon up' first.")]
+    ImageNotFound,
+
+    /// The container of the workspace is not running.
+    #[error("Container not running. Run 'devcon start' or 'devcon up' first.")]
+    ContainerNotRunning,
 
-/// Stream build output from a child process with a rolling window display.
+    /// The runtime's build command failed, e.g. a Dockerfile step.
+    #[error("{0} build command failed")]
+    BuildFailed(&'static str),
+
+    /// The build was cancelled.
+    #[error("Build cancelled")]
+    Cancelled,
+
+    /// A command executed in the container exited with a non-zero code.
+    #[error("Command exited with code {0}")]
+    CommandFailed(i32),
+}
+
+impl RuntimeError {
+    /// Returns the process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotAvailable => exit_code::RUNTIME_UNAVAILABLE,
+            Self::ImageNotFound | Self::ContainerNotRunning => exit_code::CONTAINER_NOT_RUNNING,
+            Self::Unknown(_) => exit_code::CONFIG,
+            Self::BuildFailed(_) => exit_code::BUILD_FAILED,
+            Self::Cancelled => exit_code::INTERRUPTED,
+            Self::CommandFailed(code) => *code,
+        }
+    }
+}
+
+/// Creates the runtime of the given name with its configuration.
+///
+/// # Errors
+///
+/// Returns an error if the runtime name is unknown.
+pub fn create_runtime(
+    config: &Config,
+    runtime_name: &str,
+) -> anyhow::Result<Box<dyn ContainerRuntime>> {
+    let runtime_config = config.get_runtime_config();
+
+    let runtime: Box<dyn ContainerRuntime> = match runtime_name {
+        "docker" => {
+            let docker_config = runtime_config.docker.unwrap_or_default();
+            Box::new(DockerRuntime::new(docker_config))
+        }
+        "podman" => {
+            let docker_config = runtime_config.docker.unwrap_or_default();
+            Box::new(DockerRuntime::podman(docker_config))
+        }
+        "apple" => {
+            let apple_config = runtime_config.apple.unwrap_or_default();
+            Box::new(AppleRuntime::new(apple_config))
+        }
+        _ => return Err(RuntimeError::Unknown(runtime_name.to_string()).into()),
+    };
+
+    Ok(runtime)
+}
+
+/// Time a cancelled build process gets to stop before it is killed.
+const BUILD_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of output lines shown when a build fails.
+pub const BUILD_FAILURE_LINES: usize = 30;
+
+/// How the output of an image build is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildProgress {
+    /// `Tty` on an interactive terminal, `Plain` otherwise.
+    #[default]
+    Auto,
+    /// Every line as it arrives.
+    Plain,
+    /// A spinner with the last lines of output.
+    Tty,
+    /// Nothing unless the build fails.
+    Quiet,
+}
+
+impl BuildProgress {
+    /// Resolves `Auto` for the kind of standard output.
+    pub fn resolve(self, is_terminal: bool) -> Self {
+        match self {
+            Self::Auto if is_terminal => Self::Tty,
+            Self::Auto => Self::Plain,
+            progress => progress,
+        }
+    }
+}
+
+impl FromStr for BuildProgress {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "plain" => Ok(Self::Plain),
+            "tty" => Ok(Self::Tty),
+            "quiet" => Ok(Self::Quiet),
+            _ => anyhow::bail!(
+                "Invalid build progress '{}', expected auto, plain, tty or quiet",
+                value
+            ),
+        }
+    }
+}
+
+/// Inputs of an image build besides the Dockerfile and its context.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Build arguments in format "KEY=value".
+    pub build_args: Vec<String>,
+    /// Files available to `RUN --mount=type=secret` by id, not stored in the image.
+    pub secrets: Vec<BuildSecret>,
+    /// Images the build cache is imported from.
+    pub cache_from: Vec<String>,
+    /// Whether the build cache is embedded in the image, so it can be used
+    /// with `cache_from` once pushed.
+    pub inline_cache: bool,
+    /// Additional directories by name, available with `COPY --from=<name>`.
+    pub build_contexts: Vec<(String, PathBuf)>,
+}
+
+/// A file passed to a build as BuildKit secret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildSecret {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+/// Display and capture of the output of an image build, shared by all runtimes.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOutput {
+    /// How the output is shown while building.
+    pub progress: BuildProgress,
+    /// File the complete output is saved to.
+    pub log_path: Option<PathBuf>,
+    /// Token stopping the build process once cancelled.
+    pub cancel: CancelToken,
+}
+
+/// Stream build output from a child process.
 ///
 /// This function:
 /// - Captures stdout and stderr from the child process
-/// - Prints all lines as they arrive (permanent output)
-/// - Maintains a rolling buffer of the last 10 lines displayed at the bottom
-/// - If the process fails, prints the complete output again
+/// - Shows the lines according to the progress mode, for `tty` as a rolling
+///   window of the last 10 lines
+/// - Saves the complete output to the build log
+/// - If the process fails, prints the last lines and the path of the log
+/// - Stops the process once the build is cancelled
 ///
 /// # Arguments
 ///
 /// * `child` - The child process to stream output from
+/// * `output` - Progress mode, log file and cancellation of the build
 ///
 /// # Returns
 ///
-/// Returns `Ok(ExitStatus)` if the process completes, `Err` if there's an I/O error
-pub fn stream_build_output(mut child: Child) -> anyhow::Result<std::process::ExitStatus> {
+/// Returns `Ok(ExitStatus)` if the process completes, `Err` if there's an I/O
+/// error or the build was cancelled
+pub fn stream_build_output(
+    child: Child,
+    output: &BuildOutput,
+) -> anyhow::Result<std::process::ExitStatus> {
+    stream_output(child, output, &BUILD_MESSAGES)
+}
+
+/// Messages printed around the streamed output of a process.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamMessages {
+    /// Printed before the output, unless quiet.
+    pub start: &'static str,
+    /// Printed after a successful run, unless quiet.
+    pub done: &'static str,
+    /// Heading of the output tail printed after a failure.
+    pub failed: &'static str,
+}
+
+/// Messages of an image build.
+const BUILD_MESSAGES: StreamMessages = StreamMessages {
+    start: "Building Image..",
+    done: "Building image complete",
+    failed: "Build failed!",
+};
+
+/// Streams the output of a child process like [`stream_build_output`], with
+/// other messages around it, e.g. for host commands run as part of the build.
+///
+/// # Errors
+///
+/// Returns an error if there's an I/O error or the build was cancelled.
+pub fn stream_output(
+    mut child: Child,
+    output: &BuildOutput,
+    messages: &StreamMessages,
+) -> anyhow::Result<std::process::ExitStatus> {
+    let progress = output.progress.resolve(std::io::stdout().is_terminal());
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
 
-    println!("Building Image..");
+    if progress != BuildProgress::Quiet {
+        println!("{}", messages.start);
+    }
 
     // Buffer for last 10 lines (rolling window)
     let rolling_buffer: Arc<Mutex<VecDeque<String>>> =
         Arc::new(Mutex::new(VecDeque::with_capacity(10)));
 
-    // Buffer for all output (for error reporting)
+    // Buffer for all output (for the log and error reporting)
     let all_output: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
 
-    let rolling_clone = Arc::clone(&rolling_buffer);
-    let all_output_clone = Arc::clone(&all_output);
-
-    let bar = ProgressBar::new_spinner();
-    bar.set_style(ProgressStyle::default_spinner().template("{spinner} {msg}")?);
-    bar.enable_steady_tick(Duration::from_millis(100));
-
-    // Stream stdout in a separate thread
-    let stdout_thread = stdout.map(|stdout| {
-        let rolling = Arc::clone(&rolling_buffer);
-        let all = Arc::clone(&all_output);
-        std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line_result in reader.lines() {
-                // Handle UTF-8 decoding errors gracefully
-                let line = match line_result {
-                    Ok(l) => l,
-                    Err(_) => continue, // Skip lines with UTF-8 errors
-                };
-
-                // Try to strip ANSI escapes safely, fall back to original if it fails
-                let clean_line = std::panic::catch_unwind(|| strip_ansi_escapes::strip_str(&line))
-                    .unwrap_or_else(|_| line.clone());
-
-                // Add to rolling buffer
-                let mut roll = rolling.lock().unwrap();
-                if roll.len() >= 10 {
-                    roll.pop_front();
-                }
-                roll.push_back(clean_line);
-                drop(roll);
+    let bar = if progress == BuildProgress::Tty {
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::default_spinner().template("{spinner} {msg}")?);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
 
-                // Add to complete output (with original ANSI codes)
-                let mut all_buf = all.lock().unwrap();
-                all_buf.push(line);
-            }
-        })
-    });
+    // Stream stdout and stderr in separate threads
+    let plain = progress == BuildProgress::Plain;
+    let stdout_thread =
+        stdout.map(|stdout| collect_lines(stdout, &rolling_buffer, &all_output, plain));
+    let stderr_thread =
+        stderr.map(|stderr| collect_lines(stderr, &rolling_buffer, &all_output, plain));
 
-    // Stream stderr in a separate thread
-    let stderr_thread = stderr.map(|stderr| {
-        let rolling = Arc::clone(&rolling_clone);
-        let all = Arc::clone(&all_output_clone);
+    // Update progress bar with last 10 lines
+    let update_thread = (progress == BuildProgress::Tty).then(|| {
+        let display_buffer = Arc::clone(&rolling_buffer);
+        let display_bar = bar.clone();
         std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            for line_result in reader.lines() {
-                // Handle UTF-8 decoding errors gracefully
-                let line = match line_result {
-                    Ok(l) => l,
-                    Err(_) => continue, // Skip lines with UTF-8 errors
-                };
-
-                // Try to strip ANSI escapes safely, fall back to original if it fails
-                let clean_line = std::panic::catch_unwind(|| strip_ansi_escapes::strip_str(&line))
-                    .unwrap_or_else(|_| line.clone());
-
-                // Add to rolling buffer
-                let mut roll = rolling.lock().unwrap();
-                if roll.len() >= 10 {
-                    roll.pop_front();
+            let grey_style = Style::new().dim();
+            loop {
+                let buf = display_buffer.lock().unwrap();
+                if !buf.is_empty() {
+                    let display_text = format!(
+                        "\n{}",
+                        buf.iter()
+                            .map(|s| grey_style.apply_to(s).to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    );
+                    display_bar.set_message(display_text);
                 }
-                roll.push_back(clean_line);
-                drop(roll);
-
-                // Add to complete output (with original ANSI codes)
-                let mut all_buf = all.lock().unwrap();
-                all_buf.push(line);
+                drop(buf);
+                std::thread::sleep(Duration::from_millis(100));
             }
         })
     });
 
-    // Update progress bar with last 10 lines
-    let display_buffer = Arc::clone(&rolling_clone);
-    let display_bar = bar.clone();
-    let update_thread = std::thread::spawn(move || {
-        let grey_style = Style::new().dim();
-        loop {
-            let buf = display_buffer.lock().unwrap();
-            if !buf.is_empty() {
-                let display_text = format!(
-                    "\n{}",
-                    buf.iter()
-                        .map(|s| grey_style.apply_to(s).to_string())
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                );
-                display_bar.set_message(display_text);
-            }
-            drop(buf);
-            std::thread::sleep(Duration::from_millis(100));
-        }
-    });
+    let result = wait_for_build(&mut child, &output.cancel, &bar)?;
+    if output.cancel.is_cancelled() {
+        // Processes started by the build may still hold the output open
+        bar.finish_and_clear();
+        save_build_log(output.log_path.as_deref(), &all_output.lock().unwrap());
+        return Err(RuntimeError::Cancelled.into());
+    }
 
     // Wait for stdout thread to complete
     if let Some(handle) = stdout_thread {
@@ -172,27 +342,148 @@ pub fn stream_build_output(mut child: Child) -> anyhow::Result<std::process::Exi
         let _ = handle.join();
     }
 
-    let result = child.wait()?;
-
     // Stop the update thread
     bar.finish_and_clear();
     drop(update_thread);
 
-    // If the build failed, print the complete output for debugging
+    let full_output = all_output.lock().unwrap();
+    let log_path = save_build_log(output.log_path.as_deref(), &full_output);
+
+    // If the build failed, print the end of the output for debugging
     if !result.success() {
-        eprintln!("\n=== Build failed! Complete output: ===");
-        let full_output = all_output_clone.lock().unwrap();
-        for line in full_output.iter() {
+        let shown = match log_path {
+            Some(_) => &full_output[full_output.len().saturating_sub(BUILD_FAILURE_LINES)..],
+            None => &full_output[..],
+        };
+        eprintln!(
+            "\n=== {} Last {} lines of output: ===",
+            messages.failed,
+            shown.len()
+        );
+        for line in shown {
             eprintln!("{}", line);
         }
-        eprintln!("=== End of output ===\n");
-    } else {
-        println!("Building image complete");
+        eprintln!("=== End of output ===");
+        if let Some(path) = log_path {
+            eprintln!("Full log: {}", path.display());
+        }
+        eprintln!();
+    } else if progress != BuildProgress::Quiet {
+        println!("{}", messages.done);
     }
 
     Ok(result)
 }
 
+/// Reads the lines of a build output stream on a separate thread.
+///
+/// Lines are added to the rolling window and the complete output, and
+/// printed right away in plain mode.
+fn collect_lines(
+    stream: impl Read + Send + 'static,
+    rolling: &Arc<Mutex<VecDeque<String>>>,
+    all: &Arc<Mutex<Vec<String>>>,
+    plain: bool,
+) -> JoinHandle<()> {
+    let rolling = Arc::clone(rolling);
+    let all = Arc::clone(all);
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stream);
+        for line_result in reader.lines() {
+            // Handle UTF-8 decoding errors gracefully
+            let line = match line_result {
+                Ok(l) => l,
+                Err(_) => continue, // Skip lines with UTF-8 errors
+            };
+
+            // Try to strip ANSI escapes safely, fall back to original if it fails
+            let clean_line = std::panic::catch_unwind(|| strip_ansi_escapes::strip_str(&line))
+                .unwrap_or_else(|_| line.clone());
+
+            if plain {
+                println!("{}", line);
+            }
+
+            // Add to rolling buffer
+            let mut roll = rolling.lock().unwrap();
+            if roll.len() >= 10 {
+                roll.pop_front();
+            }
+            roll.push_back(clean_line.clone());
+            drop(roll);
+
+            // Add to complete output (without ANSI codes, for the log)
+            let mut all_buf = all.lock().unwrap();
+            all_buf.push(clean_line);
+        }
+    })
+}
+
+/// Saves the complete build output, returning the path if it was written.
+fn save_build_log(path: Option<&Path>, lines: &[String]) -> Option<PathBuf> {
+    let path = path?;
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            fs::write(
+                path,
+                lines.iter().map(|l| format!("{}\n", l)).collect::<String>(),
+            )
+        });
+    match result {
+        Ok(()) => Some(path.to_path_buf()),
+        Err(e) => {
+            warn!("Failed to save the build log {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Waits for a build process, stopping it once `cancel` is cancelled.
+///
+/// The process is asked to stop first, so the runtime can abort the build
+/// cleanly, and killed if it is still running after [`BUILD_STOP_TIMEOUT`].
+fn wait_for_build(
+    child: &mut Child,
+    cancel: &CancelToken,
+    bar: &ProgressBar,
+) -> anyhow::Result<ExitStatus> {
+    let mut stopping: Option<Instant> = None;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        match stopping {
+            None if cancel.is_cancelled() => {
+                bar.suspend(|| eprintln!("Cancelling build.."));
+                terminate(child);
+                stopping = Some(Instant::now());
+            }
+            Some(since) if since.elapsed() > BUILD_STOP_TIMEOUT => {
+                let _ = child.kill();
+            }
+            _ => {}
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Asks a process to stop, like Ctrl+C does for the runtime CLI.
+#[cfg(unix)]
+fn terminate(child: &mut Child) {
+    // SAFETY: kill has no memory safety requirements, the pid is our child
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) {
+    let _ = child.kill();
+}
+
 /// Parameters for container runtime execution.
 /// This struct encapsulates additional settings for running containers.
 ///
@@ -204,8 +495,64 @@ pub struct RuntimeParameters {
     /// Port forwards to apply to the container.
     pub ports: Vec<crate::devcontainer::ForwardPort>,
 
+    /// Ports published by the runtime itself, from the legacy `appPort`.
+    pub published_ports: Vec<crate::devcontainer::ForwardPort>,
+
     /// Whether the container requires privileged mode.
     pub requires_privileged: bool,
+
+    /// Linux capabilities to add to the container.
+    pub cap_add: Vec<String>,
+
+    /// Whether to run an init process reaping zombie processes as PID 1.
+    pub init: bool,
+
+    /// Entrypoint replacing the one of the image.
+    pub entrypoint: Option<String>,
+
+    /// Arguments passed to the entrypoint.
+    pub command: Vec<String>,
+
+    /// CPU and memory limits of the container.
+    pub limits: ResourceLimits,
+}
+
+/// Label recording the CPU limit a container was started with.
+pub const CPUS_LABEL: &str = "devcon.cpus";
+
+/// Label recording the memory limit a container was started with.
+pub const MEMORY_LABEL: &str = "devcon.memory";
+
+/// CPU and memory limits applied to a container at run time.
+///
+/// Values use the runtime CLI format, e.g. `"1.5"` CPUs and `"4g"` memory.
+/// `None` leaves the resource unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub cpus: Option<String>,
+    pub memory: Option<String>,
+}
+
+impl ResourceLimits {
+    /// Reads the limits a listed container was started with from its labels.
+    pub fn from_handle(handle: &dyn ContainerHandle) -> Self {
+        Self {
+            cpus: handle.label(CPUS_LABEL).map(str::to_string),
+            memory: handle.label(MEMORY_LABEL).map(str::to_string),
+        }
+    }
+
+    /// Returns the labels recording these limits on the container.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        if let Some(cpus) = &self.cpus {
+            labels.push(format!("{}={}", CPUS_LABEL, cpus));
+        }
+        if let Some(memory) = &self.memory {
+            labels.push(format!("{}={}", MEMORY_LABEL, memory));
+        }
+        labels
+    }
 }
 
 /// Trait for container runtime implementations.
@@ -215,6 +562,14 @@ pub struct RuntimeParameters {
 pub trait ContainerHandle: Send {
     /// Returns the container ID.
     fn id(&self) -> &str;
+
+    /// Returns the value of a label attached to the container, if known.
+    ///
+    /// Handles returned by `run` don't carry labels; handles returned by
+    /// `list` are populated from the runtime's container metadata.
+    fn label(&self, _key: &str) -> Option<&str> {
+        None
+    }
 }
 
 pub trait ContainerRuntime: Send {
@@ -225,15 +580,19 @@ pub trait ContainerRuntime: Send {
     /// * `dockerfile_path` - Path to the Dockerfile
     /// * `context_path` - Build context directory path
     /// * `image_tag` - Tag to apply to the built image
+    /// * `options` - Build arguments, secrets and cache sources
+    /// * `output` - Progress mode, log file and cancellation of the build
     ///
     /// # Errors
     ///
-    /// Returns an error if the build command fails.
+    /// Returns an error if the build command fails or is cancelled.
     fn build(
         &self,
         dockerfile_path: &Path,
         context_path: &Path,
         image_tag: &str,
+        options: &BuildOptions,
+        output: &BuildOutput,
     ) -> anyhow::Result<()>;
 
     /// Starts a container instance.
@@ -242,7 +601,7 @@ pub trait ContainerRuntime: Send {
     ///
     /// * `image_tag` - Image to run
     /// * `volume_mount` - Volume mount in format "host_path:container_path"
-    /// * `label` - Label in format "key=value"
+    /// * `labels` - Labels in format "key=value"
     /// * `env_vars` - Environment variables to set
     /// * `runtime_parameters` - Additional runtime parameters
     ///
@@ -253,7 +612,7 @@ pub trait ContainerRuntime: Send {
         &self,
         image_tag: &str,
         volume_mount: &str,
-        label: &str,
+        labels: &[String],
         env_vars: &[String],
         runtime_parameters: RuntimeParameters,
     ) -> anyhow::Result<Box<dyn ContainerHandle>>;
@@ -268,7 +627,8 @@ pub trait ContainerRuntime: Send {
     ///
     /// # Errors
     ///
-    /// Returns an error if the exec command fails.
+    /// Returns [`RuntimeError::CommandFailed`] with the exit code if the
+    /// command fails, or an error if the exec command cannot be run.
     fn exec(
         &self,
         container_handle: &dyn ContainerHandle,
@@ -277,6 +637,35 @@ pub trait ContainerRuntime: Send {
         attach_stdin: bool,
     ) -> anyhow::Result<()>;
 
+    /// Stops a running container.
+    ///
+    /// Containers are started with `--rm`, so stopping also removes them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stop command fails.
+    fn stop(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<()>;
+
+    /// Suspends all processes of a running container, keeping its state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot pause containers or the pause
+    /// command fails.
+    fn pause(&self, _container_handle: &dyn ContainerHandle) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support pausing containers")
+    }
+
+    /// Resumes the processes of a container suspended with [`Self::pause`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot pause containers or the unpause
+    /// command fails.
+    fn unpause(&self, _container_handle: &dyn ContainerHandle) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support pausing containers")
+    }
+
     /// Lists running containers.
     ///
     /// # Returns
@@ -300,6 +689,195 @@ pub trait ContainerRuntime: Send {
     /// Returns an error if the list images command fails or output cannot be parsed.
     fn images(&self) -> anyhow::Result<Vec<String>>;
 
+    /// Checks whether an image is present locally, without pulling it.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - Image reference, e.g. `mcr.microsoft.com/devcontainers/base:ubuntu`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime CLI cannot be executed.
+    fn image_exists(&self, image: &str) -> anyhow::Result<bool>;
+
+    /// Returns the labels of a local image, empty if it has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image does not exist or the inspect output
+    /// cannot be parsed.
+    fn image_labels(&self, image: &str) -> anyhow::Result<HashMap<String, String>>;
+
+    /// Returns the entrypoint followed by the command of a local image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image does not exist or the inspect output
+    /// cannot be parsed.
+    fn image_command(&self, image: &str) -> anyhow::Result<Vec<String>>;
+
+    /// Lists the mounts of a running container.
+    ///
+    /// # Returns
+    ///
+    /// A vector of mounts in format "source:destination".
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inspect command fails or output cannot be parsed.
+    fn mounts(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<Vec<String>>;
+
+    /// Inspects a running container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inspect command fails or output cannot be parsed.
+    fn inspect(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<ContainerDetails>;
+
+    /// Commits the filesystem of a running container to an image.
+    ///
+    /// Named volumes are not part of the image.
+    ///
+    /// # Arguments
+    ///
+    /// * `container_handle` - Handle of the container
+    /// * `image` - Tag of the new image
+    /// * `labels` - Labels in format "key=value" added to the image
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime cannot commit containers or the commit
+    /// command fails.
+    fn commit(
+        &self,
+        _container_handle: &dyn ContainerHandle,
+        _image: &str,
+        _labels: &[String],
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support committing containers")
+    }
+
+    /// Adds the tag `target` to the local image `source`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source image does not exist or the tag command
+    /// fails.
+    fn tag_image(&self, _source: &str, _target: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support tagging images")
+    }
+
+    /// Removes a local image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image is in use or the remove command fails.
+    fn remove_image(&self, _image: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support removing images")
+    }
+
+    /// Pulls an image from its registry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image does not exist or the pull command
+    /// fails.
+    fn pull_image(&self, _image: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support pulling images")
+    }
+
+    /// Writes a local image with its layers to a tar archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image does not exist or the save command fails.
+    fn save_image(&self, _image: &str, _archive: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support saving images")
+    }
+
+    /// Loads the images of a tar archive written by [`Self::save_image`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or the load command
+    /// fails.
+    fn load_image(&self, _archive: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support loading images")
+    }
+
+    /// Removes a named volume which is not used by any container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the volume is in use or the remove command fails.
+    fn remove_volume(&self, _name: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support removing volumes")
+    }
+
+    /// Creates a network containers can join, unless it exists already.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime has no user-defined networks or the
+    /// create command fails.
+    fn create_network(&self, _name: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support networks")
+    }
+
+    /// Connects a running container to a network, where the other containers
+    /// reach it by `aliases`. Connecting a connected container does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime has no user-defined networks or the
+    /// connect command fails.
+    fn connect_network(
+        &self,
+        _container_handle: &dyn ContainerHandle,
+        _network: &str,
+        _aliases: &[String],
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support networks")
+    }
+
+    /// Writes a path of a running container to a tar archive on the host.
+    ///
+    /// The archive contains the path as its top-level entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or the copy command fails.
+    fn export_path(
+        &self,
+        _container_handle: &dyn ContainerHandle,
+        _path: &str,
+        _archive: &Path,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support copying files")
+    }
+
+    /// Extracts a tar archive on the host into a directory of a running
+    /// container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be read or the copy command fails.
+    fn import_path(
+        &self,
+        _container_handle: &dyn ContainerHandle,
+        _archive: &Path,
+        _directory: &str,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("The container runtime does not support copying files")
+    }
+
+    /// Samples the CPU, memory and network usage of a running container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stats command fails or output cannot be parsed.
+    fn stats(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<ContainerStats>;
+
     /// Get the host address for the runtime.
     ///
     /// This is used to configure containers to connect back to the host.
@@ -308,4 +886,271 @@ pub trait ContainerRuntime: Send {
     ///
     /// A string representing the host address.
     fn get_host_address(&self) -> String;
+
+    /// Subscribes to start, stop, die and OOM events of the containers
+    /// carrying `label` (`key` or `key=value`).
+    ///
+    /// The returned stream blocks until the next event and stops the runtime
+    /// CLI when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the runtime does not report events or its CLI
+    /// cannot be executed.
+    fn events(&self, _label: &str) -> anyhow::Result<EventStream> {
+        anyhow::bail!("The container runtime does not report container events")
+    }
+}
+
+/// Details of a running container as reported by [`ContainerRuntime::inspect`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerDetails {
+    /// ID of the container.
+    pub id: String,
+    /// Image the container was started from.
+    pub image: String,
+    /// State of the container, e.g. `running`.
+    pub state: String,
+    /// When the container was started, `None` if the runtime doesn't report it.
+    pub started_at: Option<String>,
+    /// Labels of the container.
+    pub labels: BTreeMap<String, String>,
+    /// Environment of the container in format "NAME=value".
+    pub env: Vec<String>,
+    /// Mounts of the container in format "source:destination".
+    pub mounts: Vec<String>,
+    /// Destinations of the named volumes of the container by volume name.
+    pub volumes: BTreeMap<String, String>,
+    /// Addresses of the container in its networks.
+    pub addresses: Vec<IpAddr>,
+}
+
+/// Resource usage of a running container as reported by [`ContainerRuntime::stats`].
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerStats {
+    /// CPU usage in percent of one CPU, `None` if the runtime doesn't report it.
+    pub cpu_percent: Option<f64>,
+    /// Memory used in bytes.
+    pub memory_usage: u64,
+    /// Memory available to the container in bytes, `None` if unknown.
+    pub memory_limit: Option<u64>,
+    /// Bytes received over the network.
+    pub network_rx: u64,
+    /// Bytes sent over the network.
+    pub network_tx: u64,
+}
+
+impl ContainerStats {
+    /// Formats the CPU usage, e.g. `12.5%`.
+    pub fn cpu_label(&self) -> String {
+        match self.cpu_percent {
+            Some(percent) => format!("{:.1}%", percent),
+            None => "-".to_string(),
+        }
+    }
+
+    /// Formats the memory usage with the limit, e.g. `512.0 MiB / 2.0 GiB`.
+    pub fn memory_label(&self) -> String {
+        match self.memory_limit {
+            Some(limit) => format!(
+                "{} / {}",
+                format_bytes(self.memory_usage),
+                format_bytes(limit)
+            ),
+            None => format_bytes(self.memory_usage),
+        }
+    }
+
+    /// Formats the received and sent bytes, e.g. `1.5 KiB / 648 B`.
+    pub fn network_label(&self) -> String {
+        format!(
+            "{} / {}",
+            format_bytes(self.network_rx),
+            format_bytes(self.network_tx)
+        )
+    }
+}
+
+/// What happened to a container in a [`RuntimeEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerAction {
+    /// The container was started.
+    Start,
+    /// The container was stopped.
+    Stop,
+    /// The main process of the container exited, with its exit code if known.
+    Die(Option<i32>),
+    /// A process of the container was killed for running out of memory.
+    Oom,
+}
+
+/// Event of a container reported by the runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeEvent {
+    /// ID of the container.
+    pub container_id: String,
+    /// What happened to the container.
+    pub action: ContainerAction,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Labels of the container.
+    pub labels: HashMap<String, String>,
+}
+
+/// Container events read from a running runtime CLI, one per output line.
+pub struct EventStream {
+    child: Child,
+    lines: std::io::Lines<BufReader<std::process::ChildStdout>>,
+    parse: fn(&str) -> Option<RuntimeEvent>,
+}
+
+impl EventStream {
+    /// Reads the events of `child` from its piped stdout, parsing every line
+    /// with `parse`. Lines which are not a container event are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if stdout of the child is not piped.
+    pub fn new(mut child: Child, parse: fn(&str) -> Option<RuntimeEvent>) -> anyhow::Result<Self> {
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Event output of the runtime is not captured"))?;
+        Ok(Self {
+            child,
+            lines: BufReader::new(stdout).lines(),
+            parse,
+        })
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = RuntimeEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            match line {
+                Ok(line) => {
+                    if let Some(event) = (self.parse)(&line) {
+                        return Some(event);
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to read container events: {}", e);
+                    return None;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_stops_build_process() {
+        let child = Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let cancel = CancelToken::new();
+        let canceller = cancel.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            canceller.cancel();
+        });
+
+        let output = BuildOutput {
+            progress: BuildProgress::Quiet,
+            cancel,
+            ..BuildOutput::default()
+        };
+        let started = Instant::now();
+        let error = stream_build_output(child, &output).unwrap_err();
+        assert_eq!(error.to_string(), "Build cancelled");
+        assert!(started.elapsed() < BUILD_STOP_TIMEOUT);
+    }
+
+    #[test]
+    fn test_completed_build_is_not_cancelled() {
+        let child = Command::new("true")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        assert!(
+            stream_build_output(child, &BuildOutput::default())
+                .unwrap()
+                .success()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_log_is_saved() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("workspaces").join("project.build.log");
+        let child = Command::new("sh")
+            .args(["-c", "echo step one; echo step two >&2; exit 1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = BuildOutput {
+            progress: BuildProgress::Quiet,
+            log_path: Some(log_path.clone()),
+            ..BuildOutput::default()
+        };
+
+        assert!(!stream_build_output(child, &output).unwrap().success());
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("step one\n"));
+        assert!(log.contains("step two\n"));
+    }
+
+    #[test]
+    fn test_build_progress() {
+        assert_eq!(
+            "quiet".parse::<BuildProgress>().unwrap(),
+            BuildProgress::Quiet
+        );
+        assert!("verbose".parse::<BuildProgress>().is_err());
+        assert_eq!(BuildProgress::Auto.resolve(true), BuildProgress::Tty);
+        assert_eq!(BuildProgress::Auto.resolve(false), BuildProgress::Plain);
+        assert_eq!(BuildProgress::Quiet.resolve(true), BuildProgress::Quiet);
+    }
+
+    #[test]
+    fn test_container_stats_labels() {
+        let stats = ContainerStats {
+            cpu_percent: Some(12.54),
+            memory_usage: 512 * 1024 * 1024,
+            memory_limit: Some(2 * 1024 * 1024 * 1024),
+            network_rx: 1536,
+            network_tx: 648,
+        };
+        assert_eq!(stats.cpu_label(), "12.5%");
+        assert_eq!(stats.memory_label(), "512.0 MiB / 2.0 GiB");
+        assert_eq!(stats.network_label(), "1.5 KiB / 648 B");
+
+        let stats = ContainerStats::default();
+        assert_eq!(stats.cpu_label(), "-");
+        assert_eq!(stats.memory_label(), "0 B");
+    }
 }
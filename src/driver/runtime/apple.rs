@@ -25,18 +25,20 @@
 //! Implementation of ContainerRuntime trait for Apple's `container` CLI.
 
 use std::{
+    collections::{BTreeMap, HashMap},
+    io::IsTerminal,
     path::Path,
     process::{Command, Stdio},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::bail;
 
 use crate::config::AppleRuntimeConfig;
-use crate::driver::runtime::RuntimeParameters;
+use crate::driver::runtime::{ContainerDetails, ContainerStats, RuntimeParameters};
 use tracing::{debug, trace};
 
-use super::{ContainerRuntime, stream_build_output};
+use super::{BuildOptions, BuildOutput, ContainerRuntime, RuntimeError, stream_build_output};
 
 /// Extract container-side port from a ForwardPort
 fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16> {
@@ -44,8 +46,10 @@ fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16
     match port {
         ForwardPort::Port(p) => Some(*p),
         ForwardPort::HostPort(mapping) => {
-            // Format is "host:container", we want the container port
-            mapping.split(':').nth(1).and_then(|s| {
+            // Format is "[ip:]host:container[/protocol]", we want the container port
+            let container = mapping.rsplit(':').next()?;
+            let container = container.split('/').next().unwrap_or(container);
+            Some(container).and_then(|s| {
                 s.parse::<u16>().ok().or_else(|| {
                     tracing::warn!("Failed to parse container port from mapping: {}", mapping);
                     None
@@ -55,6 +59,84 @@ fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16
     }
 }
 
+/// Formats a ForwardPort as `--publish` argument.
+///
+/// Unlike Docker, the container CLI requires an explicit host port, so plain
+/// ports are published on the same host port.
+fn publish_arg(port: &crate::devcontainer::ForwardPort) -> String {
+    use crate::devcontainer::ForwardPort;
+    match port {
+        ForwardPort::Port(p) => format!("{}:{}", p, p),
+        ForwardPort::HostPort(mapping) => mapping.clone(),
+    }
+}
+
+/// Strips the default registry the container CLI adds to local image names,
+/// e.g. `docker.io/library/devcon-app:latest` to `devcon-app:latest`.
+fn short_reference(reference: &str) -> &str {
+    reference
+        .strip_prefix("docker.io/library/")
+        .unwrap_or(reference)
+}
+
+/// Formats a mount of `container inspect` as "source:destination".
+fn mount_entry(mount: &serde_json::Value) -> String {
+    format!(
+        "{}:{}",
+        mount["source"].as_str().unwrap_or_default(),
+        mount["destination"].as_str().unwrap_or_default()
+    )
+}
+
+/// Reads the details of a container from an entry of `container inspect`.
+fn parse_inspect(container: &serde_json::Value) -> ContainerDetails {
+    let configuration = &container["configuration"];
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    ContainerDetails {
+        id: text(&configuration["id"]),
+        image: text(&configuration["image"]["reference"]),
+        state: text(&container["status"]),
+        started_at: container["startedDate"].as_str().map(str::to_string),
+        labels: serde_json::from_value(configuration["labels"].clone()).unwrap_or_default(),
+        env: serde_json::from_value(configuration["initProcess"]["environment"].clone())
+            .unwrap_or_default(),
+        mounts: configuration["mounts"]
+            .as_array()
+            .map(|mounts| mounts.iter().map(mount_entry).collect())
+            .unwrap_or_default(),
+        // Named volumes are only reported among the mounts
+        volumes: BTreeMap::new(),
+        // Addresses are reported with their prefix length, e.g. `192.168.64.3/24`
+        addresses: container["networks"]
+            .as_array()
+            .map(|networks| {
+                networks
+                    .iter()
+                    .filter_map(|network| network["address"].as_str()?.split('/').next())
+                    .filter_map(|address| address.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Time between the two samples the CPU usage is derived from.
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reads the usage of a container from an entry of `container stats`,
+/// together with the CPU time it used so far in microseconds.
+fn parse_stats(container: &serde_json::Value) -> (ContainerStats, u64) {
+    let bytes = |key: &str| container[key].as_u64().unwrap_or_default();
+    let stats = ContainerStats {
+        cpu_percent: None,
+        memory_usage: bytes("memoryUsageBytes"),
+        memory_limit: container["memoryLimitBytes"].as_u64(),
+        network_rx: bytes("networkRxBytes"),
+        network_tx: bytes("networkTxBytes"),
+    };
+    (stats, bytes("cpuUsageUsec"))
+}
+
 /// Apple's container CLI runtime implementation.
 pub struct AppleRuntime {
     config: AppleRuntimeConfig,
@@ -63,18 +145,46 @@ pub struct AppleRuntime {
 /// Handle for an Apple container instance.
 pub struct AppleContainerHandle {
     id: String,
+    labels: HashMap<String, String>,
 }
 
 impl super::ContainerHandle for AppleContainerHandle {
     fn id(&self) -> &str {
         &self.id
     }
+
+    fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(|v| v.as_str())
+    }
 }
 
 impl AppleRuntime {
     pub fn new(config: AppleRuntimeConfig) -> Self {
         Self { config }
     }
+
+    /// Returns the OCI config of a local image, holding its labels, entrypoint and command.
+    fn image_config(&self, image: &str) -> anyhow::Result<serde_json::Value> {
+        let output = Command::new("container")
+            .arg("image")
+            .arg("inspect")
+            .arg(image)
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Container image inspect command failed")
+        }
+
+        // The config is part of every platform variant
+        let images: Vec<serde_json::Value> =
+            serde_json::from_str(&String::from_utf8_lossy(&output.stdout))?;
+        Ok(images
+            .first()
+            .and_then(|image| image["variants"].as_array())
+            .and_then(|variants| variants.first())
+            .map(|variant| variant["config"]["config"].clone())
+            .unwrap_or_default())
+    }
 }
 
 impl ContainerRuntime for AppleRuntime {
@@ -83,10 +193,25 @@ impl ContainerRuntime for AppleRuntime {
         dockerfile_path: &Path,
         context_path: &Path,
         image_tag: &str,
+        options: &BuildOptions,
+        output: &BuildOutput,
     ) -> anyhow::Result<()> {
         let mut cmd = Command::new("container");
         cmd.arg("build");
 
+        for build_arg in &options.build_args {
+            cmd.arg("--build-arg").arg(build_arg);
+        }
+        // Secret mounts are optional, without them the files are missing
+        if !options.secrets.is_empty()
+            || !options.cache_from.is_empty()
+            || !options.build_contexts.is_empty()
+        {
+            debug!(
+                "Build secrets, cache sources and contexts are not supported by the container CLI"
+            );
+        }
+
         // Add memory limit if configured (default: 4g)
         let memory = self.config.build_memory.as_deref().unwrap_or("4g");
         cmd.arg("--memory").arg(memory);
@@ -106,10 +231,10 @@ impl ContainerRuntime for AppleRuntime {
 
         let child = cmd.spawn()?;
 
-        let result = stream_build_output(child)?;
+        let result = stream_build_output(child, output)?;
 
         if !result.success() {
-            bail!("Container build command failed")
+            return Err(RuntimeError::BuildFailed("Container").into());
         }
 
         Ok(())
@@ -119,7 +244,7 @@ impl ContainerRuntime for AppleRuntime {
         &self,
         image_tag: &str,
         volume_mount: &str,
-        label: &str,
+        labels: &[String],
         env_vars: &[String],
         runtime_parameters: RuntimeParameters,
     ) -> anyhow::Result<Box<dyn super::ContainerHandle>> {
@@ -128,14 +253,34 @@ impl ContainerRuntime for AppleRuntime {
             .arg("--rm")
             .arg("-d")
             .arg("-v")
-            .arg(volume_mount)
-            .arg("-l")
-            .arg(label);
+            .arg(volume_mount);
+
+        for label in labels {
+            cmd.arg("-l").arg(label);
+        }
 
         // Add privileged flag if required
         if runtime_parameters.requires_privileged {
             cmd.arg("--privileged");
         }
+        // Containers run in their own lightweight VM, whose init reaps processes
+        if runtime_parameters.init {
+            debug!("Init process requested, provided by the container VM");
+        }
+        if let Some(entrypoint) = &runtime_parameters.entrypoint {
+            cmd.arg("--entrypoint").arg(entrypoint);
+        }
+        for cap in &runtime_parameters.cap_add {
+            cmd.arg("--cap-add").arg(cap);
+        }
+
+        // Add resource limits
+        if let Some(cpus) = &runtime_parameters.limits.cpus {
+            cmd.arg("--cpus").arg(cpus);
+        }
+        if let Some(memory) = &runtime_parameters.limits.memory {
+            cmd.arg("--memory").arg(memory);
+        }
 
         // Add environment variables
         for env_var in env_vars {
@@ -146,6 +291,7 @@ impl ContainerRuntime for AppleRuntime {
         let excluded_ports: Vec<String> = runtime_parameters
             .ports
             .iter()
+            .chain(&runtime_parameters.published_ports)
             .filter_map(extract_container_port)
             .map(|p| p.to_string())
             .collect();
@@ -187,21 +333,34 @@ impl ContainerRuntime for AppleRuntime {
         }
 
         // Add port forwards
-        for port in runtime_parameters.ports {
-            cmd.arg("-p").arg(port.to_string());
+        for port in runtime_parameters
+            .ports
+            .iter()
+            .chain(&runtime_parameters.published_ports)
+        {
+            cmd.arg("-p").arg(publish_arg(port));
         }
 
-        cmd.arg(image_tag);
+        cmd.arg(image_tag).args(&runtime_parameters.command);
 
+        debug!("Executing container run command: {:?}", cmd);
         let result = cmd.output()?;
 
         if result.status.code() != Some(0) {
-            bail!("Container start command failed")
+            bail!(
+                "Container start command failed: {}",
+                String::from_utf8_lossy(&result.stderr).trim()
+            )
         }
         std::thread::sleep(Duration::from_secs(10));
 
         Ok(Box::new(AppleContainerHandle {
             id: String::from_utf8_lossy(&result.stdout).trim().to_string(),
+            labels: labels
+                .iter()
+                .filter_map(|label| label.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
         }))
     }
 
@@ -213,12 +372,23 @@ impl ContainerRuntime for AppleRuntime {
         attach_stdin: bool,
     ) -> anyhow::Result<()> {
         let mut cmd = Command::new("container");
-        cmd.arg("exec").arg("-t");
+        cmd.arg("exec");
 
+        // A TTY is only allocated for a terminal, otherwise output stays pipeable
+        let tty = std::io::stdout().is_terminal();
+        if tty {
+            cmd.arg("-t");
+        }
         if attach_stdin {
             cmd.arg("-i");
         }
 
+        // The container CLI does not set TERM for TTY sessions like Docker does
+        if tty && !env_vars.iter().any(|env_var| env_var.starts_with("TERM=")) {
+            let term = std::env::var("TERM").unwrap_or_else(|_| "xterm".to_string());
+            cmd.arg("-e").arg(format!("TERM={}", term));
+        }
+
         for env_var in env_vars {
             cmd.arg("-e").arg(env_var);
         }
@@ -228,8 +398,22 @@ impl ContainerRuntime for AppleRuntime {
         debug!("Executing container exec command: {:?}", cmd);
         let result = cmd.status()?;
 
+        match result.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(RuntimeError::CommandFailed(code).into()),
+            None => bail!("Container exec command failed"),
+        }
+    }
+
+    fn stop(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<()> {
+        let result = Command::new("container")
+            .arg("stop")
+            .arg(container_handle.id())
+            .stdout(Stdio::null())
+            .status()?;
+
         if result.code() != Some(0) {
-            bail!("Container exec command failed")
+            bail!("Container stop command failed")
         }
 
         Ok(())
@@ -267,8 +451,18 @@ impl ContainerRuntime for AppleRuntime {
 
                 debug!("Found container with ID: {}", id);
 
+                let labels: HashMap<String, String> = container["configuration"]["labels"]
+                    .as_object()
+                    .map(|labels| {
+                        labels
+                            .iter()
+                            .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
                 let container_name = format!("devcon.{}", project_name);
-                let handle = AppleContainerHandle { id };
+                let handle = AppleContainerHandle { id, labels };
                 Some((
                     container_name,
                     Box::new(handle) as Box<dyn super::ContainerHandle>,
@@ -279,6 +473,66 @@ impl ContainerRuntime for AppleRuntime {
         Ok(result)
     }
 
+    fn pull_image(&self, image: &str) -> anyhow::Result<()> {
+        let result = Command::new("container")
+            .arg("image")
+            .arg("pull")
+            .arg(image)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Container image pull command failed")
+        }
+
+        Ok(())
+    }
+
+    fn save_image(&self, image: &str, archive: &Path) -> anyhow::Result<()> {
+        let result = Command::new("container")
+            .arg("image")
+            .arg("save")
+            .arg("--output")
+            .arg(archive)
+            .arg(image)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Container image save command failed")
+        }
+
+        Ok(())
+    }
+
+    fn load_image(&self, archive: &Path) -> anyhow::Result<()> {
+        let result = Command::new("container")
+            .arg("image")
+            .arg("load")
+            .arg("--input")
+            .arg(archive)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Container image load command failed")
+        }
+
+        Ok(())
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> anyhow::Result<()> {
+        let result = Command::new("container")
+            .arg("image")
+            .arg("tag")
+            .arg(source)
+            .arg(target)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Container image tag command failed")
+        }
+
+        Ok(())
+    }
+
     fn images(&self) -> anyhow::Result<Vec<String>> {
         let output = Command::new("container")
             .arg("image")
@@ -295,23 +549,185 @@ impl ContainerRuntime for AppleRuntime {
             .iter()
             .filter_map(|image| {
                 trace!("Inspecting image: {}", image);
-                let name = &image["reference"];
-                if name.is_null() {
-                    return None;
-                }
-
-                if name.as_str().unwrap_or_default().starts_with("devcon") {
-                    Some(name.as_str().unwrap_or_default().trim().to_string())
-                } else {
-                    None
-                }
+                let name = short_reference(image["reference"].as_str()?.trim());
+                name.starts_with("devcon").then(|| name.to_string())
             })
             .collect();
 
         Ok(result)
     }
 
+    fn image_exists(&self, image: &str) -> anyhow::Result<bool> {
+        let status = Command::new("container")
+            .arg("image")
+            .arg("inspect")
+            .arg(image)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        Ok(status.success())
+    }
+
+    fn image_labels(&self, image: &str) -> anyhow::Result<HashMap<String, String>> {
+        let labels = self.image_config(image)?["Labels"].clone();
+        Ok(serde_json::from_value::<Option<HashMap<String, String>>>(labels)?.unwrap_or_default())
+    }
+
+    fn image_command(&self, image: &str) -> anyhow::Result<Vec<String>> {
+        let config = self.image_config(image)?;
+        let entrypoint: Option<Vec<String>> = serde_json::from_value(config["Entrypoint"].clone())?;
+        let cmd: Option<Vec<String>> = serde_json::from_value(config["Cmd"].clone())?;
+        Ok(entrypoint.into_iter().chain(cmd).flatten().collect())
+    }
+
+    fn mounts(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<Vec<String>> {
+        let output = Command::new("container")
+            .arg("inspect")
+            .arg(container_handle.id())
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Container inspect command failed")
+        }
+
+        let containers: Vec<serde_json::Value> =
+            serde_json::from_str(&String::from_utf8_lossy(&output.stdout))?;
+        let mounts = containers
+            .first()
+            .and_then(|container| container["configuration"]["mounts"].as_array())
+            .cloned()
+            .unwrap_or_default();
+        Ok(mounts.iter().map(mount_entry).collect())
+    }
+
+    fn inspect(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+    ) -> anyhow::Result<ContainerDetails> {
+        let output = Command::new("container")
+            .arg("inspect")
+            .arg(container_handle.id())
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Container inspect command failed")
+        }
+
+        let containers: Vec<serde_json::Value> =
+            serde_json::from_str(&String::from_utf8_lossy(&output.stdout))?;
+        match containers.first() {
+            Some(container) => Ok(parse_inspect(container)),
+            None => bail!("Container inspect returned no container"),
+        }
+    }
+
+    fn stats(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+    ) -> anyhow::Result<ContainerStats> {
+        let sample = || -> anyhow::Result<(ContainerStats, u64)> {
+            let output = Command::new("container")
+                .arg("stats")
+                .arg("--no-stream")
+                .arg("--format")
+                .arg("json")
+                .arg(container_handle.id())
+                .output()?;
+
+            if !output.status.success() {
+                bail!("Container stats command failed")
+            }
+
+            let containers: Vec<serde_json::Value> =
+                serde_json::from_str(&String::from_utf8_lossy(&output.stdout))?;
+            match containers.first() {
+                Some(container) => Ok(parse_stats(container)),
+                None => bail!("Container stats returned no container"),
+            }
+        };
+
+        // The CLI reports the CPU time used so far, the usage in percent is
+        // derived from two samples
+        let started = Instant::now();
+        let (_, first_cpu_usec) = sample()?;
+        std::thread::sleep(CPU_SAMPLE_INTERVAL);
+        let (mut stats, cpu_usec) = sample()?;
+        let elapsed_usec = started.elapsed().as_micros() as f64;
+        stats.cpu_percent =
+            Some(cpu_usec.saturating_sub(first_cpu_usec) as f64 / elapsed_usec * 100.0);
+        Ok(stats)
+    }
+
     fn get_host_address(&self) -> String {
         "host.container.internal".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devcontainer::ForwardPort;
+
+    #[test]
+    fn test_publish_arg() {
+        assert_eq!(publish_arg(&ForwardPort::Port(3000)), "3000:3000");
+        assert_eq!(
+            publish_arg(&ForwardPort::HostPort("8080:80".to_string())),
+            "8080:80"
+        );
+    }
+
+    #[test]
+    fn test_short_reference() {
+        assert_eq!(
+            short_reference("docker.io/library/devcon-app:latest"),
+            "devcon-app:latest"
+        );
+        assert_eq!(short_reference("ghcr.io/org/app:1"), "ghcr.io/org/app:1");
+    }
+
+    #[test]
+    fn test_parse_inspect() {
+        let container = serde_json::json!({
+            "status": "running",
+            "configuration": {
+                "id": "devcon.demo",
+                "image": {"reference": "docker.io/library/devcon-demo:latest"},
+                "labels": {"devcon.project": "demo"},
+                "initProcess": {"environment": ["PATH=/usr/bin"]},
+                "mounts": [{"source": "/Users/me/demo", "destination": "/workspaces/demo"}],
+            },
+            "networks": [{"network": "default", "address": "192.168.64.3/24"}],
+        });
+        let details = parse_inspect(&container);
+        assert_eq!(details.id, "devcon.demo");
+        assert_eq!(details.image, "docker.io/library/devcon-demo:latest");
+        assert_eq!(details.state, "running");
+        assert_eq!(details.labels["devcon.project"], "demo");
+        assert_eq!(details.env, vec!["PATH=/usr/bin"]);
+        assert_eq!(details.mounts, vec!["/Users/me/demo:/workspaces/demo"]);
+        assert_eq!(
+            details.addresses,
+            vec!["192.168.64.3".parse::<std::net::IpAddr>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let container = serde_json::json!({
+            "id": "devcon.demo",
+            "cpuUsageUsec": 2_500_000,
+            "memoryUsageBytes": 536_870_912,
+            "memoryLimitBytes": 2_147_483_648u64,
+            "networkRxBytes": 1500,
+            "networkTxBytes": 648,
+        });
+        let (stats, cpu_usec) = parse_stats(&container);
+        assert_eq!(cpu_usec, 2_500_000);
+        assert_eq!(stats.memory_usage, 536_870_912);
+        assert_eq!(stats.memory_limit, Some(2_147_483_648));
+        assert_eq!(stats.network_rx, 1500);
+        assert_eq!(stats.network_tx, 648);
+    }
+}
@@ -25,6 +25,9 @@
 //! Implementation of ContainerRuntime trait for Docker CLI.
 
 use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::IsTerminal,
     path::Path,
     process::{Command, Stdio},
 };
@@ -35,7 +38,36 @@ use tracing::trace;
 use crate::config::DockerRuntimeConfig;
 use crate::driver::runtime::RuntimeParameters;
 
-use super::{ContainerRuntime, stream_build_output};
+use super::{
+    BuildOptions, BuildOutput, ContainerAction, ContainerDetails, ContainerRuntime, ContainerStats,
+    EventStream, RuntimeError, RuntimeEvent, stream_build_output,
+};
+
+/// Arguments of `docker build` and `podman build` for the build options.
+fn build_option_args(options: &BuildOptions) -> Vec<String> {
+    let mut args = Vec::new();
+    for build_arg in &options.build_args {
+        args.push("--build-arg".to_string());
+        args.push(build_arg.clone());
+    }
+    if options.inline_cache {
+        args.push("--build-arg".to_string());
+        args.push("BUILDKIT_INLINE_CACHE=1".to_string());
+    }
+    for image in &options.cache_from {
+        args.push("--cache-from".to_string());
+        args.push(image.clone());
+    }
+    for secret in &options.secrets {
+        args.push("--secret".to_string());
+        args.push(format!("id={},src={}", secret.id, secret.path.display()));
+    }
+    for (name, path) in &options.build_contexts {
+        args.push("--build-context".to_string());
+        args.push(format!("{}={}", name, path.display()));
+    }
+    args
+}
 
 /// Extract container-side port from a ForwardPort
 fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16> {
@@ -43,8 +75,10 @@ fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16
     match port {
         ForwardPort::Port(p) => Some(*p),
         ForwardPort::HostPort(mapping) => {
-            // Format is "host:container", we want the container port
-            mapping.split(':').nth(1).and_then(|s| {
+            // Format is "[ip:]host:container[/protocol]", we want the container port
+            let container = mapping.rsplit(':').next()?;
+            let container = container.split('/').next().unwrap_or(container);
+            Some(container).and_then(|s| {
                 s.parse::<u16>().ok().or_else(|| {
                     tracing::warn!("Failed to parse container port from mapping: {}", mapping);
                     None
@@ -55,26 +89,82 @@ fn extract_container_port(port: &crate::devcontainer::ForwardPort) -> Option<u16
 }
 
 /// Docker CLI runtime implementation.
+///
+/// Also drives Podman, whose CLI is compatible with the Docker commands used here.
 pub struct DockerRuntime {
     #[allow(dead_code)]
     config: DockerRuntimeConfig,
+    binary: &'static str,
 }
 
 impl DockerRuntime {
     pub fn new(config: DockerRuntimeConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            binary: "docker",
+        }
+    }
+
+    /// Creates a runtime using the `podman` CLI.
+    pub fn podman(config: DockerRuntimeConfig) -> Self {
+        Self {
+            config,
+            binary: "podman",
+        }
+    }
+
+    /// Returns whether the runtime is Podman running rootless.
+    ///
+    /// Rootless containers map the host user to root, so `--userns=keep-id`
+    /// keeps the host user's ID for files in the workspace.
+    fn is_rootless_podman(&self) -> bool {
+        if self.binary != "podman" {
+            return false;
+        }
+        Command::new(self.binary)
+            .arg("info")
+            .arg("--format")
+            .arg("{{.Host.Security.Rootless}}")
+            .output()
+            .is_ok_and(|output| {
+                output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true"
+            })
+    }
+
+    /// Returns the OCI config of a local image, holding its labels, entrypoint and command.
+    fn image_config(&self, image: &str) -> anyhow::Result<serde_json::Value> {
+        let output = Command::new(self.binary)
+            .arg("image")
+            .arg("inspect")
+            .arg("--format")
+            .arg("{{json .Config}}")
+            .arg(image)
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Docker image inspect command failed")
+        }
+
+        Ok(serde_json::from_str(
+            String::from_utf8_lossy(&output.stdout).trim(),
+        )?)
     }
 }
 
 /// Handle for a Docker container instance.
 pub struct DockerContainerHandle {
     id: String,
+    labels: HashMap<String, String>,
 }
 
 impl super::ContainerHandle for DockerContainerHandle {
     fn id(&self) -> &str {
         &self.id
     }
+
+    fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(|v| v.as_str())
+    }
 }
 
 impl ContainerRuntime for DockerRuntime {
@@ -83,13 +173,19 @@ impl ContainerRuntime for DockerRuntime {
         dockerfile_path: &Path,
         context_path: &Path,
         image_tag: &str,
+        options: &BuildOptions,
+        output: &BuildOutput,
     ) -> anyhow::Result<()> {
-        let mut cmd = Command::new("docker");
+        let mut cmd = Command::new(self.binary);
         cmd.arg("build")
+            .args(build_option_args(options))
             .arg("-f")
             .arg(dockerfile_path)
             .arg("-t")
             .arg(image_tag);
+        // Cache and secret mounts need BuildKit, which older Docker versions
+        // only use when asked to
+        cmd.env("DOCKER_BUILDKIT", "1");
 
         cmd.arg(context_path)
             .stdout(Stdio::piped())
@@ -97,10 +193,10 @@ impl ContainerRuntime for DockerRuntime {
 
         let child = cmd.spawn()?;
 
-        let result = stream_build_output(child)?;
+        let result = stream_build_output(child, output)?;
 
         if !result.success() {
-            bail!("Docker build command failed")
+            return Err(RuntimeError::BuildFailed("Docker").into());
         }
 
         Ok(())
@@ -110,24 +206,46 @@ impl ContainerRuntime for DockerRuntime {
         &self,
         image_tag: &str,
         volume_mount: &str,
-        label: &str,
+        labels: &[String],
         env_vars: &[String],
         runtime_parameters: RuntimeParameters,
     ) -> anyhow::Result<Box<dyn super::ContainerHandle>> {
         trace!("Running Docker container with image: {}", image_tag);
-        let mut cmd = Command::new("docker");
+        let mut cmd = Command::new(self.binary);
         cmd.arg("run")
             .arg("--rm")
             .arg("-d")
             .arg("-v")
-            .arg(volume_mount)
-            .arg("--label")
-            .arg(label);
+            .arg(volume_mount);
+
+        for label in labels {
+            cmd.arg("--label").arg(label);
+        }
 
         // Add privileged flag if required
         if runtime_parameters.requires_privileged {
             cmd.arg("--privileged");
         }
+        if runtime_parameters.init {
+            cmd.arg("--init");
+        }
+        if let Some(entrypoint) = &runtime_parameters.entrypoint {
+            cmd.arg("--entrypoint").arg(entrypoint);
+        }
+        for cap in &runtime_parameters.cap_add {
+            cmd.arg("--cap-add").arg(cap);
+        }
+        if self.is_rootless_podman() {
+            cmd.arg("--userns=keep-id");
+        }
+
+        // Add resource limits
+        if let Some(cpus) = &runtime_parameters.limits.cpus {
+            cmd.arg("--cpus").arg(cpus);
+        }
+        if let Some(memory) = &runtime_parameters.limits.memory {
+            cmd.arg("--memory").arg(memory);
+        }
 
         // Add environment variables
         for env_var in env_vars {
@@ -138,6 +256,7 @@ impl ContainerRuntime for DockerRuntime {
         let excluded_ports: Vec<String> = runtime_parameters
             .ports
             .iter()
+            .chain(&runtime_parameters.published_ports)
             .filter_map(extract_container_port)
             .map(|p| p.to_string())
             .collect();
@@ -182,8 +301,11 @@ impl ContainerRuntime for DockerRuntime {
         for port in runtime_parameters.ports {
             cmd.arg("-p").arg(port.to_string());
         }
+        for port in runtime_parameters.published_ports {
+            cmd.arg("-p").arg(port.to_string());
+        }
 
-        cmd.arg(image_tag);
+        cmd.arg(image_tag).args(&runtime_parameters.command);
 
         trace!("Executing Docker command: {:?}", cmd);
 
@@ -195,6 +317,7 @@ impl ContainerRuntime for DockerRuntime {
 
         Ok(Box::new(DockerContainerHandle {
             id: String::from_utf8_lossy(&result.stdout).trim().to_string(),
+            labels: HashMap::new(),
         }))
     }
 
@@ -205,9 +328,13 @@ impl ContainerRuntime for DockerRuntime {
         env_vars: &[String],
         attach_stdin: bool,
     ) -> anyhow::Result<()> {
-        let mut cmd = Command::new("docker");
-        cmd.arg("exec").arg("-t");
+        let mut cmd = Command::new(self.binary);
+        cmd.arg("exec");
 
+        // A TTY is only allocated for a terminal, otherwise output stays pipeable
+        if std::io::stdout().is_terminal() {
+            cmd.arg("-t");
+        }
         if attach_stdin {
             cmd.arg("-i");
         }
@@ -218,15 +345,255 @@ impl ContainerRuntime for DockerRuntime {
 
         let result = cmd.arg(container_handle.id()).args(command).status()?;
 
+        match result.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(RuntimeError::CommandFailed(code).into()),
+            None => bail!("Docker exec command failed"),
+        }
+    }
+
+    fn stop(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("stop")
+            .arg(container_handle.id())
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker stop command failed")
+        }
+
+        Ok(())
+    }
+
+    fn commit(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+        image: &str,
+        labels: &[String],
+    ) -> anyhow::Result<()> {
+        let mut cmd = Command::new(self.binary);
+        cmd.arg("commit");
+        for label in labels {
+            cmd.arg("--change").arg(format!("LABEL {}", label));
+        }
+        let result = cmd
+            .arg(container_handle.id())
+            .arg(image)
+            .stdout(Stdio::null())
+            .status()?;
+
         if result.code() != Some(0) {
-            bail!("Docker exec command failed")
+            bail!("Docker commit command failed")
+        }
+
+        Ok(())
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("tag")
+            .arg(source)
+            .arg(target)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker tag command failed")
+        }
+
+        Ok(())
+    }
+
+    fn pull_image(&self, image: &str) -> anyhow::Result<()> {
+        let result = Command::new(self.binary).arg("pull").arg(image).status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker pull command failed")
+        }
+
+        Ok(())
+    }
+
+    fn save_image(&self, image: &str, archive: &Path) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("save")
+            .arg("--output")
+            .arg(archive)
+            .arg(image)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker save command failed")
+        }
+
+        Ok(())
+    }
+
+    fn load_image(&self, archive: &Path) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("load")
+            .arg("--input")
+            .arg(archive)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker load command failed")
+        }
+
+        Ok(())
+    }
+
+    fn remove_image(&self, image: &str) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("image")
+            .arg("rm")
+            .arg(image)
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker image rm command failed")
+        }
+
+        Ok(())
+    }
+
+    fn remove_volume(&self, name: &str) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("volume")
+            .arg("rm")
+            .arg(name)
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker volume rm command failed")
+        }
+
+        Ok(())
+    }
+
+    fn create_network(&self, name: &str) -> anyhow::Result<()> {
+        let exists = Command::new(self.binary)
+            .arg("network")
+            .arg("inspect")
+            .arg(name)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if exists.success() {
+            return Ok(());
+        }
+
+        let result = Command::new(self.binary)
+            .arg("network")
+            .arg("create")
+            .arg("--label")
+            .arg("devcon.network=link")
+            .arg(name)
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker network create command failed")
+        }
+
+        Ok(())
+    }
+
+    fn connect_network(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+        network: &str,
+        aliases: &[String],
+    ) -> anyhow::Result<()> {
+        let mut cmd = Command::new(self.binary);
+        cmd.arg("network").arg("connect");
+        for alias in aliases {
+            cmd.arg("--alias").arg(alias);
+        }
+        let result = cmd.arg(network).arg(container_handle.id()).output()?;
+
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        // Podman and Docker report connected containers differently
+        if result.status.code() != Some(0) && !stderr.contains("already") {
+            bail!("Docker network connect command failed: {}", stderr.trim())
+        }
+
+        Ok(())
+    }
+
+    fn export_path(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+        path: &str,
+        archive: &Path,
+    ) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("cp")
+            .arg(format!("{}:{}", container_handle.id(), path))
+            .arg("-")
+            .stdout(File::create(archive)?)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker cp command failed")
+        }
+
+        Ok(())
+    }
+
+    fn import_path(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+        archive: &Path,
+        directory: &str,
+    ) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("cp")
+            .arg("--archive")
+            .arg("-")
+            .arg(format!("{}:{}", container_handle.id(), directory))
+            .stdin(File::open(archive)?)
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker cp command failed")
+        }
+
+        Ok(())
+    }
+
+    fn pause(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("pause")
+            .arg(container_handle.id())
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker pause command failed")
+        }
+
+        Ok(())
+    }
+
+    fn unpause(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<()> {
+        let result = Command::new(self.binary)
+            .arg("unpause")
+            .arg(container_handle.id())
+            .stdout(Stdio::null())
+            .status()?;
+
+        if result.code() != Some(0) {
+            bail!("Docker unpause command failed")
         }
 
         Ok(())
     }
 
     fn list(&self) -> anyhow::Result<Vec<(String, Box<dyn super::ContainerHandle>)>> {
-        let output = Command::new("docker")
+        let output = Command::new(self.binary)
             .arg("ps")
             .arg("--filter")
             .arg("label=devcon.project")
@@ -246,19 +613,20 @@ impl ContainerRuntime for DockerRuntime {
 
             let container: serde_json::Value = serde_json::from_str(line)?;
 
-            // Parse labels to find devcon.project label value
-            let labels = container["Labels"].as_str().unwrap_or_default();
-            let mut container_name = String::new();
-
             // Labels format: "key1=value1,key2=value2"
-            for label_pair in labels.split(',') {
-                if let Some((key, value)) = label_pair.split_once('=')
-                    && key == "devcon.project"
-                {
-                    container_name = format!("devcon.{}", value);
-                    break;
-                }
-            }
+            let labels: HashMap<String, String> = container["Labels"]
+                .as_str()
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+
+            // Parse labels to find devcon.project label value
+            let container_name = labels
+                .get("devcon.project")
+                .map(|value| format!("devcon.{}", value))
+                .unwrap_or_default();
 
             let id = container["ID"]
                 .as_str()
@@ -267,7 +635,7 @@ impl ContainerRuntime for DockerRuntime {
                 .to_string();
 
             if !container_name.is_empty() {
-                let handle = DockerContainerHandle { id: id.clone() };
+                let handle = DockerContainerHandle { id, labels };
                 result.push((container_name, Box::new(handle)));
             }
         }
@@ -276,7 +644,7 @@ impl ContainerRuntime for DockerRuntime {
     }
 
     fn images(&self) -> anyhow::Result<Vec<String>> {
-        let output = Command::new("docker")
+        let output = Command::new(self.binary)
             .arg("image")
             .arg("list")
             .arg("--format")
@@ -303,7 +671,397 @@ impl ContainerRuntime for DockerRuntime {
         Ok(result)
     }
 
+    fn image_exists(&self, image: &str) -> anyhow::Result<bool> {
+        let status = Command::new(self.binary)
+            .arg("image")
+            .arg("inspect")
+            .arg(image)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+
+        Ok(status.success())
+    }
+
+    fn image_labels(&self, image: &str) -> anyhow::Result<HashMap<String, String>> {
+        // Images without labels report `null`
+        let labels: Option<HashMap<String, String>> =
+            serde_json::from_value(self.image_config(image)?["Labels"].clone())?;
+        Ok(labels.unwrap_or_default())
+    }
+
+    fn image_command(&self, image: &str) -> anyhow::Result<Vec<String>> {
+        let config = self.image_config(image)?;
+        let entrypoint: Option<Vec<String>> = serde_json::from_value(config["Entrypoint"].clone())?;
+        let cmd: Option<Vec<String>> = serde_json::from_value(config["Cmd"].clone())?;
+        Ok(entrypoint.into_iter().chain(cmd).flatten().collect())
+    }
+
+    fn mounts(&self, container_handle: &dyn super::ContainerHandle) -> anyhow::Result<Vec<String>> {
+        let output = Command::new(self.binary)
+            .arg("inspect")
+            .arg("--format")
+            .arg("{{json .Mounts}}")
+            .arg(container_handle.id())
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Docker inspect command failed")
+        }
+
+        let mounts: Vec<serde_json::Value> =
+            serde_json::from_str(String::from_utf8_lossy(&output.stdout).trim())?;
+        Ok(mounts.iter().map(mount_entry).collect())
+    }
+
+    fn inspect(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+    ) -> anyhow::Result<ContainerDetails> {
+        let output = Command::new(self.binary)
+            .arg("inspect")
+            .arg(container_handle.id())
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Docker inspect command failed")
+        }
+
+        let containers: Vec<serde_json::Value> =
+            serde_json::from_str(String::from_utf8_lossy(&output.stdout).trim())?;
+        match containers.first() {
+            Some(container) => Ok(parse_inspect(container)),
+            None => bail!("Docker inspect returned no container"),
+        }
+    }
+
+    fn stats(
+        &self,
+        container_handle: &dyn super::ContainerHandle,
+    ) -> anyhow::Result<ContainerStats> {
+        let output = Command::new(self.binary)
+            .arg("stats")
+            .arg("--no-stream")
+            .arg("--format")
+            .arg("{{.CPUPerc}}\t{{.MemUsage}}\t{{.NetIO}}")
+            .arg(container_handle.id())
+            .output()?;
+
+        if !output.status.success() {
+            bail!("Docker stats command failed")
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match parse_stats(stdout.trim()) {
+            Some(stats) => Ok(stats),
+            None => bail!("Unexpected docker stats output: {}", stdout.trim()),
+        }
+    }
+
     fn get_host_address(&self) -> String {
         "host.docker.internal".to_string()
     }
+
+    fn events(&self, label: &str) -> anyhow::Result<EventStream> {
+        let child = Command::new(self.binary)
+            .arg("events")
+            .arg("--filter")
+            .arg("type=container")
+            .arg("--filter")
+            .arg(format!("label={}", label))
+            .arg("--format")
+            .arg("{{json .}}")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        EventStream::new(child, parse_event)
+    }
+}
+
+/// Parses a line of `docker events --format '{{json .}}'`.
+///
+/// Podman prints the same information with different field names (`Status`,
+/// `ID`, `Attributes`, `died`), both are accepted.
+/// Formats a mount of `docker inspect` as "source:destination", using the
+/// volume name for named volumes.
+fn mount_entry(mount: &serde_json::Value) -> String {
+    let source = mount["Source"]
+        .as_str()
+        .or_else(|| mount["Name"].as_str())
+        .unwrap_or_default();
+    let destination = mount["Destination"].as_str().unwrap_or_default();
+    format!("{}:{}", source, destination)
+}
+
+/// Reads the details of a container from an entry of `docker inspect`.
+fn parse_inspect(container: &serde_json::Value) -> ContainerDetails {
+    let text = |value: &serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    ContainerDetails {
+        id: text(&container["Id"]),
+        image: text(&container["Config"]["Image"]),
+        state: text(&container["State"]["Status"]),
+        started_at: container["State"]["StartedAt"].as_str().map(str::to_string),
+        // Containers without labels or environment report `null`
+        labels: serde_json::from_value::<Option<BTreeMap<String, String>>>(
+            container["Config"]["Labels"].clone(),
+        )
+        .ok()
+        .flatten()
+        .unwrap_or_default(),
+        env: serde_json::from_value::<Option<Vec<String>>>(container["Config"]["Env"].clone())
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+        mounts: container["Mounts"]
+            .as_array()
+            .map(|mounts| mounts.iter().map(mount_entry).collect())
+            .unwrap_or_default(),
+        volumes: container["Mounts"]
+            .as_array()
+            .map(|mounts| {
+                mounts
+                    .iter()
+                    .filter(|mount| mount["Type"] == "volume")
+                    .filter_map(|mount| {
+                        Some((
+                            mount["Name"].as_str()?.to_string(),
+                            mount["Destination"].as_str()?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        addresses: container["NetworkSettings"]["Networks"]
+            .as_object()
+            .map(|networks| {
+                networks
+                    .values()
+                    .filter_map(|network| network["IPAddress"].as_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// Parses a size of `docker stats` like `10.5MiB` or `1.2kB` into bytes.
+///
+/// Memory is reported in binary units and network I/O in decimal units.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(value.len()),
+    );
+    let multiplier = match unit {
+        "" | "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    Some((number * multiplier) as u64)
+}
+
+/// Parses a line of `docker stats` in the format `CPU\tMEMORY\tNETWORK`,
+/// e.g. `0.50%\t10.5MiB / 7.6GiB\t1.2kB / 648B`.
+fn parse_stats(line: &str) -> Option<ContainerStats> {
+    let mut fields = line.split('\t');
+    let cpu_percent = fields.next()?.trim().trim_end_matches('%').parse().ok();
+    let (memory_usage, memory_limit) = fields.next()?.split_once('/')?;
+    let (network_rx, network_tx) = fields.next()?.split_once('/')?;
+    Some(ContainerStats {
+        cpu_percent,
+        memory_usage: parse_size(memory_usage)?,
+        memory_limit: parse_size(memory_limit),
+        network_rx: parse_size(network_rx)?,
+        network_tx: parse_size(network_tx)?,
+    })
+}
+
+fn parse_event(line: &str) -> Option<RuntimeEvent> {
+    let event: serde_json::Value = serde_json::from_str(line).ok()?;
+    let actor = &event["Actor"];
+    let attributes = if actor["Attributes"].is_object() {
+        &actor["Attributes"]
+    } else {
+        &event["Attributes"]
+    };
+    let labels: HashMap<String, String> = attributes
+        .as_object()
+        .map(|attributes| {
+            attributes
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let action = match event["Action"].as_str().or(event["Status"].as_str())? {
+        "start" => ContainerAction::Start,
+        "stop" => ContainerAction::Stop,
+        "die" | "died" => ContainerAction::Die(
+            labels
+                .get("exitCode")
+                .and_then(|code| code.parse().ok())
+                .or(event["ContainerExitCode"].as_i64().map(|code| code as i32)),
+        ),
+        "oom" => ContainerAction::Oom,
+        _ => return None,
+    };
+
+    let container_id = actor["ID"].as_str().or(event["ID"].as_str())?.to_string();
+    let timestamp = event["time"]
+        .as_u64()
+        .unwrap_or_else(crate::driver::dashboard::now);
+
+    Some(RuntimeEvent {
+        container_id,
+        action,
+        timestamp,
+        labels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::runtime::BuildSecret;
+
+    #[test]
+    fn test_extract_container_port() {
+        use crate::devcontainer::ForwardPort;
+        assert_eq!(extract_container_port(&ForwardPort::Port(3000)), Some(3000));
+        for mapping in ["8080:80", "127.0.0.1:8080:80", "8080:80/udp"] {
+            assert_eq!(
+                extract_container_port(&ForwardPort::HostPort(mapping.to_string())),
+                Some(80)
+            );
+        }
+        assert_eq!(
+            extract_container_port(&ForwardPort::HostPort("web".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_option_args() {
+        let options = BuildOptions {
+            build_args: vec!["HTTP_PROXY=http://proxy:3128".to_string()],
+            secrets: vec![BuildSecret {
+                id: "registry_auth".to_string(),
+                path: "/tmp/auth.json".into(),
+            }],
+            cache_from: vec!["ghcr.io/acme/cache:app".to_string()],
+            inline_cache: true,
+            build_contexts: vec![("template".to_string(), "/etc/devcon".into())],
+        };
+        assert_eq!(
+            build_option_args(&options),
+            vec![
+                "--build-arg",
+                "HTTP_PROXY=http://proxy:3128",
+                "--build-arg",
+                "BUILDKIT_INLINE_CACHE=1",
+                "--cache-from",
+                "ghcr.io/acme/cache:app",
+                "--secret",
+                "id=registry_auth,src=/tmp/auth.json",
+                "--build-context",
+                "template=/etc/devcon",
+            ]
+        );
+        assert!(build_option_args(&BuildOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_parse_docker_event() {
+        let line = r#"{"status":"die","id":"abc123","Type":"container","Action":"die","Actor":{"ID":"abc123","Attributes":{"devcon.project":"demo","exitCode":"137"}},"time":1700000000}"#;
+        let event = parse_event(line).unwrap();
+        assert_eq!(event.container_id, "abc123");
+        assert_eq!(event.action, ContainerAction::Die(Some(137)));
+        assert_eq!(event.timestamp, 1_700_000_000);
+        assert_eq!(event.labels["devcon.project"], "demo");
+    }
+
+    #[test]
+    fn test_parse_podman_event() {
+        let line = r#"{"ID":"def456","Image":"devcon-demo","Name":"devcon.demo","Status":"died","Type":"container","Attributes":{"devcon.project":"demo"},"ContainerExitCode":1}"#;
+        let event = parse_event(line).unwrap();
+        assert_eq!(event.container_id, "def456");
+        assert_eq!(event.action, ContainerAction::Die(Some(1)));
+        assert_eq!(event.labels["devcon.project"], "demo");
+    }
+
+    #[test]
+    fn test_parse_ignores_other_events() {
+        assert!(parse_event(r#"{"Action":"exec_start: sh","Actor":{"ID":"abc"}}"#).is_none());
+        assert!(parse_event("not json").is_none());
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let stats = parse_stats("12.50%\t512MiB / 2GiB\t1.5kB / 648B").unwrap();
+        assert_eq!(stats.cpu_percent, Some(12.5));
+        assert_eq!(stats.memory_usage, 512 * 1024 * 1024);
+        assert_eq!(stats.memory_limit, Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(stats.network_rx, 1500);
+        assert_eq!(stats.network_tx, 648);
+
+        assert!(parse_stats("--\t-- / --\t-- / --").is_none());
+        assert!(parse_stats("").is_none());
+    }
+
+    #[test]
+    fn test_parse_inspect() {
+        let container = serde_json::json!({
+            "Id": "abc123",
+            "State": {"Status": "running", "StartedAt": "2025-01-01T10:00:00Z"},
+            "Config": {
+                "Image": "devcon-demo:latest",
+                "Labels": {"devcon.project": "demo"},
+                "Env": ["PATH=/usr/bin", "DEVCON=true"],
+            },
+            "Mounts": [
+                {"Type": "bind", "Source": "/home/me/demo", "Destination": "/workspaces/demo"},
+                {"Type": "volume", "Name": "cache", "Destination": "/cache"},
+            ],
+            "NetworkSettings": {
+                "Networks": {
+                    "bridge": {"IPAddress": "172.17.0.2"},
+                    "none": {"IPAddress": ""},
+                },
+            },
+        });
+        let details = parse_inspect(&container);
+        assert_eq!(details.id, "abc123");
+        assert_eq!(details.image, "devcon-demo:latest");
+        assert_eq!(details.state, "running");
+        assert_eq!(details.started_at.as_deref(), Some("2025-01-01T10:00:00Z"));
+        assert_eq!(details.labels["devcon.project"], "demo");
+        assert_eq!(details.env, vec!["PATH=/usr/bin", "DEVCON=true"]);
+        assert_eq!(
+            details.mounts,
+            vec!["/home/me/demo:/workspaces/demo", "cache:/cache"]
+        );
+        assert_eq!(details.volumes.len(), 1);
+        assert_eq!(details.volumes["cache"], "/cache");
+        assert_eq!(
+            details.addresses,
+            vec!["172.17.0.2".parse::<std::net::IpAddr>().unwrap()]
+        );
+
+        let container =
+            serde_json::json!({"Id": "abc123", "Config": {"Labels": null, "Env": null}});
+        let details = parse_inspect(&container);
+        assert!(details.labels.is_empty());
+        assert!(details.env.is_empty());
+        assert!(details.started_at.is_none());
+    }
 }
@@ -0,0 +1,502 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Mock Runtime
+//!
+//! A [`ContainerRuntime`] for tests of the driver without a container
+//! daemon. It records every invocation which changes containers or images
+//! and answers queries from its scripted state: built, pulled and added
+//! images exist, run containers are listed until they are stopped and exec
+//! commands succeed unless an exit code was scripted.
+//!
+//! The runtime is cloneable and clones share their state, so a test keeps a
+//! clone to inspect the invocations after boxing one into the driver, e.g.
+//! `ContainerDriver::new(config, Box::new(runtime.clone()))`.
+//!
+//! Only available in tests of devcon and with the `test-util` feature.
+
+// The binary compiles the module with the feature too, without using it
+#![cfg_attr(not(test), allow(dead_code))]
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use super::{
+    BuildOptions, BuildOutput, ContainerDetails, ContainerHandle, ContainerRuntime, ContainerStats,
+    RuntimeError, RuntimeParameters,
+};
+
+/// An invocation of the mock runtime which changed containers or images.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invocation {
+    /// An image was built, with the content of its Dockerfile
+    Build {
+        image_tag: String,
+        dockerfile: String,
+    },
+    /// A container was started
+    Run {
+        image_tag: String,
+        labels: Vec<String>,
+        env_vars: Vec<String>,
+        command: Vec<String>,
+    },
+    /// A command was executed in a container
+    Exec {
+        container_id: String,
+        command: Vec<String>,
+    },
+    Stop {
+        container_id: String,
+    },
+    Pause {
+        container_id: String,
+    },
+    Unpause {
+        container_id: String,
+    },
+    Commit {
+        container_id: String,
+        image: String,
+    },
+    PullImage {
+        image: String,
+    },
+    TagImage {
+        source: String,
+        target: String,
+    },
+    RemoveImage {
+        image: String,
+    },
+    RemoveVolume {
+        name: String,
+    },
+    CreateNetwork {
+        name: String,
+    },
+    ConnectNetwork {
+        container_id: String,
+        network: String,
+        aliases: Vec<String>,
+    },
+}
+
+/// Handle of a container of the mock runtime.
+#[derive(Debug, Clone)]
+pub struct MockContainerHandle {
+    id: String,
+    labels: HashMap<String, String>,
+}
+
+impl ContainerHandle for MockContainerHandle {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn label(&self, key: &str) -> Option<&str> {
+        self.labels.get(key).map(|v| v.as_str())
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    invocations: Vec<Invocation>,
+    images: Vec<String>,
+    image_labels: HashMap<String, HashMap<String, String>>,
+    containers: Vec<(String, MockContainerHandle, String)>,
+    exec_exit_codes: VecDeque<i32>,
+    next_id: usize,
+}
+
+/// Container runtime recording invocations and answering from scripted
+/// state, see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct MockRuntime {
+    state: Arc<Mutex<MockState>>,
+}
+
+/// Adds the implicit `latest` tag to an image without one.
+fn tagged(image: &str) -> String {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    if name.contains(':') || name.contains('@') {
+        image.to_string()
+    } else {
+        format!("{}:latest", image)
+    }
+}
+
+impl MockRuntime {
+    /// Creates a mock runtime without images and containers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an existing image.
+    pub fn with_image(self, image: &str) -> Self {
+        self.state().images.push(tagged(image));
+        self
+    }
+
+    /// Sets the labels of an image, adding the image.
+    pub fn with_image_labels(self, image: &str, labels: &[(&str, &str)]) -> Self {
+        let image = tagged(image);
+        {
+            let mut state = self.state();
+            if !state.images.contains(&image) {
+                state.images.push(image.clone());
+            }
+            state.image_labels.insert(
+                image,
+                labels
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// Adds a running container named `devcon.<project>`, labelled with
+    /// `devcon.project=<project>` and owned by the current user.
+    pub fn with_container(self, project: &str, id: &str) -> Self {
+        self.with_container_of(project, id, &crate::config::Config::current_user())
+    }
+
+    /// Adds a running container named `devcon.<project>` owned by `owner`.
+    pub fn with_container_of(self, project: &str, id: &str, owner: &str) -> Self {
+        self.state().containers.push((
+            format!("devcon.{}", project),
+            MockContainerHandle {
+                id: id.to_string(),
+                labels: HashMap::from([
+                    ("devcon.project".to_string(), project.to_string()),
+                    (
+                        crate::driver::audit::OWNER_LABEL.to_string(),
+                        owner.to_string(),
+                    ),
+                ]),
+            },
+            String::new(),
+        ));
+        self
+    }
+
+    /// Scripts the exit codes of the next exec commands, in order. Later
+    /// commands succeed.
+    pub fn with_exec_exit_codes(self, exit_codes: &[i32]) -> Self {
+        self.state().exec_exit_codes.extend(exit_codes);
+        self
+    }
+
+    /// Returns the recorded invocations, in order.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.state().invocations.clone()
+    }
+
+    /// Returns the commands executed in containers, in order.
+    pub fn exec_commands(&self) -> Vec<Vec<String>> {
+        self.invocations()
+            .into_iter()
+            .filter_map(|invocation| match invocation {
+                Invocation::Exec { command, .. } => Some(command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the Dockerfile of the last built image.
+    pub fn last_dockerfile(&self) -> Option<String> {
+        self.invocations()
+            .into_iter()
+            .rev()
+            .find_map(|invocation| match invocation {
+                Invocation::Build { dockerfile, .. } => Some(dockerfile),
+                _ => None,
+            })
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        // A test which panicked while holding the lock already failed
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, invocation: Invocation) {
+        self.state().invocations.push(invocation);
+    }
+
+    fn container(&self, id: &str) -> anyhow::Result<(String, MockContainerHandle, String)> {
+        self.state()
+            .containers
+            .iter()
+            .find(|(_, handle, _)| handle.id == id)
+            .cloned()
+            .ok_or_else(|| RuntimeError::ContainerNotRunning.into())
+    }
+
+    fn add_image(&self, image: &str) {
+        let image = tagged(image);
+        let mut state = self.state();
+        if !state.images.contains(&image) {
+            state.images.push(image);
+        }
+    }
+}
+
+impl ContainerRuntime for MockRuntime {
+    fn build(
+        &self,
+        dockerfile_path: &Path,
+        _context_path: &Path,
+        image_tag: &str,
+        _options: &BuildOptions,
+        _output: &BuildOutput,
+    ) -> anyhow::Result<()> {
+        self.record(Invocation::Build {
+            image_tag: image_tag.to_string(),
+            dockerfile: fs::read_to_string(dockerfile_path)?,
+        });
+        self.add_image(image_tag);
+        Ok(())
+    }
+
+    fn run(
+        &self,
+        image_tag: &str,
+        _volume_mount: &str,
+        labels: &[String],
+        env_vars: &[String],
+        runtime_parameters: RuntimeParameters,
+    ) -> anyhow::Result<Box<dyn ContainerHandle>> {
+        self.record(Invocation::Run {
+            image_tag: image_tag.to_string(),
+            labels: labels.to_vec(),
+            env_vars: env_vars.to_vec(),
+            command: runtime_parameters.command,
+        });
+        if !self.state().images.contains(&tagged(image_tag)) {
+            return Err(RuntimeError::ImageNotFound.into());
+        }
+
+        let labels: HashMap<String, String> = labels
+            .iter()
+            .filter_map(|label| label.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        let name = labels
+            .get("devcon.project")
+            .map(|project| format!("devcon.{}", project))
+            .unwrap_or_default();
+
+        let mut state = self.state();
+        state.next_id += 1;
+        let handle = MockContainerHandle {
+            id: format!("mock{}", state.next_id),
+            labels,
+        };
+        state
+            .containers
+            .push((name, handle.clone(), tagged(image_tag)));
+        Ok(Box::new(handle))
+    }
+
+    fn exec(
+        &self,
+        container_handle: &dyn ContainerHandle,
+        command: Vec<&str>,
+        _env_vars: &[String],
+        _attach_stdin: bool,
+    ) -> anyhow::Result<()> {
+        self.record(Invocation::Exec {
+            container_id: container_handle.id().to_string(),
+            command: command.iter().map(|c| c.to_string()).collect(),
+        });
+        match self.state().exec_exit_codes.pop_front() {
+            Some(code) if code != 0 => Err(RuntimeError::CommandFailed(code).into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn stop(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<()> {
+        self.record(Invocation::Stop {
+            container_id: container_handle.id().to_string(),
+        });
+        self.container(container_handle.id())?;
+        // Containers are started with --rm
+        self.state()
+            .containers
+            .retain(|(_, handle, _)| handle.id != container_handle.id());
+        Ok(())
+    }
+
+    fn pause(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<()> {
+        self.record(Invocation::Pause {
+            container_id: container_handle.id().to_string(),
+        });
+        self.container(container_handle.id()).map(|_| ())
+    }
+
+    fn unpause(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<()> {
+        self.record(Invocation::Unpause {
+            container_id: container_handle.id().to_string(),
+        });
+        self.container(container_handle.id()).map(|_| ())
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<(String, Box<dyn ContainerHandle>)>> {
+        Ok(self
+            .state()
+            .containers
+            .iter()
+            .map(|(name, handle, _)| {
+                (
+                    name.clone(),
+                    Box::new(handle.clone()) as Box<dyn ContainerHandle>,
+                )
+            })
+            .collect())
+    }
+
+    fn images(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.state().images.clone())
+    }
+
+    fn image_exists(&self, image: &str) -> anyhow::Result<bool> {
+        Ok(self.state().images.contains(&tagged(image)))
+    }
+
+    fn image_labels(&self, image: &str) -> anyhow::Result<HashMap<String, String>> {
+        Ok(self
+            .state()
+            .image_labels
+            .get(&tagged(image))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn image_command(&self, _image: &str) -> anyhow::Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn mounts(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<Vec<String>> {
+        self.container(container_handle.id())?;
+        Ok(Vec::new())
+    }
+
+    fn inspect(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<ContainerDetails> {
+        let (_, handle, image) = self.container(container_handle.id())?;
+        Ok(ContainerDetails {
+            id: handle.id,
+            image,
+            state: "running".to_string(),
+            labels: handle.labels.into_iter().collect(),
+            ..Default::default()
+        })
+    }
+
+    fn commit(
+        &self,
+        container_handle: &dyn ContainerHandle,
+        image: &str,
+        _labels: &[String],
+    ) -> anyhow::Result<()> {
+        self.record(Invocation::Commit {
+            container_id: container_handle.id().to_string(),
+            image: image.to_string(),
+        });
+        self.container(container_handle.id())?;
+        self.add_image(image);
+        Ok(())
+    }
+
+    fn pull_image(&self, image: &str) -> anyhow::Result<()> {
+        self.record(Invocation::PullImage {
+            image: image.to_string(),
+        });
+        self.add_image(image);
+        Ok(())
+    }
+
+    fn tag_image(&self, source: &str, target: &str) -> anyhow::Result<()> {
+        self.record(Invocation::TagImage {
+            source: source.to_string(),
+            target: target.to_string(),
+        });
+        if !self.state().images.contains(&tagged(source)) {
+            return Err(RuntimeError::ImageNotFound.into());
+        }
+        self.add_image(target);
+        Ok(())
+    }
+
+    fn remove_image(&self, image: &str) -> anyhow::Result<()> {
+        self.record(Invocation::RemoveImage {
+            image: image.to_string(),
+        });
+        let image = tagged(image);
+        let mut state = self.state();
+        if state.containers.iter().any(|(_, _, used)| used == &image) {
+            anyhow::bail!("Image {} is used by a container", image);
+        }
+        state.images.retain(|existing| existing != &image);
+        Ok(())
+    }
+
+    fn remove_volume(&self, name: &str) -> anyhow::Result<()> {
+        self.record(Invocation::RemoveVolume {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+
+    fn create_network(&self, name: &str) -> anyhow::Result<()> {
+        self.record(Invocation::CreateNetwork {
+            name: name.to_string(),
+        });
+        Ok(())
+    }
+
+    fn connect_network(
+        &self,
+        container_handle: &dyn ContainerHandle,
+        network: &str,
+        aliases: &[String],
+    ) -> anyhow::Result<()> {
+        self.record(Invocation::ConnectNetwork {
+            container_id: container_handle.id().to_string(),
+            network: network.to_string(),
+            aliases: aliases.to_vec(),
+        });
+        self.container(container_handle.id()).map(|_| ())
+    }
+
+    fn stats(&self, container_handle: &dyn ContainerHandle) -> anyhow::Result<ContainerStats> {
+        self.container(container_handle.id())?;
+        Ok(ContainerStats::default())
+    }
+
+    fn get_host_address(&self) -> String {
+        "host.docker.internal".to_string()
+    }
+}
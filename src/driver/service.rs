@@ -0,0 +1,324 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Control Server Service
+//!
+//! Installs the control server as a user service, so it starts at login and
+//! port forwarding is always available without running `devcon serve`. Linux
+//! uses a systemd user unit, macOS a launchd agent. The service runs
+//! `devcon serve` in the foreground and is restarted if it exits.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use tracing::debug;
+
+/// Name of the systemd user unit.
+const SYSTEMD_UNIT: &str = "devcon-serve.service";
+
+/// Label of the launchd agent.
+const LAUNCHD_LABEL: &str = "com.github.kreemer.devcon.serve";
+
+/// Service manager of the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    /// systemd user units on Linux
+    Systemd,
+    /// launchd agents on macOS
+    Launchd,
+}
+
+/// Settings of the installed control server.
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    /// Path of the devcon binary
+    pub binary: PathBuf,
+    /// Port the control server listens on
+    pub port: u16,
+    /// Port of the dashboard and metrics, if served
+    pub metrics_port: Option<u16>,
+    /// File receiving the output (launchd only, systemd uses the journal)
+    pub log_path: PathBuf,
+}
+
+impl ServiceConfig {
+    /// Arguments of `devcon serve` run by the service.
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "serve".to_string(),
+            "--port".to_string(),
+            self.port.to_string(),
+        ];
+        if let Some(metrics_port) = self.metrics_port {
+            args.push("--metrics-port".to_string());
+            args.push(metrics_port.to_string());
+        }
+        args
+    }
+}
+
+impl ServiceManager {
+    /// Returns the service manager of the host.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on platforms without a supported service manager.
+    pub fn detect() -> Result<Self> {
+        if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else {
+            bail!("Installing the control server as a service is only supported on Linux and macOS")
+        }
+    }
+
+    /// Path of the service file.
+    pub fn service_path(&self) -> Result<PathBuf> {
+        Ok(match self {
+            Self::Systemd => dirs::config_dir()
+                .context("Failed to determine config directory")?
+                .join("systemd/user")
+                .join(SYSTEMD_UNIT),
+            Self::Launchd => dirs::home_dir()
+                .context("Failed to determine home directory")?
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", LAUNCHD_LABEL)),
+        })
+    }
+
+    /// Renders the service file running the control server.
+    pub fn render(&self, config: &ServiceConfig) -> String {
+        match self {
+            Self::Systemd => {
+                let command = std::iter::once(config.binary.to_string_lossy().to_string())
+                    .chain(config.args())
+                    .map(|arg| systemd_quote(&arg))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!(
+                    "[Unit]\n\
+                     Description=devcon control server\n\
+                     After=network.target\n\
+                     \n\
+                     [Service]\n\
+                     ExecStart={}\n\
+                     Restart=on-failure\n\
+                     RestartSec=5\n\
+                     \n\
+                     [Install]\n\
+                     WantedBy=default.target\n",
+                    command
+                )
+            }
+            Self::Launchd => {
+                let arguments: String =
+                    std::iter::once(config.binary.to_string_lossy().to_string())
+                        .chain(config.args())
+                        .map(|arg| format!("        <string>{}</string>\n", xml_escape(&arg)))
+                        .collect();
+                format!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+                     <plist version=\"1.0\">\n\
+                     <dict>\n\
+                     \x20   <key>Label</key>\n\
+                     \x20   <string>{}</string>\n\
+                     \x20   <key>ProgramArguments</key>\n\
+                     \x20   <array>\n\
+                     {}\
+                     \x20   </array>\n\
+                     \x20   <key>RunAtLoad</key>\n\
+                     \x20   <true/>\n\
+                     \x20   <key>KeepAlive</key>\n\
+                     \x20   <dict>\n\
+                     \x20       <key>SuccessfulExit</key>\n\
+                     \x20       <false/>\n\
+                     \x20   </dict>\n\
+                     \x20   <key>StandardOutPath</key>\n\
+                     \x20   <string>{}</string>\n\
+                     \x20   <key>StandardErrorPath</key>\n\
+                     \x20   <string>{}</string>\n\
+                     </dict>\n\
+                     </plist>\n",
+                    LAUNCHD_LABEL,
+                    arguments,
+                    xml_escape(&config.log_path.to_string_lossy()),
+                    xml_escape(&config.log_path.to_string_lossy()),
+                )
+            }
+        }
+    }
+
+    /// Writes the service file and starts the service.
+    ///
+    /// An installed service is replaced, e.g. to change the port.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written or the service manager
+    /// fails to start the service.
+    pub fn install(&self, config: &ServiceConfig) -> Result<PathBuf> {
+        let path = self.service_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        if path.exists() {
+            self.stop(&path);
+        }
+        std::fs::write(&path, self.render(config))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        match self {
+            Self::Systemd => {
+                run("systemctl", &["--user", "daemon-reload"])?;
+                run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])?;
+            }
+            Self::Launchd => {
+                run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Stops the service and removes its file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the service isn't installed or its file cannot be
+    /// removed.
+    pub fn uninstall(&self) -> Result<PathBuf> {
+        let path = self.service_path()?;
+        if !path.exists() {
+            bail!("The control server service is not installed");
+        }
+        self.stop(&path);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+        if *self == Self::Systemd {
+            run("systemctl", &["--user", "daemon-reload"])?;
+        }
+        Ok(path)
+    }
+
+    /// Returns whether the service file exists and whether the service runs.
+    pub fn status(&self) -> Result<(bool, bool)> {
+        let installed = self.service_path()?.exists();
+        let running = match self {
+            Self::Systemd => run(
+                "systemctl",
+                &["--user", "is-active", "--quiet", SYSTEMD_UNIT],
+            ),
+            Self::Launchd => run("launchctl", &["list", LAUNCHD_LABEL]),
+        }
+        .is_ok();
+        Ok((installed, running))
+    }
+
+    /// Stops and disables the service, ignoring failures of a service which
+    /// isn't loaded.
+    fn stop(&self, path: &Path) {
+        let result = match self {
+            Self::Systemd => run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT]),
+            Self::Launchd => run("launchctl", &["unload", "-w", &path.to_string_lossy()]),
+        };
+        if let Err(e) = result {
+            debug!("Ignoring failure to stop the service: {:#}", e);
+        }
+    }
+}
+
+/// Runs a service manager command, failing on a non-zero exit.
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    debug!("Running {} {}", program, args.join(" "));
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Quotes an `ExecStart` argument for systemd if needed.
+fn systemd_quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Escapes text for an XML element.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ServiceConfig {
+        ServiceConfig {
+            binary: PathBuf::from("/opt/my tools/devcon"),
+            port: 15000,
+            metrics_port: Some(15001),
+            log_path: PathBuf::from("/tmp/serve-15000.log"),
+        }
+    }
+
+    #[test]
+    fn test_render_systemd() {
+        let unit = ServiceManager::Systemd.render(&config());
+        assert!(unit.contains(
+            "ExecStart=\"/opt/my tools/devcon\" serve --port 15000 --metrics-port 15001\n"
+        ));
+        assert!(unit.contains("Restart=on-failure\n"));
+        assert!(unit.contains("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn test_render_launchd() {
+        let plist = ServiceManager::Launchd.render(&ServiceConfig {
+            metrics_port: None,
+            ..config()
+        });
+        assert!(plist.contains("<string>com.github.kreemer.devcon.serve</string>"));
+        assert!(plist.contains(
+            "        <string>/opt/my tools/devcon</string>\n        <string>serve</string>\n        <string>--port</string>\n        <string>15000</string>\n    </array>"
+        ));
+        assert!(!plist.contains("--metrics-port"));
+        assert!(
+            plist.contains("<key>StandardOutPath</key>\n    <string>/tmp/serve-15000.log</string>")
+        );
+    }
+}
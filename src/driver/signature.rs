@@ -0,0 +1,344 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Signature Verification
+//!
+//! With `verifySignatures` enabled, registry features and base images are
+//! only used if they carry a cosign signature made with one of the
+//! `trustedKeys`. Cosign stores the signatures of the manifest
+//! `sha256:<hex>` as the artifact `sha256-<hex>.sig` in the same repository:
+//! each layer is a simple signing payload naming the signed manifest digest,
+//! annotated with the base64 signature of the payload.
+//!
+//! Keys are ECDSA P-256 public keys in PEM format, as written by
+//! `cosign generate-key-pair`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::signature::{ECDSA_P256_SHA256_ASN1, UnparsedPublicKey};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+use crate::download::DownloadPolicy;
+use crate::driver::oci::{HttpOciClient, ImageReference, OciClient};
+
+/// Annotation of a signature layer holding the base64 signature
+pub const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+
+/// DER prefix of a P-256 subject public key info, followed by the
+/// uncompressed point
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// Decodes the uncompressed P-256 point of a PEM public key.
+///
+/// # Errors
+///
+/// Returns an error if the PEM holds no public key or a key of another type.
+pub fn parse_public_key(pem: &str) -> Result<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "-----BEGIN PUBLIC KEY-----")
+        .skip(1)
+        .take_while(|line| *line != "-----END PUBLIC KEY-----")
+        .collect();
+    if body.is_empty() {
+        bail!("No PEM public key found");
+    }
+    let der = STANDARD.decode(body).context("Invalid PEM encoding")?;
+    match der.strip_prefix(&P256_SPKI_PREFIX[..]) {
+        Some(point) if point.len() == 65 => Ok(point.to_vec()),
+        _ => bail!("Only ECDSA P-256 keys are supported"),
+    }
+}
+
+/// The keys signatures have to be made with.
+#[derive(Clone)]
+pub struct TrustedKeys {
+    keys: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl fmt::Debug for TrustedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.keys.iter().map(|(path, _)| path))
+            .finish()
+    }
+}
+
+impl TrustedKeys {
+    /// Loads the public keys of the given PEM files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no key is given or a key cannot be read.
+    pub fn load(paths: &[PathBuf]) -> Result<Self> {
+        if paths.is_empty() {
+            bail!("verifySignatures is enabled, but no trustedKeys are configured");
+        }
+        let keys = paths
+            .iter()
+            .map(|path| {
+                let pem = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read trusted key {}", path.display()))?;
+                let point = parse_public_key(&pem)
+                    .with_context(|| format!("Invalid trusted key {}", path.display()))?;
+                Ok((path.clone(), point))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { keys })
+    }
+
+    /// Verifies that the manifest `digest` of `repository` is signed with a
+    /// trusted key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the problem of every signature found, or that
+    /// there is none.
+    pub fn verify(
+        &self,
+        client: &dyn OciClient,
+        repository: &str,
+        token: &str,
+        digest: &str,
+    ) -> Result<()> {
+        let tag = signature_tag(digest)?;
+        let manifest = client
+            .manifest(repository, &tag, token)
+            .with_context(|| format!("No cosign signature found for {}@{}", repository, digest))?;
+
+        let mut problems = Vec::new();
+        for layer in manifest.layers() {
+            let Some(signature) = layer
+                .annotations()
+                .as_ref()
+                .and_then(|annotations| annotations.get(SIGNATURE_ANNOTATION))
+            else {
+                continue;
+            };
+            let mut payload = Vec::new();
+            client.blob(
+                repository,
+                layer.digest().as_ref(),
+                token,
+                &mut payload,
+                &mut |_, _| {},
+            )?;
+            if format!("sha256:{:x}", Sha256::digest(&payload)) != layer.digest().as_ref() {
+                problems.push(format!(
+                    "payload {} does not match its digest",
+                    layer.digest()
+                ));
+                continue;
+            }
+            match self.check(&payload, signature, digest) {
+                Ok(key) => {
+                    debug!("{}@{} is signed with {}", repository, digest, key.display());
+                    return Ok(());
+                }
+                Err(e) => problems.push(format!("{:#}", e)),
+            }
+        }
+
+        if problems.is_empty() {
+            bail!("{}:{} holds no cosign signature", repository, tag);
+        }
+        bail!(
+            "No valid signature of {}@{}:\n  - {}",
+            repository,
+            digest,
+            problems.join("\n  - ")
+        )
+    }
+
+    /// Checks one signature, returning the key it was made with
+    fn check(&self, payload: &[u8], signature: &str, digest: &str) -> Result<&Path> {
+        let signature = STANDARD
+            .decode(signature.trim())
+            .context("signature is not base64")?;
+        let Some((key, _)) = self.keys.iter().find(|(_, point)| {
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+                .verify(payload, &signature)
+                .is_ok()
+        }) else {
+            bail!("signature is not made with a trusted key");
+        };
+
+        // A valid signature of another image must not be accepted
+        let payload: serde_json::Value =
+            serde_json::from_slice(payload).context("payload is not JSON")?;
+        let signed = payload["critical"]["image"]["docker-manifest-digest"]
+            .as_str()
+            .unwrap_or_default();
+        if signed != digest {
+            bail!(
+                "signature of {} made with {} is for {}",
+                digest,
+                key.display(),
+                signed
+            );
+        }
+        Ok(key)
+    }
+}
+
+/// Returns the tag cosign stores the signatures of `digest` under.
+fn signature_tag(digest: &str) -> Result<String> {
+    match digest.strip_prefix("sha256:") {
+        Some(hex) => Ok(format!("sha256-{}.sig", hex)),
+        None => bail!("Unsupported digest {}", digest),
+    }
+}
+
+/// Verifies the signature of a base image, returning the image pinned to
+/// the verified digest, so the runtime cannot pull a different one.
+///
+/// # Errors
+///
+/// Returns an error if the registry cannot be reached or the image has no
+/// valid signature.
+pub fn verify_image(image: &str, keys: &TrustedKeys, policy: &DownloadPolicy) -> Result<String> {
+    if policy.offline {
+        bail!(
+            "Signature of base image {} cannot be verified offline",
+            image
+        );
+    }
+    let reference = ImageReference::parse(image);
+    let client = HttpOciClient::for_registry(&reference.registry, policy)?;
+    verify_reference(&reference, keys, &client)
+}
+
+fn verify_reference(
+    reference: &ImageReference,
+    keys: &TrustedKeys,
+    client: &dyn OciClient,
+) -> Result<String> {
+    let token = client.token(&reference.repository)?;
+    let digest = client.manifest_digest(&reference.repository, &reference.reference, &token)?;
+    keys.verify(client, &reference.repository, &token, &digest)
+        .with_context(|| {
+            format!(
+                "Signature verification of base image {} failed",
+                reference.name
+            )
+        })?;
+    Ok(reference.pinned(&digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::oci::mock::{MockOciClient, SigningKey};
+
+    fn trusted(key: &SigningKey) -> TrustedKeys {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("cosign.pub");
+        std::fs::write(&path, key.public_key_pem()).unwrap();
+        TrustedKeys::load(&[path]).unwrap()
+    }
+
+    fn image_client() -> (MockOciClient, String) {
+        let client = MockOciClient::new().with_artifact(
+            "library/ubuntu",
+            "22.04",
+            "layer",
+            b"rootfs".to_vec(),
+        );
+        let digest = client
+            .manifest_digest("library/ubuntu", "22.04", "")
+            .unwrap();
+        (client, digest)
+    }
+
+    #[test]
+    fn test_parse_public_key() {
+        let key = SigningKey::generate();
+        assert_eq!(parse_public_key(&key.public_key_pem()).unwrap().len(), 65);
+        assert!(parse_public_key("hello").is_err());
+        let rsa =
+            "-----BEGIN PUBLIC KEY-----\nMA0GCSqGSIb3DQEBAQUAA4IBDwAw\n-----END PUBLIC KEY-----\n";
+        assert!(
+            parse_public_key(rsa)
+                .unwrap_err()
+                .to_string()
+                .contains("P-256")
+        );
+    }
+
+    #[test]
+    fn test_verify_signed_image() {
+        let key = SigningKey::generate();
+        let (client, digest) = image_client();
+        let (payload, signature) = key.sign(&digest);
+        let client = client.with_signature("library/ubuntu", &digest, payload, &signature);
+
+        let pinned = verify_reference(
+            &ImageReference::parse("ubuntu:22.04"),
+            &trusted(&key),
+            &client,
+        )
+        .unwrap();
+        assert_eq!(pinned, format!("ubuntu@{}", digest));
+    }
+
+    #[test]
+    fn test_verify_rejects_untrusted_and_unsigned() {
+        let key = SigningKey::generate();
+        let other = SigningKey::generate();
+        let reference = ImageReference::parse("ubuntu:22.04");
+
+        let (client, _) = image_client();
+        let error = verify_reference(&reference, &trusted(&key), &client).unwrap_err();
+        assert!(format!("{:#}", error).contains("No cosign signature found"));
+
+        let (client, digest) = image_client();
+        let (payload, signature) = other.sign(&digest);
+        let client = client.with_signature("library/ubuntu", &digest, payload, &signature);
+        let error = verify_reference(&reference, &trusted(&key), &client).unwrap_err();
+        assert!(format!("{:#}", error).contains("not made with a trusted key"));
+    }
+
+    #[test]
+    fn test_verify_rejects_signature_of_other_digest() {
+        let key = SigningKey::generate();
+        let (client, digest) = image_client();
+        let (payload, signature) = key.sign(&format!("sha256:{}", "0".repeat(64)));
+        let client = client.with_signature("library/ubuntu", &digest, payload, &signature);
+
+        let error = verify_reference(
+            &ImageReference::parse("ubuntu:22.04"),
+            &trusted(&key),
+            &client,
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", error).contains("is for sha256:000"));
+    }
+}
@@ -0,0 +1,222 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Workspace Snapshots
+//!
+//! A snapshot captures a running container so an experiment can be rolled
+//! back. The filesystem of the container is committed to an image tagged
+//! `snapshot-<timestamp>` next to the `latest` image of the workspace. Named
+//! volumes are not part of images, their contents are exported as tar
+//! archives into the `snapshots` directory of the devcon state directory,
+//! together with a manifest recording where every volume was mounted.
+//!
+//! Restoring tags the snapshot image as `latest`, so the next container of
+//! the workspace starts from it, and imports the archives into recreated
+//! volumes. `devcon gc` removes all but the newest snapshots of every
+//! workspace.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// Prefix of the image tag of a snapshot.
+pub const TAG_PREFIX: &str = "snapshot-";
+
+/// Label recording the snapshot name on its image.
+pub const SNAPSHOT_LABEL: &str = "devcon.snapshot";
+
+/// Format of the timestamp in snapshot names.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// File of the manifest in the directory of a snapshot.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Returns the name of a snapshot taken at `time`, e.g.
+/// `snapshot-20250101-103000`.
+pub fn name_at(time: DateTime<Local>) -> String {
+    format!("{}{}", TAG_PREFIX, time.format(TIMESTAMP_FORMAT))
+}
+
+/// Returns when a snapshot was taken, `None` if `name` is no snapshot name.
+pub fn taken_at(name: &str) -> Option<NaiveDateTime> {
+    let timestamp = name.strip_prefix(TAG_PREFIX)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()
+}
+
+/// Returns the snapshots among `images` of the image `repository`, newest
+/// first.
+pub fn list(images: &[String], repository: &str) -> Vec<String> {
+    let mut snapshots: Vec<String> = images
+        .iter()
+        .filter_map(|image| image.split_once(':'))
+        .filter(|(image_repository, tag)| {
+            *image_repository == repository && taken_at(tag).is_some()
+        })
+        .map(|(_, tag)| tag.to_string())
+        .collect();
+    // Timestamps sort like their names
+    snapshots.sort_by(|a, b| b.cmp(a));
+    snapshots
+}
+
+/// Returns the snapshot images among `images` to remove so that only the
+/// `keep` newest snapshots of every image repository remain.
+pub fn prune(images: &[String], keep: usize) -> Vec<String> {
+    let repositories: BTreeSet<&str> = images
+        .iter()
+        .filter_map(|image| image.split_once(':'))
+        .map(|(repository, _)| repository)
+        .collect();
+
+    repositories
+        .into_iter()
+        .flat_map(|repository| {
+            list(images, repository)
+                .into_iter()
+                .skip(keep)
+                .map(move |name| format!("{}:{}", repository, name))
+        })
+        .collect()
+}
+
+/// Directory of the volume archives of a snapshot.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined.
+pub fn snapshot_dir(workspace: &str, name: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("snapshots")
+        .join(workspace)
+        .join(name))
+}
+
+/// Path of the archive of a volume in the directory of a snapshot.
+pub fn archive_path(dir: &Path, volume: &str) -> PathBuf {
+    dir.join(format!("{}.tar", volume))
+}
+
+/// Volumes exported with a snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Mount destination of every exported volume by volume name
+    pub volumes: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Loads the manifest of a snapshot, empty if the snapshot has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest exists but cannot be read.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read snapshot manifest {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid snapshot manifest {}", path.display()))
+    }
+
+    /// Saves the manifest into the directory of a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be written.
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write snapshot manifest {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_snapshot_name() {
+        let time = Local.with_ymd_and_hms(2025, 1, 2, 10, 30, 5).unwrap();
+        let name = name_at(time);
+        assert_eq!(name, "snapshot-20250102-103005");
+        assert_eq!(taken_at(&name), Some(time.naive_local()));
+        assert_eq!(taken_at("latest"), None);
+        assert_eq!(taken_at("snapshot-yesterday"), None);
+    }
+
+    #[test]
+    fn test_list_and_prune() {
+        let images: Vec<String> = [
+            "devcon-app:latest",
+            "devcon-app:snapshot-20250101-100000",
+            "devcon-app:snapshot-20250103-100000",
+            "devcon-app:snapshot-20250102-100000",
+            "devcon-other:snapshot-20250101-100000",
+        ]
+        .iter()
+        .map(|image| image.to_string())
+        .collect();
+
+        assert_eq!(
+            list(&images, "devcon-app"),
+            vec![
+                "snapshot-20250103-100000",
+                "snapshot-20250102-100000",
+                "snapshot-20250101-100000",
+            ]
+        );
+        assert_eq!(
+            prune(&images, 1),
+            vec![
+                "devcon-app:snapshot-20250102-100000",
+                "devcon-app:snapshot-20250101-100000",
+            ]
+        );
+        assert!(prune(&images, 3).is_empty());
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Manifest::load(dir.path()).unwrap(), Manifest::default());
+
+        let manifest = Manifest {
+            volumes: BTreeMap::from([("cache".to_string(), "/cache".to_string())]),
+        };
+        manifest.save(dir.path()).unwrap();
+        assert_eq!(Manifest::load(dir.path()).unwrap(), manifest);
+        assert_eq!(
+            archive_path(dir.path(), "cache"),
+            dir.path().join("cache.tar")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Tunnel Keys
+//!
+//! Keys for encrypted tunnels are derived per workspace from a random host
+//! secret, stored as `tunnel.key` in the devcon state directory. This way
+//! `devcon start` and the control server agree on the key of a workspace
+//! without exchanging it, and a container only ever learns its own key.
+//!
+//! `devcon start` also records which workspaces enable encrypted tunnels in
+//! the `encrypted-tunnels` directory of the state directory, so the control
+//! server refuses plain tunnels of their agents instead of trusting them to
+//! encrypt.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use devcon_proto::noise::{self, TunnelKey};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+
+/// File name of the host secret in the state directory.
+const SECRET_FILE: &str = "tunnel.key";
+
+/// Directory in the state directory marking the workspaces with encrypted
+/// tunnels.
+const ENCRYPTED_DIR: &str = "encrypted-tunnels";
+
+/// Returns the tunnel key of a workspace, creating the host secret if needed.
+///
+/// # Errors
+///
+/// Returns an error if the host secret cannot be read or created.
+pub fn workspace_key(workspace: &str) -> Result<TunnelKey> {
+    let secret = load_or_create_secret(&Config::get_state_dir()?.join(SECRET_FILE))?;
+    Ok(derive_key(&secret, workspace))
+}
+
+/// Records whether the tunnels of a workspace must be encrypted.
+///
+/// # Errors
+///
+/// Returns an error if the marker cannot be written or removed.
+pub fn set_encrypted(workspace: &str, encrypted: bool) -> Result<()> {
+    let dir = Config::get_state_dir()?.join(ENCRYPTED_DIR);
+    set_marker(&dir, workspace, encrypted)
+        .with_context(|| format!("Failed to record encrypted tunnels of {}", workspace))
+}
+
+/// Whether the tunnels of a workspace must be encrypted.
+pub fn is_encrypted(workspace: &str) -> bool {
+    Config::get_state_dir()
+        .is_ok_and(|dir| marker_path(&dir.join(ENCRYPTED_DIR), workspace).exists())
+}
+
+/// Path of the marker of a workspace in `dir`. Names are chosen by agents,
+/// so they are hashed rather than used as file names.
+fn marker_path(dir: &Path, workspace: &str) -> PathBuf {
+    dir.join(format!("{:x}", Sha256::digest(workspace.as_bytes())))
+}
+
+fn set_marker(dir: &Path, workspace: &str, encrypted: bool) -> std::io::Result<()> {
+    let path = marker_path(dir, workspace);
+    if encrypted {
+        fs::create_dir_all(dir)?;
+        fs::write(path, workspace)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// Derives the key of a workspace from the host secret.
+fn derive_key(secret: &TunnelKey, workspace: &str) -> TunnelKey {
+    let mut hasher = Sha256::new();
+    hasher.update(b"devcon tunnel key\0");
+    hasher.update(secret);
+    hasher.update(workspace.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Reads the host secret at `path`, generating it on first use.
+fn load_or_create_secret(path: &Path) -> Result<TunnelKey> {
+    if path.exists() {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tunnel secret {}", path.display()))?;
+        return noise::parse_key(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid tunnel secret {}: {}", path.display(), e));
+    }
+
+    let secret = noise::generate_key().context("Failed to generate tunnel secret")?;
+    write_secret(path, &noise::format_key(&secret))
+        .with_context(|| format!("Failed to write tunnel secret {}", path.display()))?;
+    Ok(secret)
+}
+
+#[cfg(unix)]
+fn write_secret(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(content.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_secret(path: &Path, content: &str) -> std::io::Result<()> {
+    fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_differ_per_workspace() {
+        let secret = [7u8; 32];
+        assert_eq!(derive_key(&secret, "web"), derive_key(&secret, "web"));
+        assert_ne!(derive_key(&secret, "web"), derive_key(&secret, "api"));
+        assert_ne!(derive_key(&secret, "web"), derive_key(&[8u8; 32], "web"));
+    }
+
+    #[test]
+    fn test_encrypted_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join(ENCRYPTED_DIR);
+
+        set_marker(&dir, "web", true).unwrap();
+        assert!(marker_path(&dir, "web").exists());
+        assert!(!marker_path(&dir, "api").exists());
+        assert!(!marker_path(&dir, "../web").starts_with(dir.parent().unwrap().join("web")));
+
+        set_marker(&dir, "web", false).unwrap();
+        assert!(!marker_path(&dir, "web").exists());
+        set_marker(&dir, "web", false).unwrap();
+    }
+
+    #[test]
+    fn test_secret_is_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SECRET_FILE);
+
+        let secret = load_or_create_secret(&path).unwrap();
+        assert_eq!(load_or_create_secret(&path).unwrap(), secret);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
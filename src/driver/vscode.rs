@@ -0,0 +1,210 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # VS Code Customizations
+//!
+//! Features and the devcontainer.json configure VS Code in
+//! `customizations.vscode`: the `extensions` to install and the `settings`
+//! to apply. They are merged in install order with the devcontainer.json
+//! last: extensions are collected without duplicates, an id prefixed with
+//! `-` removes an extension contributed earlier, and later settings replace
+//! earlier ones.
+//!
+//! Unless `bakeVscodeCustomizations` is disabled, images are built with the
+//! settings as machine settings of the VS Code server, and their metadata
+//! label keeps the merged extensions, which VS Code installs when attaching,
+//! in the devcontainer.json entry. Any VS
+//! Code instance attaching to the container has the project's tooling right
+//! away, without a devcontainer.json on its side.
+
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::devcontainer::Devcontainer;
+use crate::driver::feature_process::FeatureProcessResult;
+
+/// Machine settings of the VS Code server, relative to the home directory.
+pub const MACHINE_SETTINGS: &str = ".vscode-server/data/Machine/settings.json";
+
+/// Name of the settings file in the build directory.
+const SETTINGS_FILE: &str = "vscode_settings.json";
+
+/// VS Code customizations merged from features and the devcontainer.json.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VscodeCustomizations {
+    pub extensions: Vec<String>,
+    pub settings: Map<String, Value>,
+}
+
+impl VscodeCustomizations {
+    /// Merges the customizations of the features, in install order, and of
+    /// the devcontainer.json.
+    pub fn collect(features: &[FeatureProcessResult], devcontainer: &Devcontainer) -> Self {
+        let mut merged = Self::default();
+        let sources = features
+            .iter()
+            .map(|result| result.feature.customizations.as_ref())
+            .chain([devcontainer.customizations.as_ref()]);
+        for vscode in sources.flatten().filter_map(|c| c.get("vscode")) {
+            merged.add(vscode);
+        }
+        merged
+    }
+
+    fn add(&mut self, vscode: &Value) {
+        let extensions = vscode
+            .get("extensions")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str);
+        for id in extensions {
+            // Extension ids are case-insensitive
+            match id.strip_prefix('-') {
+                Some(removed) => self
+                    .extensions
+                    .retain(|extension| !extension.eq_ignore_ascii_case(removed)),
+                None => {
+                    if !self.extensions.iter().any(|e| e.eq_ignore_ascii_case(id)) {
+                        self.extensions.push(id.to_string());
+                    }
+                }
+            }
+        }
+        if let Some(settings) = vscode.get("settings").and_then(Value::as_object) {
+            self.settings.extend(settings.clone());
+        }
+    }
+
+    /// Builds the instructions installing the settings as machine settings
+    /// of the VS Code server of the remote user, none without settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the settings cannot be written to the build directory.
+    pub fn settings_stage(
+        &self,
+        remote_user: &str,
+        remote_user_home: &str,
+        directory_path: &Path,
+    ) -> anyhow::Result<String> {
+        if self.settings.is_empty() {
+            return Ok(String::new());
+        }
+        fs::write(
+            directory_path.join(SETTINGS_FILE),
+            serde_json::to_string_pretty(&self.settings)?,
+        )?;
+
+        let home = remote_user_home.trim_end_matches('/');
+        let target = format!("{}/{}", home, MACHINE_SETTINGS);
+        let directory = target.rsplit_once('/').map_or("", |(dir, _)| dir);
+        Ok(format!(
+            "COPY {file} /tmp/{file}\nRUN mkdir -p {directory} && mv /tmp/{file} {target} && chown -R {user} {home}/.vscode-server\n",
+            file = SETTINGS_FILE,
+            directory = directory,
+            target = target,
+            user = remote_user,
+            home = home,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use crate::devcontainer::{FeatureRef, FeatureSource};
+
+    fn feature(customizations: Value) -> FeatureProcessResult {
+        FeatureProcessResult {
+            feature_ref: FeatureRef::new(FeatureSource::Local {
+                path: PathBuf::from("/features/node"),
+            }),
+            feature: serde_json::from_value(serde_json::json!({
+                "id": "node",
+                "version": "1.0.0",
+                "customizations": customizations,
+            }))
+            .unwrap(),
+            path: PathBuf::from("/features/node"),
+            fetch_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_collect_merges_features_and_devcontainer() {
+        let features = [feature(serde_json::json!({
+            "vscode": {
+                "extensions": ["dbaeumer.vscode-eslint", "esbenp.prettier-vscode"],
+                "settings": { "editor.formatOnSave": false, "eslint.enable": true }
+            }
+        }))];
+        let devcontainer: Devcontainer = serde_json::from_value(serde_json::json!({
+            "image": "ubuntu",
+            "customizations": {
+                "vscode": {
+                    "extensions": ["DBAEUMER.vscode-eslint", "-esbenp.prettier-vscode", "rust-lang.rust-analyzer"],
+                    "settings": { "editor.formatOnSave": true }
+                }
+            }
+        }))
+        .unwrap();
+
+        let merged = VscodeCustomizations::collect(&features, &devcontainer);
+        assert_eq!(
+            merged.extensions,
+            vec!["dbaeumer.vscode-eslint", "rust-lang.rust-analyzer"]
+        );
+        assert_eq!(merged.settings["editor.formatOnSave"], true);
+        assert_eq!(merged.settings["eslint.enable"], true);
+    }
+
+    #[test]
+    fn test_settings_stage() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            VscodeCustomizations::default()
+                .settings_stage("vscode", "/home/vscode", temp.path())
+                .unwrap(),
+            ""
+        );
+
+        let mut customizations = VscodeCustomizations::default();
+        customizations
+            .settings
+            .insert("editor.tabSize".to_string(), Value::from(2));
+        let stage = customizations
+            .settings_stage("vscode", "/home/vscode/", temp.path())
+            .unwrap();
+        assert!(stage.contains("mkdir -p /home/vscode/.vscode-server/data/Machine"));
+        assert!(stage.contains("/home/vscode/.vscode-server/data/Machine/settings.json"));
+        assert!(stage.contains("chown -R vscode /home/vscode/.vscode-server"));
+        let written = fs::read_to_string(temp.path().join(SETTINGS_FILE)).unwrap();
+        assert!(written.contains("\"editor.tabSize\": 2"));
+    }
+}
@@ -0,0 +1,167 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Workspace Locks
+//!
+//! Operations changing the image or container of a workspace hold an
+//! advisory lock on a file in the state directory, so concurrent `devcon up`
+//! runs of the same workspace don't race on the build directory, the
+//! feature cache and container creation. The operating system releases the
+//! lock when the process exits, even if it crashes.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::workspace::Workspace;
+
+/// Errors of locking a workspace.
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    /// Another process holds the lock of the workspace.
+    #[error(
+        "Another devcon operation is in progress for workspace {workspace}{}. Wait for it to finish or pass --wait.",
+        holder_suffix(*.pid)
+    )]
+    Busy { workspace: String, pid: Option<u32> },
+}
+
+fn holder_suffix(pid: Option<u32>) -> String {
+    pid.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+}
+
+/// An exclusive lock of a workspace, released when dropped.
+#[derive(Debug)]
+pub struct WorkspaceLock {
+    _file: File,
+}
+
+/// Lock `workspace` for an operation changing its image or container.
+///
+/// With `wait`, blocks until a concurrent operation finished, otherwise
+/// fails at once.
+///
+/// # Errors
+///
+/// Returns [`LockError::Busy`] if another process holds the lock and `wait`
+/// is not set, or an error if the lock file cannot be opened.
+pub fn lock(workspace: &Workspace, wait: bool) -> Result<WorkspaceLock> {
+    let name = workspace.get_sanitized_name();
+    lock_file(lock_path(&name)?, &name, wait)
+}
+
+fn lock_file(path: PathBuf, workspace: &str, wait: bool) -> Result<WorkspaceLock> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let pid = holder_pid(&mut file);
+            if !wait {
+                return Err(LockError::Busy {
+                    workspace: workspace.to_string(),
+                    pid,
+                }
+                .into());
+            }
+            println!(
+                "Waiting for another devcon operation on {}{} to finish..",
+                workspace,
+                holder_suffix(pid)
+            );
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => return Err(e.into()),
+    }
+
+    // Record the holder for the error message of concurrent operations
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    file.flush()?;
+
+    Ok(WorkspaceLock { _file: file })
+}
+
+/// Read the process ID the current holder recorded in the lock file
+fn holder_pid(file: &mut File) -> Option<u32> {
+    let mut content = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+fn lock_path(workspace: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("locks")
+        .join(format!("{}.lock", workspace)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("locks").join("my-project.lock");
+
+        let lock = lock_file(path.clone(), "my-project", false).unwrap();
+        let error = lock_file(path.clone(), "my-project", false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Another devcon operation is in progress for workspace my-project (pid {}). Wait for it to finish or pass --wait.",
+                std::process::id()
+            )
+        );
+
+        drop(lock);
+        lock_file(path, "my-project", false).unwrap();
+    }
+
+    #[test]
+    fn test_wait_for_lock() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("my-project.lock");
+
+        let lock = lock_file(path.clone(), "my-project", false).unwrap();
+        let waiter = std::thread::spawn(move || lock_file(path, "my-project", true).is_ok());
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+
+        drop(lock);
+        assert!(waiter.join().unwrap());
+    }
+}
@@ -0,0 +1,413 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Workspace State
+//!
+//! This module records what devcon did for a workspace that the container
+//! runtime doesn't know about: the configuration hash of the last successful
+//! build and the lifecycle hooks which completed in the current container.
+//! `devcon status` uses it to report stale images and skipped hooks, `devcon
+//! shell` to wait for the lifecycle stage of `waitFor`.
+//!
+//! Container labels can't change after the start, so the lifecycle progress
+//! is kept here instead.
+//!
+//! The state of every workspace is a JSON file in the `workspaces` directory
+//! of the devcon state directory.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::driver::feature_options::OptionAnswers;
+use crate::driver::image_metadata::HookCommand;
+use crate::workspace::Workspace;
+
+/// Recorded state of a single workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceState {
+    /// Configuration hash the image was last built from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// Container the lifecycle hooks were recorded for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    /// Lifecycle hooks which completed in the container, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lifecycle_hooks: Vec<String>,
+    /// Lifecycle hook which failed in the container, no later one runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_hook: Option<String>,
+    /// Lifecycle hook currently running in the container
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running_hook: Option<String>,
+    /// Lifecycle hooks left to the background process, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub background_hooks: Vec<BackgroundHook>,
+    /// Networks of `devcon network link`, joined by every new container
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<String>,
+    /// Answers to required feature options asked for by `build` or `up`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub feature_options: OptionAnswers,
+}
+
+/// Lifecycle hook run by the background process of `backgroundLifecycle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackgroundHook {
+    pub hook: String,
+    pub commands: Vec<HookCommand>,
+}
+
+/// Progress of a container's lifecycle towards a hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleProgress {
+    /// No progress was recorded for the container, e.g. it was started by an
+    /// older devcon
+    Unknown,
+    /// The hook didn't complete yet
+    Pending,
+    /// The hook completed
+    Completed,
+    /// An earlier or the same hook failed
+    Failed(String),
+}
+
+impl WorkspaceState {
+    /// Loads the state of a workspace, the default if none was recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file cannot be read or parsed.
+    pub fn load(workspace: &str) -> Result<Self> {
+        Self::load_from(&state_path(workspace)?)
+    }
+
+    /// Loads, modifies and saves the state of a workspace.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file cannot be read or written.
+    pub fn update(workspace: &str, modify: impl FnOnce(&mut Self)) -> Result<()> {
+        let path = state_path(workspace)?;
+        let mut state = Self::load_from(&path)?;
+        modify(&mut state);
+        state.save_to(&path)
+    }
+
+    /// Removes the recorded state of a workspace, e.g. of a throwaway one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file exists but cannot be removed.
+    pub fn remove(workspace: &str) -> Result<()> {
+        let path = state_path(workspace)?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove workspace state {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the lifecycle was recorded for `container_id`.
+    ///
+    /// Runtimes list containers by a prefix of the id `run` returned, e.g.
+    /// Docker's short ids, so either may be a prefix of the other.
+    pub fn is_container(&self, container_id: &str) -> bool {
+        self.container_id.as_deref().is_some_and(|recorded| {
+            !recorded.is_empty()
+                && !container_id.is_empty()
+                && (recorded.starts_with(container_id) || container_id.starts_with(recorded))
+        })
+    }
+
+    /// Returns the lifecycle progress of `container_id` towards `hook`.
+    pub fn lifecycle_progress(&self, container_id: &str, hook: &str) -> LifecycleProgress {
+        if !self.is_container(container_id) {
+            LifecycleProgress::Unknown
+        } else if self.lifecycle_hooks.iter().any(|h| h == hook) {
+            LifecycleProgress::Completed
+        } else if let Some(failed) = &self.failed_hook {
+            LifecycleProgress::Failed(failed.clone())
+        } else {
+            LifecycleProgress::Pending
+        }
+    }
+
+    /// Returns the background hooks which didn't complete yet, nothing once a
+    /// hook failed.
+    pub fn pending_background_hooks(&self) -> Vec<&str> {
+        if self.failed_hook.is_some() {
+            return Vec::new();
+        }
+        self.background_hooks
+            .iter()
+            .map(|background| background.hook.as_str())
+            .filter(|hook| !self.lifecycle_hooks.iter().any(|h| h == hook))
+            .collect()
+    }
+
+    /// Describes the lifecycle stage of the container for `devcon status`.
+    pub fn lifecycle_stage(&self) -> String {
+        let pending = self.pending_background_hooks();
+        if let Some(failed) = &self.failed_hook {
+            format!("failed in {}", failed)
+        } else if let Some(running) = &self.running_hook {
+            let background = if pending.contains(&running.as_str()) {
+                " in the background"
+            } else {
+                ""
+            };
+            format!("running {}{}", running, background)
+        } else if !pending.is_empty() {
+            format!("{} pending in the background", pending.join(", "))
+        } else {
+            "complete".to_string()
+        }
+    }
+
+    /// Starts recording the lifecycle of a new container.
+    pub fn reset_lifecycle(&mut self, container_id: &str) {
+        self.container_id = Some(container_id.to_string());
+        self.lifecycle_hooks.clear();
+        self.failed_hook = None;
+        self.running_hook = None;
+        self.background_hooks.clear();
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read workspace state {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid workspace state {}", path.display()))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write workspace state {}", path.display()))
+    }
+}
+
+/// Hashes everything an image is built from: `devcontainer.json`, the
+/// definitions it extends and the additional features of the user config.
+pub fn config_hash(workspace: &Workspace, config: &Config) -> String {
+    let content = workspace
+        .devcontainer_files()
+        .and_then(|paths| {
+            paths
+                .iter()
+                .map(|path| Ok(fs::read_to_string(path)?))
+                .collect::<anyhow::Result<String>>()
+        })
+        .unwrap_or_default();
+    hash_config(&content, &config.additional_features)
+}
+
+fn hash_config(
+    devcontainer: &str,
+    additional_features: &HashMap<String, serde_json::Value>,
+) -> String {
+    // Sorted, so the hash doesn't depend on the map's iteration order
+    let features: BTreeMap<_, _> = additional_features.iter().collect();
+
+    let mut hasher = Sha256::new();
+    hasher.update(devcontainer.as_bytes());
+    hasher.update(
+        serde_json::to_string(&features)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    format!("{:x}", hasher.finalize())
+}
+
+/// Path of the output log of the last image build.
+pub fn build_log_path(workspace: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("workspaces")
+        .join(format!("{}.build.log", workspace)))
+}
+
+/// Path of the output log of the last `initializeCommand` run on the host.
+pub fn initialize_log_path(workspace: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("workspaces")
+        .join(format!("{}.initialize.log", workspace)))
+}
+
+/// Path of the output log of background lifecycle hooks.
+pub fn lifecycle_log_path(workspace: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("workspaces")
+        .join(format!("{}.lifecycle.log", workspace)))
+}
+
+fn state_path(workspace: &str) -> Result<PathBuf> {
+    Ok(Config::get_state_dir()?
+        .join("workspaces")
+        .join(format!("{}.json", workspace)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_roundtrip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("workspaces").join("project.json");
+        assert_eq!(
+            WorkspaceState::load_from(&path).unwrap(),
+            WorkspaceState::default()
+        );
+
+        let state = WorkspaceState {
+            config_hash: Some("abc".to_string()),
+            container_id: Some("1234".to_string()),
+            lifecycle_hooks: vec!["onCreateCommand".to_string()],
+            failed_hook: None,
+            running_hook: Some("postCreateCommand".to_string()),
+            background_hooks: vec![BackgroundHook {
+                hook: "postStartCommand".to_string(),
+                commands: vec![HookCommand {
+                    feature: None,
+                    command: crate::devcontainer::LifecycleCommand::String(
+                        "npm run dev".to_string(),
+                    ),
+                }],
+            }],
+            networks: vec!["devcon-link-api--web".to_string()],
+            feature_options: BTreeMap::from([(
+                "node".to_string(),
+                BTreeMap::from([("version".to_string(), serde_json::Value::from("20"))]),
+            )]),
+        };
+        state.save_to(&path).unwrap();
+        assert_eq!(WorkspaceState::load_from(&path).unwrap(), state);
+    }
+
+    #[test]
+    fn test_lifecycle_progress() {
+        let mut state = WorkspaceState::default();
+        assert_eq!(
+            state.lifecycle_progress("1234", "postCreateCommand"),
+            LifecycleProgress::Unknown
+        );
+
+        state.reset_lifecycle("1234");
+        state.lifecycle_hooks.push("onCreateCommand".to_string());
+        assert_eq!(
+            state.lifecycle_progress("1234", "onCreateCommand"),
+            LifecycleProgress::Completed
+        );
+        assert_eq!(
+            state.lifecycle_progress("1234", "postCreateCommand"),
+            LifecycleProgress::Pending
+        );
+        assert_eq!(
+            state.lifecycle_progress("5678", "onCreateCommand"),
+            LifecycleProgress::Unknown
+        );
+        assert_eq!(
+            state.lifecycle_progress("12", "onCreateCommand"),
+            LifecycleProgress::Completed
+        );
+        assert!(!state.is_container(""));
+
+        state.failed_hook = Some("updateContentCommand".to_string());
+        assert_eq!(
+            state.lifecycle_progress("1234", "postCreateCommand"),
+            LifecycleProgress::Failed("updateContentCommand".to_string())
+        );
+
+        state.running_hook = Some("postCreateCommand".to_string());
+        state.failed_hook = None;
+        assert_eq!(state.lifecycle_stage(), "running postCreateCommand");
+        state.reset_lifecycle("5678");
+        assert!(state.lifecycle_hooks.is_empty());
+        assert_eq!(state.failed_hook, None);
+        assert_eq!(state.running_hook, None);
+    }
+
+    #[test]
+    fn test_background_lifecycle_stage() {
+        let background = |hook: &str| BackgroundHook {
+            hook: hook.to_string(),
+            commands: Vec::new(),
+        };
+        let mut state = WorkspaceState::default();
+        state.reset_lifecycle("1234");
+        state.lifecycle_hooks = vec![
+            "onCreateCommand".to_string(),
+            "updateContentCommand".to_string(),
+        ];
+        state.background_hooks = vec![
+            background("postCreateCommand"),
+            background("postStartCommand"),
+        ];
+        assert_eq!(
+            state.lifecycle_stage(),
+            "postCreateCommand, postStartCommand pending in the background"
+        );
+
+        state.running_hook = Some("postCreateCommand".to_string());
+        assert_eq!(
+            state.lifecycle_stage(),
+            "running postCreateCommand in the background"
+        );
+
+        state.running_hook = None;
+        state.lifecycle_hooks.push("postCreateCommand".to_string());
+        assert_eq!(state.pending_background_hooks(), vec!["postStartCommand"]);
+        state.lifecycle_hooks.push("postStartCommand".to_string());
+        assert_eq!(state.lifecycle_stage(), "complete");
+
+        state.failed_hook = Some("postStartCommand".to_string());
+        assert!(state.pending_background_hooks().is_empty());
+        assert_eq!(state.lifecycle_stage(), "failed in postStartCommand");
+    }
+
+    #[test]
+    fn test_config_hash_covers_additional_features() {
+        let mut features = HashMap::new();
+        let without = hash_config("{}", &features);
+        assert_eq!(without, hash_config("{}", &features));
+        assert_ne!(without, hash_config("{\"image\": \"debian\"}", &features));
+
+        features.insert(
+            "ghcr.io/devcontainers/features/node:1".to_string(),
+            serde_json::json!({}),
+        );
+        assert_ne!(without, hash_config("{}", &features));
+    }
+}
@@ -0,0 +1,148 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Exit Codes
+//!
+//! devcon exits with a code telling scripts and CI why a command failed, so
+//! they can branch on the failure instead of parsing stderr:
+//!
+//! | Code | Meaning                                                   |
+//! |------|-----------------------------------------------------------|
+//! | 0    | Success                                                   |
+//! | 1    | Any other error                                           |
+//! | 2    | Invalid configuration or command line usage              |
+//! | 3    | Container runtime not available                           |
+//! | 4    | Building the image failed                                 |
+//! | 5    | Container not running or image not built                  |
+//! | 6    | Network or tunnel error; retrying may help                |
+//! | 7    | Invalid feature definition                                |
+//! | 130  | Cancelled with Ctrl+C                                     |
+//!
+//! Usage errors are reported by clap, which exits with 2 as well. A command
+//! run in the container, e.g. by `devcon ci`, passes its own exit code through.
+
+use crate::config::ConfigError;
+use crate::driver::control_server::TunnelError;
+use crate::driver::feature_process::FeatureError;
+use crate::driver::runtime::RuntimeError;
+
+/// Any error without a more specific code.
+pub const FAILURE: i32 = 1;
+
+/// Invalid configuration or command line usage.
+pub const CONFIG: i32 = 2;
+
+/// No usable container runtime is installed or running.
+pub const RUNTIME_UNAVAILABLE: i32 = 3;
+
+/// The container runtime failed to build the image.
+pub const BUILD_FAILED: i32 = 4;
+
+/// The container is not running or its image was not built.
+pub const CONTAINER_NOT_RUNNING: i32 = 5;
+
+/// A download or tunnel failed, the command may succeed when retried.
+pub const NETWORK: i32 = 6;
+
+/// Invalid feature definition.
+pub const INVALID_DEFINITION: i32 = 7;
+
+/// The command was cancelled, as for SIGINT.
+pub const INTERRUPTED: i32 = 130;
+
+/// Returns the exit code for an error.
+///
+/// The code is taken from the outermost typed error of the chain, errors
+/// without one exit with [`FAILURE`].
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| {
+            if let Some(error) = cause.downcast_ref::<ConfigError>() {
+                Some(error.exit_code())
+            } else if let Some(error) = cause.downcast_ref::<FeatureError>() {
+                Some(error.exit_code())
+            } else if let Some(error) = cause.downcast_ref::<RuntimeError>() {
+                Some(error.exit_code())
+            } else {
+                cause
+                    .downcast_ref::<TunnelError>()
+                    .map(TunnelError::exit_code)
+            }
+        })
+        .unwrap_or(FAILURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn test_exit_code_of_typed_errors() {
+        let error: anyhow::Error = RuntimeError::NotAvailable.into();
+        assert_eq!(exit_code(&error), RUNTIME_UNAVAILABLE);
+
+        let error: anyhow::Error = RuntimeError::BuildFailed("Docker").into();
+        assert_eq!(exit_code(&error), BUILD_FAILED);
+
+        let error: anyhow::Error = RuntimeError::ContainerNotRunning.into();
+        assert_eq!(exit_code(&error), CONTAINER_NOT_RUNNING);
+
+        let error: anyhow::Error = ConfigError::UnknownProperty("foo".to_string()).into();
+        assert_eq!(exit_code(&error), CONFIG);
+
+        let error: anyhow::Error = TunnelError::Timeout.into();
+        assert_eq!(exit_code(&error), NETWORK);
+
+        let error: anyhow::Error = RuntimeError::CommandFailed(42).into();
+        assert_eq!(exit_code(&error), 42);
+    }
+
+    #[test]
+    fn test_exit_code_through_context() {
+        let error = Err::<(), _>(RuntimeError::Cancelled)
+            .context("Failed to build")
+            .unwrap_err();
+        assert_eq!(exit_code(&error), INTERRUPTED);
+    }
+
+    #[test]
+    fn test_exit_code_of_failed_features() {
+        let error: anyhow::Error = FeatureError::Failed {
+            total: 2,
+            failures: vec![(
+                "node".to_string(),
+                FeatureError::NotCached("ghcr.io/devcontainers/features/node:1".to_string()).into(),
+            )],
+        }
+        .into();
+        assert_eq!(exit_code(&error), NETWORK);
+        assert!(error.to_string().starts_with("Failed to process 1 of 2 features:\n  - node: Feature ghcr.io/devcontainers/features/node:1 is not in the local cache"));
+    }
+
+    #[test]
+    fn test_exit_code_of_untyped_error() {
+        assert_eq!(exit_code(&anyhow::anyhow!("Something failed")), FAILURE);
+    }
+}
@@ -193,7 +193,8 @@ pub struct FeatureOption {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
-    /// Default value
+    /// Default value, options without one are required
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub default: serde_json::Value,
 
     /// Allowed values (user cannot provide custom values)
@@ -205,6 +206,13 @@ pub struct FeatureOption {
     pub proposals: Option<Vec<String>>,
 }
 
+impl FeatureOption {
+    /// Whether the option has no default and must be set by the user.
+    pub fn is_required(&self) -> bool {
+        self.default.is_null()
+    }
+}
+
 /// Type of feature option.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -443,4 +451,21 @@ mod tests {
         assert_eq!(feature.id, deserialized.id);
         assert_eq!(feature.version, deserialized.version);
     }
+
+    #[test]
+    fn test_option_without_default_is_required() {
+        let json = json!({
+            "id": "test-feature",
+            "version": "1.0.0",
+            "options": {
+                "token": { "type": "string" },
+                "version": { "type": "string", "default": "" }
+            }
+        });
+
+        let feature: Feature = serde_json::from_value(json).unwrap();
+        let options = feature.options.unwrap();
+        assert!(options["token"].is_required());
+        assert!(!options["version"].is_required());
+    }
 }
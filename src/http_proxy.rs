@@ -0,0 +1,346 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # HTTP Proxy Settings
+//!
+//! Corporate networks often only allow outgoing HTTP(S) through a proxy. The
+//! host's proxy settings are detected from the usual environment variables
+//! (`HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY`, `NO_PROXY`, upper or lower case)
+//! and, on macOS, from the system configuration (`scutil --proxy`).
+//!
+//! The settings are used for feature downloads and, unless `propagateProxy`
+//! is disabled, passed to image builds as build arguments and to containers
+//! as environment variables.
+//!
+//! HTTP clients additionally trust the CA certificates configured with
+//! `caCertificates`, which TLS-intercepting proxies require.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+/// Proxy settings of the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxySettings {
+    /// Proxy URL for plain HTTP requests
+    pub http: Option<String>,
+    /// Proxy URL for HTTPS requests
+    pub https: Option<String>,
+    /// Comma-separated hosts and domains which are accessed directly
+    pub no_proxy: Option<String>,
+}
+
+impl ProxySettings {
+    /// Detects the proxy settings of the host.
+    ///
+    /// Environment variables take precedence over the macOS system configuration.
+    pub fn detect() -> Self {
+        let settings = Self::from_env(|name| std::env::var(name).ok());
+        if !settings.is_empty() {
+            return settings;
+        }
+
+        if cfg!(target_os = "macos") {
+            match Command::new("scutil").arg("--proxy").output() {
+                Ok(output) if output.status.success() => {
+                    return Self::from_scutil(&String::from_utf8_lossy(&output.stdout));
+                }
+                Ok(output) => debug!("scutil --proxy failed with {}", output.status),
+                Err(e) => debug!("Failed to run scutil --proxy: {}", e),
+            }
+        }
+
+        settings
+    }
+
+    /// Reads the settings from environment variables using `lookup`.
+    fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            lookup(name)
+                .or_else(|| lookup(&name.to_lowercase()))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let all = var("ALL_PROXY");
+        Self {
+            http: var("HTTP_PROXY").or_else(|| all.clone()),
+            https: var("HTTPS_PROXY").or(all),
+            no_proxy: var("NO_PROXY"),
+        }
+    }
+
+    /// Parses the output of `scutil --proxy`.
+    fn from_scutil(output: &str) -> Self {
+        let mut enabled = (false, false);
+        let mut http = (None, None);
+        let mut https = (None, None);
+        let mut exceptions = Vec::new();
+        let mut in_exceptions = false;
+
+        for line in output.lines().map(str::trim) {
+            if in_exceptions {
+                if line == "}" {
+                    in_exceptions = false;
+                } else if let Some((_, host)) = line.split_once(" : ") {
+                    exceptions.push(host.trim().to_string());
+                }
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(" : ") else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "HTTPEnable" => enabled.0 = value == "1",
+                "HTTPProxy" => http.0 = Some(value),
+                "HTTPPort" => http.1 = Some(value),
+                "HTTPSEnable" => enabled.1 = value == "1",
+                "HTTPSProxy" => https.0 = Some(value),
+                "HTTPSPort" => https.1 = Some(value),
+                "ExceptionsList" => in_exceptions = value.ends_with('{'),
+                _ => {}
+            }
+        }
+
+        let url = |enabled: bool, (host, port): (Option<String>, Option<String>)| {
+            let host = host.filter(|_| enabled)?;
+            Some(match port {
+                Some(port) => format!("http://{}:{}", host, port),
+                None => format!("http://{}", host),
+            })
+        };
+
+        Self {
+            http: url(enabled.0, http),
+            https: url(enabled.1, https),
+            no_proxy: (!exceptions.is_empty()).then(|| exceptions.join(",")),
+        }
+    }
+
+    /// Whether no proxy is configured.
+    pub fn is_empty(&self) -> bool {
+        self.http.is_none() && self.https.is_none()
+    }
+
+    /// Returns the settings as `KEY=value` pairs, in upper and lower case.
+    ///
+    /// Used both as build arguments, which Docker accepts for these names
+    /// without an `ARG` instruction, and as container environment.
+    pub fn env_vars(&self) -> Vec<String> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut vars = Vec::new();
+        for (name, value) in [
+            ("HTTP_PROXY", &self.http),
+            ("HTTPS_PROXY", &self.https),
+            ("NO_PROXY", &self.no_proxy),
+        ] {
+            if let Some(value) = value {
+                vars.push(format!("{}={}", name, value));
+                vars.push(format!("{}={}", name.to_lowercase(), value));
+            }
+        }
+
+        for url in [&self.http, &self.https].into_iter().flatten() {
+            if ["localhost", "127.0.0.1", "[::1]"]
+                .iter()
+                .any(|host| url.contains(&format!("//{}", host)) || url.starts_with(host))
+            {
+                warn!(
+                    "Proxy {} points to the host's loopback interface and is not reachable from containers",
+                    url
+                );
+            }
+        }
+        vars
+    }
+
+    /// Builds a blocking HTTP client using these settings, trusting the
+    /// given CA certificate files in addition to the system roots.
+    ///
+    /// Without detected settings the client falls back to reqwest's own
+    /// system proxy detection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a proxy URL or a certificate file is invalid.
+    pub fn client(&self, ca_certificates: &[PathBuf]) -> Result<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder();
+        if !ca_certificates.is_empty() {
+            builder = builder.tls_certs_merge(load_certificates(ca_certificates)?);
+        }
+        if self.is_empty() {
+            return builder.build().context("Failed to create HTTP client");
+        }
+
+        let no_proxy = self
+            .no_proxy
+            .as_deref()
+            .and_then(reqwest::NoProxy::from_string);
+        if let Some(http) = &self.http {
+            builder = builder.proxy(
+                reqwest::Proxy::http(http)
+                    .with_context(|| format!("Invalid HTTP proxy '{}'", http))?
+                    .no_proxy(no_proxy.clone()),
+            );
+        }
+        if let Some(https) = &self.https {
+            builder = builder.proxy(
+                reqwest::Proxy::https(https)
+                    .with_context(|| format!("Invalid HTTPS proxy '{}'", https))?
+                    .no_proxy(no_proxy),
+            );
+        }
+        builder.build().context("Failed to create HTTP client")
+    }
+}
+
+/// Builds a blocking HTTP client using the detected host proxy settings
+/// and the given additional CA certificate files.
+///
+/// # Errors
+///
+/// Returns an error if a detected proxy URL or a certificate file is invalid.
+pub fn http_client(ca_certificates: &[PathBuf]) -> Result<reqwest::blocking::Client> {
+    ProxySettings::detect().client(ca_certificates)
+}
+
+/// Reads all certificates of the given PEM files.
+fn load_certificates(paths: &[PathBuf]) -> Result<Vec<reqwest::Certificate>> {
+    let mut certificates = Vec::new();
+    for path in paths {
+        certificates.extend(load_pem(path)?);
+    }
+    Ok(certificates)
+}
+
+fn load_pem(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let pem = std::fs::read(path)
+        .with_context(|| format!("Failed to read CA certificate {}", path.display()))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Invalid CA certificate {}", path.display()))?;
+    if certificates.is_empty() {
+        anyhow::bail!("No certificate found in {}", path.display());
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env() {
+        let settings = ProxySettings::from_env(|name| match name {
+            "https_proxy" => Some("http://proxy.corp:8080".to_string()),
+            "ALL_PROXY" => Some("http://fallback.corp:3128".to_string()),
+            "NO_PROXY" => Some("localhost,.corp".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            settings,
+            ProxySettings {
+                http: Some("http://fallback.corp:3128".to_string()),
+                https: Some("http://proxy.corp:8080".to_string()),
+                no_proxy: Some("localhost,.corp".to_string()),
+            }
+        );
+
+        assert!(ProxySettings::from_env(|_| None).is_empty());
+    }
+
+    #[test]
+    fn test_from_scutil() {
+        let output = "<dictionary> {
+  ExceptionsList : <array> {
+    0 : *.local
+    1 : 169.254/16
+  }
+  FTPPassive : 1
+  HTTPEnable : 1
+  HTTPPort : 8080
+  HTTPProxy : proxy.corp
+  HTTPSEnable : 0
+  HTTPSPort : 8443
+  HTTPSProxy : secure.corp
+}";
+        assert_eq!(
+            ProxySettings::from_scutil(output),
+            ProxySettings {
+                http: Some("http://proxy.corp:8080".to_string()),
+                https: None,
+                no_proxy: Some("*.local,169.254/16".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_env_vars() {
+        assert!(ProxySettings::default().env_vars().is_empty());
+
+        let settings = ProxySettings {
+            http: None,
+            https: Some("http://proxy.corp:8080".to_string()),
+            no_proxy: Some(".corp".to_string()),
+        };
+        assert_eq!(
+            settings.env_vars(),
+            vec![
+                "HTTPS_PROXY=http://proxy.corp:8080",
+                "https_proxy=http://proxy.corp:8080",
+                "NO_PROXY=.corp",
+                "no_proxy=.corp",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_client() {
+        let settings = ProxySettings {
+            http: Some("http://proxy.corp:8080".to_string()),
+            https: Some("http://proxy.corp:8080".to_string()),
+            no_proxy: Some("localhost".to_string()),
+        };
+        assert!(settings.client(&[]).is_ok());
+        assert!(ProxySettings::default().client(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_client_rejects_invalid_certificate() {
+        let temp = tempfile::tempdir().unwrap();
+        let empty = temp.path().join("empty.pem");
+        std::fs::write(&empty, "no certificate here").unwrap();
+
+        assert!(ProxySettings::default().client(&[empty]).is_err());
+        assert!(
+            ProxySettings::default()
+                .client(&[temp.path().join("missing.pem")])
+                .is_err()
+        );
+    }
+}
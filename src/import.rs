@@ -0,0 +1,391 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Import from VS Code
+//!
+//! Eases switching from the VS Code Dev Containers extension: the devcontainer
+//! projects recently opened in VS Code become recent paths of devcon, and the
+//! dotfiles, default features and Docker path settings of the extension fill
+//! in the matching devcon settings.
+//!
+//! Recent folders are read from `storage.json` of VS Code's global storage.
+//! Folders opened in a container are recognized by their
+//! `vscode-remote://dev-container+<hex>` URI, which encodes the host folder.
+//! Settings already set in devcon are kept.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::discovery::is_project;
+
+/// Settings and recent projects found in a VS Code installation.
+#[derive(Debug, Default, PartialEq)]
+pub struct VscodeImport {
+    /// Devcontainer projects opened recently, most recent first
+    pub recent_paths: Vec<PathBuf>,
+    /// `dotfiles.repository`
+    pub dotfiles_repository: Option<String>,
+    /// `dotfiles.installCommand`
+    pub dotfiles_install_command: Option<String>,
+    /// `dotfiles.targetPath`
+    pub dotfiles_target_dir: Option<String>,
+    /// `dev.containers.defaultFeatures`
+    pub default_features: HashMap<String, serde_json::Value>,
+    /// Runtime derived from `dev.containers.dockerPath`
+    pub runtime: Option<String>,
+}
+
+/// Returns the user data directories of VS Code, VS Code Insiders and
+/// VSCodium which exist on this host.
+pub fn vscode_user_dirs() -> Vec<PathBuf> {
+    let Some(config_dir) = dirs::config_dir() else {
+        return Vec::new();
+    };
+    ["Code", "Code - Insiders", "VSCodium"]
+        .iter()
+        .map(|name| config_dir.join(name).join("User"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+impl VscodeImport {
+    /// Reads the settings and recent projects of a VS Code user directory.
+    ///
+    /// Missing files are skipped, so a fresh installation imports nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file exists but cannot be read or parsed.
+    pub fn read(user_dir: &Path) -> Result<Self> {
+        let mut import = Self::default();
+
+        let settings_path = user_dir.join("settings.json");
+        if let Some(settings) = read_json(&settings_path)? {
+            import.read_settings(&settings);
+        }
+
+        let storage_path = user_dir.join("globalStorage").join("storage.json");
+        if let Some(storage) = read_json(&storage_path)? {
+            let mut uris = Vec::new();
+            collect_folder_uris(&storage, &mut uris);
+            for uri in uris {
+                if let Some(path) = folder_path(&uri)
+                    && is_project(&path)
+                    && !import.recent_paths.contains(&path)
+                {
+                    import.recent_paths.push(path);
+                }
+            }
+        }
+
+        Ok(import)
+    }
+
+    fn read_settings(&mut self, settings: &serde_json::Value) {
+        let string = |key: &str| {
+            settings
+                .get(key)
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        self.dotfiles_repository = string("dotfiles.repository");
+        self.dotfiles_install_command = string("dotfiles.installCommand");
+        self.dotfiles_target_dir = string("dotfiles.targetPath");
+        if let Some(features) = settings
+            .get("dev.containers.defaultFeatures")
+            .and_then(|v| v.as_object())
+        {
+            self.default_features = features
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+        }
+        self.runtime = string("dev.containers.dockerPath").and_then(|path| {
+            let binary = Path::new(&path).file_stem()?.to_string_lossy().to_string();
+            ["docker", "podman"]
+                .contains(&binary.as_str())
+                .then_some(binary)
+        });
+    }
+
+    /// Applies the import to `config`, keeping settings which are already set.
+    ///
+    /// Returns a description of every change.
+    pub fn apply(&self, config: &mut Config) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        for path in &self.recent_paths {
+            let path = path.to_string_lossy().to_string();
+            if config.add_recent(&path) {
+                changes.push(format!("recent project {}", path));
+            }
+        }
+
+        let settings = [
+            (
+                "dotfilesRepository",
+                &mut config.dotfiles_repository,
+                &self.dotfiles_repository,
+            ),
+            (
+                "dotfilesInstallCommand",
+                &mut config.dotfiles_install_command,
+                &self.dotfiles_install_command,
+            ),
+            (
+                "dotfilesTargetDir",
+                &mut config.dotfiles_target_dir,
+                &self.dotfiles_target_dir,
+            ),
+        ];
+        for (name, current, imported) in settings {
+            if current.is_none()
+                && let Some(value) = imported
+            {
+                changes.push(format!("{} = {}", name, value));
+                *current = Some(value.clone());
+            }
+        }
+
+        for (feature, options) in &self.default_features {
+            if !config.additional_features.contains_key(feature) {
+                changes.push(format!("additional feature {}", feature));
+                config
+                    .additional_features
+                    .insert(feature.clone(), options.clone());
+            }
+        }
+
+        if let Some(runtime) = &self.runtime
+            && config.runtime == "auto"
+        {
+            changes.push(format!("runtime = {}", runtime));
+            config.runtime = runtime.clone();
+        }
+
+        changes
+    }
+}
+
+/// Reads a JSON file with comments, `None` if it doesn't exist.
+fn read_json(path: &Path) -> Result<Option<serde_json::Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    json_strip_comments::strip(&mut content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Collects the folder URIs of a VS Code storage file in document order.
+///
+/// The recent folders are kept in several places depending on the VS Code
+/// version, so every `folderUri` or `folder` value and every URI key is taken.
+fn collect_folder_uris(value: &serde_json::Value, uris: &mut Vec<String>) {
+    let is_uri = |s: &str| s.starts_with("file://") || s.starts_with("vscode-remote://");
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                if is_uri(key) {
+                    uris.push(key.clone());
+                }
+                match value {
+                    serde_json::Value::String(s)
+                        if (key == "folderUri" || key == "folder") && is_uri(s) =>
+                    {
+                        uris.push(s.clone())
+                    }
+                    _ => collect_folder_uris(value, uris),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_folder_uris(item, uris);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the host folder of a VS Code folder URI.
+///
+/// `file://` URIs are local folders. For `vscode-remote://dev-container+<hex>`
+/// URIs the hex part encodes the host folder, either as plain path or as JSON
+/// object with a `hostPath`.
+fn folder_path(uri: &str) -> Option<PathBuf> {
+    if let Some(path) = uri.strip_prefix("file://") {
+        let path = percent_decode(path);
+        // Windows drives come as /c:/Users/...
+        let path = match path.as_bytes() {
+            [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+            _ => path,
+        };
+        return Some(PathBuf::from(path));
+    }
+
+    let authority = uri.strip_prefix("vscode-remote://")?;
+    let authority = percent_decode(authority.split('/').next()?);
+    let hex = authority.strip_prefix("dev-container+")?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    let decoded = String::from_utf8(bytes).ok()?;
+    match serde_json::from_str::<serde_json::Value>(&decoded) {
+        Ok(json) => json
+            .get("hostPath")
+            .and_then(|p| p.as_str())
+            .map(PathBuf::from),
+        Err(_) => Some(PathBuf::from(decoded)),
+    }
+}
+
+/// Decodes `%XX` escapes of a URI component.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(value: &str) -> String {
+        value.bytes().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_folder_path() {
+        assert_eq!(
+            folder_path("file:///home/me/my%20project"),
+            Some(PathBuf::from("/home/me/my project"))
+        );
+        assert_eq!(
+            folder_path("file:///c%3A/src/app"),
+            Some(PathBuf::from("c:/src/app"))
+        );
+        assert_eq!(
+            folder_path(&format!(
+                "vscode-remote://dev-container%2B{}/workspaces/app",
+                hex("/home/me/app")
+            )),
+            Some(PathBuf::from("/home/me/app"))
+        );
+        assert_eq!(
+            folder_path(&format!(
+                "vscode-remote://dev-container+{}/workspaces/app",
+                hex(r#"{"hostPath":"/home/me/app","configFile":{}}"#)
+            )),
+            Some(PathBuf::from("/home/me/app"))
+        );
+        assert_eq!(folder_path("vscode-remote://ssh-remote+host/src"), None);
+    }
+
+    #[test]
+    fn test_read_and_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join(".devcontainer")).unwrap();
+        std::fs::write(project.join(".devcontainer/devcontainer.json"), "{}").unwrap();
+        let plain = dir.path().join("plain");
+        std::fs::create_dir_all(&plain).unwrap();
+
+        let user_dir = dir.path().join("User");
+        std::fs::create_dir_all(user_dir.join("globalStorage")).unwrap();
+        std::fs::write(
+            user_dir.join("settings.json"),
+            r#"{
+                // comments are allowed
+                "dotfiles.repository": "me/dotfiles",
+                "dev.containers.defaultFeatures": {
+                    "ghcr.io/devcontainers/features/github-cli:1": {}
+                },
+                "dev.containers.dockerPath": "/usr/bin/podman"
+            }"#,
+        )
+        .unwrap();
+        let container_uri = format!(
+            "vscode-remote://dev-container+{}/workspaces/app",
+            hex(&project.to_string_lossy())
+        );
+        std::fs::write(
+            user_dir.join("globalStorage/storage.json"),
+            serde_json::json!({
+                "openedPathsList": {"entries": [
+                    {"folderUri": container_uri},
+                    {"folderUri": format!("file://{}", plain.display())},
+                ]},
+                "windowsState": {"lastActiveWindow": {"folder": container_uri}},
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let import = VscodeImport::read(&user_dir).unwrap();
+        assert_eq!(import.recent_paths, vec![project.clone()]);
+        assert_eq!(import.dotfiles_repository.as_deref(), Some("me/dotfiles"));
+        assert_eq!(import.runtime.as_deref(), Some("podman"));
+
+        let mut config = Config {
+            dotfiles_repository: Some("kept/dotfiles".to_string()),
+            ..Config::default()
+        };
+        let changes = import.apply(&mut config);
+        assert_eq!(config.dotfiles_repository.as_deref(), Some("kept/dotfiles"));
+        assert_eq!(config.recent_paths.len(), 1);
+        assert_eq!(config.recent_paths[0].path, project.to_string_lossy());
+        assert!(
+            config
+                .additional_features
+                .contains_key("ghcr.io/devcontainers/features/github-cli:1")
+        );
+        assert_eq!(config.runtime, "podman");
+        assert_eq!(changes.len(), 3);
+
+        // A second import changes nothing
+        assert!(import.apply(&mut config).is_empty());
+    }
+}
@@ -22,10 +22,24 @@
 
 //! # DevCon Library
 //!
-//! This library provides core functionality for the DevCon CLI tool.
-//! Currently serves as a placeholder for potential library exports in the future.
+//! This library provides the functionality of the DevCon CLI tool. Tools
+//! embedding devcon should use the [`api`] module, the other modules are
+//! building blocks of the CLI and may change between releases.
 
+pub mod api;
 pub mod config;
+pub mod config_diff;
+pub mod config_migration;
+pub mod credentials;
 pub mod devcontainer;
+pub mod discovery;
+pub mod download;
+pub mod driver;
+pub mod exit_code;
 pub mod feature;
+pub mod http_proxy;
+pub mod import;
+pub mod project;
+pub mod secrets;
+pub mod validate;
 pub mod workspace;
@@ -20,28 +20,57 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::{ArgValueCompleter, CompleteEnv};
 use std::path::PathBuf;
 use tracing::{Level, trace};
 use tracing_indicatif::IndicatifLayer;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::command::*;
+use crate::config::DotfilesRepository;
+use crate::driver::build_stage::{BuildStage, BuildStages};
 
 mod command;
+mod completion;
 mod config;
+mod config_diff;
+mod config_migration;
+mod credentials;
 mod devcontainer;
+mod discovery;
+mod download;
 mod driver;
+mod exit_code;
 mod feature;
+mod http_proxy;
+mod import;
+mod project;
+mod secrets;
+mod validate;
 mod workspace;
 
+/// Exit codes listed by `devcon --help`, see [`exit_code`]
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0    Success
+  1    Any other error
+  2    Invalid configuration or command line usage
+  3    Container runtime not available
+  4    Building the image failed
+  5    Container not running or image not built
+  6    Network or tunnel error; retrying may help
+  7    Invalid feature definition
+  130  Cancelled with Ctrl+C
+Commands run in the container, e.g. by 'devcon ci', pass their exit code through.";
+
 #[derive(Parser, Debug)]
 #[command(
     name = "devcon",
     author = "kreemer",
     about = "A CLI tool for managing development containers",
     long_about = None,
-    version = env!("CARGO_PKG_VERSION")
+    version = env!("CARGO_PKG_VERSION"),
+    after_long_help = EXIT_CODES_HELP
 )]
 struct Cli {
     /// Turn debugging information on
@@ -76,6 +105,10 @@ enum ConfigAction {
         /// Value to set
         #[arg(help = "Value to set")]
         value: String,
+
+        /// Only show the change
+        #[arg(help = "Only show the change without saving it", long)]
+        dry_run: bool,
     },
 
     /// Unset (remove) a configuration property value
@@ -90,6 +123,56 @@ enum ConfigAction {
     #[command(about = "Validate all configuration values")]
     Validate,
 
+    /// Edit the configuration file
+    #[command(about = "Edit the configuration file in $EDITOR, validating it before saving")]
+    Edit,
+
+    /// Migrate the configuration file to the current version
+    #[command(about = "Migrate the configuration file to the current version, keeping a backup")]
+    Migrate {
+        /// Only show the transformations
+        #[arg(help = "Only show the transformations without changing the file", long)]
+        dry_run: bool,
+    },
+
+    /// Show the merged configuration of a workspace
+    #[command(about = "Show the merged configuration of a workspace with the source of each value")]
+    Effective {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Use only cached features and local base images.
+        #[arg(long, help = "Use only cached features and local base images.")]
+        offline: bool,
+
+        /// Output format
+        #[arg(
+            help = "Output format",
+            long,
+            default_value = "table",
+            value_parser = ["table", "json"]
+        )]
+        format: String,
+    },
+
+    /// Print the built-in Dockerfile template
+    #[command(
+        about = "Print the built-in Dockerfile template as a starting point for dockerfileTemplate"
+    )]
+    DockerfileTemplate,
+
     /// Show the configuration file path
     #[command(about = "Show the configuration file location")]
     Path,
@@ -101,13 +184,837 @@ enum ConfigAction {
         #[arg(help = "Filter properties by substring", long, short)]
         filter: Option<String>,
     },
+
+    /// Manage additional features added to all containers
+    #[command(about = "Manage additional features added to all containers")]
+    Features {
+        #[command(subcommand)]
+        action: FeatureAction,
+    },
+
+    /// Manage the dotfiles repositories applied to new containers
+    #[command(about = "Manage the dotfiles repositories applied to new containers")]
+    Dotfiles {
+        #[command(subcommand)]
+        action: DotfilesAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DotfilesAction {
+    /// Append a dotfiles repository
+    #[command(about = "Append a dotfiles repository, applied after the configured ones")]
+    Add {
+        /// Repository URL
+        #[arg(help = "Repository URL, e.g. https://github.com/me/dotfiles")]
+        repo: String,
+
+        /// Install command
+        #[arg(
+            help = "Install command run in the clone, auto-detected if not given",
+            long,
+            value_name = "COMMAND"
+        )]
+        install_command: Option<String>,
+
+        /// Clone directory
+        #[arg(
+            help = "Directory the repository is cloned into, e.g. ~/.config/nvim",
+            long,
+            value_name = "DIR"
+        )]
+        target_dir: Option<String>,
+
+        /// Branch
+        #[arg(help = "Branch to clone, dotfilesBranch if not given", long)]
+        branch: Option<String>,
+    },
+
+    /// List the dotfiles repositories
+    #[command(about = "List the dotfiles repositories in the order they are applied")]
+    List,
+
+    /// Remove a dotfiles repository
+    #[command(about = "Remove a dotfiles repository")]
+    Remove {
+        /// Repository URL as configured
+        #[arg(help = "Repository URL to remove")]
+        repo: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FeatureAction {
+    /// Add a feature to all containers
+    #[command(about = "Add a feature to all containers")]
+    Add {
+        /// Feature reference (e.g., ghcr.io/devcontainers/features/node:1)
+        #[arg(
+            help = "Feature reference, e.g. ghcr.io/devcontainers/features/node:1",
+            add = ArgValueCompleter::new(completion::complete_feature_reference)
+        )]
+        feature: String,
+
+        /// Feature options, each denoted by KEY=VALUE
+        #[arg(
+            help = "Feature option in the form KEY=VALUE, can be repeated",
+            long = "option",
+            short,
+            value_name = "KEY=VALUE"
+        )]
+        options: Vec<String>,
+    },
+
+    /// List the configured features
+    #[command(about = "List the configured features")]
+    List,
+
+    /// Remove a feature
+    #[command(about = "Remove a configured feature")]
+    Remove {
+        /// Feature reference as configured
+        #[arg(
+            help = "Feature reference to remove",
+            add = ArgValueCompleter::new(completion::complete_configured_feature)
+        )]
+        feature: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SecretAction {
+    /// Store a secret in the system keychain
+    #[command(about = "Store a secret in the system keychain")]
+    Set {
+        /// Name of the secret
+        #[arg(help = "Name of the secret, e.g. github-token", value_name = "NAME")]
+        name: String,
+
+        /// Read the value from stdin
+        #[arg(help = "Read the secret value from stdin instead of prompting", long)]
+        stdin: bool,
+    },
+
+    /// List the stored secrets
+    #[command(about = "List the names of stored secrets")]
+    List,
+
+    /// Remove a secret
+    #[command(about = "Remove a secret from the system keychain")]
+    Rm {
+        /// Name of the secret
+        #[arg(help = "Name of the secret to remove", value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RecentAction {
+    /// List the recent projects
+    #[command(about = "List recent projects with their pin, label and last opened time")]
+    List,
+    /// Pin a recent project
+    #[command(about = "Pin a recent project, so it is never dropped for newer ones")]
+    Pin {
+        /// Number, label or path of the project
+        #[arg(
+            help = "Number in the list, label or path of the project",
+            value_name = "ENTRY"
+        )]
+        entry: String,
+
+        /// Label shown next to the project
+        #[arg(help = "Label shown next to the project, empty to remove it", long)]
+        label: Option<String>,
+    },
+    /// Unpin a recent project
+    #[command(about = "Unpin a recent project")]
+    Unpin {
+        /// Number, label or path of the project
+        #[arg(
+            help = "Number in the list, label or path of the project",
+            value_name = "ENTRY"
+        )]
+        entry: String,
+    },
+    /// Remove a recent project
+    #[command(about = "Remove a project from the recent list")]
+    Rm {
+        /// Number, label or path of the project
+        #[arg(
+            help = "Number in the list, label or path of the project",
+            value_name = "ENTRY"
+        )]
+        entry: String,
+    },
+    /// Remove the unpinned recent projects
+    #[command(about = "Remove the unpinned recent projects")]
+    Clear {
+        /// Remove pinned projects too
+        #[arg(help = "Remove pinned projects too", long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportAction {
+    /// Import from the VS Code Dev Containers extension
+    #[command(
+        about = "Import recent devcontainer projects and dotfiles, default features and Docker path settings from VS Code"
+    )]
+    Vscode {
+        /// VS Code user directory
+        #[arg(
+            help = "VS Code user directory, e.g. ~/.config/Code/User. If not provided, all found ones are used.",
+            long,
+            value_name = "PATH"
+        )]
+        user_dir: Option<PathBuf>,
+
+        /// Only show what would be imported
+        #[arg(help = "Only show what would be imported", long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RuntimeAction {
+    /// List the container runtimes of this host
+    #[command(about = "List container runtimes with their versions and the one auto selects")]
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+enum ServeAction {
+    /// Reload the configuration of a running control server
+    #[command(
+        about = "Reload forwarding policy, port lists and notification settings of a running control server"
+    )]
+    Reload {
+        /// Port of the running control server
+        #[arg(
+            help = "Port the running control server listens on",
+            long,
+            short,
+            default_value = "15000"
+        )]
+        port: u16,
+    },
+    /// Install the control server as a user service started at login
+    #[command(
+        about = "Start the control server at login as a systemd user unit (Linux) or launchd agent (macOS)"
+    )]
+    InstallService {
+        /// Port the control server listens on
+        #[arg(
+            help = "Port to listen on for agent connections",
+            long,
+            short,
+            default_value = "15000"
+        )]
+        port: u16,
+
+        /// Port on localhost serving forward metrics
+        #[arg(
+            help = "Serve the web dashboard and metrics on this localhost port",
+            long
+        )]
+        metrics_port: Option<u16>,
+    },
+    /// Remove the control server service
+    #[command(about = "Stop and remove the control server service")]
+    UninstallService,
+    /// Show the state of the control server service
+    #[command(about = "Show whether the control server service is installed and running")]
+    Status {
+        /// Port of the control server
+        #[arg(
+            help = "Port the control server listens on",
+            long,
+            short,
+            default_value = "15000"
+        )]
+        port: u16,
+    },
+    /// Stop a control server running in the background
+    #[command(about = "Stop a control server started with --detach")]
+    Stop {
+        /// Port of the running control server
+        #[arg(
+            help = "Port the running control server listens on",
+            long,
+            short,
+            default_value = "15000"
+        )]
+        port: u16,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum NetworkAction {
+    /// Link two workspaces
+    #[command(about = "Connect the containers of two workspaces to a shared network")]
+    Link {
+        /// Path to the project directory of the first workspace
+        #[arg(
+            help = "Path to the project directory of the first workspace.",
+            value_name = "PATH"
+        )]
+        first: PathBuf,
+
+        /// Path to the project directory of the second workspace
+        #[arg(
+            help = "Path to the project directory of the second workspace.",
+            value_name = "PATH"
+        )]
+        second: PathBuf,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+    },
 }
 
-#[derive(Subcommand, Debug)]
-enum Commands {
-    /// Builds a development container for the specified path
-    #[command(about = "Create a development container")]
-    Build {
+#[derive(Subcommand, Debug)]
+enum SnapshotAction {
+    /// Take a snapshot of the running container
+    #[command(about = "Commit the running container and its volumes to a snapshot")]
+    Create {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+
+    /// List the snapshots of a workspace
+    #[command(about = "List the snapshots of a workspace, newest first")]
+    List {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+
+    /// Restore a snapshot
+    #[command(about = "Restart the container from a snapshot, discarding later changes")]
+    Restore {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+
+        /// Snapshot to restore
+        #[arg(
+            help = "Snapshot to restore, the newest if not given",
+            long,
+            value_name = "SNAPSHOT"
+        )]
+        snapshot: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Builds a development container for the specified path
+    #[command(about = "Create a development container")]
+    Build {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Path to the build directory.
+        #[arg(short, long, help = "Path to the build directory.")]
+        build_path: Option<PathBuf>,
+
+        /// Use only cached features and local base images.
+        #[arg(
+            long,
+            help = "Work without network access, using only local artifacts."
+        )]
+        offline: bool,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+
+        /// How the build output is shown
+        #[arg(
+            long,
+            help = "How the build output is shown, overriding buildProgress.",
+            value_name = "MODE",
+            value_parser = ["auto", "plain", "tty", "quiet"]
+        )]
+        progress: Option<String>,
+
+        /// Print the Dockerfiles without building
+        #[arg(
+            long,
+            help = "Print the generated Dockerfiles without contacting the runtime."
+        )]
+        dry_run: bool,
+        /// Wait for a concurrent operation on the workspace
+        #[arg(
+            long,
+            help = "Wait for another devcon operation on the workspace to finish instead of failing."
+        )]
+        wait: bool,
+
+        /// Stop the build after a stage
+        #[arg(
+            long,
+            help = "Stop after a stage, building a partial image tagged ':partial'.",
+            value_name = "STAGE",
+            value_parser = ["base", "features", "env", "dotfiles"]
+        )]
+        until: Option<String>,
+
+        /// Skip installing the features
+        #[arg(long, help = "Build without the features stage.")]
+        skip_features: bool,
+
+        /// Skip the environment setup
+        #[arg(long, help = "Build without the env stage.")]
+        skip_env: bool,
+
+        /// Skip the dotfiles setup
+        #[arg(long, help = "Build without the dotfiles stage.")]
+        skip_dotfiles: bool,
+
+        /// Fail on missing feature options instead of asking
+        #[arg(
+            long,
+            help = "Fail if required feature options are missing instead of asking for them."
+        )]
+        no_interaction: bool,
+    },
+
+    /// Starts a development container for the specified path
+    #[command(about = "Create a development container")]
+    Start {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Use only cached features and local base images.
+        #[arg(
+            long,
+            help = "Work without network access, using only local artifacts."
+        )]
+        offline: bool,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+        /// Wait for a concurrent operation on the workspace
+        #[arg(
+            long,
+            help = "Wait for another devcon operation on the workspace to finish instead of failing."
+        )]
+        wait: bool,
+    },
+    /// Builds and starts a development container for the specified path
+    #[command(about = "Build and start a development container (combines build + start)")]
+    Up {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Path to the build directory.
+        #[arg(short, long, help = "Path to the build directory.")]
+        build_path: Option<PathBuf>,
+
+        /// Use only cached features and local base images.
+        #[arg(
+            long,
+            help = "Work without network access, using only local artifacts."
+        )]
+        offline: bool,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+
+        /// How the build output is shown
+        #[arg(
+            long,
+            help = "How the build output is shown, overriding buildProgress.",
+            value_name = "MODE",
+            value_parser = ["auto", "plain", "tty", "quiet"]
+        )]
+        progress: Option<String>,
+
+        /// Print the build and run plan without executing it
+        #[arg(
+            long,
+            visible_alias = "dry-run",
+            help = "Print the Dockerfiles and the planned container run without contacting the runtime."
+        )]
+        plan: bool,
+        /// Wait for a concurrent operation on the workspace
+        #[arg(
+            long,
+            help = "Wait for another devcon operation on the workspace to finish instead of failing."
+        )]
+        wait: bool,
+
+        /// Format of the timing report printed at the end
+        #[arg(
+            help = "Format of the report of build and lifecycle timings printed at the end",
+            long,
+            default_value = "text",
+            value_parser = ["text", "json"]
+        )]
+        format: String,
+
+        /// Fail on missing feature options instead of asking
+        #[arg(
+            long,
+            help = "Fail if required feature options are missing instead of asking for them."
+        )]
+        no_interaction: bool,
+    },
+    /// Execs a shell in a development container for the specified path
+    #[command(about = "Exec a shell in a development container with the devcontainer CLI")]
+    Shell {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Environment variables which will be processed. Each should be denoted by KEY=VALUE
+        #[arg(
+            help = "Environment variables which will be processed. Each should be denoted by KEY=VALUE.",
+            value_name = "PATH"
+        )]
+        env: Vec<String>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Runs the lifecycle hooks `devcon start` left to the background
+    #[command(hide = true)]
+    RunLifecycle {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(long, value_name = "PATH")]
+        path: PathBuf,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(long, value_name = "NAME")]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+    },
+    /// Shows the status of the specified path
+    #[command(
+        about = "Show the image, container, lifecycle hook, mount, agent and port status of a workspace"
+    )]
+    Status {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Output format
+        #[arg(
+            help = "Output format",
+            long,
+            default_value = "table",
+            value_parser = ["table", "json"]
+        )]
+        format: String,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Prints the details of the workspace of the specified path as JSON
+    #[command(
+        about = "Print the resolved configuration, features, image labels, container and agent details of a workspace as JSON"
+    )]
+    Inspect {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(long, value_name = "NAME")]
+        config: Option<String>,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Stops the development container of the specified path
+    #[command(about = "Stop a running development container")]
+    Stop {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Pauses the development container of the specified path
+    #[command(about = "Pause a running development container, keeping its state and forwards")]
+    Pause {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Resumes the paused development container of the specified path
+    #[command(about = "Resume a paused development container")]
+    Resume {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Snapshots of development containers
+    #[command(about = "Create, list and restore snapshots of a development container")]
+    Snapshot {
+        #[command(subcommand)]
+        action: SnapshotAction,
+    },
+    /// Networks between development containers
+    #[command(about = "Link the development containers of workspaces")]
+    Network {
+        #[command(subcommand)]
+        action: NetworkAction,
+    },
+    /// Removes stale devcon artifacts
+    #[command(about = "Remove all but the newest snapshots of every workspace")]
+    Gc {
+        /// Number of snapshots kept per workspace
+        #[arg(
+            help = "Number of snapshots kept per workspace",
+            long,
+            default_value = "3"
+        )]
+        keep: usize,
+    },
+    /// Runs a command in a throwaway development container
+    #[command(
+        about = "Build, start a throwaway container without the agent, run a command and remove it again, for CI pipelines"
+    )]
+    Ci {
         /// Path to the project directory containing .devcontainer configuration
         #[arg(
             help = "Path to the project directory. If not provided, uses current directory.",
@@ -115,24 +1022,89 @@ enum Commands {
         )]
         path: Option<PathBuf>,
 
-        /// Path to the build directory.
-        #[arg(short, long, help = "Path to the build directory.")]
-        build_path: Option<PathBuf>,
-    },
+        /// Prebuilt image to pull instead of building
+        #[arg(
+            help = "Prebuilt image of the devcontainer to pull instead of building one",
+            long,
+            value_name = "IMAGE"
+        )]
+        image: Option<String>,
 
-    /// Starts a development container for the specified path
-    #[command(about = "Create a development container")]
-    Start {
+        /// Use only cached features and local base images.
+        #[arg(
+            long,
+            help = "Work without network access, using only local artifacts."
+        )]
+        offline: bool,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// How the build output is shown
+        #[arg(
+            long,
+            help = "How the build output is shown, plain by default.",
+            value_name = "MODE",
+            value_parser = ["auto", "plain", "tty", "quiet"]
+        )]
+        progress: Option<String>,
+
+        /// Command to run
+        #[arg(
+            help = "Command run in the workspace folder, followed by its arguments",
+            last = true,
+            required = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+    /// Exports a built development container for offline use
+    #[command(
+        about = "Bundle the built image, feature lockfile and configuration into an archive for 'devcon import'"
+    )]
+    Export {
         /// Path to the project directory containing .devcontainer configuration
         #[arg(
             help = "Path to the project directory. If not provided, uses current directory.",
             value_name = "PATH"
         )]
         path: Option<PathBuf>,
+
+        /// Path of the archive
+        #[arg(
+            help = "Path of the archive to write",
+            long,
+            short,
+            value_name = "FILE"
+        )]
+        output: PathBuf,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
     },
-    /// Builds and starts a development container for the specified path
-    #[command(about = "Build and start a development container (combines build + start)")]
-    Up {
+    /// Prints a software bill of materials of a development container
+    #[command(
+        about = "Print an SBOM of the base image, features and dotfiles of a devcontainer image"
+    )]
+    Sbom {
         /// Path to the project directory containing .devcontainer configuration
         #[arg(
             help = "Path to the project directory. If not provided, uses current directory.",
@@ -140,13 +1112,45 @@ enum Commands {
         )]
         path: Option<PathBuf>,
 
-        /// Path to the build directory.
-        #[arg(short, long, help = "Path to the build directory.")]
-        build_path: Option<PathBuf>,
+        /// Document format
+        #[arg(
+            help = "Document format",
+            long,
+            default_value = "cyclonedx",
+            value_parser = ["cyclonedx", "spdx"]
+        )]
+        format: String,
+
+        /// File to write the document to
+        #[arg(
+            help = "File to write the document to instead of stdout",
+            long,
+            short,
+            value_name = "FILE"
+        )]
+        output: Option<PathBuf>,
+
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
+        #[arg(
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
+        )]
+        config: Option<String>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
     },
-    /// Execs a shell in a development container for the specified path
-    #[command(about = "Exec a shell in a development container with the devcontainer CLI")]
-    Shell {
+    /// Validates the devcontainer.json of a project
+    #[command(
+        about = "Validate devcontainer.json against the devcontainer schema and resolve its features"
+    )]
+    Validate {
         /// Path to the project directory containing .devcontainer configuration
         #[arg(
             help = "Path to the project directory. If not provided, uses current directory.",
@@ -154,12 +1158,27 @@ enum Commands {
         )]
         path: Option<PathBuf>,
 
-        /// Environment variables which will be processed. Each should be denoted by KEY=VALUE
+        /// Named configuration in .devcontainer/<name>/devcontainer.json
         #[arg(
-            help = "Environment variables which will be processed. Each should be denoted by KEY=VALUE.",
-            value_name = "PATH"
+            long,
+            help = "Name of the configuration in .devcontainer/<name>/ to use.",
+            value_name = "NAME"
         )]
-        env: Vec<String>,
+        config: Option<String>,
+    },
+    /// Lists running development containers
+    #[command(about = "List running development containers")]
+    List {
+        /// Include containers started by other users
+        #[arg(help = "Include containers started by other users", long)]
+        all_users: bool,
+
+        /// Show resource usage
+        #[arg(
+            help = "Show the CPU, memory and network usage of the containers",
+            long
+        )]
+        stats: bool,
     },
     /// Prints the config file location path
     #[command(about = "Manage DevCon configuration")]
@@ -170,6 +1189,9 @@ enum Commands {
     /// Starts the control server for agent connections
     #[command(about = "Start the control server for managing agent connections")]
     Serve {
+        #[command(subcommand)]
+        action: Option<ServeAction>,
+
         /// Port to listen on
         #[arg(
             help = "Port to listen on for agent connections",
@@ -178,10 +1200,233 @@ enum Commands {
             default_value = "15000"
         )]
         port: u16,
+
+        /// Seconds between heartbeat pings to agents
+        #[arg(
+            help = "Seconds between heartbeat pings to agents (0 disables heartbeats)",
+            long,
+            default_value = "10"
+        )]
+        heartbeat_interval: u64,
+
+        /// Seconds without agent messages before a connection is dropped
+        #[arg(
+            help = "Seconds without agent messages before the connection is considered dead",
+            long,
+            default_value = "30"
+        )]
+        heartbeat_timeout: u64,
+
+        /// Port on localhost serving forward metrics
+        #[arg(
+            help = "Serve the web dashboard, forward metrics (/metrics) and JSON API (/api/*) on this localhost port",
+            long
+        )]
+        metrics_port: Option<u16>,
+
+        /// Run the control server in the background
+        #[arg(
+            help = "Run in the background, with the output in serve-<port>.log of the state directory",
+            long
+        )]
+        detach: bool,
+    },
+    /// Lists ports forwarded by the control server
+    #[command(about = "List ports forwarded by the control server")]
+    Ports {
+        /// Show transfer statistics
+        #[arg(help = "Show transfer statistics for each forwarded port", long)]
+        stats: bool,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+    },
+    /// Prints control server events of a workspace
+    #[command(about = "Print forwarding and agent events of a development container")]
+    Events {
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Path to the project directory, alternative to PATH
+        #[arg(
+            help = "Path to the project directory, same as PATH",
+            long,
+            value_name = "PATH",
+            conflicts_with = "path"
+        )]
+        workspace: Option<PathBuf>,
+
+        /// Keep printing new events
+        #[arg(
+            help = "Keep printing new events, including container and lifecycle events, until interrupted",
+            long,
+            short
+        )]
+        follow: bool,
+
+        /// Output format
+        #[arg(
+            help = "Output format, json prints one event with the workspace status per line",
+            long,
+            visible_alias = "output",
+            default_value = "text",
+            value_parser = ["text", "json"]
+        )]
+        format: String,
+
+        /// Metrics port of the control server
+        #[arg(
+            help = "Metrics port of the running control server",
+            long,
+            default_value = "15001"
+        )]
+        metrics_port: u16,
+    },
+    /// Runs a command alias of the project in the container
+    #[command(
+        about = "Run a command alias from the project's .devcon.yaml in the container",
+        visible_alias = "x"
+    )]
+    RunAlias {
+        /// Name of the alias
+        #[arg(
+            help = "Name of the alias defined in .devcon.yaml",
+            value_name = "ALIAS"
+        )]
+        alias: String,
+
+        /// Additional arguments appended to the command
+        #[arg(
+            help = "Additional arguments appended to the alias command",
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS"
+        )]
+        args: Vec<String>,
+
+        /// Path to the project directory containing .devcontainer configuration
+        #[arg(
+            help = "Path to the project directory. If not provided, uses current directory.",
+            long,
+            short,
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+    },
+    /// Manages secrets injected into containers
+    #[command(about = "Manage secrets injected into containers")]
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+    /// Lists the projects found below the configured project roots
+    #[command(about = "Find devcontainer projects below the configured project roots")]
+    Projects {
+        /// Fuzzy filter on the project path
+        #[arg(
+            help = "Fuzzy filter on the project path, best matches first",
+            value_name = "QUERY"
+        )]
+        query: Option<String>,
+
+        /// Directory levels scanned below each root
+        #[arg(
+            help = "Number of directory levels scanned below each project root",
+            long,
+            value_name = "DEPTH",
+            default_value_t = discovery::DEFAULT_DISCOVERY_DEPTH
+        )]
+        depth: usize,
+    },
+    /// Manages the recent projects
+    #[command(about = "List, pin, label and remove recent projects")]
+    Recent {
+        #[command(subcommand)]
+        action: RecentAction,
+    },
+    /// Imports exported environments, projects and settings of other tools
+    #[command(
+        about = "Import an environment written by 'devcon export', or projects and settings from other devcontainer tools",
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true
+    )]
+    Import {
+        #[command(subcommand)]
+        action: Option<ImportAction>,
+
+        /// Environment archive to import
+        #[arg(
+            help = "Environment archive written by 'devcon export'",
+            required = true,
+            value_name = "ARCHIVE"
+        )]
+        archive: Option<PathBuf>,
+
+        /// Project directory to import the environment into
+        #[arg(
+            help = "Project directory to import into. If not provided, uses current directory.",
+            long,
+            value_name = "PATH"
+        )]
+        path: Option<PathBuf>,
+
+        /// Named container instance of the workspace
+        #[arg(
+            long,
+            help = "Name of a further container instance of the workspace, e.g. a branch.",
+            value_name = "NAME"
+        )]
+        name: Option<String>,
+    },
+    /// Inspects the available container runtimes
+    #[command(about = "Inspect the available container runtimes")]
+    Runtime {
+        #[command(subcommand)]
+        action: RuntimeAction,
+    },
+    /// Stores registry credentials in the system keychain
+    #[command(about = "Store credentials for a registry in the system keychain")]
+    Login {
+        /// Registry host
+        #[arg(help = "Registry host, e.g. ghcr.io", value_name = "REGISTRY")]
+        registry: String,
+
+        /// Username for the registry
+        #[arg(help = "Username, prompted for if not provided", long, short)]
+        username: Option<String>,
+
+        /// Read the secret from stdin
+        #[arg(help = "Read the password or token from stdin", long)]
+        password_stdin: bool,
     },
+    /// Removes registry credentials from the system keychain
+    #[command(about = "Remove credentials for a registry from the system keychain")]
+    Logout {
+        /// Registry host
+        #[arg(help = "Registry host, e.g. ghcr.io", value_name = "REGISTRY")]
+        registry: String,
+    },
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("Error: {:?}", error);
+        std::process::exit(exit_code::exit_code(&error));
+    }
 }
 
-fn main() -> anyhow::Result<()> {
+fn run() -> anyhow::Result<()> {
+    CompleteEnv::with_factory(Cli::command).complete();
+
     let indicatif_layer = IndicatifLayer::new();
     let cli = Cli::parse();
     let level = match cli.debug {
@@ -212,27 +1457,253 @@ fn main() -> anyhow::Result<()> {
     trace!("Starting devcon with CLI args: {:?}", cli);
 
     match &cli.command {
-        Commands::Build { path, build_path } => {
+        Commands::Build {
+            path,
+            build_path,
+            offline,
+            config,
+            name,
+            progress,
+            dry_run,
+            wait,
+            until,
+            skip_features,
+            skip_env,
+            skip_dotfiles,
+            no_interaction,
+        } => {
+            let skip = [
+                (*skip_features, BuildStage::Features),
+                (*skip_env, BuildStage::Env),
+                (*skip_dotfiles, BuildStage::Dotfiles),
+            ]
+            .into_iter()
+            .filter_map(|(skipped, stage)| skipped.then_some(stage))
+            .collect();
+            let until = until.as_deref().map(str::parse).transpose()?;
             handle_build_command(
                 path.clone().unwrap_or(PathBuf::from(".").to_path_buf()),
                 build_path.clone(),
+                *offline,
+                config.as_deref(),
+                name.as_deref(),
+                progress.as_deref(),
+                *dry_run,
+                *wait,
+                BuildStages::new(until, skip)?,
+                *no_interaction,
             )?;
         }
-        Commands::Start { path } => {
-            handle_start_command(path.clone().unwrap_or(PathBuf::from(".").to_path_buf()))?;
+        Commands::Start {
+            path,
+            offline,
+            config,
+            name,
+            wait,
+        } => {
+            handle_start_command(
+                path.clone().unwrap_or(PathBuf::from(".").to_path_buf()),
+                *offline,
+                config.as_deref(),
+                name.as_deref(),
+                *wait,
+            )?;
         }
-        Commands::Up { path, build_path } => {
+        Commands::Up {
+            path,
+            build_path,
+            offline,
+            config,
+            name,
+            progress,
+            plan,
+            wait,
+            format,
+            no_interaction,
+        } => {
             handle_up_command(
                 path.clone().unwrap_or(PathBuf::from(".").to_path_buf()),
                 build_path.clone(),
+                *offline,
+                config.as_deref(),
+                name.as_deref(),
+                progress.as_deref(),
+                *plan,
+                *wait,
+                format,
+                *no_interaction,
             )?;
         }
-        Commands::Shell { path, env } => {
+        Commands::Shell {
+            path,
+            env,
+            config,
+            name,
+        } => {
             handle_shell_command(
                 path.clone().unwrap_or(PathBuf::from(".").to_path_buf()),
                 env,
+                config.as_deref(),
+                name.as_deref(),
+            )?;
+        }
+        Commands::RunLifecycle { path, config, name } => {
+            handle_run_lifecycle_command(path.clone(), config.as_deref(), name.as_deref())?;
+        }
+        Commands::Status {
+            path,
+            format,
+            metrics_port,
+            name,
+        } => {
+            handle_status_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                format,
+                *metrics_port,
+                name.as_deref(),
+            )?;
+        }
+        Commands::Inspect {
+            path,
+            config,
+            metrics_port,
+            name,
+        } => {
+            handle_inspect_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                config.as_deref(),
+                *metrics_port,
+                name.as_deref(),
+            )?;
+        }
+        Commands::Stop { path, config, name } => {
+            handle_stop_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                config.as_deref(),
+                name.as_deref(),
+            )?;
+        }
+        Commands::Pause {
+            path,
+            config,
+            metrics_port,
+            name,
+        } => {
+            handle_pause_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                config.as_deref(),
+                *metrics_port,
+                name.as_deref(),
+            )?;
+        }
+        Commands::Resume {
+            path,
+            config,
+            metrics_port,
+            name,
+        } => {
+            handle_resume_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                config.as_deref(),
+                *metrics_port,
+                name.as_deref(),
+            )?;
+        }
+        Commands::Snapshot { action } => match action {
+            SnapshotAction::Create { path, config, name } => {
+                handle_snapshot_create_command(
+                    path.clone().unwrap_or(PathBuf::from(".")),
+                    config.as_deref(),
+                    name.as_deref(),
+                )?;
+            }
+            SnapshotAction::List { path, config, name } => {
+                handle_snapshot_list_command(
+                    path.clone().unwrap_or(PathBuf::from(".")),
+                    config.as_deref(),
+                    name.as_deref(),
+                )?;
+            }
+            SnapshotAction::Restore {
+                path,
+                config,
+                name,
+                snapshot,
+            } => {
+                handle_snapshot_restore_command(
+                    path.clone().unwrap_or(PathBuf::from(".")),
+                    config.as_deref(),
+                    name.as_deref(),
+                    snapshot.as_deref(),
+                )?;
+            }
+        },
+        Commands::Network { action } => match action {
+            NetworkAction::Link {
+                first,
+                second,
+                config,
+            } => {
+                handle_network_link_command(first.clone(), second.clone(), config.as_deref())?;
+            }
+        },
+        Commands::Gc { keep } => {
+            handle_gc_command(*keep)?;
+        }
+        Commands::Ci {
+            path,
+            image,
+            offline,
+            config,
+            progress,
+            command,
+        } => {
+            handle_ci_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                image.as_deref(),
+                *offline,
+                config.as_deref(),
+                progress.as_deref(),
+                command,
+            )?;
+        }
+        Commands::Export {
+            path,
+            output,
+            config,
+            name,
+        } => {
+            handle_export_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                output,
+                config.as_deref(),
+                name.as_deref(),
+            )?;
+        }
+        Commands::Sbom {
+            path,
+            format,
+            output,
+            config,
+            name,
+        } => {
+            handle_sbom_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                format,
+                output.as_deref(),
+                config.as_deref(),
+                name.as_deref(),
+            )?;
+        }
+        Commands::Validate { path, config } => {
+            handle_validate_command(
+                path.clone().unwrap_or(PathBuf::from(".")),
+                config.as_deref(),
             )?;
         }
+        Commands::List { all_users, stats } => {
+            handle_list_command(*all_users, *stats)?;
+        }
         Commands::Config { action } => match action {
             ConfigAction::Show => {
                 handle_config_show()?;
@@ -240,8 +1711,12 @@ fn main() -> anyhow::Result<()> {
             ConfigAction::Get { property } => {
                 handle_config_get(property)?;
             }
-            ConfigAction::Set { property, value } => {
-                handle_config_set(property, value)?;
+            ConfigAction::Set {
+                property,
+                value,
+                dry_run,
+            } => {
+                handle_config_set(property, value, *dry_run)?;
             }
             ConfigAction::Unset { property } => {
                 handle_config_unset(property)?;
@@ -249,15 +1724,219 @@ fn main() -> anyhow::Result<()> {
             ConfigAction::Validate => {
                 handle_config_validate()?;
             }
+            ConfigAction::Edit => {
+                handle_config_edit()?;
+            }
+            ConfigAction::Migrate { dry_run } => {
+                handle_config_migrate(*dry_run)?;
+            }
+            ConfigAction::Effective {
+                path,
+                config,
+                offline,
+                format,
+            } => {
+                handle_config_effective(
+                    path.clone().unwrap_or(PathBuf::from(".")),
+                    config.as_deref(),
+                    *offline,
+                    format,
+                )?;
+            }
+            ConfigAction::DockerfileTemplate => {
+                handle_config_dockerfile_template();
+            }
             ConfigAction::Path => {
                 handle_config_path()?;
             }
             ConfigAction::List { filter } => {
                 handle_config_list(filter.as_deref())?;
             }
+            ConfigAction::Features { action } => match action {
+                FeatureAction::Add { feature, options } => {
+                    handle_config_features_add(feature, options)?;
+                }
+                FeatureAction::List => {
+                    handle_config_features_list()?;
+                }
+                FeatureAction::Remove { feature } => {
+                    handle_config_features_remove(feature)?;
+                }
+            },
+            ConfigAction::Dotfiles { action } => match action {
+                DotfilesAction::Add {
+                    repo,
+                    install_command,
+                    target_dir,
+                    branch,
+                } => {
+                    handle_config_dotfiles_add(DotfilesRepository {
+                        repo: repo.clone(),
+                        install_command: install_command.clone(),
+                        target_dir: target_dir.clone(),
+                        branch: branch.clone(),
+                    })?;
+                }
+                DotfilesAction::List => {
+                    handle_config_dotfiles_list()?;
+                }
+                DotfilesAction::Remove { repo } => {
+                    handle_config_dotfiles_remove(repo)?;
+                }
+            },
         },
-        Commands::Serve { port } => {
-            handle_serve_command(*port)?;
+        Commands::Serve {
+            action: Some(ServeAction::Reload { port }),
+            ..
+        } => {
+            handle_serve_reload_command(*port)?;
+        }
+        Commands::Serve {
+            action: Some(ServeAction::Stop { port }),
+            ..
+        } => {
+            handle_serve_stop_command(*port)?;
+        }
+        Commands::Serve {
+            action: Some(ServeAction::InstallService { port, metrics_port }),
+            ..
+        } => {
+            handle_serve_install_service_command(*port, *metrics_port)?;
+        }
+        Commands::Serve {
+            action: Some(ServeAction::UninstallService),
+            ..
+        } => {
+            handle_serve_uninstall_service_command()?;
+        }
+        Commands::Serve {
+            action: Some(ServeAction::Status { port }),
+            ..
+        } => {
+            handle_serve_status_command(*port)?;
+        }
+        Commands::Serve {
+            action: None,
+            port,
+            heartbeat_interval,
+            heartbeat_timeout,
+            metrics_port,
+            detach: true,
+        } => {
+            handle_serve_detach_command(
+                *port,
+                *heartbeat_interval,
+                *heartbeat_timeout,
+                *metrics_port,
+            )?;
+        }
+        Commands::Serve {
+            action: None,
+            port,
+            heartbeat_interval,
+            heartbeat_timeout,
+            metrics_port,
+            detach: false,
+        } => {
+            handle_serve_command(
+                *port,
+                *heartbeat_interval,
+                *heartbeat_timeout,
+                *metrics_port,
+            )?;
+        }
+        Commands::Ports {
+            stats,
+            metrics_port,
+        } => {
+            handle_ports_command(*stats, *metrics_port)?;
+        }
+        Commands::Events {
+            path,
+            workspace,
+            follow,
+            format,
+            metrics_port,
+        } => {
+            handle_events_command(
+                path.clone()
+                    .or(workspace.clone())
+                    .unwrap_or(PathBuf::from(".").to_path_buf()),
+                *follow,
+                format,
+                *metrics_port,
+            )?;
+        }
+        Commands::RunAlias { alias, args, path } => {
+            handle_run_alias_command(
+                path.clone().unwrap_or(PathBuf::from(".").to_path_buf()),
+                alias,
+                args,
+            )?;
+        }
+        Commands::Secret { action } => match action {
+            SecretAction::Set { name, stdin } => {
+                handle_secret_set_command(name, *stdin)?;
+            }
+            SecretAction::List => {
+                handle_secret_list_command()?;
+            }
+            SecretAction::Rm { name } => {
+                handle_secret_rm_command(name)?;
+            }
+        },
+        Commands::Projects { query, depth } => {
+            handle_projects_command(query.as_deref().unwrap_or_default(), *depth)?;
+        }
+        Commands::Recent { action } => match action {
+            RecentAction::List => {
+                handle_recent_list_command()?;
+            }
+            RecentAction::Pin { entry, label } => {
+                handle_recent_pin_command(entry, true, label.as_deref())?;
+            }
+            RecentAction::Unpin { entry } => {
+                handle_recent_pin_command(entry, false, None)?;
+            }
+            RecentAction::Rm { entry } => {
+                handle_recent_rm_command(entry)?;
+            }
+            RecentAction::Clear { all } => {
+                handle_recent_clear_command(*all)?;
+            }
+        },
+        Commands::Import {
+            action,
+            archive,
+            path,
+            name,
+        } => match (action, archive) {
+            (Some(ImportAction::Vscode { user_dir, dry_run }), _) => {
+                handle_import_vscode_command(user_dir.clone(), *dry_run)?;
+            }
+            (None, Some(archive)) => {
+                handle_import_environment_command(
+                    archive,
+                    path.clone().unwrap_or(PathBuf::from(".")),
+                    name.as_deref(),
+                )?;
+            }
+            (None, None) => unreachable!("clap requires an archive without a subcommand"),
+        },
+        Commands::Runtime { action } => match action {
+            RuntimeAction::List => {
+                handle_runtime_list_command()?;
+            }
+        },
+        Commands::Login {
+            registry,
+            username,
+            password_stdin,
+        } => {
+            handle_login_command(registry, username.as_deref(), *password_stdin)?;
+        }
+        Commands::Logout { registry } => {
+            handle_logout_command(registry)?;
         }
     }
 
@@ -0,0 +1,163 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Project Configuration
+//!
+//! Per-project settings live in `.devcon.yaml` in the project root, next to
+//! the `.devcontainer` directory, and are meant to be committed with the
+//! project. Unlike the user config they only apply to this one project.
+//!
+//! ```yaml
+//! aliases:
+//!   test: cargo nextest run
+//!   lint: cargo clippy --all-targets -- -D warnings
+//! ```
+//!
+//! Aliases are run inside the container with `devcon run-alias <name>` (or
+//! `devcon x <name>`), additional arguments are appended to the command.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// File name of the project configuration.
+pub const PROJECT_CONFIG_FILE: &str = ".devcon.yaml";
+
+/// Settings of a single project from `.devcon.yaml`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectConfig {
+    /// Commands by alias name, run in the container's workspace folder.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl ProjectConfig {
+    /// Loads `.devcon.yaml` from the project directory.
+    ///
+    /// A missing file results in an empty configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or an alias
+    /// name or command is invalid.
+    pub fn load(project_path: &Path) -> Result<Self> {
+        let path = project_path.join(PROJECT_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: ProjectConfig = yaml_serde::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Returns the command of an alias.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the available aliases if `name` is not defined.
+    pub fn alias(&self, name: &str) -> Result<&str> {
+        if let Some(command) = self.aliases.get(name) {
+            return Ok(command);
+        }
+
+        if self.aliases.is_empty() {
+            bail!(
+                "Alias '{}' not found, no aliases are defined in {}",
+                name,
+                PROJECT_CONFIG_FILE
+            );
+        }
+        bail!(
+            "Alias '{}' not found in {}. Available aliases: {}",
+            name,
+            PROJECT_CONFIG_FILE,
+            self.aliases.keys().cloned().collect::<Vec<_>>().join(", ")
+        )
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, command) in &self.aliases {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+            {
+                bail!(
+                    "Invalid alias name '{}': only letters, digits, '-', '_', '.' and ':' are allowed",
+                    name
+                );
+            }
+            if command.trim().is_empty() {
+                bail!("Alias '{}' has an empty command", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = ProjectConfig::load(temp_dir.path()).unwrap();
+        assert!(config.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_load_aliases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join(PROJECT_CONFIG_FILE),
+            "aliases:\n  test: cargo nextest run\n  lint: cargo clippy\n",
+        )
+        .unwrap();
+
+        let config = ProjectConfig::load(temp_dir.path()).unwrap();
+        assert_eq!(config.alias("test").unwrap(), "cargo nextest run");
+        let error = config.alias("build").unwrap_err().to_string();
+        assert!(error.contains("lint, test"), "{}", error);
+    }
+
+    #[test]
+    fn test_invalid_alias() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(PROJECT_CONFIG_FILE);
+
+        fs::write(&path, "aliases:\n  'run tests': cargo test\n").unwrap();
+        assert!(ProjectConfig::load(temp_dir.path()).is_err());
+
+        fs::write(&path, "aliases:\n  test: ''\n").unwrap();
+        assert!(ProjectConfig::load(temp_dir.path()).is_err());
+    }
+}
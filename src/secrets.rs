@@ -0,0 +1,236 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Secrets
+//!
+//! Secrets like API tokens are kept in the OS keychain, as a single entry of
+//! the `devcon-secrets` service holding all secrets as JSON. They are managed
+//! with `devcon secret set|list|rm` and never written to `config.yaml` or
+//! baked into images.
+//!
+//! Projects reference secrets in `devcontainer.json`, and `devcon start`
+//! injects them as environment variables or files:
+//!
+//! ```json
+//! "customizations": {
+//!   "devcon": {
+//!     "secrets": [
+//!       { "name": "github-token", "env": "GITHUB_TOKEN" },
+//!       { "name": "npm-token", "file": "/run/secrets/npm-token" }
+//!     ]
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// Keychain service of the secrets entry.
+const SERVICE: &str = "devcon-secrets";
+
+/// Keychain account of the secrets entry.
+const ACCOUNT: &str = "secrets";
+
+/// Reference of a project to a secret, from `customizations.devcon.secrets`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SecretRef {
+    /// Name of the stored secret
+    pub name: String,
+    /// Environment variable the secret is set as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Absolute container path the secret is written to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl SecretRef {
+    /// Validates that the reference has a valid name and at least one target.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the invalid field.
+    pub fn validate(&self) -> Result<()> {
+        validate_name(&self.name)?;
+        if self.env.is_none() && self.file.is_none() {
+            bail!("Secret '{}' needs an 'env' or 'file' target", self.name);
+        }
+        if let Some(env) = &self.env
+            && (env.is_empty()
+                || env.starts_with(|c: char| c.is_ascii_digit())
+                || !env.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            bail!(
+                "Invalid environment variable '{}' for secret '{}'",
+                env,
+                self.name
+            );
+        }
+        if let Some(file) = &self.file
+            && !file.starts_with('/')
+        {
+            bail!(
+                "File '{}' of secret '{}' must be an absolute path",
+                file,
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Validates a secret name, e.g. `github-token`.
+///
+/// # Errors
+///
+/// Returns an error if the name is empty or contains characters other than
+/// letters, digits, `-`, `_` and `.`.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        bail!(
+            "Invalid secret name '{}': only letters, digits, '-', '_' and '.' are allowed",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Stores a secret, replacing a previous value.
+///
+/// # Errors
+///
+/// Returns an error if the name is invalid or the keychain is unavailable.
+pub fn set(name: &str, value: &str) -> Result<()> {
+    validate_name(name)?;
+    let mut secrets = load_all()?;
+    secrets.insert(name.to_string(), value.to_string());
+    store_all(&secrets)
+}
+
+/// Returns the value of a secret, `None` if it is not set.
+///
+/// # Errors
+///
+/// Returns an error if the keychain is unavailable.
+pub fn get(name: &str) -> Result<Option<String>> {
+    Ok(load_all()?.remove(name))
+}
+
+/// Returns the names of all stored secrets in alphabetical order.
+///
+/// # Errors
+///
+/// Returns an error if the keychain is unavailable.
+pub fn list() -> Result<Vec<String>> {
+    Ok(load_all()?.into_keys().collect())
+}
+
+/// Removes a secret. Returns whether it was set.
+///
+/// # Errors
+///
+/// Returns an error if the keychain is unavailable.
+pub fn remove(name: &str) -> Result<bool> {
+    let mut secrets = load_all()?;
+    if secrets.remove(name).is_none() {
+        return Ok(false);
+    }
+    store_all(&secrets)?;
+    Ok(true)
+}
+
+fn load_all() -> Result<BTreeMap<String, String>> {
+    match entry()?.get_password() {
+        Ok(value) => {
+            serde_json::from_str(&value).context("Invalid secrets entry in the system keychain")
+        }
+        Err(keyring::Error::NoEntry) => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context("Failed to read secrets from the system keychain"),
+    }
+}
+
+fn store_all(secrets: &BTreeMap<String, String>) -> Result<()> {
+    let entry = entry()?;
+    if secrets.is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e).context("Failed to remove secrets from the system keychain"),
+        };
+    }
+
+    entry
+        .set_password(&serde_json::to_string(secrets)?)
+        .context("Failed to store secrets in the system keychain")
+}
+
+fn entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, ACCOUNT).context("Failed to access the system keychain")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("github-token").is_ok());
+        assert!(validate_name("NPM_TOKEN.v2").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("my secret").is_err());
+        assert!(validate_name("../token").is_err());
+    }
+
+    #[test]
+    fn test_secret_ref_validation() {
+        let parse = |json: &str| serde_json::from_str::<SecretRef>(json).unwrap();
+
+        assert!(
+            parse(r#"{"name": "token", "env": "GITHUB_TOKEN"}"#)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            parse(r#"{"name": "token", "file": "/run/secrets/token"}"#)
+                .validate()
+                .is_ok()
+        );
+        assert!(parse(r#"{"name": "token"}"#).validate().is_err());
+        assert!(
+            parse(r#"{"name": "token", "env": "1TOKEN"}"#)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            parse(r#"{"name": "token", "file": "secrets/token"}"#)
+                .validate()
+                .is_err()
+        );
+        assert!(serde_json::from_str::<SecretRef>(r#"{"name": "token", "path": "/x"}"#).is_err());
+    }
+}
@@ -0,0 +1,631 @@
+// MIT License
+//
+// Copyright (c) 2025 DevCon Contributors
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! # Devcontainer Validation
+//!
+//! This module checks a `devcontainer.json` against the devcontainer schema
+//! and reports problems with their line and column, as used by
+//! `devcon validate`.
+//!
+//! JSONC comments and trailing commas are blanked out before parsing, which
+//! keeps every position of the original file intact. The schema covers the
+//! properties of the devcontainer specification with their allowed JSON
+//! types and values; unknown properties are reported as warnings.
+
+use std::fmt;
+
+use crate::devcontainer::Devcontainer;
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a devcontainer file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Line of the problem, starting at 1
+    pub line: usize,
+    /// Column of the problem in characters, starting at 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates an error at byte `offset` of `content`.
+    pub fn error(content: &str, offset: usize, message: impl Into<String>) -> Self {
+        let (line, column) = position(content, offset);
+        Self {
+            severity: Severity::Error,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Creates a warning at byte `offset` of `content`.
+    pub fn warning(content: &str, offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(content, offset, message)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+// JSON types allowed by a schema, combined with `|`
+const STRING: u8 = 1;
+const BOOLEAN: u8 = 2;
+const NUMBER: u8 = 4;
+const OBJECT: u8 = 8;
+const ARRAY: u8 = 16;
+const NULL: u8 = 32;
+
+/// Expected shape of a JSON value.
+struct Schema {
+    types: u8,
+    /// Allowed string values, any if empty
+    values: &'static [&'static str],
+    /// Schema of array items
+    items: Option<&'static Schema>,
+    /// Known object properties, any property is allowed if `None`
+    properties: Option<&'static [(&'static str, Schema)]>,
+    /// Schema of the values of object properties not listed in `properties`
+    additional: Option<&'static Schema>,
+}
+
+const fn of(types: u8) -> Schema {
+    Schema {
+        types,
+        values: &[],
+        items: None,
+        properties: None,
+        additional: None,
+    }
+}
+
+const fn one_of(values: &'static [&'static str]) -> Schema {
+    Schema {
+        values,
+        ..of(STRING)
+    }
+}
+
+const fn array_of(types: u8, items: &'static Schema) -> Schema {
+    Schema {
+        items: Some(items),
+        ..of(types | ARRAY)
+    }
+}
+
+const fn map_of(types: u8, additional: &'static Schema) -> Schema {
+    Schema {
+        additional: Some(additional),
+        ..of(types | OBJECT)
+    }
+}
+
+const fn object(properties: &'static [(&'static str, Schema)]) -> Schema {
+    Schema {
+        properties: Some(properties),
+        ..of(OBJECT)
+    }
+}
+
+static ANY_STRING: Schema = of(STRING);
+static PORT: Schema = of(NUMBER | STRING);
+static COMMAND: Schema = array_of(STRING, &ANY_STRING);
+
+/// A lifecycle command is a string, an array of arguments or an object of
+/// named commands running in parallel.
+const fn lifecycle_command() -> Schema {
+    Schema {
+        items: Some(&ANY_STRING),
+        additional: Some(&COMMAND),
+        ..of(STRING | ARRAY | OBJECT)
+    }
+}
+
+static FEATURE_OPTIONS: Schema = of(STRING | BOOLEAN | OBJECT);
+static PORT_ATTRIBUTES: Schema = of(OBJECT);
+static MOUNT: Schema = of(STRING | OBJECT);
+static REMOTE_ENV_VALUE: Schema = of(STRING | NULL);
+
+static DEVCONTAINER: Schema = object(&[
+    ("$schema", of(STRING)),
+    ("name", of(STRING)),
+    ("extends", of(STRING)),
+    ("features", map_of(0, &FEATURE_OPTIONS)),
+    ("overrideFeatureInstallOrder", array_of(0, &ANY_STRING)),
+    ("forwardPorts", array_of(0, &PORT)),
+    ("portsAttributes", map_of(0, &PORT_ATTRIBUTES)),
+    ("otherPortsAttributes", of(OBJECT)),
+    ("image", of(STRING)),
+    (
+        "build",
+        object(&[
+            ("dockerfile", of(STRING)),
+            ("context", of(STRING)),
+            ("args", map_of(0, &ANY_STRING)),
+            ("target", of(STRING)),
+            ("cacheFrom", array_of(STRING, &ANY_STRING)),
+            ("options", array_of(0, &ANY_STRING)),
+        ]),
+    ),
+    ("dockerFile", of(STRING)),
+    ("context", of(STRING)),
+    ("dockerComposeFile", array_of(STRING, &ANY_STRING)),
+    ("service", of(STRING)),
+    ("runServices", array_of(0, &ANY_STRING)),
+    ("workspaceFolder", of(STRING)),
+    ("workspaceMount", of(STRING)),
+    ("mounts", array_of(0, &MOUNT)),
+    ("runArgs", array_of(0, &ANY_STRING)),
+    ("appPort", array_of(NUMBER | STRING, &PORT)),
+    ("overrideCommand", of(BOOLEAN)),
+    (
+        "shutdownAction",
+        one_of(&["none", "stopContainer", "stopCompose"]),
+    ),
+    ("remoteUser", of(STRING)),
+    ("containerUser", of(STRING)),
+    ("updateRemoteUserUID", of(BOOLEAN)),
+    ("containerEnv", map_of(0, &ANY_STRING)),
+    ("remoteEnv", map_of(0, &REMOTE_ENV_VALUE)),
+    ("init", of(BOOLEAN)),
+    ("privileged", of(BOOLEAN)),
+    ("capAdd", array_of(0, &ANY_STRING)),
+    ("securityOpt", array_of(0, &ANY_STRING)),
+    ("initializeCommand", lifecycle_command()),
+    ("onCreateCommand", lifecycle_command()),
+    ("updateContentCommand", lifecycle_command()),
+    ("postCreateCommand", lifecycle_command()),
+    ("postStartCommand", lifecycle_command()),
+    ("postAttachCommand", lifecycle_command()),
+    (
+        "waitFor",
+        one_of(&[
+            "initializeCommand",
+            "onCreateCommand",
+            "updateContentCommand",
+            "postCreateCommand",
+            "postStartCommand",
+        ]),
+    ),
+    (
+        "userEnvProbe",
+        one_of(&[
+            "none",
+            "loginShell",
+            "loginInteractiveShell",
+            "interactiveShell",
+        ]),
+    ),
+    (
+        "hostRequirements",
+        object(&[
+            ("cpus", of(NUMBER)),
+            ("memory", of(STRING)),
+            ("storage", of(STRING)),
+            ("gpu", of(BOOLEAN | STRING | OBJECT)),
+        ]),
+    ),
+    ("customizations", of(OBJECT)),
+]);
+
+/// Validates the content of a `devcontainer.json`.
+///
+/// Syntax errors stop the validation, as their position is all that can be
+/// reported reliably. Otherwise every type error and unknown property is
+/// reported, ordered by position.
+pub fn validate(content: &str) -> Vec<Diagnostic> {
+    let mut stripped = content.to_string();
+    if json_strip_comments::strip(&mut stripped).is_err() {
+        return vec![Diagnostic::error(content, 0, "Failed to strip comments")];
+    }
+
+    if let Err(e) = serde_json::from_str::<serde_json::Value>(&stripped) {
+        return vec![Diagnostic {
+            severity: Severity::Error,
+            line: e.line().max(1),
+            column: e.column().max(1),
+            message: syntax_message(&e),
+        }];
+    }
+
+    let Some(root) = Parser::new(&stripped).parse() else {
+        return vec![Diagnostic::error(content, 0, "Failed to parse file")];
+    };
+
+    let mut diagnostics = Vec::new();
+    check(content, &root, &DEVCONTAINER, "", &mut diagnostics);
+
+    // Catches what the schema cannot express, e.g. malformed feature references
+    if !diagnostics.iter().any(|d| d.severity == Severity::Error)
+        && let Err(e) = Devcontainer::try_from(content.to_string())
+    {
+        diagnostics.push(Diagnostic::error(content, 0, format!("{:#}", e)));
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Returns the line and column of a top-level property name, e.g. to report
+/// problems found while resolving a feature.
+pub fn property_position(content: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let mut stripped = content.to_string();
+    json_strip_comments::strip(&mut stripped).ok()?;
+    let mut node = &Parser::new(&stripped).parse()?;
+    let mut offset = node.offset;
+    for key in path {
+        let Value::Object(entries) = &node.value else {
+            return None;
+        };
+        let entry = entries.iter().find(|entry| entry.key == *key)?;
+        offset = entry.key_offset;
+        node = &entry.value;
+    }
+    Some(position(content, offset))
+}
+
+/// Converts a byte offset into a 1-based line and character column.
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Strips serde's position suffix, which is reported separately.
+fn syntax_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+fn check(content: &str, node: &Node, schema: &Schema, path: &str, out: &mut Vec<Diagnostic>) {
+    let name = if path.is_empty() {
+        "devcontainer"
+    } else {
+        path
+    };
+    let actual = node.value.json_type();
+    if schema.types & actual == 0 {
+        out.push(Diagnostic::error(
+            content,
+            node.offset,
+            format!(
+                "'{}' must be {}, found {}",
+                name,
+                describe(schema.types),
+                describe(actual)
+            ),
+        ));
+        return;
+    }
+
+    match &node.value {
+        Value::String(value) if !schema.values.is_empty() && !schema.values.contains(&&**value) => {
+            out.push(Diagnostic::error(
+                content,
+                node.offset,
+                format!(
+                    "'{}' must be one of {}, found '{}'",
+                    name,
+                    schema.values.join(", "),
+                    value
+                ),
+            ));
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.items {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{}[{}]", path, index);
+                    check(content, item, item_schema, &item_path, out);
+                }
+            }
+        }
+        Value::Object(entries) => {
+            for entry in entries {
+                let entry_path = if path.is_empty() {
+                    entry.key.clone()
+                } else {
+                    format!("{}.{}", path, entry.key)
+                };
+                let known = schema
+                    .properties
+                    .and_then(|properties| properties.iter().find(|(key, _)| *key == entry.key));
+                if let Some((_, property_schema)) = known {
+                    check(content, &entry.value, property_schema, &entry_path, out);
+                } else if let Some(additional) = schema.additional {
+                    check(content, &entry.value, additional, &entry_path, out);
+                } else if schema.properties.is_some() {
+                    out.push(Diagnostic::warning(
+                        content,
+                        entry.key_offset,
+                        format!("Unknown property '{}'", entry_path),
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn describe(types: u8) -> String {
+    [
+        (STRING, "a string"),
+        (BOOLEAN, "a boolean"),
+        (NUMBER, "a number"),
+        (OBJECT, "an object"),
+        (ARRAY, "an array"),
+        (NULL, "null"),
+    ]
+    .iter()
+    .filter(|(bit, _)| types & bit != 0)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>()
+    .join(" or ")
+}
+
+/// JSON value remembering the byte offset it starts at.
+struct Node {
+    offset: usize,
+    value: Value,
+}
+
+struct Entry {
+    key: String,
+    key_offset: usize,
+    value: Node,
+}
+
+enum Value {
+    Null,
+    Bool,
+    Number,
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<Entry>),
+}
+
+impl Value {
+    fn json_type(&self) -> u8 {
+        match self {
+            Value::Null => NULL,
+            Value::Bool => BOOLEAN,
+            Value::Number => NUMBER,
+            Value::String(_) => STRING,
+            Value::Array(_) => ARRAY,
+            Value::Object(_) => OBJECT,
+        }
+    }
+}
+
+/// Minimal JSON parser keeping positions, which `serde_json::Value` drops.
+///
+/// Only used on input serde already accepted, so it does not report why
+/// parsing failed.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn parse(&mut self) -> Option<Node> {
+        self.skip_whitespace();
+        let offset = self.pos;
+        let value = match self.peek()? {
+            b'{' => self.parse_object()?,
+            b'[' => self.parse_array()?,
+            b'"' => Value::String(self.parse_string()?),
+            b't' => self.parse_literal("true", Value::Bool)?,
+            b'f' => self.parse_literal("false", Value::Bool)?,
+            b'n' => self.parse_literal("null", Value::Null)?,
+            _ => {
+                let len = self.text[self.pos..]
+                    .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(self.text.len() - self.pos);
+                if len == 0 {
+                    return None;
+                }
+                self.pos += len;
+                Value::Number
+            }
+        };
+        Some(Node { offset, value })
+    }
+
+    fn parse_object(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                b'}' => {
+                    self.pos += 1;
+                    return Some(Value::Object(entries));
+                }
+                b',' => self.pos += 1,
+                _ => {
+                    let key_offset = self.pos;
+                    let key = self.parse_string()?;
+                    self.skip_whitespace();
+                    if self.peek()? != b':' {
+                        return None;
+                    }
+                    self.pos += 1;
+                    let value = self.parse()?;
+                    entries.push(Entry {
+                        key,
+                        key_offset,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Option<Value> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek()? {
+                b']' => {
+                    self.pos += 1;
+                    return Some(Value::Array(items));
+                }
+                b',' => self.pos += 1,
+                _ => items.push(self.parse()?),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        let bytes = self.text.as_bytes();
+        if bytes.get(self.pos) != Some(&b'"') {
+            return None;
+        }
+        let start = self.pos;
+        let mut end = start + 1;
+        while *bytes.get(end)? != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        self.pos = end + 1;
+        serde_json::from_str(&self.text[start..self.pos]).ok()
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Value) -> Option<Value> {
+        if !self.text[self.pos..].starts_with(literal) {
+            return None;
+        }
+        self.pos += literal.len();
+        Some(value)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_file_with_comments() {
+        let content = r#"{
+    // Image to use
+    "name": "Test",
+    "image": "ubuntu:24.04", /* inline */
+    "forwardPorts": [3000, "db:5432"],
+    "postCreateCommand": { "a": "echo a", "b": ["echo", "b"] },
+    "features": {
+        "ghcr.io/devcontainers/features/node:1": { "version": "20" },
+    },
+}"#;
+        assert_eq!(validate(content), vec![]);
+    }
+
+    #[test]
+    fn test_syntax_error_position() {
+        let content = "{\n  \"name\": \"Test\"\n  \"image\": \"ubuntu\"\n}";
+        let diagnostics = validate(content);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 3));
+    }
+
+    #[test]
+    fn test_unknown_property_and_type_error() {
+        let content = r#"{
+  "image": "ubuntu",
+  "forwardPort": [3000],
+  "build": { "dockerfile": 1 },
+  "shutdownAction": "halt"
+}"#;
+        let diagnostics = validate(content);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "3:3: warning: Unknown property 'forwardPort'",
+                "4:28: error: 'build.dockerfile' must be a string, found a number",
+                "5:21: error: 'shutdownAction' must be one of none, stopContainer, stopCompose, found 'halt'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_counts_characters() {
+        let content = "{ \"name\": \"äöü\", \"init\": \"yes\" }";
+        let diagnostics = validate(content);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 26));
+    }
+
+    #[test]
+    fn test_property_position() {
+        let content = "{\n  // features\n  \"features\": {\n    \"ghcr.io/a/b/c:1\": {}\n  }\n}";
+        assert_eq!(
+            property_position(content, &["features", "ghcr.io/a/b/c:1"]),
+            Some((4, 5))
+        );
+        assert_eq!(property_position(content, &["image"]), None);
+    }
+}
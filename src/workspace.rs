@@ -27,7 +27,9 @@
 use std::fs;
 use std::path::PathBuf;
 
-use crate::devcontainer::Devcontainer;
+use crate::devcontainer::{
+    Devcontainer, extended_definition, find_devcontainer_file, list_devcontainer_configs,
+};
 
 /// Represents a workspace containing a devcontainer configuration.
 ///
@@ -38,6 +40,10 @@ use crate::devcontainer::Devcontainer;
 ///
 /// * `path` - The path to the project directory
 /// * `devcontainer` - The parsed devcontainer configuration
+/// * `config` - The selected configuration of a multi-config project, i.e.
+///   `.devcontainer/<config>/devcontainer.json`
+/// * `instance` - Name of a further container of the workspace, e.g. for a
+///   git worktree, see [`Workspace::with_instance`]
 ///
 /// # Examples
 ///
@@ -52,23 +58,107 @@ use crate::devcontainer::Devcontainer;
 pub struct Workspace {
     pub path: PathBuf,
     pub devcontainer: Devcontainer,
+    pub config: Option<String>,
+    pub instance: Option<String>,
 }
 
 impl TryFrom<PathBuf> for Workspace {
     type Error = anyhow::Error;
 
+    /// Opens the workspace of a project without selecting a configuration.
+    ///
+    /// Fails if the project has several named configurations and no default
+    /// `devcontainer.json`, see [`Workspace::open`].
     fn try_from(path: PathBuf) -> std::result::Result<Self, Self::Error> {
+        Self::open(path, None)
+    }
+}
+
+impl Workspace {
+    /// Opens the workspace of a project, using the named configuration
+    /// `.devcontainer/<config>/devcontainer.json` if `config` is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path does not exist or the configuration
+    /// cannot be found or parsed.
+    pub fn open(path: PathBuf, config: Option<&str>) -> anyhow::Result<Self> {
         let canonical_path = fs::canonicalize(&path)?;
-        let devcontainer = Devcontainer::try_from(canonical_path.clone())?;
+        let devcontainer = Devcontainer::load(&canonical_path, config)?;
 
         Ok(Workspace {
             path: canonical_path,
             devcontainer,
+            config: config.map(str::to_string),
+            instance: None,
         })
     }
-}
 
-impl Workspace {
+    /// Selects a named instance of the workspace.
+    ///
+    /// Every instance has its own image, container, state and volumes, so
+    /// several containers of a project can run side by side. The name may
+    /// contain lowercase letters, digits, `-` and `_`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty or contains other characters.
+    pub fn with_instance(mut self, instance: Option<&str>) -> anyhow::Result<Self> {
+        if let Some(instance) = instance {
+            let valid = instance
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+            if instance.is_empty() || !valid {
+                anyhow::bail!(
+                    "Invalid instance name '{}', use lowercase letters, digits, '-' and '_'",
+                    instance
+                );
+            }
+        }
+        self.instance = instance.map(str::to_string);
+        Ok(self)
+    }
+
+    /// Returns the name the agent of the workspace reports to the control
+    /// server: the directory name, followed by `@instance` for an instance.
+    pub fn agent_name(&self) -> String {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match &self.instance {
+            Some(instance) => format!("{}@{}", name, instance),
+            None => name,
+        }
+    }
+
+    /// Lists the named configurations of a project, sorted by name.
+    pub fn configs(path: &std::path::Path) -> Vec<String> {
+        list_devcontainer_configs(path)
+    }
+
+    /// Returns the path of the `devcontainer.json` the workspace was loaded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file was removed since.
+    pub fn devcontainer_file(&self) -> anyhow::Result<PathBuf> {
+        find_devcontainer_file(&self.path, self.config.as_deref())
+    }
+
+    /// Returns the path of the `devcontainer.json` followed by the paths of
+    /// the definitions it extends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the files was removed or became invalid.
+    pub fn devcontainer_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut chain = Vec::new();
+        extended_definition(&self.devcontainer_file()?, &mut chain)?;
+        Ok(chain)
+    }
+
     pub fn get_name(&self) -> String {
         if let Some(name) = self.devcontainer.name.as_ref() {
             return name.clone();
@@ -82,11 +172,49 @@ impl Workspace {
     }
 
     pub fn get_sanitized_name(&self) -> String {
-        let name = self.get_name();
-
-        name.to_ascii_lowercase().replace(
+        let name = self.get_name().to_ascii_lowercase().replace(
             |c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '_',
             "-",
+        );
+
+        // The dot can't occur in a sanitized name, so instances never clash
+        // with other projects
+        match &self.instance {
+            Some(instance) => format!("{}.{}", name, instance),
+            None => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(dir: &tempfile::TempDir) -> Workspace {
+        let project = dir.path().join("My Project");
+        std::fs::create_dir_all(project.join(".devcontainer")).unwrap();
+        std::fs::write(
+            project.join(".devcontainer/devcontainer.json"),
+            r#"{"image": "alpine"}"#,
         )
+        .unwrap();
+        Workspace::open(project, None).unwrap()
+    }
+
+    #[test]
+    fn test_with_instance() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let default = workspace(&dir);
+        assert_eq!(default.get_sanitized_name(), "my-project");
+        assert_eq!(default.agent_name(), "My Project");
+
+        let instance = workspace(&dir).with_instance(Some("feature-1")).unwrap();
+        assert_eq!(instance.get_sanitized_name(), "my-project.feature-1");
+        assert_eq!(instance.agent_name(), "My Project@feature-1");
+
+        assert!(workspace(&dir).with_instance(Some("")).is_err());
+        assert!(workspace(&dir).with_instance(Some("Feature")).is_err());
+        assert!(workspace(&dir).with_instance(Some("a/b")).is_err());
     }
 }